@@ -1,7 +1,12 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
 use nalgebra::{UnitQuaternion, Vector3};
 use rogue_engine::{
+    consts,
+    graphics::camera::Camera,
     input::{Input, keyboard::Key, mouse},
     physics::transform::Transform,
+    settings::Settings,
     window::{time::Time, window::Window},
 };
 
@@ -12,22 +17,69 @@ pub enum EditorCameraControllerType {
     Fps,
 }
 
+/// An axis aligned orthographic view the editor camera can snap to, named by the side of the
+/// anchor it looks from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorAxisView {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl EditorAxisView {
+    /// The view whose action was pressed this frame.
+    pub fn from_pressed_actions(input: &Input) -> Option<Self> {
+        [
+            (consts::actions::EDITOR_VIEW_FRONT, Self::Front),
+            (consts::actions::EDITOR_VIEW_BACK, Self::Back),
+            (consts::actions::EDITOR_VIEW_RIGHT, Self::Right),
+            (consts::actions::EDITOR_VIEW_LEFT, Self::Left),
+            (consts::actions::EDITOR_VIEW_TOP, Self::Top),
+            (consts::actions::EDITOR_VIEW_BOTTOM, Self::Bottom),
+        ]
+        .into_iter()
+        .find(|(action, _)| input.is_action_pressed(action))
+        .map(|(_, view)| view)
+    }
+
+    /// The camera euler angles looking down this view's axis.
+    pub fn euler(&self) -> Vector3<f32> {
+        match self {
+            Self::Front => Vector3::new(0.0, 0.0, 0.0),
+            Self::Back => Vector3::new(0.0, PI, 0.0),
+            Self::Left => Vector3::new(0.0, FRAC_PI_2, 0.0),
+            Self::Right => Vector3::new(0.0, -FRAC_PI_2, 0.0),
+            Self::Top => Vector3::new(FRAC_PI_2, 0.0, 0.0),
+            Self::Bottom => Vector3::new(-FRAC_PI_2, 0.0, 0.0),
+        }
+    }
+}
+
 pub struct EditorCameraController {
     pub rotation_anchor: Vector3<f32>,
     pub euler: Vector3<f32>,
     pub distance: f32,
+    /// The orthographic view the camera is snapped to, orbiting leaves it.
+    pub axis_view: Option<EditorAxisView>,
 
     pub controller_type: EditorCameraControllerType,
 }
 
 impl EditorCameraController {
     const SENS: f32 = 0.001;
+    /// How much further back than `distance` the camera sits in axis views, so geometry
+    /// between the anchor and the camera isn't cut off by the orthographic view plane.
+    const AXIS_VIEW_BACKOFF: f32 = 20.0;
 
     pub fn new() -> Self {
         Self {
             rotation_anchor: Vector3::zeros(),
             euler: Vector3::zeros(),
             distance: 10.0,
+            axis_view: None,
 
             controller_type: EditorCameraControllerType::PanOrbit,
         }
@@ -38,6 +90,7 @@ impl EditorCameraController {
             rotation_anchor: settings.editor_camera_anchor,
             euler: settings.editor_camera_rotation,
             distance: settings.editor_camera_distance,
+            axis_view: None,
             controller_type: EditorCameraControllerType::PanOrbit,
         }
     }
@@ -51,41 +104,89 @@ impl EditorCameraController {
         }
     }
 
+    /// Snaps to the orthographic `axis_view` looking at `anchor`.
+    pub fn snap_to_axis_view(&mut self, axis_view: EditorAxisView, anchor: Vector3<f32>) {
+        if !matches!(self.controller_type, EditorCameraControllerType::PanOrbit) {
+            return;
+        }
+        self.axis_view = Some(axis_view);
+        self.rotation_anchor = anchor;
+        self.euler = axis_view.euler();
+    }
+
+    /// Orbits `center` close enough that a sphere of `radius` around it fills the view.
+    pub fn frame_bounds(&mut self, center: Vector3<f32>, radius: f32, camera: &Camera) {
+        self.focus_on_position(center);
+        self.distance = (radius / (camera.fov() * 0.5).sin()).clamp(0.01, 250.0);
+    }
+
+    /// The point the camera orbits around, for the fps controller this is the point
+    /// `distance` in front of the camera.
+    pub fn orbit_anchor(&self, transform: &Transform) -> Vector3<f32> {
+        match self.controller_type {
+            EditorCameraControllerType::PanOrbit => self.rotation_anchor,
+            EditorCameraControllerType::Fps => {
+                transform.position
+                    + transform
+                        .rotation
+                        .transform_vector(&Vector3::new(0.0, 0.0, self.distance))
+            }
+        }
+    }
+
+    /// Switches between the pan orbit and fps controllers.
+    pub fn toggle_fps(&mut self, transform: &Transform, window: &mut Window) {
+        match self.controller_type {
+            EditorCameraControllerType::PanOrbit => {
+                self.controller_type = EditorCameraControllerType::Fps;
+                window.set_cursor_lock(true);
+            }
+            EditorCameraControllerType::Fps => {
+                self.rotation_anchor = self.orbit_anchor(transform);
+                self.controller_type = EditorCameraControllerType::PanOrbit;
+                window.set_cursor_lock(false);
+            }
+        }
+    }
+
     pub fn update(
         &mut self,
         transform: &mut Transform,
+        camera: &mut Camera,
         input: &Input,
         time: &Time,
         window: &mut Window,
+        settings: &Settings,
     ) {
-        if input.is_key_pressed(Key::Escape) {
-            match self.controller_type {
-                EditorCameraControllerType::PanOrbit => {
-                    self.controller_type = EditorCameraControllerType::Fps;
-                    window.set_cursor_lock(true);
-                }
-                EditorCameraControllerType::Fps => {
-                    self.controller_type = EditorCameraControllerType::PanOrbit;
-                    self.rotation_anchor = transform.position
-                        + transform.rotation.transform_vector(&Vector3::new(
-                            0.0,
-                            0.0,
-                            self.distance,
-                        ));
-                    window.set_cursor_lock(false);
-                    window.set_cursor_position(window.inner_size_vec2().cast::<i32>() / 2);
-                }
+        let sensitivity = settings.editor_mouse_sensitivity;
+        match self.controller_type {
+            EditorCameraControllerType::PanOrbit => {
+                // Looking around captures the cursor so the drag isn't cut short by the edge of
+                // the window, releasing puts the cursor back where the drag started.
+                window.set_cursor_lock(input.is_mouse_button_down(mouse::Button::Right));
+                self.update_pan_orbit(transform, input, sensitivity);
+            }
+            EditorCameraControllerType::Fps => {
+                self.axis_view = None;
+                self.update_fps(transform, input, time, sensitivity);
             }
         }
 
-        match self.controller_type {
-            EditorCameraControllerType::PanOrbit => self.update_pan_orbit(transform, input),
-            EditorCameraControllerType::Fps => self.update_fps(transform, input, time),
-        }
+        // Orthographic views show what the perspective view shows at the anchor so leaving
+        // them doesn't change the framing.
+        camera.orthographic_half_height = self
+            .axis_view
+            .map(|_| self.distance * (camera.fov() * 0.5).tan());
     }
 
-    fn update_fps(&mut self, transform: &mut Transform, input: &Input, time: &Time) {
-        let mouse_delta = input.mouse_delta() * Self::SENS;
+    fn update_fps(
+        &mut self,
+        transform: &mut Transform,
+        input: &Input,
+        time: &Time,
+        sensitivity: f32,
+    ) {
+        let mouse_delta = input.mouse_delta() * sensitivity;
         self.euler.x = (self.euler.x - mouse_delta.y)
             .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
         self.euler.y += mouse_delta.x;
@@ -111,7 +212,7 @@ impl EditorCameraController {
         transform.position += translation * movement_speed * time.delta_time().as_secs_f32();
     }
 
-    fn update_pan_orbit(&mut self, transform: &mut Transform, input: &Input) {
+    fn update_pan_orbit(&mut self, transform: &mut Transform, input: &Input, sensitivity: f32) {
         if input.is_mouse_button_down(mouse::Button::Middle) {
             let delta = -input.mouse_delta() * Self::SENS * self.distance.max(1.0);
             let up = transform.rotation.transform_vector(&Vector3::y());
@@ -120,7 +221,8 @@ impl EditorCameraController {
         }
 
         if input.is_mouse_button_down(mouse::Button::Right) {
-            let delta = input.mouse_delta() * Self::SENS * 0.8;
+            self.axis_view = None;
+            let delta = input.mouse_delta() * sensitivity * 0.8;
             self.euler.x = (self.euler.x - delta.y)
                 .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
             self.euler.y += delta.x;
@@ -129,11 +231,24 @@ impl EditorCameraController {
         let mut scroll_delta = input.mouse().scroll_delta() * 0.05;
         self.distance = (self.distance * (1.0 + scroll_delta)).clamp(0.01, 250.0);
 
-        let rot = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.euler.y)
-            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), self.euler.x);
-        let pos = self.rotation_anchor + self.distance * (rot.transform_vector(&-Vector3::z()));
+        let distance = match self.axis_view {
+            Some(_) => self.distance + Self::AXIS_VIEW_BACKOFF,
+            None => self.distance,
+        };
+        Self::set_orbit_transform(transform, self.rotation_anchor, distance, self.euler);
+    }
+
+    /// Sets `transform` to orbit around `anchor` at `distance` with the given euler angles.
+    pub fn set_orbit_transform(
+        transform: &mut Transform,
+        anchor: Vector3<f32>,
+        distance: f32,
+        euler: Vector3<f32>,
+    ) {
+        let rot = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), euler.y)
+            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), euler.x);
+        let pos = anchor + distance * (rot.transform_vector(&-Vector3::z()));
         transform.position = pos;
-        transform.rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.euler.y)
-            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), self.euler.x);
+        transform.rotation = rot;
     }
 }
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector3;
+use rogue_engine::{
+    asset::repr::game_entity::WorldGameEntityAsset,
+    egui::Egui,
+    entity::{
+        EntityChildren, EntityParent,
+        component::GameComponentCloneContext,
+        ecs_world::{ECSWorld, Entity},
+    },
+    input::{
+        Input,
+        keyboard::{Key, Modifier},
+    },
+    physics::{physics_world::PhysicsWorld, transform::Transform},
+    resource::{Res, ResMut},
+    voxel::voxel_registry::VoxelModelRegistry,
+};
+use rogue_macros::Resource;
+
+use crate::{editing::voxel_editing::EditorVoxelEditing, session::EditorSession};
+
+/// Entities copied with Ctrl+C, pasted as new entities with Ctrl+V. Holding shift when pasting
+/// shares the copied entities' voxel models instead of copying them. Colliders are only
+/// referenced when copying and registered when pasting, so collider edits made between the
+/// copy and the paste show up in the pasted entities.
+#[derive(Resource)]
+pub struct EditorCopyBuffer {
+    /// The copied entities and their descendants with their components as they were when
+    /// copied.
+    entities: Vec<CopiedEntity>,
+}
+
+struct CopiedEntity {
+    asset: WorldGameEntityAsset,
+    /// The world transform if the entity's parent wasn't copied, it is pasted without a parent.
+    root_world_transform: Option<Transform>,
+}
+
+impl EditorCopyBuffer {
+    /// Pasted entities are offset so they don't overlap with the copied entities.
+    const PASTE_OFFSET: Vector3<f32> = Vector3::new(0.5, 0.0, 0.5);
+
+    pub fn new() -> Self {
+        Self {
+            entities: Vec::new(),
+        }
+    }
+
+    pub fn update_copy_paste(
+        mut copy_buffer: ResMut<EditorCopyBuffer>,
+        mut session: ResMut<EditorSession>,
+        mut ecs_world: ResMut<ECSWorld>,
+        mut physics_world: ResMut<PhysicsWorld>,
+        mut voxel_registry: ResMut<VoxelModelRegistry>,
+        input: Res<Input>,
+        voxel_editing: Res<EditorVoxelEditing>,
+        egui: Res<Egui>,
+    ) {
+        if voxel_editing.is_enabled()
+            || !session.is_editor_camera_focused()
+            || egui.context().wants_keyboard_input()
+        {
+            return;
+        }
+
+        let mut clone_ctx = GameComponentCloneContext {
+            voxel_registry: &mut voxel_registry,
+            collider_registry: &mut physics_world.colliders,
+            // Copying only references the models, pasting decides whether they're copied.
+            share_voxel_models: true,
+            // Copying only references the colliders so buffers that are never pasted don't
+            // leave their copies in the registry.
+            share_colliders: true,
+        };
+        if input.is_key_pressed_with_modifiers(Key::C, &[Modifier::Control]) {
+            copy_buffer.copy(&ecs_world, session.selected_entities(), &mut clone_ctx);
+        } else if input.is_key_pressed_with_modifiers(Key::V, &[Modifier::Control])
+            && !copy_buffer.entities.is_empty()
+        {
+            clone_ctx.share_voxel_models = input.keyboard().is_modifiers_down(&[Modifier::Shift]);
+            clone_ctx.share_colliders = false;
+            let pasted = copy_buffer.paste(&mut ecs_world, &mut clone_ctx);
+            session.select_entity(None);
+            session.extend_selection(pasted);
+        }
+    }
+
+    fn copy(
+        &mut self,
+        ecs_world: &ECSWorld,
+        selected_entities: &[Entity],
+        clone_ctx: &mut GameComponentCloneContext<'_>,
+    ) {
+        let mut copied_entities = Vec::new();
+        for entity in selected_entities {
+            Self::push_with_descendants(ecs_world, *entity, &mut copied_entities);
+        }
+        if copied_entities.is_empty() {
+            return;
+        }
+
+        self.entities = copied_entities
+            .iter()
+            .map(|entity| {
+                let parent = ecs_world
+                    .get::<&EntityParent>(*entity)
+                    .ok()
+                    .map(|parent| parent.parent());
+                let is_root = parent.is_none_or(|parent| !copied_entities.contains(&parent));
+                let root_world_transform = ecs_world
+                    .get::<&Transform>(*entity)
+                    .ok()
+                    .filter(|_| is_root)
+                    .map(|transform| ecs_world.get_world_transform(*entity, &transform));
+                CopiedEntity {
+                    asset: ecs_world.create_game_entity_asset(*entity, clone_ctx),
+                    root_world_transform,
+                }
+            })
+            .collect();
+        log::info!("Copied {} entities.", self.entities.len());
+    }
+
+    /// Spawns the copied entities, returning the pasted root entities.
+    fn paste(
+        &self,
+        ecs_world: &mut ECSWorld,
+        clone_ctx: &mut GameComponentCloneContext<'_>,
+    ) -> Vec<Entity> {
+        let uuid_to_entity = self
+            .entities
+            .iter()
+            .map(|copied| {
+                let entity = ecs_world.spawn_prefab(&copied.asset, clone_ctx);
+                (copied.asset.uuid, entity)
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut pasted_roots = Vec::new();
+        for copied in &self.entities {
+            let entity = uuid_to_entity[&copied.asset.uuid];
+            if let Some(parent) = copied
+                .asset
+                .parent
+                .and_then(|parent| uuid_to_entity.get(&parent))
+            {
+                // Keeps the local transform relative to the pasted parent.
+                ecs_world.set_parent(entity, Some(*parent), false);
+                continue;
+            }
+
+            if let Some(world_transform) = &copied.root_world_transform
+                && let Ok(mut transform) = ecs_world.get::<&mut Transform>(entity)
+            {
+                *transform = world_transform.clone();
+                transform.position += Self::PASTE_OFFSET;
+            }
+            pasted_roots.push(entity);
+        }
+        log::info!("Pasted {} entities.", self.entities.len());
+
+        pasted_roots
+    }
+
+    fn push_with_descendants(ecs_world: &ECSWorld, entity: Entity, entities: &mut Vec<Entity>) {
+        if entities.contains(&entity) {
+            return;
+        }
+        entities.push(entity);
+        let Ok(children) = ecs_world.get::<&EntityChildren>(entity) else {
+            return;
+        };
+        let children = children.children.clone();
+        for child in children {
+            Self::push_with_descendants(ecs_world, child, entities);
+        }
+    }
+}
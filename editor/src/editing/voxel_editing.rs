@@ -13,7 +13,8 @@ use crate::{
 use nalgebra::Vector3;
 use rogue_engine::material::{material_bank::MaterialId, model_material_map::ModelMaterialMap};
 use rogue_engine::{
-    common::color::ColorSrgba,
+    asset::repr::voxel::material_palette::VoxelMaterialPaletteAsset,
+    common::{color::ColorSrgba, geometry::axis::Axis},
     entity::{
         RenderableVoxelEntity,
         ecs_world::{ECSWorld, Entity},
@@ -27,7 +28,10 @@ use rogue_engine::{
     resource::{Res, ResMut, ResourceBank},
     voxel::{
         sft_compressed::VoxelModelSFTCompressed,
-        voxel::{VoxelMaterialData, VoxelModelEdit, VoxelModelEditMaskLayer},
+        voxel::{
+            VoxelEditMirror, VoxelEditMirrorAxes, VoxelMaterialData, VoxelModelEdit,
+            VoxelModelEditMaskLayer, VoxelModelEditOperator,
+        },
         voxel_registry::{VoxelModelEvent, VoxelModelId, VoxelModelRegistry},
     },
     world::terrain::region_map::{ChunkId, RegionMap, VoxelTerrainEdit},
@@ -63,17 +67,52 @@ pub enum EditorEditingTool {
         brush_size: u32,
     },
     ColorPicker,
+    /// Places a line of voxels between two clicked voxels, the first click sets the anchor.
+    Line {
+        brush_size: u32,
+    },
+    /// Flood fills the connected voxels of the same material as the hovered voxel, or the
+    /// connected empty voxels for terrain.
+    Bucket {
+        /// Fills larger than this are cancelled so a miss doesn't fill the sky.
+        max_voxels: u32,
+    },
 }
 
 impl EditorEditingTool {
+    pub const DEFAULT_BUCKET_MAX_VOXELS: u32 = 100_000;
+
     pub fn should_offset(&self) -> bool {
         match self {
-            EditorEditingTool::Pencil { .. } => true,
+            EditorEditingTool::Pencil { .. } | EditorEditingTool::Line { .. } => true,
             _ => false,
         }
     }
 }
 
+/// The shape of the voxels placed by the brush tools, always within the brush size's rect.
+#[derive(strum_macros::VariantArray, strum_macros::Display, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditorBrushShape {
+    Sphere,
+    Cube,
+    /// Stands upright, the radius is tested on the x and z axes and the height on the y axis.
+    Cylinder,
+}
+
+impl EditorBrushShape {
+    pub fn mask_layer(&self, center: Vector3<i32>, diameter: u32) -> VoxelModelEditMaskLayer {
+        match self {
+            EditorBrushShape::Sphere => VoxelModelEditMaskLayer::Sphere { center, diameter },
+            EditorBrushShape::Cube => VoxelModelEditMaskLayer::Cube { center, diameter },
+            EditorBrushShape::Cylinder => VoxelModelEditMaskLayer::Cylinder {
+                center,
+                diameter,
+                axis: Axis::Y,
+            },
+        }
+    }
+}
+
 pub enum EditorVoxelEditingHistoryItem {
     ModelEdit {
         model_id: VoxelModelId,
@@ -105,6 +144,24 @@ impl EditorVoxelEditingTarget {
 
 pub struct VoxelEditingSelection {}
 
+/// Applies every edit again mirrored across the enabled axes.
+pub struct EditorVoxelEditingMirror {
+    pub axes: VoxelEditMirrorAxes,
+    /// Entities are mirrored around their model center, terrain is mirrored around the min faces
+    /// of this world voxel.
+    pub terrain_plane: Vector3<i32>,
+}
+
+impl EditorVoxelEditingMirror {
+    pub fn entity_mirror(&self, model_length: Vector3<u32>) -> VoxelEditMirror {
+        VoxelEditMirror::around_model_center(self.axes, model_length)
+    }
+
+    pub fn terrain_mirror(&self) -> VoxelEditMirror {
+        VoxelEditMirror::around_voxel_faces(self.axes, self.terrain_plane)
+    }
+}
+
 #[derive(Resource)]
 pub struct EditorVoxelEditing {
     pub enabled: bool,
@@ -112,9 +169,17 @@ pub struct EditorVoxelEditing {
     pub masks: Vec<VoxelModelEditMaskLayer>,
     pub tools: HashMap<EditorEditingToolType, EditorEditingTool>,
     pub selected_tool_type: EditorEditingToolType,
+    /// Shared by every tool with a brush size.
+    pub brush_shape: EditorBrushShape,
+    /// How far placed colors mix into the existing voxel colors, falling off toward the brush
+    /// edge, one replaces them outright.
+    pub brush_opacity: f32,
     pub editing_material: EditorEditingMaterial,
     pub color: ColorSrgba,
     pub material: Option<MaterialId>,
+    /// Named colors and materials to paint with, saved with the project.
+    pub palette: VoxelMaterialPaletteAsset,
+    pub mirror: EditorVoxelEditingMirror,
 
     pub edit_target: Option<EditorVoxelEditingTarget>,
     /// True if can't change the edit target.
@@ -131,6 +196,12 @@ impl EditorVoxelEditing {
     pub fn new() -> Self {
         let mut tools = HashMap::new();
         for tool in EditorEditingTool::iter() {
+            let tool = match tool {
+                EditorEditingTool::Bucket { .. } => EditorEditingTool::Bucket {
+                    max_voxels: EditorEditingTool::DEFAULT_BUCKET_MAX_VOXELS,
+                },
+                tool => tool,
+            };
             tools.insert(tool.discriminant(), tool);
         }
         Self {
@@ -140,9 +211,16 @@ impl EditorVoxelEditing {
             masks: Vec::new(),
             tools,
             selected_tool_type: EditorEditingToolType::Pencil,
+            brush_shape: EditorBrushShape::Sphere,
+            brush_opacity: 1.0,
             editing_material: EditorEditingMaterial::Color,
             color: ColorSrgba::new(1.0, 0.0, 1.0, 1.0),
             material: None,
+            palette: VoxelMaterialPaletteAsset::new(),
+            mirror: EditorVoxelEditingMirror {
+                axes: VoxelEditMirrorAxes::NONE,
+                terrain_plane: Vector3::zeros(),
+            },
             draw_entity_bounds: false,
 
             edit_target: None,
@@ -383,42 +461,48 @@ impl EditorVoxelEditing {
         edit: VoxelTerrainEdit,
         save_history: bool,
     ) {
-        let affected_chunks = edit.region.get_affected_chunk_models(region_map);
+        let terrain_mirror = self.mirror.terrain_mirror();
+        let mirrored_edits = terrain_mirror
+            .combinations()
+            .map(|mirror| edit.mirrored(&mirror))
+            .collect::<Vec<_>>();
+        let edits = std::iter::once(edit)
+            .chain(mirrored_edits)
+            .collect::<Vec<_>>();
+
+        let affected_chunks = edits
+            .iter()
+            .flat_map(|edit| edit.region.get_affected_chunk_models(region_map))
+            .collect::<Vec<_>>();
         if save_history {
-            let mut saved_chunk_states = Vec::new();
-            for (chunk_id, chunk_model_id) in affected_chunks {
-                let saved_model_state = chunk_model_id.map(|model_id| {
-                    voxel_registry
-                        .get_model::<VoxelModelSFTCompressed>(model_id)
-                        .clone()
-                });
-                saved_chunk_states.push((chunk_id, saved_model_state));
-            }
             self.history
                 .undo_buffer
-                .push_back(EditorVoxelEditingHistoryItem::TerrainEdit { saved_chunk_states });
-        } else {
-            match self.history.undo_buffer.back_mut() {
-                Some(EditorVoxelEditingHistoryItem::TerrainEdit { saved_chunk_states }) => {
-                    for (chunk_id, chunk_model_id) in affected_chunks {
-                        if saved_chunk_states
-                            .iter()
-                            .any(|(existing_chunk_id, _)| existing_chunk_id == &chunk_id)
-                        {
-                            continue;
-                        }
-                        let saved_model_state = chunk_model_id.map(|model_id| {
-                            voxel_registry
-                                .get_model::<VoxelModelSFTCompressed>(model_id)
-                                .clone()
-                        });
-                        saved_chunk_states.push((chunk_id, saved_model_state));
+                .push_back(EditorVoxelEditingHistoryItem::TerrainEdit {
+                    saved_chunk_states: Vec::new(),
+                });
+        }
+        match self.history.undo_buffer.back_mut() {
+            Some(EditorVoxelEditingHistoryItem::TerrainEdit { saved_chunk_states }) => {
+                for (chunk_id, chunk_model_id) in affected_chunks {
+                    if saved_chunk_states
+                        .iter()
+                        .any(|(existing_chunk_id, _)| existing_chunk_id == &chunk_id)
+                    {
+                        continue;
                     }
+                    let saved_model_state = chunk_model_id.map(|model_id| {
+                        voxel_registry
+                            .get_model::<VoxelModelSFTCompressed>(model_id)
+                            .clone()
+                    });
+                    saved_chunk_states.push((chunk_id, saved_model_state));
                 }
-                _ => {}
             }
+            _ => {}
+        }
+        for edit in edits {
+            region_map.apply_voxel_edit(edit);
         }
-        region_map.apply_voxel_edit(edit);
     }
 
     pub fn apply_entity_edit<'a>(
@@ -439,18 +523,49 @@ impl EditorVoxelEditing {
                         .clone(),
                 });
         }
-        voxel_registry
-            .get_dyn_model_mut(model_id)
-            .set_voxel_range_impl(&edit);
+        let model = voxel_registry.get_dyn_model_mut(model_id);
+        model.set_voxel_range_impl(&edit);
+        let entity_mirror = self.mirror.entity_mirror(model.length());
+        for mirror in entity_mirror.combinations() {
+            if let Some(mirrored_edit) = edit.mirrored(&mirror, model.length()) {
+                model.set_voxel_range_impl(&mirrored_edit);
+            }
+        }
         events.push(VoxelModelEvent::UpdatedModel(model_id))
     }
 
+    /// The operator placing `voxel_material` with the brush opacity.
+    pub fn brush_operator(&self, voxel_material: VoxelMaterialData) -> VoxelModelEditOperator {
+        if self.brush_opacity >= 1.0 {
+            return VoxelModelEditOperator::Replace(Some(voxel_material));
+        }
+        VoxelModelEditOperator::Blend {
+            material: voxel_material,
+            opacity: self.brush_opacity,
+        }
+    }
+
     pub fn current_voxel_material(&self) -> Option<VoxelMaterialData> {
         match self.editing_material {
             EditorEditingMaterial::Color => Some(VoxelMaterialData::Baked { color: self.color }),
             EditorEditingMaterial::Material => self.material.map(VoxelMaterialData::Unbaked),
         }
     }
+
+    /// Paints with `voxel_material` from now on, such as when sampling a voxel or picking a
+    /// palette entry.
+    pub fn set_voxel_material(&mut self, voxel_material: &VoxelMaterialData) {
+        match voxel_material {
+            VoxelMaterialData::Baked { color } => {
+                self.color = *color;
+                self.editing_material = EditorEditingMaterial::Color;
+            }
+            VoxelMaterialData::Unbaked(material_id) => {
+                self.material = Some(*material_id);
+                self.editing_material = EditorEditingMaterial::Material;
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
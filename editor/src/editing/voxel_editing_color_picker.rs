@@ -1,11 +1,10 @@
 use rogue_engine::{
+    consts,
     entity::ecs_world::ECSWorld,
     input::{Input, mouse},
     resource::{Res, ResMut},
-    voxel::{
-        sft_compressed::VoxelModelSFTCompressed, voxel::VoxelMaterialData,
-        voxel_registry::VoxelModelRegistry,
-    },
+    voxel::voxel_registry::VoxelModelRegistry,
+    world::terrain::region_map::RegionMap,
 };
 use rogue_macros::Resource;
 
@@ -20,35 +19,42 @@ impl EditorVoxelEditingColorPicker {
     pub fn try_update_color_picker_tool(
         mut editing: ResMut<EditorVoxelEditing>,
         input: Res<Input>,
-        mut voxel_registry: ResMut<VoxelModelRegistry>,
+        voxel_registry: Res<VoxelModelRegistry>,
+        region_map: Res<RegionMap>,
         editor_session: Res<EditorSession>,
         ecs_world: Res<ECSWorld>,
     ) {
         let tool = editing.tools.get(&editing.selected_tool_type).unwrap();
-        if tool != &EditorEditingTool::ColorPicker {
+        let is_picking = (tool == &EditorEditingTool::ColorPicker
+            && input.is_mouse_button_pressed(mouse::Button::Left))
+            || input.is_action_pressed(consts::actions::EDITOR_EYEDROPPER);
+        if !is_picking {
             return;
         }
 
-        if !input.is_mouse_button_pressed(mouse::Button::Left) {
-            return;
-        }
-
-        match &editing.edit_target {
+        let voxel = match &editing.edit_target {
             Some(EditorVoxelEditingTarget::Entity(entity)) => {
                 let Some(raycast) = editor_session.entity_raycast() else {
                     return;
                 };
-                assert_eq!(*entity, raycast.entity);
-                let model = voxel_registry.get_model::<VoxelModelSFTCompressed>(raycast.model_id);
-                match model.get_voxel(raycast.model_trace.local_position) {
-                    Some(VoxelMaterialData::Baked { color }) => {
-                        editing.color = color;
-                    }
-                    Some(VoxelMaterialData::Unbaked(material_id)) => {}
-                    None => unreachable!(),
+                if *entity != raycast.entity {
+                    return;
                 }
+                voxel_registry
+                    .get_dyn_model(raycast.model_id)
+                    .get_voxel(raycast.model_trace.local_position)
             }
-            _ => {}
+            Some(EditorVoxelEditingTarget::Terrain) => {
+                let Some(raycast) = &editor_session.terrain_raycast else {
+                    return;
+                };
+                region_map.get_voxel(&voxel_registry, &raycast.world_voxel_pos)
+            }
+            None => return,
+        };
+
+        if let Some(voxel) = voxel {
+            editing.set_voxel_material(&voxel);
         }
     }
 }
@@ -1,13 +1,18 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+};
+
 use nalgebra::Vector3;
 use rogue_engine::{
     entity::{RenderableVoxelEntity, ecs_world::ECSWorld},
     event::Events,
-    input::{Input, mouse},
+    input::{Input, keyboard, mouse},
     physics::transform::Transform,
     resource::{Res, ResMut, ResourceBank},
     voxel::{
-        voxel::{VoxelModelEdit, VoxelModelEditMaskLayer, VoxelModelEditRegion},
-        voxel_registry::VoxelModelRegistry,
+        voxel::{VoxelMaterialData, VoxelModelEdit, VoxelModelEditMaskLayer, VoxelModelEditRegion},
+        voxel_registry::{VoxelModelId, VoxelModelRegistry},
     },
     world::terrain::region_map::{
         RegionMap, VoxelTerrainEdit, VoxelTerrainEditMask, VoxelTerrainEditMaskLayer,
@@ -31,19 +36,43 @@ use crate::{
 #[derive(Resource)]
 pub struct EditorVoxelEditingEditTools {
     paint: EditorVoxelEditingPaintState,
+    line: EditorVoxelEditingLineState,
 }
 
 pub struct EditorVoxelEditingPaintState {
     is_down: bool,
 }
 
+pub struct EditorVoxelEditingLineState {
+    /// The first clicked voxel of the line, in model space for entities and world space for
+    /// terrain.
+    anchor: Option<(EditorVoxelEditingTarget, Vector3<i32>)>,
+}
+
+/// The voxels reached by a bucket flood fill.
+pub struct EditorVoxelEditingFloodFill {
+    pub voxels: Arc<HashSet<Vector3<i32>>>,
+    pub min: Vector3<i32>,
+    pub max: Vector3<i32>,
+}
+
 impl EditorVoxelEditingEditTools {
     pub fn new() -> Self {
         Self {
             paint: EditorVoxelEditingPaintState { is_down: false },
+            line: EditorVoxelEditingLineState { anchor: None },
         }
     }
 
+    /// The pending anchor of the line tool if it was placed on `target`.
+    pub fn line_anchor(&self, target: &EditorVoxelEditingTarget) -> Option<Vector3<i32>> {
+        self.line
+            .anchor
+            .as_ref()
+            .filter(|(anchor_target, _)| anchor_target == target)
+            .map(|(_, anchor)| *anchor)
+    }
+
     pub fn update_edit_application_systems(rb: &ResourceBank) {
         if rb.get_resource::<EditorVoxelEditing>().is_click_consumed {
             return;
@@ -51,6 +80,8 @@ impl EditorVoxelEditingEditTools {
         rb.run_system(Self::update_pencil_tool);
         rb.run_system(Self::update_paint_tool);
         rb.run_system(Self::update_eraser_tool);
+        rb.run_system(Self::update_bucket_tool);
+        rb.run_system(Self::update_line_tool);
     }
 
     fn update_pencil_tool(
@@ -138,15 +169,10 @@ impl EditorVoxelEditingEditTools {
                 let edit = VoxelModelEdit {
                     region: brush_edit_rect,
                     mask: rogue_engine::voxel::voxel::VoxelModelEditMask {
-                        layers: vec![VoxelModelEditMaskLayer::Sphere {
-                            center: hit_pos,
-                            diameter: *brush_size,
-                        }],
+                        layers: vec![editing.brush_shape.mask_layer(hit_pos, *brush_size)],
                         mask_source: None,
                     },
-                    operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(Some(
-                        voxel_material,
-                    )),
+                    operator: editing.brush_operator(voxel_material),
                 };
                 editing.apply_entity_edit(
                     &mut voxel_registry,
@@ -167,14 +193,11 @@ impl EditorVoxelEditingEditTools {
                 let edit = VoxelTerrainEdit {
                     region: VoxelTerrainRegion::new_rect(brush_min, brush_max),
                     mask: VoxelTerrainEditMask {
-                        layers: vec![VoxelTerrainEditMaskLayer(VoxelModelEditMaskLayer::Sphere {
-                            center: hit_pos,
-                            diameter: *brush_size,
-                        })],
+                        layers: vec![VoxelTerrainEditMaskLayer(
+                            editing.brush_shape.mask_layer(hit_pos, *brush_size),
+                        )],
                     },
-                    operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(Some(
-                        voxel_material,
-                    )),
+                    operator: editing.brush_operator(voxel_material),
                 };
                 editing.apply_terrain_edit(&mut region_map, &mut voxel_registry, edit, true);
             }
@@ -247,17 +270,12 @@ impl EditorVoxelEditingEditTools {
                     region: brush_edit_rect,
                     mask: rogue_engine::voxel::voxel::VoxelModelEditMask {
                         layers: vec![
-                            VoxelModelEditMaskLayer::Sphere {
-                                center: hit_pos,
-                                diameter: *brush_size,
-                            },
+                            editing.brush_shape.mask_layer(hit_pos, *brush_size),
                             VoxelModelEditMaskLayer::Presence,
                         ],
                         mask_source: None,
                     },
-                    operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(Some(
-                        voxel_material,
-                    )),
+                    operator: editing.brush_operator(voxel_material),
                 };
                 editing.apply_entity_edit(
                     &mut voxel_registry,
@@ -278,16 +296,13 @@ impl EditorVoxelEditingEditTools {
                     region: VoxelTerrainRegion::new_rect(brush_min, brush_max),
                     mask: VoxelTerrainEditMask {
                         layers: vec![
-                            VoxelTerrainEditMaskLayer(VoxelModelEditMaskLayer::Sphere {
-                                center: hit_pos,
-                                diameter: *brush_size,
-                            }),
+                            VoxelTerrainEditMaskLayer(
+                                editing.brush_shape.mask_layer(hit_pos, *brush_size),
+                            ),
                             VoxelTerrainEditMaskLayer(VoxelModelEditMaskLayer::Presence),
                         ],
                     },
-                    operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(Some(
-                        voxel_material,
-                    )),
+                    operator: editing.brush_operator(voxel_material),
                 };
                 editing.apply_terrain_edit(
                     &mut region_map,
@@ -350,10 +365,7 @@ impl EditorVoxelEditingEditTools {
                 let edit = VoxelModelEdit {
                     region: brush_edit_rect,
                     mask: rogue_engine::voxel::voxel::VoxelModelEditMask {
-                        layers: vec![VoxelModelEditMaskLayer::Sphere {
-                            center: hit_pos,
-                            diameter: *brush_size,
-                        }],
+                        layers: vec![editing.brush_shape.mask_layer(hit_pos, *brush_size)],
                         mask_source: None,
                     },
                     operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(None),
@@ -376,10 +388,9 @@ impl EditorVoxelEditingEditTools {
                 let edit = VoxelTerrainEdit {
                     region: VoxelTerrainRegion::new_rect(brush_min, brush_max),
                     mask: VoxelTerrainEditMask {
-                        layers: vec![VoxelTerrainEditMaskLayer(VoxelModelEditMaskLayer::Sphere {
-                            center: hit_pos,
-                            diameter: *brush_size,
-                        })],
+                        layers: vec![VoxelTerrainEditMaskLayer(
+                            editing.brush_shape.mask_layer(hit_pos, *brush_size),
+                        )],
                     },
                     operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(None),
                 };
@@ -391,6 +402,345 @@ impl EditorVoxelEditingEditTools {
         }
     }
 
+    fn update_bucket_tool(
+        mut editing: ResMut<EditorVoxelEditing>,
+        mut voxel_registry: ResMut<VoxelModelRegistry>,
+        ecs_world: Res<ECSWorld>,
+        input: Res<Input>,
+        mut region_map: ResMut<RegionMap>,
+        editor_session: Res<EditorSession>,
+        mut events: ResMut<Events>,
+    ) {
+        if !input.is_mouse_button_pressed(mouse::Button::Left) {
+            return;
+        }
+        let tool = editing.tools.get(&editing.selected_tool_type).unwrap();
+        let EditorEditingTool::Bucket { max_voxels } = tool else {
+            return;
+        };
+
+        let Some(voxel_material) = editing.current_voxel_material() else {
+            return;
+        };
+
+        match &editing.edit_target {
+            Some(EditorVoxelEditingTarget::Entity(target_entity)) => {
+                let Some(raycast) = &editor_session.entity_raycast else {
+                    return;
+                };
+                if &raycast.entity != target_entity {
+                    return;
+                }
+                let renderable = ecs_world
+                    .get::<&RenderableVoxelEntity>(*target_entity)
+                    .expect("Target entity should have a renderable model attached.");
+                if !renderable.is_dynamic() {
+                    return;
+                }
+                let entity_model_id = renderable
+                    .voxel_model_id()
+                    .expect("Target entity should have a voxel model");
+                let hit_pos = raycast.model_trace.local_position.cast::<i32>();
+                let Some(fill) =
+                    Self::flood_fill_entity(&voxel_registry, entity_model_id, hit_pos, *max_voxels)
+                else {
+                    log::warn!(
+                        "Bucket fill reached more than {} voxels, cancelling the fill.",
+                        max_voxels
+                    );
+                    return;
+                };
+                let entity_model_side_length =
+                    voxel_registry.get_dyn_model(entity_model_id).length();
+
+                let edit = VoxelModelEdit {
+                    region: VoxelModelEditRegion::saturate_rect(
+                        fill.min,
+                        fill.max,
+                        entity_model_side_length,
+                    ),
+                    mask: rogue_engine::voxel::voxel::VoxelModelEditMask {
+                        layers: vec![VoxelModelEditMaskLayer::Voxels {
+                            voxels: fill.voxels,
+                            offset: Vector3::zeros(),
+                        }],
+                        mask_source: None,
+                    },
+                    operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(Some(
+                        voxel_material,
+                    )),
+                };
+                editing.apply_entity_edit(
+                    &mut voxel_registry,
+                    &mut events,
+                    edit,
+                    entity_model_id,
+                    true,
+                );
+            }
+            Some(EditorVoxelEditingTarget::Terrain) => {
+                let Some(raycast) = &editor_session.terrain_raycast else {
+                    return;
+                };
+
+                let hit_pos =
+                    raycast.world_voxel_pos + raycast.model_trace.local_normal.cast::<i32>();
+                let Some(fill) =
+                    Self::flood_fill_terrain(&region_map, &voxel_registry, hit_pos, *max_voxels)
+                else {
+                    log::warn!(
+                        "Bucket fill reached more than {} voxels, cancelling the fill.",
+                        max_voxels
+                    );
+                    return;
+                };
+
+                let edit = VoxelTerrainEdit {
+                    region: VoxelTerrainRegion::new_rect(fill.min, fill.max),
+                    mask: VoxelTerrainEditMask {
+                        layers: vec![VoxelTerrainEditMaskLayer(VoxelModelEditMaskLayer::Voxels {
+                            voxels: fill.voxels,
+                            offset: Vector3::zeros(),
+                        })],
+                    },
+                    operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(Some(
+                        voxel_material,
+                    )),
+                };
+                editing.apply_terrain_edit(&mut region_map, &mut voxel_registry, edit, true);
+            }
+            None => {
+                return;
+            }
+        }
+    }
+
+    fn update_line_tool(
+        mut edit_tools: ResMut<EditorVoxelEditingEditTools>,
+        mut editing: ResMut<EditorVoxelEditing>,
+        mut voxel_registry: ResMut<VoxelModelRegistry>,
+        ecs_world: Res<ECSWorld>,
+        mut region_map: ResMut<RegionMap>,
+        input: Res<Input>,
+        editor_session: Res<EditorSession>,
+        mut events: ResMut<Events>,
+    ) {
+        let tool = editing.tools.get(&editing.selected_tool_type).unwrap();
+        let EditorEditingTool::Line { brush_size } = tool else {
+            edit_tools.line.anchor = None;
+            return;
+        };
+        let brush_size = *brush_size;
+
+        if input.is_mouse_button_pressed(mouse::Button::Right)
+            || input.is_key_pressed(keyboard::Key::Escape)
+        {
+            edit_tools.line.anchor = None;
+            return;
+        }
+        if !input.is_mouse_button_pressed(mouse::Button::Left) {
+            return;
+        }
+
+        let Some(voxel_material) = editing.current_voxel_material() else {
+            return;
+        };
+        let Some(target) = editing.edit_target.clone() else {
+            return;
+        };
+
+        let entity_model_id = match &target {
+            EditorVoxelEditingTarget::Entity(target_entity) => {
+                let renderable = ecs_world
+                    .get::<&RenderableVoxelEntity>(*target_entity)
+                    .expect("Target entity should have a renderable model attached.");
+                if !renderable.is_dynamic() {
+                    return;
+                }
+                Some(
+                    renderable
+                        .voxel_model_id()
+                        .expect("Target entity should have a voxel model"),
+                )
+            }
+            EditorVoxelEditingTarget::Terrain => None,
+        };
+        let Some(hit_pos) = Self::line_hit_pos(&target, &editor_session) else {
+            return;
+        };
+
+        let Some(anchor) = edit_tools.line_anchor(&target) else {
+            edit_tools.line.anchor = Some((target, hit_pos));
+            return;
+        };
+        edit_tools.line.anchor = None;
+
+        // Each step is its own brush edit, only the first saves history so the line is undone
+        // as one edit.
+        for (i, voxel_pos) in Self::rasterize_line(anchor, hit_pos)
+            .into_iter()
+            .enumerate()
+        {
+            let save_history = i == 0;
+            let (brush_min, brush_max) = Self::calculate_brush_min_max(voxel_pos, brush_size);
+            let brush_layer = editing.brush_shape.mask_layer(voxel_pos, brush_size);
+            let operator = editing.brush_operator(voxel_material.clone());
+            match entity_model_id {
+                Some(entity_model_id) => {
+                    let entity_model_side_length =
+                        voxel_registry.get_dyn_model(entity_model_id).length();
+                    let edit = VoxelModelEdit {
+                        region: VoxelModelEditRegion::saturate_rect(
+                            brush_min,
+                            brush_max,
+                            entity_model_side_length,
+                        ),
+                        mask: rogue_engine::voxel::voxel::VoxelModelEditMask {
+                            layers: vec![brush_layer],
+                            mask_source: None,
+                        },
+                        operator,
+                    };
+                    editing.apply_entity_edit(
+                        &mut voxel_registry,
+                        &mut events,
+                        edit,
+                        entity_model_id,
+                        save_history,
+                    );
+                }
+                None => {
+                    let edit = VoxelTerrainEdit {
+                        region: VoxelTerrainRegion::new_rect(brush_min, brush_max),
+                        mask: VoxelTerrainEditMask {
+                            layers: vec![VoxelTerrainEditMaskLayer(brush_layer)],
+                        },
+                        operator,
+                    };
+                    editing.apply_terrain_edit(
+                        &mut region_map,
+                        &mut voxel_registry,
+                        edit,
+                        save_history,
+                    );
+                }
+            }
+        }
+    }
+
+    /// The voxel in front of the hovered voxel of `target`, in model space for entities and
+    /// world space for terrain.
+    pub fn line_hit_pos(
+        target: &EditorVoxelEditingTarget,
+        editor_session: &EditorSession,
+    ) -> Option<Vector3<i32>> {
+        match target {
+            EditorVoxelEditingTarget::Entity(target_entity) => {
+                let raycast = editor_session
+                    .entity_raycast
+                    .as_ref()
+                    .filter(|hit| &hit.entity == target_entity)?;
+                Some(
+                    raycast.model_trace.local_position.cast::<i32>()
+                        + raycast.model_trace.local_normal,
+                )
+            }
+            EditorVoxelEditingTarget::Terrain => {
+                let raycast = editor_session.terrain_raycast.as_ref()?;
+                Some(raycast.world_voxel_pos + raycast.model_trace.local_normal.cast::<i32>())
+            }
+        }
+    }
+
+    /// The voxels along the line from `start` to `end` inclusive, stepping one voxel at a time
+    /// along the longest axis.
+    pub fn rasterize_line(start: Vector3<i32>, end: Vector3<i32>) -> Vec<Vector3<i32>> {
+        let diff = end - start;
+        let steps = diff.abs().max();
+        if steps == 0 {
+            return vec![start];
+        }
+        (0..=steps)
+            .map(|i| {
+                start + (diff.cast::<f32>() * (i as f32 / steps as f32)).map(|x| x.round() as i32)
+            })
+            .collect()
+    }
+
+    /// Flood fills from the model voxel `seed` through the voxels with the same material as it.
+    pub fn flood_fill_entity(
+        voxel_registry: &VoxelModelRegistry,
+        model_id: VoxelModelId,
+        seed: Vector3<i32>,
+        max_voxels: u32,
+    ) -> Option<EditorVoxelEditingFloodFill> {
+        let model = voxel_registry.get_dyn_model(model_id);
+        Self::flood_fill(seed, max_voxels, |voxel_pos| {
+            model
+                .in_bounds(voxel_pos)
+                .then(|| model.get_voxel(voxel_pos.map(|x| x as u32)))
+        })
+    }
+
+    /// Flood fills from the world voxel `seed` through the voxels with the same material as it,
+    /// which for the bucket is the empty space in front of the hovered voxel.
+    pub fn flood_fill_terrain(
+        region_map: &RegionMap,
+        voxel_registry: &VoxelModelRegistry,
+        seed: Vector3<i32>,
+        max_voxels: u32,
+    ) -> Option<EditorVoxelEditingFloodFill> {
+        Self::flood_fill(seed, max_voxels, |voxel_pos| {
+            Some(region_map.get_voxel(voxel_registry, &voxel_pos))
+        })
+    }
+
+    /// Breadth first fill over the 6-connected neighbors of `seed` which have the same voxel,
+    /// `sample_voxel` returns None for positions outside of the fillable bounds. Returns None if
+    /// the fill would exceed `max_voxels`.
+    fn flood_fill(
+        seed: Vector3<i32>,
+        max_voxels: u32,
+        sample_voxel: impl Fn(Vector3<i32>) -> Option<Option<VoxelMaterialData>>,
+    ) -> Option<EditorVoxelEditingFloodFill> {
+        const NEIGHBORS: [Vector3<i32>; 6] = [
+            Vector3::new(1, 0, 0),
+            Vector3::new(-1, 0, 0),
+            Vector3::new(0, 1, 0),
+            Vector3::new(0, -1, 0),
+            Vector3::new(0, 0, 1),
+            Vector3::new(0, 0, -1),
+        ];
+
+        let target_voxel = sample_voxel(seed)?;
+        let mut voxels = HashSet::from([seed]);
+        let mut queue = VecDeque::from([seed]);
+        let (mut min, mut max) = (seed, seed);
+        while let Some(voxel_pos) = queue.pop_front() {
+            for neighbor_offset in &NEIGHBORS {
+                let neighbor = voxel_pos + neighbor_offset;
+                if voxels.contains(&neighbor)
+                    || sample_voxel(neighbor).is_none_or(|voxel| voxel != target_voxel)
+                {
+                    continue;
+                }
+                if voxels.len() >= max_voxels as usize {
+                    return None;
+                }
+                voxels.insert(neighbor);
+                min = min.inf(&neighbor);
+                max = max.sup(&neighbor);
+                queue.push_back(neighbor);
+            }
+        }
+
+        Some(EditorVoxelEditingFloodFill {
+            voxels: Arc::new(voxels),
+            min,
+            max,
+        })
+    }
+
     pub fn calculate_brush_min_max(
         hit_pos: Vector3<i32>,
         brush_size: u32,
@@ -406,3 +756,69 @@ impl EditorVoxelEditingEditTools {
         (min, max)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rogue_engine::common::color::ColorSrgba;
+
+    use super::*;
+
+    #[test]
+    fn flood_fill_stops_at_other_materials() {
+        let red = VoxelMaterialData::Baked {
+            color: ColorSrgba::new(1.0, 0.0, 0.0, 1.0),
+        };
+        // An 8x8x8 box of empty space with a red wall at x = 4 splitting it in two.
+        let sample_voxel = |voxel_pos: Vector3<i32>| {
+            if voxel_pos.iter().any(|x| *x < 0 || *x >= 8) {
+                return None;
+            }
+            Some((voxel_pos.x == 4).then(|| red.clone()))
+        };
+
+        let fill =
+            EditorVoxelEditingEditTools::flood_fill(Vector3::new(1, 2, 3), 1000, sample_voxel)
+                .expect("Fill is within the max voxels.");
+        assert_eq!(fill.voxels.len(), 4 * 8 * 8);
+        assert!(fill.voxels.iter().all(|voxel| voxel.x < 4));
+        assert_eq!(fill.min, Vector3::new(0, 0, 0));
+        assert_eq!(fill.max, Vector3::new(3, 7, 7));
+
+        let wall_fill =
+            EditorVoxelEditingEditTools::flood_fill(Vector3::new(4, 0, 0), 1000, sample_voxel)
+                .expect("Fill is within the max voxels.");
+        assert_eq!(wall_fill.voxels.len(), 8 * 8);
+
+        // Runaway fills are cancelled.
+        assert!(
+            EditorVoxelEditingEditTools::flood_fill(Vector3::new(1, 2, 3), 100, sample_voxel)
+                .is_none()
+        );
+        // Seeds outside of the fillable bounds fill nothing.
+        assert!(
+            EditorVoxelEditingEditTools::flood_fill(Vector3::new(-1, 0, 0), 1000, sample_voxel)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn rasterized_line_is_contiguous() {
+        let start = Vector3::new(2, -3, 5);
+        let end = Vector3::new(9, 1, -1);
+        let line = EditorVoxelEditingEditTools::rasterize_line(start, end);
+        // One voxel per step along the longest axis.
+        assert_eq!(line.len(), 8);
+        assert_eq!(line.first(), Some(&start));
+        assert_eq!(line.last(), Some(&end));
+        for step in line.windows(2) {
+            let offset = step[1] - step[0];
+            assert_eq!(offset.x, 1);
+            assert_eq!(offset.abs().max(), 1);
+        }
+
+        assert_eq!(
+            EditorVoxelEditingEditTools::rasterize_line(start, start),
+            vec![start]
+        );
+    }
+}
@@ -1,18 +1,23 @@
-use nalgebra::Vector3;
+use nalgebra::{UnitQuaternion, Vector2, Vector3};
 use rogue_engine::{
-    common::color::Color,
+    common::{
+        color::Color,
+        geometry::{aabb::AABB, obb::OBB},
+    },
     consts,
+    debug::debug_renderer::{DebugRenderer, DebugShapeFlags},
     entity::{
         RenderableVoxelEntity,
         ecs_world::{self, ECSWorld},
     },
+    input::{Input, keyboard, mouse},
     physics::transform::Transform,
     resource::{Res, ResMut, ResourceBank},
     voxel::{
         attachment::Attachment,
         sft_compressed::VoxelModelSFTCompressed,
         voxel::{
-            VoxelModelEdit, VoxelModelEditMask, VoxelModelEditMaskLayer,
+            VoxelEditMirrorAxes, VoxelModelEdit, VoxelModelEditMask, VoxelModelEditMaskLayer,
             VoxelModelEditMaskModelSource, VoxelModelEditMaskSource,
             VoxelModelEditMaskSourceMethods, VoxelModelEditMaskTerrainSource, VoxelModelEditRegion,
             VoxelModelImpl, VoxelModelImplMethods,
@@ -26,8 +31,10 @@ use rogue_macros::Resource;
 
 use crate::{
     editing::{
-        voxel_editing::{EditorEditingTool, EditorVoxelEditing, EditorVoxelEditingTarget},
-        voxel_editing_edit_tools::EditorVoxelEditingEditTools,
+        voxel_editing::{
+            EditorBrushShape, EditorEditingTool, EditorVoxelEditing, EditorVoxelEditingTarget,
+        },
+        voxel_editing_edit_tools::{EditorVoxelEditingEditTools, EditorVoxelEditingFloodFill},
         voxel_editing_preview_gpu::EditorVoxelEditingPreviewGpu,
     },
     session::EditorSession,
@@ -39,6 +46,16 @@ pub struct EditorVoxelEditingPreview {
     // Tracked to update the gpu model, realistically I could just also update the gpu model here
     pub show_preview: bool,
     preview_model_transform: Transform,
+    bucket_fill: Option<EditorVoxelEditingBucketPreview>,
+}
+
+/// The last bucket fill preview, flood filling is too slow to redo every frame so it is only
+/// redone when the hovered voxel changes or an edit may have changed the fill.
+struct EditorVoxelEditingBucketPreview {
+    target: EditorVoxelEditingTarget,
+    seed: Vector3<i32>,
+    max_voxels: u32,
+    fill: Option<EditorVoxelEditingFloodFill>,
 }
 
 impl EditorVoxelEditingPreview {
@@ -47,6 +64,7 @@ impl EditorVoxelEditingPreview {
             preview_model: None,
             show_preview: false,
             preview_model_transform: Transform::new(),
+            bucket_fill: None,
         }
     }
 
@@ -55,6 +73,10 @@ impl EditorVoxelEditingPreview {
         rb.run_system(Self::update_preview_pencil_tool);
         rb.run_system(Self::update_preview_paint_tool);
         rb.run_system(Self::update_preview_eraser_tool);
+        rb.run_system(Self::update_preview_bucket_tool);
+        rb.run_system(Self::update_preview_line_tool);
+        rb.run_system(Self::update_preview_brush_shape);
+        rb.run_system(Self::update_preview_mirror_planes);
     }
 
     pub fn update_preview_model(
@@ -76,6 +98,7 @@ impl EditorVoxelEditingPreview {
         mut voxel_registry_gpu: ResMut<VoxelModelRegistryGpu>,
         editor_session: Res<EditorSession>,
         ecs_world: ResMut<ECSWorld>,
+        mut debug_renderer: ResMut<DebugRenderer>,
     ) {
         let preview_model_id = preview.preview_model.unwrap();
         let tool = editing.tools.get(&editing.selected_tool_type).unwrap();
@@ -101,7 +124,7 @@ impl EditorVoxelEditingPreview {
                     .get::<&Transform>(*target_entity)
                     .expect("Target entity should have a renderable model attached.");
                 let entity_world_transform =
-                    ecs_world.get_world_transform(*target_entity, &entity_transform);
+                    ecs_world.get_global_transform(*target_entity, &entity_transform);
                 let renderable = ecs_world
                     .get::<&RenderableVoxelEntity>(*target_entity)
                     .expect("Target entity should have a renderable model attached.");
@@ -153,10 +176,7 @@ impl EditorVoxelEditingPreview {
                 let edit = VoxelModelEdit {
                     region: brush_edit_rect,
                     mask: rogue_engine::voxel::voxel::VoxelModelEditMask {
-                        layers: vec![VoxelModelEditMaskLayer::Sphere {
-                            center: preview_center,
-                            diameter: *brush_size,
-                        }],
+                        layers: vec![editing.brush_shape.mask_layer(preview_center, *brush_size)],
                         mask_source: None,
                     },
                     operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(Some(
@@ -190,6 +210,17 @@ impl EditorVoxelEditingPreview {
                 let hit_pos =
                     raycast.world_voxel_pos + raycast.model_trace.local_normal.cast::<i32>();
 
+                let hit_voxel_center = (raycast.world_voxel_pos.cast::<f32>()
+                    + Vector3::repeat(0.5))
+                    * consts::voxel::VOXEL_METER_LENGTH;
+                debug_renderer.draw_line_arrow(
+                    hit_voxel_center,
+                    hit_voxel_center + raycast.model_trace.local_normal.cast::<f32>() * 0.5,
+                    0.01,
+                    Color::new_srgba_hex("#2368DF", 1.0),
+                    DebugShapeFlags::NONE,
+                );
+
                 preview.show_preview = true;
 
                 let preview_model = voxel_registry.get_dyn_model_mut(preview_model_id);
@@ -216,10 +247,7 @@ impl EditorVoxelEditingPreview {
                     preview_model_side_length,
                 );
                 let mask = VoxelModelEditMask {
-                    layers: vec![VoxelModelEditMaskLayer::Sphere {
-                        center: preview_center,
-                        diameter: *brush_size,
-                    }],
+                    layers: vec![editing.brush_shape.mask_layer(preview_center, *brush_size)],
                     mask_source: None,
                 };
 
@@ -280,7 +308,7 @@ impl EditorVoxelEditingPreview {
                 //    return;
                 //}
                 let entity_world_transform =
-                    ecs_world.get_world_transform(*target_entity, entity_transform);
+                    ecs_world.get_global_transform(*target_entity, entity_transform);
                 let entity_model_id = renderable
                     .voxel_model_id()
                     .expect("Target entity should have a voxel model");
@@ -308,10 +336,7 @@ impl EditorVoxelEditingPreview {
                     region: brush_edit_rect,
                     mask: rogue_engine::voxel::voxel::VoxelModelEditMask {
                         layers: vec![
-                            VoxelModelEditMaskLayer::Sphere {
-                                center: preview_center,
-                                diameter: *brush_size,
-                            },
+                            editing.brush_shape.mask_layer(preview_center, *brush_size),
                             VoxelModelEditMaskLayer::Presence,
                         ],
                         mask_source: Some(rogue_engine::voxel::voxel::VoxelModelEditMaskSource {
@@ -388,10 +413,7 @@ impl EditorVoxelEditingPreview {
                     hit_pos - terrain_mask_source.chunk_min().get_min_world_voxel_pos();
                 let mask = VoxelModelEditMask {
                     layers: vec![
-                        VoxelModelEditMaskLayer::Sphere {
-                            center: preview_center,
-                            diameter: *brush_size,
-                        },
+                        editing.brush_shape.mask_layer(preview_center, *brush_size),
                         VoxelModelEditMaskLayer::Presence,
                     ],
                     mask_source: Some(VoxelModelEditMaskSource {
@@ -454,7 +476,7 @@ impl EditorVoxelEditingPreview {
                 //    return;
                 //}
                 let entity_world_transform =
-                    ecs_world.get_world_transform(*target_entity, entity_transform);
+                    ecs_world.get_global_transform(*target_entity, entity_transform);
                 let entity_model_id = renderable
                     .voxel_model_id()
                     .expect("Target entity should have a voxel model");
@@ -478,10 +500,7 @@ impl EditorVoxelEditingPreview {
                 let edit = VoxelModelEdit {
                     region: brush_edit_rect,
                     mask: rogue_engine::voxel::voxel::VoxelModelEditMask {
-                        layers: vec![VoxelModelEditMaskLayer::Sphere {
-                            center: preview_center,
-                            diameter: *brush_size,
-                        }],
+                        layers: vec![editing.brush_shape.mask_layer(preview_center, *brush_size)],
                         mask_source: None,
                     },
                     operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(Some(
@@ -544,10 +563,7 @@ impl EditorVoxelEditingPreview {
                     preview_model_side_length,
                 );
                 let mask = VoxelModelEditMask {
-                    layers: vec![VoxelModelEditMaskLayer::Sphere {
-                        center: preview_center,
-                        diameter: *brush_size,
-                    }],
+                    layers: vec![editing.brush_shape.mask_layer(preview_center, *brush_size)],
                     mask_source: None,
                 };
 
@@ -573,6 +589,377 @@ impl EditorVoxelEditingPreview {
         }
     }
 
+    /// Outlines the bounds of the region the bucket would fill.
+    pub fn update_preview_bucket_tool(
+        editing: Res<EditorVoxelEditing>,
+        mut preview: ResMut<EditorVoxelEditingPreview>,
+        voxel_registry: Res<VoxelModelRegistry>,
+        region_map: Res<RegionMap>,
+        editor_session: Res<EditorSession>,
+        ecs_world: Res<ECSWorld>,
+        input: Res<Input>,
+        mut debug_renderer: ResMut<DebugRenderer>,
+    ) {
+        let tool = editing.tools.get(&editing.selected_tool_type).unwrap();
+        let EditorEditingTool::Bucket { max_voxels } = tool else {
+            preview.bucket_fill = None;
+            return;
+        };
+        // Terrain edits are applied later in the frame so wait for the release to refill.
+        if input.is_mouse_button_released(mouse::Button::Left)
+            || input.is_key_pressed_with_modifiers(keyboard::Key::Z, &[keyboard::Modifier::Control])
+        {
+            preview.bucket_fill = None;
+        }
+
+        let Some(target) = &editing.edit_target else {
+            return;
+        };
+        let seed = match target {
+            EditorVoxelEditingTarget::Entity(target_entity) => {
+                let Some(raycast) = &editor_session.entity_raycast else {
+                    return;
+                };
+                if &raycast.entity != target_entity {
+                    return;
+                }
+                raycast.model_trace.local_position.cast::<i32>()
+            }
+            EditorVoxelEditingTarget::Terrain => {
+                let Some(raycast) = &editor_session.terrain_raycast else {
+                    return;
+                };
+                raycast.world_voxel_pos + raycast.model_trace.local_normal.cast::<i32>()
+            }
+        };
+
+        let is_cached = preview.bucket_fill.as_ref().is_some_and(|bucket_fill| {
+            &bucket_fill.target == target
+                && bucket_fill.seed == seed
+                && bucket_fill.max_voxels == *max_voxels
+        });
+        if !is_cached {
+            let fill = match target {
+                EditorVoxelEditingTarget::Entity(target_entity) => {
+                    let Ok(renderable) = ecs_world.get::<&RenderableVoxelEntity>(*target_entity)
+                    else {
+                        return;
+                    };
+                    let Some(entity_model_id) = renderable.voxel_model_id() else {
+                        return;
+                    };
+                    EditorVoxelEditingEditTools::flood_fill_entity(
+                        &voxel_registry,
+                        entity_model_id,
+                        seed,
+                        *max_voxels,
+                    )
+                }
+                EditorVoxelEditingTarget::Terrain => {
+                    EditorVoxelEditingEditTools::flood_fill_terrain(
+                        &region_map,
+                        &voxel_registry,
+                        seed,
+                        *max_voxels,
+                    )
+                }
+            };
+            preview.bucket_fill = Some(EditorVoxelEditingBucketPreview {
+                target: target.clone(),
+                seed,
+                max_voxels: *max_voxels,
+                fill,
+            });
+        }
+
+        // Nothing to show if the fill is too large and would be cancelled.
+        let Some(fill) = &preview.bucket_fill.as_ref().unwrap().fill else {
+            return;
+        };
+
+        let Some(fill_obb) =
+            Self::voxel_rect_obb(target, fill.min, fill.max, &ecs_world, &voxel_registry)
+        else {
+            return;
+        };
+        debug_renderer.draw_obb_outline(
+            &fill_obb,
+            0.002,
+            Color::new_srgba_hex("#2368DF", 1.0),
+            DebugShapeFlags::NONE,
+        );
+    }
+
+    /// Outlines the brush rect of cube brushes, the preview model alone makes it hard to tell
+    /// where the flat faces will line up.
+    pub fn update_preview_brush_shape(
+        editing: Res<EditorVoxelEditing>,
+        voxel_registry: Res<VoxelModelRegistry>,
+        editor_session: Res<EditorSession>,
+        ecs_world: Res<ECSWorld>,
+        mut debug_renderer: ResMut<DebugRenderer>,
+    ) {
+        if editing.brush_shape != EditorBrushShape::Cube {
+            return;
+        }
+        let tool = editing.current_tool();
+        let brush_size = match tool {
+            EditorEditingTool::Pencil { brush_size, .. }
+            | EditorEditingTool::Paint { brush_size }
+            | EditorEditingTool::Eraser { brush_size }
+            | EditorEditingTool::Line { brush_size } => *brush_size,
+            _ => return,
+        };
+        let Some(target) = &editing.edit_target else {
+            return;
+        };
+        let hit_pos = match target {
+            EditorVoxelEditingTarget::Entity(target_entity) => {
+                let Some(raycast) = editor_session
+                    .entity_raycast
+                    .as_ref()
+                    .filter(|hit| &hit.entity == target_entity)
+                else {
+                    return;
+                };
+                let mut hit_pos = raycast.model_trace.local_position.cast::<i32>();
+                if tool.should_offset() {
+                    hit_pos += raycast.model_trace.local_normal;
+                }
+                hit_pos
+            }
+            EditorVoxelEditingTarget::Terrain => {
+                let Some(raycast) = &editor_session.terrain_raycast else {
+                    return;
+                };
+                let mut hit_pos = raycast.world_voxel_pos;
+                if tool.should_offset() {
+                    hit_pos += raycast.model_trace.local_normal.cast::<i32>();
+                }
+                hit_pos
+            }
+        };
+
+        let (brush_min, brush_max) =
+            EditorVoxelEditingEditTools::calculate_brush_min_max(hit_pos, brush_size);
+        let Some(brush_obb) =
+            Self::voxel_rect_obb(target, brush_min, brush_max, &ecs_world, &voxel_registry)
+        else {
+            return;
+        };
+        debug_renderer.draw_obb_outline(
+            &brush_obb,
+            0.002,
+            Color::new_srgba_hex("#2368DF", 1.0),
+            DebugShapeFlags::NONE,
+        );
+    }
+
+    /// The world space obb of the inclusive voxel rect `min`..=`max`, in model space for
+    /// entities and world space for terrain.
+    fn voxel_rect_obb(
+        target: &EditorVoxelEditingTarget,
+        min: Vector3<i32>,
+        max: Vector3<i32>,
+        ecs_world: &ECSWorld,
+        voxel_registry: &VoxelModelRegistry,
+    ) -> Option<OBB> {
+        match target {
+            EditorVoxelEditingTarget::Entity(target_entity) => {
+                let (transform, renderable) = ecs_world
+                    .query_one::<(&Transform, &RenderableVoxelEntity)>(*target_entity)
+                    .get()?;
+                let model_id = renderable.voxel_model_id()?;
+                let world_transform = ecs_world.get_global_transform(*target_entity, transform);
+                let model_obb = world_transform
+                    .as_voxel_model_obb(voxel_registry.get_dyn_model(model_id).length());
+                let rect_aabb_min = model_obb.aabb.min
+                    + min.cast::<f32>().component_mul(&world_transform.scale)
+                        * consts::voxel::VOXEL_METER_LENGTH;
+                let rect_aabb_max = model_obb.aabb.min
+                    + (max + Vector3::new(1, 1, 1))
+                        .cast::<f32>()
+                        .component_mul(&world_transform.scale)
+                        * consts::voxel::VOXEL_METER_LENGTH;
+                let rect_aabb_center = (rect_aabb_min + rect_aabb_max) * 0.5;
+                Some(OBB::new(
+                    AABB::new_two_point(rect_aabb_min, rect_aabb_max),
+                    world_transform.rotation,
+                    model_obb.aabb.center() - rect_aabb_center,
+                ))
+            }
+            EditorVoxelEditingTarget::Terrain => Some(OBB::new(
+                AABB::new_two_point(
+                    min.cast::<f32>() * consts::voxel::VOXEL_METER_LENGTH,
+                    (max + Vector3::new(1, 1, 1)).cast::<f32>() * consts::voxel::VOXEL_METER_LENGTH,
+                ),
+                UnitQuaternion::identity(),
+                Vector3::zeros(),
+            )),
+        }
+    }
+
+    /// Draws the pending line from the line tool's anchor to the hovered voxel.
+    pub fn update_preview_line_tool(
+        editing: Res<EditorVoxelEditing>,
+        edit_tools: Res<EditorVoxelEditingEditTools>,
+        voxel_registry: Res<VoxelModelRegistry>,
+        editor_session: Res<EditorSession>,
+        ecs_world: Res<ECSWorld>,
+        mut debug_renderer: ResMut<DebugRenderer>,
+    ) {
+        let tool = editing.tools.get(&editing.selected_tool_type).unwrap();
+        let EditorEditingTool::Line { .. } = tool else {
+            return;
+        };
+        let Some(target) = &editing.edit_target else {
+            return;
+        };
+        let Some(anchor) = edit_tools.line_anchor(target) else {
+            return;
+        };
+        let Some(hit_pos) = EditorVoxelEditingEditTools::line_hit_pos(target, &editor_session)
+        else {
+            return;
+        };
+        if anchor == hit_pos {
+            return;
+        }
+
+        let voxel_center = |voxel_pos: Vector3<i32>| -> Option<Vector3<f32>> {
+            let center = voxel_pos.cast::<f32>() + Vector3::repeat(0.5);
+            match target {
+                EditorVoxelEditingTarget::Entity(target_entity) => {
+                    let (transform, renderable) = ecs_world
+                        .query_one::<(&Transform, &RenderableVoxelEntity)>(*target_entity)
+                        .get()?;
+                    let model_id = renderable.voxel_model_id()?;
+                    let world_transform = ecs_world.get_global_transform(*target_entity, transform);
+                    let model_half_length = voxel_registry
+                        .get_dyn_model(model_id)
+                        .length()
+                        .cast::<f32>()
+                        * 0.5;
+                    Some(
+                        world_transform.position
+                            + world_transform.rotation.transform_vector(
+                                &((center - model_half_length)
+                                    .component_mul(&world_transform.scale)
+                                    * consts::voxel::VOXEL_METER_LENGTH),
+                            ),
+                    )
+                }
+                EditorVoxelEditingTarget::Terrain => {
+                    Some(center * consts::voxel::VOXEL_METER_LENGTH)
+                }
+            }
+        };
+        let (Some(start), Some(end)) = (voxel_center(anchor), voxel_center(hit_pos)) else {
+            return;
+        };
+        debug_renderer.draw_line_3d(
+            start,
+            end,
+            0.005,
+            Color::new_srgba_hex("#2368DF", 1.0),
+            DebugShapeFlags::NONE,
+        );
+    }
+
+    /// Draws the planes edits are mirrored across.
+    pub fn update_preview_mirror_planes(
+        editing: Res<EditorVoxelEditing>,
+        voxel_registry: Res<VoxelModelRegistry>,
+        editor_session: Res<EditorSession>,
+        ecs_world: Res<ECSWorld>,
+        mut debug_renderer: ResMut<DebugRenderer>,
+    ) {
+        // Terrain mirror planes are infinite so only the part around the hovered voxel is drawn.
+        const TERRAIN_PLANE_LENGTH: f32 = 8.0;
+
+        if editing.mirror.axes.is_empty() {
+            return;
+        }
+        let (center, rotation, size) = match &editing.edit_target {
+            Some(EditorVoxelEditingTarget::Entity(target_entity)) => {
+                let Some((transform, renderable)) = ecs_world
+                    .query_one::<(&Transform, &RenderableVoxelEntity)>(*target_entity)
+                    .get()
+                else {
+                    return;
+                };
+                let Some(model_id) = renderable.voxel_model_id() else {
+                    return;
+                };
+                let world_transform = ecs_world.get_global_transform(*target_entity, transform);
+                let model_size = voxel_registry
+                    .get_dyn_model(model_id)
+                    .length()
+                    .cast::<f32>()
+                    .component_mul(&world_transform.scale)
+                    * consts::voxel::VOXEL_METER_LENGTH;
+                (
+                    world_transform.position,
+                    world_transform.rotation,
+                    model_size,
+                )
+            }
+            Some(EditorVoxelEditingTarget::Terrain) => {
+                let Some(raycast) = &editor_session.terrain_raycast else {
+                    return;
+                };
+                let plane_pos =
+                    editing.mirror.terrain_plane.cast::<f32>() * consts::voxel::VOXEL_METER_LENGTH;
+                let hit_pos = (raycast.world_voxel_pos.cast::<f32>() + Vector3::repeat(0.5))
+                    * consts::voxel::VOXEL_METER_LENGTH;
+                let mirrored_axes = editing.mirror.terrain_mirror().axes_vector();
+                let center = Vector3::from_fn(|i, _| {
+                    if mirrored_axes[i] == 1 {
+                        plane_pos[i]
+                    } else {
+                        hit_pos[i]
+                    }
+                });
+                (
+                    center,
+                    UnitQuaternion::identity(),
+                    Vector3::repeat(TERRAIN_PLANE_LENGTH),
+                )
+            }
+            None => return,
+        };
+
+        // The debug plane lies on the xz plane so rotate it to face each axis.
+        for (axis, plane_rotation, plane_size) in [
+            (
+                VoxelEditMirrorAxes::X,
+                UnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f32::consts::FRAC_PI_2),
+                Vector2::new(size.y, size.z),
+            ),
+            (
+                VoxelEditMirrorAxes::Y,
+                UnitQuaternion::identity(),
+                Vector2::new(size.x, size.z),
+            ),
+            (
+                VoxelEditMirrorAxes::Z,
+                UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::FRAC_PI_2),
+                Vector2::new(size.x, size.y),
+            ),
+        ] {
+            if !editing.mirror.axes.contains(axis) {
+                continue;
+            }
+            debug_renderer.draw_plane(
+                center,
+                rotation * plane_rotation,
+                plane_size,
+                Color::new_srgba_hex("#2368DF", 0.15),
+                DebugShapeFlags::NONE,
+            );
+        }
+    }
+
     pub fn should_show_preview(&self) -> bool {
         self.show_preview
     }
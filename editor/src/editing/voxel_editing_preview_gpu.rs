@@ -1,4 +1,4 @@
-use nalgebra::Vector3;
+use nalgebra::{Vector2, Vector3};
 use rogue_engine::{
     common::{
         color::Color,
@@ -23,6 +23,7 @@ use rogue_engine::{
         voxel_registry_gpu::VoxelModelRegistryGpu,
     },
     window::time::Time,
+    world::renderable::rt_pass::WorldRTPass,
 };
 use rogue_macros::Resource;
 
@@ -46,6 +47,8 @@ pub struct EditorVoxelEditingPreviewGpu {
     preview_pass: Option<FrameGraphResource<Pass>>,
     graph_framebuffer: Option<FrameGraphResource<Image>>,
     graph_framebuffer_depth: Option<FrameGraphResource<Image>>,
+    /// The workgroup size the preview compute pipeline is specialized with.
+    workgroup_size: Option<Vector2<u32>>,
 }
 
 impl EditorVoxelEditingPreviewGpu {
@@ -63,6 +66,7 @@ impl EditorVoxelEditingPreviewGpu {
             preview_pass: None,
             graph_framebuffer: None,
             graph_framebuffer_depth: None,
+            workgroup_size: None,
         }
     }
 
@@ -103,6 +107,32 @@ impl EditorVoxelEditingPreviewGpu {
         }
     }
 
+    /// Specializes the preview pipeline with the world rt pass's workgroup size since both trace
+    /// the same voxel models per pixel.
+    pub fn update_workgroup_size(
+        mut preview_gpu: ResMut<EditorVoxelEditingPreviewGpu>,
+        rt_pass: Res<WorldRTPass>,
+        mut renderer: ResMut<Renderer>,
+    ) {
+        let Some(target_size) = rt_pass.workgroup_size() else {
+            return;
+        };
+        if preview_gpu.workgroup_size == Some(target_size) {
+            return;
+        }
+        preview_gpu.workgroup_size = Some(target_size);
+        renderer.set_compute_pipeline_define(
+            Self::GRAPH.compute_pipeline_name,
+            "RT_WORKGROUP_SIZE_X",
+            target_size.x,
+        );
+        renderer.set_compute_pipeline_define(
+            Self::GRAPH.compute_pipeline_name,
+            "RT_WORKGROUP_SIZE_Y",
+            target_size.y,
+        );
+    }
+
     pub fn write_render_preview_pass(
         voxel_editing: Res<EditorVoxelEditing>,
         preview: Res<EditorVoxelEditingPreview>,
@@ -46,7 +46,7 @@ impl EditorVoxelEditingSelectionsGpu {
         let mut draw_selection = |selection_obb: &OBB| {
             debug_renderer.draw_obb_filled(
                 &selection_obb,
-                Color::new_srgba(1.0, 1.0, 1.0, 0.1),
+                Color::WHITE.to_srgba(0.1),
                 DebugShapeFlags::NONE,
             );
             debug_renderer.draw_obb_outline(
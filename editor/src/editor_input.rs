@@ -1,4 +1,9 @@
 use nalgebra::Vector2;
+use rogue_engine::{
+    egui::Egui,
+    input::Input,
+    resource::{Res, ResMut},
+};
 use rogue_macros::Resource;
 
 #[derive(Resource)]
@@ -12,4 +17,10 @@ impl EditorInput {
             global_mouse_pos: Vector2::new(0.0, 0.0),
         }
     }
+
+    /// Stops actions from triggering while typing into the ui, this uses the last frame's ui
+    /// since the ui is resolved after the update systems.
+    pub fn update_text_input_focus(mut input: ResMut<Input>, egui: Res<Egui>) {
+        input.text_input_focused = egui.context().wants_keyboard_input();
+    }
 }
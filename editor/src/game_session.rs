@@ -1,8 +1,13 @@
+use std::collections::HashMap;
+
 use rogue_engine::{
-    asset::repr::project::ProjectSettings,
+    asset::repr::{collider::joint::JointAsset, project::ProjectSettings},
+    common::rng::Rng,
     entity::{
+        GameEntity,
         component::GameComponentCloneContext,
         ecs_world::{ECSWorld, Entity},
+        scripting::Scripts,
     },
     event::{EventReader, Events},
     graphics::camera::MainCamera,
@@ -33,6 +38,9 @@ pub struct EditorGameSession {
     pub game_camera: Option<Entity>,
     game_state: SessionGameState,
     saved_game_world: Option<ECSWorld>,
+    /// Joints reference entities by id which the saved world doesn't keep, so they are saved
+    /// by uuid.
+    saved_joints: Vec<JointAsset>,
 
     game_session_event_reader: EventReader<EditorGameSessionEvent>,
 }
@@ -43,6 +51,7 @@ impl EditorGameSession {
             game_camera: project_settings.game_camera,
             game_state: SessionGameState::Stopped,
             saved_game_world: None,
+            saved_joints: Vec::new(),
 
             game_session_event_reader: EventReader::new(),
         }
@@ -56,6 +65,8 @@ impl EditorGameSession {
         mut physics_world: ResMut<PhysicsWorld>,
         mut main_camera: ResMut<MainCamera>,
         mut editor_session: ResMut<EditorSession>,
+        mut scripts: ResMut<Scripts>,
+        rng: Res<Rng>,
     ) {
         let game_session = &mut *game_session;
         let mut new_game_state = None;
@@ -83,18 +94,20 @@ impl EditorGameSession {
         match new_game_state {
             SessionGameState::Playing => {
                 if game_session.game_state == SessionGameState::Paused {
-                    game_session.resume_game(&mut physics_world, &mut ecs_world);
+                    game_session.resume_game(&mut physics_world, &mut ecs_world, &mut scripts);
                 } else {
                     game_session.start_game(
                         &mut ecs_world,
                         &mut physics_world,
                         &mut voxel_registry,
                         &mut main_camera,
+                        &mut scripts,
+                        &rng,
                     );
                 }
             }
             SessionGameState::Paused => {
-                game_session.pause_game(&mut physics_world);
+                game_session.pause_game(&mut physics_world, &mut scripts);
             }
             SessionGameState::Stopped => {
                 game_session.stop_game(
@@ -103,6 +116,7 @@ impl EditorGameSession {
                     &mut voxel_registry,
                     &mut editor_session,
                     &mut main_camera,
+                    &mut scripts,
                 );
             }
         }
@@ -121,9 +135,15 @@ impl EditorGameSession {
         self.game_state != SessionGameState::Stopped
     }
 
-    pub fn resume_game(&mut self, physics_world: &mut PhysicsWorld, ecs_world: &mut ECSWorld) {
+    pub fn resume_game(
+        &mut self,
+        physics_world: &mut PhysicsWorld,
+        ecs_world: &mut ECSWorld,
+        scripts: &mut Scripts,
+    ) {
         assert_eq!(self.game_state, SessionGameState::Paused);
         physics_world.do_dynamics = true;
+        scripts.run_scripts = true;
         PhysicsWorld::sync_transforms(ecs_world);
     }
 
@@ -133,23 +153,38 @@ impl EditorGameSession {
         physics_world: &mut PhysicsWorld,
         voxel_registry: &mut VoxelModelRegistry,
         main_camera: &mut MainCamera,
+        scripts: &mut Scripts,
+        rng: &Rng,
     ) {
         assert!(self.can_start_game() && self.game_state == SessionGameState::Stopped);
 
         let fresh_ecs_world = ecs_world.clone_game_entities(&mut GameComponentCloneContext {
             voxel_registry,
             collider_registry: &mut physics_world.colliders,
+            share_voxel_models: false,
+            share_colliders: false,
         });
         self.saved_game_world = Some(fresh_ecs_world);
+        let entity_uuid_map = ecs_world
+            .query::<&GameEntity>()
+            .into_iter()
+            .map(|(entity, game_entity)| (entity, game_entity.uuid))
+            .collect::<HashMap<_, _>>();
+        self.saved_joints = physics_world
+            .joints()
+            .filter_map(|(_, joint)| JointAsset::from_joint(joint, &entity_uuid_map))
+            .collect();
         main_camera.set_camera(self.game_camera.clone().unwrap(), "game_camera");
 
         physics_world.do_dynamics = true;
+        scripts.start(rng);
         PhysicsWorld::sync_transforms(ecs_world);
     }
 
-    pub fn pause_game(&mut self, physics_world: &mut PhysicsWorld) {
+    pub fn pause_game(&mut self, physics_world: &mut PhysicsWorld, scripts: &mut Scripts) {
         assert_eq!(self.game_state, SessionGameState::Playing);
         physics_world.do_dynamics = false;
+        scripts.run_scripts = false;
     }
 
     pub fn stop_game(
@@ -159,15 +194,35 @@ impl EditorGameSession {
         voxel_registry: &mut VoxelModelRegistry,
         editor_session: &mut EditorSession,
         main_camera: &mut MainCamera,
+        scripts: &mut Scripts,
     ) {
         assert_ne!(self.game_state, SessionGameState::Stopped);
         let saved_game_world = self.saved_game_world.take().unwrap();
         *ecs_world = saved_game_world;
 
+        physics_world.clear_joints();
+        let uuid_to_entity_map = ecs_world
+            .query::<&GameEntity>()
+            .into_iter()
+            .map(|(entity, game_entity)| (game_entity.uuid, entity))
+            .collect::<HashMap<_, _>>();
+        for joint in self.saved_joints.drain(..) {
+            let Some(joint) = joint.to_joint(&uuid_to_entity_map) else {
+                continue;
+            };
+            if let Err(err) = physics_world.add_joint(joint) {
+                log::error!(
+                    "Failed to restore joint after stopping the game. Error: {:?}",
+                    err
+                );
+            }
+        }
+
         let editor_camera = EditorSession::init_editor_camera(ecs_world);
         main_camera.set_camera(editor_camera, "editor_camera");
         editor_session.editor_camera = editor_camera;
         physics_world.do_dynamics = false;
+        scripts.stop();
     }
 
     pub fn try_run_game_on_update(rb: &ResourceBank) {
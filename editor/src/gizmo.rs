@@ -1,3 +1,5 @@
+use std::f32::consts::{PI, TAU};
+
 use nalgebra::{UnitQuaternion, Vector3};
 use rogue_engine::{
     common::{
@@ -5,9 +7,13 @@ use rogue_engine::{
         geometry::ray::Ray,
     },
     debug::debug_renderer::{DebugRenderer, DebugShapeFlags},
-    entity::{RenderableVoxelEntity, ecs_world::ECSWorld},
+    entity::{
+        RenderableVoxelEntity,
+        ecs_world::{ECSWorld, Entity},
+    },
+    consts,
     graphics::camera::MainCamera,
-    input::{Input, mouse},
+    input::{Input, keyboard, mouse},
     physics::{
         collider_component::EntityColliders, physics_world::PhysicsWorld, transform::Transform,
     },
@@ -19,18 +25,40 @@ use rogue_macros::Resource;
 
 use crate::{
     editing::voxel_editing::EditorVoxelEditing, editor_transform_euler::EditorTransformEuler,
-    session::EditorSession, ui::EditorUI,
+    measure_tool::EditorMeasureTool, session::EditorSession, ui::EditorUI,
 };
 
 #[derive(Copy, Clone, strum_macros::EnumDiscriminants)]
 enum GizmoType {
-    Translation { start_proj: f32 },
-    Rotation { last_rot: f32 },
+    Translation {
+        start_proj: f32,
+    },
+    Rotation {
+        last_rot: f32,
+        /// The unsnapped rotation dragged so far.
+        dragged_rot: f32,
+        /// The rotation applied to the entity so far, differs from `dragged_rot` while snapping.
+        applied_rot: f32,
+    },
+    /// Rotates freely around the pivot by dragging across the view plane.
+    Trackball {
+        /// The last dragged point on the view plane relative to the pivot.
+        last_offset: Vector3<f32>,
+    },
+}
+
+/// Which axes the gizmo handles follow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum_macros::Display, strum_macros::VariantArray)]
+pub enum GizmoOrientation {
+    /// Oriented with the primary selection's rotation.
+    Local,
+    /// Aligned to the world axes.
+    Global,
 }
 
 struct ActiveGizmo {
     gizmo_type: GizmoType,
-    axis: Vector3<f32>,
+    /// The handle's axis in world space, the view direction for the trackball.
     world_axis: Vector3<f32>,
     initial_entity_pos: Vector3<f32>,
 }
@@ -94,47 +122,135 @@ impl ActiveGizmo {
         return Some(rot);
     }
 
-    pub fn apply_update(&mut self, ray: &Ray, world_transform: &mut Transform) {
+    /// Rounds `x` to the nearest multiple of `step`, leaving it untouched without a step.
+    fn snap_to_step(x: f32, step: Option<f32>) -> f32 {
+        match step {
+            Some(step) if step > 0.0 => (x / step).round() * step,
+            _ => x,
+        }
+    }
+
+    /// The point `ray` hits on the plane facing `view_dir` through `pos`, relative to `pos`.
+    pub fn view_plane_offset(
+        pos: &Vector3<f32>,
+        view_dir: &Vector3<f32>,
+        ray: &Ray,
+    ) -> Option<Vector3<f32>> {
+        let t = ray.intersect_plane(*pos, *view_dir)?;
+        Some(ray.origin + t * ray.dir - pos)
+    }
+
+    pub fn apply_update(&mut self, ray: &Ray, world_transform: &mut Transform, snap: &GizmoSnap) {
         match &mut self.gizmo_type {
             GizmoType::Translation { start_proj } => {
                 let Some(mut proj) =
-                    Self::plane_proj_axis(&self.initial_entity_pos, &self.axis, ray)
+                    Self::plane_proj_axis(&self.initial_entity_pos, &self.world_axis, ray)
                 else {
                     return;
                 };
-                proj -= *start_proj;
-                world_transform.position = self.initial_entity_pos + self.axis * proj;
+                proj = Self::snap_to_step(proj - *start_proj, snap.translation_step());
+                world_transform.position = self.initial_entity_pos + self.world_axis * proj;
             }
-            GizmoType::Rotation { last_rot } => {
-                let Some(mut rot) =
+            GizmoType::Rotation {
+                last_rot,
+                dragged_rot,
+                applied_rot,
+            } => {
+                let Some(rot) =
                     Self::plane_proj_rotation(&self.initial_entity_pos, &self.world_axis, ray)
                 else {
                     return;
                 };
-                let drot = rot - *last_rot;
+                // Wrap the delta so crossing the atan2 seam doesn't count as a full turn.
+                *dragged_rot += (rot - *last_rot + PI).rem_euclid(TAU) - PI;
                 *last_rot = rot;
-                world_transform.rotation = world_transform.rotation
-                    * UnitQuaternion::from_axis_angle(
-                        &nalgebra::Unit::new_normalize(self.axis),
-                        -drot,
-                    );
+                let snapped_rot = Self::snap_to_step(*dragged_rot, snap.rotation_step());
+                let drot = snapped_rot - *applied_rot;
+                *applied_rot = snapped_rot;
+                world_transform.rotation = UnitQuaternion::from_axis_angle(
+                    &nalgebra::Unit::new_normalize(self.world_axis),
+                    -drot,
+                ) * world_transform.rotation;
+            }
+            GizmoType::Trackball { last_offset } => {
+                let Some(offset) =
+                    Self::view_plane_offset(&self.initial_entity_pos, &self.world_axis, ray)
+                else {
+                    return;
+                };
+                let drag = offset - *last_offset;
+                *last_offset = offset;
+                let drag_length = drag.norm();
+                if drag_length <= f32::EPSILON {
+                    return;
+                }
+                // Roll the side facing the camera along with the drag, dragging across the
+                // ring's radius turns it by a radian.
+                world_transform.rotation = UnitQuaternion::from_axis_angle(
+                    &nalgebra::Unit::new_normalize(drag.cross(&self.world_axis)),
+                    drag_length / EditorGizmo::TRACKBALL_RADIUS,
+                ) * world_transform.rotation;
             }
         }
     }
 }
 
+/// Quantizes gizmo drags while `enabled` is toggled on or control is held. Steps are applied to
+/// the drag delta so entities keep their offset from the grid they started at.
+pub struct GizmoSnap {
+    pub enabled: bool,
+    /// Meters, defaults to one voxel so voxel aligned entities stay on the voxel grid.
+    pub translation_step: f32,
+    /// Radians.
+    pub rotation_step: f32,
+    is_active: bool,
+}
+
+impl GizmoSnap {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            translation_step: consts::voxel::VOXEL_METER_LENGTH,
+            rotation_step: 15.0f32.to_radians(),
+            is_active: false,
+        }
+    }
+
+    /// Whether drags are currently being snapped.
+    pub fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    fn translation_step(&self) -> Option<f32> {
+        self.is_active.then_some(self.translation_step)
+    }
+
+    fn rotation_step(&self) -> Option<f32> {
+        self.is_active.then_some(self.rotation_step)
+    }
+}
+
 /// Tool for modifying the currently selected entity.
 #[derive(Resource)]
 pub struct EditorGizmo {
     hovering_gizmo: bool,
     active_gizmo: Option<ActiveGizmo>,
+    pub snap: GizmoSnap,
+    pub translation_orientation: GizmoOrientation,
+    pub rotation_orientation: GizmoOrientation,
 }
 
 impl EditorGizmo {
+    const RADIUS: f32 = 0.6;
+    const TRACKBALL_RADIUS: f32 = 0.75;
+
     pub fn new() -> Self {
         Self {
             hovering_gizmo: false,
             active_gizmo: None,
+            snap: GizmoSnap::new(),
+            translation_orientation: GizmoOrientation::Global,
+            rotation_orientation: GizmoOrientation::Local,
         }
     }
 
@@ -142,6 +258,21 @@ impl EditorGizmo {
         self.hovering_gizmo
     }
 
+    /// The snap step of the gizmo being dragged, shown next to the cursor while snapping.
+    pub fn active_snap_label(&self) -> Option<String> {
+        let active_gizmo = self.active_gizmo.as_ref()?;
+        if !self.snap.is_active() {
+            return None;
+        }
+        Some(match active_gizmo.gizmo_type {
+            GizmoType::Translation { .. } => format!("Snap {}m", self.snap.translation_step),
+            GizmoType::Rotation { .. } => {
+                format!("Snap {}°", self.snap.rotation_step.to_degrees().round())
+            }
+            GizmoType::Trackball { .. } => return None,
+        })
+    }
+
     pub fn update(
         mut gizmo: ResMut<EditorGizmo>,
         mut editor_session: ResMut<EditorSession>,
@@ -152,9 +283,18 @@ impl EditorGizmo {
         input: Res<Input>,
         editor_ui: Res<EditorUI>,
         window: Res<Window>,
+        measure_tool: Res<EditorMeasureTool>,
     ) {
         gizmo.hovering_gizmo = false;
-        if voxel_editing.is_enabled() || !editor_session.is_editor_camera_focused() {
+        gizmo.snap.is_active = gizmo.snap.enabled
+            || input
+                .keyboard()
+                .is_modifiers_down(&[keyboard::Modifier::Control]);
+        if voxel_editing.is_enabled()
+            || measure_tool.enabled
+            || editor_session.picking_camera_focus
+            || !editor_session.is_editor_camera_focused()
+        {
             return;
         }
         let Some(selected_entity) = editor_session.selected_entity else {
@@ -165,15 +305,38 @@ impl EditorGizmo {
             .expect("Editor camera should have a transform");
         let camera_rot = editor_session.editor_camera_controller().euler;
 
+        // The gizmo sits on the selection's shared pivot, oriented with the primary selection.
+        let moved_entities = Self::moved_entities(&ecs_world, editor_session.selected_entities());
         let world_transform = {
             let local_transform = ecs_world
                 .get::<&Transform>(selected_entity)
                 .expect("Should have a transform");
-            ecs_world.get_world_transform(selected_entity, &local_transform)
+            let mut world_transform =
+                ecs_world.get_world_transform(selected_entity, &local_transform);
+            if moved_entities.len() > 1 {
+                world_transform.position = moved_entities
+                    .iter()
+                    .map(|(_, entity_world_transform)| entity_world_transform.position)
+                    .sum::<Vector3<f32>>()
+                    / moved_entities.len() as f32;
+            }
+            world_transform
         };
         let mut editor_transform_euler = ecs_world
             .get::<&mut EditorTransformEuler>(selected_entity)
             .unwrap();
+        let view_dir = camera_transform.forward();
+        let translation_orientation = gizmo.translation_orientation;
+        let rotation_orientation = gizmo.rotation_orientation;
+        let orient = |orientation: GizmoOrientation, axis: Vector3<f32>| match orientation {
+            GizmoOrientation::Local => world_transform.rotation.transform_vector(&axis),
+            GizmoOrientation::Global => axis,
+        };
+        // `rotation_between` has no answer for opposite vectors.
+        let ring_rotation = |axis: &Vector3<f32>| {
+            UnitQuaternion::rotation_between(&Vector3::y(), axis)
+                .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&Vector3::x_axis(), PI))
+        };
 
         struct AxisInfo {
             hover_t: Option<f32>,
@@ -186,6 +349,7 @@ impl EditorGizmo {
                                        axis: Vector3<f32>,
                                        color: ColorSrgba|
          -> AxisInfo {
+            let axis = orient(translation_orientation, axis);
             let hover_t = debug_renderer.raycast_arrow(
                 &editor_session.editor_camera_ray,
                 world_transform.position,
@@ -199,37 +363,55 @@ impl EditorGizmo {
                 color,
             }
         };
-        const RADIUS: f32 = 0.6;
         const THICKNESS: f32 = 0.02;
-        let mut create_rotation_axis = |debug_renderer: &mut DebugRenderer,
-                                        axis: Vector3<f32>,
-                                        color: ColorSrgba|
+        let ring_radius = |gizmo_type: GizmoTypeDiscriminants| match gizmo_type {
+            GizmoTypeDiscriminants::Trackball => Self::TRACKBALL_RADIUS,
+            _ => Self::RADIUS,
+        };
+        let create_ring_axis = |debug_renderer: &mut DebugRenderer,
+                                axis: Vector3<f32>,
+                                gizmo_type: GizmoTypeDiscriminants,
+                                color: ColorSrgba|
          -> AxisInfo {
-            let rot = world_transform.rotation
-                * UnitQuaternion::rotation_between(&Vector3::y(), &axis).unwrap();
             let hover_t = debug_renderer.raycast_ring(
                 &editor_session.editor_camera_ray,
                 world_transform.position,
-                rot,
-                RADIUS,
+                ring_rotation(&axis),
+                ring_radius(gizmo_type),
                 THICKNESS,
             );
             AxisInfo {
                 hover_t,
                 axis,
-                gizmo_type: GizmoTypeDiscriminants::Rotation,
+                gizmo_type,
                 color,
             }
         };
+        let create_rotation_axis =
+            |debug_renderer: &mut DebugRenderer, axis: Vector3<f32>, color: ColorSrgba| {
+                let axis = orient(rotation_orientation, axis);
+                create_ring_axis(
+                    debug_renderer,
+                    axis,
+                    GizmoTypeDiscriminants::Rotation,
+                    color,
+                )
+            };
         const ALPHA: f32 = 0.6;
         let dr = &mut *debug_renderer;
         let mut axes = [
-            create_translation_axis(dr, Vector3::x(), Color::new_srgba(1.0, 0.0, 0.0, ALPHA)),
-            create_translation_axis(dr, Vector3::y(), Color::new_srgba(0.0, 1.0, 0.0, ALPHA)),
-            create_translation_axis(dr, Vector3::z(), Color::new_srgba(0.0, 0.0, 1.0, ALPHA)),
-            create_rotation_axis(dr, Vector3::x(), Color::new_srgba(1.0, 0.0, 0.0, ALPHA)),
-            create_rotation_axis(dr, Vector3::y(), Color::new_srgba(0.0, 1.0, 0.0, ALPHA)),
-            create_rotation_axis(dr, Vector3::z(), Color::new_srgba(0.0, 0.0, 1.0, ALPHA)),
+            create_translation_axis(dr, Vector3::x(), Color::RED.to_srgba(ALPHA)),
+            create_translation_axis(dr, Vector3::y(), Color::GREEN.to_srgba(ALPHA)),
+            create_translation_axis(dr, Vector3::z(), Color::BLUE.to_srgba(ALPHA)),
+            create_rotation_axis(dr, Vector3::x(), Color::RED.to_srgba(ALPHA)),
+            create_rotation_axis(dr, Vector3::y(), Color::GREEN.to_srgba(ALPHA)),
+            create_rotation_axis(dr, Vector3::z(), Color::BLUE.to_srgba(ALPHA)),
+            create_ring_axis(
+                dr,
+                view_dir,
+                GizmoTypeDiscriminants::Trackball,
+                Color::WHITE.to_srgba(ALPHA),
+            ),
         ];
         let mut closest_axis = None;
         let mut closest_t = None;
@@ -260,16 +442,25 @@ impl EditorGizmo {
                     GizmoTypeDiscriminants::Rotation => GizmoType::Rotation {
                         last_rot: ActiveGizmo::plane_proj_rotation(
                             &world_transform.position,
-                            &world_transform.rotation.transform_vector(&axis.axis),
+                            &axis.axis,
                             &editor_session.editor_camera_ray,
                         )
                         .unwrap_or(0.0),
+                        dragged_rot: 0.0,
+                        applied_rot: 0.0,
+                    },
+                    GizmoTypeDiscriminants::Trackball => GizmoType::Trackball {
+                        last_offset: ActiveGizmo::view_plane_offset(
+                            &world_transform.position,
+                            &axis.axis,
+                            &editor_session.editor_camera_ray,
+                        )
+                        .unwrap_or_else(Vector3::zeros),
                     },
                 };
                 gizmo.active_gizmo = Some(ActiveGizmo {
                     gizmo_type,
-                    axis: axis.axis,
-                    world_axis: world_transform.rotation.transform_vector(&axis.axis),
+                    world_axis: axis.axis,
                     initial_entity_pos: world_transform.position,
                 });
             }
@@ -286,13 +477,11 @@ impl EditorGizmo {
                         DebugShapeFlags::NONE,
                     );
                 }
-                GizmoTypeDiscriminants::Rotation => {
-                    let rot = world_transform.rotation
-                        * UnitQuaternion::rotation_between(&Vector3::y(), &axis.axis).unwrap();
+                GizmoTypeDiscriminants::Rotation | GizmoTypeDiscriminants::Trackball => {
                     debug_renderer.draw_ring(
                         world_transform.position,
-                        rot,
-                        RADIUS,
+                        ring_rotation(&axis.axis),
+                        ring_radius(axis.gizmo_type),
                         THICKNESS,
                         axis.color,
                         DebugShapeFlags::NONE,
@@ -301,27 +490,53 @@ impl EditorGizmo {
             }
         }
 
+        let gizmo = &mut *gizmo;
         if let Some(active_gizmo) = &mut gizmo.active_gizmo {
-            let backbuffer_size = editor_ui.backbuffer_size(&window).cast::<f32>();
-            let mut world_transform = {
-                let mut local_transform = ecs_world
-                    .get::<&Transform>(selected_entity)
-                    .expect("Should have a transform");
-                let mut world_transform =
-                    ecs_world.get_world_transform(selected_entity, &local_transform);
-                // Apply translation/rotation/scale.
-                active_gizmo.apply_update(&editor_session.editor_camera_ray, &mut world_transform);
-                world_transform
-            };
-            let mut new_local_transform =
-                ecs_world.get_world_to_local_transform(selected_entity, &world_transform);
-            *ecs_world
-                .get::<&mut Transform>(selected_entity)
-                .expect("Should have a transform") = new_local_transform;
+            // Apply translation/rotation/scale to the pivot then move every entity with it so
+            // their offsets from each other are kept.
+            let mut pivot_transform = world_transform.clone();
+            active_gizmo.apply_update(
+                &editor_session.editor_camera_ray,
+                &mut pivot_transform,
+                &gizmo.snap,
+            );
+            let delta_rotation = pivot_transform.rotation * world_transform.rotation.inverse();
+            for (entity, mut entity_world_transform) in moved_entities {
+                entity_world_transform.position = pivot_transform.position
+                    + delta_rotation.transform_vector(
+                        &(entity_world_transform.position - world_transform.position),
+                    );
+                entity_world_transform.rotation = delta_rotation * entity_world_transform.rotation;
+                let new_local_transform =
+                    ecs_world.get_world_to_local_transform(entity, &entity_world_transform);
+                *ecs_world
+                    .get::<&mut Transform>(entity)
+                    .expect("Should have a transform") = new_local_transform;
+            }
         }
 
         if input.is_mouse_button_released(mouse::Button::Left) {
             gizmo.active_gizmo = None;
         }
     }
+
+    /// The selected entities along with their world transforms, skipping any whose ancestor is
+    /// also selected since they already move with it.
+    fn moved_entities(ecs_world: &ECSWorld, selected: &[Entity]) -> Vec<(Entity, Transform)> {
+        selected
+            .iter()
+            .filter(|entity| {
+                !selected
+                    .iter()
+                    .any(|ancestor| ecs_world.has_parent(**entity, *ancestor))
+            })
+            .filter_map(|entity| {
+                let local_transform = ecs_world.get::<&Transform>(*entity).ok()?;
+                Some((
+                    *entity,
+                    ecs_world.get_world_transform(*entity, &local_transform),
+                ))
+            })
+            .collect()
+    }
 }
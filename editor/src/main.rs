@@ -7,24 +7,44 @@ use rogue_engine::{
     app::{App, AppCreateInfo, AppStage},
     asset::{
         asset::{AssetPath, Assets},
-        repr::project::ProjectAsset,
+        repr::{
+            project::{ProjectAsset, ProjectSerializeContext},
+            voxel::material_palette::VoxelMaterialPaletteAsset,
+        },
     },
+    common::rng::Rng,
     consts::{self, editor},
     debug::debug_renderer::DebugRenderer,
     egui::{Egui, egui_gpu::EguiGpu},
     entity::ecs_world::ECSWorld,
-    graphics::camera::MainCamera,
+    graphics::{
+        camera::MainCamera,
+        passes::{
+            bloom_pass::BloomPass, depth_of_field_pass::DepthOfFieldPass, fxaa_pass::FxaaPass,
+            taa_pass::TaaPass,
+        },
+        screenshot::Screenshots,
+    },
     impl_asset_load_save_serde,
     input::Input,
+    material::material_bank::MaterialBank,
+    physics::physics_world::PhysicsWorld,
     resource::ResourceBank,
     task::tasks::Tasks,
+    voxel::voxel_registry::VoxelModelRegistry,
     window::window::Window,
-    world::renderable::rt_pass::WorldRTPass,
+    world::{
+        renderable::rt_pass::WorldRTPass,
+        sky::SkyLight,
+        terrain::{region_map::RegionMap, terrain_generator::TerrainGeneratorSettings},
+        world_origin::WorldOrigin,
+    },
 };
 use winit::event::{DeviceEvent, ElementState};
 
 use crate::{
     animation_preview::EditorAnimationPreviewer,
+    copy_buffer::EditorCopyBuffer,
     editing::{
         voxel_editing::EditorVoxelEditing, voxel_editing_edit_tools::EditorVoxelEditingEditTools,
         voxel_editing_preview::EditorVoxelEditingPreview,
@@ -38,10 +58,13 @@ use crate::{
     editor_transform_euler::EditorTransformEuler,
     game_session::EditorGameSession,
     gizmo::EditorGizmo,
+    measure_tool::EditorMeasureTool,
     render_graph::EditorRenderGraph,
     selected_entity_visualizer::SelectedEntityVisualizer,
     session::EditorSession,
+    turntable::EditorTurntable,
     ui::EditorUI,
+    wireframe_visualizer::WireframeVisualizer,
     world::generator::WorldGenerator,
 };
 
@@ -56,10 +79,13 @@ pub mod editor_transform_euler;
 pub mod game_session;
 pub mod gizmo;
 pub mod history_buffer;
+pub mod measure_tool;
 mod render_graph;
 pub mod selected_entity_visualizer;
 pub mod session;
+pub mod turntable;
 pub mod ui;
+pub mod wireframe_visualizer;
 pub mod world;
 
 fn main() {
@@ -91,6 +117,7 @@ fn main() {
         on_post_graphics_init_fn: Some(Box::new(on_post_graphics_init)),
         on_window_event_fn: Some(Box::new(on_window_event)),
         on_device_event_fn: Some(Box::new(on_device_event)),
+        on_fatal_error_fn: Some(Box::new(on_fatal_error)),
     });
     app.insert_resource(editor_settings.user_project_settings);
     app.insert_resource(game_session);
@@ -118,6 +145,10 @@ fn on_post_graphics_init(rb: &mut ResourceBank) {
 
     rb.insert(DebugRenderer::new());
 
+    // Editing tools work with absolute voxel positions so keep the world origin fixed.
+    rb.get_resource_mut::<WorldOrigin>()
+        .set_shift_distance(None);
+
     let egui = Egui::new(&rb.get_resource::<Window>());
     rb.insert(egui);
     rb.insert(EguiGpu::new());
@@ -125,7 +156,11 @@ fn on_post_graphics_init(rb: &mut ResourceBank) {
     let world_generator = WorldGenerator::new(&rb.get_resource::<Tasks>());
     rb.insert(world_generator);
 
-    rb.insert(EditorVoxelEditing::new());
+    let mut voxel_editing = EditorVoxelEditing::new();
+    if let Some(project_dir) = rb.get_resource::<Assets>().project_dir() {
+        voxel_editing.palette = VoxelMaterialPaletteAsset::load_project_palette(project_dir);
+    }
+    rb.insert(voxel_editing);
     rb.insert(EditorVoxelEditingSelections::new());
     rb.insert(EditorVoxelEditingEditTools::new());
     rb.insert(EditorVoxelEditingPreview::new());
@@ -133,13 +168,37 @@ fn on_post_graphics_init(rb: &mut ResourceBank) {
 
     rb.insert(EditorAnimationPreviewer::new());
 
+    rb.insert(EditorTurntable::new());
+
     rb.insert(EditorGizmo::new());
+    rb.insert(EditorMeasureTool::new());
 
     rb.insert(EditorInput::new());
 
+    rb.insert(EditorCopyBuffer::new());
+
     rb.run_system(EditorRenderGraph::init_render_graph);
 }
 
+/// Called when shutting down from an unrecoverable error, autosaves the project so unsaved
+/// work isn't lost.
+fn on_fatal_error(rb: &mut ResourceBank) {
+    let assets = rb.get_resource::<Assets>();
+    let game_session = rb.get_resource::<EditorGameSession>();
+    assets.save_project_autosave(ProjectSerializeContext {
+        ecs_world: &rb.get_resource::<ECSWorld>(),
+        physics_world: &rb.get_resource::<PhysicsWorld>(),
+        voxel_registry: &rb.get_resource::<VoxelModelRegistry>(),
+        material_bank: &rb.get_resource::<MaterialBank>(),
+        main_camera: &rb.get_resource::<MainCamera>(),
+        region_map: &rb.get_resource::<RegionMap>(),
+        rng: &rb.get_resource::<Rng>(),
+        terrain_generator: &rb.get_resource::<TerrainGeneratorSettings>(),
+        sky_light: &rb.get_resource::<SkyLight>(),
+        game_camera: game_session.game_camera.clone(),
+    });
+}
+
 fn on_window_event(rb: &mut ResourceBank, event: &mut winit::event::WindowEvent) -> bool {
     if rb.has_resource::<Egui>() {
         let window = rb.get_resource::<Window>();
@@ -218,6 +277,10 @@ fn on_device_event(rb: &mut ResourceBank, event: &mut winit::event::DeviceEvent)
 }
 
 fn setup_systems(app: &mut App) {
+    // ======= EDITOR INPUT =====
+    // Block actions while a text field has focus, before any system checks them.
+    app.insert_system(AppStage::Update, EditorInput::update_text_input_focus);
+
     // ======= EDITOR EULER ANGLES =====
     // We want to work with euler rotations in the editor so we have a separate representation for
     // them.
@@ -236,6 +299,10 @@ fn setup_systems(app: &mut App) {
     );
     // Handles events such as project/settings saving and loading.
     app.insert_system(AppStage::Update, EditorSession::update_editor_events);
+    // Requests a full window screenshot on the screenshot action.
+    app.insert_system(AppStage::Update, EditorSession::update_screenshot_keybind);
+    // Saves the project on the save action, ctrl+s by default.
+    app.insert_system(AppStage::Update, EditorSession::update_save_keybind);
 
     // ======= EDITOR GIZMO =======
     // Update editor gizmo actions and rendering.
@@ -245,10 +312,22 @@ fn setup_systems(app: &mut App) {
         AppStage::Update,
         SelectedEntityVisualizer::visualize_selected_entity,
     );
+    // Draws the chunk and voxel wireframe overlays toggled in the top bar.
+    app.insert_system(AppStage::Update, WireframeVisualizer::visualize_wireframes);
+
+    // ======= MEASURE TOOL =======
+    // Pick and draw measurements, this takes over clicks from the gizmo and selection.
+    app.insert_system(AppStage::Update, EditorMeasureTool::update);
 
     // ======== EDITOR SESSION - SELECTED ENTITY =======
     // Update editor session selected entity based on the raycast.
     app.insert_system(AppStage::Update, EditorSession::update_selected_entity);
+    // Set the editor camera's focus distance on click after picking focus in the top bar.
+    app.insert_system(AppStage::Update, EditorSession::update_camera_focus_picking);
+
+    // ======== COPY BUFFER =======
+    // Copy and paste the selected entities with ctrl+c and ctrl+v.
+    app.insert_system(AppStage::Update, EditorCopyBuffer::update_copy_paste);
 
     // ======== VOXEL_EDITING =======
     // Update editor voxel editing systems for entities and terrain.
@@ -285,12 +364,41 @@ fn setup_systems(app: &mut App) {
     app.insert_system(AppStage::RenderWrite, EditorUI::resolve_egui_ui);
 
     // ======== RENDER GRAPH =========
+    // Pick the world raytrace pass workgroup size for this device.
+    app.insert_system(AppStage::Update, WorldRTPass::update_workgroup_size);
+    // Match the voxel edit preview's workgroup size to the world raytrace pass.
+    app.insert_system(
+        AppStage::Update,
+        EditorVoxelEditingPreviewGpu::update_workgroup_size,
+    );
     app.insert_system(
         AppStage::PreUniformsRenderWrite,
         EditorRenderGraph::write_general_inputs,
     );
+    // Orbit the editor camera and queue the captures of a turntable export, this overrides the
+    // editor camera controller.
+    app.insert_system(AppStage::Update, EditorTurntable::update_turntable);
+    // Start any queued screenshot captures and save the ones read back from the gpu.
+    app.insert_system(AppStage::Update, Screenshots::update_captures);
+    app.insert_system(AppStage::Update, Screenshots::update_readbacks);
     // Write the world raytrace pass.
     app.insert_system(AppStage::RenderWrite, WorldRTPass::write_graph_rt_pass);
+    // Write the taa pass resolving the world render with the previous frames.
+    app.insert_system(AppStage::RenderWrite, TaaPass::write_graph_taa_pass);
+    // Write the depth of field pass blurring by the main camera's focus.
+    app.insert_system(
+        AppStage::RenderWrite,
+        DepthOfFieldPass::write_graph_depth_of_field_pass,
+    );
+    // Write the bloom pass blurring the bright parts of the resolved world render.
+    app.insert_system(AppStage::RenderWrite, BloomPass::write_graph_bloom_pass);
+    // Write the fxaa pass smoothing the edges of the display encoded image.
+    app.insert_system(AppStage::RenderWrite, FxaaPass::write_graph_fxaa_pass);
+    // Write the screenshot pass, copying out the viewport if a capture was requested.
+    app.insert_system(
+        AppStage::RenderWrite,
+        Screenshots::write_graph_screenshot_pass,
+    );
     // Write the images and vertex/index buffers to render the ui.
     app.insert_system(AppStage::RenderWrite, EguiGpu::write_render_data);
     // Write the render graph pass input for rasterizing the ui.
@@ -0,0 +1,181 @@
+use nalgebra::Vector3;
+use rogue_engine::{
+    common::{color::Color, geometry::ray::Ray},
+    consts,
+    debug::debug_renderer::{DebugRenderer, DebugShapeFlags},
+    entity::{RenderableVoxelEntity, ecs_world::ECSWorld},
+    graphics::camera::Camera,
+    input::{Input, mouse},
+    physics::transform::Transform,
+    resource::{Res, ResMut},
+    voxel::voxel_registry::VoxelModelRegistry,
+    world::{World, WorldTraceInfo, WorldVoxelHit, terrain::region_map::RegionMap},
+};
+use rogue_macros::Resource;
+
+use crate::{editing::voxel_editing::EditorVoxelEditing, session::EditorSession};
+
+/// Measures the distance between two points picked in the viewport. The first click places the
+/// start, the end follows the cursor until the second click places it.
+#[derive(Resource)]
+pub struct EditorMeasureTool {
+    pub enabled: bool,
+    /// Measure between the centers of the hit voxels instead of the surface points hit.
+    pub snap_to_voxel_centers: bool,
+    start: Option<Vector3<f32>>,
+    end: Option<Vector3<f32>>,
+    hovered_point: Option<Vector3<f32>>,
+}
+
+impl EditorMeasureTool {
+    const MAX_TRACE_DISTANCE: f32 = 500.0;
+    /// The height of a line of the label as a fraction of the view's height.
+    const LABEL_VIEW_HEIGHT_RATIO: f32 = 0.025;
+
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            snap_to_voxel_centers: false,
+            start: None,
+            end: None,
+            hovered_point: None,
+        }
+    }
+
+    pub fn has_measurement(&self) -> bool {
+        self.start.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.end = None;
+    }
+
+    /// The start and end of the current measurement, the end is the hovered point until placed.
+    pub fn measurement(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        Some((self.start?, self.end.or(self.hovered_point)?))
+    }
+
+    /// The midpoint of the measurement and the text describing it.
+    pub fn label(&self) -> Option<(Vector3<f32>, String)> {
+        let (start, end) = self.measurement()?;
+        let diff = end - start;
+        let voxel_diff = diff.abs() / consts::voxel::VOXEL_METER_LENGTH;
+        let text = format!(
+            "{:.3}m, {:.1} voxels\n{:.1} x {:.1} x {:.1} voxels",
+            diff.norm(),
+            diff.norm() / consts::voxel::VOXEL_METER_LENGTH,
+            voxel_diff.x,
+            voxel_diff.y,
+            voxel_diff.z,
+        );
+        Some(((start + end) * 0.5, text))
+    }
+
+    pub fn update(
+        mut measure_tool: ResMut<EditorMeasureTool>,
+        mut debug_renderer: ResMut<DebugRenderer>,
+        session: Res<EditorSession>,
+        ecs_world: Res<ECSWorld>,
+        voxel_registry: Res<VoxelModelRegistry>,
+        region_map: Res<RegionMap>,
+        voxel_editing: Res<EditorVoxelEditing>,
+        input: Res<Input>,
+    ) {
+        if !measure_tool.enabled {
+            measure_tool.clear();
+            return;
+        }
+        if voxel_editing.is_enabled() || !session.is_editor_camera_focused() {
+            return;
+        }
+        if input.is_action_pressed(consts::actions::EDITOR_CLEAR_MEASUREMENT) {
+            measure_tool.clear();
+        }
+
+        let ray = &session.editor_camera_ray;
+        measure_tool.hovered_point = World::trace_detailed(
+            ray,
+            Self::MAX_TRACE_DISTANCE,
+            &ecs_world,
+            &voxel_registry,
+            &region_map,
+        )
+        .map(|hit| measure_tool.hit_point(ray, &hit, &ecs_world, &voxel_registry));
+        if input.is_mouse_button_pressed(mouse::Button::Left)
+            && let Some(hovered_point) = measure_tool.hovered_point
+        {
+            if measure_tool.start.is_some() && measure_tool.end.is_none() {
+                measure_tool.end = Some(hovered_point);
+            } else {
+                measure_tool.start = Some(hovered_point);
+                measure_tool.end = None;
+            }
+        }
+
+        let color = Color::new_srgba_hex("#F5C542", 1.0);
+        if let Some(hovered_point) = measure_tool.hovered_point {
+            debug_renderer.draw_sphere(hovered_point, 0.03, color, DebugShapeFlags::NONE);
+        }
+        if let Some((start, end)) = measure_tool.measurement() {
+            debug_renderer.draw_sphere(start, 0.03, color, DebugShapeFlags::NONE);
+            debug_renderer.draw_sphere(end, 0.03, color, DebugShapeFlags::NONE);
+            debug_renderer.draw_line_3d(start, end, 0.01, color, DebugShapeFlags::NONE);
+        }
+        if let Some((midpoint, text)) = measure_tool.label() {
+            let editor_camera = session.editor_camera();
+            let camera_transform = ecs_world
+                .get::<&Transform>(editor_camera)
+                .expect("Editor camera should have a transform");
+            let camera = ecs_world
+                .get::<&Camera>(editor_camera)
+                .expect("Editor camera should have a camera");
+            let size =
+                camera.view_height_at(&camera_transform, midpoint) * Self::LABEL_VIEW_HEIGHT_RATIO;
+            // Float the label above the line so it stays readable.
+            let position = midpoint + camera_transform.up() * size * 1.5;
+            debug_renderer.draw_text_3d(position, &text, size, color, DebugShapeFlags::NONE);
+        }
+    }
+
+    /// Where `hit` lands, the center of the voxel hit when snapping to voxel centers.
+    fn hit_point(
+        &self,
+        ray: &Ray,
+        hit: &WorldVoxelHit,
+        ecs_world: &ECSWorld,
+        voxel_registry: &VoxelModelRegistry,
+    ) -> Vector3<f32> {
+        let surface_point = ray.origin + ray.dir * hit.depth_t;
+        if !self.snap_to_voxel_centers {
+            return surface_point;
+        }
+
+        match &hit.info {
+            WorldTraceInfo::Terrain { global_voxel_pos } => {
+                (global_voxel_pos.cast::<f32>() + Vector3::repeat(0.5))
+                    * consts::voxel::VOXEL_METER_LENGTH
+            }
+            WorldTraceInfo::Entity { entity_id } => {
+                let Ok(local_transform) = ecs_world.get::<&Transform>(*entity_id) else {
+                    return surface_point;
+                };
+                let Some(model_id) = ecs_world
+                    .get::<&RenderableVoxelEntity>(*entity_id)
+                    .ok()
+                    .and_then(|renderable| renderable.voxel_model_id())
+                else {
+                    return surface_point;
+                };
+                // Models are centered on their entity's position.
+                let world_transform = ecs_world.get_world_transform(*entity_id, &local_transform);
+                let side_length = voxel_registry.get_dyn_model(model_id).length();
+                let voxel_offset = (hit.local_voxel_pos.cast::<f32>() + Vector3::repeat(0.5)
+                    - side_length.cast::<f32>() * 0.5)
+                    .component_mul(&world_transform.scale)
+                    * consts::voxel::VOXEL_METER_LENGTH;
+                world_transform.position + world_transform.rotation.transform_vector(&voxel_offset)
+            }
+        }
+    }
+}
@@ -5,9 +5,15 @@ use rogue_engine::egui::egui_gpu::EguiGpu;
 use rogue_engine::graphics::backend::{GfxBlitInfo, GfxFilterMode};
 use rogue_engine::graphics::device::DeviceResource;
 use rogue_engine::graphics::frame_graph::FrameGraphImageInfo;
+use rogue_engine::graphics::passes::bloom_pass::BloomPass;
+use rogue_engine::graphics::passes::depth_of_field_pass::DepthOfFieldPass;
+use rogue_engine::graphics::passes::fxaa_pass::FxaaPass;
 use rogue_engine::graphics::passes::post_process_pass::PostProcessPass;
+use rogue_engine::graphics::passes::taa_pass::TaaPass;
+use rogue_engine::graphics::screenshot::Screenshots;
 use rogue_engine::graphics::{frame_graph::FrameGraphBuilder, renderer::Renderer};
 use rogue_engine::resource::{Res, ResMut};
+use rogue_engine::settings::Settings;
 use rogue_engine::voxel::baker_gpu::VoxelBakerGpu;
 use rogue_engine::world::renderable::rt_pass::WorldRTPass;
 
@@ -20,8 +26,10 @@ pub struct EditorRenderGraphConstants {
     pub backbuffer_depth_r16_name: &'static str,
     pub backbuffer_depth_name: &'static str,
     pub backbuffer_blit_offset_input: &'static str,
+    pub backbuffer_blit_size_input: &'static str,
 
     pub intermediate_image_name: &'static str,
+    pub swapchain_blit_pass_name: &'static str,
 }
 
 pub struct EditorRenderGraph {}
@@ -33,18 +41,40 @@ impl EditorRenderGraph {
         backbuffer_depth_r16_name: "editor_backbuffer_r16_depth",
         backbuffer_depth_name: "editor_backbuffer_depth",
         backbuffer_blit_offset_input: "editor_backbuffer_blit_offset",
+        backbuffer_blit_size_input: "editor_backbuffer_blit_size",
         intermediate_image_name: "editor_intermediate_image",
+        swapchain_blit_pass_name: "blit_intermediate_to_swapchain_pass",
     };
 
+    /// The passes drawing the editor ui to the screen, disabling these would hide the ui needed
+    /// to enable them again.
+    pub fn ui_pass_names() -> [&'static str; 2] {
+        [
+            EguiGpu::GRAPH.pass_name,
+            Self::GRAPH.swapchain_blit_pass_name,
+        ]
+    }
+
     /// Supplies inputs such as backbuffer size or backbuffer blit offset, etc. to the
     /// render graph executor.
-    pub fn write_general_inputs(mut renderer: ResMut<Renderer>, mut editor_ui: Res<EditorUI>) {
+    pub fn write_general_inputs(
+        mut renderer: ResMut<Renderer>,
+        mut editor_ui: Res<EditorUI>,
+        screenshots: Res<Screenshots>,
+        settings: Res<Settings>,
+        device: Res<DeviceResource>,
+    ) {
         let swapchain_size = renderer.swapchain_size();
         let pad = editor_ui.content_padding();
-        let backbuffer_size = Vector2::new(
+        // The ui lays out the viewport in window pixels, only the scene is rendered scaled.
+        let viewport_size = Vector2::new(
             swapchain_size.x.saturating_sub(pad.z + pad.w),
             swapchain_size.y.saturating_sub(pad.x + pad.y),
         );
+        let render_size = settings
+            .graphics
+            .render_size(viewport_size, device.device_info().max_image_dimension_2d);
+        let backbuffer_size = screenshots.capture_scene_size(viewport_size, render_size);
         renderer
             .executor()
             .supply_input(Self::GRAPH.backbuffer_size_input, Box::new(backbuffer_size));
@@ -52,6 +82,10 @@ impl EditorRenderGraph {
         renderer
             .executor()
             .supply_input(Self::GRAPH.backbuffer_blit_offset_input, Box::new(pad.zx()));
+        renderer.executor().supply_input(
+            Self::GRAPH.backbuffer_blit_size_input,
+            Box::new(viewport_size),
+        );
     }
 
     pub fn init_render_graph(
@@ -61,6 +95,11 @@ impl EditorRenderGraph {
         mut voxel_baker_gpu: ResMut<VoxelBakerGpu>,
         mut debug_renderer: ResMut<DebugRenderer>,
         mut voxel_editing_preview: ResMut<EditorVoxelEditingPreviewGpu>,
+        mut screenshots: ResMut<Screenshots>,
+        mut taa_pass: ResMut<TaaPass>,
+        mut dof_pass: ResMut<DepthOfFieldPass>,
+        mut bloom_pass: ResMut<BloomPass>,
+        mut fxaa_pass: ResMut<FxaaPass>,
     ) {
         let mut fg = FrameGraphBuilder::new();
 
@@ -115,8 +154,22 @@ impl EditorRenderGraph {
             },
         );
 
-        // Debug render pass for shapes.
-        debug_renderer.set_graph_debug_pass(&mut fg, backbuffer, backbuffer_depth, &[]);
+        // Resolve the jittered backbuffer with the previous frames.
+        let resolved_backbuffer =
+            taa_pass.set_graph_taa_pass(&mut fg, backbuffer, backbuffer_depth_r16);
+        // Blur the parts of the image out of the camera's focus.
+        let resolved_backbuffer = dof_pass.set_graph_depth_of_field_pass(
+            &mut fg,
+            resolved_backbuffer,
+            backbuffer_depth_r16,
+            backbuffer_size_input,
+        );
+        // Bloom the bright parts of the hdr image before it's converted for display.
+        let resolved_backbuffer =
+            bloom_pass.set_graph_bloom_pass(&mut fg, resolved_backbuffer, backbuffer_size_input);
+        // Debug render pass for shapes and gizmos, drawn over the resolved image so they aren't
+        // jittered or accumulated into the taa history.
+        debug_renderer.set_graph_debug_pass(&mut fg, resolved_backbuffer, backbuffer_depth, &[]);
 
         let swapchain_image = fg.create_input_image(Renderer::GRAPH.image_swapchain);
         let swapchain_image_size =
@@ -130,19 +183,32 @@ impl EditorRenderGraph {
         // post processing effects.
         let blit_offset_input =
             fg.create_input::<Vector2<u32>>(Self::GRAPH.backbuffer_blit_offset_input);
+        let blit_size_input =
+            fg.create_input::<Vector2<u32>>(Self::GRAPH.backbuffer_blit_size_input);
         PostProcessPass::set_graph_post_process_blit_pass(
             &mut fg,
             blit_offset_input,
-            backbuffer,
+            blit_size_input,
+            resolved_backbuffer,
+            intermediate_image,
+        );
+        // Smooth the edges of the display encoded scene when fxaa is the anti-aliasing.
+        let intermediate_image = fxaa_pass.set_graph_fxaa_pass(
+            &mut fg,
             intermediate_image,
+            swapchain_image_size,
+            blit_offset_input,
+            blit_size_input,
         );
+        // Captures screenshots of the viewport before the editor UI is drawn over it.
+        screenshots.set_graph_screenshot_pass(&mut fg, intermediate_image, resolved_backbuffer);
 
         // Egui pass, draws the editor UI.
         // TODO: Pass dependencies so its not just linear.
         egui_gpu.set_graph_egui_pass(&mut fg, intermediate_image, &[]);
 
         fg.create_pass(
-            "blit_intermediate_to_swapchain_pass",
+            Self::GRAPH.swapchain_blit_pass_name,
             &[&intermediate_image, &swapchain_image],
             &[&swapchain_image],
             move |recorder, ctx| {
@@ -29,40 +29,38 @@ impl SelectedEntityVisualizer {
             return;
         }
         const SELECTION_COLOR: &'static str = "#ffffff";
-        let Some(selected_entity) = editor_session.selected_entity else {
-            return;
-        };
-
-        let local_transform = ecs_world
-            .get::<&Transform>(selected_entity)
-            .expect("Should have a transform");
-        let world_transform = ecs_world.get_world_transform(selected_entity, &local_transform);
-
         let color = Color::new_srgba_hex(SELECTION_COLOR, 1.0);
-        if let Ok(renderable) = ecs_world.get::<&RenderableVoxelEntity>(selected_entity)
-            && let Some(model_id) = renderable.voxel_model_id()
-        {
-            let side_length = voxel_registry.get_dyn_model(model_id).length();
-            let obb = world_transform.as_voxel_model_obb(side_length);
-            debug_renderer.draw_obb_outline(
-                &obb,
-                0.025 * world_transform.scale.min(),
-                color,
-                DebugShapeFlags::NONE,
-            );
-        }
+        for &selected_entity in editor_session.selected_entities() {
+            let local_transform = ecs_world
+                .get::<&Transform>(selected_entity)
+                .expect("Should have a transform");
+            let world_transform = ecs_world.get_world_transform(selected_entity, &local_transform);
+
+            if let Ok(renderable) = ecs_world.get::<&RenderableVoxelEntity>(selected_entity)
+                && let Some(model_id) = renderable.voxel_model_id()
+            {
+                let side_length = voxel_registry.get_dyn_model(model_id).length();
+                let obb = world_transform.as_voxel_model_obb(side_length);
+                debug_renderer.draw_obb_outline(
+                    &obb,
+                    0.025 * world_transform.scale.min(),
+                    color,
+                    DebugShapeFlags::NONE,
+                );
+            }
 
-        if editor_session.render_colliders {
-            if let Ok(colliders) = ecs_world.get::<&EntityColliders>(selected_entity) {
-                for collider_id in &colliders.colliders {
-                    physics_world
-                        .colliders
-                        .get_collider_dyn(collider_id)
-                        .render_debug(
-                            &world_transform,
-                            &mut debug_renderer,
-                            ColorSrgba::new_srgb_hex("#22FF22", 0.1),
-                        );
+            if editor_session.render_colliders {
+                if let Ok(colliders) = ecs_world.get::<&EntityColliders>(selected_entity) {
+                    for collider_id in &colliders.colliders {
+                        physics_world
+                            .colliders
+                            .get_collider_dyn(collider_id)
+                            .render_debug(
+                                &world_transform,
+                                &mut debug_renderer,
+                                ColorSrgba::new_srgb_hex("#22FF22", 0.1),
+                            );
+                    }
                 }
             }
         }
@@ -5,31 +5,51 @@ use std::{
 };
 
 use crate::{
-    camera_controller::{EditorCameraController, EditorCameraControllerType},
+    camera_controller::{EditorAxisView, EditorCameraController, EditorCameraControllerType},
+    editing::voxel_editing::EditorVoxelEditing,
     editor_project_settings::{EditorProjectSettings, EditorProjectSettingsData},
     editor_settings::{UserEditorSettingsAsset, UserEditorSettingsAssetProxy},
     game_session::EditorGameSession,
     gizmo::EditorGizmo,
+    measure_tool::EditorMeasureTool,
     ui::EditorUI,
 };
 use nalgebra::Vector3;
 use rogue_engine::world::terrain::region_map::{RegionMap, TerrainRaycastHit};
 use rogue_engine::{
     animation::{animation::Animation, animation_bank::AnimationBank},
-    asset::asset::{Assets, GameAssetPath},
-    common::geometry::ray::Ray,
-    entity::ecs_world::{ECSWorld, Entity},
+    asset::{
+        asset::{Assets, GameAssetPath},
+        repr::settings::UserSettingsAsset,
+    },
+    common::{geometry::ray::Ray, rng::Rng},
+    consts,
+    egui::Egui,
+    entity::{
+        RenderableVoxelEntity,
+        ecs_world::{ECSWorld, Entity},
+    },
     event::{EventReader, Events},
-    graphics::camera::{Camera, MainCamera},
-    input::{input_buffer::InputBuffer, mouse, Input},
+    graphics::{
+        camera::{Camera, MainCamera},
+        renderer::Renderer,
+        screenshot::{ScreenshotRegion, ScreenshotRequest, Screenshots},
+    },
+    input::{Input, input_buffer::InputBuffer, keyboard, mouse},
     physics::{physics_world::PhysicsWorld, transform::Transform},
     resource::{Res, ResMut},
+    settings::Settings,
     voxel::{
         rvox_asset::RVOXAsset,
         voxel_registry::{self, VoxelModelId, VoxelModelRegistry},
     },
     window::{time::Time, window::Window},
-    world::world_entities::{WorldEntities, WorldEntityRaycastHit},
+    world::{
+        World,
+        sky::SkyLight,
+        terrain::terrain_generator::TerrainGeneratorSettings,
+        world_entities::{WorldEntities, WorldEntityRaycastHit},
+    },
 };
 use rogue_macros::Resource;
 use winit::event::MouseButton;
@@ -41,6 +61,11 @@ pub enum EditorCommandEvent {
     SaveProject,
     SaveVoxelModel(VoxelModelId),
     SaveAnimation(GameAssetPath),
+    /// Captures the viewport without the editor UI, rendering the scene at
+    /// `supersample_scale` times its resolution for the capture.
+    CaptureViewport {
+        supersample_scale: u32,
+    },
 }
 
 pub enum EditorEvent {
@@ -52,8 +77,11 @@ pub struct EditorSession {
     pub entity_raycast: Option<WorldEntityRaycastHit>,
     pub terrain_raycast: Option<TerrainRaycastHit>,
     pub editor_camera_ray: Ray,
+    /// The primary selection, the most recently selected entity of `selected_entities`.
     pub selected_entity: Option<Entity>,
     pub last_selected_entity: Option<Entity>,
+    /// Every selected entity in the order they were selected.
+    selected_entities: Vec<Entity>,
     pub hovered_entity: Option<Entity>,
 
     pub editor_camera: Entity,
@@ -62,11 +90,22 @@ pub struct EditorSession {
     double_right_click_buffer: InputBuffer,
 
     pub render_colliders: bool,
+    /// Draws the bounds of every terrain chunk near the editor camera.
+    pub render_chunk_bounds: bool,
+    /// Draws the edges of the hovered model's surface voxels.
+    pub render_voxel_wireframe: bool,
+    /// The next click in the viewport sets the editor camera's focus distance instead of
+    /// selecting.
+    pub picking_camera_focus: bool,
 
     editor_event_reader: EventReader<EditorCommandEvent>,
 }
 
 impl EditorSession {
+    pub const TOGGLE_FPS_CAMERA_KEY: keyboard::Key = keyboard::Key::Escape;
+    /// Clicks further than this don't hit anything to focus on.
+    const MAX_FOCUS_PICK_DISTANCE: f32 = 500.0;
+
     pub fn new(
         ecs_world: &mut ECSWorld,
         main_camera: &mut MainCamera,
@@ -81,9 +120,13 @@ impl EditorSession {
             editor_camera_ray: Ray::new(Vector3::zeros(), Vector3::zeros()),
             selected_entity: None,
             last_selected_entity: None,
+            selected_entities: Vec::new(),
             hovered_entity: None,
 
             render_colliders: false,
+            render_chunk_bounds: false,
+            render_voxel_wireframe: false,
+            picking_camera_focus: false,
 
             editor_camera,
             editor_camera_focused: true,
@@ -115,18 +158,59 @@ impl EditorSession {
         time: Res<Time>,
         mut window: ResMut<Window>,
         main_camera: Res<MainCamera>,
+        voxel_registry: Res<VoxelModelRegistry>,
+        egui: Res<Egui>,
+        measure_tool: Res<EditorMeasureTool>,
+        settings: Res<Settings>,
     ) {
         session.editor_camera_focused = main_camera.camera() == Some(session.editor_camera());
         if !session.editor_camera_focused {
             return;
         }
 
-        let camera_transform = &mut ecs_world
-            .get::<&mut Transform>(session.editor_camera)
-            .unwrap();
-        session
-            .editor_camera_controller
-            .update(camera_transform, &input, &time, &mut window);
+        if !egui.context().wants_keyboard_input() {
+            // Axis views look at the selection, or the world origin without one.
+            let selection_bounds = session
+                .selected_entity
+                .and_then(|entity| Self::entity_bounds(&ecs_world, &voxel_registry, entity));
+            if let Some(axis_view) = EditorAxisView::from_pressed_actions(&input) {
+                let anchor = selection_bounds.map_or(Vector3::zeros(), |(center, _)| center);
+                session
+                    .editor_camera_controller
+                    .snap_to_axis_view(axis_view, anchor);
+            }
+            if input.is_action_pressed(consts::actions::EDITOR_FRAME_SELECTION)
+                && let Some((center, radius)) = selection_bounds
+            {
+                let camera = ecs_world
+                    .get::<&Camera>(session.editor_camera)
+                    .expect("Editor camera should have a camera");
+                session
+                    .editor_camera_controller
+                    .frame_bounds(center, radius, &camera);
+            }
+        }
+
+        let mut camera_query =
+            ecs_world.query_one::<(&mut Transform, &mut Camera)>(session.editor_camera);
+        let (mut camera_transform, mut camera) = camera_query
+            .get()
+            .expect("Editor camera should have a transform and camera");
+        // Escape clears a measurement before it toggles the fps camera.
+        if input.is_key_pressed(Self::TOGGLE_FPS_CAMERA_KEY) && !measure_tool.has_measurement() {
+            session
+                .editor_camera_controller
+                .toggle_fps(&camera_transform, &mut window);
+        }
+        session.editor_camera_controller.update(
+            &mut camera_transform,
+            &mut camera,
+            &input,
+            &time,
+            &mut window,
+            &settings,
+        );
+        drop(camera_query);
         const RIGHT_CLICK_DOUBLE_CLICK_MS: u64 = 200;
         session
             .double_right_click_buffer
@@ -151,6 +235,29 @@ impl EditorSession {
         &self.editor_camera_controller
     }
 
+    /// The center and bounding sphere radius of an entity, its voxel model's box if it has one.
+    fn entity_bounds(
+        ecs_world: &ECSWorld,
+        voxel_registry: &VoxelModelRegistry,
+        entity: Entity,
+    ) -> Option<(Vector3<f32>, f32)> {
+        let local_transform = ecs_world.get::<&Transform>(entity).ok()?;
+        let world_transform = ecs_world.get_world_transform(entity, &local_transform);
+        let radius = ecs_world
+            .get::<&RenderableVoxelEntity>(entity)
+            .ok()
+            .and_then(|renderable| renderable.voxel_model_id())
+            .map_or(1.0, |model_id| {
+                let side_length = voxel_registry.get_dyn_model(model_id).length();
+                world_transform
+                    .as_voxel_model_obb(side_length)
+                    .aabb
+                    .half_side_length()
+                    .norm()
+            });
+        Some((world_transform.position, radius))
+    }
+
     pub fn update_raycasts(
         mut session: ResMut<EditorSession>,
         ecs_world: Res<ECSWorld>,
@@ -200,16 +307,42 @@ impl EditorSession {
         window: Res<Window>,
         gizmo: Res<EditorGizmo>,
         mut events: ResMut<Events>,
+        measure_tool: Res<EditorMeasureTool>,
     ) {
-        // Update selected entity.
-        if input.is_mouse_button_pressed(mouse::Button::Left) && !gizmo.is_hovering() {
-            if let Some(hit) = &session.entity_raycast {
-                session.selected_entity = Some(hit.entity);
-            } else {
-                session.selected_entity = None;
+        // Update selected entity, control toggles the clicked entity in the selection.
+        if input.is_mouse_button_pressed(mouse::Button::Left)
+            && !gizmo.is_hovering()
+            && !measure_tool.enabled
+            && !session.picking_camera_focus
+        {
+            let hit_entity = session.entity_raycast.as_ref().map(|hit| hit.entity);
+            let is_toggling = input
+                .keyboard()
+                .is_modifiers_down(&[keyboard::Modifier::Control]);
+            if !is_toggling {
+                session.select_entity(hit_entity);
+            } else if let Some(entity) = hit_entity {
+                session.toggle_entity_selection(entity);
             }
         }
 
+        // Something assigned `selected_entity` directly so it replaces the selection.
+        if session.selected_entity != session.selected_entities.last().copied() {
+            let selected_entity = session.selected_entity;
+            session.select_entity(selected_entity);
+        }
+        // Drop despawned entities from the selection.
+        if session
+            .selected_entities
+            .iter()
+            .any(|entity| !ecs_world.contains_entity(*entity))
+        {
+            session
+                .selected_entities
+                .retain(|entity| ecs_world.contains_entity(*entity));
+            session.selected_entity = session.selected_entities.last().copied();
+        }
+
         // Send out event if selected entity changed at any point.
         if session.selected_entity != session.last_selected_entity {
             session.last_selected_entity = session.selected_entity;
@@ -217,10 +350,90 @@ impl EditorSession {
         }
     }
 
+    /// Sets the editor camera's focus distance to the point clicked while picking focus, this
+    /// runs after selection so the click doesn't also select.
+    pub fn update_camera_focus_picking(
+        mut session: ResMut<EditorSession>,
+        ecs_world: Res<ECSWorld>,
+        voxel_registry: Res<VoxelModelRegistry>,
+        region_map: Res<RegionMap>,
+        input: Res<Input>,
+    ) {
+        if !session.picking_camera_focus
+            || !session.is_editor_camera_focused()
+            || !input.is_mouse_button_pressed(mouse::Button::Left)
+        {
+            return;
+        }
+        session.picking_camera_focus = false;
+
+        let Some(hit) = World::trace_detailed(
+            &session.editor_camera_ray,
+            Self::MAX_FOCUS_PICK_DISTANCE,
+            &ecs_world,
+            &voxel_registry,
+            &region_map,
+        ) else {
+            return;
+        };
+        let mut camera = ecs_world
+            .get::<&mut Camera>(session.editor_camera)
+            .expect("Editor camera should have a camera");
+        camera.focus_distance = hit.depth_t;
+    }
+
+    pub fn selected_entities(&self) -> &[Entity] {
+        &self.selected_entities
+    }
+
+    pub fn is_entity_selected(&self, entity: Entity) -> bool {
+        self.selected_entities.contains(&entity)
+    }
+
+    /// Replaces the selection with just `entity`, or clears it.
+    pub fn select_entity(&mut self, entity: Option<Entity>) {
+        self.selected_entity = entity;
+        self.selected_entities = entity.into_iter().collect();
+    }
+
+    /// Adds `entity` to the selection as the primary selection, or removes it if it is
+    /// already selected.
+    pub fn toggle_entity_selection(&mut self, entity: Entity) {
+        if let Some(i) = self.selected_entities.iter().position(|e| *e == entity) {
+            self.selected_entities.remove(i);
+        } else {
+            self.selected_entities.push(entity);
+        }
+        self.selected_entity = self.selected_entities.last().copied();
+    }
+
+    /// Adds `entities` to the selection, the last entity becomes the primary selection.
+    pub fn extend_selection(&mut self, entities: impl IntoIterator<Item = Entity>) {
+        for entity in entities {
+            self.selected_entities.retain(|e| *e != entity);
+            self.selected_entities.push(entity);
+        }
+        self.selected_entity = self.selected_entities.last().copied();
+    }
+
     pub fn entity_raycast(&self) -> Option<&WorldEntityRaycastHit> {
         self.entity_raycast.as_ref()
     }
 
+    pub fn update_screenshot_keybind(input: Res<Input>, mut renderer: ResMut<Renderer>) {
+        if input.is_action_pressed(consts::actions::EDITOR_SCREENSHOT) {
+            renderer.request_screenshot(Screenshots::new_screenshot_path().path().to_owned());
+        }
+    }
+
+    /// Saves the project and editor settings on the save action, the same as File -> Save.
+    pub fn update_save_keybind(input: Res<Input>, assets: Res<Assets>, mut events: ResMut<Events>) {
+        if assets.project_dir().is_some() && input.is_action_pressed(consts::actions::EDITOR_SAVE) {
+            events.push(EditorCommandEvent::SaveProject);
+            events.push(EditorCommandEvent::SaveEditorSettings);
+        }
+    }
+
     pub fn update_editor_events(
         assets: Res<Assets>,
         editor_ui: Res<EditorUI>,
@@ -235,6 +448,15 @@ impl EditorSession {
         game_session: Res<EditorGameSession>,
         mut project_settings: ResMut<EditorProjectSettings>,
         mut animation_bank: ResMut<AnimationBank>,
+        mut screenshots: ResMut<Screenshots>,
+        rng: Res<Rng>,
+        (settings, input, voxel_editing, terrain_generator, sky_light): (
+            Res<Settings>,
+            Res<Input>,
+            Res<EditorVoxelEditing>,
+            Res<TerrainGeneratorSettings>,
+            Res<SkyLight>,
+        ),
     ) {
         let session = &mut *session;
         let mut unique_events = HashSet::new();
@@ -264,6 +486,7 @@ impl EditorSession {
                         user_project_settings: &project_settings,
                     };
                     editor_settings.save_settings();
+                    UserSettingsAsset::new(&settings, input.keybinds()).save_user_settings();
                 }
                 EditorCommandEvent::SaveProject => {
                     log::info!("Saving project");
@@ -275,9 +498,15 @@ impl EditorSession {
                             material_bank: &material_bank,
                             main_camera: &main_camera,
                             region_map: &region_map,
+                            rng: &rng,
+                            terrain_generator: &terrain_generator,
+                            sky_light: &sky_light,
                             game_camera: game_session.game_camera.clone(),
                         },
                     );
+                    if let Some(project_dir) = assets.project_dir() {
+                        voxel_editing.palette.save_project_palette(project_dir);
+                    }
                 }
                 EditorCommandEvent::SaveVoxelModel(voxel_model_id) => {
                     let Some(project_dir) = assets.project_dir() else {
@@ -300,6 +529,14 @@ impl EditorSession {
                         Assets::save_asset_sync::<Animation>(asset_path, animation.clone());
                     }
                 }
+                EditorCommandEvent::CaptureViewport { supersample_scale } => {
+                    screenshots.request(
+                        ScreenshotRequest::new(ScreenshotRegion::Padded(
+                            *editor_ui.content_padding(),
+                        ))
+                        .with_supersample_scale(*supersample_scale),
+                    );
+                }
             }
         }
     }
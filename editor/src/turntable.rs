@@ -0,0 +1,208 @@
+use std::path::PathBuf;
+
+use nalgebra::{Vector2, Vector3};
+use rogue_engine::{
+    asset::asset::AssetPath,
+    entity::ecs_world::ECSWorld,
+    graphics::screenshot::{ScreenshotRegion, ScreenshotRequest, Screenshots},
+    physics::transform::Transform,
+    resource::{Res, ResMut},
+};
+use rogue_macros::Resource;
+
+use crate::{camera_controller::EditorCameraController, session::EditorSession};
+
+#[derive(Clone)]
+pub struct TurntableSettings {
+    pub frame_count: u32,
+    pub resolution: Vector2<u32>,
+    pub output_dir: PathBuf,
+}
+
+impl TurntableSettings {
+    pub fn new() -> Self {
+        Self {
+            frame_count: 120,
+            resolution: Vector2::new(1280, 720),
+            output_dir: PathBuf::from("rogue_user_data").join("turntable"),
+        }
+    }
+
+    pub fn frame_path(&self, frame: u32) -> AssetPath {
+        AssetPath::new(self.output_dir.join(format!("frame_{:04}.png", frame)))
+    }
+}
+
+/// The orbit the camera follows, captured when the export starts so camera input mid export
+/// can't change the framing.
+struct TurntableOrbit {
+    anchor: Vector3<f32>,
+    distance: f32,
+    euler: Vector3<f32>,
+}
+
+impl TurntableOrbit {
+    /// The euler angles of `frame`, the last frame stops one step short of a full revolution so
+    /// the sequence loops seamlessly.
+    fn frame_euler(&self, frame: u32, frame_count: u32) -> Vector3<f32> {
+        let t = frame as f64 / frame_count as f64;
+        let yaw = self.euler.y as f64 + t * std::f64::consts::TAU;
+        Vector3::new(self.euler.x, yaw as f32, self.euler.z)
+    }
+}
+
+struct TurntableExport {
+    settings: TurntableSettings,
+    orbit: TurntableOrbit,
+    /// The number of frames that have been requested, all but the last being saved.
+    requested_frames: u32,
+    /// Restored once the export finishes or is cancelled.
+    original_camera_transform: Transform,
+}
+
+/// Orbits the editor camera 360 degrees around its focus point, capturing each frame to an
+/// image sequence.
+#[derive(Resource)]
+pub struct EditorTurntable {
+    export: Option<TurntableExport>,
+    cancel_requested: bool,
+}
+
+impl EditorTurntable {
+    pub fn new() -> Self {
+        Self {
+            export: None,
+            cancel_requested: false,
+        }
+    }
+
+    pub fn is_exporting(&self) -> bool {
+        self.export.is_some()
+    }
+
+    /// The (saved, total) frame counts of the current export.
+    pub fn progress(&self) -> Option<(u32, u32)> {
+        self.export.as_ref().map(|export| {
+            (
+                export.requested_frames.saturating_sub(1),
+                export.settings.frame_count,
+            )
+        })
+    }
+
+    pub fn start(
+        &mut self,
+        settings: TurntableSettings,
+        session: &EditorSession,
+        ecs_world: &ECSWorld,
+    ) {
+        assert!(
+            settings.frame_count > 0,
+            "Turntable needs at least one frame."
+        );
+        if self.is_exporting() {
+            log::warn!("Can't start a turntable export while another is in progress.");
+            return;
+        }
+
+        let camera_transform = ecs_world
+            .get::<&Transform>(session.editor_camera())
+            .expect("Editor camera should have a transform.")
+            .clone();
+        let controller = session.editor_camera_controller();
+        let orbit = TurntableOrbit {
+            anchor: controller.orbit_anchor(&camera_transform),
+            distance: controller.distance,
+            euler: controller.euler,
+        };
+
+        log::info!(
+            "Starting {} frame turntable export to {:?}.",
+            settings.frame_count,
+            settings.output_dir
+        );
+        self.export = Some(TurntableExport {
+            settings,
+            orbit,
+            requested_frames: 0,
+            original_camera_transform: camera_transform,
+        });
+        self.cancel_requested = false;
+    }
+
+    pub fn cancel(&mut self) {
+        if self.is_exporting() {
+            self.cancel_requested = true;
+        }
+    }
+
+    /// Moves the editor camera to the next frame's orbit position and requests its capture once
+    /// the previous frame has been saved. Runs after the editor camera controller so the
+    /// turntable takes priority.
+    pub fn update_turntable(
+        mut turntable: ResMut<EditorTurntable>,
+        session: Res<EditorSession>,
+        ecs_world: ResMut<ECSWorld>,
+        mut screenshots: ResMut<Screenshots>,
+    ) {
+        let turntable: &mut EditorTurntable = &mut turntable;
+        let Some(export) = &mut turntable.export else {
+            return;
+        };
+        let mut camera_transform = ecs_world
+            .get::<&mut Transform>(session.editor_camera())
+            .unwrap();
+
+        if turntable.cancel_requested || !session.is_editor_camera_focused() {
+            if turntable.cancel_requested {
+                log::info!("Cancelled turntable export.");
+            } else {
+                log::warn!("Cancelled turntable export since the editor camera lost focus.");
+            }
+            *camera_transform = export.original_camera_transform.clone();
+            turntable.export = None;
+            turntable.cancel_requested = false;
+            return;
+        }
+
+        let frame_count = export.settings.frame_count;
+        if !screenshots.is_idle() {
+            // Hold the camera on the frame being captured since the camera controller moves it
+            // back every frame.
+            if let Some(frame) = export.requested_frames.checked_sub(1) {
+                EditorCameraController::set_orbit_transform(
+                    &mut camera_transform,
+                    export.orbit.anchor,
+                    export.orbit.distance,
+                    export.orbit.frame_euler(frame, frame_count),
+                );
+            }
+            return;
+        }
+
+        if export.requested_frames == frame_count {
+            log::info!(
+                "Finished turntable export of {} frames to {:?}.",
+                frame_count,
+                export.settings.output_dir
+            );
+            *camera_transform = export.original_camera_transform.clone();
+            turntable.export = None;
+            return;
+        }
+
+        let frame = export.requested_frames;
+        EditorCameraController::set_orbit_transform(
+            &mut camera_transform,
+            export.orbit.anchor,
+            export.orbit.distance,
+            export.orbit.frame_euler(frame, frame_count),
+        );
+        screenshots.request(
+            ScreenshotRequest::new(ScreenshotRegion::Full)
+                .with_resolution(export.settings.resolution)
+                .with_path(export.settings.frame_path(frame)),
+        );
+        export.requested_frames += 1;
+    }
+}
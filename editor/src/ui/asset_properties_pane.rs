@@ -1,18 +1,24 @@
 use std::path::{Path, PathBuf};
 
 use rogue_engine::{
-    asset::asset::{AssetPath, GameAssetPath},
+    asset::asset::{AssetHandle, AssetPath, AssetStatus, GameAssetPath},
     material::{MaterialAsset, MaterialTextureType},
 };
 
 use crate::ui::{EditorCommand, FilePickerType, pane::EditorUIPane};
 
 #[derive(serde::Serialize, serde::Deserialize)]
-pub struct AssetPropertiesPane {}
+pub struct AssetPropertiesPane {
+    /// The selected material asset while it loads, kept so it is only requested once.
+    #[serde(skip)]
+    loading_material: Option<AssetHandle>,
+}
 
 impl AssetPropertiesPane {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            loading_material: None,
+        }
     }
 
     pub fn show_header(ui: &mut egui::Ui, ctx: &mut super::EditorUIContext<'_>) {
@@ -32,7 +38,7 @@ impl AssetPropertiesPane {
         }
     }
 
-    pub fn show_properties(ui: &mut egui::Ui, ctx: &mut super::EditorUIContext<'_>) {
+    pub fn show_properties(&mut self, ui: &mut egui::Ui, ctx: &mut super::EditorUIContext<'_>) {
         let Some(selected_asset) = ctx.ui_state.selected_asset.clone() else {
             ui.label("No asset selected.");
             return;
@@ -42,7 +48,7 @@ impl AssetPropertiesPane {
         let ext = ctx.ui_state.selected_asset_extension();
         match ext.as_deref() {
             Some("rmat") => {
-                Self::show_material_properties(ui, ctx, selected_asset);
+                self.show_material_properties(ui, ctx, selected_asset);
             }
             _ => {
                 ui.label("Unknown asset type.");
@@ -51,18 +57,43 @@ impl AssetPropertiesPane {
     }
 
     pub fn show_material_properties(
+        &mut self,
         ui: &mut egui::Ui,
         ctx: &mut super::EditorUIContext<'_>,
         asset_path: AssetPath,
     ) {
-        let asset_handle = ctx
-            .assets
-            .get_asset_handle::<MaterialAsset>(&asset_path)
-            .unwrap_or_else(|| {
-                let handle = ctx.assets.load_asset::<MaterialAsset>(asset_path);
-                ctx.assets.wait_until_all_loaded();
-                handle
-            });
+        let asset_handle = match ctx.assets.get_asset_handle::<MaterialAsset>(&asset_path) {
+            Some(handle) => handle,
+            None => {
+                let handle = match &self.loading_material {
+                    Some(handle) if handle.asset_path() == &asset_path => handle.clone(),
+                    _ => {
+                        let handle = ctx.assets.load_asset::<MaterialAsset>(asset_path);
+                        self.loading_material = Some(handle.clone());
+                        handle
+                    }
+                };
+                match ctx.assets.get_asset_status(&handle) {
+                    AssetStatus::InProgress => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Loading material asset...");
+                        });
+                        return;
+                    }
+                    AssetStatus::Loaded => handle,
+                    AssetStatus::Saved => unreachable!(),
+                    AssetStatus::NotFound => {
+                        ui.label("Material asset not found.");
+                        return;
+                    }
+                    AssetStatus::Error(err) => {
+                        ui.label(format!("Failed to load material asset: {}", err));
+                        return;
+                    }
+                }
+            }
+        };
         let Some(material_asset) = ctx.assets.get_asset::<MaterialAsset>(&asset_handle) else {
             ui.label("Failed to load material asset.");
             return;
@@ -118,7 +149,7 @@ impl EditorUIPane for AssetPropertiesPane {
         ui.vertical(|ui| {
             Self::show_header(ui, ctx);
             ui.add_space(4.0);
-            Self::show_properties(ui, ctx);
+            self.show_properties(ui, ctx);
         });
     }
 }
@@ -4,9 +4,10 @@ use nalgebra::Vector3;
 use rogue_engine::{
     asset::asset::GameAssetPath,
     common::color::{Color, ColorSpaceSrgb},
+    consts,
     entity::{GameEntity, RenderableVoxelEntity},
     material::material_bank::MaterialId,
-    voxel::voxel::{VoxelModelEditMaskLayer, VoxelModelEditRegion},
+    voxel::voxel::{VoxelEditMirrorAxes, VoxelMaterialData, VoxelModelEditRegion},
     world::terrain::region_map::{
         VoxelTerrainEdit, VoxelTerrainEditMask, VoxelTerrainEditMaskLayer, VoxelTerrainRegion,
     },
@@ -16,7 +17,7 @@ use strum::VariantArray;
 use crate::{
     editing::{
         voxel_editing::{
-            EditorEditingMaterial, EditorEditingTool, EditorEditingToolType,
+            EditorBrushShape, EditorEditingMaterial, EditorEditingTool, EditorEditingToolType,
             EditorVoxelEditingTarget,
         },
         voxel_editing_edit_tools::EditorVoxelEditingEditTools,
@@ -28,6 +29,8 @@ use crate::{
 #[serde(default)]
 pub struct EditingPane {
     recent_materials: VecDeque<MaterialId>,
+    #[serde(skip)]
+    new_palette_entry_name: String,
 }
 
 impl Default for EditingPane {
@@ -40,6 +43,7 @@ impl EditingPane {
     pub fn new() -> Self {
         Self {
             recent_materials: VecDeque::new(),
+            new_palette_entry_name: String::new(),
         }
     }
 
@@ -78,6 +82,71 @@ impl EditingPane {
         );
     }
 
+    pub fn show_palette(&mut self, ui: &mut egui::Ui, ctx: &mut super::EditorUIContext<'_>) {
+        let current_material = ctx.voxel_editing.current_voxel_material();
+        let mut selected_entry = None;
+        let mut removed_entry = None;
+        ui.label("Palette:");
+        ui.horizontal_wrapped(|ui| {
+            for (i, entry) in ctx.voxel_editing.palette.entries.iter().enumerate() {
+                let response = ui
+                    .horizontal(|ui| {
+                        if let VoxelMaterialData::Baked { color } = &entry.material {
+                            egui::color_picker::show_color(
+                                ui,
+                                egui::Color32::from_rgb(color.r_u8(), color.g_u8(), color.b_u8()),
+                                egui::vec2(12.0, 12.0),
+                            );
+                        }
+                        ui.selectable_label(
+                            current_material.as_ref() == Some(&entry.material),
+                            &entry.name,
+                        )
+                    })
+                    .inner;
+                if response.clicked() {
+                    selected_entry = Some(i);
+                }
+                response.context_menu(|ui| {
+                    if ui.button("Remove").clicked() {
+                        removed_entry = Some(i);
+                        ui.close_menu();
+                    }
+                });
+            }
+        });
+        if let Some(i) = selected_entry {
+            let material = ctx.voxel_editing.palette.entries[i].material.clone();
+            ctx.voxel_editing.set_voxel_material(&material);
+        }
+        if let Some(i) = removed_entry {
+            ctx.voxel_editing.palette.remove(i);
+        }
+
+        // Adds what is currently being painted with, which is also how a voxel sampled with
+        // the color picker is saved.
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_palette_entry_name)
+                    .hint_text("Name")
+                    .desired_width(120.0),
+            );
+            let name = self.new_palette_entry_name.trim();
+            if ui
+                .add_enabled(
+                    !name.is_empty() && current_material.is_some(),
+                    egui::Button::new("Add current"),
+                )
+                .clicked()
+            {
+                ctx.voxel_editing
+                    .palette
+                    .push(name, current_material.clone().unwrap());
+                self.new_palette_entry_name.clear();
+            }
+        });
+    }
+
     pub fn show_contents(&mut self, ui: &mut egui::Ui, ctx: &mut super::EditorUIContext<'_>) {
         ui.horizontal(|ui| {
             ui.label("Editing Enabled:");
@@ -175,6 +244,7 @@ impl EditingPane {
                 });
             }
         }
+        self.show_palette(ui, ctx);
 
         ui.separator();
         ui.horizontal_wrapped(|ui| {
@@ -191,11 +261,27 @@ impl EditingPane {
             }
         });
 
-        fn brush_size_ui(ui: &mut egui::Ui, brush_size: &mut u32) {
+        fn brush_size_ui(
+            ui: &mut egui::Ui,
+            brush_size: &mut u32,
+            brush_shape: &mut EditorBrushShape,
+        ) {
             ui.horizontal(|ui| {
                 ui.label("Brush Size:");
                 ui.add(egui::DragValue::new(brush_size).range(1..=128));
             });
+            ui.horizontal(|ui| {
+                ui.label("Brush Shape:");
+                for shape in EditorBrushShape::VARIANTS {
+                    ui.selectable_value(brush_shape, *shape, shape.to_string());
+                }
+            });
+        }
+        fn brush_opacity_ui(ui: &mut egui::Ui, brush_opacity: &mut f32) {
+            ui.horizontal(|ui| {
+                ui.label("Opacity:");
+                ui.add(egui::Slider::new(brush_opacity, 0.0..=1.0));
+            });
         }
         let current_voxel_material = ctx.voxel_editing.current_voxel_material().clone();
         let tool = ctx
@@ -208,7 +294,8 @@ impl EditingPane {
                 brush_size,
                 air_place,
             } => {
-                brush_size_ui(ui, brush_size);
+                brush_size_ui(ui, brush_size, &mut ctx.voxel_editing.brush_shape);
+                brush_opacity_ui(ui, &mut ctx.voxel_editing.brush_opacity);
                 if ctx
                     .voxel_editing
                     .edit_target
@@ -241,10 +328,9 @@ impl EditingPane {
                             region: VoxelTerrainRegion::new_rect(brush_min, brush_max),
                             mask: VoxelTerrainEditMask {
                                 layers: vec![VoxelTerrainEditMaskLayer(
-                                    VoxelModelEditMaskLayer::Sphere {
-                                        center: origin_pos,
-                                        diameter: *brush_size,
-                                    },
+                                    ctx.voxel_editing
+                                        .brush_shape
+                                        .mask_layer(origin_pos, *brush_size),
                                 )],
                             },
                             operator: rogue_engine::voxel::voxel::VoxelModelEditOperator::Replace(
@@ -261,19 +347,65 @@ impl EditingPane {
                 }
             }
             EditorEditingTool::Paint { brush_size } => {
-                brush_size_ui(ui, brush_size);
+                brush_size_ui(ui, brush_size, &mut ctx.voxel_editing.brush_shape);
+                brush_opacity_ui(ui, &mut ctx.voxel_editing.brush_opacity);
             }
             EditorEditingTool::Eraser { brush_size } => {
-                brush_size_ui(ui, brush_size);
+                brush_size_ui(ui, brush_size, &mut ctx.voxel_editing.brush_shape);
+            }
+            EditorEditingTool::Line { brush_size } => {
+                brush_size_ui(ui, brush_size, &mut ctx.voxel_editing.brush_shape);
+                brush_opacity_ui(ui, &mut ctx.voxel_editing.brush_opacity);
+                ui.label("Click twice to place a line, right click to cancel.");
             }
             EditorEditingTool::Selection => {
                 ui.label("Rectangle Selection:");
             }
             EditorEditingTool::ColorPicker => {
-                ui.label("Color picker");
+                ui.label("Click a voxel to pick its color or material.");
+                if let Some(binding) = ctx
+                    .input
+                    .keybinds()
+                    .binding(consts::actions::EDITOR_EYEDROPPER)
+                {
+                    ui.label(format!("Press {} to pick with any tool.", binding));
+                }
+            }
+            EditorEditingTool::Bucket { max_voxels } => {
+                ui.horizontal(|ui| {
+                    ui.label("Max voxels:");
+                    ui.add(egui::DragValue::new(max_voxels).range(1..=10_000_000));
+                });
             }
         }
 
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Mirror:");
+            for (axis, axis_name) in [
+                (VoxelEditMirrorAxes::X, "X"),
+                (VoxelEditMirrorAxes::Y, "Y"),
+                (VoxelEditMirrorAxes::Z, "Z"),
+            ] {
+                let mut is_mirrored = ctx.voxel_editing.mirror.axes.contains(axis);
+                if ui.checkbox(&mut is_mirrored, axis_name).changed() {
+                    ctx.voxel_editing.mirror.axes.set(axis, is_mirrored);
+                }
+            }
+        });
+        if matches!(
+            ctx.voxel_editing.edit_target,
+            Some(EditorVoxelEditingTarget::Terrain)
+        ) {
+            ui.horizontal(|ui| {
+                ui.label("Mirror plane:");
+                let terrain_plane = &mut ctx.voxel_editing.mirror.terrain_plane;
+                ui.add(egui::DragValue::new(&mut terrain_plane.x).prefix("x: "));
+                ui.add(egui::DragValue::new(&mut terrain_plane.y).prefix("y: "));
+                ui.add(egui::DragValue::new(&mut terrain_plane.z).prefix("z: "));
+            });
+        }
+
         ui.separator();
         ui.horizontal(|ui| {
             ui.label("Masks");
@@ -313,6 +445,9 @@ impl EditingPane {
                         center,
                         diameter,
                     } => {}
+                    rogue_engine::voxel::voxel::VoxelModelEditMaskLayer::Cube { .. } => {}
+                    rogue_engine::voxel::voxel::VoxelModelEditMaskLayer::Cylinder { .. } => {}
+                    rogue_engine::voxel::voxel::VoxelModelEditMaskLayer::Voxels { .. } => {}
                 }
             }
             if let Some(i) = to_remove {
@@ -1,5 +1,9 @@
 use nalgebra::Translation3;
 use rogue_engine::{
+    asset::{
+        asset::{Assets, GameAssetPath},
+        repr::game_entity::PrefabAsset,
+    },
     entity::{
         EntityChildren, EntityParent, GameEntity,
         component::GameComponentCloneContext,
@@ -9,7 +13,7 @@ use rogue_engine::{
 };
 
 use crate::ui::{
-    EditorCommand, EditorUIContext,
+    EditorCommand, EditorUIContext, FilePickerType,
     entity_properties::EntityPropertiesPane,
     pane::{EditorUIPane, EditorUIPaneMethods},
 };
@@ -69,6 +73,11 @@ impl EntityHierarchyUI {
                     modify_transform: true,
                 });
             }
+            if let Some(path) = label.dnd_release_payload::<GameAssetPath>()
+                && path.extension() == PrefabAsset::EXTENSION
+            {
+                Self::instantiate_prefab(ctx, &path, None);
+            }
 
             Self::add_menu(ui, None, ctx);
         });
@@ -124,9 +133,7 @@ impl EntityHierarchyUI {
             if is_hovering {
                 text = text.background_color(egui::Color32::from_white_alpha(2));
             }
-            if ctx.session.selected_entity.is_some()
-                && ctx.session.selected_entity.unwrap() == entity_id
-            {
+            if ctx.session.is_entity_selected(entity_id) {
                 text = text.background_color(egui::Color32::from_white_alpha(3));
             }
 
@@ -153,6 +160,11 @@ impl EntityHierarchyUI {
                     });
                 }
             }
+            if let Some(path) = label.dnd_release_payload::<GameAssetPath>()
+                && path.extension() == PrefabAsset::EXTENSION
+            {
+                Self::instantiate_prefab(ctx, &path, Some(entity_id));
+            }
 
             ui.data_mut(|w| w.insert_temp(label_hover_id, label.hovered()));
             if label.hovered() {
@@ -160,7 +172,17 @@ impl EntityHierarchyUI {
             }
 
             if label.interact(egui::Sense::click()).clicked() {
-                ctx.session.selected_entity = Some(entity_id);
+                let modifiers = ui.input(|i| i.modifiers);
+                if modifiers.command {
+                    ctx.session.toggle_entity_selection(entity_id);
+                } else if modifiers.shift
+                    && let Some(anchor) = ctx.session.selected_entity
+                {
+                    let range = Self::hierarchy_range(ctx, anchor, entity_id);
+                    ctx.session.extend_selection(range);
+                } else {
+                    ctx.session.select_entity(Some(entity_id));
+                }
                 if !ctx.voxel_editing.enabled {
                     ctx.commands
                         .push(EditorCommand::open_ui(EntityPropertiesPane::ID));
@@ -171,6 +193,15 @@ impl EntityHierarchyUI {
                 Self::add_menu(ui, Some(entity_id), ctx);
 
                 if ui.button("Save as prefab").clicked() {
+                    ctx.commands.push(EditorCommand::FilePicker {
+                        picker_type: FilePickerType::CreateFile,
+                        callback: Box::new(move |ctx, asset_path| {
+                            let asset_path = GameAssetPath::from_relative_path(&asset_path);
+                            Self::save_prefab(&ctx, entity_id, &asset_path);
+                        }),
+                        extensions: vec![PrefabAsset::EXTENSION.to_owned()],
+                        preset_file_path: None,
+                    });
                     ui.close_menu();
                 }
                 if ui.button("Copy").clicked() {
@@ -183,14 +214,17 @@ impl EntityHierarchyUI {
                         .get::<&EntityParent>(entity_id)
                         .map(|p| p.parent())
                         .ok();
-                    ctx.session.selected_entity = Some(ctx.ecs_world.duplicate_entity(
+                    let duplicate = ctx.ecs_world.duplicate_entity(
                         entity_id,
                         existing_parent,
                         &mut GameComponentCloneContext {
                             voxel_registry: ctx.voxel_registry,
                             collider_registry: &mut ctx.physics_world.colliders,
+                            share_voxel_models: false,
+                            share_colliders: false,
                         },
-                    ));
+                    );
+                    ctx.session.select_entity(Some(duplicate));
                     ui.close_menu();
                 }
                 if ui.button("Delete").clicked() {
@@ -200,13 +234,73 @@ impl EntityHierarchyUI {
                         entity: entity_id,
                         despawn_children: true,
                     });
-                    ctx.session.selected_entity = None;
+                    ctx.session.select_entity(None);
                     ui.close_menu();
                 }
             });
         });
     }
 
+    /// Instantiates the prefab as a child of `parent`, or at the editor camera's anchor if it
+    /// has no parent.
+    pub fn instantiate_prefab(
+        ctx: &mut EditorUIContext<'_>,
+        path: &GameAssetPath,
+        parent: Option<Entity>,
+    ) {
+        let Some(project_dir) = ctx.assets.project_dir().clone() else {
+            log::error!("Can't instantiate a prefab without a project open.");
+            return;
+        };
+        let entity = match ctx.ecs_world.instantiate_prefab(
+            path,
+            &project_dir,
+            ctx.voxel_registry,
+            &mut ctx.physics_world.colliders,
+        ) {
+            Ok(entity) => entity,
+            Err(err) => {
+                log::error!(
+                    "Failed to instantiate prefab {}: {}",
+                    path.as_relative_path_str(),
+                    err
+                );
+                return;
+            }
+        };
+
+        if let Some(parent) = parent {
+            ctx.events.push(EntityCommandEvent::SetParent {
+                parent: Some(parent),
+                child: entity,
+                modify_transform: false,
+            });
+        } else if let Ok(mut transform) = ctx.ecs_world.get::<&mut Transform>(entity) {
+            transform.position = ctx.session.editor_camera_controller().rotation_anchor;
+        }
+        ctx.session.select_entity(Some(entity));
+    }
+
+    fn save_prefab(ctx: &EditorUIContext<'_>, entity: Entity, path: &GameAssetPath) {
+        let Some(project_dir) = ctx.assets.project_dir() else {
+            return;
+        };
+        let result = PrefabAsset::serialize(
+            ctx.ecs_world,
+            entity,
+            ctx.voxel_registry,
+            &ctx.physics_world.colliders,
+        )
+        .and_then(|prefab| Assets::save_asset_sync(path.as_file_asset_path(project_dir), prefab));
+        if let Err(err) = result {
+            log::error!(
+                "Failed to save prefab {}: {}",
+                path.as_relative_path_str(),
+                err
+            );
+        }
+    }
+
     // Renders any children the entity has, if any.
     fn render_children(ui: &mut egui::Ui, ctx: &mut EditorUIContext<'_>, entity_id: Entity) {
         let Ok(children_query) = ctx.ecs_world.get::<&EntityChildren>(entity_id) else {
@@ -228,8 +322,54 @@ impl EntityHierarchyUI {
                 }
             });
             if res.response.interact(egui::Sense::click()).clicked() {
-                ctx.session.selected_entity = None;
+                ctx.session.select_entity(None);
             }
         });
     }
+
+    /// The entities listed from `anchor` to `entity` in the hierarchy, ending with `entity`.
+    fn hierarchy_range(ctx: &EditorUIContext<'_>, anchor: Entity, entity: Entity) -> Vec<Entity> {
+        let mut order = Vec::new();
+        let roots = ctx
+            .ecs_world
+            .query::<&GameEntity>()
+            .without::<(EntityParent,)>()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        for root in roots {
+            Self::push_hierarchy_order(ctx, root, &mut order);
+        }
+
+        let (Some(anchor_index), Some(entity_index)) = (
+            order.iter().position(|e| *e == anchor),
+            order.iter().position(|e| *e == entity),
+        ) else {
+            return vec![entity];
+        };
+        if anchor_index <= entity_index {
+            order[anchor_index..=entity_index].to_vec()
+        } else {
+            order[entity_index..=anchor_index]
+                .iter()
+                .rev()
+                .copied()
+                .collect()
+        }
+    }
+
+    /// Pushes `entity` and its children in the same order they are rendered.
+    fn push_hierarchy_order(ctx: &EditorUIContext<'_>, entity: Entity, order: &mut Vec<Entity>) {
+        order.push(entity);
+        let Ok(children_query) = ctx.ecs_world.get::<&EntityChildren>(entity) else {
+            return;
+        };
+        let children = children_query.children.clone();
+        drop(children_query);
+        for child in children {
+            if ctx.ecs_world.contains::<GameEntity>(child) {
+                Self::push_hierarchy_order(ctx, child, order);
+            }
+        }
+    }
 }
@@ -15,8 +15,10 @@ use rogue_engine::{
     audio::AudioPlayer,
     egui::egui_util,
     entity::{
-        EntityChildren, EntityParent, GameEntity, RenderableVoxelEntity, component::GameComponent,
-        ecs_world::EntityCommandEvent,
+        EntityChildren, EntityParent, GameEntity, PrefabInstance, RenderableVoxelEntity,
+        component::GameComponent,
+        ecs_world::{Entity, EntityCommandEvent},
+        scripting::ScriptableEntity,
     },
     event::Events,
     graphics::camera::Camera,
@@ -25,6 +27,7 @@ use rogue_engine::{
         capsule_collider::CapsuleCollider,
         collider_component::EntityColliders,
         collider_registry::ColliderId,
+        joint::{Joint, JointKind},
         physics_world::PhysicsWorld,
         plane_collider::PlaneCollider,
         rigid_body::{RigidBody, RigidBodyPositionInterpolation, RigidBodyType},
@@ -35,6 +38,7 @@ use rogue_engine::{
         voxel_registry::{VoxelModelId, VoxelModelRegistry},
     },
 };
+use nalgebra::Vector3;
 use rogue_game::player::player_controller::PlayerController;
 use std::{
     any::TypeId,
@@ -106,6 +110,8 @@ impl EntityPropertiesShowFns {
         s.register_component_ui::<RigidBody>(Self::show_rigid_body_component);
         s.register_component_ui::<Animator>(Self::show_animator_component);
         s.register_component_ui::<AudioPlayer>(Self::show_audio_player_component);
+        s.register_component_ui::<PrefabInstance>(Self::show_prefab_instance_component);
+        s.register_component_ui::<ScriptableEntity>(Self::show_scriptable_entity_component);
 
         // TODO: Expose the editor api as a library and then have the game code able to register
         // editor stuff with a feature or something. Possibly just make the these show fns a global
@@ -345,6 +351,19 @@ impl EntityPropertiesShowFns {
             ui.checkbox(&mut rigid_body.locked_rotational_axes.y, "Y");
             ui.checkbox(&mut rigid_body.locked_rotational_axes.z, "Z");
         });
+        ui.horizontal(|ui| {
+            ui.label("Collision layer");
+            ui.add(
+                egui::DragValue::new(&mut rigid_body.collision_layer).hexadecimal(8, false, true),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Collision mask");
+            ui.add(
+                egui::DragValue::new(&mut rigid_body.collision_mask).hexadecimal(8, false, true),
+            );
+        });
+        ui.checkbox(&mut rigid_body.continuous, "Continuous collision");
         ui.label(format!(
             "Velocity  X: {:.2}, Y: {:.2}, Z: {:.2}",
             rigid_body.velocity.x, rigid_body.velocity.y, rigid_body.velocity.z
@@ -362,6 +381,40 @@ impl EntityPropertiesShowFns {
             ui.add(egui::Slider::new(&mut deg, 1.0..=180.0));
             camera.fov = deg.to_radians();
         });
+        ui.horizontal(|ui| {
+            let mut is_orthographic = camera.is_orthographic();
+            if ui.checkbox(&mut is_orthographic, "Orthographic").changed() {
+                camera.orthographic_half_height = is_orthographic.then_some(5.0);
+            }
+            if let Some(half_height) = &mut camera.orthographic_half_height {
+                ui.add(
+                    egui::DragValue::new(half_height)
+                        .range(0.01..=f32::MAX)
+                        .speed(0.1)
+                        .prefix("Half height: ")
+                        .suffix("m"),
+                );
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Focus distance");
+            ui.add(
+                egui::DragValue::new(&mut camera.focus_distance)
+                    .range(0.01..=f32::MAX)
+                    .speed(0.1)
+                    .suffix("m"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Aperture");
+            ui.add(
+                egui::DragValue::new(&mut camera.aperture)
+                    .range(0.0..=1.0)
+                    .speed(0.005)
+                    .suffix("m"),
+            )
+            .on_hover_text("The lens diameter with depth of field, larger blurs more.");
+        });
     }
 
     fn show_colliders_component(
@@ -535,6 +588,42 @@ impl EntityPropertiesShowFns {
         }
     }
 
+    fn show_scriptable_entity_component(
+        scriptable: &mut ScriptableEntity,
+        ui: &mut egui::Ui,
+        ctx: &mut ShowComponentContext,
+    ) {
+        let mut to_remove = None;
+        for (i, script) in scriptable.scripts.iter_mut().enumerate() {
+            let mut path = Some(script.clone());
+            egui_util::game_asset_path_button(ui, &mut path, "Script:".to_owned(), |_| {});
+            match path {
+                Some(path) => *script = path,
+                None => to_remove = Some(i),
+            }
+        }
+        if let Some(i) = to_remove {
+            scriptable.scripts.remove(i);
+        }
+
+        let mut new_script = None;
+        egui_util::game_asset_path_button(ui, &mut new_script, "Add script:".to_owned(), |_| {});
+        if let Some(new_script) = new_script {
+            scriptable.scripts.push(new_script);
+        }
+    }
+
+    fn show_prefab_instance_component(
+        prefab_instance: &mut PrefabInstance,
+        ui: &mut egui::Ui,
+        ctx: &mut ShowComponentContext,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("Prefab:");
+            ui.label(prefab_instance.source.as_relative_path_str());
+        });
+    }
+
     fn show_animator_component(
         animator: &mut Animator,
         ui: &mut egui::Ui,
@@ -600,6 +689,10 @@ impl EntityPropertiesPane {
             ui.label("No entity selected");
             return;
         };
+        if ctx.session.selected_entities().len() > 1 {
+            Self::shared_components(ui, ctx);
+            return;
+        }
 
         Self::component_widget(ui, "General", None, |ui| {
             let mut game_entity = ctx
@@ -667,6 +760,8 @@ impl EntityPropertiesPane {
             });
         }
 
+        Self::joints(ui, ctx, selected_entity);
+
         let component_types = ctx.ecs_world.get_entity_components(selected_entity);
         let mut component_ctx = ShowComponentContext {
             physics_world: ctx.physics_world,
@@ -730,6 +825,282 @@ impl EntityPropertiesPane {
         }
     }
 
+    /// The joints connecting the entity to others, joints aren't components since they belong
+    /// to two entities.
+    fn joints(ui: &mut egui::Ui, ctx: &mut EditorUIContext<'_>, selected_entity: Entity) {
+        let joint_ids = ctx
+            .physics_world
+            .joints()
+            .filter(|(_, joint)| {
+                joint.entity_a == selected_entity || joint.entity_b == selected_entity
+            })
+            .map(|(joint_id, _)| joint_id)
+            .collect::<Vec<_>>();
+
+        Self::component_widget(ui, "Joints", None, |ui| {
+            let mut to_remove = None;
+            for joint_id in joint_ids {
+                let joint = ctx.physics_world.get_joint_mut(joint_id).unwrap();
+                let other_entity = if joint.entity_a == selected_entity {
+                    joint.entity_b
+                } else {
+                    joint.entity_a
+                };
+                let other_name = ctx.ecs_world.get::<&GameEntity>(other_entity).map_or_else(
+                    |_| "Missing".to_owned(),
+                    |game_entity| game_entity.name.clone(),
+                );
+                let kind_name = match joint.kind {
+                    JointKind::Distance { .. } => "Distance",
+                    JointKind::Hinge { .. } => "Hinge",
+                };
+
+                ui.push_id(joint_id.index(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} to {}", kind_name, other_name));
+                        if ui.button("Remove").clicked() {
+                            to_remove = Some(joint_id);
+                        }
+                    });
+                    match &mut joint.kind {
+                        JointKind::Distance { distance } => {
+                            ui.horizontal(|ui| {
+                                ui.label("Distance:");
+                                ui.add(
+                                    egui::DragValue::new(distance)
+                                        .suffix(" m")
+                                        .speed(0.01)
+                                        .range(0.0..=f32::MAX),
+                                );
+                            });
+                        }
+                        JointKind::Hinge { limits, .. } => {
+                            let mut has_limits = limits.is_some();
+                            ui.checkbox(&mut has_limits, "Limits");
+                            if has_limits != limits.is_some() {
+                                *limits = has_limits.then_some((
+                                    -std::f32::consts::FRAC_PI_4,
+                                    std::f32::consts::FRAC_PI_4,
+                                ));
+                            }
+                            if let Some((min_angle, max_angle)) = limits {
+                                let mut min_degrees = min_angle.to_degrees();
+                                let mut max_degrees = max_angle.to_degrees();
+                                ui.horizontal(|ui| {
+                                    ui.label("Min:");
+                                    ui.add(
+                                        egui::DragValue::new(&mut min_degrees)
+                                            .suffix("°")
+                                            .range(-180.0..=max_degrees),
+                                    );
+                                    ui.label("Max:");
+                                    ui.add(
+                                        egui::DragValue::new(&mut max_degrees)
+                                            .suffix("°")
+                                            .range(min_degrees..=180.0),
+                                    );
+                                });
+                                *min_angle = min_degrees.to_radians();
+                                *max_angle = max_degrees.to_radians();
+                            }
+                        }
+                    }
+                });
+            }
+            if let Some(joint_id) = to_remove {
+                ctx.physics_world.remove_joint(joint_id);
+            }
+
+            // The joint is made in the entities' current placement, distance joints keep the
+            // entities' origins apart and hinges rotate around the chosen axis of this entity
+            // through its origin.
+            let mut to_create = None;
+            ui.menu_button("Add joint", |ui| {
+                for (other_entity, game_entity) in ctx
+                    .ecs_world
+                    .query::<&GameEntity>()
+                    .with::<(Transform,)>()
+                    .into_iter()
+                {
+                    if other_entity == selected_entity {
+                        continue;
+                    }
+                    ui.menu_button(&game_entity.name, |ui| {
+                        if ui.button("Distance").clicked() {
+                            to_create = Some((other_entity, None));
+                            ui.close_menu();
+                        }
+                        ui.menu_button("Hinge", |ui| {
+                            for (axis_name, axis) in [
+                                ("X axis", Vector3::x()),
+                                ("Y axis", Vector3::y()),
+                                ("Z axis", Vector3::z()),
+                            ] {
+                                if ui.button(axis_name).clicked() {
+                                    to_create = Some((other_entity, Some(axis)));
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    });
+                }
+            });
+            let Some((other_entity, hinge_local_axis)) = to_create else {
+                return;
+            };
+            let world_transform = |entity: Entity| {
+                let transform = ctx.ecs_world.get::<&Transform>(entity).unwrap();
+                ctx.ecs_world.get_world_transform(entity, &transform)
+            };
+            let other_transform = world_transform(other_entity);
+            let selected_transform = world_transform(selected_entity);
+            let joint = if let Some(local_axis) = hinge_local_axis {
+                Joint::new_hinge(
+                    other_entity,
+                    &other_transform,
+                    selected_entity,
+                    &selected_transform,
+                    selected_transform.position,
+                    selected_transform.rotation * local_axis,
+                    None,
+                )
+            } else {
+                Joint::new_distance(
+                    other_entity,
+                    &other_transform,
+                    other_transform.position,
+                    selected_entity,
+                    &selected_transform,
+                    selected_transform.position,
+                )
+            };
+            if let Err(err) = ctx.physics_world.add_joint(joint) {
+                log::error!("Failed to add joint. Error: {:?}", err);
+            }
+        });
+    }
+
+    /// Shows the properties every selected entity has, editing them applies to all of them.
+    fn shared_components(ui: &mut egui::Ui, ctx: &mut EditorUIContext<'_>) {
+        let selected_entities = ctx.session.selected_entities().to_vec();
+        ui.label(format!("{} entities selected", selected_entities.len()));
+
+        let transforms = selected_entities
+            .iter()
+            .filter_map(|entity| {
+                ctx.ecs_world
+                    .get::<&Transform>(*entity)
+                    .ok()
+                    .map(|transform| transform.clone())
+            })
+            .collect::<Vec<_>>();
+        if transforms.len() == selected_entities.len() {
+            Self::component_widget(ui, "Transform", None, |ui| {
+                let positions = transforms.iter().map(|t| t.position).collect::<Vec<_>>();
+                let rotations = transforms.iter().map(|t| t.rotation).collect::<Vec<_>>();
+                let scales = transforms.iter().map(|t| t.scale).collect::<Vec<_>>();
+                let new_position =
+                    Self::shared_field_ui(ui, &positions, rogue_engine::egui::util::position_ui);
+                let new_rotation =
+                    Self::shared_field_ui(ui, &rotations, rogue_engine::egui::util::rotation_ui);
+                let new_scale =
+                    Self::shared_field_ui(ui, &scales, rogue_engine::egui::util::scale_ui);
+
+                for entity in &selected_entities {
+                    let mut transform = ctx.ecs_world.get::<&mut Transform>(*entity).unwrap();
+                    // Only the edited axes are written so the others keep their own values.
+                    for i in 0..3 {
+                        if let Some(new_position) = new_position
+                            && new_position[i] != positions[0][i]
+                        {
+                            transform.position[i] = new_position[i];
+                        }
+                        if let Some(new_scale) = new_scale
+                            && new_scale[i] != scales[0][i]
+                        {
+                            transform.scale[i] = new_scale[i];
+                        }
+                    }
+                    if let Some(new_rotation) = new_rotation {
+                        transform.rotation = new_rotation;
+                    }
+                }
+            });
+        }
+
+        // Components we are rendering manually.
+        let to_avoid_components = HashSet::from([
+            std::any::TypeId::of::<Transform>(),
+            std::any::TypeId::of::<GameEntity>(),
+            std::any::TypeId::of::<EntityParent>(),
+            std::any::TypeId::of::<EntityChildren>(),
+        ]);
+        let mut shared_types = ctx
+            .ecs_world
+            .get_entity_components(selected_entities[0])
+            .into_iter()
+            .map(|ty| ty.type_id)
+            .filter(|type_id| !to_avoid_components.contains(type_id))
+            .collect::<Vec<_>>();
+        for entity in &selected_entities[1..] {
+            let entity_types = ctx
+                .ecs_world
+                .get_entity_components(*entity)
+                .into_iter()
+                .map(|ty| ty.type_id)
+                .collect::<HashSet<_>>();
+            shared_types.retain(|type_id| entity_types.contains(type_id));
+        }
+
+        for type_id in shared_types {
+            let Some(component_name) = ctx
+                .ecs_world
+                .game_components
+                .get(&type_id)
+                .map(|c| c.component_name.clone())
+            else {
+                continue;
+            };
+
+            let mut should_remove = false;
+            Self::component_widget(ui, &component_name, Some(&mut should_remove), |ui| {
+                ui.weak("Select a single entity to edit.");
+            });
+
+            if should_remove {
+                for entity in &selected_entities {
+                    // Safety: We dont use the returned ptr.
+                    unsafe { ctx.ecs_world.try_remove_one_raw(*entity, &type_id) }.unwrap_or_else(
+                        || {
+                            panic!(
+                                "Component {} should exist if it is removable via UI.",
+                                component_name
+                            )
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Shows `field_ui` with the first entity's value, greyed out if the selected entities
+    /// have differing values. Returns the edited value if it was changed.
+    fn shared_field_ui<T: Clone + PartialEq>(
+        ui: &mut egui::Ui,
+        values: &[T],
+        field_ui: impl FnOnce(&mut egui::Ui, &mut T),
+    ) -> Option<T> {
+        let mut value = values[0].clone();
+        let is_mixed = values.iter().any(|other| *other != values[0]);
+        ui.scope(|ui| {
+            if is_mixed {
+                ui.multiply_opacity(0.5);
+            }
+            field_ui(ui, &mut value);
+        });
+        (value != values[0]).then_some(value)
+    }
+
     fn component_widget<R>(
         ui: &mut egui::Ui,
         header: &str,
@@ -800,18 +1171,25 @@ impl EntityPropertiesPane {
                         }
                     }
                 });
-                if ui.button("Delete Entity").clicked() {
-                    ctx.events.push(EntityCommandEvent::Despawn {
-                        entity: *selected_entity,
-                        despawn_children: true,
-                    });
-                    if let Some(EditorVoxelEditingTarget::Entity(target_entity)) =
-                        &ctx.voxel_editing.edit_target
-                        && target_entity == selected_entity
-                    {
-                        ctx.voxel_editing.edit_target = None;
+                let delete_text = if ctx.session.selected_entities().len() > 1 {
+                    "Delete Entities"
+                } else {
+                    "Delete Entity"
+                };
+                if ui.button(delete_text).clicked() {
+                    for selected_entity in ctx.session.selected_entities() {
+                        ctx.events.push(EntityCommandEvent::Despawn {
+                            entity: *selected_entity,
+                            despawn_children: true,
+                        });
+                        if let Some(EditorVoxelEditingTarget::Entity(target_entity)) =
+                            &ctx.voxel_editing.edit_target
+                            && target_entity == selected_entity
+                        {
+                            ctx.voxel_editing.edit_target = None;
+                        }
                     }
-                    ctx.session.selected_entity = None;
+                    ctx.session.select_entity(None);
                 }
             }
         });
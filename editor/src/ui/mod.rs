@@ -15,6 +15,9 @@ pub mod material_selection_dialog;
 pub mod materials_pane;
 pub mod pane;
 pub mod resize_model_dialog;
+pub mod stats_pane;
 pub mod top_bar;
+pub mod turntable_dialog;
+pub mod user_pane;
 pub mod util;
 pub mod world_pane;
@@ -5,7 +5,8 @@ use crate::ui::{
     EditorUIContext, animation_pane::AnimationPane, asset_pane::AssetsPane,
     asset_properties_pane::AssetPropertiesPane, editing_pane::EditingPane,
     entity_hierarchy::EntityHierarchyUI, entity_properties::EntityPropertiesPane,
-    materials_pane::MaterialsPane, world_pane::WorldPane,
+    materials_pane::MaterialsPane, stats_pane::StatsPane, user_pane::UserPane,
+    world_pane::WorldPane,
 };
 
 pub struct EditorUIPaneData {
@@ -96,7 +97,12 @@ impl<'de> serde::de::DeserializeSeed<'de> for EditorUIPaneDataDeserializeSeed {
             AssetPropertiesPane::ID => deserialize_pane::<AssetPropertiesPane, D>(de),
             EditingPane::ID => deserialize_pane::<EditingPane, D>(de),
             AnimationPane::ID => deserialize_pane::<AnimationPane, D>(de),
-            _ => panic!("Unknown pane id: {}", self.id),
+            StatsPane::ID => deserialize_pane::<StatsPane, D>(de),
+            UserPane::ID => deserialize_pane::<UserPane, D>(de),
+            _ => Err(serde::de::Error::custom(format!(
+                "Unknown pane id `{}`.",
+                self.id
+            ))),
         }
     }
 }
@@ -333,6 +339,8 @@ impl EditorUIPane for EditorUITabPane {
             });
         }
 
+        // The saved selected tab may be out of range if the layout was edited by hand.
+        self.selected_tab = self.selected_tab.min(self.tabs.len() - 1);
         self.tabs[self.selected_tab].show(ui, ctx);
     }
 
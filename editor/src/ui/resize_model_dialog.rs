@@ -76,7 +76,7 @@ fn resize_voxel_model_dialog_show_fn(
         ctx.debug_renderer.draw_obb_outline(
             &new_obb,
             OBB_THICKNESS,
-            Color::new_srgba_hex("#FF0000", 1.0),
+            Color::RED.to_srgba(1.0),
             DebugShapeFlags::NONE,
         );
     };
@@ -0,0 +1,469 @@
+use nalgebra::Vector2;
+use rogue_engine::graphics::{
+    backend::{GfxPresentMode, GfxValidation},
+    passes::fxaa_pass::FxaaQuality,
+    renderer::Antialiasing,
+};
+use rogue_engine::settings::GraphicsSettings;
+use strum::VariantArray;
+
+use crate::{
+    render_graph::EditorRenderGraph,
+    ui::{EditorUIContext, pane::EditorUIPane},
+};
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct StatsPane;
+
+impl StatsPane {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl EditorUIPane for StatsPane {
+    const ID: &'static str = "stats";
+    const NAME: &'static str = "Stats";
+
+    fn show(&mut self, ui: &mut egui::Ui, ctx: &mut EditorUIContext<'_>) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("Stats").size(20.0));
+        });
+
+        let frame_ms = ctx.time.delta_time().as_secs_f32() * 1000.0;
+        let pass_timings = ctx.renderer.last_pass_timings().to_vec();
+        egui::CollapsingHeader::new("Frame")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_frame_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("FPS");
+                        ui.label(ctx.time.fps().to_string());
+                        ui.end_row();
+
+                        ui.label("Frame time");
+                        ui.label(format!("{:.2}ms", frame_ms));
+                        ui.end_row();
+
+                        if !pass_timings.is_empty() {
+                            let gpu_ms = pass_timings
+                                .iter()
+                                .map(|timing| timing.duration.as_secs_f32() * 1000.0)
+                                .sum::<f32>();
+                            ui.label("Gpu time");
+                            ui.label(format!("{:.2}ms", gpu_ms));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if !pass_timings.is_empty() {
+            egui::CollapsingHeader::new("Gpu passes")
+                .default_open(true)
+                .show_unindented(ui, |ui| {
+                    egui::Grid::new("stats_gpu_passes_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            // Each bar is the pass's share of the cpu frame time.
+                            for timing in &pass_timings {
+                                let pass_ms = timing.duration.as_secs_f32() * 1000.0;
+                                ui.label(&timing.name);
+                                ui.add(
+                                    egui::ProgressBar::new((pass_ms / frame_ms).clamp(0.0, 1.0))
+                                        .desired_width(120.0)
+                                        .text(format!("{:.2}ms", pass_ms)),
+                                );
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+
+        egui::CollapsingHeader::new("Entities")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_entities_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        // Instances of the same model share its gpu upload.
+                        ui.label("Unique models");
+                        ui.label(ctx.entities_gpu.unique_model_count().to_string());
+                        ui.end_row();
+
+                        ui.label("Instances");
+                        ui.label(ctx.entities_gpu.instance_count().to_string());
+                        ui.end_row();
+                    });
+            });
+
+        egui::CollapsingHeader::new("Shaders")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                let shader_errors = ctx.renderer.shader_errors();
+                if shader_errors.is_empty() {
+                    ui.colored_label(egui::Color32::GREEN, "Shaders OK");
+                    return;
+                }
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!(
+                        "{} shader error(s), using the last working pipelines",
+                        shader_errors.len()
+                    ),
+                );
+                for err in shader_errors {
+                    ui.label(egui::RichText::new(err).monospace());
+                }
+            });
+
+        let pass_names = ctx.renderer.pass_names().to_vec();
+        egui::CollapsingHeader::new("Frame graph passes")
+            .default_open(false)
+            .show_unindented(ui, |ui| {
+                // Disabled passes keep their outputs defined, so toggling a pass shows what it
+                // costs and what it contributes.
+                for pass_name in &pass_names {
+                    let is_ui_pass =
+                        EditorRenderGraph::ui_pass_names().contains(&pass_name.as_str());
+                    let mut enabled = ctx.renderer.is_pass_enabled(pass_name);
+                    if ui
+                        .add_enabled(
+                            !is_ui_pass,
+                            egui::Checkbox::new(&mut enabled, pass_name.as_str()),
+                        )
+                        .changed()
+                    {
+                        ctx.renderer.set_pass_enabled(pass_name, enabled);
+                    }
+                }
+            });
+
+        egui::CollapsingHeader::new("Present")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_present_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let graphics = &mut ctx.settings.graphics;
+                        ui.label("Present mode");
+                        egui::ComboBox::from_id_salt("Present mode")
+                            .selected_text(format!("{:?}", graphics.present_mode))
+                            .show_ui(ui, |ui| {
+                                for val in GfxPresentMode::VARIANTS {
+                                    ui.selectable_value(
+                                        &mut graphics.present_mode,
+                                        *val,
+                                        format!("{:?}", val),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Triple buffering");
+                        ui.checkbox(&mut graphics.triple_buffering, "");
+                        ui.end_row();
+
+                        ui.label("Validation");
+                        egui::ComboBox::from_id_salt("Validation")
+                            .selected_text(format!("{:?}", graphics.validation))
+                            .show_ui(ui, |ui| {
+                                for val in GfxValidation::VARIANTS {
+                                    ui.selectable_value(
+                                        &mut graphics.validation,
+                                        *val,
+                                        format!("{:?}", val),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(format!(
+                                "Applied on restart, {} overrides it.",
+                                GfxValidation::OVERRIDE_ENV_VAR
+                            ));
+                        ui.end_row();
+
+                        ui.label("Frame rate cap");
+                        ui.add(
+                            egui::DragValue::new(&mut ctx.settings.frame_rate_cap)
+                                .range(0..=1000)
+                                .custom_formatter(|n, _| match n as u32 {
+                                    0 => "Uncapped".to_owned(),
+                                    n => n.to_string(),
+                                }),
+                        );
+                        ui.end_row();
+
+                        ui.label("Render scale");
+                        ui.add(
+                            egui::Slider::new(
+                                &mut ctx.settings.graphics.render_scale,
+                                GraphicsSettings::MIN_RENDER_SCALE
+                                    ..=GraphicsSettings::MAX_RENDER_SCALE,
+                            )
+                            .suffix("x"),
+                        )
+                        .on_hover_text("Above 1 supersamples, below 1 upscales to the viewport.");
+                        ui.end_row();
+                    });
+            });
+
+        egui::CollapsingHeader::new("Anti-aliasing")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_antialiasing_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let graphics = &mut ctx.settings.graphics;
+                        // A single mode so taa and fxaa are never layered.
+                        ui.label("Mode");
+                        ui.horizontal(|ui| {
+                            for mode in Antialiasing::VARIANTS {
+                                ui.selectable_value(
+                                    &mut graphics.antialiasing,
+                                    *mode,
+                                    format!("{:?}", mode),
+                                );
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Taa feedback");
+                        ui.add_enabled(
+                            graphics.antialiasing == Antialiasing::TAA,
+                            egui::Slider::new(&mut graphics.taa_feedback, 0.0..=0.98),
+                        );
+                        ui.end_row();
+
+                        ui.label("Fxaa quality");
+                        ui.add_enabled_ui(graphics.antialiasing == Antialiasing::FXAA, |ui| {
+                            egui::ComboBox::from_id_salt("Fxaa quality")
+                                .selected_text(format!("{:?}", graphics.fxaa_quality))
+                                .show_ui(ui, |ui| {
+                                    for quality in FxaaQuality::VARIANTS {
+                                        ui.selectable_value(
+                                            &mut graphics.fxaa_quality,
+                                            *quality,
+                                            format!("{:?}", quality),
+                                        );
+                                    }
+                                });
+                        });
+                        ui.end_row();
+                    });
+            });
+
+        egui::CollapsingHeader::new("Shadows")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_shadows_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let graphics = &mut ctx.settings.graphics;
+                        ui.label("Soft shadows");
+                        ui.checkbox(&mut graphics.soft_shadows, "")
+                            .on_hover_text("Noisy without taa to accumulate the samples.");
+                        ui.end_row();
+
+                        ui.label("Samples");
+                        ui.add_enabled(
+                            graphics.soft_shadows,
+                            egui::DragValue::new(&mut graphics.soft_shadow_samples).range(1..=16),
+                        );
+                        ui.end_row();
+
+                        ui.label("Sun size");
+                        ui.add_enabled(
+                            graphics.soft_shadows,
+                            egui::DragValue::new(&mut graphics.sun_angular_size)
+                                .speed(0.001)
+                                .range(0.0..=0.5)
+                                .suffix(" rad"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Voxel AO");
+                        ui.add(egui::Slider::new(
+                            &mut graphics.voxel_ao_strength,
+                            0.0..=1.0,
+                        ))
+                        .on_hover_text("Baked into models when they are imported.");
+                        ui.end_row();
+                    });
+            });
+
+        egui::CollapsingHeader::new("Depth of field")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                ui.checkbox(&mut ctx.settings.graphics.depth_of_field, "Enabled")
+                    .on_hover_text("Focus and aperture are on the camera component.");
+            });
+
+        egui::CollapsingHeader::new("Bloom")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_bloom_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let graphics = &mut ctx.settings.graphics;
+                        ui.label("Bloom");
+                        ui.checkbox(&mut graphics.bloom, "");
+                        ui.end_row();
+
+                        ui.label("Threshold");
+                        ui.add_enabled(
+                            graphics.bloom,
+                            egui::DragValue::new(&mut graphics.bloom_threshold)
+                                .speed(0.01)
+                                .range(0.0..=f32::MAX),
+                        );
+                        ui.end_row();
+
+                        ui.label("Intensity");
+                        ui.add_enabled(
+                            graphics.bloom,
+                            egui::DragValue::new(&mut graphics.bloom_intensity)
+                                .speed(0.01)
+                                .range(0.0..=4.0),
+                        );
+                        ui.end_row();
+                    });
+            });
+
+        egui::CollapsingHeader::new("Advanced")
+            .default_open(false)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_advanced_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let graphics = &mut ctx.settings.graphics;
+                        ui.label("RT workgroup size");
+                        ui.horizontal(|ui| {
+                            let tuned_size = graphics.rt_workgroup_size;
+                            let mut is_overridden = graphics.rt_workgroup_size_override.is_some();
+                            if ui
+                                .checkbox(&mut is_overridden, "Override")
+                                .on_hover_text(match tuned_size {
+                                    Some(size) => format!("Auto-tuned to {}x{}.", size.x, size.y),
+                                    None => "Not auto-tuned yet.".to_owned(),
+                                })
+                                .changed()
+                            {
+                                graphics.rt_workgroup_size_override =
+                                    is_overridden.then(|| tuned_size.unwrap_or(Vector2::new(8, 8)));
+                            }
+                            if let Some(size) = &mut graphics.rt_workgroup_size_override {
+                                ui.add(egui::DragValue::new(&mut size.x).range(1..=32));
+                                ui.label("x");
+                                ui.add(egui::DragValue::new(&mut size.y).range(1..=32));
+                            }
+                        });
+                        ui.end_row();
+                    });
+            });
+
+        let stats = ctx.device.last_frame_stats();
+        egui::CollapsingHeader::new("Gpu")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_gpu_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let mut row = |label: &str, value: String| {
+                            ui.label(label);
+                            ui.label(value);
+                            ui.end_row();
+                        };
+                        row("Dispatches", stats.dispatches.to_string());
+                        row("Draws", stats.draws.to_string());
+                        row(
+                            "Descriptor set binds",
+                            stats.descriptor_set_binds.to_string(),
+                        );
+                        row(
+                            "Descriptor set writes",
+                            stats.descriptor_set_writes.to_string(),
+                        );
+                        row("Buffer writes", stats.buffer_writes.to_string());
+                        row("Image writes", stats.image_writes.to_string());
+                        row(
+                            "Staging uploaded",
+                            format!("{:.2}KiB", stats.staging_bytes as f64 / 1024.0),
+                        );
+                    });
+            });
+
+        let memory_stats = ctx.device.memory_stats();
+        let mib = |bytes: u64| format!("{:.2}MiB", bytes as f64 / (1024.0 * 1024.0));
+        egui::CollapsingHeader::new("Gpu memory")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_gpu_memory_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let mut row = |label: &str, value: String| {
+                            ui.label(label);
+                            ui.label(value);
+                            ui.end_row();
+                        };
+                        row("Allocated", mib(memory_stats.allocated_bytes));
+                        row("In use", mib(memory_stats.used_bytes));
+                        row(
+                            "Dedicated allocations",
+                            format!(
+                                "{} ({})",
+                                memory_stats.dedicated_allocation_count,
+                                mib(memory_stats.dedicated_bytes)
+                            ),
+                        );
+                    });
+
+                egui::Grid::new("stats_gpu_memory_pools_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (i, pool) in memory_stats.shared_pools.iter().enumerate() {
+                            ui.label(format!("Pool {}", i)).on_hover_text(format!(
+                                "{} allocations, largest free block {} of {} free blocks",
+                                pool.active_allocations,
+                                mib(pool.largest_free_block),
+                                pool.free_block_count
+                            ));
+                            ui.add(
+                                egui::ProgressBar::new(pool.used_bytes as f32 / pool.size as f32)
+                                    .desired_width(120.0)
+                                    .text(format!(
+                                        "{}, {:.0}% fragmented",
+                                        mib(pool.used_bytes),
+                                        pool.fragmentation() * 100.0
+                                    )),
+                            );
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        let generator = &ctx.world_generator;
+        egui::CollapsingHeader::new("Terrain generation")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("stats_terrain_generation_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let mut row = |label: &str, value: String| {
+                            ui.label(label);
+                            ui.label(value);
+                            ui.end_row();
+                        };
+                        row("Queued chunks", generator.queued_chunk_count().to_string());
+                        row(
+                            "Generating chunks",
+                            generator.generating_chunk_count().to_string(),
+                        );
+                        row(
+                            "Completed chunks/s",
+                            generator.completed_chunks_per_second().to_string(),
+                        );
+                    });
+            });
+    }
+}
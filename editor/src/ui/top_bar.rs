@@ -1,4 +1,9 @@
 use rogue_engine::{
+    asset::{
+        asset::{Assets, GameAssetPath},
+        repr::{TextAsset, game_entity::SceneAsset},
+    },
+    consts,
     entity::RenderableVoxelEntity,
     voxel::voxel::VoxelModelEdit,
     world::renderable::rt_pass::{ShadingMode, WorldRTPass},
@@ -8,11 +13,13 @@ use strum::VariantArray;
 use crate::{
     editing::voxel_editing::EditorVoxelEditingTarget,
     game_session::EditorGameSessionEvent,
+    gizmo::GizmoOrientation,
     session::EditorCommandEvent,
     ui::{
-        EditorCommand, EditorUIContext, animation_pane::AnimationPane, asset_pane::AssetsPane,
-        editing_pane::EditingPane, entity_hierarchy::EntityHierarchyUI,
+        EditorCommand, EditorUIContext, FilePickerType, animation_pane::AnimationPane,
+        asset_pane::AssetsPane, editing_pane::EditingPane, entity_hierarchy::EntityHierarchyUI,
         entity_properties::EntityPropertiesPane, materials_pane::MaterialsPane, pane::EditorUIPane,
+        stats_pane::StatsPane, turntable_dialog::turntable_dialog_cmd, user_pane::UserPane,
         world_pane::WorldPane,
     },
 };
@@ -39,8 +46,63 @@ impl TopBarPane {
                     ui.close_menu();
                 }
                 if ui.button("Open").clicked() {}
+                ui.separator();
+                if ui
+                    .add_enabled(
+                        ctx.assets.project_dir().is_some(),
+                        egui::Button::new("Save scene as"),
+                    )
+                    .clicked()
+                {
+                    ctx.commands.push(EditorCommand::FilePicker {
+                        picker_type: FilePickerType::CreateFile,
+                        callback: Box::new(|ctx, asset_path| {
+                            Self::save_scene(&ctx, &GameAssetPath::from_relative_path(&asset_path));
+                        }),
+                        extensions: vec![SceneAsset::EXTENSION.to_owned()],
+                        preset_file_path: None,
+                    });
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(
+                        ctx.assets.project_dir().is_some(),
+                        egui::Button::new("Load scene"),
+                    )
+                    .clicked()
+                {
+                    ctx.commands.push(EditorCommand::FilePicker {
+                        picker_type: FilePickerType::OpenFile,
+                        callback: Box::new(|mut ctx, asset_path| {
+                            Self::load_scene(
+                                &mut ctx,
+                                &GameAssetPath::from_relative_path(&asset_path),
+                            );
+                        }),
+                        extensions: vec![SceneAsset::EXTENSION.to_owned()],
+                        preset_file_path: None,
+                    });
+                    ui.close_menu();
+                }
+            });
+            ui.menu_button("View", |ui| {
+                if ui.button("Capture viewport").clicked() {
+                    ctx.events.push(EditorCommandEvent::CaptureViewport {
+                        supersample_scale: 1,
+                    });
+                    ui.close_menu();
+                }
+                if ui.button("Capture viewport (2x supersampled)").clicked() {
+                    ctx.events.push(EditorCommandEvent::CaptureViewport {
+                        supersample_scale: 2,
+                    });
+                    ui.close_menu();
+                }
+                if ui.button("Export turntable").clicked() {
+                    ctx.commands.push(turntable_dialog_cmd());
+                    ui.close_menu();
+                }
             });
-            ui.menu_button("View", |ui| {});
             ui.menu_button("Open", |ui| {
                 if ui.button("Animation").clicked() {
                     ctx.commands.push(EditorCommand::open_ui(AnimationPane::ID));
@@ -64,6 +126,14 @@ impl TopBarPane {
                     ctx.commands.push(EditorCommand::open_ui(MaterialsPane::ID));
                     ui.close_menu();
                 }
+                if ui.button("Stats").clicked() {
+                    ctx.commands.push(EditorCommand::open_ui(StatsPane::ID));
+                    ui.close_menu();
+                }
+                if ui.button("User").clicked() {
+                    ctx.commands.push(EditorCommand::open_ui(UserPane::ID));
+                    ui.close_menu();
+                }
                 if ui.button("Voxel Editing").clicked() {
                     ctx.commands.push(EditorCommand::open_ui(EditingPane::ID));
                     ui.close_menu();
@@ -155,6 +225,112 @@ impl TopBarPane {
                 ui.label("Show colliders:");
                 ui.checkbox(&mut ctx.session.render_colliders, "");
             });
+            ui.horizontal(|ui| {
+                ui.label("Show chunks:");
+                ui.checkbox(&mut ctx.session.render_chunk_bounds, "");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Show voxels:");
+                ui.checkbox(&mut ctx.session.render_voxel_wireframe, "")
+                    .on_hover_text("Outlines the hovered model's voxels when it's close and small.");
+            });
+            ui.separator();
+            let gizmo = &mut *ctx.gizmo;
+            for (label, orientation) in [
+                ("Move:", &mut gizmo.translation_orientation),
+                ("Rotate:", &mut gizmo.rotation_orientation),
+            ] {
+                ui.label(label);
+                for variant in GizmoOrientation::VARIANTS {
+                    ui.selectable_value(orientation, *variant, variant.to_string());
+                }
+            }
+            ui.separator();
+            let snap = &mut gizmo.snap;
+            ui.checkbox(&mut snap.enabled, "Snap")
+                .on_hover_text("Hold control while dragging the gizmo to snap temporarily.");
+            ui.add(
+                egui::DragValue::new(&mut snap.translation_step)
+                    .range(0.0..=f32::MAX)
+                    .speed(consts::voxel::VOXEL_METER_LENGTH)
+                    .suffix("m"),
+            );
+            ui.drag_angle(&mut snap.rotation_step);
+            snap.rotation_step = snap.rotation_step.max(0.0);
+            ui.separator();
+            let clear_binding = ctx
+                .input
+                .keybinds()
+                .binding(consts::actions::EDITOR_CLEAR_MEASUREMENT)
+                .map_or("Unbound".to_owned(), |binding| binding.to_string());
+            ui.toggle_value(&mut ctx.measure_tool.enabled, "Measure")
+                .on_hover_text(format!(
+                    "Click two points to measure between them, {} clears.",
+                    clear_binding
+                ));
+            ui.checkbox(&mut ctx.measure_tool.snap_to_voxel_centers, "Voxel centers");
+            ui.toggle_value(&mut ctx.session.picking_camera_focus, "Focus")
+                .on_hover_text("Click a voxel to focus the camera's depth of field on it.");
+
+            // Shader errors are otherwise only in the log, the stats pane has the full messages.
+            let shader_errors = ctx.renderer.shader_errors();
+            if !shader_errors.is_empty() {
+                ui.separator();
+                ui.colored_label(ui.visuals().error_fg_color, "Shader error")
+                    .on_hover_text(shader_errors.join("\n\n"));
+            }
+            if let Some(script_error) = ctx.scripts.script_error() {
+                ui.separator();
+                ui.colored_label(ui.visuals().error_fg_color, "Script error")
+                    .on_hover_text(script_error);
+            }
         });
     }
+
+    fn save_scene(ctx: &EditorUIContext<'_>, path: &GameAssetPath) {
+        let Some(project_dir) = ctx.assets.project_dir() else {
+            return;
+        };
+        let result = SceneAsset::serialize(
+            ctx.ecs_world,
+            ctx.voxel_registry,
+            &ctx.physics_world.colliders,
+        )
+        .and_then(|scene| Assets::save_asset_sync(path.as_file_asset_path(project_dir), scene));
+        if let Err(err) = result {
+            log::error!(
+                "Failed to save scene {}: {}",
+                path.as_relative_path_str(),
+                err
+            );
+        }
+    }
+
+    /// Adds a copy of the scene's entities to the world and selects its roots.
+    fn load_scene(ctx: &mut EditorUIContext<'_>, path: &GameAssetPath) {
+        let Some(project_dir) = ctx.assets.project_dir().clone() else {
+            return;
+        };
+        let result = Assets::load_asset_sync::<TextAsset>(path.as_file_asset_path(&project_dir))
+            .map_err(anyhow::Error::from)
+            .and_then(|scene| {
+                SceneAsset::instantiate(
+                    &scene,
+                    ctx.ecs_world,
+                    ctx.voxel_registry,
+                    &mut ctx.physics_world.colliders,
+                )
+            });
+        match result {
+            Ok(roots) => {
+                ctx.session.select_entity(None);
+                ctx.session.extend_selection(roots);
+            }
+            Err(err) => log::error!(
+                "Failed to load scene {}: {}",
+                path.as_relative_path_str(),
+                err
+            ),
+        }
+    }
 }
@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+
+use crate::{
+    turntable::TurntableSettings,
+    ui::{EditorCommand, EditorDialog, EditorUIContext},
+};
+
+const DIALOG_ID: &str = "turntable_dialog";
+
+#[derive(Clone)]
+struct TurntableDialogState {
+    settings: TurntableSettings,
+    output_dir: String,
+}
+
+pub fn turntable_dialog_cmd() -> EditorCommand {
+    EditorCommand::OpenDialog(EditorDialog {
+        id: DIALOG_ID.to_owned(),
+        title: "Export Turntable".to_owned(),
+        show_fn: Box::new(move |ui, ctx| turntable_dialog_show_fn(ui, ctx)),
+    })
+}
+
+fn turntable_dialog_show_fn(ui: &mut egui::Ui, ctx: &mut EditorUIContext) -> bool {
+    let id = egui::Id::new(DIALOG_ID);
+    let mut state = ui.data_mut(|w| {
+        w.get_temp_mut_or_insert_with(id, || {
+            let settings = TurntableSettings::new();
+            TurntableDialogState {
+                output_dir: settings.output_dir.to_string_lossy().to_string(),
+                settings,
+            }
+        })
+        .clone()
+    });
+
+    let is_exporting = ctx.turntable.is_exporting();
+    ui.add_enabled_ui(!is_exporting, |ui| {
+        egui::Grid::new("turntable_dialog_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Frames");
+                ui.add(egui::DragValue::new(&mut state.settings.frame_count).range(1..=3600));
+                ui.end_row();
+
+                ui.label("Resolution");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::DragValue::new(&mut state.settings.resolution.x)
+                            .range(1..=8192)
+                            .suffix("px"),
+                    );
+                    ui.label("x");
+                    ui.add(
+                        egui::DragValue::new(&mut state.settings.resolution.y)
+                            .range(1..=8192)
+                            .suffix("px"),
+                    );
+                });
+                ui.end_row();
+
+                ui.label("Output directory");
+                ui.text_edit_singleline(&mut state.output_dir);
+                ui.end_row();
+            });
+    });
+    state.settings.output_dir = PathBuf::from(state.output_dir.trim());
+
+    if let Some((saved_frames, frame_count)) = ctx.turntable.progress() {
+        ui.add(
+            egui::ProgressBar::new(saved_frames as f32 / frame_count as f32)
+                .text(format!("{}/{} frames", saved_frames, frame_count)),
+        );
+    }
+
+    let mut close_requested = false;
+    ui.horizontal(|ui| {
+        if is_exporting {
+            if ui.button("Cancel").clicked() {
+                ctx.turntable.cancel();
+            }
+        } else {
+            let can_start =
+                ctx.session.is_editor_camera_focused() && !state.output_dir.trim().is_empty();
+            if ui
+                .add_enabled(can_start, egui::Button::new("Export"))
+                .clicked()
+            {
+                ctx.turntable
+                    .start(state.settings.clone(), ctx.session, ctx.ecs_world);
+            }
+
+            if ui.button("Close").clicked() {
+                close_requested = true;
+            }
+        }
+    });
+
+    // Preserve state.
+    ui.data_mut(|w| {
+        w.insert_temp(id, state);
+    });
+    return close_requested;
+}
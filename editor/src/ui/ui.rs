@@ -7,7 +7,10 @@ use crate::{
     animation_preview::EditorAnimationPreviewer,
     editing::voxel_editing::EditorVoxelEditing,
     game_session::EditorGameSession,
+    gizmo::EditorGizmo,
+    measure_tool::EditorMeasureTool,
     session::EditorSession,
+    turntable::EditorTurntable,
     ui::{
         animation_pane::AnimationPane,
         asset_pane::AssetsPane,
@@ -21,7 +24,9 @@ use crate::{
             EditorUIContentPane, EditorUIPane, EditorUIPaneData, EditorUIPaneMethods,
             EditorUITabPane,
         },
+        stats_pane::StatsPane,
         top_bar::TopBarPane,
+        user_pane::UserPane,
         world_pane::WorldPane,
     },
     world::generator::WorldGenerator,
@@ -29,22 +34,30 @@ use crate::{
 use nalgebra::{Vector2, Vector4};
 use rogue_engine::material::material_bank::MaterialBank;
 use rogue_engine::world::terrain::region_map::RegionMap;
+use rogue_engine::world::terrain::terrain_generator::TerrainGeneratorSettings;
 use rogue_engine::{
     animation::animation_bank::AnimationBank,
-    asset::asset::Assets,
+    asset::{
+        asset::{Assets, GameAssetPath},
+        repr::game_entity::PrefabAsset,
+    },
     debug::debug_renderer::DebugRenderer,
     egui::Egui,
-    entity::ecs_world::ECSWorld,
+    entity::{ecs_world::ECSWorld, scripting::Scripts},
     event::Events,
-    graphics::camera::MainCamera,
+    graphics::{camera::MainCamera, device::DeviceResource, renderer::Renderer},
+    input::Input,
     physics::physics_world::{self, PhysicsWorld},
     resource::{Res, ResMut, Resource},
+    settings::Settings,
     voxel::voxel_registry::VoxelModelRegistry,
-    window::window::Window,
-    world::{renderable::rt_pass::WorldRTPass, sky::Sky},
+    window::{time::Time, window::Window},
+    world::{
+        renderable::rt_pass::WorldRTPass, sky::SkyLight, world_entities_gpu::WorldEntitiesGpu,
+    },
 };
 use rogue_macros::Resource;
-use serde_with::serde_as;
+use serde_with::DeserializeAs;
 
 /// Context that we pass down to every component so we don't have 10 argument functions.
 pub struct EditorUIContext<'a> {
@@ -58,15 +71,26 @@ pub struct EditorUIContext<'a> {
     pub region_map: &'a mut RegionMap,
     pub events: &'a mut Events,
     pub world_generator: &'a mut WorldGenerator,
+    pub terrain_generator: &'a mut TerrainGeneratorSettings,
     pub assets: &'a mut Assets,
     pub commands: &'a mut EditorCommands,
-    pub sky: &'a mut Sky,
+    pub sky_light: &'a mut SkyLight,
     pub ui_state: &'a mut GlobalStateEditorUI,
     pub voxel_editing: &'a mut EditorVoxelEditing,
     pub debug_renderer: &'a mut DebugRenderer,
     pub world_rt_pass: &'a mut WorldRTPass,
     pub animation_preview: &'a mut EditorAnimationPreviewer,
     pub animation_bank: &'a mut AnimationBank,
+    pub turntable: &'a mut EditorTurntable,
+    pub gizmo: &'a mut EditorGizmo,
+    pub measure_tool: &'a mut EditorMeasureTool,
+    pub entities_gpu: &'a WorldEntitiesGpu,
+    pub device: &'a DeviceResource,
+    pub renderer: &'a mut Renderer,
+    pub time: &'a Time,
+    pub input: &'a mut Input,
+    pub settings: &'a mut Settings,
+    pub scripts: &'a Scripts,
 }
 
 pub struct EditorCommands {
@@ -244,14 +268,13 @@ impl EditorFilePicker {
     }
 }
 
-#[serde_as]
 #[derive(Resource, serde::Serialize, serde::Deserialize)]
 #[serde(default = "EditorUI::new")]
 pub struct EditorUI {
     /// Top, bottom, left, right
     content_padding: Vector4<u32>,
 
-    #[serde_as(deserialize_as = "serde_with::DefaultOnError")]
+    #[serde(deserialize_with = "EditorUI::deserialize_side_panes")]
     side_panes: [Option<EditorUIPaneData>; EditorSide::COUNT],
     global_state: GlobalStateEditorUI,
 
@@ -281,6 +304,22 @@ impl EditorUI {
         sides
     }
 
+    /// Falls back to the default layout if the saved one can't be restored, such as when it
+    /// references a pane which no longer exists.
+    fn deserialize_side_panes<'de, D>(
+        deserializer: D,
+    ) -> Result<[Option<EditorUIPaneData>; EditorSide::COUNT], D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let side_panes: Option<[Option<EditorUIPaneData>; EditorSide::COUNT]> =
+            <serde_with::DefaultOnError as DeserializeAs<'de, _>>::deserialize_as(deserializer)?;
+        Ok(side_panes.unwrap_or_else(|| {
+            log::warn!("Couldn't restore the saved editor layout, using the default layout.");
+            Self::default_panes()
+        }))
+    }
+
     pub fn content_padding(&self) -> &Vector4<u32> {
         &self.content_padding
     }
@@ -309,14 +348,41 @@ impl EditorUI {
         mut main_camera: ResMut<MainCamera>,
         mut region_map: ResMut<RegionMap>,
         mut world_generator: ResMut<WorldGenerator>,
-        mut sky: ResMut<Sky>,
+        mut sky_light: ResMut<SkyLight>,
         mut voxel_editing: ResMut<EditorVoxelEditing>,
         mut debug_renderer: ResMut<DebugRenderer>,
-        (mut game_session, mut world_rt_pass, mut animation_preview, mut animation_bank): (
+        (
+            mut game_session,
+            mut world_rt_pass,
+            mut animation_preview,
+            mut animation_bank,
+            mut turntable,
+            device,
+            mut renderer,
+            time,
+            mut input,
+            mut terrain_generator,
+            mut settings,
+            mut gizmo,
+            mut measure_tool,
+            entities_gpu,
+            scripts,
+        ): (
             ResMut<EditorGameSession>,
             ResMut<WorldRTPass>,
             ResMut<EditorAnimationPreviewer>,
             ResMut<AnimationBank>,
+            ResMut<EditorTurntable>,
+            Res<DeviceResource>,
+            ResMut<Renderer>,
+            Res<Time>,
+            ResMut<Input>,
+            ResMut<TerrainGeneratorSettings>,
+            ResMut<Settings>,
+            ResMut<EditorGizmo>,
+            ResMut<EditorMeasureTool>,
+            Res<WorldEntitiesGpu>,
+            Res<Scripts>,
         ),
     ) {
         let editor_ui = &mut *editor_ui;
@@ -338,13 +404,24 @@ impl EditorUI {
                 main_camera: &mut main_camera,
                 region_map: &mut region_map,
                 world_generator: &mut world_generator,
-                sky: &mut sky,
+                terrain_generator: &mut terrain_generator,
+                sky_light: &mut sky_light,
                 ui_state: &mut editor_ui.global_state,
                 voxel_editing: &mut voxel_editing,
                 debug_renderer: &mut debug_renderer,
                 world_rt_pass: &mut world_rt_pass,
                 animation_preview: &mut animation_preview,
                 animation_bank: &mut animation_bank,
+                turntable: &mut turntable,
+                gizmo: &mut gizmo,
+                measure_tool: &mut measure_tool,
+                entities_gpu: &entities_gpu,
+                device: &device,
+                renderer: &mut renderer,
+                time: &time,
+                input: &mut input,
+                settings: &mut settings,
+                scripts: &scripts,
             };
             let default_padding = editor_ui
                 .content_padding
@@ -405,6 +482,30 @@ impl EditorUI {
 
             editor_ui.content_padding = (padding * ctx.pixels_per_point()).map(|x| x as u32);
 
+            // Prefabs dragged from the asset pane and dropped onto the viewport, other payloads
+            // are left for whatever they were dragged to.
+            if !ctx.is_pointer_over_area()
+                && ctx.input(|i| i.pointer.any_released())
+                && egui::DragAndDrop::payload::<GameAssetPath>(ctx)
+                    .is_some_and(|path| path.extension() == PrefabAsset::EXTENSION)
+                && let Some(path) = egui::DragAndDrop::take_payload::<GameAssetPath>(ctx)
+            {
+                EntityHierarchyUI::instantiate_prefab(&mut res_ctx, &path, None);
+            }
+
+            if let Some(snap_label) = res_ctx.gizmo.active_snap_label()
+                && let Some(pointer_pos) = ctx.pointer_latest_pos()
+            {
+                egui::Area::new(egui::Id::new("gizmo_snap_overlay"))
+                    .fixed_pos(pointer_pos + egui::vec2(16.0, 16.0))
+                    .interactable(false)
+                    .show(ctx, |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.label(snap_label);
+                        });
+                    });
+            }
+
             // Render any open dialogs
             let mut to_close_indices = Vec::new();
             for (i, EditorDialog { title, show_fn, id }) in
@@ -442,13 +543,24 @@ impl EditorUI {
             main_camera: &mut main_camera,
             region_map: &mut region_map,
             world_generator: &mut world_generator,
-            sky: &mut sky,
+            terrain_generator: &mut terrain_generator,
+            sky_light: &mut sky_light,
             ui_state: &mut editor_ui.global_state,
             voxel_editing: &mut voxel_editing,
             debug_renderer: &mut debug_renderer,
             world_rt_pass: &mut world_rt_pass,
             animation_preview: &mut animation_preview,
             animation_bank: &mut animation_bank,
+            turntable: &mut turntable,
+            gizmo: &mut gizmo,
+            measure_tool: &mut measure_tool,
+            entities_gpu: &entities_gpu,
+            device: &device,
+            renderer: &mut renderer,
+            time: &time,
+            input: &mut input,
+            settings: &mut settings,
+            scripts: &scripts,
         };
         editor_ui.file_picker.update(res_ctx);
 
@@ -508,6 +620,8 @@ impl EditorUI {
                 }
                 MaterialsPane::ID => self.spawn_pane(MaterialsPane::new(), EditorSide::Right),
                 WorldPane::ID => self.spawn_pane(WorldPane::new(), EditorSide::Right),
+                StatsPane::ID => self.spawn_pane(StatsPane::new(), EditorSide::Right),
+                UserPane::ID => self.spawn_pane(UserPane::new(), EditorSide::Right),
                 AssetsPane::ID => self.spawn_pane(AssetsPane::new(), EditorSide::Left),
                 AssetPropertiesPane::ID => {
                     self.spawn_pane(AssetPropertiesPane::new(), EditorSide::Right)
@@ -0,0 +1,235 @@
+use rogue_engine::input::{
+    keyboard, mouse,
+    mapper::{ActionBinding, ActionInput, ActionModifiers, Keybinds},
+};
+
+use crate::{
+    session::EditorCommandEvent,
+    ui::{EditorUIContext, pane::EditorUIPane},
+};
+
+/// User specific settings such as keybinds, saved with the editor settings.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct UserPane {
+    /// The action waiting on a key, mouse button or gamepad button press to be rebound.
+    #[serde(skip)]
+    capturing_action: Option<String>,
+}
+
+impl UserPane {
+    pub fn new() -> Self {
+        Self {
+            capturing_action: None,
+        }
+    }
+
+    /// "editor_gizmo_rotation" -> "Editor gizmo rotation"
+    fn action_display_name(action: &str) -> String {
+        let name = action.replace('_', " ");
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => name,
+        }
+    }
+
+    /// Rebinds the capturing action to the first key or mouse button pressed along with the held
+    /// modifiers, or the first gamepad button pressed. Modifier keys on their own wait for the
+    /// rest of the chord and the left mouse button is left for the ui.
+    fn capture_binding(&mut self, ctx: &mut EditorUIContext<'_>) {
+        let Some(action) = &self.capturing_action else {
+            return;
+        };
+        let keyboard = ctx.input.keyboard();
+        let is_modifier_key = |key: &keyboard::Key| {
+            keyboard::Modifier::ALL
+                .iter()
+                .any(|modifier| modifier.get_keys().contains(key))
+        };
+        let pressed_key = keyboard
+            .pressed_keys()
+            .find(|key| !is_modifier_key(key))
+            .copied();
+        let binding = match pressed_key {
+            Some(keyboard::Key::Escape) => None,
+            Some(key) => Some(ActionBinding::key(key)),
+            None => {
+                let mouse_button = ctx
+                    .input
+                    .mouse()
+                    .pressed_buttons()
+                    .find(|button| **button != mouse::Button::Left)
+                    .copied();
+                let gamepad_button = ctx
+                    .input
+                    .gamepad()
+                    .primary_player()
+                    .and_then(|player| player.pressed_buttons().next().copied());
+                match (mouse_button, gamepad_button) {
+                    (Some(button), _) => Some(ActionBinding::mouse_button(button)),
+                    (None, Some(button)) => Some(ActionBinding::gamepad_button(button)),
+                    (None, None) => return,
+                }
+            }
+        };
+
+        if let Some(mut binding) = binding {
+            if !matches!(binding.input, ActionInput::GamepadButton(_)) {
+                binding.modifiers = ActionModifiers::held(ctx.input.keyboard());
+            }
+            ctx.input.keybinds_mut().set_binding(action, binding);
+            ctx.events.push(EditorCommandEvent::SaveEditorSettings);
+        }
+        self.capturing_action = None;
+    }
+}
+
+impl EditorUIPane for UserPane {
+    const ID: &'static str = "user";
+    const NAME: &'static str = "User";
+
+    fn show(&mut self, ui: &mut egui::Ui, ctx: &mut EditorUIContext<'_>) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("User").size(20.0));
+        });
+
+        self.capture_binding(ctx);
+
+        egui::CollapsingHeader::new("Keybinds")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("user_keybinds_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (action, _) in Keybinds::DEFAULT_KEYBINDS {
+                            let keybinds = ctx.input.keybinds();
+                            let Some(binding) = keybinds.binding(action) else {
+                                continue;
+                            };
+
+                            ui.label(Self::action_display_name(action));
+                            ui.horizontal(|ui| {
+                                let is_capturing = self.capturing_action.as_deref() == Some(action);
+                                let text = if is_capturing {
+                                    "Press a key...".to_owned()
+                                } else {
+                                    binding.to_string()
+                                };
+                                let button = ui.add(egui::Button::new(text).selected(is_capturing));
+                                if button.clicked() {
+                                    self.capturing_action = Some(action.to_owned());
+                                }
+                                if is_capturing {
+                                    button.on_hover_text("Press escape to cancel.");
+                                }
+
+                                let conflicts = keybinds
+                                    .conflicting_actions(action, binding)
+                                    .map(Self::action_display_name)
+                                    .collect::<Vec<_>>();
+                                if !conflicts.is_empty() {
+                                    ui.colored_label(
+                                        ui.visuals().warn_fg_color,
+                                        format!("Conflicts with {}", conflicts.join(", ")),
+                                    );
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+
+                if ui.button("Reset to defaults").clicked() {
+                    ctx.input.keybinds_mut().reset_to_defaults();
+                    ctx.events.push(EditorCommandEvent::SaveEditorSettings);
+                    self.capturing_action = None;
+                }
+            });
+
+        egui::CollapsingHeader::new("Mouse")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("user_mouse_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let settings = &mut *ctx.settings;
+                        for (label, sensitivity) in [
+                            (
+                                "Editor sensitivity:",
+                                &mut settings.editor_mouse_sensitivity,
+                            ),
+                            ("Game sensitivity:", &mut settings.game_mouse_sensitivity),
+                        ] {
+                            ui.label(label);
+                            let response = ui.add(
+                                egui::DragValue::new(sensitivity)
+                                    .range(0.0..=0.01)
+                                    .speed(0.00001)
+                                    .fixed_decimals(5),
+                            );
+                            if response.drag_stopped() || response.lost_focus() {
+                                ctx.events.push(EditorCommandEvent::SaveEditorSettings);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        egui::CollapsingHeader::new("Camera shake")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new("user_camera_shake_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let settings = &mut *ctx.settings;
+                        ui.label("Decay per second:");
+                        let decay = ui.add(
+                            egui::DragValue::new(&mut settings.camera_shake_decay)
+                                .range(0.0..=10.0)
+                                .speed(0.01),
+                        );
+                        ui.end_row();
+                        ui.label("Max angle:");
+                        let max_angle = ui.drag_angle(&mut settings.camera_shake_max_angle);
+                        settings.camera_shake_max_angle = settings.camera_shake_max_angle.max(0.0);
+                        ui.end_row();
+                        ui.label("Max offset:");
+                        let max_offset = ui.add(
+                            egui::DragValue::new(&mut settings.camera_shake_max_offset)
+                                .range(0.0..=2.0)
+                                .speed(0.01)
+                                .suffix("m"),
+                        );
+                        ui.end_row();
+                        if [decay, max_angle, max_offset]
+                            .iter()
+                            .any(|response| response.drag_stopped() || response.lost_focus())
+                        {
+                            ctx.events.push(EditorCommandEvent::SaveEditorSettings);
+                        }
+                    });
+            });
+
+        egui::CollapsingHeader::new("Gamepad")
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Deadzone:");
+                    let deadzone = ui.add(
+                        egui::Slider::new(&mut ctx.settings.gamepad_deadzone, 0.0..=0.9)
+                            .fixed_decimals(2),
+                    );
+                    if deadzone.drag_stopped() || deadzone.lost_focus() {
+                        ctx.events.push(EditorCommandEvent::SaveEditorSettings);
+                    }
+                });
+
+                let mut players = ctx.input.gamepad().players().peekable();
+                if players.peek().is_none() {
+                    ui.label("No gamepads connected.");
+                }
+                for (player_index, player) in players {
+                    ui.label(format!("Player {}: {}", player_index + 1, player.name()));
+                }
+            });
+    }
+}
@@ -1,7 +1,17 @@
+use std::f32::consts::PI;
+
 use rogue_engine::{
     asset::asset::GameAssetPath,
+    common::color::Color,
     egui::egui_util,
-    world::{sky::Sky, terrain::region_map::RegionMapCommandEvent},
+    noise::fbm::FbmOptions,
+    world::{
+        sky::SkyLight,
+        terrain::{
+            region_map::RegionMapCommandEvent,
+            terrain_generator::{MaterialBand, TerrainGeneratorSettings, TerrainNoiseLayer},
+        },
+    },
 };
 
 use crate::ui::{EditorCommand, FilePickerType, pane::EditorUIPane};
@@ -57,22 +67,202 @@ impl WorldPane {
                         });
                     }
                 });
+                egui::CollapsingHeader::new("Generator")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        Self::show_terrain_generator(ui, ctx.terrain_generator);
+                    });
             });
         egui::CollapsingHeader::new("Sky")
             .default_open(true)
             .show_unindented(ui, |ui| {
+                Self::show_sky_light(ui, ctx.sky_light);
+            });
+    }
+
+    fn show_sky_light(ui: &mut egui::Ui, sky_light: &mut SkyLight) {
+        ui.horizontal(|ui| {
+            ui.label("Sun azimuth");
+            ui.drag_angle(&mut sky_light.sun_azimuth);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Sun elevation");
+            ui.add(
+                egui::Slider::new(&mut sky_light.sun_elevation, -PI..=PI)
+                    .custom_formatter(|n, _| format!("{:.0}°", n.to_degrees())),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Do day/night cycle");
+            ui.checkbox(&mut sky_light.do_day_night_cycle, "");
+        });
+        Self::show_color_edit(ui, "Sun color", &mut sky_light.sun_color);
+        ui.horizontal(|ui| {
+            ui.label("Sun intensity");
+            ui.add(
+                egui::DragValue::new(&mut sky_light.sun_intensity)
+                    .speed(0.01)
+                    .range(0.0..=16.0),
+            );
+        });
+        Self::show_color_edit(ui, "Zenith color", &mut sky_light.zenith_color);
+        Self::show_color_edit(ui, "Horizon color", &mut sky_light.horizon_color);
+    }
+
+    fn show_color_edit(ui: &mut egui::Ui, label: &str, color: &mut Color) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            let mut rgb = [color.r_u8(), color.g_u8(), color.b_u8()];
+            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                color.set_rgb_u8(rgb[0], rgb[1], rgb[2]);
+            }
+        });
+    }
+
+    fn show_terrain_generator(ui: &mut egui::Ui, settings: &mut TerrainGeneratorSettings) {
+        ui.horizontal(|ui| {
+            ui.label("Seed");
+            ui.add(egui::DragValue::new(&mut settings.seed));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Base material");
+            ui.text_edit_singleline(&mut settings.base_material);
+        });
+
+        let mut removed_layer = None;
+        for (i, layer) in settings.layers.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
                 ui.horizontal(|ui| {
-                    ui.label("Time of day");
+                    ui.label(egui::RichText::new(layer.name()).strong());
+                    if ui.button("Remove").clicked() {
+                        removed_layer = Some(i);
+                    }
+                });
+                ui.indent("layer", |ui| Self::show_noise_layer(ui, layer));
+            });
+        }
+        if let Some(i) = removed_layer {
+            settings.layers.remove(i);
+        }
+
+        ui.menu_button("Add layer", |ui| {
+            let new_layer = if ui.button("Height field").clicked() {
+                Some(TerrainNoiseLayer::new_height_field())
+            } else if ui.button("Caves").clicked() {
+                Some(TerrainNoiseLayer::new_caves())
+            } else if ui.button("Material bands").clicked() {
+                Some(TerrainNoiseLayer::MaterialBands { bands: Vec::new() })
+            } else {
+                None
+            };
+            if let Some(new_layer) = new_layer {
+                settings.layers.push(new_layer);
+                ui.close_menu();
+            }
+        });
+    }
+
+    fn show_noise_layer(ui: &mut egui::Ui, layer: &mut TerrainNoiseLayer) {
+        match layer {
+            TerrainNoiseLayer::HeightField {
+                frequency,
+                base_height,
+                amplitude,
+                fbm,
+            } => {
+                ui.horizontal(|ui| {
+                    ui.label("Frequency");
                     ui.add(
-                        egui::Slider::new(&mut ctx.sky.time_of_day_secs, 0.0..=Sky::SECS_PER_DAY)
-                            .show_value(true),
+                        egui::DragValue::new(frequency)
+                            .speed(0.0001)
+                            .range(0.0..=1.0),
                     );
                 });
                 ui.horizontal(|ui| {
-                    ui.label("Do day/night cycle");
-                    ui.checkbox(&mut ctx.sky.do_day_night_cycle, "");
+                    ui.label("Base height");
+                    ui.add(egui::DragValue::new(base_height).speed(0.1).suffix("m"));
                 });
-            });
+                ui.horizontal(|ui| {
+                    ui.label("Amplitude");
+                    ui.add(egui::DragValue::new(amplitude).speed(0.1).suffix("m"));
+                });
+                Self::show_fbm_options(ui, fbm);
+            }
+            TerrainNoiseLayer::Caves {
+                frequency,
+                threshold,
+                fbm,
+            } => {
+                ui.horizontal(|ui| {
+                    ui.label("Frequency");
+                    ui.add(
+                        egui::DragValue::new(frequency)
+                            .speed(0.0001)
+                            .range(0.0..=1.0),
+                    );
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Threshold");
+                    ui.add(
+                        egui::DragValue::new(threshold)
+                            .speed(0.01)
+                            .range(-1.0..=1.0),
+                    );
+                });
+                Self::show_fbm_options(ui, fbm);
+            }
+            TerrainNoiseLayer::MaterialBands { bands } => {
+                let mut removed_band = None;
+                for (i, band) in bands.iter_mut().enumerate() {
+                    ui.push_id(i, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut band.min_height)
+                                    .speed(0.1)
+                                    .prefix("min height: ")
+                                    .suffix("m"),
+                            );
+                            ui.text_edit_singleline(&mut band.material);
+                            if ui.button("Remove").clicked() {
+                                removed_band = Some(i);
+                            }
+                        });
+                    });
+                }
+                if let Some(i) = removed_band {
+                    bands.remove(i);
+                }
+                if ui.button("Add band").clicked() {
+                    bands.push(MaterialBand {
+                        min_height: 0.0,
+                        material: String::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn show_fbm_options(ui: &mut egui::Ui, fbm: &mut FbmOptions) {
+        ui.horizontal(|ui| {
+            ui.label("Octaves");
+            ui.add(egui::DragValue::new(&mut fbm.octaves).range(1..=16));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Lacunarity");
+            ui.add(
+                egui::DragValue::new(&mut fbm.lacunarity)
+                    .speed(0.01)
+                    .range(1.0..=4.0),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Gain");
+            ui.add(
+                egui::DragValue::new(&mut fbm.gain)
+                    .speed(0.01)
+                    .range(0.0..=1.0),
+            );
+        });
     }
 }
 
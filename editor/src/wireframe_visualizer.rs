@@ -0,0 +1,193 @@
+use nalgebra::Vector3;
+use rogue_engine::{
+    common::{
+        color::Color,
+        geometry::{aabb::AABB, obb::OBB},
+    },
+    consts,
+    debug::debug_renderer::{DebugRenderer, DebugShapeFlags},
+    entity::{
+        RenderableVoxelEntity,
+        ecs_world::{ECSWorld, Entity},
+    },
+    physics::transform::Transform,
+    resource::{Res, ResMut},
+    voxel::voxel_registry::VoxelModelRegistry,
+    world::terrain::region_map::RegionMap,
+};
+
+use crate::{editing::voxel_editing::EditorVoxelEditing, session::EditorSession};
+
+/// Draws terrain chunk bounds and the voxel edges of the hovered model as wireframe for
+/// debugging model topology.
+pub struct WireframeVisualizer;
+
+impl WireframeVisualizer {
+    /// Chunks further than this from the editor camera aren't drawn.
+    const MAX_CHUNK_DISTANCE: f32 = 256.0;
+    /// Models further than this from the editor camera don't get their voxels drawn.
+    const MAX_VOXEL_MODEL_DISTANCE: f32 = 16.0;
+    /// Models with more voxels than this aren't scanned for surface voxels at all.
+    const MAX_VOXEL_MODEL_VOLUME: u64 = 64 * 64 * 64;
+    /// Stop drawing voxels past this many so a large model doesn't draw millions of edges.
+    const MAX_DRAWN_VOXELS: usize = 4096;
+
+    pub fn visualize_wireframes(
+        mut debug_renderer: ResMut<DebugRenderer>,
+        session: Res<EditorSession>,
+        ecs_world: Res<ECSWorld>,
+        voxel_registry: Res<VoxelModelRegistry>,
+        region_map: Res<RegionMap>,
+        voxel_editing: Res<EditorVoxelEditing>,
+    ) {
+        if !session.render_chunk_bounds && !session.render_voxel_wireframe {
+            return;
+        }
+        if voxel_editing.is_enabled() || !session.is_editor_camera_focused() {
+            return;
+        }
+        let editor_camera = session.editor_camera();
+        let camera_position = ecs_world
+            .get::<&Transform>(editor_camera)
+            .expect("Editor camera should have a transform")
+            .position;
+
+        if session.render_chunk_bounds {
+            Self::draw_chunk_bounds(&mut debug_renderer, &region_map, camera_position);
+        }
+        if session.render_voxel_wireframe
+            && let Some(hit) = session.entity_raycast()
+        {
+            Self::draw_entity_voxels(
+                &mut debug_renderer,
+                &ecs_world,
+                &voxel_registry,
+                hit.entity,
+                camera_position,
+            );
+        }
+    }
+
+    fn draw_chunk_bounds(
+        debug_renderer: &mut DebugRenderer,
+        region_map: &RegionMap,
+        camera_position: Vector3<f32>,
+    ) {
+        let full_res_color = Color::new_srgba_hex("#4FC3F7", 1.0);
+        let lod_color = Color::new_srgba_hex("#7E57C2", 1.0);
+        for region in region_map.regions.values() {
+            for chunk_id in region.chunk_ids() {
+                let aabb = region_map.chunk_aabb(&chunk_id);
+                if Self::distance_to_aabb(camera_position, &aabb) > Self::MAX_CHUNK_DISTANCE {
+                    continue;
+                }
+
+                let leaf_chunk_length = chunk_id.chunk_lod.leaf_chunk_length() as f32;
+                let color = if leaf_chunk_length == 1.0 {
+                    full_res_color
+                } else {
+                    lod_color
+                };
+                debug_renderer.draw_aabb(
+                    &aabb,
+                    0.02 * leaf_chunk_length,
+                    color,
+                    DebugShapeFlags::DEPTH_TEST,
+                );
+            }
+        }
+    }
+
+    /// Draws the outline of every voxel of the entity's model that has an empty neighbour, the
+    /// interior voxels can't be seen anyways.
+    fn draw_entity_voxels(
+        debug_renderer: &mut DebugRenderer,
+        ecs_world: &ECSWorld,
+        voxel_registry: &VoxelModelRegistry,
+        entity: Entity,
+        camera_position: Vector3<f32>,
+    ) {
+        let Ok(local_transform) = ecs_world.get::<&Transform>(entity) else {
+            return;
+        };
+        let Some(model_id) = ecs_world
+            .get::<&RenderableVoxelEntity>(entity)
+            .ok()
+            .and_then(|renderable| renderable.voxel_model_id())
+        else {
+            return;
+        };
+        let model = voxel_registry.get_dyn_model(model_id);
+        if model.volume() > Self::MAX_VOXEL_MODEL_VOLUME {
+            return;
+        }
+        let world_transform = ecs_world.get_world_transform(entity, &local_transform);
+        let side_length = model.length();
+        let model_aabb = world_transform
+            .as_voxel_model_obb(side_length)
+            .bounding_aabb();
+        if Self::distance_to_aabb(camera_position, &model_aabb) > Self::MAX_VOXEL_MODEL_DISTANCE {
+            return;
+        }
+
+        let is_solid = |position: Vector3<i32>| {
+            model.in_bounds(position) && model.get_voxel(position.map(|x| x as u32)).is_some()
+        };
+        let voxel_length = world_transform.scale * consts::voxel::VOXEL_METER_LENGTH;
+        let line_radius = 0.005 * world_transform.scale.min();
+        let color = Color::new_srgba_hex("#FFFFFF", 0.6);
+        let mut drawn_voxels = 0;
+        for x in 0..side_length.x as i32 {
+            for y in 0..side_length.y as i32 {
+                for z in 0..side_length.z as i32 {
+                    let position = Vector3::new(x, y, z);
+                    if !is_solid(position) {
+                        continue;
+                    }
+                    let is_surface = [
+                        Vector3::x(),
+                        -Vector3::x(),
+                        Vector3::y(),
+                        -Vector3::y(),
+                        Vector3::z(),
+                        -Vector3::z(),
+                    ]
+                    .into_iter()
+                    .any(|dir| !is_solid(position + dir));
+                    if !is_surface {
+                        continue;
+                    }
+                    if drawn_voxels == Self::MAX_DRAWN_VOXELS {
+                        return;
+                    }
+                    drawn_voxels += 1;
+
+                    // Models are centered on their entity's position.
+                    let voxel_offset = (position.cast::<f32>() + Vector3::repeat(0.5)
+                        - side_length.cast::<f32>() * 0.5)
+                        .component_mul(&voxel_length);
+                    let center = world_transform.position
+                        + world_transform.rotation.transform_vector(&voxel_offset);
+                    let obb = OBB::new(
+                        AABB::new_center_extents(center, voxel_length * 0.5),
+                        world_transform.rotation,
+                        Vector3::zeros(),
+                    );
+                    debug_renderer.draw_obb_outline(
+                        &obb,
+                        line_radius,
+                        color,
+                        DebugShapeFlags::DEPTH_TEST,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Zero when `point` is inside `aabb`.
+    fn distance_to_aabb(point: Vector3<f32>, aabb: &AABB) -> f32 {
+        let closest_point =
+            point.zip_zip_map(&aabb.min, &aabb.max, |x, min, max| x.clamp(min, max));
+        (closest_point - point).norm()
+    }
+}
@@ -1,54 +1,49 @@
 use std::{
-    collections::{HashMap, HashSet},
-    path::Path,
+    collections::{HashSet, VecDeque},
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use nalgebra::Vector3;
-use rand::{Rng, SeedableRng};
-use rogue_engine::material::{
-    material_bank::{MaterialBank, MaterialId},
-    model_material_map::ModelMaterialMap,
-};
+use rogue_engine::material::{material_bank::MaterialBank, model_material_map::ModelMaterialMap};
 use rogue_engine::world::terrain::chunk_lod::ChunkLOD;
 use rogue_engine::world::terrain::chunk_pos::ChunkPos;
 use rogue_engine::world::terrain::region::RegionTree;
 use rogue_engine::world::terrain::region_map::{ChunkId, RegionMap};
+use rogue_engine::world::terrain::terrain_generator::{TerrainGeneratorSettings, TerrainNoiseStack};
 use rogue_engine::{
-    asset::asset::GameAssetPath,
     consts,
+    entity::ecs_world::ECSWorld,
     event::{EventReader, Events},
+    graphics::camera::MainCamera,
     input::{Input, keyboard::Key},
-    noise::{
-        fbm::{Fbm, FbmOptions},
-        perlin::PerlinNoise,
-    },
+    physics::transform::Transform,
     resource::{Res, ResMut},
     task::tasks::Tasks,
     voxel::{
         attachment::Attachment, flat::VoxelModelFlat, sft_compressed::VoxelModelSFTCompressed,
         voxel::VoxelMaterialData, voxel_registry::VoxelModelRegistry,
     },
-    world::world_streaming::ChunkStreamEvent,
+    world::{world_origin::WorldOrigin, world_streaming::ChunkStreamEvent},
 };
 use rogue_macros::Resource;
-use wide::CmpGt;
-
-struct GeneratorMaterials;
-
-impl GeneratorMaterials {
-    const GRASS: &'static str = "./materials/grass/grass_mat.rmat";
-    const DIRT: &'static str = "./materials/dirt/dirt_mat.rmat";
-
-    const MATERIALS: [&'static str; 2] = [Self::GRASS, Self::DIRT];
-}
 
 #[derive(Resource)]
 pub struct WorldGenerator {
     chunk_generator: Option<Arc<ChunkGenerator>>,
-    requested_materials: bool,
+    /// The settings `chunk_generator` was last built from, rebuilt when the project's settings
+    /// change.
+    generator_settings: Option<TerrainGeneratorSettings>,
+    /// Incremented each time `chunk_generator` is rebuilt so chunks from an older generator
+    /// still in flight are dropped.
+    generator_version: u32,
 
     generated_chunks: HashSet<ChunkId>,
+    /// Chunks waiting for a free background thread, the chunks closest to the camera are
+    /// generated first.
+    queued_chunks: Vec<ChunkId>,
+    /// When each chunk finished generating within the last second.
+    completed_chunk_times: VecDeque<Instant>,
     chunk_stream_event_reader: EventReader<ChunkStreamEvent>,
 
     /// The number of chunks currently being generated on background threads.
@@ -64,15 +59,16 @@ pub struct WorldGenerator {
 
 impl WorldGenerator {
     pub fn new(tasks: &Tasks) -> Self {
-        let center = Vector3::new(0, 0, 0);
-
         let (generated_chunk_send, generated_chunk_recv) =
             std::sync::mpsc::channel::<GeneratedChunkData>();
         Self {
-            requested_materials: false,
             chunk_generator: None,
+            generator_settings: None,
+            generator_version: 0,
 
             generated_chunks: HashSet::new(),
+            queued_chunks: Vec::new(),
+            completed_chunk_times: VecDeque::new(),
             chunk_stream_event_reader: EventReader::new(),
 
             currently_generating_chunks: 0,
@@ -88,12 +84,54 @@ impl WorldGenerator {
         self.paused = paused;
     }
 
+    /// The number of chunks waiting to be generated.
+    pub fn queued_chunk_count(&self) -> usize {
+        self.queued_chunks.len()
+    }
+
+    /// The number of chunks currently being generated on background threads.
+    pub fn generating_chunk_count(&self) -> u32 {
+        self.currently_generating_chunks
+    }
+
+    /// The number of chunks that finished generating within the last second.
+    pub fn completed_chunks_per_second(&self) -> usize {
+        self.completed_chunk_times.len()
+    }
+
+    /// The absolute position of the main camera in meters.
+    fn camera_world_pos(
+        ecs_world: &ECSWorld,
+        main_camera: &MainCamera,
+        world_origin: &WorldOrigin,
+    ) -> Option<Vector3<f64>> {
+        let camera = main_camera.camera()?;
+        let camera_transform = ecs_world.get::<&Transform>(camera).ok()?;
+        let camera_pos = ecs_world
+            .get_world_transform(camera, &camera_transform)
+            .position;
+        Some(world_origin.to_absolute_world_pos(&camera_pos))
+    }
+
+    /// Sorts the queue so the chunk closest to `camera_pos` is last.
+    fn sort_queued_chunks(&mut self, camera_pos: Vector3<f64>) {
+        self.queued_chunks.sort_by_cached_key(|chunk_id| {
+            let half_length = chunk_id.chunk_lod.leaf_chunk_length() as f64 * 0.5;
+            let chunk_center = (chunk_id.chunk_pos.cast::<f64>()
+                + Vector3::new(half_length, half_length, half_length))
+                * consts::voxel::TERRAIN_CHUNK_METER_LENGTH as f64;
+            std::cmp::Reverse(chunk_center.metric_distance(&camera_pos) as u64)
+        });
+    }
+
     pub fn update(
         mut generator: ResMut<WorldGenerator>,
         mut region_map: ResMut<RegionMap>,
         mut voxel_registry: ResMut<VoxelModelRegistry>,
         mut tasks: ResMut<Tasks>,
-        mut material_bank: ResMut<MaterialBank>,
+        material_bank: Res<MaterialBank>,
+        terrain_generator_settings: Res<TerrainGeneratorSettings>,
+        (ecs_world, main_camera, world_origin): (Res<ECSWorld>, Res<MainCamera>, Res<WorldOrigin>),
         input: Res<Input>,
         events: Res<Events>,
     ) {
@@ -103,50 +141,50 @@ impl WorldGenerator {
             generator.paused = !generator.paused;
         }
 
-        if generator.chunk_generator.is_none() {
-            if !generator.requested_materials {
-                generator.requested_materials = true;
-                for material in GeneratorMaterials::MATERIALS {
-                    //material_bank
-                    //    .request_material(GameAssetPath::from_relative_path(Path::new(material)));
+        if generator.generator_settings.as_ref() != Some(&*terrain_generator_settings) {
+            match ChunkGenerator::new(&terrain_generator_settings, &material_bank) {
+                Ok(chunk_generator) => {
+                    generator.chunk_generator = Some(Arc::new(chunk_generator));
+                    generator.generator_settings = Some(terrain_generator_settings.clone());
+                    generator.generator_version += 1;
+                    // Anything generated or in flight came from the old generator, queued chunks
+                    // are already a part of the generated chunks.
+                    generator.queued_chunks = generator.generated_chunks.iter().copied().collect();
                 }
-            }
-
-            let mut all_loaded = false;
-            for material in GeneratorMaterials::MATERIALS {
-                if material_bank
-                    .asset_path_map
-                    .get(&GameAssetPath::from_relative_path(Path::new(material)))
-                    .is_none()
-                {
-                    all_loaded = false;
-                    break;
+                // The materials may just not be loaded yet so try again next frame.
+                Err(_) if material_bank.loading_materials() => {}
+                Err(err) => {
+                    log::error!("Failed to create the terrain generator: {}", err);
+                    generator.chunk_generator = None;
+                    generator.generator_settings = Some(terrain_generator_settings.clone());
                 }
             }
-
-            if all_loaded {
-                generator.chunk_generator = Some(Arc::new(ChunkGenerator::new(0, &material_bank)));
-            }
-        }
-
-        let can_generate_chunks = generator.currently_generating_chunks
-            < generator.max_generating_chunks
-            && !generator.paused
-            && generator.chunk_generator.is_some();
-        if !can_generate_chunks {
-            return;
         }
 
         for event in generator.chunk_stream_event_reader.read(&events) {
-            let chunk_id = event.chunk_id;
+            if generator.generated_chunks.insert(event.chunk_id) {
+                generator.queued_chunks.push(event.chunk_id);
+            }
+        }
 
-            if generator.generated_chunks.contains(&chunk_id) {
-                continue;
+        let can_generate_chunks = !generator.paused
+            && generator.chunk_generator.is_some()
+            && !generator.queued_chunks.is_empty();
+        if can_generate_chunks {
+            if let Some(camera_pos) =
+                Self::camera_world_pos(&ecs_world, &main_camera, &world_origin)
+            {
+                generator.sort_queued_chunks(camera_pos);
             }
-            generator.generated_chunks.insert(chunk_id);
 
+            // Bound the chunks in flight so a large streaming radius doesn't flood the thread
+            // pool, only cpu models are built off the main thread.
+            while generator.currently_generating_chunks < generator.max_generating_chunks
+                && let Some(chunk_id) = generator.queued_chunks.pop()
             {
+                generator.currently_generating_chunks += 1;
                 let chunk_generator = generator.chunk_generator.clone().unwrap();
+                let generator_version = generator.generator_version;
                 let generated_chunk_send = generator.generated_chunk_send.clone();
                 tasks.spawn_background_process(move || {
                     let sft =
@@ -155,6 +193,7 @@ impl WorldGenerator {
                         .send(GeneratedChunkData {
                             generated_sft: sft,
                             chunk_id,
+                            generator_version,
                         })
                         .expect("Failed to send generated chunk data to main thread.");
                 });
@@ -165,15 +204,27 @@ impl WorldGenerator {
         while let Ok(GeneratedChunkData {
             generated_sft: sft,
             chunk_id,
+            generator_version,
         }) = generator.generated_chunk_recv.try_recv()
         {
             generator.currently_generating_chunks =
                 generator.currently_generating_chunks.saturating_sub(1);
+            if generator_version != generator.generator_version {
+                continue;
+            }
+            generator.completed_chunk_times.push_back(Instant::now());
 
-            // Non-empty chunk so update region and parent node accordingly.
+            // Non-empty chunk so update region and parent node accordingly, the model is
+            // allocated on the gpu by the registry on the main thread.
             let sft_id = (!sft.is_empty()).then(|| voxel_registry.register_voxel_model(sft, None));
             region_map.set_chunk(chunk_id, sft_id);
         }
+
+        while let Some(completed_time) = generator.completed_chunk_times.front()
+            && completed_time.elapsed() > Duration::from_secs(1)
+        {
+            generator.completed_chunk_times.pop_front();
+        }
     }
 }
 
@@ -195,121 +246,38 @@ pub struct GeneratedChunkData {
     // None if there cannot be any voxels in this chunk at the requested LOD.
     generated_sft: VoxelModelSFTCompressed,
     chunk_id: ChunkId,
+    generator_version: u32,
 }
 
 pub struct ChunkGenerator {
-    sample_offset: Vector3<f32>,
-    height_noise: Fbm<PerlinNoise>,
-    density_noise: Fbm<PerlinNoise>,
-    model_material_map: ModelMaterialMap,
-    material_name_map: HashMap<String, MaterialId>,
+    noise_stack: TerrainNoiseStack,
+    /// The encoded voxel material for each of `TerrainGeneratorSettings::material_names()`.
+    materials: Vec<u64>,
 }
 
 impl ChunkGenerator {
-    pub fn new(seed: u64, material_bank: &MaterialBank) -> Self {
-        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
-        let offset = Vector3::new(
-            (rng.next_u32() % 25600) as f32 * 0.01,
-            (rng.next_u32() % 25600) as f32 * 0.01,
-            (rng.next_u32() % 25600) as f32 * 0.01,
-        );
-
-        let mut material_map = ModelMaterialMap::new();
-        let mut material_name_map = HashMap::new();
-        for material in GeneratorMaterials::MATERIALS {
+    pub fn new(
+        settings: &TerrainGeneratorSettings,
+        material_bank: &MaterialBank,
+    ) -> anyhow::Result<Self> {
+        let mut model_material_map = ModelMaterialMap::new();
+        let mut material_ids = Vec::new();
+        for material in settings.material_names() {
             let Some(material_id) = material_bank.find_first_material_by_name(material) else {
-                log::warn!("Failed to find EditorGenerator material '{}'", material);
-                continue;
+                anyhow::bail!("Couldn't find the terrain material '{}'.", material);
             };
-            material_map.push(material_id);
-            material_name_map.insert(material.to_owned(), material_id);
-        }
-
-        Self {
-            sample_offset: offset,
-            height_noise: Fbm::new(
-                PerlinNoise::new(seed),
-                FbmOptions {
-                    lacunarity: 1.7,
-                    octaves: 8,
-                    gain: 0.6,
-                },
-            ),
-            density_noise: Fbm::new(
-                PerlinNoise::new(seed),
-                FbmOptions {
-                    lacunarity: 2.0,
-                    octaves: 4,
-                    gain: 0.5,
-                },
-            ),
-            model_material_map: material_map,
-            material_name_map,
+            model_material_map.push(material_id);
+            material_ids.push(material_id);
         }
-    }
-
-    pub fn lipshitz_constant() -> f32 {
-        todo!()
-    }
-
-    pub fn sample_shaping(
-        &self,
-        world_voxel_pos: Vector3<f32>,
-    ) -> (/*density*/ f32, /*height*/ f32) {
-        let height_frequency = 0.003;
-        let height_sample_pos = world_voxel_pos * height_frequency + self.sample_offset;
-        let height = self
-            .height_noise
-            .noise_2d(height_sample_pos.x, height_sample_pos.z)
-            * 0.0;
-
-        let density_frequency = 0.01;
-        let density_sample_pos = world_voxel_pos * density_frequency + self.sample_offset;
-        let mut density = self.density_noise.noise_3d(
-            density_sample_pos.x,
-            density_sample_pos.y,
-            density_sample_pos.z,
-        );
-
-        // 10m of possible 3d noise, rest must be from y shaping.
-        const SURFACE_FALLOFF: f32 = 0.0;
-        density += -(world_voxel_pos.y - height) / SURFACE_FALLOFF;
-
-        (density, height)
-    }
-
-    pub fn sample_material(&self, world_voxel_pos: Vector3<f32>, density: f32, height: f32) -> u64 {
-        let mut material_id = None;
-        if density < 0.1 {
-            material_id = self.material_name_map.get(GeneratorMaterials::GRASS);
-        } else {
-            material_id = self.material_name_map.get(GeneratorMaterials::DIRT);
-        }
-        let Some(material_id) = material_id else {
-            panic!(
-                "Generator missing material? oops. we shouldn't be running then or populate with backup.."
-            );
-        };
-        return VoxelMaterialData::Unbaked(*material_id).encode(&self.model_material_map);
-    }
-
-    pub fn sample_chunk_shaping(&self, world_chunk_pos: ChunkPos, lod: ChunkLOD) -> (f32, f32) {
-        let voxel_meter_size = lod.voxel_meter_size();
-        let chunk_voxel_pos = world_chunk_pos.map(|x| {
-            x as f32
-                * consts::voxel::TERRAIN_CHUNK_VOXEL_LENGTH as f32
-                * consts::voxel::VOXEL_METER_LENGTH
-        });
-        let half_chunk_meter_size =
-            consts::voxel::TERRAIN_CHUNK_VOXEL_LENGTH as f32 * voxel_meter_size * 0.5;
-        return self.sample_shaping(
-            chunk_voxel_pos
-                + Vector3::new(
-                    half_chunk_meter_size,
-                    half_chunk_meter_size,
-                    half_chunk_meter_size,
-                ),
-        );
+        let materials = material_ids
+            .into_iter()
+            .map(|material_id| VoxelMaterialData::Unbaked(material_id).encode(&model_material_map))
+            .collect();
+
+        Ok(Self {
+            noise_stack: TerrainNoiseStack::new(settings),
+            materials,
+        })
     }
 
     pub fn generate_chunk_sft(
@@ -334,31 +302,23 @@ impl ChunkGenerator {
             for local_y in 0..consts::voxel::TERRAIN_CHUNK_VOXEL_LENGTH {
                 let voxel_y = chunk_voxel_pos.y + local_y as f32 * voxel_meter_size;
                 for local_x in 0..(consts::voxel::TERRAIN_CHUNK_VOXEL_LENGTH / 8) {
-                    // Operating on 8 voxels along the x direction at a time with SIMD, targetting
-                    // AVX2.
-                    let voxel_x = wide::f32x8::splat(
-                        chunk_voxel_pos.x + (local_x as f32 * 8.0 * voxel_meter_size),
-                    ) + (wide::f32x8::new([0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0])
-                        * voxel_meter_size);
-                    let mut density = wide::f32x8::splat(0.0);
+                    // Written 8 voxels along the x direction at a time to match the bitset.
+                    let mut presence_bitmask = 0u32;
                     let mut material = wide::u64x8::splat(0);
                     for i in 0..8 {
-                        let (d, h) = self.sample_shaping(Vector3::new(
-                            voxel_x.as_array()[i],
-                            voxel_y,
-                            voxel_z,
-                        ));
-                        density.as_mut_array()[i] = d;
-                        if d > 0.0 {
-                            material.as_mut_array()[i] = self.sample_material(
-                                Vector3::new(voxel_x.as_array()[i], voxel_y, voxel_z),
-                                d,
-                                h,
-                            );
-                        }
+                        let voxel_x =
+                            chunk_voxel_pos.x + (local_x * 8 + i) as f32 * voxel_meter_size;
+                        let Some(material_index) = self
+                            .noise_stack
+                            .sample(Vector3::new(voxel_x, voxel_y, voxel_z))
+                        else {
+                            continue;
+                        };
+                        presence_bitmask |= 1 << i;
+                        material.as_mut_array()[i as usize] =
+                            self.materials[material_index as usize];
                     }
 
-                    let presence_bitmask = density.simd_gt(wide::f32x8::splat(0.0)).to_bitmask();
                     let index = flat.get_voxel_index(Vector3::new(local_x * 8, local_y, local_z));
                     flat.presence_data.set_bits(index, 8, presence_bitmask);
                     flat.attachment_presence_data
@@ -369,21 +329,6 @@ impl ChunkGenerator {
                     flat.attachment_data.get_mut(Attachment::BMAT_ID).unwrap()[attachment_offset
                         ..(attachment_offset + 8 * Attachment::BMAT.size() as usize)]
                         .copy_from_slice(bytemuck::cast_slice::<u64, u32>(material.as_array()));
-                    for i in index..(index + 8) {
-                        if density.as_array()[i - index] > 0.0 {
-                            let attachment_value =
-                                flat.attachment_data.get_mut(Attachment::BMAT_ID).unwrap()
-                                    [i * Attachment::BMAT.size() as usize];
-                            let attachment_value_b =
-                                flat.attachment_data.get_mut(Attachment::BMAT_ID).unwrap()
-                                    [i * Attachment::BMAT.size() as usize + 1];
-                            //log::info!(
-                            //    "Attachment data is {:032b} {:032b}",
-                            //    attachment_value,
-                            //    attachment_value_b
-                            //);
-                        }
-                    }
                 }
             }
         }
@@ -1,24 +1,43 @@
 use std::{
     collections::HashMap,
+    path::PathBuf,
     sync::mpsc::{Receiver, Sender, channel},
+    time::Duration,
 };
 
+use anyhow::anyhow;
+use nalgebra::Vector2;
+
 use winit::{
     application::ApplicationHandler, event::WindowEvent as WinitWindowEvent, event_loop::EventLoop,
 };
 
 use crate::audio::Audio;
-use crate::graphics::{backend::GraphicsBackendEvent, camera::MainCamera, device::DeviceResource};
+use crate::common::rng::Rng;
+use crate::entity::scripting::Scripts;
+use crate::graphics::{
+    backend::{GfxDeviceError, GraphicsBackendEvent},
+    camera::{CameraShake, MainCamera},
+    device::DeviceResource,
+};
 use crate::world::terrain::region_map::RegionMap;
 use crate::world::terrain::region_map_gpu::RegionMapGpu;
 use crate::world::world_entities_gpu::WorldEntitiesGpu;
+use crate::world::world_origin::WorldOrigin;
 use crate::{
     animation::animation_bank::AnimationBank,
     asset::{
-        asset::Assets,
+        asset::{AssetPath, Assets},
         repr::{project::ProjectAsset, settings::UserSettingsAsset},
     },
-    graphics::renderer::Renderer,
+    graphics::{
+        passes::{
+            bloom_pass::BloomPass, depth_of_field_pass::DepthOfFieldPass, fxaa_pass::FxaaPass,
+            taa_pass::TaaPass,
+        },
+        renderer::Renderer,
+        screenshot::{ScreenshotRegion, ScreenshotRequest, Screenshots},
+    },
     material::material_gpu::MaterialBankGpu,
     world::{renderable::rt_pass::WorldRTPass, world_entities::WorldEntities},
 };
@@ -35,7 +54,7 @@ use crate::{
 };
 use crate::{
     system::{System, SystemErased},
-    world::sky::Sky,
+    world::sky::SkyLight,
 };
 use crate::{
     voxel::baker_gpu::VoxelBakerGpu,
@@ -56,6 +75,28 @@ pub struct AppCreateInfo {
     pub on_post_graphics_init_fn: Option<Box<dyn Fn(&mut ResourceBank)>>,
     pub on_window_event_fn: Option<OnWindowEventBoxedFn>,
     pub on_device_event_fn: Option<OnDeviceEventBoxedFn>,
+    /// Called before shutting down from an unrecoverable error such as the graphics device
+    /// being lost, the last chance to save any state.
+    pub on_fatal_error_fn: Option<Box<dyn Fn(&mut ResourceBank)>>,
+}
+
+/// Renders a fixed number of frames into an offscreen image instead of a window and saves the
+/// last one, so rendering can be regression tested against known good frames.
+pub struct HeadlessRenderInfo {
+    /// The size of the offscreen image standing in for the swapchain.
+    pub size: Vector2<u32>,
+    pub frame_count: u32,
+    /// Time advances by exactly this much each frame regardless of how long frames take.
+    pub delta_time: Duration,
+    /// Where the last frame is saved as a png.
+    pub output_path: PathBuf,
+}
+
+struct HeadlessRender {
+    info: HeadlessRenderInfo,
+    rendered_frames: u32,
+    /// Set once the last frame is saved or the app had to shut down.
+    result: Option<anyhow::Result<()>>,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
@@ -82,6 +123,7 @@ pub struct App {
     did_first_resize: bool,
     initialized_graphics: bool,
     graphics_event_reader: EventReader<GraphicsBackendEvent>,
+    headless: Option<HeadlessRender>,
 
     resource_bank: ResourceBank,
     systems: HashMap<AppStage, Vec<SystemErased>>,
@@ -89,6 +131,7 @@ pub struct App {
     on_post_graphics_init_fn: Option<Box<dyn Fn(&mut ResourceBank)>>,
     on_window_event_fn: Option<OnWindowEventBoxedFn>,
     on_device_event_fn: Option<OnDeviceEventBoxedFn>,
+    on_fatal_error_fn: Option<Box<dyn Fn(&mut ResourceBank)>>,
 
     event_sender: Sender<AppEvent>,
     event_receiver: Receiver<AppEvent>,
@@ -107,6 +150,7 @@ impl App {
             did_first_resize: false,
             initialized_graphics: false,
             graphics_event_reader: EventReader::new(),
+            headless: None,
 
             resource_bank: ResourceBank::new(),
             systems: HashMap::new(),
@@ -114,6 +158,7 @@ impl App {
             on_post_graphics_init_fn: create_info.on_post_graphics_init_fn,
             on_window_event_fn: create_info.on_window_event_fn,
             on_device_event_fn: create_info.on_device_event_fn,
+            on_fatal_error_fn: create_info.on_fatal_error_fn,
 
             event_sender,
             event_receiver,
@@ -121,20 +166,29 @@ impl App {
 
         app.insert_resource(Tasks::new());
         app.insert_resource(Events::new());
-        app.insert_resource(Settings::from(&UserSettingsAsset::default()));
-        app.insert_resource(Input::new());
+        let user_settings = UserSettingsAsset::load_user_settings();
+        app.insert_resource(Settings::from(&user_settings));
+        app.insert_resource(Input::new(user_settings.keybinds));
         app.insert_resource(Time::new());
         app.insert_resource(Audio::new());
         app.insert_resource(MainCamera::new_empty());
-        app.insert_resource(Sky::new());
+        app.insert_resource(CameraShake::new());
 
         let project = create_info.project;
+        let rng = Rng::new(project.settings.seed);
+        let mut scripts = Scripts::new();
+        scripts.seed_rng(&rng);
+        app.insert_resource(rng);
+        app.insert_resource(scripts);
+        app.insert_resource(project.settings.terrain_generator);
+        app.insert_resource(project.settings.sky_light);
         app.insert_resource(project.ecs_world);
         app.insert_resource(project.voxel_registry);
         app.insert_resource(project.physics_world);
         app.insert_resource(project.material_bank);
         app.insert_resource(Assets::new(project.project_dir));
         app.insert_resource(RegionMap::new());
+        app.insert_resource(WorldOrigin::new());
 
         app
     }
@@ -156,6 +210,11 @@ impl App {
         self.insert_resource(WorldChunkStreamer::new(WorldStreamingOptions::default()));
 
         self.insert_resource(DebugRenderer::new());
+        self.insert_resource(Screenshots::new());
+        self.insert_resource(TaaPass::new());
+        self.insert_resource(DepthOfFieldPass::new());
+        self.insert_resource(BloomPass::new());
+        self.insert_resource(FxaaPass::new());
         self.insert_resource(region_map_gpu);
         self.insert_resource(voxel_registry_gpu);
 
@@ -164,6 +223,129 @@ impl App {
         }
     }
 
+    /// The device can't be recreated in place since every system owning gpu resources would
+    /// have to recreate them, so instead save what we can and shut down cleanly rather than
+    /// panicking somewhere in the graphics code.
+    fn shutdown_from_graphics_error(
+        &mut self,
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        error: GfxDeviceError,
+    ) {
+        log::error!(
+            "Shutting down due to an unrecoverable graphics error: {}.",
+            error
+        );
+        if let Some(fatal_error_fn) = &self.on_fatal_error_fn {
+            (*fatal_error_fn)(&mut self.resource_bank);
+        }
+        self.resource_bank
+            .get_resource_mut::<Assets>()
+            .wait_until_all_saved();
+
+        if let Some(headless) = &mut self.headless {
+            headless.result = Some(Err(error.into()));
+        } else {
+            rfd::MessageDialog::new()
+                .set_level(rfd::MessageLevel::Error)
+                .set_title("Graphics error")
+                .set_description(format!(
+                    "{}, this can happen after a driver update or crash. The app will now close.",
+                    error
+                ))
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
+        }
+        event_loop.exit();
+    }
+
+    /// Initializes everything relying on the graphics device once the backend is ready, returns
+    /// whether graphics are initialized.
+    fn try_init_graphics(&mut self) -> bool {
+        if self.initialized_graphics {
+            return true;
+        }
+
+        self.run_system(DeviceResource::pre_graphics_update);
+        let events = self.resource_bank.get_resource::<Events>();
+        for event in self.graphics_event_reader.read(&events) {
+            match event {
+                GraphicsBackendEvent::Initialized => {
+                    self.initialized_graphics = true;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        drop(events);
+
+        // Graphics backend still isn't ready yet.
+        if !self.initialized_graphics {
+            return false;
+        }
+
+        self.init_post_graphics();
+        true
+    }
+
+    /// Runs a frame of the game loop, returns false if the app is shutting down from a fatal
+    /// error.
+    fn run_frame(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) -> bool {
+        game_loop::game_loop(self);
+
+        let fatal_error = self
+            .resource_bank
+            .get_resource::<DeviceResource>()
+            .fatal_error();
+        if let Some(error) = fatal_error {
+            self.shutdown_from_graphics_error(event_loop, error);
+            return false;
+        }
+
+        self.resource_bank
+            .get_resource_mut::<Window>()
+            .finish_frame();
+        true
+    }
+
+    /// Renders the next headless frame, capturing the last one and exiting once it's saved.
+    fn update_headless(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        if !self.try_init_graphics() {
+            return;
+        }
+
+        let headless = self.headless.as_ref().unwrap();
+        if headless.result.is_some() {
+            return;
+        }
+        if headless.rendered_frames < headless.info.frame_count {
+            if headless.rendered_frames + 1 == headless.info.frame_count {
+                let request = ScreenshotRequest::new(ScreenshotRegion::Full)
+                    .with_path(AssetPath::new(headless.info.output_path.clone()));
+                self.get_resource_mut::<Screenshots>().request(request);
+            }
+            if self.run_frame(event_loop) {
+                self.headless.as_mut().unwrap().rendered_frames += 1;
+            }
+            return;
+        }
+
+        // Wait on the last frame's capture without rendering any further frames.
+        if self.get_resource::<Screenshots>().is_reading_back() {
+            self.run_system(Screenshots::update_readbacks);
+            return;
+        }
+        let result = if self.get_resource::<Screenshots>().is_idle() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "The last frame wasn't captured, the render graph needs a screenshot pass."
+            ))
+        };
+        self.get_resource_mut::<Assets>().wait_until_all_saved();
+        self.headless.as_mut().unwrap().result = Some(result);
+        event_loop.exit();
+    }
+
     pub fn systems(&self, stage: AppStage) -> Option<&Vec<SystemErased>> {
         self.systems.get(&stage)
     }
@@ -216,6 +398,34 @@ impl App {
         }
     }
 
+    /// Runs without presenting to a window until the frames in `info` are rendered and the last
+    /// one is saved. A hidden window is still created for the event loop. The app must have the
+    /// screenshot pass in its render graph along with `Screenshots::update_captures` and
+    /// `Screenshots::update_readbacks`, since the frame is saved through them.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_headless(mut self, info: HeadlessRenderInfo) -> anyhow::Result<()> {
+        assert!(
+            info.frame_count > 0,
+            "Headless rendering needs at least one frame."
+        );
+        self.get_resource_mut::<Time>()
+            .set_fixed_delta_time(Some(info.delta_time));
+        self.headless = Some(HeadlessRender {
+            info,
+            rendered_frames: 0,
+            result: None,
+        });
+
+        let event_loop = self.event_loop.take().unwrap();
+        event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
+        event_loop.run_app(&mut self)?;
+        self.headless.take().unwrap().result.unwrap_or_else(|| {
+            Err(anyhow!(
+                "The app exited before the last headless frame was saved."
+            ))
+        })
+    }
+
     pub fn run_system<Marker>(&self, mut system: impl System<Marker>) {
         system.run(self.resource_bank());
     }
@@ -226,7 +436,19 @@ impl winit::application::ApplicationHandler for App {
         if !self.initialized_window {
             self.initialized_window = true;
 
-            let window = Window::new(event_loop);
+            // Headless apps render offscreen from the start, the hidden window never resizes.
+            if let Some(size) = self.headless.as_ref().map(|headless| headless.info.size) {
+                let mut gfx_device = DeviceResource::new();
+                gfx_device.init_headless(
+                    size,
+                    &self.resource_bank.get_resource::<Settings>().graphics,
+                );
+                self.resource_bank.insert(Window::new(event_loop, false));
+                self.resource_bank.insert(gfx_device);
+                return;
+            }
+
+            let window = Window::new(event_loop, true);
             let window_id = window.handle().id();
             let event = winit::event::WindowEvent::Resized(window.handle().inner_size());
             self.resource_bank_mut().insert(window);
@@ -240,6 +462,11 @@ impl winit::application::ApplicationHandler for App {
         _window_id: winit::window::WindowId,
         mut event: winit::event::WindowEvent,
     ) {
+        if self.resource_bank().has_resource::<Window>() {
+            self.resource_bank()
+                .get_resource_mut::<Window>()
+                .handle_winit_window_event(&event);
+        }
         if let Some(on_window_event_fn) = &self.on_window_event_fn {
             let consumed = (*on_window_event_fn)(&mut self.resource_bank, &mut event);
             if consumed {
@@ -253,6 +480,10 @@ impl winit::application::ApplicationHandler for App {
                 .handle_winit_window_event(event.clone());
         }
         match event {
+            // Headless frames are driven from `about_to_wait` since hidden windows may never be
+            // redrawn, and the offscreen image doesn't follow the window's size.
+            WinitWindowEvent::RedrawRequested | WinitWindowEvent::Resized(_)
+                if self.headless.is_some() => {}
             WinitWindowEvent::RedrawRequested => {
                 // We need to always request a redraw even with `ControlFlow::Poll` event though I
                 // thought Poll always does this anyways.
@@ -261,34 +492,10 @@ impl winit::application::ApplicationHandler for App {
                     .handle()
                     .request_redraw();
 
-                if !self.initialized_graphics {
-                    self.run_system(DeviceResource::pre_graphics_update);
-
-                    let events = self.resource_bank.get_resource::<Events>();
-                    for event in self.graphics_event_reader.read(&events) {
-                        match event {
-                            GraphicsBackendEvent::Initialized => {
-                                self.initialized_graphics = true;
-                                break;
-                            }
-                            _ => {}
-                        }
-                    }
-                    drop(events);
-
-                    // Graphics backend still isn't ready yet.
-                    if !self.initialized_graphics {
-                        return;
-                    }
-
-                    self.init_post_graphics();
+                if !self.try_init_graphics() {
+                    return;
                 }
-
-                game_loop::game_loop(self);
-
-                self.resource_bank
-                    .get_resource_mut::<Window>()
-                    .finish_frame();
+                self.run_frame(event_loop);
             }
             WinitWindowEvent::Resized(new_size) => {
                 if !self.did_first_resize && new_size.width > 0 && new_size.height > 0 {
@@ -324,6 +531,12 @@ impl winit::application::ApplicationHandler for App {
         }
     }
 
+    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        if self.headless.is_some() && self.resource_bank.has_resource::<DeviceResource>() {
+            self.update_headless(event_loop);
+        }
+    }
+
     fn device_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,
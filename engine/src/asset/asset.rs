@@ -14,7 +14,7 @@ use std::{
         atomic::AtomicU64,
         mpsc::{Receiver, channel},
     },
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 use log::{debug, info};
@@ -45,6 +45,14 @@ pub struct Assets {
     assets_dir_modified: Option<Instant>,
     assets_dir_check_timer: Timer,
 
+    /// File assets loaded through `load_watched_asset`, checked for modification so they are
+    /// reloaded when changed on disk. Entries are removed with `unwatch_asset`.
+    watched_assets: HashMap<AssetId, WatchedAsset>,
+    reloading_assets: HashSet<AssetId>,
+    /// Assets which finished reloading this frame.
+    reloaded_assets: HashSet<AssetId>,
+    asset_reload_check_timer: Timer,
+
     project_dir: Option<PathBuf>,
 
     thread_pool: rayon::ThreadPool,
@@ -69,6 +77,11 @@ impl Assets {
             assets_dir_modified: None,
             assets_dir_check_timer: Timer::new(Duration::from_millis(100)),
 
+            watched_assets: HashMap::new(),
+            reloading_assets: HashSet::new(),
+            reloaded_assets: HashSet::new(),
+            asset_reload_check_timer: Timer::new(Duration::from_millis(500)),
+
             project_dir,
 
             thread_pool: rayon::ThreadPoolBuilder::default()
@@ -129,6 +142,31 @@ impl Assets {
         assets.update_impl();
     }
 
+    /// Saves the project to `project.autosave.json` next to `project.json` so an autosave never
+    /// overwrites the user's last save. Returns false if there is no project to save to.
+    pub fn save_project_autosave(&self, ser_context: ProjectSerializeContext<'_>) -> bool {
+        let Some(project_dir) = &self.project_dir else {
+            return false;
+        };
+        let autosave_path = AssetPath::new(project_dir.join("project.autosave.json"));
+        let project_asset = match ProjectAsset::serialize(ser_context) {
+            Ok(asset) => asset,
+            Err(err) => {
+                log::error!("Error while trying to serialize project autosave: {}", err);
+                return false;
+            }
+        };
+        if let Err(err) = Self::save_asset_sync(autosave_path, project_asset) {
+            log::error!("Failed to save project autosave: {}", err);
+            return false;
+        }
+        log::info!(
+            "Saved project autosave to {:?}.",
+            project_dir.join("project.autosave.json")
+        );
+        true
+    }
+
     pub fn save_project(&self, ser_context: ProjectSerializeContext<'_>) {
         let project_dir = self
             .project_dir
@@ -153,6 +191,10 @@ impl Assets {
         if assets.assets_dir_check_timer.try_complete() {
             assets.check_assets_dir_for_updates();
         }
+        assets.reloaded_assets.clear();
+        if assets.asset_reload_check_timer.try_complete() {
+            assets.check_watched_assets_for_reload();
+        }
 
         // Process finished tasks from the thread pool.
         let mut finished_ids = Vec::new();
@@ -160,12 +202,21 @@ impl Assets {
             match asset {
                 ProcessingAsset::Load { id, asset_recv } => {
                     match asset_recv.try_recv() {
+                        // A failed reload keeps the last loaded asset around, the file is most
+                        // likely being written to and will be reloaded again once it's done.
+                        Ok(Err(err)) if assets.reloading_assets.remove(id) => {
+                            log::warn!("Failed to reload asset {}: {}", id, err);
+                        }
                         Ok(res) => match res {
                             Ok(ProcessedAsset {
                                 data,
                                 path: asset_path,
                                 hash,
                             }) => {
+                                if assets.reloading_assets.remove(id) {
+                                    log::info!("Reloaded asset {}.", asset_path.path_str());
+                                    assets.reloaded_assets.insert(*id);
+                                }
                                 assets.assets.insert(
                                     *id,
                                     AssetData {
@@ -198,7 +249,13 @@ impl Assets {
                                 log::error!(
                                     "Error with asset thread disconnection while loading asset {}",
                                     id
-                                )
+                                );
+                                assets.asset_statuses.insert(
+                                    *id,
+                                    AssetStatus::Error(anyhow::anyhow!(
+                                        "Asset thread disconnected while loading."
+                                    )),
+                                );
                             }
                         },
                     }
@@ -207,7 +264,7 @@ impl Assets {
                 ProcessingAsset::Save { id, asset_recv } => {
                     match asset_recv.try_recv() {
                         Ok(res) => match res {
-                            _ => {
+                            Ok(()) => {
                                 assets.asset_statuses.insert(*id, AssetStatus::Saved);
                                 log::debug!(
                                     "Saved asset to {:?}.",
@@ -227,7 +284,13 @@ impl Assets {
                                 log::error!(
                                     "Error with asset thread disconnection while saving asset {}",
                                     id
-                                )
+                                );
+                                assets.asset_statuses.insert(
+                                    *id,
+                                    AssetStatus::Error(anyhow::anyhow!(
+                                        "Asset thread disconnected while saving."
+                                    )),
+                                );
                             }
                         },
                     }
@@ -261,7 +324,17 @@ impl Assets {
                     let (send, recv) = channel::<ProcessingAssetLoadResult>();
 
                     assets.thread_pool.spawn(move || {
-                        let asset = pollster::block_on(load_fut);
+                        // A panicking loader would otherwise abort the whole process, instead
+                        // the error ends up in the asset's status like any other load error.
+                        let asset = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                            pollster::block_on(load_fut)
+                        }))
+                        .unwrap_or_else(|panic| {
+                            Err(AssetLoadError::Other(anyhow::anyhow!(
+                                "Asset loader panicked: {}",
+                                panic_message(&panic)
+                            )))
+                        });
                         send.send(asset);
                     });
 
@@ -274,7 +347,16 @@ impl Assets {
                     let (send, recv) = channel::<anyhow::Result<()>>();
 
                     assets.thread_pool.spawn(move || {
-                        let save_res = pollster::block_on(save_fut);
+                        let save_res =
+                            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                                pollster::block_on(save_fut)
+                            }))
+                            .unwrap_or_else(|panic| {
+                                Err(anyhow::anyhow!(
+                                    "Asset saver panicked: {}",
+                                    panic_message(&panic)
+                                ))
+                            });
                         send.send(save_res);
                     });
 
@@ -318,7 +400,22 @@ impl Assets {
             id: self.next_id(),
         };
 
-        let load_fut = async move {
+        self.currently_loading_assets.insert(handle.id);
+        self.queued_assets.push_back(QueuedAsset::Load {
+            id: handle.id,
+            load_fut: Self::create_load_future::<T>(path),
+        });
+        self.asset_statuses
+            .insert(handle.id, AssetStatus::InProgress);
+
+        handle
+    }
+
+    fn create_load_future<T>(path: AssetPath) -> Pin<Box<dyn ProcessingAssetLoadFuture>>
+    where
+        T: AssetLoader + Send + 'static,
+    {
+        Box::pin(async move {
             let storage = AssetFile::from_path(&path);
             let hash = storage.calculate_hash();
             let contents = T::load(&storage);
@@ -336,19 +433,68 @@ impl Assets {
                     AssetLoadError::Other(e) => AssetLoadError::Other(e),
                 }),
             }
-        };
+        })
+    }
 
-        let pin_box = Box::pin(load_fut);
+    /// Same as `load_asset` but the asset is reloaded whenever its file is modified, see
+    /// `is_asset_reloaded`. The owner of the handle must call `unwatch_asset` once it is done
+    /// with the handle.
+    pub fn load_watched_asset<T>(&mut self, path: AssetPath) -> AssetHandle
+    where
+        T: AssetLoader + Send + 'static,
+    {
+        let handle = self.load_asset::<T>(path.clone());
+        self.watched_assets.insert(
+            handle.id,
+            WatchedAsset {
+                modified: Self::file_modified_time(&path),
+                path,
+                load_fn: Self::create_load_future::<T>,
+            },
+        );
+        handle
+    }
 
-        self.currently_loading_assets.insert(handle.id);
-        self.queued_assets.push_back(QueuedAsset::Load {
-            id: handle.id,
-            load_fut: pin_box,
-        });
-        self.asset_statuses
-            .insert(handle.id, AssetStatus::InProgress);
+    /// Stops reloading the asset when its file is modified.
+    pub fn unwatch_asset(&mut self, handle: &AssetHandle) {
+        self.watched_assets.remove(&handle.id);
+    }
 
-        handle
+    /// True for the frame the asset finished reloading since its file was modified, users of
+    /// the asset should pick up the new data from `get_asset` or `take_asset`.
+    pub fn is_asset_reloaded(&self, handle: &AssetHandle) -> bool {
+        self.reloaded_assets.contains(&handle.id)
+    }
+
+    fn file_modified_time(path: &AssetPath) -> Option<SystemTime> {
+        std::fs::metadata(path.path())
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    fn check_watched_assets_for_reload(&mut self) {
+        // There are no files to watch on the web.
+        if cfg!(target_arch = "wasm32") {
+            return;
+        }
+
+        for (id, watched_asset) in &mut self.watched_assets {
+            if self.currently_loading_assets.contains(id) {
+                continue;
+            }
+            let modified = Self::file_modified_time(&watched_asset.path);
+            if modified.is_none() || modified == watched_asset.modified {
+                continue;
+            }
+            watched_asset.modified = modified;
+
+            self.currently_loading_assets.insert(*id);
+            self.reloading_assets.insert(*id);
+            self.queued_assets.push_back(QueuedAsset::Load {
+                id: *id,
+                load_fut: (watched_asset.load_fn)(watched_asset.path.clone()),
+            });
+        }
     }
 
     pub fn wait_until_all_loaded(&mut self) {
@@ -526,6 +672,17 @@ impl Assets {
     }
 }
 
+/// The message of a caught panic, panics with a non string payload have no message.
+fn panic_message(panic: &Box<dyn Any + Send>) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "Unknown panic."
+    }
+}
+
 pub enum AssetStatus {
     // Still loading or saving.
     InProgress,
@@ -548,6 +705,13 @@ impl AssetStatus {
     }
 }
 
+struct WatchedAsset {
+    path: AssetPath,
+    /// The modification time of the file when it was last loaded.
+    modified: Option<SystemTime>,
+    load_fn: fn(AssetPath) -> Pin<Box<dyn ProcessingAssetLoadFuture>>,
+}
+
 enum QueuedAsset {
     Load {
         id: AssetId,
@@ -1205,3 +1369,23 @@ impl AssetFile {
         self.file_handle.calculate_hash()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::repr::TextAsset;
+
+    #[test]
+    fn only_watched_assets_are_tracked_until_unwatched() {
+        let mut assets = Assets::new(None);
+        let path = AssetPath::new(PathBuf::from("script.lua"));
+
+        assets.load_asset::<TextAsset>(path.clone());
+        assert!(assets.watched_assets.is_empty());
+
+        let handle = assets.load_watched_asset::<TextAsset>(path);
+        assert!(assets.watched_assets.contains_key(&handle.id));
+        assets.unwatch_asset(&handle);
+        assert!(assets.watched_assets.is_empty());
+    }
+}
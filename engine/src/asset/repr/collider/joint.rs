@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector3;
+use uuid::Uuid;
+
+use crate::entity::ecs_world::Entity;
+use crate::physics::joint::{Joint, JointKind};
+
+/// Serializable type for a `Joint`, the entities are referenced by their `GameEntity` uuid.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct JointAsset {
+    pub entity_a: Uuid,
+    pub entity_b: Uuid,
+    pub local_anchor_a: Vector3<f32>,
+    pub local_anchor_b: Vector3<f32>,
+    pub kind: JointKind,
+}
+
+impl JointAsset {
+    /// None if either entity isn't a `GameEntity`.
+    pub fn from_joint(joint: &Joint, entity_uuid_map: &HashMap<Entity, Uuid>) -> Option<Self> {
+        Some(Self {
+            entity_a: *entity_uuid_map.get(&joint.entity_a)?,
+            entity_b: *entity_uuid_map.get(&joint.entity_b)?,
+            local_anchor_a: joint.local_anchor_a,
+            local_anchor_b: joint.local_anchor_b,
+            kind: joint.kind.clone(),
+        })
+    }
+
+    /// None if either entity doesn't exist.
+    pub fn to_joint(&self, uuid_to_entity_map: &HashMap<Uuid, Entity>) -> Option<Joint> {
+        Some(Joint {
+            entity_a: *uuid_to_entity_map.get(&self.entity_a)?,
+            entity_b: *uuid_to_entity_map.get(&self.entity_b)?,
+            local_anchor_a: self.local_anchor_a,
+            local_anchor_b: self.local_anchor_b,
+            kind: self.kind.clone(),
+        })
+    }
+}
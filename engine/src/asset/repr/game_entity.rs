@@ -1,8 +1,25 @@
-use std::{any::TypeId, collections::HashMap};
+use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
+};
 
+use serde::de::DeserializeSeed;
 use uuid::Uuid;
 
+use crate::asset::asset::GameAssetPath;
+use crate::asset::repr::{
+    TextAsset,
+    project::{ProjectSceneDeserializeContext, ProjectSceneVisitor},
+};
 use crate::common::dyn_vec::TypeInfo;
+use crate::entity::{
+    EntityChildren, EntityParent, GameEntity, PrefabInstance,
+    component::{GameComponent, GameComponentDeserializeContext, GameComponentSerializeContext},
+    ecs_world::{ECSWorld, Entity},
+};
+use crate::physics::collider_registry::ColliderRegistry;
+use crate::voxel::voxel_registry::VoxelModelRegistry;
+
 /// A standalone game entity with all of its component data, essentially a prefab.
 /// Any references to colliders or voxel models are also specific to this entity instance.
 pub struct WorldGameEntityAsset {
@@ -23,6 +40,11 @@ impl WorldGameComponentAsset {
         Self { type_info, data }
     }
 
+    pub fn data(&self) -> *const u8 {
+        assert!(!self.data.is_null(), "Data is already taken.");
+        self.data
+    }
+
     pub fn take_data(&mut self) -> *mut u8 {
         let ptr = self.data;
         assert!(!self.data.is_null(), "Data is already taken.");
@@ -46,3 +68,534 @@ impl Drop for WorldGameComponentAsset {
 }
 
 impl WorldGameEntityAsset {}
+
+/// Every game entity in an `ECSWorld` with its components and hierarchy, written in the same
+/// format as the project's scene and saved as its own file. Voxel models are referenced by their
+/// asset path, so models which were never saved to an asset aren't part of the scene.
+pub struct SceneAsset;
+
+impl SceneAsset {
+    pub const EXTENSION: &str = "scene";
+
+    pub fn serialize(
+        ecs_world: &ECSWorld,
+        voxel_registry: &VoxelModelRegistry,
+        collider_registry: &ColliderRegistry,
+    ) -> anyhow::Result<TextAsset> {
+        let entity_uuid_map = ecs_world
+            .query::<&GameEntity>()
+            .into_iter()
+            .map(|(entity, game_entity)| (entity, game_entity.uuid))
+            .collect::<HashMap<_, _>>();
+        let contents = serde_json::to_string_pretty(&ecs_world.serialize_world(
+            &GameComponentSerializeContext {
+                voxel_registry,
+                collider_registry,
+                entity_uuid_map: &entity_uuid_map,
+            },
+        ))?;
+        Ok(TextAsset { contents })
+    }
+
+    /// Spawns the scene's entities into `ecs_world` and links them to their parents once all of
+    /// them exist. Entities whose parent isn't in the scene are spawned at the root instead.
+    /// Returns every game entity in `ecs_world` by uuid.
+    pub fn deserialize_into(
+        scene: &TextAsset,
+        ecs_world: &mut ECSWorld,
+        voxel_registry: &mut VoxelModelRegistry,
+        collider_registry: &mut ColliderRegistry,
+    ) -> anyhow::Result<HashMap<Uuid, Entity>> {
+        let mut uuid_to_entity_map = HashMap::new();
+        let mut de = serde_json::Deserializer::from_str(&scene.contents);
+        ProjectSceneVisitor {
+            ctx: &mut ProjectSceneDeserializeContext {
+                ecs_world,
+                uuid_to_entity_map: &mut uuid_to_entity_map,
+                to_parent_entities: &mut Vec::new(),
+                component_ctx: &mut GameComponentDeserializeContext {
+                    voxel_registry,
+                    collider_registry,
+                    entity_parent: Uuid::nil(),
+                },
+            },
+        }
+        .deserialize(&mut de)?;
+        de.end()?;
+        Ok(uuid_to_entity_map)
+    }
+
+    /// Spawns a copy of the scene's entities into `ecs_world` with fresh uuids, so a scene can
+    /// be loaded into a world which already contains its entities. Returns the spawned root
+    /// entities.
+    pub fn instantiate(
+        scene: &TextAsset,
+        ecs_world: &mut ECSWorld,
+        voxel_registry: &mut VoxelModelRegistry,
+        collider_registry: &mut ColliderRegistry,
+    ) -> anyhow::Result<Vec<Entity>> {
+        let mut scene_json = serde_json::from_str::<serde_json::Value>(&scene.contents)?;
+        let Some(entities) = scene_json["entities"].as_array() else {
+            anyhow::bail!("Scene doesn't contain an `entities` array.");
+        };
+        let new_uuids = entities
+            .iter()
+            .filter_map(|entity| {
+                let components = entity["components"].as_array()?;
+                PrefabAsset::entity_uuid(components)
+            })
+            .map(|uuid| (uuid, Uuid::new_v4()))
+            .collect::<HashMap<_, _>>();
+        remap_uuids(&mut scene_json, &new_uuids);
+
+        let uuid_to_entity = Self::deserialize_into(
+            &TextAsset {
+                contents: scene_json.to_string(),
+            },
+            ecs_world,
+            voxel_registry,
+            collider_registry,
+        )?;
+        Ok(new_uuids
+            .values()
+            .filter_map(|uuid| uuid_to_entity.get(uuid))
+            .filter(|entity| !ecs_world.contains::<EntityParent>(**entity))
+            .copied()
+            .collect())
+    }
+}
+
+/// An entity and its descendants saved in the scene format so copies of them can be
+/// instantiated, see `ECSWorld::instantiate_prefab()`. Nested prefab instances only save their
+/// root entity, the rest of them is instantiated from their own prefab.
+pub struct PrefabAsset;
+
+impl PrefabAsset {
+    pub const EXTENSION: &str = "prefab";
+
+    pub fn serialize(
+        ecs_world: &ECSWorld,
+        root: Entity,
+        voxel_registry: &VoxelModelRegistry,
+        collider_registry: &ColliderRegistry,
+    ) -> anyhow::Result<TextAsset> {
+        let root_uuid = ecs_world
+            .get::<&GameEntity>(root)
+            .map_err(|_| anyhow::anyhow!("Prefab root {:?} isn't a game entity.", root))?
+            .uuid;
+
+        let mut entities = HashSet::new();
+        let mut nested_roots = HashSet::new();
+        let mut to_visit = vec![root];
+        while let Some(entity) = to_visit.pop() {
+            if !entities.insert(entity) {
+                continue;
+            }
+            if entity != root && ecs_world.contains::<PrefabInstance>(entity) {
+                nested_roots.insert(ecs_world.get::<&GameEntity>(entity).unwrap().uuid);
+                continue;
+            }
+            if let Ok(children) = ecs_world.get::<&EntityChildren>(entity) {
+                to_visit.extend(
+                    children
+                        .children
+                        .iter()
+                        .filter(|child| ecs_world.contains::<GameEntity>(**child)),
+                );
+            }
+        }
+
+        let entity_uuid_map = ecs_world
+            .query::<&GameEntity>()
+            .into_iter()
+            .map(|(entity, game_entity)| (entity, game_entity.uuid))
+            .collect::<HashMap<_, _>>();
+        let mut scene = serde_json::to_value(ecs_world.serialize_entities(
+            &GameComponentSerializeContext {
+                voxel_registry,
+                collider_registry,
+                entity_uuid_map: &entity_uuid_map,
+            },
+            &entities,
+        ))?;
+
+        // The root is placed wherever the prefab is instantiated and isn't an instance of
+        // itself, and nested roots get their children from their own prefab.
+        for entity in scene["entities"].as_array_mut().unwrap() {
+            let components = entity["components"].as_array_mut().unwrap();
+            let uuid = Self::entity_uuid(components);
+            if uuid == Some(root_uuid) {
+                components.retain(|component| {
+                    component["name"] != EntityParent::NAME
+                        && component["name"] != PrefabInstance::NAME
+                });
+            } else if uuid.is_some_and(|uuid| nested_roots.contains(&uuid)) {
+                components.retain(|component| component["name"] != EntityChildren::NAME);
+            }
+        }
+
+        Ok(TextAsset {
+            contents: serde_json::to_string_pretty(&scene)?,
+        })
+    }
+
+    /// Spawns the prefab at `path` into `ecs_world` with fresh uuids and returns its root.
+    /// `load_prefab` reads the prefab files for `path` and any nested prefabs, a prefab nested
+    /// within itself is left as its saved root entity rather than instantiated forever.
+    pub fn instantiate(
+        path: &GameAssetPath,
+        load_prefab: &mut dyn FnMut(&GameAssetPath) -> anyhow::Result<TextAsset>,
+        ecs_world: &mut ECSWorld,
+        voxel_registry: &mut VoxelModelRegistry,
+        collider_registry: &mut ColliderRegistry,
+    ) -> anyhow::Result<Entity> {
+        Self::instantiate_nested(
+            path,
+            load_prefab,
+            ecs_world,
+            voxel_registry,
+            collider_registry,
+            &mut Vec::new(),
+        )
+    }
+
+    fn instantiate_nested(
+        path: &GameAssetPath,
+        load_prefab: &mut dyn FnMut(&GameAssetPath) -> anyhow::Result<TextAsset>,
+        ecs_world: &mut ECSWorld,
+        voxel_registry: &mut VoxelModelRegistry,
+        collider_registry: &mut ColliderRegistry,
+        instantiating: &mut Vec<GameAssetPath>,
+    ) -> anyhow::Result<Entity> {
+        let prefab = load_prefab(path)?;
+        let mut scene = serde_json::from_str::<serde_json::Value>(&prefab.contents)?;
+        let Some(entities) = scene["entities"].as_array() else {
+            anyhow::bail!("Prefab doesn't contain an `entities` array.");
+        };
+
+        let mut new_uuids = HashMap::new();
+        let mut root_uuids = Vec::new();
+        for entity in entities {
+            let components = entity["components"]
+                .as_array()
+                .map_or(&[][..], |c| c.as_slice());
+            let Some(uuid) = Self::entity_uuid(components) else {
+                anyhow::bail!("Prefab contains an entity without a GameEntity uuid.");
+            };
+            let new_uuid = Uuid::new_v4();
+            new_uuids.insert(uuid, new_uuid);
+            if !components
+                .iter()
+                .any(|component| component["name"] == EntityParent::NAME)
+            {
+                root_uuids.push(new_uuid);
+            }
+        }
+        let &[root_uuid] = root_uuids.as_slice() else {
+            anyhow::bail!(
+                "Prefab should have one root entity but has {}.",
+                root_uuids.len()
+            );
+        };
+        // Parent references are remapped along with the entities since they are uuids too.
+        remap_uuids(&mut scene, &new_uuids);
+
+        let uuid_to_entity = SceneAsset::deserialize_into(
+            &TextAsset {
+                contents: scene.to_string(),
+            },
+            ecs_world,
+            voxel_registry,
+            collider_registry,
+        )?;
+        let root = uuid_to_entity[&root_uuid];
+        ecs_world.insert_one(
+            root,
+            PrefabInstance {
+                source: path.clone(),
+            },
+        );
+
+        let nested_roots = new_uuids
+            .values()
+            .filter(|uuid| **uuid != root_uuid)
+            .filter_map(|uuid| {
+                let entity = uuid_to_entity[uuid];
+                let source = ecs_world
+                    .get::<&PrefabInstance>(entity)
+                    .ok()?
+                    .source
+                    .clone();
+                Some((entity, source))
+            })
+            .collect::<Vec<_>>();
+        instantiating.push(path.clone());
+        for (nested_root, source) in nested_roots {
+            if instantiating.contains(&source) {
+                log::error!(
+                    "Prefab {} is nested within itself, not instantiating it again.",
+                    source.as_relative_path_str()
+                );
+                continue;
+            }
+            let instance = match Self::instantiate_nested(
+                &source,
+                load_prefab,
+                ecs_world,
+                voxel_registry,
+                collider_registry,
+                instantiating,
+            ) {
+                Ok(instance) => instance,
+                Err(err) => {
+                    log::error!(
+                        "Failed to instantiate nested prefab {}: {}",
+                        source.as_relative_path_str(),
+                        err
+                    );
+                    continue;
+                }
+            };
+
+            // The nested root keeps the components saved in this prefab and takes the
+            // instance's children.
+            let children = ecs_world
+                .get::<&EntityChildren>(instance)
+                .map(|children| children.children.clone())
+                .unwrap_or_default();
+            for child in children {
+                ecs_world.set_parent(child, Some(nested_root), false);
+            }
+            ecs_world.despawn(instance, false);
+        }
+        instantiating.pop();
+
+        Ok(root)
+    }
+
+    fn entity_uuid(components: &[serde_json::Value]) -> Option<Uuid> {
+        components
+            .iter()
+            .find(|component| component["name"] == GameEntity::NAME)
+            .and_then(|component| component["data"]["uuid"].as_str())
+            .and_then(|uuid| Uuid::parse_str(uuid).ok())
+    }
+}
+
+fn remap_uuids(value: &mut serde_json::Value, new_uuids: &HashMap<Uuid, Uuid>) {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Ok(uuid) = Uuid::parse_str(s)
+                && let Some(new_uuid) = new_uuids.get(&uuid)
+            {
+                *s = new_uuid.to_string();
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                remap_uuids(value, new_uuids);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for value in map.values_mut() {
+                remap_uuids(value, new_uuids);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::transform::Transform;
+
+    #[test]
+    fn scene_relinks_parents_and_roots_dangling_children() {
+        let mut ecs_world = ECSWorld::new();
+        let parent = ecs_world.spawn((GameEntity::new("parent"), Transform::new()));
+        let child = ecs_world.spawn((GameEntity::new("child"), Transform::new()));
+        ecs_world.set_parent(child, Some(parent), false);
+        let parent_uuid = ecs_world.get::<&GameEntity>(parent).unwrap().uuid;
+        let child_uuid = ecs_world.get::<&GameEntity>(child).unwrap().uuid;
+        let scene = SceneAsset::serialize(
+            &ecs_world,
+            &VoxelModelRegistry::new(),
+            &ColliderRegistry::new(),
+        )
+        .unwrap();
+
+        let load = |scene: &TextAsset| {
+            let mut loaded_world = ECSWorld::new();
+            let uuid_to_entity = SceneAsset::deserialize_into(
+                scene,
+                &mut loaded_world,
+                &mut VoxelModelRegistry::new(),
+                &mut ColliderRegistry::new(),
+            )
+            .unwrap();
+            (loaded_world, uuid_to_entity)
+        };
+
+        let (loaded_world, uuid_to_entity) = load(&scene);
+        let loaded_child = uuid_to_entity[&child_uuid];
+        assert_eq!(
+            loaded_world
+                .get::<&EntityParent>(loaded_child)
+                .unwrap()
+                .parent(),
+            uuid_to_entity[&parent_uuid]
+        );
+
+        // Without the parent in the scene the child is loaded at the root.
+        let mut scene_json = serde_json::from_str::<serde_json::Value>(&scene.contents).unwrap();
+        scene_json["entities"]
+            .as_array_mut()
+            .unwrap()
+            .retain(|entity| !entity.to_string().contains("\"name\":\"parent\""));
+        let (loaded_world, uuid_to_entity) = load(&TextAsset {
+            contents: scene_json.to_string(),
+        });
+        assert!(!uuid_to_entity.contains_key(&parent_uuid));
+        assert!(!loaded_world.contains::<EntityParent>(uuid_to_entity[&child_uuid]));
+    }
+
+    #[test]
+    fn scene_instantiates_copies_with_fresh_uuids() {
+        let mut ecs_world = ECSWorld::new();
+        let parent = ecs_world.spawn((GameEntity::new("parent"), Transform::new()));
+        let child = ecs_world.spawn((GameEntity::new("child"), Transform::new()));
+        ecs_world.set_parent(child, Some(parent), false);
+        let scene = SceneAsset::serialize(
+            &ecs_world,
+            &VoxelModelRegistry::new(),
+            &ColliderRegistry::new(),
+        )
+        .unwrap();
+
+        let mut voxel_registry = VoxelModelRegistry::new();
+        let mut collider_registry = ColliderRegistry::new();
+        let roots = SceneAsset::instantiate(
+            &scene,
+            &mut ecs_world,
+            &mut voxel_registry,
+            &mut collider_registry,
+        )
+        .unwrap();
+        let &[root] = roots.as_slice() else {
+            panic!("Scene should have one root but has {}.", roots.len());
+        };
+        assert!(root != parent);
+        assert!(
+            ecs_world.get::<&GameEntity>(root).unwrap().uuid
+                != ecs_world.get::<&GameEntity>(parent).unwrap().uuid
+        );
+        let children = ecs_world
+            .get::<&EntityChildren>(root)
+            .unwrap()
+            .children
+            .clone();
+        let &[new_child] = children.as_slice() else {
+            panic!("The copied parent should have one child.");
+        };
+        assert!(new_child != child);
+        assert_eq!(
+            ecs_world.get::<&EntityParent>(new_child).unwrap().parent(),
+            root
+        );
+    }
+
+    #[test]
+    fn prefab_instantiates_nested_prefabs_once() {
+        let spawn_child = |ecs_world: &mut ECSWorld, name: &str, parent: Entity| {
+            let child = ecs_world.spawn((GameEntity::new(name), Transform::new()));
+            ecs_world.set_parent(child, Some(parent), false);
+            child
+        };
+        let wheel_path = GameAssetPath::new("wheel::prefab", false).unwrap();
+        let cart_path = GameAssetPath::new("cart::prefab", false).unwrap();
+
+        let mut wheel_world = ECSWorld::new();
+        let wheel = wheel_world.spawn((GameEntity::new("wheel"), Transform::new()));
+        spawn_child(&mut wheel_world, "bolt", wheel);
+        let wheel_prefab = PrefabAsset::serialize(
+            &wheel_world,
+            wheel,
+            &VoxelModelRegistry::new(),
+            &ColliderRegistry::new(),
+        )
+        .unwrap();
+
+        let mut cart_world = ECSWorld::new();
+        let cart = cart_world.spawn((GameEntity::new("cart"), Transform::new()));
+        spawn_child(&mut cart_world, "seat", cart);
+        let cart_wheel = spawn_child(&mut cart_world, "front_wheel", cart);
+        cart_world.insert_one(
+            cart_wheel,
+            PrefabInstance {
+                source: wheel_path.clone(),
+            },
+        );
+        // Not saved since the wheel's children come from its own prefab.
+        spawn_child(&mut cart_world, "stale_bolt", cart_wheel);
+        let nested_cart = spawn_child(&mut cart_world, "nested_cart", cart);
+        cart_world.insert_one(
+            nested_cart,
+            PrefabInstance {
+                source: cart_path.clone(),
+            },
+        );
+        let cart_prefab = PrefabAsset::serialize(
+            &cart_world,
+            cart,
+            &VoxelModelRegistry::new(),
+            &ColliderRegistry::new(),
+        )
+        .unwrap();
+
+        let mut ecs_world = ECSWorld::new();
+        let instance = PrefabAsset::instantiate(
+            &cart_path,
+            &mut |path| {
+                let prefab = if *path == cart_path {
+                    &cart_prefab
+                } else {
+                    &wheel_prefab
+                };
+                Ok(TextAsset {
+                    contents: prefab.contents.clone(),
+                })
+            },
+            &mut ecs_world,
+            &mut VoxelModelRegistry::new(),
+            &mut ColliderRegistry::new(),
+        )
+        .unwrap();
+
+        assert!(ecs_world.get::<&PrefabInstance>(instance).unwrap().source == cart_path);
+        assert_ne!(
+            ecs_world.get::<&GameEntity>(instance).unwrap().uuid,
+            cart_world.get::<&GameEntity>(cart).unwrap().uuid
+        );
+        let names = ecs_world
+            .query::<&GameEntity>()
+            .into_iter()
+            .map(|(entity, game_entity)| (game_entity.name.clone(), entity))
+            .collect::<HashMap<_, _>>();
+        let mut sorted_names = names.keys().cloned().collect::<Vec<_>>();
+        sorted_names.sort();
+        // The cart nested within itself is left as its saved root.
+        assert_eq!(
+            sorted_names,
+            ["bolt", "cart", "front_wheel", "nested_cart", "seat"]
+        );
+        assert_eq!(
+            ecs_world
+                .get::<&EntityParent>(names["bolt"])
+                .unwrap()
+                .parent(),
+            names["front_wheel"]
+        );
+        assert!(!ecs_world.contains::<EntityChildren>(names["nested_cart"]));
+    }
+}
@@ -1,6 +1,6 @@
-use std::io::BufReader;
+use std::io::{BufReader, BufWriter};
 
-use crate::asset::asset::{AssetFile, AssetLoadError, AssetLoader};
+use crate::asset::asset::{AssetFile, AssetLoadError, AssetLoader, AssetSaver};
 use nalgebra::Vector2;
 pub struct ImageAsset {
     pub data: Vec<u8>,
@@ -118,3 +118,33 @@ impl AssetLoader for ImageAsset {
         }
     }
 }
+
+impl AssetSaver for ImageAsset {
+    fn save(data: &Self, out_file: &AssetFile) -> anyhow::Result<()>
+    where
+        Self: Sized,
+    {
+        match out_file.path().extension() {
+            "png" => {
+                let writer = BufWriter::new(out_file.write_file());
+                let mut encoder = png::Encoder::new(writer, data.size.x, data.size.y);
+                encoder.set_color(match data.format {
+                    ImageAssetFormat::RGB => png::ColorType::Rgb,
+                    ImageAssetFormat::RGBA => png::ColorType::Rgba,
+                });
+                encoder.set_depth(png::BitDepth::Eight);
+                let mut writer = encoder
+                    .write_header()
+                    .map_err(|err| anyhow::anyhow!("Failed to write png header: {}", err))?;
+                writer
+                    .write_image_data(&data.data)
+                    .map_err(|err| anyhow::anyhow!("Failed to write png data: {}", err))?;
+                Ok(())
+            }
+            ext => Err(anyhow::anyhow!(
+                "Unsupported extension \"{}\" for saving images.",
+                ext
+            )),
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use super::asset::{AssetLoadError, AssetLoader, AssetSaver};
 
+pub mod collider;
 pub mod game_entity;
 pub mod image;
 pub mod project;
 pub mod settings;
+pub mod voxel;
 
 pub struct TextAsset {
     pub contents: String,
@@ -11,7 +11,7 @@ use crate::material::material_bank::{
 
 use crate::asset::{
     asset::{AssetPath, Assets},
-    repr::TextAsset,
+    repr::{TextAsset, collider::joint::JointAsset},
 };
 use crate::entity::{
     EntityChildren, EntityParent, GameEntity,
@@ -23,16 +23,27 @@ use crate::material::MaterialAsset;
 use crate::physics::physics_world::PhysicsWorld;
 use crate::voxel::voxel_registry::VoxelModelRegistry;
 use crate::world::terrain::region_map::RegionMap;
+use crate::world::sky::SkyLight;
+use crate::world::terrain::terrain_generator::TerrainGeneratorSettings;
 use serde::{Deserializer, ser::SerializeStruct};
 
 #[derive(Clone)]
 pub struct ProjectSettings {
     pub game_camera: Option<Entity>,
+    /// The seed of the project's `Rng`.
+    pub seed: u64,
+    pub terrain_generator: TerrainGeneratorSettings,
+    pub sky_light: SkyLight,
 }
 
 impl ProjectSettings {
     pub fn new_empty() -> Self {
-        Self { game_camera: None }
+        Self {
+            game_camera: None,
+            seed: 0,
+            terrain_generator: TerrainGeneratorSettings::default(),
+            sky_light: SkyLight::default(),
+        }
     }
 
     pub fn as_serializable(&self, ecs_world: &ECSWorld) -> ProjectSettingsSerializable {
@@ -41,6 +52,9 @@ impl ProjectSettings {
             .map(|e| ecs_world.get::<&GameEntity>(e).unwrap().uuid.clone());
         ProjectSettingsSerializable {
             game_camera: game_camera_uuid,
+            seed: self.seed,
+            terrain_generator: self.terrain_generator.clone(),
+            sky_light: self.sky_light.clone(),
         }
     }
 }
@@ -48,6 +62,12 @@ impl ProjectSettings {
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct ProjectSettingsSerializable {
     pub game_camera: Option<Uuid>,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub terrain_generator: TerrainGeneratorSettings,
+    #[serde(default)]
+    pub sky_light: SkyLight,
 }
 
 pub struct ProjectAssetRaw {
@@ -70,6 +90,9 @@ pub struct ProjectSerializeContext<'a> {
     pub material_bank: &'a MaterialBank,
     pub main_camera: &'a MainCamera,
     pub region_map: &'a RegionMap,
+    pub rng: &'a Rng,
+    pub terrain_generator: &'a TerrainGeneratorSettings,
+    pub sky_light: &'a SkyLight,
     pub game_camera: Option<Entity>,
 }
 
@@ -91,7 +114,7 @@ impl ProjectAsset {
         ))?;
         let mut de = serde_json::Deserializer::from_str(&json_text.contents);
 
-        const FIELDS: [&str; 3] = ["material_bank", "project_settings", "scene"];
+        const FIELDS: [&str; 4] = ["material_bank", "project_settings", "scene", "joints"];
         Ok(de.deserialize_struct(
             "project",
             &FIELDS,
@@ -105,6 +128,9 @@ impl ProjectAsset {
     pub fn serialize(context: ProjectSerializeContext<'_>) -> anyhow::Result<TextAsset> {
         let project_settings = ProjectSettings {
             game_camera: context.game_camera,
+            seed: context.rng.seed(),
+            terrain_generator: context.terrain_generator.clone(),
+            sky_light: context.sky_light.clone(),
         };
 
         let mut str = serde_json::to_string_pretty(&ProjectSerializer {
@@ -152,6 +178,13 @@ impl serde::Serialize for ProjectSerializer<'_> {
                     entity_uuid_map: &entity_uuid_map,
                 }),
         );
+
+        let joints = self
+            .physics_world
+            .joints()
+            .filter_map(|(_, joint)| JointAsset::from_joint(joint, &entity_uuid_map))
+            .collect::<Vec<_>>();
+        s.serialize_field("joints", &joints);
         s.end()
     }
 }
@@ -166,6 +199,7 @@ enum ProjectField {
     ProjectSettings,
     Scene,
     MaterialBank,
+    Joints,
 }
 
 impl<'de> serde::de::Visitor<'de> for ProjectVisitor {
@@ -187,6 +221,7 @@ impl<'de> serde::de::Visitor<'de> for ProjectVisitor {
         let mut project_settings_ser = None;
         let mut visited_scene = false;
         let mut visited_materials = false;
+        let mut joints = None;
         while let Some(key) = map.next_key::<ProjectField>()? {
             match key {
                 ProjectField::ProjectSettings => {
@@ -224,6 +259,12 @@ impl<'de> serde::de::Visitor<'de> for ProjectVisitor {
                         material_bank: &mut material_bank,
                     })?;
                 }
+                ProjectField::Joints => {
+                    if joints.is_some() {
+                        return Err(serde::de::Error::duplicate_field("joints"));
+                    }
+                    joints = Some(map.next_value::<Vec<JointAsset>>()?);
+                }
             }
         }
 
@@ -249,7 +290,23 @@ impl<'de> serde::de::Visitor<'de> for ProjectVisitor {
             .transpose()?
             .map(|e| *e);
 
-        let project_settings = ProjectSettings { game_camera };
+        let project_settings = ProjectSettings {
+            game_camera,
+            seed: project_settings_ser.seed,
+            terrain_generator: project_settings_ser.terrain_generator,
+            sky_light: project_settings_ser.sky_light,
+        };
+
+        // Projects from before joints existed don't have the field.
+        for joint in joints.unwrap_or_default() {
+            let Some(joint) = joint.to_joint(&uuid_to_entity_map) else {
+                log::warn!("Skipping joint between entities which don't exist.");
+                continue;
+            };
+            if let Err(err) = physics_world.add_joint(joint) {
+                log::warn!("Skipping invalid joint. Error: {:?}", err);
+            }
+        }
 
         Ok(ProjectAsset {
             project_dir: Some(self.project_dir),
@@ -335,18 +392,16 @@ impl<'de> serde::de::Visitor<'de> for ProjectSceneVisitor<'_> {
 
         // Populate the EntityParent and EntityChildren entity references.
         for (child_entity, parent_uuid) in self.ctx.to_parent_entities.drain(..) {
+            let Some(parent_entity_id) = self.ctx.uuid_to_entity_map.get(&parent_uuid) else {
+                log::warn!(
+                    "Entity references parent with uuid {} but that doesn't exist, moving it to the root.",
+                    parent_uuid
+                );
+                self.ctx.ecs_world.remove_one::<EntityParent>(child_entity);
+                continue;
+            };
             let mut parent_component = self.ctx.ecs_world.get::<&mut EntityParent>(child_entity)
                 .expect("If entity is in to_parent_entities but doesnt have an EntityParent component something logic wise went wrong.");
-            let parent_entity_id = self
-                .ctx
-                .uuid_to_entity_map
-                .get(&parent_uuid)
-                .unwrap_or_else(|| {
-                    panic!(
-                        "Entity references parent with uuid {} but that doesn't exist",
-                        parent_uuid.to_string()
-                    )
-                });
             parent_component.set_parent(*parent_entity_id);
 
             let mut children_component = self.ctx.ecs_world.get::<&mut EntityChildren>(*parent_entity_id)
@@ -1,23 +1,110 @@
-use nalgebra::Vector3;
+use nalgebra::{Vector2, Vector3};
 
-use crate::asset::asset::{
-    impl_asset_load_save_serde, AssetFile, AssetLoadError, AssetLoader, AssetSaver,
+use crate::{
+    asset::asset::{
+        impl_asset_load_save_serde, AssetFile, AssetLoadError, AssetLoader, AssetPath, AssetSaver,
+        Assets,
+    },
+    consts,
+    graphics::backend::{GfxPresentMode, GfxValidation},
+    input::mapper::Keybinds,
+    settings::{GraphicsSettings, Settings},
 };
 
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct UserSettingsAsset {
     pub mouse_sensitivity: f32,
+    pub game_mouse_sensitivity: f32,
     pub controller_sensitivity: f32,
+    pub gamepad_deadzone: f32,
+    pub camera_shake_decay: f32,
+    pub camera_shake_max_angle: f32,
+    pub camera_shake_max_offset: f32,
     pub chunk_render_distance: u32,
+    pub graphics_validation: GfxValidation,
+    pub present_mode: GfxPresentMode,
+    /// 0 is uncapped.
+    pub frame_rate_cap: u32,
+    pub rt_workgroup_size: Option<Vector2<u32>>,
+    pub rt_workgroup_size_override: Option<Vector2<u32>>,
+    pub keybinds: Keybinds,
 }
 
 impl Default for UserSettingsAsset {
     fn default() -> Self {
         Self {
             mouse_sensitivity: 0.001,
+            game_mouse_sensitivity: 0.0005,
             controller_sensitivity: 90.0f32.to_radians(),
+            gamepad_deadzone: 0.1,
+            camera_shake_decay: 1.5,
+            camera_shake_max_angle: 0.05,
+            camera_shake_max_offset: 0.1,
             chunk_render_distance: 24,
+            graphics_validation: GraphicsSettings::default().validation,
+            present_mode: GraphicsSettings::default().present_mode,
+            frame_rate_cap: 0,
+            rt_workgroup_size: None,
+            rt_workgroup_size_override: None,
+            keybinds: Keybinds::default_keybinds(),
+        }
+    }
+}
+
+impl UserSettingsAsset {
+    pub fn new(settings: &Settings, keybinds: &Keybinds) -> Self {
+        Self {
+            mouse_sensitivity: settings.editor_mouse_sensitivity,
+            game_mouse_sensitivity: settings.game_mouse_sensitivity,
+            controller_sensitivity: settings.controller_sensitity,
+            gamepad_deadzone: settings.gamepad_deadzone,
+            camera_shake_decay: settings.camera_shake_decay,
+            camera_shake_max_angle: settings.camera_shake_max_angle,
+            camera_shake_max_offset: settings.camera_shake_max_offset,
+            chunk_render_distance: settings.chunk_render_distance,
+            graphics_validation: settings.graphics.validation,
+            present_mode: settings.graphics.present_mode,
+            frame_rate_cap: settings.frame_rate_cap,
+            rt_workgroup_size: settings.graphics.rt_workgroup_size,
+            rt_workgroup_size_override: settings.graphics.rt_workgroup_size_override,
+            keybinds: keybinds.clone(),
+        }
+    }
+
+    pub fn load_user_settings() -> Self {
+        let settings_path = AssetPath::new_user_dir(consts::io::GAME_USER_SETTINGS_FILE);
+        let mut settings = match Assets::load_asset_sync::<UserSettingsAsset>(settings_path.clone())
+        {
+            Ok(settings) => settings,
+            Err(AssetLoadError::NotFound { .. }) => {
+                log::info!(
+                    "Couldn't find existing user settings at {:?}",
+                    settings_path
+                );
+                Self::default()
+            }
+            Err(AssetLoadError::Other(error)) => {
+                log::error!(
+                    "Error when trying to load user settings at {:?}. Error: {:?}",
+                    settings_path,
+                    error
+                );
+                Self::default()
+            }
+        };
+        settings.keybinds.insert_missing_defaults();
+        settings
+    }
+
+    pub fn save_user_settings(&self) {
+        let settings_path = AssetPath::new_user_dir(consts::io::GAME_USER_SETTINGS_FILE);
+        if let Err(error) = Assets::save_asset_sync(settings_path.clone(), self) {
+            log::error!(
+                "Error when trying to save user settings at {:?}. Error: {:?}",
+                settings_path,
+                error
+            );
         }
     }
 }
@@ -0,0 +1,117 @@
+use std::path::Path;
+
+use crate::{
+    asset::asset::{AssetLoadError, AssetPath, Assets, impl_asset_load_save_serde},
+    consts,
+    voxel::voxel::VoxelMaterialData,
+};
+
+/// A project's named voxel colors and materials, painting with these keeps the same values
+/// used across a build rather than picking raw colors by hand each time.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct VoxelMaterialPaletteAsset {
+    pub entries: Vec<VoxelMaterialPaletteEntry>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct VoxelMaterialPaletteEntry {
+    pub name: String,
+    pub material: VoxelMaterialData,
+}
+
+impl VoxelMaterialPaletteAsset {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds an entry returning its index, the name is suffixed with a number if it is already
+    /// taken so entries can always be told apart by name.
+    pub fn push(&mut self, name: impl ToString, material: VoxelMaterialData) -> usize {
+        let base_name = name.to_string();
+        let mut name = base_name.clone();
+        let mut suffix = 1;
+        while self.find_by_name(&name).is_some() {
+            name = format!("{} {}", base_name, suffix);
+            suffix += 1;
+        }
+        self.entries
+            .push(VoxelMaterialPaletteEntry { name, material });
+        self.entries.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> VoxelMaterialPaletteEntry {
+        self.entries.remove(index)
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&VoxelMaterialPaletteEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Loads the palette saved in `project_dir`, a project without one starts with an empty
+    /// palette.
+    pub fn load_project_palette(project_dir: &Path) -> Self {
+        let palette_path = AssetPath::new(project_dir.join(consts::io::PROJECT_VOXEL_PALETTE_FILE));
+        match Assets::load_asset_sync::<VoxelMaterialPaletteAsset>(palette_path.clone()) {
+            Ok(palette) => palette,
+            Err(AssetLoadError::NotFound { .. }) => Self::new(),
+            Err(AssetLoadError::Other(error)) => {
+                log::error!(
+                    "Error when trying to load voxel palette at {:?}. Error: {:?}",
+                    palette_path,
+                    error
+                );
+                Self::new()
+            }
+        }
+    }
+
+    pub fn save_project_palette(&self, project_dir: &Path) {
+        let palette_path = AssetPath::new(project_dir.join(consts::io::PROJECT_VOXEL_PALETTE_FILE));
+        if let Err(error) = Assets::save_asset_sync(palette_path.clone(), self) {
+            log::error!(
+                "Error when trying to save voxel palette at {:?}. Error: {:?}",
+                palette_path,
+                error
+            );
+        }
+    }
+}
+
+impl_asset_load_save_serde!(VoxelMaterialPaletteAsset);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::color::ColorSrgba;
+
+    #[test]
+    fn palette_names_stay_unique() {
+        let mut palette = VoxelMaterialPaletteAsset::new();
+        let grass = VoxelMaterialData::Baked {
+            color: ColorSrgba::new(0.2, 0.6, 0.1, 1.0),
+        };
+        assert_eq!(palette.push("Grass", grass.clone()), 0);
+        assert_eq!(palette.push("Grass", grass.clone()), 1);
+        assert_eq!(palette.push("Stone", VoxelMaterialData::Unbaked(3)), 2);
+        assert_eq!(palette.push("Grass", grass.clone()), 3);
+        let names = palette
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Grass", "Grass 1", "Stone", "Grass 2"]);
+
+        palette.remove(0);
+        assert!(palette.find_by_name("Grass").is_none());
+        assert_eq!(palette.push("Grass", grass), 3);
+        assert_eq!(palette.entries[3].name, "Grass");
+
+        let json = serde_json::to_string(&palette).unwrap();
+        let loaded = serde_json::from_str::<VoxelMaterialPaletteAsset>(&json).unwrap();
+        assert!(loaded.entries == palette.entries);
+        assert!(loaded.find_by_name("Stone").unwrap().material == VoxelMaterialData::Unbaked(3));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod material_palette;
+pub mod vox;
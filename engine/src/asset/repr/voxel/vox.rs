@@ -0,0 +1,578 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use nalgebra::Vector3;
+
+use crate::{
+    asset::asset::{AssetFile, AssetLoadError, AssetLoader, AssetSaver},
+    common::color::{Color, Palette},
+    voxel::{
+        attachment::{Attachment, PTMaterial},
+        flat::VoxelModelFlat,
+    },
+};
+
+/// The models of a MagicaVoxel `.vox` file, one per `SIZE`/`XYZI` chunk pair in file order.
+/// Spec: https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt
+pub struct VoxAsset {
+    pub models: Vec<VoxelModelFlat>,
+}
+
+impl VoxAsset {
+    pub fn supported_extensions() -> &'static [&'static str] {
+        &["vox"]
+    }
+
+    /// Parses the raw bytes of a `.vox` file, scene graph, material and other chunks are
+    /// skipped since only the voxels and palette map onto our models.
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = VoxReader { bytes, offset: 0 };
+        if reader.read_id()? != *b"VOX " {
+            anyhow::bail!("File is not a MagicaVoxel .vox file.");
+        }
+        let _version = reader.read_u32()?;
+
+        let main = reader.read_chunk()?;
+        if main.id != *b"MAIN" {
+            anyhow::bail!("Expected the MAIN chunk but found {:?}.", main.id_str());
+        }
+
+        let mut children = VoxReader {
+            bytes: main.children,
+            offset: 0,
+        };
+        let mut sizes = Vec::new();
+        let mut voxels = Vec::new();
+        let mut palette = None;
+        while !children.is_empty() {
+            let chunk = children.read_chunk()?;
+            let mut content = VoxReader {
+                bytes: chunk.content,
+                offset: 0,
+            };
+            match &chunk.id {
+                b"SIZE" => {
+                    let size = Vector3::new(
+                        content.read_u32()?,
+                        content.read_u32()?,
+                        content.read_u32()?,
+                    );
+                    // Positions are bytes so larger models are malformed, and would overflow
+                    // the voxel count.
+                    if size.iter().any(|x| *x > Self::MAX_SIDE_LENGTH) {
+                        anyhow::bail!(
+                            "Model has SIZE {:?} but each axis must be at most {}.",
+                            size.as_slice(),
+                            Self::MAX_SIDE_LENGTH
+                        );
+                    }
+                    sizes.push(size);
+                }
+                b"XYZI" => {
+                    let count = content.read_u32()? as usize;
+                    let data = content.read_bytes(count * 4)?;
+                    voxels.push(
+                        data.chunks_exact(4)
+                            .map(|v| ([v[0], v[1], v[2]], v[3]))
+                            .collect::<Vec<_>>(),
+                    );
+                }
+                b"RGBA" => {
+                    // Color index `i` maps to `i - 1` in the chunk, index 0 is always empty.
+                    let data = content.read_bytes(256 * 4)?;
+                    let mut rgba = [[0u8; 4]; 256];
+                    for (i, color) in data.chunks_exact(4).take(255).enumerate() {
+                        rgba[i + 1] = [color[0], color[1], color[2], color[3]];
+                    }
+                    palette = Some(rgba);
+                }
+                _ => {}
+            }
+        }
+
+        if sizes.len() != voxels.len() {
+            anyhow::bail!(
+                "Found {} SIZE chunks but {} XYZI chunks.",
+                sizes.len(),
+                voxels.len()
+            );
+        }
+        let palette = palette.unwrap_or_else(default_palette);
+
+        let models = sizes
+            .into_iter()
+            .zip(voxels)
+            .map(|(size, voxels)| Self::create_model(size, &voxels, &palette))
+            .collect();
+        Ok(Self { models })
+    }
+
+    fn create_model(
+        vox_size: Vector3<u32>,
+        voxels: &[([u8; 3], u8)],
+        palette: &[[u8; 4]; 256],
+    ) -> VoxelModelFlat {
+        // MagicaVoxel is z-up, swap to y-up and flip the depth axis to keep the handedness.
+        let side_length = Vector3::new(vox_size.x, vox_size.z, vox_size.y).map(|x| x.max(1));
+        let mut flat_model = VoxelModelFlat::new_empty(side_length);
+        for ([x, y, z], color_index) in voxels {
+            let position = Vector3::new(
+                *x as u32,
+                *z as u32,
+                side_length.z.saturating_sub(1 + *y as u32),
+            )
+            .zip_map(&side_length, |x, max| x.min(max - 1));
+            let [r, g, b, _a] = palette[*color_index as usize];
+            let color = Color::new_srgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+            flat_model.get_voxel_mut(position).set_attachment(
+                Attachment::PTMATERIAL,
+                Some(PTMaterial::diffuse(color).encode()),
+            );
+        }
+        // `.vox` files have no normals, derive them from the shape so lighting isn't flat.
+        flat_model.compute_normals();
+        flat_model.compute_ao();
+
+        flat_model
+    }
+
+    /// The largest side length a `.vox` model can have since positions are stored as bytes.
+    pub const MAX_SIDE_LENGTH: u32 = 256;
+    /// Palette index 0 is reserved for empty voxels.
+    const MAX_PALETTE_COLORS: usize = 255;
+
+    /// Serializes models into a `.vox` file sharing one palette. Voxels without a
+    /// `PTMaterial` attachment are skipped since they have no color to export, and colors are
+    /// quantized with median cut if there are more than the palette can hold.
+    pub fn write(models: &[&VoxelModelFlat]) -> anyhow::Result<Vec<u8>> {
+        for model in models {
+            let side_length = model.side_length();
+            if side_length.iter().any(|x| *x > Self::MAX_SIDE_LENGTH) {
+                anyhow::bail!(
+                    "Model with side length {:?} can't be exported to .vox, each axis must be at most {}.",
+                    side_length.as_slice(),
+                    Self::MAX_SIDE_LENGTH
+                );
+            }
+        }
+
+        let model_voxels = models
+            .iter()
+            .map(|model| Self::model_voxel_colors(model))
+            .collect::<Vec<_>>();
+        let colors = model_voxels
+            .iter()
+            .flatten()
+            .map(|(_, color)| *color)
+            .collect::<Vec<_>>();
+        let palette = quantize_palette(&colors);
+        let srgb_palette = Palette::from_colors(
+            palette
+                .iter()
+                .map(|[r, g, b]| {
+                    Color::new_srgb(*r as f32 / 255.0, *g as f32 / 255.0, *b as f32 / 255.0)
+                })
+                .collect(),
+        );
+        let mut color_indices = HashMap::new();
+
+        let mut children = Vec::new();
+        write_chunk(&mut children, b"PACK", &(models.len() as u32).to_le_bytes());
+        for (model, voxels) in models.iter().zip(&model_voxels) {
+            let side_length = model.side_length();
+            let vox_size = Vector3::new(side_length.x, side_length.z, side_length.y);
+            let mut size = Vec::new();
+            for x in vox_size.iter() {
+                size.extend_from_slice(&x.to_le_bytes());
+            }
+            write_chunk(&mut children, b"SIZE", &size);
+
+            let mut xyzi = (voxels.len() as u32).to_le_bytes().to_vec();
+            for (position, color) in voxels {
+                let color_index = *color_indices.entry(*color).or_insert_with(|| {
+                    let [r, g, b] = *color;
+                    let color =
+                        Color::new_srgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+                    srgb_palette.nearest(&color) as u8 + 1
+                });
+                // Inverse of the axis swap when loading.
+                xyzi.extend_from_slice(&[
+                    position.x as u8,
+                    (vox_size.y - 1 - position.z) as u8,
+                    position.y as u8,
+                    color_index,
+                ]);
+            }
+            write_chunk(&mut children, b"XYZI", &xyzi);
+        }
+
+        let mut rgba = vec![0u8; 256 * 4];
+        for (i, [r, g, b]) in palette.iter().enumerate() {
+            rgba[i * 4..i * 4 + 4].copy_from_slice(&[*r, *g, *b, 0xFF]);
+        }
+        write_chunk(&mut children, b"RGBA", &rgba);
+
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&150u32.to_le_bytes());
+        bytes.extend_from_slice(b"MAIN");
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        bytes.extend(children);
+        Ok(bytes)
+    }
+
+    fn model_voxel_colors(model: &VoxelModelFlat) -> Vec<(Vector3<u32>, [u8; 3])> {
+        let (Some(material_presence), Some(material_data)) = (
+            model
+                .attachment_presence_data
+                .get(Attachment::PTMATERIAL_ID),
+            model.attachment_data.get(Attachment::PTMATERIAL_ID),
+        ) else {
+            return Vec::new();
+        };
+
+        (0..model.volume())
+            .filter(|i| model.presence_data.get_bit(*i) && material_presence.get_bit(*i))
+            .map(|i| {
+                let PTMaterial::Diffuse { albedo } =
+                    Attachment::decode_ptmaterial(&material_data[i]);
+                let color = albedo.xyz.map(|x| (x * 255.0).round() as u8);
+                (model.get_voxel_position(i), [color.x, color.y, color.z])
+            })
+            .collect()
+    }
+}
+
+impl AssetSaver for VoxAsset {
+    fn save(data: &Self, out_file: &AssetFile) -> anyhow::Result<()>
+    where
+        Self: Sized,
+    {
+        let bytes = Self::write(&data.models.iter().collect::<Vec<_>>())?;
+        out_file.write_file().write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+impl AssetLoader for VoxAsset {
+    fn load(data: &AssetFile) -> std::result::Result<Self, AssetLoadError>
+    where
+        Self: Sized + std::any::Any,
+    {
+        let mut bytes = Vec::new();
+        data.read_file()?.read_to_end(&mut bytes)?;
+        Ok(Self::parse(&bytes)?)
+    }
+}
+
+struct VoxChunk<'a> {
+    id: [u8; 4],
+    content: &'a [u8],
+    children: &'a [u8],
+}
+
+impl VoxChunk<'_> {
+    fn id_str(&self) -> String {
+        String::from_utf8_lossy(&self.id).into_owned()
+    }
+}
+
+struct VoxReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> VoxReader<'a> {
+    fn is_empty(&self) -> bool {
+        self.offset >= self.bytes.len()
+    }
+
+    fn read_bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let Some(bytes) = self.bytes.get(self.offset..self.offset + len) else {
+            anyhow::bail!("Unexpected end of .vox data at byte {}.", self.offset);
+        };
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn read_id(&mut self) -> anyhow::Result<[u8; 4]> {
+        Ok(self.read_bytes(4)?.try_into().unwrap())
+    }
+
+    fn read_u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_chunk(&mut self) -> anyhow::Result<VoxChunk<'a>> {
+        let id = self.read_id()?;
+        let content_len = self.read_u32()? as usize;
+        let children_len = self.read_u32()? as usize;
+        Ok(VoxChunk {
+            id,
+            content: self.read_bytes(content_len)?,
+            children: self.read_bytes(children_len)?,
+        })
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(content);
+}
+
+/// Reduces `colors` to at most 255 palette colors using median cut, the colors are used as is
+/// if they already fit.
+fn quantize_palette(colors: &[[u8; 3]]) -> Vec<[u8; 3]> {
+    let mut unique = colors.to_vec();
+    unique.sort();
+    unique.dedup();
+    if unique.len() <= VoxAsset::MAX_PALETTE_COLORS {
+        return unique;
+    }
+
+    // Boxes are split on the channel with the widest range at the median until there are
+    // enough boxes, weighted by voxel count so common colors get more precision.
+    let channel_range = |colors: &[[u8; 3]], channel: usize| {
+        let (min, max) = colors.iter().fold((u8::MAX, u8::MIN), |(min, max), color| {
+            (min.min(color[channel]), max.max(color[channel]))
+        });
+        max.saturating_sub(min)
+    };
+    let widest_channel = |colors: &[[u8; 3]]| {
+        (0..3)
+            .map(|channel| (channel, channel_range(colors, channel)))
+            .max_by_key(|(_, range)| *range)
+            .unwrap()
+    };
+    let mut boxes = vec![colors.to_vec()];
+    while boxes.len() < VoxAsset::MAX_PALETTE_COLORS {
+        let Some((box_index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, colors)| (i, widest_channel(colors)))
+            .filter(|(_, (_, range))| *range > 0)
+            .max_by_key(|(_, (_, range))| *range)
+            .map(|(i, (channel, _))| (i, channel))
+        else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(box_index);
+        colors.sort_by_key(|color| color[channel]);
+        let upper = colors.split_off(colors.len() / 2);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|colors| {
+            let sum = colors.iter().fold([0u64; 3], |sum, color| {
+                [
+                    sum[0] + color[0] as u64,
+                    sum[1] + color[1] as u64,
+                    sum[2] + color[2] as u64,
+                ]
+            });
+            sum.map(|x| (x / colors.len() as u64) as u8)
+        })
+        .collect()
+}
+
+/// The palette MagicaVoxel uses when a file has no RGBA chunk, a 6x6x6 color cube without
+/// black followed by ramps of red, green, blue and grey.
+fn default_palette() -> [[u8; 4]; 256] {
+    const CUBE: [u8; 6] = [0xFF, 0xCC, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xEE, 0xDD, 0xBB, 0xAA, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+    let mut palette = [[0u8; 4]; 256];
+    let mut i = 1;
+    for r in CUBE {
+        for g in CUBE {
+            for b in CUBE {
+                if r == 0 && g == 0 && b == 0 {
+                    continue;
+                }
+                palette[i] = [r, g, b, 0xFF];
+                i += 1;
+            }
+        }
+    }
+    for channel in 0..4 {
+        for x in RAMP {
+            palette[i] = match channel {
+                0 => [x, 0, 0, 0xFF],
+                1 => [0, x, 0, 0xFF],
+                2 => [0, 0, x, 0xFF],
+                _ => [x, x, x, 0xFF],
+            };
+            i += 1;
+        }
+    }
+    debug_assert_eq!(i, 256);
+
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(content);
+        bytes.extend_from_slice(children);
+        bytes
+    }
+
+    fn size_chunk(x: u32, y: u32, z: u32) -> Vec<u8> {
+        let content = [x, y, z]
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect::<Vec<_>>();
+        chunk(b"SIZE", &content, &[])
+    }
+
+    fn xyzi_chunk(voxels: &[[u8; 4]]) -> Vec<u8> {
+        let mut content = (voxels.len() as u32).to_le_bytes().to_vec();
+        content.extend(voxels.iter().flatten());
+        chunk(b"XYZI", &content, &[])
+    }
+
+    fn vox_file(children: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&150u32.to_le_bytes());
+        bytes.extend(chunk(b"MAIN", &[], &children.concat()));
+        bytes
+    }
+
+    fn material_at(model: &VoxelModelFlat, position: Vector3<u32>) -> Option<u32> {
+        let voxel = model.get_voxel(position);
+        voxel.exists().then(|| {
+            model
+                .attachment_data
+                .get(Attachment::PTMATERIAL_ID)
+                .unwrap()[voxel.index]
+        })
+    }
+
+    fn encoded(r: u8, g: u8, b: u8) -> u32 {
+        PTMaterial::diffuse(Color::new_srgb(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+        ))
+        .encode()
+    }
+
+    #[test]
+    fn parse_models_and_palette() {
+        let mut rgba = vec![0u8; 256 * 4];
+        rgba[0..4].copy_from_slice(&[255, 0, 0, 255]);
+        rgba[4..8].copy_from_slice(&[0, 0, 255, 255]);
+        let bytes = vox_file(&[
+            chunk(b"PACK", &2u32.to_le_bytes(), &[]),
+            size_chunk(2, 3, 4),
+            // The last voxel is out of the model's bounds and gets clamped.
+            xyzi_chunk(&[[0, 0, 0, 1], [1, 2, 3, 2], [9, 0, 0, 1]]),
+            size_chunk(1, 1, 1),
+            xyzi_chunk(&[[0, 0, 0, 2]]),
+            chunk(b"nTRN", &[0; 12], &[]),
+            chunk(b"RGBA", &rgba, &[]),
+        ]);
+
+        let vox = VoxAsset::parse(&bytes).unwrap();
+        assert_eq!(vox.models.len(), 2);
+
+        let model = &vox.models[0];
+        assert_eq!(*model.side_length(), Vector3::new(2, 4, 3));
+        assert_eq!(
+            material_at(model, Vector3::new(0, 0, 2)),
+            Some(encoded(255, 0, 0))
+        );
+        assert_eq!(
+            material_at(model, Vector3::new(1, 3, 0)),
+            Some(encoded(0, 0, 255))
+        );
+        assert_eq!(
+            material_at(model, Vector3::new(1, 0, 2)),
+            Some(encoded(255, 0, 0))
+        );
+        assert_eq!(material_at(model, Vector3::new(0, 0, 0)), None);
+
+        let model = &vox.models[1];
+        assert_eq!(*model.side_length(), Vector3::new(1, 1, 1));
+        assert_eq!(
+            material_at(model, Vector3::zeros()),
+            Some(encoded(0, 0, 255))
+        );
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let mut model = VoxelModelFlat::new_empty(Vector3::new(3, 2, 4));
+        let voxels = [
+            (Vector3::new(0, 0, 0), (255, 0, 0)),
+            (Vector3::new(2, 1, 3), (12, 34, 56)),
+            (Vector3::new(1, 0, 2), (255, 0, 0)),
+        ];
+        for (position, (r, g, b)) in voxels {
+            model
+                .get_voxel_mut(position)
+                .set_attachment(Attachment::PTMATERIAL, Some(encoded(r, g, b)));
+        }
+
+        let bytes = VoxAsset::write(&[&model]).unwrap();
+        let vox = VoxAsset::parse(&bytes).unwrap();
+        assert_eq!(vox.models.len(), 1);
+        let loaded = &vox.models[0];
+        assert_eq!(loaded.side_length(), model.side_length());
+        for i in 0..model.volume() {
+            let position = model.get_voxel_position(i);
+            assert_eq!(material_at(loaded, position), material_at(&model, position));
+        }
+
+        let too_large = VoxelModelFlat::new_empty(Vector3::new(257, 1, 1));
+        assert!(VoxAsset::write(&[&too_large]).is_err());
+    }
+
+    #[test]
+    fn quantize_many_colors() {
+        let colors = (0..1000u32)
+            .map(|i| [(i % 256) as u8, (i * 7 % 256) as u8, (i / 4) as u8])
+            .collect::<Vec<_>>();
+        let palette = quantize_palette(&colors);
+        assert!(palette.len() <= 255);
+        assert!(palette.len() > 200);
+
+        // Few colors are kept exactly.
+        let colors = [[1, 2, 3], [4, 5, 6], [1, 2, 3]];
+        assert_eq!(quantize_palette(&colors), vec![[1, 2, 3], [4, 5, 6]]);
+    }
+
+    #[test]
+    fn parse_default_palette() {
+        let bytes = vox_file(&[size_chunk(1, 1, 1), xyzi_chunk(&[[0, 0, 0, 7]])]);
+        let vox = VoxAsset::parse(&bytes).unwrap();
+        assert_eq!(
+            material_at(&vox.models[0], Vector3::zeros()),
+            Some(encoded(0xFF, 0xCC, 0xFF))
+        );
+
+        let palette = default_palette();
+        assert_eq!(palette[1], [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(palette[215], [0x00, 0x00, 0x33, 0xFF]);
+        assert_eq!(palette[255], [0x11, 0x11, 0x11, 0xFF]);
+
+        assert!(VoxAsset::parse(b"VOX \x96\x00\x00\x00MAIN").is_err());
+        assert!(VoxAsset::parse(b"PNG").is_err());
+
+        let too_large = vox_file(&[size_chunk(257, 1, u32::MAX), xyzi_chunk(&[])]);
+        assert!(VoxAsset::parse(&too_large).is_err());
+    }
+}
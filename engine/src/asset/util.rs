@@ -124,11 +124,19 @@ impl AssetByteReader {
     }
 
     pub fn read_to_slice<T: bytemuck::Pod>(&mut self, buf: &mut [T]) -> anyhow::Result<()> {
-        let read_bytes = self.file.read(bytemuck::cast_slice_mut::<T, u8>(buf))?;
-        assert_eq!(read_bytes, buf.len() * std::mem::size_of::<T>());
+        // A single read may return less than requested for large buffers.
+        self.file
+            .read_exact(bytemuck::cast_slice_mut::<T, u8>(buf))?;
         return Ok(());
     }
 
+    /// Reads everything after the cursor, for formats parsed from an in-memory buffer.
+    pub fn read_remaining(&mut self) -> anyhow::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.file.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
     pub fn cursor_pos(&mut self) -> anyhow::Result<u64> {
         Ok(self.file.stream_position()?)
     }
@@ -1,5 +1,8 @@
 use nalgebra::{ComplexField, Matrix3, Vector3, Vector4};
 
+mod palette;
+pub use palette::Palette;
+
 #[derive(serde::Serialize, serde::Deserialize, Copy, Clone, PartialEq)]
 pub struct ColorSrgba {
     pub rgb: Color<ColorSpaceSrgb>,
@@ -35,6 +38,13 @@ impl ColorSrgba {
         Vector4::new(self.r(), self.g(), self.b(), self.a())
     }
 
+    /// The color converted to linear srgb with alpha untouched, this is what should be uploaded
+    /// for float color inputs on the gpu.
+    pub fn linear_rgba_vec(&self) -> Vector4<f32> {
+        let rgb = self.linear_rgb_vec();
+        Vector4::new(rgb.x, rgb.y, rgb.z, self.a())
+    }
+
     pub fn a(&self) -> f32 {
         self.alpha
     }
@@ -54,6 +64,10 @@ impl std::ops::DerefMut for ColorSrgba {
     }
 }
 
+/// Colors are stored gamma encoded in srgb since that is what the ui and color pickers work in,
+/// while shaders do all their lighting math in linear srgb. Float colors are converted with
+/// `linear_rgb_vec` before being uploaded, colors quantized to 8 bits per channel stay gamma
+/// encoded to keep precision in the darks and are linearized by the shader when decoded.
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Color<S: ColorSpace = ColorSpaceSrgb> {
     pub xyz: Vector3<f32>,
@@ -122,6 +136,24 @@ impl<S: ColorSpace> Color<S> {
 }
 
 impl Color<ColorSpaceSrgb> {
+    pub const BLACK: Self = Self::new_const(0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self::new_const(1.0, 1.0, 1.0);
+    pub const GREY: Self = Self::new_const(0.5, 0.5, 0.5);
+    pub const RED: Self = Self::new_const(1.0, 0.0, 0.0);
+    pub const GREEN: Self = Self::new_const(0.0, 1.0, 0.0);
+    pub const BLUE: Self = Self::new_const(0.0, 0.0, 1.0);
+    pub const YELLOW: Self = Self::new_const(1.0, 1.0, 0.0);
+    pub const CYAN: Self = Self::new_const(0.0, 1.0, 1.0);
+    pub const MAGENTA: Self = Self::new_const(1.0, 0.0, 1.0);
+    pub const ORANGE: Self = Self::new_const(1.0, 0.5, 0.0);
+
+    const fn new_const(r: f32, g: f32, b: f32) -> Self {
+        Self {
+            xyz: Vector3::new(r, g, b),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
     pub fn new_srgb(r: f32, g: f32, b: f32) -> Self {
         Self::new(r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0))
     }
@@ -140,23 +172,52 @@ impl Color<ColorSpaceSrgb> {
         ColorSrgba::new_srgb_hex(hex, alpha)
     }
 
+    /// Panics if `hex` isn't a valid 3, 6 or 8 digit hex color, any alpha component is
+    /// ignored. Use `try_from_hex` for user input.
     pub fn new_srgb_hex(hex: impl ToString) -> Self {
         let hex_str = hex.to_string();
-        let hex_str = hex_str.trim_start_matches("#");
-        assert_eq!(hex_str.len(), 6);
-        let r = u32::from_str_radix(&hex_str[0..2], 16).unwrap() as f32;
-        let g = u32::from_str_radix(&hex_str[2..4], 16).unwrap() as f32;
-        let b = u32::from_str_radix(&hex_str[4..6], 16).unwrap() as f32;
-        Self::new(r / 255.0, g / 255.0, b / 255.0)
+        match Self::try_from_hex(&hex_str) {
+            Ok((color, _alpha)) => color,
+            Err(err) => panic!("Invalid hex color {:?}: {}", hex_str, err),
+        }
     }
 
-    pub fn black() -> Self {
-        Self {
-            xyz: Vector3::new(0.0, 0.0, 0.0),
-            _marker: std::marker::PhantomData,
+    /// Parses a `#` prefixed or bare hex color in the form `rgb`, `rrggbb` or `rrggbbaa`,
+    /// returning the color and its alpha which is 1.0 when omitted.
+    pub fn try_from_hex(hex: &str) -> anyhow::Result<(Self, f32)> {
+        let hex_str = hex.trim().trim_start_matches("#");
+        if !hex_str.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Hex color contains non hex digits.");
         }
+        let channels = match hex_str.len() {
+            3 => hex_str
+                .chars()
+                .map(|c| {
+                    // Shorthand nibbles expand to a byte, so `f` is `ff`.
+                    let nibble = c.to_digit(16).unwrap();
+                    nibble * 16 + nibble
+                })
+                .collect::<Vec<_>>(),
+            6 | 8 => (0..hex_str.len())
+                .step_by(2)
+                .map(|i| u32::from_str_radix(&hex_str[i..i + 2], 16).unwrap())
+                .collect::<Vec<_>>(),
+            len => anyhow::bail!("Hex color has {} digits, expected 3, 6 or 8.", len),
+        };
+        let channels = channels
+            .into_iter()
+            .map(|x| x as f32 / 255.0)
+            .collect::<Vec<_>>();
+        let alpha = channels.get(3).copied().unwrap_or(1.0);
+        Ok((Self::new(channels[0], channels[1], channels[2]), alpha))
+    }
+
+    pub fn black() -> Self {
+        Self::BLACK
     }
 
+    /// Interpolates the gamma encoded components, mix in `ColorSpaceOklab` for perceptually
+    /// even blends of saturated colors.
     pub fn mix(&self, other: &Self, t: f32) -> Self {
         Self::new(
             (1.0 - t) * self.r() + other.r() * t,
@@ -165,13 +226,44 @@ impl Color<ColorSpaceSrgb> {
         )
     }
 
+    /// Naively scales the gamma encoded components, this is not a physical brightness scale
+    /// since halving the encoded value makes the color about 4x darker. Only use this for ui
+    /// style tinting, anything representing light intensity, such as debug shape intensity
+    /// should use `scale_luminance`.
     pub fn multiply_gamma(&mut self, mul: f32) {
         self.xyz *= mul;
     }
 
+    /// Scales the physical brightness of the color by `factor`, doing the scale in linear srgb.
+    /// The result is clamped to the srgb gamut.
+    pub fn scale_luminance(&mut self, factor: f32) {
+        let linear = self.into_color_space::<ColorSpaceSrgbLinear>();
+        let scaled = Color::<ColorSpaceSrgbLinear>::new(
+            linear.r() * factor,
+            linear.g() * factor,
+            linear.b() * factor,
+        );
+        let srgb = scaled.into_color_space::<ColorSpaceSrgb>();
+        *self = Self::new_srgb(srgb.r(), srgb.g(), srgb.b());
+    }
+
+    /// Rotates the hue by `degrees` keeping saturation and value, wrapping around the color
+    /// wheel. Greys have no hue so they are unchanged.
+    pub fn with_hue_shift(&self, degrees: f32) -> Self {
+        let mut hsv = self.into_color_space::<ColorSpaceHSV>();
+        hsv.xyz.x = (hsv.h() + degrees).rem_euclid(360.0);
+        hsv.into_color_space::<ColorSpaceSrgb>()
+    }
+
     pub fn to_srgba(&self, a: f32) -> ColorSrgba {
         ColorSrgba::new(self.r(), self.g(), self.b(), a)
     }
+
+    /// The color converted to linear srgb, this is what should be uploaded for float color
+    /// inputs on the gpu.
+    pub fn linear_rgb_vec(&self) -> Vector3<f32> {
+        self.into_color_space::<ColorSpaceSrgbLinear>().xyz
+    }
 }
 
 impl From<nalgebra::Vector3<f32>> for Color<ColorSpaceSrgb> {
@@ -303,12 +395,172 @@ impl ColorSpaceTransitionFrom<ColorSpaceSrgbLinear> for ColorSpaceXYZ {
     }
 }
 
+/// The Oklab perceptual color space, where L is lightness and a/b are green-red and blue-yellow,
+/// interpolating here avoids the muddy midpoints of mixing in srgb.
+/// Source: https://bottosson.github.io/posts/oklab/
+pub struct ColorSpaceOklab;
+impl ColorSpace for ColorSpaceOklab {}
+
+impl ColorSpaceOklab {
+    /// Nonlinear LMS cone responses to Lab.
+    #[rustfmt::skip]
+    fn lms_to_lab(lms: Vector3<f32>) -> Vector3<f32> {
+        let m2 = Matrix3::new(
+            0.2104542553, 0.7936177850, -0.0040720468,
+            1.9779984951, -2.4285922050, 0.4505937099,
+            0.0259040371, 0.7827717662, -0.8086757660,
+        );
+
+        m2 * lms.map(|x| x.cbrt())
+    }
+
+    /// Lab to linear LMS cone responses.
+    #[rustfmt::skip]
+    fn lab_to_lms(lab: Vector3<f32>) -> Vector3<f32> {
+        let m2_inv = Matrix3::new(
+            1.0, 0.3963377774, 0.2158037573,
+            1.0, -0.1055613458, -0.0638541728,
+            1.0, -0.0894841775, -1.2914855480,
+        );
+
+        (m2_inv * lab).map(|x| x * x * x)
+    }
+}
+
+impl ColorSpaceTransitionFrom<ColorSpaceXYZ> for ColorSpaceOklab {
+    #[rustfmt::skip]
+    fn transition(xyz: Vector3<f32>) -> Vector3<f32> {
+        let m1 = Matrix3::new(
+            0.8189330101, 0.3618667424, -0.1288597137,
+            0.0329845436, 0.9293118715, 0.0361456387,
+            0.0482003018, 0.2643662691, 0.6338517070,
+        );
+
+        ColorSpaceOklab::lms_to_lab(m1 * xyz)
+    }
+}
+impl ColorSpaceTransitionFrom<ColorSpaceOklab> for ColorSpaceXYZ {
+    #[rustfmt::skip]
+    fn transition(xyz: Vector3<f32>) -> Vector3<f32> {
+        let m1_inv = Matrix3::new(
+            1.2270138511, -0.5577999807, 0.2812561490,
+            -0.0405801784, 1.1122568696, -0.0716766787,
+            -0.0763812845, -0.4214819784, 1.5861632204,
+        );
+
+        m1_inv * ColorSpaceOklab::lab_to_lms(xyz)
+    }
+}
+
+impl ColorSpaceTransitionFrom<ColorSpaceSrgbLinear> for ColorSpaceOklab {
+    #[rustfmt::skip]
+    fn transition(xyz: Vector3<f32>) -> Vector3<f32> {
+        // Linear srgb to LMS directly which is more precise than going through XYZ.
+        let m1 = Matrix3::new(
+            0.4122214708, 0.5363325363, 0.0514459929,
+            0.2119034982, 0.6806995451, 0.1073969566,
+            0.0883024619, 0.2817188376, 0.6299787005,
+        );
+
+        ColorSpaceOklab::lms_to_lab(m1 * xyz)
+    }
+}
+impl ColorSpaceTransitionFrom<ColorSpaceOklab> for ColorSpaceSrgbLinear {
+    #[rustfmt::skip]
+    fn transition(xyz: Vector3<f32>) -> Vector3<f32> {
+        let m1_inv = Matrix3::new(
+            4.0767416621, -3.3077115913, 0.2309699292,
+            -1.2684380046, 2.6097574011, -0.3413193965,
+            -0.0041960863, -0.7034186147, 1.7076147010,
+        );
+
+        m1_inv * ColorSpaceOklab::lab_to_lms(xyz)
+    }
+}
+
+impl ColorSpaceTransitionFrom<ColorSpaceSrgb> for ColorSpaceOklab {
+    fn transition(xyz: Vector3<f32>) -> Vector3<f32> {
+        <ColorSpaceOklab as ColorSpaceTransitionFrom<ColorSpaceSrgbLinear>>::transition(
+            <ColorSpaceSrgbLinear as ColorSpaceTransitionFrom<ColorSpaceSrgb>>::transition(xyz),
+        )
+    }
+}
+impl ColorSpaceTransitionFrom<ColorSpaceOklab> for ColorSpaceSrgb {
+    fn transition(xyz: Vector3<f32>) -> Vector3<f32> {
+        <ColorSpaceSrgb as ColorSpaceTransitionFrom<ColorSpaceSrgbLinear>>::transition(
+            <ColorSpaceSrgbLinear as ColorSpaceTransitionFrom<ColorSpaceOklab>>::transition(xyz),
+        )
+    }
+}
+
+impl Color<ColorSpaceOklab> {
+    /// Interpolates the L, a and b channels directly, which is perceptually uniform.
+    pub fn mix(&self, other: &Self, t: f32) -> Self {
+        Self {
+            xyz: self.xyz.lerp(&other.xyz, t),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+/// Hue, saturation and value of gamma encoded srgb, where hue is in degrees within [0, 360)
+/// and saturation and value are within [0, 1]. Greys have an undefined hue which is 0.
+pub struct ColorSpaceHSV;
+impl ColorSpace for ColorSpaceHSV {}
+
+impl ColorSpaceTransitionFrom<ColorSpaceSrgb> for ColorSpaceHSV {
+    fn transition(xyz: Vector3<f32>) -> Vector3<f32> {
+        // Source: https://en.wikipedia.org/wiki/HSL_and_HSV#From_RGB
+        let max = xyz.max();
+        let chroma = max - xyz.min();
+        let hue = if chroma <= 0.0 {
+            0.0
+        } else if max == xyz.x {
+            60.0 * ((xyz.y - xyz.z) / chroma).rem_euclid(6.0)
+        } else if max == xyz.y {
+            60.0 * ((xyz.z - xyz.x) / chroma + 2.0)
+        } else {
+            60.0 * ((xyz.x - xyz.y) / chroma + 4.0)
+        };
+        let saturation = if max <= 0.0 { 0.0 } else { chroma / max };
+        Vector3::new(hue, saturation, max)
+    }
+}
+impl ColorSpaceTransitionFrom<ColorSpaceHSV> for ColorSpaceSrgb {
+    fn transition(xyz: Vector3<f32>) -> Vector3<f32> {
+        // Source: https://en.wikipedia.org/wiki/HSL_and_HSV#HSV_to_RGB_alternative
+        let (hue, saturation, value) = (xyz.x.rem_euclid(360.0), xyz.y, xyz.z);
+        let f = |n: f32| {
+            let k = (n + hue / 60.0) % 6.0;
+            value - value * saturation * k.min(4.0 - k).clamp(0.0, 1.0)
+        };
+        Vector3::new(f(5.0), f(3.0), f(1.0))
+    }
+}
+
+impl Color<ColorSpaceHSV> {
+    /// Hue in degrees within [0, 360).
+    pub fn h(&self) -> f32 {
+        self.xyz.x
+    }
+
+    pub fn s(&self) -> f32 {
+        self.xyz.y
+    }
+
+    pub fn v(&self) -> f32 {
+        self.xyz.z
+    }
+}
+
 mod tests {
     use nalgebra::Vector3;
 
-    use crate::common::color::{ColorSpaceSrgb, ColorSpaceXYZ};
+    use crate::common::color::{
+        ColorSpaceHSV, ColorSpaceOklab, ColorSpaceSrgb, ColorSpaceSrgbLinear, ColorSpaceXYZ,
+    };
 
-    use super::Color;
+    use super::{Color, ColorSrgba};
 
     // Since our matrices on only go to the 4th decimal place, our epsilon is also the 4th decimal.
     const EPSILON: f32 = 0.0001;
@@ -333,4 +585,158 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn gpu_color_is_linear() {
+        // Mid grey in srgb is about 21% in linear srgb, uploading the gamma encoded value would
+        // make it render more than twice as bright.
+        let color = ColorSrgba::new(0.5, 0.5, 0.5, 0.5);
+        let gpu_color = color.linear_rgba_vec();
+        for i in 0..3 {
+            assert!((gpu_color[i] - 0.214_041).abs() < EPSILON);
+        }
+        assert_eq!(gpu_color.w, 0.5);
+
+        let white = Color::new_srgb_hex("#FFFFFF").linear_rgb_vec();
+        assert_eq!(white, Vector3::new(1.0, 1.0, 1.0));
+        assert_eq!(Color::black().linear_rgb_vec(), Vector3::zeros());
+    }
+
+    #[test]
+    fn scale_luminance_differs_from_multiply_gamma() {
+        let mid_grey = Color::new_srgb(0.5, 0.5, 0.5);
+
+        let mut naive = mid_grey;
+        naive.multiply_gamma(0.5);
+        let mut scaled = mid_grey;
+        scaled.scale_luminance(0.5);
+
+        // Halving the light of mid grey lands at ~0.36 gamma encoded, not 0.25.
+        assert!((naive.r() - 0.25).abs() < EPSILON);
+        assert!((scaled.r() - 0.3608).abs() < EPSILON);
+        let naive_linear = naive.linear_rgb_vec().x;
+        let scaled_linear = scaled.linear_rgb_vec().x;
+        assert!((scaled_linear - mid_grey.linear_rgb_vec().x * 0.5).abs() < EPSILON);
+        assert!(naive_linear < scaled_linear * 0.5);
+
+        // Scaling up clamps to white.
+        let mut bright = mid_grey;
+        bright.scale_luminance(10.0);
+        assert_eq!(bright, Color::WHITE);
+    }
+
+    #[test]
+    fn oklab_to_and_from() {
+        let colors = [
+            Color::<ColorSpaceSrgb>::new(0.5, 0.5, 0.5),
+            Color::new(0.9, 0.2, 0.1),
+            Color::new(0.1, 0.6, 0.8),
+            Color::WHITE,
+            Color::BLACK,
+        ];
+        for color in colors {
+            let from_linear = color
+                .into_color_space::<ColorSpaceSrgbLinear>()
+                .into_color_space::<ColorSpaceOklab>()
+                .into_color_space::<ColorSpaceSrgbLinear>()
+                .into_color_space::<ColorSpaceSrgb>();
+            let from_xyz = color
+                .into_color_space::<ColorSpaceXYZ>()
+                .into_color_space::<ColorSpaceOklab>()
+                .into_color_space::<ColorSpaceXYZ>()
+                .into_color_space::<ColorSpaceSrgb>();
+            for i in 0..3 {
+                assert!(
+                    (from_linear.xyz[i] - color.xyz[i]).abs() < EPSILON,
+                    "{:?} didn't round trip through oklab from linear srgb, got {:?}",
+                    color,
+                    from_linear
+                );
+                assert!(
+                    (from_xyz.xyz[i] - color.xyz[i]).abs() < EPSILON,
+                    "{:?} didn't round trip through oklab from xyz, got {:?}",
+                    color,
+                    from_xyz
+                );
+            }
+        }
+
+        // White is full lightness with no chroma.
+        let white = Color::WHITE.into_color_space::<ColorSpaceOklab>();
+        assert!((white.xyz - Vector3::new(1.0, 0.0, 0.0)).abs().max() < EPSILON);
+
+        // Mixing complementary colors stays brighter than mixing in srgb.
+        let blue = Color::BLUE.into_color_space::<ColorSpaceOklab>();
+        let yellow = Color::YELLOW.into_color_space::<ColorSpaceOklab>();
+        let mid = blue.mix(&yellow, 0.5);
+        assert!((mid.xyz.x - (blue.xyz.x + yellow.xyz.x) * 0.5).abs() < EPSILON);
+        let srgb_mid = Color::BLUE
+            .mix(&Color::YELLOW, 0.5)
+            .into_color_space::<ColorSpaceOklab>();
+        assert!(mid.xyz.x > srgb_mid.xyz.x);
+    }
+
+    #[test]
+    fn hex_forms() {
+        let (white, alpha) = Color::try_from_hex("fff").unwrap();
+        assert_eq!(white, Color::WHITE);
+        assert_eq!(alpha, 1.0);
+
+        let (white, alpha) = Color::try_from_hex("#ffffff").unwrap();
+        assert_eq!(white, Color::WHITE);
+        assert_eq!(alpha, 1.0);
+
+        let (red, alpha) = Color::try_from_hex("ff000080").unwrap();
+        assert_eq!(red, Color::RED);
+        assert!((alpha - 128.0 / 255.0).abs() < EPSILON);
+        assert_eq!(Color::new_srgb_hex("#ff000080"), Color::RED);
+
+        assert_eq!(
+            Color::try_from_hex("#18a").unwrap().0,
+            Color::new_srgb_hex("#1188aa")
+        );
+        assert!(Color::try_from_hex("").is_err());
+        assert!(Color::try_from_hex("#ff00").is_err());
+        assert!(Color::try_from_hex("#gg0000").is_err());
+    }
+
+    #[test]
+    fn hsv_to_and_from() {
+        let colors = [
+            Color::<ColorSpaceSrgb>::new(0.9, 0.2, 0.1),
+            Color::new(0.1, 0.6, 0.8),
+            Color::new(0.3, 0.8, 0.4),
+            Color::MAGENTA,
+            Color::WHITE,
+            Color::BLACK,
+        ];
+        for color in colors {
+            let round_trip = color
+                .into_color_space::<ColorSpaceHSV>()
+                .into_color_space::<ColorSpaceSrgb>();
+            assert!(
+                (round_trip.xyz - color.xyz).abs().max() < EPSILON,
+                "{:?} didn't round trip through hsv, got {:?}",
+                color,
+                round_trip
+            );
+        }
+
+        let orange = Color::ORANGE.into_color_space::<ColorSpaceHSV>();
+        assert!((orange.h() - 30.0).abs() < EPSILON);
+        assert_eq!((orange.s(), orange.v()), (1.0, 1.0));
+
+        // Grey has no hue so it is 0 and shifting it does nothing.
+        let grey = Color::GREY.into_color_space::<ColorSpaceHSV>();
+        assert_eq!((grey.h(), grey.s(), grey.v()), (0.0, 0.0, 0.5));
+        assert_eq!(Color::GREY.with_hue_shift(90.0), Color::GREY);
+
+        let shifted = Color::RED.with_hue_shift(120.0);
+        assert!((shifted.xyz - Color::GREEN.xyz).abs().max() < EPSILON);
+        // Wraps around in both directions.
+        let shifted = Color::RED.with_hue_shift(-120.0);
+        assert!((shifted.xyz - Color::BLUE.xyz).abs().max() < EPSILON);
+        let shifted = Color::BLUE.with_hue_shift(480.0);
+        assert!((shifted.xyz - Color::RED.xyz).abs().max() < EPSILON);
+    }
 }
@@ -0,0 +1,126 @@
+use super::{Color, ColorSpaceSrgb};
+
+/// An indexed set of colors, used where colors are referenced by index such as brush palettes
+/// or quantizing colors for formats with a fixed palette.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct Palette {
+    colors: Vec<Color<ColorSpaceSrgb>>,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self { colors: Vec::new() }
+    }
+
+    pub fn from_colors(colors: Vec<Color<ColorSpaceSrgb>>) -> Self {
+        Self { colors }
+    }
+
+    /// A palette of the named color constants.
+    pub fn basic() -> Self {
+        Self::from_colors(vec![
+            Color::BLACK,
+            Color::WHITE,
+            Color::GREY,
+            Color::RED,
+            Color::GREEN,
+            Color::BLUE,
+            Color::YELLOW,
+            Color::CYAN,
+            Color::MAGENTA,
+            Color::ORANGE,
+        ])
+    }
+
+    /// Adds the color returning its index.
+    pub fn push(&mut self, color: Color<ColorSpaceSrgb>) -> usize {
+        self.colors.push(color);
+        self.colors.len() - 1
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Color<ColorSpaceSrgb>> {
+        self.colors.get(index)
+    }
+
+    pub fn set(&mut self, index: usize, color: Color<ColorSpaceSrgb>) {
+        self.colors[index] = color;
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    pub fn colors(&self) -> &[Color<ColorSpaceSrgb>] {
+        &self.colors
+    }
+
+    /// The index of the palette color closest to `color`, compared in gamma encoded srgb since
+    /// it is closer to perceptually uniform than linear srgb. Ties go to the lowest index.
+    pub fn nearest(&self, color: &Color<ColorSpaceSrgb>) -> usize {
+        assert!(
+            !self.colors.is_empty(),
+            "Can't find the nearest color of an empty palette."
+        );
+        let mut nearest_index = 0;
+        let mut nearest_distance = f32::MAX;
+        for (i, palette_color) in self.colors.iter().enumerate() {
+            let distance = (palette_color.xyz - color.xyz).norm_squared();
+            if distance < nearest_distance {
+                nearest_index = i;
+                nearest_distance = distance;
+            }
+        }
+        nearest_index
+    }
+}
+
+impl std::ops::Index<usize> for Palette {
+    type Output = Color<ColorSpaceSrgb>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.colors[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_picks_closest_color() {
+        let palette = Palette::basic();
+        let index_of = |color: Color<ColorSpaceSrgb>| {
+            palette
+                .colors()
+                .iter()
+                .position(|palette_color| *palette_color == color)
+                .unwrap()
+        };
+
+        assert_eq!(palette.nearest(&Color::RED), index_of(Color::RED));
+        assert_eq!(
+            palette.nearest(&Color::new_srgb(0.9, 0.1, 0.05)),
+            index_of(Color::RED)
+        );
+        assert_eq!(
+            palette.nearest(&Color::new_srgb(0.05, 0.05, 0.1)),
+            index_of(Color::BLACK)
+        );
+        assert_eq!(
+            palette.nearest(&Color::new_srgb(0.55, 0.45, 0.5)),
+            index_of(Color::GREY)
+        );
+        assert_eq!(
+            palette.nearest(&Color::new_srgb(0.95, 0.6, 0.1)),
+            index_of(Color::ORANGE)
+        );
+
+        // Ties resolve to the first color.
+        let palette = Palette::from_colors(vec![Color::BLACK, Color::WHITE]);
+        assert_eq!(palette.nearest(&Color::GREY), 0);
+    }
+}
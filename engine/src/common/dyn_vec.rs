@@ -54,6 +54,25 @@ impl DynVec {
         };
     }
 
+    /// Drops the element at `index` and moves the last element into its slot.
+    pub fn swap_remove(&mut self, index: usize) {
+        assert!(index < self.size);
+        unsafe {
+            let dst_ptr = self.data.byte_add(self.type_info.stride() * index);
+            (self.type_info.drop_fn)(dst_ptr.as_ptr());
+        }
+
+        self.size -= 1;
+        if index == self.size {
+            return;
+        }
+        unsafe {
+            let src_ptr = self.data.byte_add(self.type_info.stride() * self.size);
+            let dst_ptr = self.data.byte_add(self.type_info.stride() * index);
+            src_ptr.copy_to_nonoverlapping(dst_ptr, self.type_info.stride());
+        }
+    }
+
     pub fn get<T: 'static>(&self, index: usize) -> &T {
         let type_info = TypeInfo::new::<T>();
         assert_eq!(self.type_info, type_info);
@@ -204,6 +204,15 @@ impl<T> FreeList<T> {
             .unwrap_or(false);
     }
 
+    /// Unlike `has_value`, false if the handle's slot was freed and reused since.
+    pub fn contains(&self, handle: FreeListHandle<T>) -> bool {
+        return self
+            .data
+            .get(handle.index as usize)
+            .map(|n| !n.is_null() && n.generation == handle.generation)
+            .unwrap_or(false);
+    }
+
     pub fn has_value_index(&self, index: u32) -> bool {
         return self
             .data
@@ -0,0 +1,15 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    /// The index of this axis' component in a vector.
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
@@ -1,4 +1,5 @@
 pub mod aabb;
+pub mod axis;
 pub mod obb;
 pub mod ray;
 pub mod shape;
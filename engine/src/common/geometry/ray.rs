@@ -176,8 +176,19 @@ impl Ray {
     pub fn intersect_aabb(&self, aabb: &AABB) -> Option<RayAABBHitInfo> {
         let t0 = self.intersect_point(aabb.min);
         let t1 = self.intersect_point(aabb.max);
-        let t_min = t0.zip_map(&t1, |x, y| x.min(y));
-        let t_max = t0.zip_map(&t1, |x, y| x.max(y));
+        let mut t_min = t0.zip_map(&t1, |x, y| x.min(y));
+        let mut t_max = t0.zip_map(&t1, |x, y| x.max(y));
+        // The ray never crosses the slabs of axes it is parallel to, so those only matter if the
+        // ray is outside of them.
+        for i in 0..3 {
+            if self.dir[i] == 0.0 {
+                if self.origin[i] < aabb.min[i] || self.origin[i] > aabb.max[i] {
+                    return None;
+                }
+                t_min[i] = f32::NEG_INFINITY;
+                t_max[i] = f32::INFINITY;
+            }
+        }
 
         let t_enter = t_min.max().max(0.0);
         let t_exit = t_max.min();
@@ -8,6 +8,7 @@ pub mod id;
 pub mod morton;
 pub mod oneshot;
 pub mod ring_queue;
+pub mod rng;
 pub mod serde_util;
 pub mod util;
 pub mod vtable;
@@ -46,6 +46,8 @@ pub mod gfx {
 
 pub mod io {
     pub const GAME_USER_SETTINGS_FILE: &str = "user_settings::json";
+    /// Relative to the project directory.
+    pub const PROJECT_VOXEL_PALETTE_FILE: &str = "voxel_palette.json";
 
     pub const REGION_FILE_HEADER: &str = "vcr ";
     pub mod header {
@@ -55,13 +57,35 @@ pub mod io {
 
 pub mod actions {
     pub mod keybind {
-        use crate::input::keyboard::Key;
-
-        pub const EDITOR_TOGGLE: Key = Key::F2;
-        pub const EDITOR_TOGGLE_DEBUG: Key = Key::C;
-
-        pub const EDITOR_GIZMO_TRANSLATION: Key = Key::T;
-        pub const EDITOR_GIZMO_ROTATION: Key = Key::R;
+        use crate::input::{
+            keyboard::{Key, Modifier},
+            mapper::ActionBinding,
+        };
+
+        pub const EDITOR_TOGGLE: ActionBinding = ActionBinding::key(Key::F2);
+        pub const EDITOR_TOGGLE_DEBUG: ActionBinding = ActionBinding::key(Key::C);
+
+        pub const EDITOR_GIZMO_TRANSLATION: ActionBinding = ActionBinding::key(Key::T);
+        pub const EDITOR_GIZMO_ROTATION: ActionBinding = ActionBinding::key(Key::R);
+
+        pub const EDITOR_SAVE: ActionBinding =
+            ActionBinding::key(Key::S).with_modifier(Modifier::Control);
+
+        pub const EDITOR_VIEW_FRONT: ActionBinding = ActionBinding::key(Key::Num1);
+        pub const EDITOR_VIEW_BACK: ActionBinding =
+            ActionBinding::key(Key::Num1).with_modifier(Modifier::Control);
+        pub const EDITOR_VIEW_RIGHT: ActionBinding = ActionBinding::key(Key::Num3);
+        pub const EDITOR_VIEW_LEFT: ActionBinding =
+            ActionBinding::key(Key::Num3).with_modifier(Modifier::Control);
+        pub const EDITOR_VIEW_TOP: ActionBinding = ActionBinding::key(Key::Num7);
+        pub const EDITOR_VIEW_BOTTOM: ActionBinding =
+            ActionBinding::key(Key::Num7).with_modifier(Modifier::Control);
+
+        pub const EDITOR_EYEDROPPER: ActionBinding = ActionBinding::key(Key::I);
+
+        pub const EDITOR_SCREENSHOT: ActionBinding = ActionBinding::key(Key::F12);
+        pub const EDITOR_FRAME_SELECTION: ActionBinding = ActionBinding::key(Key::Home);
+        pub const EDITOR_CLEAR_MEASUREMENT: ActionBinding = ActionBinding::key(Key::Escape);
     }
 
     pub const EDITOR_TOGGLE: &str = "editor_toggle";
@@ -70,6 +94,22 @@ pub mod actions {
     pub const EDITOR_TOGGLE_DEBUG: &str = "editor_toggle_debug";
     pub const EDITOR_GIZMO_TRANSLATION: &str = "editor_gizmo_translation";
     pub const EDITOR_GIZMO_ROTATION: &str = "editor_gizmo_rotation";
+    // Saves the project and editor settings.
+    pub const EDITOR_SAVE: &str = "editor_save";
+    // Snaps the editor camera to look down an axis.
+    pub const EDITOR_VIEW_FRONT: &str = "editor_view_front";
+    pub const EDITOR_VIEW_BACK: &str = "editor_view_back";
+    pub const EDITOR_VIEW_RIGHT: &str = "editor_view_right";
+    pub const EDITOR_VIEW_LEFT: &str = "editor_view_left";
+    pub const EDITOR_VIEW_TOP: &str = "editor_view_top";
+    pub const EDITOR_VIEW_BOTTOM: &str = "editor_view_bottom";
+    // Samples the hovered voxel's color or material with any editing tool selected.
+    pub const EDITOR_EYEDROPPER: &str = "editor_eyedropper";
+    // Saves a screenshot of the whole editor window to the user screenshots directory.
+    pub const EDITOR_SCREENSHOT: &str = "editor_screenshot";
+    // Frames the primary selection's bounding box.
+    pub const EDITOR_FRAME_SELECTION: &str = "editor_frame_selection";
+    pub const EDITOR_CLEAR_MEASUREMENT: &str = "editor_clear_measurement";
 }
 
 pub mod egui {
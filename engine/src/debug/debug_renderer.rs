@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use nalgebra::{
     Isometry, Isometry3, Matrix4, Point3, Translation3, UnitQuaternion, Vector2, Vector3, Vector4,
@@ -12,14 +12,16 @@ use crate::{
     },
     common::{
         color::{Color, ColorSpaceSrgb, ColorSrgba},
-        geometry::{obb::OBB, ray::Ray},
+        geometry::{aabb::AABB, obb::OBB, ray::Ray},
     },
+    debug::debug_text,
     graphics::{
         backend::{
             Buffer, GfxBlendFactor, GfxBlendOp, GfxBufferCreateInfo, GfxCullMode, GfxFrontFace,
             GfxRasterPipelineBlendStateAttachmentInfo, GfxRenderPassAttachment,
             GraphicsBackendRecorder, Image, ResourceId, ShaderWriter,
         },
+        camera::Camera,
         device::DeviceResource,
         frame_graph::{
             FrameGraphBuilder, FrameGraphContext, FrameGraphRasterBlendInfo, FrameGraphRasterInfo,
@@ -29,7 +31,8 @@ use crate::{
         renderer::Renderer,
     },
     physics::transform::Transform,
-    resource::ResMut,
+    resource::{Res, ResMut},
+    window::time::{Instant, Time},
 };
 
 pub struct DebugRendererGraphConstants {
@@ -64,6 +67,8 @@ pub enum DebugShapeType {
         radius: u32,
         thickness: u32,
     },
+    /// An ascii character of `debug_text`'s font.
+    Glyph(u8),
 }
 
 impl DebugShapeType {
@@ -80,6 +85,20 @@ pub struct DebugShape {
     transform: nalgebra::Matrix4<f32>,
     color: ColorSrgba,
     flags: DebugShapeFlags,
+    /// Offset of the mesh in its local space before `transform`, used to lay out glyphs.
+    local_offset: Vector2<f32>,
+    /// When the shape stops being drawn, `None` shapes only last the frame they were drawn in.
+    expires_at: Option<Instant>,
+}
+
+/// A wireframe sphere drawn as three orthogonal rings so its outline reads from any angle,
+/// useful for collision radii and light ranges.
+#[derive(Clone)]
+pub struct DebugSphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+    pub color: ColorSrgba,
+    pub flags: DebugShapeFlags,
 }
 
 bitflags::bitflags! {
@@ -87,12 +106,16 @@ bitflags::bitflags! {
     pub struct DebugShapeFlags: u32 {
         const NONE = 0;
         const SHADING = 1;
+        /// Hides the shape behind the world rather than drawing it on top.
         const DEPTH_TEST = 1 << 1;
+        /// Rotates the shape's local axes to the camera's so its xy plane faces the camera.
+        const BILLBOARD = 1 << 2;
     }
 }
 
 /// Used to draw basic primitives, all commands should be sent in AppStage::OnUpdate since the
-/// DebugRenderer flushes all requests before rendering.
+/// DebugRenderer flushes all requests before rendering, except those drawn within
+/// `DebugRenderer::persist_for`.
 #[derive(Resource)]
 pub struct DebugRenderer {
     meshes: HashMap<DebugShapeType, DebugMesh>,
@@ -111,6 +134,12 @@ pub struct DebugRenderer {
     last_written_mesh_count: usize,
 
     shapes: HashMap<DebugShapeType, Vec<DebugShape>>,
+    /// How long shapes drawn right now persist for, set within `persist_for`.
+    ttl: Duration,
+    /// The engine time of the last frame the shapes were written on, persisted shapes expire
+    /// relative to it so they follow the engine clock.
+    curr_time: Instant,
+    warned_max_draw_count: bool,
     graph_framebuffer: Option<FrameGraphResource<Image>>,
     graph_framebuffer_depth: Option<FrameGraphResource<Image>>,
 }
@@ -150,6 +179,9 @@ impl DebugRenderer {
             meshes: HashMap::new(),
             count_draw_buffer: None,
             shapes: HashMap::new(),
+            ttl: Duration::ZERO,
+            curr_time: Instant::now(),
+            warned_max_draw_count: false,
             graph_framebuffer: None,
             graph_framebuffer_depth: None,
 
@@ -286,6 +318,68 @@ impl DebugRenderer {
             .insert(shape_type, DebugMesh { vertices, indices });
     }
 
+    /// Shapes drawn within `f` keep being drawn for `ttl` rather than only this frame, for
+    /// visualizing one-off events such as a raycast hit. A zero `ttl` is the usual one frame.
+    pub fn persist_for(&mut self, ttl: Duration, f: impl FnOnce(&mut Self)) {
+        let last_ttl = std::mem::replace(&mut self.ttl, ttl);
+        f(self);
+        self.ttl = last_ttl;
+    }
+
+    fn push_shape(
+        &mut self,
+        shape_type: DebugShapeType,
+        transform: Matrix4<f32>,
+        color: ColorSrgba,
+        flags: DebugShapeFlags,
+    ) {
+        self.push_shape_with_offset(shape_type, transform, Vector2::zeros(), color, flags);
+    }
+
+    fn push_shape_with_offset(
+        &mut self,
+        shape_type: DebugShapeType,
+        transform: Matrix4<f32>,
+        local_offset: Vector2<f32>,
+        color: ColorSrgba,
+        flags: DebugShapeFlags,
+    ) {
+        let expires_at = (!self.ttl.is_zero()).then(|| self.curr_time + self.ttl);
+        self.shapes.entry(shape_type).or_default().push(DebugShape {
+            transform,
+            color,
+            flags,
+            local_offset,
+            expires_at,
+        });
+    }
+
+    /// Draws `text` centered on `position` facing the camera, `size` is the height of a line in
+    /// meters. Each character is an instance of its glyph's mesh so any number of labels only
+    /// take one draw per distinct character. Drawn on top of the world unless `flags` has
+    /// `DEPTH_TEST`.
+    pub fn draw_text_3d(
+        &mut self,
+        position: Vector3<f32>,
+        text: &str,
+        size: f32,
+        color: ColorSrgba,
+        flags: DebugShapeFlags,
+    ) {
+        let pixel_size = size / debug_text::GLYPH_HEIGHT as f32;
+        let transform = Matrix4::new_translation(&position)
+            * Matrix4::new_nonuniform_scaling(&Vector3::repeat(pixel_size));
+        for (glyph, offset) in debug_text::layout_text(text) {
+            self.push_shape_with_offset(
+                DebugShapeType::Glyph(glyph),
+                transform,
+                offset,
+                color,
+                flags | DebugShapeFlags::BILLBOARD,
+            );
+        }
+    }
+
     pub fn draw_line_3d(
         &mut self,
         start: Vector3<f32>,
@@ -306,6 +400,39 @@ impl DebugRenderer {
         self.draw_cube(isometry, scale, color, flags);
     }
 
+    /// An arrow made of lines, unlike `draw_arrow` the head scales with the arrow's length so it
+    /// suits visualizing vectors such as velocities and normals.
+    pub fn draw_line_arrow(
+        &mut self,
+        start: Vector3<f32>,
+        end: Vector3<f32>,
+        radius: f32,
+        color: ColorSrgba,
+        flags: DebugShapeFlags,
+    ) {
+        const HEAD_LENGTH_RATIO: f32 = 0.25;
+        let diff = end - start;
+        let length = diff.norm();
+        if length <= 0.0 {
+            return;
+        }
+        let dir = diff / length;
+        // Any perpendicular works, avoid crossing with a near parallel axis.
+        let perpendicular = if dir.y.abs() < 0.99 {
+            dir.cross(&Vector3::y())
+        } else {
+            dir.cross(&Vector3::x())
+        }
+        .normalize();
+        let head_length = length * HEAD_LENGTH_RATIO;
+        let head_base = end - dir * head_length;
+        let head_side = perpendicular * head_length * 0.5;
+
+        self.draw_line_3d(start, end, radius, color, flags);
+        self.draw_line_3d(end, head_base + head_side, radius, color, flags);
+        self.draw_line_3d(end, head_base - head_side, radius, color, flags);
+    }
+
     pub fn draw_obb_filled(&mut self, obb: &OBB, color: ColorSrgba, flags: DebugShapeFlags) {
         let (min, max) = obb.rotated_min_max();
         let side_length = obb.aabb.side_length();
@@ -333,17 +460,15 @@ impl DebugRenderer {
         );
 
         let transform = isometry.to_homogeneous();
-        self.shapes
-            .entry(DebugShapeType::Capsule {
+        self.push_shape(
+            DebugShapeType::Capsule {
                 height: DebugShapeType::encode_f32(height),
                 radius: DebugShapeType::encode_f32(radius),
-            })
-            .or_default()
-            .push(DebugShape {
-                transform,
-                color,
-                flags,
-            });
+            },
+            transform,
+            color,
+            flags,
+        );
     }
 
     /// rotation is rotation from Vector3::y().
@@ -365,14 +490,7 @@ impl DebugRenderer {
         let translation = nalgebra::Translation3::from(position).to_homogeneous();
         let scale = nalgebra::Scale3::new(scale.x, 0.0, scale.y).to_homogeneous();
         let transform = translation * rotation * scale;
-        self.shapes
-            .entry(DebugShapeType::Plane)
-            .or_default()
-            .push(DebugShape {
-                transform,
-                color,
-                flags,
-            });
+        self.push_shape(DebugShapeType::Plane, transform, color, flags);
     }
 
     pub fn draw_cube(
@@ -384,14 +502,7 @@ impl DebugRenderer {
     ) {
         let transform =
             isometry.to_homogeneous() * nalgebra::Matrix4::new_nonuniform_scaling(&scale);
-        self.shapes
-            .entry(DebugShapeType::Cube)
-            .or_default()
-            .push(DebugShape {
-                transform,
-                color,
-                flags,
-            });
+        self.push_shape(DebugShapeType::Cube, transform, color, flags);
     }
 
     fn arrow_transform(start: Vector3<f32>, end: Vector3<f32>, scale: f32) -> Matrix4<f32> {
@@ -461,17 +572,15 @@ impl DebugRenderer {
         assert!(radius - thickness > 0.0);
         let transform =
             Isometry::from_parts(Translation3::from(position), rotation).to_homogeneous();
-        self.shapes
-            .entry(DebugShapeType::Ring {
+        self.push_shape(
+            DebugShapeType::Ring {
                 radius: DebugShapeType::encode_f32(radius),
                 thickness: DebugShapeType::encode_f32(thickness),
-            })
-            .or_default()
-            .push(DebugShape {
-                transform,
-                color,
-                flags,
-            });
+            },
+            transform,
+            color,
+            flags,
+        );
     }
 
     pub fn draw_arrow(
@@ -483,14 +592,7 @@ impl DebugRenderer {
         flags: DebugShapeFlags,
     ) {
         let transform = Self::arrow_transform(start, end, scale);
-        self.shapes
-            .entry(DebugShapeType::Arrow)
-            .or_default()
-            .push(DebugShape {
-                transform,
-                color,
-                flags,
-            });
+        self.push_shape(DebugShapeType::Arrow, transform, color, flags);
     }
 
     pub fn draw_obb_outline(
@@ -588,6 +690,59 @@ impl DebugRenderer {
         );
     }
 
+    pub fn draw_aabb(
+        &mut self,
+        aabb: &AABB,
+        line_radius: f32,
+        color: ColorSrgba,
+        flags: DebugShapeFlags,
+    ) {
+        let corners = std::array::from_fn(|i| {
+            Vector3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            )
+        });
+        self.draw_box_edges(&corners, line_radius, color, flags);
+    }
+
+    /// Draws the wireframe of the volume `camera` sees from `transform`, useful for debugging
+    /// culling from another camera's view.
+    pub fn draw_frustum(
+        &mut self,
+        camera: &Camera,
+        transform: &Transform,
+        aspect_ratio: f32,
+        line_radius: f32,
+        color: ColorSrgba,
+        flags: DebugShapeFlags,
+    ) {
+        let proj_view = camera.projection_matrix(aspect_ratio) * transform.to_view_matrix();
+        let Some(corners) = frustum_corners(&proj_view) else {
+            return;
+        };
+        self.draw_box_edges(&corners, line_radius, color, flags);
+    }
+
+    /// Draws the 12 edges of a box whose corners are indexed by bit 0 for x, bit 1 for y and
+    /// bit 2 for z, so each edge joins two corners differing by a single bit.
+    fn draw_box_edges(
+        &mut self,
+        corners: &[Vector3<f32>; 8],
+        line_radius: f32,
+        color: ColorSrgba,
+        flags: DebugShapeFlags,
+    ) {
+        for i in 0..8 {
+            for axis_bit in [1, 2, 4] {
+                if i & axis_bit == 0 {
+                    self.draw_line_3d(corners[i], corners[i | axis_bit], line_radius, color, flags);
+                }
+            }
+        }
+    }
+
     pub fn draw_sphere(
         &mut self,
         center: Vector3<f32>,
@@ -597,14 +752,29 @@ impl DebugRenderer {
     ) {
         let mut transform = nalgebra::Matrix4::new_scaling(radius).append_translation(&center);
         transform.m44 = 1.0;
-        self.shapes
-            .entry(DebugShapeType::Sphere)
-            .or_default()
-            .push(DebugShape {
-                transform,
-                color,
-                flags,
-            });
+        self.push_shape(DebugShapeType::Sphere, transform, color, flags);
+    }
+
+    pub fn draw_sphere_outline(&mut self, sphere: &DebugSphere) {
+        // Relative so the rings stay visible without swallowing small spheres.
+        const THICKNESS_RATIO: f32 = 0.02;
+        let thickness = sphere.radius * THICKNESS_RATIO;
+        // Rings lie in the xz plane, so rotate the up axis onto each axis for the other two.
+        let rotations = [
+            UnitQuaternion::identity(),
+            UnitQuaternion::rotation_between(&Vector3::y(), &Vector3::x()).unwrap(),
+            UnitQuaternion::rotation_between(&Vector3::y(), &Vector3::z()).unwrap(),
+        ];
+        for rotation in rotations {
+            self.draw_ring(
+                sphere.center,
+                rotation,
+                sphere.radius,
+                thickness,
+                sphere.color,
+                sphere.flags,
+            );
+        }
     }
 
     fn create_ring_mesh(radius: f32, thickness: f32) -> DebugMesh {
@@ -647,6 +817,33 @@ impl DebugRenderer {
         DebugMesh { vertices, indices }
     }
 
+    /// A quad for each set pixel of the glyph in the xy plane, one unit per pixel with the origin
+    /// at the glyph's bottom left corner.
+    fn create_glyph_mesh(glyph: u8) -> DebugMesh {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for y in 0..debug_text::GLYPH_HEIGHT {
+            for x in 0..debug_text::GLYPH_WIDTH {
+                if !debug_text::is_glyph_pixel_set(glyph, x, y) {
+                    continue;
+                }
+                let bottom = (debug_text::GLYPH_HEIGHT - 1 - y) as f32;
+                let i = vertices.len() as u32;
+                for (dx, dy) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+                    vertices.push(DebugMeshVertex {
+                        position: Vector3::new(x as f32 + dx, bottom + dy, 0.0),
+                        normal: -Vector3::z(),
+                    });
+                }
+                // Double-sided so winding doesn't matter once billboarded.
+                indices.extend_from_slice(&[i, i + 1, i + 2, i + 2, i + 1, i + 3]);
+                indices.extend_from_slice(&[i, i + 2, i + 1, i + 2, i + 3, i + 1]);
+            }
+        }
+
+        DebugMesh { vertices, indices }
+    }
+
     fn create_capsule_mesh(height: f32, radius: f32) -> DebugMesh {
         const RADIUS_SUBDIVISION: u32 = 16;
         const CAP_SUBDIVISION: u32 = 8;
@@ -833,6 +1030,7 @@ impl DebugRenderer {
     pub fn write_render_data(
         mut debug_renderer: ResMut<Self>,
         mut device_resource: ResMut<DeviceResource>,
+        time: Res<Time>,
     ) {
         let debug_renderer = &mut *debug_renderer;
         let req_bytes = 16
@@ -867,6 +1065,10 @@ impl DebugRenderer {
                     );
                     debug_renderer.meshes.insert(*shape_type, mesh);
                 }
+                DebugShapeType::Glyph(glyph) => {
+                    let mesh = Self::create_glyph_mesh(*glyph);
+                    debug_renderer.meshes.insert(*shape_type, mesh);
+                }
                 _ => unreachable!(),
             }
             needs_mesh_write |= true;
@@ -882,31 +1084,52 @@ impl DebugRenderer {
             color: Vector4<f32>,
             mesh_ptr: u32,
             flags: u32,
-            padding: [u32; 2], // Pad to 16 byte alignment.
+            local_offset: Vector2<f32>,
+        }
+        // Each shape type is one indirect draw, past the max the extra shape types are skipped
+        // rather than writing past the end of the draw buffer.
+        if debug_renderer.shapes.len() > Self::MAX_DRAW_COUNT as usize
+            && !debug_renderer.warned_max_draw_count
+        {
+            log::warn!(
+                "Debug renderer has {} shape types to draw but only {} draws are supported, skipping the rest.",
+                debug_renderer.shapes.len(),
+                Self::MAX_DRAW_COUNT
+            );
+            debug_renderer.warned_max_draw_count = true;
         }
+        let draw_count = debug_renderer
+            .shapes
+            .len()
+            .min(Self::MAX_DRAW_COUNT as usize);
+
         let instances_size = debug_renderer
             .shapes
             .values()
+            .take(draw_count)
             .map(|shapes| shapes.len())
             .sum::<usize>()
             * std::mem::size_of::<MeshInstance>();
+        // Grow to the next power of two so heavy debug drawing doesn't reallocate every frame the
+        // count goes up. Reallocating creates a new buffer so the one possibly still being read by
+        // a frame in flight is untouched, and writes are staged so they are only copied when the
+        // frame is recorded.
         device_resource.create_or_reallocate_buffer(
             &mut debug_renderer.instances_buffer,
             GfxBufferCreateInfo {
                 name: "debug_renderer_to_draw_meshes".to_owned(),
-                size: (instances_size as u64).max(1), // Can't have 0 size idk make it lazy later ig
+                size: (instances_size as u64).max(1).next_power_of_two(), // Can't have 0 size idk make it lazy later ig
             },
         );
 
         let count_draw_buffer = debug_renderer.count_draw_buffer.as_ref().unwrap();
         let mut draw_data = vec![0u8; req_bytes];
         let mut instances_data = Vec::with_capacity(instances_size);
-        let draw_count = debug_renderer.shapes.len() as u32;
-        draw_data[0..4].copy_from_slice(&draw_count.to_le_bytes());
+        draw_data[0..4].copy_from_slice(&(draw_count as u32).to_le_bytes());
 
         let mut draw_offset = 0;
         let mut instances_offset = 0;
-        for (shape_type, shapes) in debug_renderer.shapes.iter() {
+        for (shape_type, shapes) in debug_renderer.shapes.iter().take(draw_count) {
             let mesh = debug_renderer
                 .meshes
                 .get(shape_type)
@@ -938,10 +1161,10 @@ impl DebugRenderer {
                 instances_data.extend_from_slice(bytemuck::bytes_of(&MeshInstance {
                     // Transpose since slang is row major.
                     transform: shape.transform.transpose(),
-                    color: shape.color.rgba_vec(),
+                    color: shape.color.linear_rgba_vec(),
                     mesh_ptr: *debug_renderer.shape_mesh_offests.get(shape_type).unwrap(),
-                    flags: 0,
-                    padding: [0; 2],
+                    flags: shape.flags.bits(),
+                    local_offset: shape.local_offset,
                 }));
             }
             instances_offset += shapes.len() as u32;
@@ -958,7 +1181,13 @@ impl DebugRenderer {
             );
         }
 
-        debug_renderer.shapes.clear();
+        // Persisted shapes are kept to be uploaded again next frame until they expire.
+        let now = time.curr_time();
+        debug_renderer.curr_time = now;
+        debug_renderer.shapes.retain(|_, shapes| {
+            shapes.retain(|shape| shape.expires_at.is_some_and(|expires_at| expires_at > now));
+            !shapes.is_empty()
+        });
     }
 
     pub fn set_graph_debug_pass(
@@ -1051,3 +1280,49 @@ impl DebugRenderer {
         );
     }
 }
+
+/// The world space corners of the frustum of `proj_view`, indexed by bit 0 for ndc x, bit 1
+/// for ndc y and bit 2 for the near or far plane. None if the matrix isn't invertible.
+fn frustum_corners(proj_view: &Matrix4<f32>) -> Option<[Vector3<f32>; 8]> {
+    let inverse = proj_view.try_inverse()?;
+    Some(std::array::from_fn(|i| {
+        // Depth is in [0, 1] from the near to the far plane.
+        let ndc = Vector4::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { 0.0 } else { 1.0 },
+            1.0,
+        );
+        let world = inverse * ndc;
+        world.xyz() / world.w
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frustum_corners_lie_on_near_and_far_planes() {
+        let mut camera = Camera::new(Camera::FOV_90);
+        camera.near_plane = 0.5;
+        camera.far_plane = 50.0;
+        let transform = Transform::new();
+        let proj_view = camera.projection_matrix(1.0) * transform.to_view_matrix();
+        let corners = frustum_corners(&proj_view).unwrap();
+        for (i, corner) in corners.iter().enumerate() {
+            let depth = if i & 4 == 0 {
+                camera.near_plane()
+            } else {
+                camera.far_plane()
+            };
+            // A 90 degree fov spans as wide as it is deep.
+            let expected = Vector3::new(
+                if i & 1 == 0 { -depth } else { depth },
+                if i & 2 == 0 { -depth } else { depth },
+                depth,
+            );
+            assert!((corner - expected).norm() < depth * 1e-3);
+        }
+    }
+}
@@ -1 +1,2 @@
 pub mod debug_renderer;
+pub mod debug_text;
@@ -69,7 +69,7 @@ pub struct EguiGpu {
 }
 
 impl EguiGpu {
-    const GRAPH: EguiGpuGraphConstants = EguiGpuGraphConstants {
+    pub const GRAPH: EguiGpuGraphConstants = EguiGpuGraphConstants {
         pass_name: "egui_ui_pass",
         raster_pipeline_name: "egui_ui_raster_pipeline",
         raster_pipeline_info: FrameGraphRasterInfo {
@@ -181,6 +181,7 @@ impl EguiGpu {
                         min_filter: options.minification.into(),
                         address_mode: options.wrap_mode.into(),
                         mipmap_filter: GfxFilterMode::Nearest,
+                        ..Default::default()
                     });
 
                     e.insert(sampler_id);
@@ -216,6 +217,7 @@ impl EguiGpu {
                                 data: bytemuck::cast_slice(image.pixels.as_slice()),
                                 offset: Vector2::zeros(),
                                 extent: Vector2::new(image.width() as u32, image.height() as u32),
+                                layer: 0,
                             });
                         }
                         egui::ImageData::Font(font) => {
@@ -225,6 +227,7 @@ impl EguiGpu {
                                 data: bytemuck::cast_slice(data.as_slice()),
                                 offset: Vector2::zeros(),
                                 extent: Vector2::new(font.width() as u32, font.height() as u32),
+                                layer: 0,
                             });
                         }
                     }
@@ -239,6 +242,7 @@ impl EguiGpu {
                                 data: bytemuck::cast_slice(image.pixels.as_slice()),
                                 offset: Vector2::new(pos[0] as u32, pos[1] as u32),
                                 extent: Vector2::new(image.width() as u32, image.height() as u32),
+                                layer: 0,
                             });
                         }
                         egui::ImageData::Font(font) => {
@@ -248,6 +252,7 @@ impl EguiGpu {
                                 data: bytemuck::cast_slice(data.as_slice()),
                                 offset: Vector2::new(pos[0] as u32, pos[1] as u32),
                                 extent: Vector2::new(font.width() as u32, font.height() as u32),
+                                layer: 0,
                             });
                         }
                     }
@@ -4,6 +4,7 @@ use crate::common::dyn_vec::{DynVec, TypeInfo};
 use crate::entity::{
     component::{Bundle, ComponentTypeBorrow},
     ecs_world::Entity,
+    query::ChangeTick,
 };
 
 /// Essentially a type erased Free List Allocator with knowledge of (X, Y, Z)'s TypeIds and sizes.
@@ -15,6 +16,8 @@ pub struct ComponentArchetype {
     pub types: Vec<TypeInfo>,
     pub borrows: Vec<Cell<ComponentTypeBorrow>>,
     data: Vec<DynVec>,
+    // Per type, the tick each entity's component was last inserted or mutably accessed at.
+    change_ticks: Vec<Vec<Cell<ChangeTick>>>,
     global_indices: Vec<Entity>,
     size: usize,
     // temporary counte for ids, will ikmplement free hashset later for removing so we reuse.
@@ -32,6 +35,7 @@ impl ComponentArchetype {
                 .iter()
                 .map(|ty| DynVec::new(ty.clone()))
                 .collect::<Vec<_>>(),
+            change_ticks: vec![Vec::new(); types_len],
             global_indices: Vec::new(),
             size: 0,
             temp: 0,
@@ -132,6 +136,7 @@ impl ComponentArchetype {
             self.get_entity(index).is_some(),
             "Can't get component of non-existent entity"
         );
+        self.mark_changed(type_id, index);
         return self.get_type_data(type_id).get_mut_unchecked(index);
     }
 
@@ -146,9 +151,10 @@ impl ComponentArchetype {
             self.get_entity(index).is_some(),
             "Can't get component of non-existent entity"
         );
-        return self
-            .try_get_type_data(type_info)
-            .map(|data| data.get_mut_unchecked(index));
+        return self.try_get_type_data(type_info).map(|data| {
+            self.mark_changed(&type_info.type_id, index);
+            data.get_mut_unchecked(index)
+        });
     }
 
     pub fn get_mut<T: 'static>(&mut self, type_info: &TypeInfo, index: usize) -> &mut T {
@@ -156,6 +162,7 @@ impl ComponentArchetype {
             self.get_entity(index).is_some(),
             "Can't get component of non-existent entity"
         );
+        self.mark_changed(&type_info.type_id, index);
         self.get_type_data_mut(type_info).get_mut(index)
     }
 
@@ -178,6 +185,31 @@ impl ComponentArchetype {
         // Safety: We have dropped the old data and both src_data and dst_data are valid ptrs to
         // type of `type_info`.
         dst_data.copy_from_nonoverlapping(src_data, type_info.size());
+        self.change_ticks[i][index].set(ChangeTick::current());
+    }
+
+    /// The tick the entity's component was last inserted or mutably accessed at.
+    pub fn change_tick(&self, type_id: &TypeId, index: usize) -> ChangeTick {
+        self.change_ticks[self.get_type_index(type_id)][index].get()
+    }
+
+    /// Marks the component as changed, this is done for any mutable access since we can't tell
+    /// if it was actually written to.
+    pub fn mark_changed(&self, type_id: &TypeId, index: usize) {
+        self.change_ticks[self.get_type_index(type_id)][index].set(ChangeTick::current());
+    }
+
+    // Newly inserted components count as changed, this includes entities moving archetypes from
+    // adding or removing a component.
+    fn stamp_inserted(&mut self, index: usize) {
+        let tick = ChangeTick::current();
+        for ticks in &mut self.change_ticks {
+            if index >= ticks.len() {
+                ticks.push(Cell::new(tick));
+            } else {
+                ticks[index].set(tick);
+            }
+        }
     }
 
     // This is unsafe if the type ordering of `src_data` doesn't match the internal archetype type
@@ -208,6 +240,7 @@ impl ComponentArchetype {
         } else {
             self.global_indices[index as usize] = entity_id;
         }
+        self.stamp_inserted(index);
         self.size += 1;
 
         index
@@ -313,6 +346,7 @@ impl ComponentArchetype {
         } else {
             self.global_indices[index as usize] = entity_id;
         }
+        self.stamp_inserted(index);
         self.size += 1;
 
         index
@@ -34,6 +34,10 @@ impl GameComponentType {
 pub struct GameComponentCloneContext<'a> {
     pub voxel_registry: &'a mut VoxelModelRegistry,
     pub collider_registry: &'a mut ColliderRegistry,
+    /// Clones reference the same dynamic voxel models instead of copying them.
+    pub share_voxel_models: bool,
+    /// Clones reference the same colliders instead of registering copies of them.
+    pub share_colliders: bool,
 }
 
 pub struct GameComponentSerializeContext<'a> {
@@ -282,6 +286,8 @@ impl<'a> RawComponentRef<'a> {
         let data = unsafe { archetype.get_raw(type_id, index) }.as_ptr() as *mut ();
         let component = NonNull::new(data).unwrap();
         let borrow = archetype.borrow_type(type_id);
+        // The ptr is handed out as mutable, such as for editing the component in the editor.
+        archetype.mark_changed(type_id, index);
         Self { component, borrow }
     }
 }
@@ -368,6 +374,7 @@ impl<'a, T: 'static> QueryItemRef<'a> for ComponentRefMut<'a, T> {
         let data = unsafe { archetype.get_raw(&TypeId::of::<T>(), index) }.as_ptr() as *mut T;
         let component = NonNull::new(data).unwrap();
         let borrow = archetype.borrow_type_mut(&std::any::TypeId::of::<T>());
+        archetype.mark_changed(&std::any::TypeId::of::<T>(), index);
         ComponentRefMut { component, borrow }
     }
 }
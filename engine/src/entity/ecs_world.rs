@@ -1,6 +1,7 @@
 use std::any::{Any, TypeId};
 use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 use std::ptr::NonNull;
 use std::{collections::HashSet, ops::Deref};
 
@@ -9,11 +10,14 @@ use serde::ser::{SerializeSeq, SerializeStruct};
 use uuid::Uuid;
 
 use super::{
-    EntityChildren, EntityParent, GameEntity, RenderableVoxelEntity, scripting::ScriptableEntity,
+    EntityChildren, EntityParent, GameEntity, GlobalTransform, PrefabInstance,
+    RenderableVoxelEntity, scripting::ScriptableEntity,
 };
 use crate::animation::animation_property::AnimationPropertyTypeInfo;
 use crate::animation::animator::Animator;
-use crate::asset::repr::game_entity::{WorldGameComponentAsset, WorldGameEntityAsset};
+use crate::asset::asset::{Assets, GameAssetPath};
+use crate::asset::repr::TextAsset;
+use crate::asset::repr::game_entity::{PrefabAsset, WorldGameComponentAsset, WorldGameEntityAsset};
 use crate::asset::repr::project::ProjectSceneDeserializeContext;
 use crate::audio::AudioPlayer;
 use crate::common::dyn_vec::TypeInfo;
@@ -28,10 +32,13 @@ use crate::entity::component::{
     RawComponentRef,
 };
 use crate::entity::ecs_world;
-use crate::entity::query::{Query, QueryBorrow, QueryItem, QueryItemRef, QueryMany, QueryOne};
+use crate::entity::query::{
+    ChangeTick, Changed, Query, QueryBorrow, QueryItem, QueryItemRef, QueryMany, QueryOne,
+};
 use crate::event::{EventReader, Events};
 use crate::graphics::camera::{Camera, MainCamera};
 use crate::physics::collider_component::EntityColliders;
+use crate::physics::collider_registry::ColliderRegistry;
 use crate::physics::{rigid_body::RigidBody, transform::Transform};
 use crate::resource::ResMut;
 use crate::system::SystemParam;
@@ -72,6 +79,7 @@ pub struct ECSWorld {
     pub game_components: HashMap<TypeId, GameComponentType>,
     pub game_component_names: HashMap</*GameComponent::NAME*/ String, TypeId>,
     pub entity_event_reader: EventReader<EntityCommandEvent>,
+    global_transform_tick: ChangeTick,
 }
 
 impl ECSWorld {
@@ -85,6 +93,7 @@ impl ECSWorld {
             game_components: HashMap::new(),
             game_component_names: HashMap::new(),
             entity_event_reader: EventReader::new(),
+            global_transform_tick: ChangeTick::default(),
         };
 
         // Makes these components cloneable and serializable in the project.
@@ -101,6 +110,8 @@ impl ECSWorld {
         ecs.register_game_component::<EntityColliders>();
         ecs.register_game_component::<Animator>();
         ecs.register_game_component::<AudioPlayer>();
+        ecs.register_game_component::<PrefabInstance>();
+        ecs.register_game_component::<ScriptableEntity>();
 
         ecs
     }
@@ -178,6 +189,20 @@ impl ECSWorld {
         ECSWorldSerializable {
             ecs_world: self,
             ctx,
+            entities: None,
+        }
+    }
+
+    /// Same as `serialize_world` but only serializes `entities`.
+    pub fn serialize_entities<'a>(
+        &'a self,
+        ctx: &'a GameComponentSerializeContext<'a>,
+        entities: &'a HashSet<Entity>,
+    ) -> ECSWorldSerializable<'a> {
+        ECSWorldSerializable {
+            ecs_world: self,
+            ctx,
+            entities: Some(entities),
         }
     }
 
@@ -801,7 +826,7 @@ impl ECSWorld {
     }
 
     pub fn contains_entity(&self, entity: Entity) -> bool {
-        self.entities.has_value(entity)
+        self.entities.contains(entity)
     }
 
     pub fn duplicate_entity(
@@ -954,7 +979,7 @@ impl ECSWorld {
         return new_entity_id;
     }
 
-    pub fn has_parent(&mut self, child: Entity, parent: Entity) -> bool {
+    pub fn has_parent(&self, child: Entity, parent: Entity) -> bool {
         let parent_to_check = parent;
         let mut curr_parent = self.get::<&EntityParent>(child);
         while let Ok(parent) = curr_parent {
@@ -1068,6 +1093,9 @@ impl ECSWorld {
             }
             self.remove_one::<EntityParent>(entity);
         }
+
+        // Keep the cache right for the rest of the frame rather than until the next update.
+        self.refresh_global_transforms([entity]);
     }
 
     /// Despawns the given entity and removes the reference from its parent if it has one
@@ -1128,6 +1156,135 @@ impl ECSWorld {
         return entity_world_transform.clone();
     }
 
+    /// Runs every frame.
+    pub fn update_global_transforms_system(mut ecs_world: ResMut<ECSWorld>) {
+        ecs_world.update_global_transforms();
+    }
+
+    /// Updates the cached `GlobalTransform` of every entity with a `Transform`, only recomputing
+    /// the subtrees of entities whose transform or parent changed since the last update.
+    pub fn update_global_transforms(&mut self) {
+        // Inserting moves the entity's archetype which marks its transform as changed.
+        let uncached = self
+            .query::<()>()
+            .with::<(Transform,)>()
+            .without::<(GlobalTransform,)>()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in uncached {
+            self.insert_one(entity, GlobalTransform::new(&Transform::new(), None))
+                .expect("Failed to insert GlobalTransform component.");
+        }
+        let stale = self
+            .query::<()>()
+            .with::<(GlobalTransform,)>()
+            .without::<(Transform,)>()
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in stale {
+            self.remove_one::<GlobalTransform>(entity);
+        }
+
+        // Unparenting removes `EntityParent` which also moves the archetype.
+        let since = std::mem::replace(&mut self.global_transform_tick, ChangeTick::now());
+        let mut dirty = self
+            .query::<()>()
+            .filter(Changed::<Transform>::since(since))
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .collect::<HashSet<_>>();
+        dirty.extend(
+            self.query::<()>()
+                .with::<(Transform,)>()
+                .filter(Changed::<EntityParent>::since(since))
+                .into_iter()
+                .map(|(entity, _)| entity),
+        );
+        self.refresh_global_transforms(dirty);
+    }
+
+    /// Recomputes the cached `GlobalTransform` of `entities` and all their descendants.
+    pub fn refresh_global_transforms(&self, entities: impl IntoIterator<Item = Entity>) {
+        // Parents first so each entity composes with its parent's updated transform.
+        let mut roots = entities
+            .into_iter()
+            .map(|entity| (self.entity_depth(entity), entity))
+            .collect::<Vec<_>>();
+        roots.sort_by_key(|(depth, _)| *depth);
+
+        let updated_at = ChangeTick::now();
+        let mut visited = HashSet::new();
+        for (_, root) in roots {
+            if visited.contains(&root) {
+                continue;
+            }
+
+            let mut stack = vec![root];
+            while let Some(entity) = stack.pop() {
+                if !visited.insert(entity) {
+                    log::error!(
+                        "Entity {:?} is its own ancestor, not propagating into it again.",
+                        entity
+                    );
+                    continue;
+                }
+
+                let Ok(local_transform) = self.get::<&Transform>(entity).map(|t| t.clone()) else {
+                    continue;
+                };
+                let parent_global = self.get::<&EntityParent>(entity).ok().and_then(|parent| {
+                    self.get::<&GlobalTransform>(parent.parent())
+                        .ok()
+                        .map(|global| global.clone())
+                });
+                let Ok(mut global) = self.get::<&mut GlobalTransform>(entity) else {
+                    continue;
+                };
+                *global = GlobalTransform::new(&local_transform, parent_global.as_ref());
+                global.updated_at = updated_at;
+                drop(global);
+
+                stack.extend(self.get_children(entity));
+            }
+        }
+    }
+
+    // The number of ancestors the entity has, stopping at a cycle.
+    fn entity_depth(&self, entity: Entity) -> usize {
+        let mut ancestors = HashSet::new();
+        let mut curr = entity;
+        while let Ok(parent) = self.get::<&EntityParent>(curr).map(|p| p.parent()) {
+            if !ancestors.insert(parent) {
+                log::error!("Entity {:?} has a cycle in its ancestors.", entity);
+                break;
+            }
+            curr = parent;
+        }
+        ancestors.len()
+    }
+
+    /// The entity's world transform as of the last `update_global_transforms()`. Composes it
+    /// from `entity_local_transform` and its parents instead if the entity isn't cached yet or
+    /// its own transform changed since, changes to just its parents aren't seen until the next
+    /// update.
+    pub fn get_global_transform(
+        &self,
+        entity: Entity,
+        entity_local_transform: &Transform,
+    ) -> Transform {
+        let entity_info = self.entities.get(entity).unwrap();
+        let archetype = &self.archetypes[entity_info.archetype_ptr];
+        if let Ok(global) = self.get::<&GlobalTransform>(entity)
+            && archetype.change_tick(&TypeId::of::<Transform>(), entity_info.index)
+                <= global.updated_at
+        {
+            return global.transform().clone();
+        }
+        self.get_world_transform(entity, entity_local_transform)
+    }
+
     pub fn get_world_transform(
         &self,
         entity: Entity,
@@ -1228,7 +1385,75 @@ impl ECSWorld {
         game_entity.uuid.clone()
     }
 
-    pub fn spawn_prefab(asset: &WorldGameEntityAsset) {}
+    /// Spawns a new entity with a fresh uuid from a clone of `asset`'s components. The asset's
+    /// parent and children aren't restored since they may not exist in this world.
+    pub fn spawn_prefab(
+        &mut self,
+        asset: &WorldGameEntityAsset,
+        ctx: &mut GameComponentCloneContext<'_>,
+    ) -> Entity {
+        let cloned_data = asset
+            .components
+            .values()
+            .filter_map(|component| {
+                let GameComponentType {
+                    methods_vtable_ptr, ..
+                } = self.game_components.get(&component.type_info().type_id)?;
+                // Safety: The asset's component data is of the type its type info describes.
+                let (cloned_ptr, cloned_layout) = unsafe {
+                    Self::clone_component(
+                        component.data(),
+                        component.type_info(),
+                        *methods_vtable_ptr,
+                        ctx,
+                    )
+                };
+                Some((component.type_info().clone(), cloned_ptr, cloned_layout))
+            })
+            .collect::<Vec<_>>();
+
+        let game_entity = GameEntity::new(&asset.name);
+        let mut data = cloned_data
+            .iter()
+            .map(|(type_info, ptr, _)| (type_info.clone(), *ptr as *const u8))
+            .collect::<Vec<_>>();
+        data.push((
+            TypeInfo::new::<GameEntity>(),
+            std::ptr::from_ref(&game_entity) as *const u8,
+        ));
+        let entity = self.spawn_raw(data);
+        // Ownership was moved into the world.
+        std::mem::forget(game_entity);
+        for (_, cloned_ptr, cloned_layout) in cloned_data {
+            // Safety: Allocated by `clone_component` and the data was copied into the world.
+            unsafe { std::alloc::dealloc(cloned_ptr, cloned_layout) };
+        }
+
+        entity
+    }
+
+    /// Spawns a fresh copy of the prefab at `path` with new uuids, nested prefabs are
+    /// instantiated from their own prefab files. Returns the root entity, which remembers its
+    /// prefab with a `PrefabInstance` component.
+    pub fn instantiate_prefab(
+        &mut self,
+        path: &GameAssetPath,
+        project_dir: &Path,
+        voxel_registry: &mut VoxelModelRegistry,
+        collider_registry: &mut ColliderRegistry,
+    ) -> anyhow::Result<Entity> {
+        PrefabAsset::instantiate(
+            path,
+            &mut |path| {
+                Ok(Assets::load_asset_sync::<TextAsset>(
+                    path.as_file_asset_path(project_dir),
+                )?)
+            },
+            self,
+            voxel_registry,
+            collider_registry,
+        )
+    }
 }
 
 pub struct PlayerQuery<'a, Q: Query>(QueryBorrow<'a, Q>);
@@ -1293,6 +1518,8 @@ impl<'de> serde::de::Visitor<'de> for &mut ProjectSceneEntitiesVisitor<'_, '_> {
 pub struct ECSWorldSerializable<'a> {
     ecs_world: &'a ECSWorld,
     ctx: &'a GameComponentSerializeContext<'a>,
+    /// Serializes every game entity when None.
+    entities: Option<&'a HashSet<Entity>>,
 }
 
 impl serde::Serialize for ECSWorldSerializable<'_> {
@@ -1366,6 +1593,11 @@ impl serde::Serialize for ECSWorldEntitiesSerializable<'_> {
                 let Some(entity) = archetype.get_entity(i) else {
                     continue;
                 };
+                if let Some(entities) = self.world.entities
+                    && !entities.contains(&entity)
+                {
+                    continue;
+                }
 
                 seq.serialize_element(&ECSWorldSceneEntitySerializable {
                     sup: self.world,
@@ -4,8 +4,11 @@ use super::ecs_world::Entity;
 use crate::asset::asset::{AssetHandle, AssetPath, GameAssetPath};
 use crate::entity::component::GameComponentSerializeContext;
 use crate::entity::component::{GameComponent, GameComponentDeserializeContext};
+use crate::entity::query::ChangeTick;
+use crate::physics::transform::Transform;
 use crate::voxel::voxel_registry::VoxelModelId;
 use erased_serde::Serialize;
+use nalgebra::Matrix3;
 use rogue_macros::game_component;
 use serde::de::DeserializeSeed;
 use uuid::serde::braced::serialize;
@@ -136,7 +139,8 @@ impl GameComponent for RenderableVoxelEntity {
     ) {
         let mut s = self.clone();
         // Clone the voxel model if it is a dynamic model renderable.
-        if s.is_dynamic()
+        if !ctx.share_voxel_models
+            && s.is_dynamic()
             && let Some(model_id) = s.voxel_model_id()
         {
             s.voxel_model_id = ctx.voxel_registry.clone_model(model_id);
@@ -301,6 +305,14 @@ struct EntityParentSerializable<'a> {
     parent_uuid: &'a uuid::Uuid,
 }
 
+/// Marks the root of an entity tree instantiated from a prefab so the instance can be traced back
+/// to the prefab it came from.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[game_component(name = "PrefabInstance", constructible = false)]
+pub struct PrefabInstance {
+    pub source: GameAssetPath,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
 #[game_component(name = "EntityChildren", constructible = false)]
 pub struct EntityChildren {
@@ -318,3 +330,43 @@ impl Drop for EntityChildren {
         );
     }
 }
+
+/// The entity's world transform, cached from its local `Transform` composed with its parents' by
+/// `ECSWorld::update_global_transforms()`. Not a game component since it's derived every frame.
+#[derive(Clone, Debug)]
+pub struct GlobalTransform {
+    transform: Transform,
+    // Maps positions local to this entity's children into world space. Parent scale is applied
+    // after the parent's rotation, so with non-uniform scale this can't be a `Transform`.
+    child_linear: Matrix3<f32>,
+    pub(crate) updated_at: ChangeTick,
+}
+
+impl GlobalTransform {
+    pub fn new(local_transform: &Transform, parent: Option<&GlobalTransform>) -> Self {
+        let local_linear = Matrix3::from_diagonal(&local_transform.scale)
+            * local_transform.rotation.to_rotation_matrix().into_inner();
+        let Some(parent) = parent else {
+            return Self {
+                transform: local_transform.clone(),
+                child_linear: local_linear,
+                updated_at: ChangeTick::default(),
+            };
+        };
+
+        Self {
+            transform: Transform {
+                position: parent.transform.position
+                    + parent.child_linear * local_transform.position,
+                rotation: parent.transform.rotation * local_transform.rotation,
+                scale: parent.transform.scale.component_mul(&local_transform.scale),
+            },
+            child_linear: parent.child_linear * local_linear,
+            updated_at: ChangeTick::default(),
+        }
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
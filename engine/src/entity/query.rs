@@ -2,6 +2,7 @@ use std::{
     any::TypeId,
     cell::Cell,
     collections::{HashMap, HashSet},
+    sync::atomic::{AtomicU64, Ordering},
 };
 
 use rogue_macros::generate_tuples;
@@ -13,6 +14,51 @@ use crate::entity::{
     ecs_world::{ECSWorld, Entity},
 };
 
+// Shared by every world so ticks stay comparable when the ECSWorld is swapped out, such as
+// when loading a project or starting the game in the editor.
+static NEXT_CHANGE_TICK: AtomicU64 = AtomicU64::new(1);
+
+/// A point in time to look for changed components since. Components are stamped with the
+/// current tick when inserted or mutably accessed, reading a component doesn't count as a
+/// change.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChangeTick(u64);
+
+impl ChangeTick {
+    /// Every component mutably accessed after this call is changed since the returned tick.
+    pub fn now() -> Self {
+        Self(NEXT_CHANGE_TICK.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// The tick components are stamped with when changed.
+    pub fn current() -> Self {
+        Self(NEXT_CHANGE_TICK.load(Ordering::Relaxed))
+    }
+}
+
+/// Query filter only matching entities whose `T` changed after `since`, see
+/// `QueryBorrow::filter()`.
+///
+/// Change flags are per system, each system keeps the tick it last ran at so it sees every
+/// change made since then exactly once, no matter which system made it or how many other
+/// systems have seen it already. A system running once a frame does
+/// `let since = std::mem::replace(&mut self.last_tick, ChangeTick::now());` at its start, which
+/// also means it sees its own changes the next frame. A default tick sees every component as
+/// changed.
+pub struct Changed<T> {
+    since: ChangeTick,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T: 'static> Changed<T> {
+    pub fn since(since: ChangeTick) -> Self {
+        Self {
+            since,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
 pub trait Query {
     /// Result type from the query.
     type Item<'a>;
@@ -206,6 +252,7 @@ pub struct QueryBorrow<'a, Q: Query> {
     ecs_world: &'a ECSWorld,
     with: HashSet<TypeId>,
     without: HashSet<TypeId>,
+    changed: Vec<(TypeId, ChangeTick)>,
     marker: std::marker::PhantomData<&'a Q>,
 }
 
@@ -217,6 +264,7 @@ impl<'a, Q: Query> QueryBorrow<'a, Q> {
                 .into_iter()
                 .collect::<HashSet<_>>(),
             without: HashSet::new(),
+            changed: Vec::new(),
             marker: std::marker::PhantomData,
         }
     }
@@ -237,6 +285,15 @@ impl<'a, Q: Query> QueryBorrow<'a, Q> {
         self
     }
 
+    /// Only yields entities whose `T` changed since the filter's tick, implies `with::<(T,)>()`.
+    pub fn filter<T: 'static>(mut self, changed: Changed<T>) -> Self {
+        let id = TypeId::of::<T>();
+        self.with.insert(id);
+        assert!(!self.without.contains(&id));
+        self.changed.push((id, changed.since));
+        self
+    }
+
     pub fn execute(&mut self) {
         //'archetype_loop: for (types, archetype) in self.ecs_world.archetypes.iter() {
         //    let mut matching_types = 0;
@@ -294,6 +351,7 @@ impl<'a, Q: Query> QueryBorrow<'a, Q> {
         QueryIter::<'a, Q> {
             ecs_world: self.ecs_world,
             borrows,
+            changed: self.changed.clone(),
             archetype_indices,
             curr_index: 0,
             marker: std::marker::PhantomData,
@@ -304,6 +362,7 @@ impl<'a, Q: Query> QueryBorrow<'a, Q> {
 pub struct QueryIter<'a, Q: Query> {
     ecs_world: &'a ECSWorld,
     borrows: Vec<&'a Cell<ComponentTypeBorrow>>,
+    changed: Vec<(TypeId, ChangeTick)>,
     archetype_indices: Vec<usize>,
     // Index within the archetype.
     curr_index: usize,
@@ -314,23 +373,33 @@ impl<'a, Q: Query> Iterator for QueryIter<'a, Q> {
     type Item = (Entity, Q::Item<'a>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(archetype_index) = self.archetype_indices.last() else {
-            return None;
-        };
-        let archetype = &self.ecs_world.archetypes[*archetype_index];
-        if self.curr_index >= archetype.len() {
-            self.archetype_indices.pop();
-            self.curr_index = 0;
-            return self.next();
-        }
+        // Loops rather than recursing since the changed filter can skip most of an archetype.
+        loop {
+            let Some(archetype_index) = self.archetype_indices.last() else {
+                return None;
+            };
+            let archetype = &self.ecs_world.archetypes[*archetype_index];
+            if self.curr_index >= archetype.len() {
+                self.archetype_indices.pop();
+                self.curr_index = 0;
+                continue;
+            }
 
-        let Some(entity) = archetype.get_entity(self.curr_index) else {
+            let index = self.curr_index;
             self.curr_index += 1;
-            return self.next();
-        };
-        let query_item = Q::fetch(archetype, self.curr_index);
-        self.curr_index += 1;
-        return Some((entity, query_item));
+            let Some(entity) = archetype.get_entity(index) else {
+                continue;
+            };
+            if !self
+                .changed
+                .iter()
+                .all(|(type_id, since)| archetype.change_tick(type_id, index) > *since)
+            {
+                continue;
+            }
+            let query_item = Q::fetch(archetype, index);
+            return Some((entity, query_item));
+        }
     }
 }
 
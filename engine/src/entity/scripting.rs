@@ -1,23 +1,38 @@
 use std::{
-    collections::{HashMap, HashSet},
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
     sync::Arc,
 };
 
 use mlua::{Lua, ObjectLike};
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
 use parking_lot::RwLock;
-use rogue_macros::Resource;
+use rogue_macros::{Resource, game_component};
+use serde::{Deserialize, de::IntoDeserializer};
 use crate::asset::{
-    asset::{AssetHandle, AssetPath, AssetStatus, Assets},
+    asset::{AssetHandle, AssetPath, AssetStatus, Assets, GameAssetPath},
     repr::TextAsset,
 };
 use crate::resource::{Res, ResMut};
-use crate::entity::GameEntity;
+use crate::common::geometry::ray::Ray;
+use crate::common::rng::{Rng, RngStream};
+use crate::entity::{GameEntity, RenderableVoxelEntity};
+use crate::event::{EventReader, Events};
+use crate::graphics::camera::MainCamera;
+use crate::input::{Input, keyboard::Key};
+use crate::physics::physics_world::{CollisionEvent, TriggerOverlapEvent, TriggerOverlapState};
 use crate::physics::transform::Transform;
+use crate::voxel::voxel_registry::{VoxelModelId, VoxelModelRegistry};
+use crate::window::time::Time;
+use crate::world::terrain::region_map::RegionMap;
+use crate::world::world::{World, WorldTraceInfo};
 use super::ecs_world::{ECSWorld, Entity};
 
-#[derive(Clone)]
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[game_component(name = "ScriptableEntity")]
 pub struct ScriptableEntity {
-    pub scripts: Vec<AssetPath>,
+    pub scripts: Vec<GameAssetPath>,
 }
 
 enum ScriptEvent {
@@ -44,17 +59,223 @@ impl ScriptingWorldState {
     }
 }
 
+/// The engine state scripts can reach while a script callback is running.
+pub struct ScriptWorldContext<'a> {
+    pub ecs_world: &'a mut ECSWorld,
+    pub voxel_registry: &'a VoxelModelRegistry,
+    pub region_map: &'a RegionMap,
+    pub main_camera: &'a MainCamera,
+    pub input: &'a Input,
+}
+
+/// The engine events scripts can subscribe to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ScriptEventKind {
+    Collision,
+    TriggerEnter,
+    TriggerExit,
+    EntitySpawned,
+    EntityDespawned,
+    KeyPressed,
+}
+
+impl ScriptEventKind {
+    const ALL: [Self; 6] = [
+        Self::Collision,
+        Self::TriggerEnter,
+        Self::TriggerExit,
+        Self::EntitySpawned,
+        Self::EntityDespawned,
+        Self::KeyPressed,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Collision => "collision",
+            Self::TriggerEnter => "trigger_enter",
+            Self::TriggerExit => "trigger_exit",
+            Self::EntitySpawned => "entity_spawned",
+            Self::EntityDespawned => "entity_despawned",
+            Self::KeyPressed => "key_pressed",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    fn is_trigger(&self) -> bool {
+        matches!(self, Self::TriggerEnter | Self::TriggerExit)
+    }
+}
+
+struct ScriptSubscription {
+    owner: Entity,
+    event: ScriptEventKind,
+    callback: mlua::Function,
+}
+
+struct ScriptSubscriptions {
+    next_id: u64,
+    /// Ordered by id so callbacks run in the order they subscribed.
+    subscriptions: BTreeMap<u64, ScriptSubscription>,
+    /// Every entity as of the last dispatch to find which were spawned and despawned, only
+    /// tracked while something is subscribed to those events.
+    known_entities: Option<HashSet<Entity>>,
+}
+
+impl ScriptSubscriptions {
+    fn new() -> Self {
+        Self {
+            next_id: 0,
+            subscriptions: BTreeMap::new(),
+            known_entities: None,
+        }
+    }
+
+    fn subscribe(
+        &mut self,
+        owner: Entity,
+        event: ScriptEventKind,
+        callback: mlua::Function,
+        ecs_world: &ECSWorld,
+    ) -> u64 {
+        let is_entity_event = matches!(
+            event,
+            ScriptEventKind::EntitySpawned | ScriptEventKind::EntityDespawned
+        );
+        if is_entity_event && self.known_entities.is_none() {
+            self.known_entities = Some(Self::all_entities(ecs_world));
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.subscriptions.insert(
+            id,
+            ScriptSubscription {
+                owner,
+                event,
+                callback,
+            },
+        );
+        id
+    }
+
+    fn all_entities(ecs_world: &ECSWorld) -> HashSet<Entity> {
+        ecs_world
+            .entities
+            .iter_with_handle()
+            .map(|(entity, _)| entity)
+            .collect()
+    }
+
+    /// The entities spawned and despawned since the last call.
+    fn take_entity_events(&mut self, ecs_world: &ECSWorld) -> Vec<(ScriptEventKind, Entity)> {
+        let is_subscribed = self.subscriptions.values().any(|subscription| {
+            matches!(
+                subscription.event,
+                ScriptEventKind::EntitySpawned | ScriptEventKind::EntityDespawned
+            )
+        });
+        if !is_subscribed {
+            self.known_entities = None;
+            return Vec::new();
+        }
+
+        let entities = Self::all_entities(ecs_world);
+        let Some(known_entities) = self.known_entities.replace(entities.clone()) else {
+            return Vec::new();
+        };
+        let mut spawned = entities
+            .difference(&known_entities)
+            .copied()
+            .collect::<Vec<_>>();
+        let mut despawned = known_entities
+            .difference(&entities)
+            .copied()
+            .collect::<Vec<_>>();
+        spawned.sort_by_key(|entity| entity.index());
+        despawned.sort_by_key(|entity| entity.index());
+        spawned
+            .into_iter()
+            .map(|entity| (ScriptEventKind::EntitySpawned, entity))
+            .chain(
+                despawned
+                    .into_iter()
+                    .map(|entity| (ScriptEventKind::EntityDespawned, entity)),
+            )
+            .collect()
+    }
+}
+
+/// Runs Lua scripts, the following globals are exposed to them:
+///
+/// - `log_bar(message)`
+/// - `rand()`, a float in [0, 1).
+/// - `rand_range(min, max)`, an integer in [min, max].
+///
+/// These can only be called from within the `on_update(dt)`, `on_trigger_enter(a, b)` and
+/// `on_trigger_exit(a, b)` callbacks, or a subscribed callback, since they touch the world.
+/// Entities are integer handles and vectors are `{ x, y, z }` tables, using a despawned entity
+/// raises a script error.
+///
+/// - `spawn_entity(name, position)`, returns the new entity.
+/// - `entity_exists(entity)`
+/// - `set_voxel_model(entity, asset_path)`, the model is loaded from a game asset path such as
+///   `"models::cube::rvox"`.
+/// - `get_transform(entity)`, returns the entity's local transform as
+///   `{ position, rotation = { x, y, z, w }, scale, forward }`.
+/// - `set_transform(entity, transform)`, fields missing from the table are left as is, `forward`
+///   is ignored.
+/// - `raycast(origin, direction, max_distance)`, traces the terrain and voxel entities returning
+///   `{ distance, position, normal, entity }` or nil, `entity` is nil for terrain hits.
+/// - `main_camera()`, the camera entity or nil.
+/// - `subscribe(entity, event, callback)`, calls `callback` for each of the event this frame
+///   until unsubscribed or the entity, which must be a `ScriptableEntity`, is despawned.
+///   Returns the subscription id. The events are `"collision"`, `"trigger_enter"` and
+///   `"trigger_exit"` involving the entity, which pass the other entity, `"entity_spawned"` and
+///   `"entity_despawned"`, which pass the entity, and `"key_pressed"`, which passes the key.
+/// - `unsubscribe(id)`
+/// - `is_key_pressed(key)`, `key` is the name of a `Key` such as `"E"` or `"Space"`.
 #[derive(Resource)]
 pub struct Scripts {
     lua: Lua,
+    /// The source of each script whose chunk is currently running.
     scripts: HashMap<AssetPath, String>,
     to_load_scripts: HashSet<AssetPath>,
     loading_scripts: HashMap<AssetPath, AssetHandle>,
+    /// The watched handles of running scripts, a script is swapped in again when its asset
+    /// reloads.
+    script_handles: HashMap<AssetPath, AssetHandle>,
+    /// The error of the last script that failed to compile or run, or the last callback that
+    /// failed. The previous version of a script keeps running if there is one.
+    script_error: Option<String>,
     world_state: Arc<RwLock<ScriptingWorldState>>,
     script_events: Arc<RwLock<Vec<ScriptEvent>>>,
+    /// Backs the `rand` and `rand_range` script functions.
+    rng: Arc<RwLock<RngStream>>,
+    trigger_event_reader: EventReader<TriggerOverlapEvent>,
+    collision_event_reader: EventReader<CollisionEvent>,
+    subscriptions: RefCell<ScriptSubscriptions>,
+    /// Whether script callbacks are called, scripts still load and hot reload while stopped.
+    pub run_scripts: bool,
 }
 
 impl Scripts {
+    /// The globals `with_world` binds.
+    const WORLD_FUNCTIONS: [&str; 10] = [
+        "spawn_entity",
+        "entity_exists",
+        "set_voxel_model",
+        "get_transform",
+        "set_transform",
+        "raycast",
+        "main_camera",
+        "subscribe",
+        "unsubscribe",
+        "is_key_pressed",
+    ];
+
     pub fn new() -> Self {
         let script_events = Arc::new(RwLock::new(Vec::new()));
         let world_state = Arc::new(RwLock::new(ScriptingWorldState::new()));
@@ -72,23 +293,74 @@ impl Scripts {
             .unwrap(),
         );
 
+        // Scripts get their own stream so they stay deterministic from the project seed
+        // without disturbing engine randomness.
+        let rng = Arc::new(RwLock::new(RngStream::new(0)));
+        let rng_ref = rng.clone();
+        lua.globals().set(
+            "rand",
+            lua.create_function(move |_, ()| Ok(rng_ref.write().next_f64()))
+                .unwrap(),
+        );
+        let rng_ref = rng.clone();
+        lua.globals().set(
+            "rand_range",
+            lua.create_function(move |_, (min, max): (i64, i64)| {
+                if min > max {
+                    return Err(mlua::Error::runtime("rand_range min must be <= max"));
+                }
+                Ok(rng_ref.write().range_i64(min, max))
+            })
+            .unwrap(),
+        );
+
         Self {
             lua,
             scripts: HashMap::new(),
             to_load_scripts: HashSet::new(),
             loading_scripts: HashMap::new(),
+            script_handles: HashMap::new(),
+            script_error: None,
             world_state,
             script_events,
+            rng,
+            trigger_event_reader: EventReader::new(),
+            collision_event_reader: EventReader::new(),
+            subscriptions: RefCell::new(ScriptSubscriptions::new()),
+            run_scripts: false,
         }
     }
 
-    pub fn refresh(&mut self) {
-        self.to_load_scripts.extend(
-            self.scripts
-                .drain()
-                .map(|(path, lua)| path)
-                .collect::<Vec<_>>(),
-        );
+    /// Restarts the script rng stream from the project seed, should be called when the game
+    /// starts so every run sees the same sequence.
+    pub fn seed_rng(&mut self, rng: &Rng) {
+        *self.rng.write() = rng.stream("scripts");
+    }
+
+    /// Starts calling script callbacks with the rng reseeded from the project seed.
+    pub fn start(&mut self, rng: &Rng) {
+        self.seed_rng(rng);
+        self.run_scripts = true;
+    }
+
+    /// Stops calling script callbacks and drops every subscription since the entities which
+    /// own them don't outlive the game.
+    pub fn stop(&mut self) {
+        self.run_scripts = false;
+        *self.subscriptions.get_mut() = ScriptSubscriptions::new();
+    }
+
+    pub fn refresh(&mut self, assets: &mut Assets) {
+        for handle in self
+            .script_handles
+            .values()
+            .chain(self.loading_scripts.values())
+        {
+            assets.unwatch_asset(handle);
+        }
+        self.script_handles.clear();
+        self.to_load_scripts
+            .extend(self.scripts.drain().map(|(path, lua)| path));
         self.to_load_scripts
             .extend(self.loading_scripts.drain().map(|(path, handle)| path));
     }
@@ -109,23 +381,388 @@ impl Scripts {
         //}
     }
 
+    /// Calls the callbacks subscribed to this frame's events, as well as the global
+    /// `on_trigger_enter(entity_a, entity_b)` and `on_trigger_exit` script functions for
+    /// trigger overlap events.
+    pub fn dispatch_events(
+        mut scripts: ResMut<Scripts>,
+        mut ecs_world: ResMut<ECSWorld>,
+        (voxel_registry, region_map, main_camera, input): (
+            Res<VoxelModelRegistry>,
+            Res<RegionMap>,
+            Res<MainCamera>,
+            Res<Input>,
+        ),
+        events: Res<Events>,
+    ) {
+        let scripts: &mut Scripts = &mut scripts;
+        let mut pair_events = Vec::new();
+        for event in scripts.trigger_event_reader.read(&events) {
+            let kind = match event.state {
+                TriggerOverlapState::Enter => ScriptEventKind::TriggerEnter,
+                TriggerOverlapState::Exit => ScriptEventKind::TriggerExit,
+            };
+            pair_events.push((kind, event.entity_a, event.entity_b));
+        }
+        for event in scripts.collision_event_reader.read(&events) {
+            pair_events.push((ScriptEventKind::Collision, event.entity_a, event.entity_b));
+        }
+        if !scripts.run_scripts {
+            return;
+        }
+        let mut pressed_keys = Vec::new();
+        if !input.input_block {
+            pressed_keys.extend(input.keyboard().pressed_keys().copied());
+        }
+
+        let ctx = ScriptWorldContext {
+            ecs_world: &mut ecs_world,
+            voxel_registry: &voxel_registry,
+            region_map: &region_map,
+            main_camera: &main_camera,
+            input: &input,
+        };
+        scripts.dispatch(ctx, &pair_events, &pressed_keys);
+    }
+
+    fn dispatch(
+        &mut self,
+        ctx: ScriptWorldContext<'_>,
+        pair_events: &[(ScriptEventKind, Entity, Entity)],
+        pressed_keys: &[Key],
+    ) {
+        let key_names = pressed_keys
+            .iter()
+            .filter_map(|key| self.lua.create_string(format!("{:?}", key)).ok())
+            .collect::<Vec<_>>();
+
+        let subscriptions = self.subscriptions.get_mut();
+        // Subscriptions are cleaned up with their owning entity.
+        subscriptions
+            .subscriptions
+            .retain(|_, subscription| ctx.ecs_world.contains_entity(subscription.owner));
+        let entity_events = subscriptions.take_entity_events(ctx.ecs_world);
+
+        // Resolve every call first since callbacks may subscribe or unsubscribe.
+        let mut calls = Vec::new();
+        for subscription in subscriptions.subscriptions.values() {
+            let mut call = |arg: mlua::Value| {
+                calls.push((
+                    subscription.event.name(),
+                    subscription.callback.clone(),
+                    arg,
+                ));
+            };
+            match subscription.event {
+                ScriptEventKind::Collision
+                | ScriptEventKind::TriggerEnter
+                | ScriptEventKind::TriggerExit => {
+                    for (kind, entity_a, entity_b) in pair_events {
+                        if *kind != subscription.event {
+                            continue;
+                        }
+                        // The callback gets the other entity of the pair.
+                        if *entity_a == subscription.owner {
+                            call(mlua::Value::Integer(entity_to_lua(*entity_b)));
+                        } else if *entity_b == subscription.owner {
+                            call(mlua::Value::Integer(entity_to_lua(*entity_a)));
+                        }
+                    }
+                }
+                ScriptEventKind::EntitySpawned | ScriptEventKind::EntityDespawned => {
+                    for (kind, entity) in &entity_events {
+                        if *kind == subscription.event {
+                            call(mlua::Value::Integer(entity_to_lua(*entity)));
+                        }
+                    }
+                }
+                ScriptEventKind::KeyPressed => {
+                    for key_name in &key_names {
+                        call(mlua::Value::String(key_name.clone()));
+                    }
+                }
+            }
+        }
+        if calls.is_empty() && !pair_events.iter().any(|(kind, ..)| kind.is_trigger()) {
+            return;
+        }
+
+        let result = self.with_world(ctx, |lua| {
+            let mut last_error = None;
+            for (kind, entity_a, entity_b) in pair_events {
+                let callback_name = match kind {
+                    ScriptEventKind::TriggerEnter => "on_trigger_enter",
+                    ScriptEventKind::TriggerExit => "on_trigger_exit",
+                    _ => continue,
+                };
+                let args = (entity_to_lua(*entity_a), entity_to_lua(*entity_b));
+                if let Err(err) = Self::call_global_callback(lua, callback_name, args) {
+                    last_error = Some(err);
+                }
+            }
+            for (event_name, callback, arg) in calls {
+                if let Err(err) = Self::call_callback(event_name, &callback, arg) {
+                    last_error = Some(err);
+                }
+            }
+            Ok(last_error)
+        });
+        if let Ok(Some(err)) = result {
+            self.script_error = Some(err);
+        }
+    }
+
+    /// Calls the global `on_update(dt)` script function every frame.
+    pub fn update_scripts(
+        mut scripts: ResMut<Scripts>,
+        mut ecs_world: ResMut<ECSWorld>,
+        (voxel_registry, region_map, main_camera, input): (
+            Res<VoxelModelRegistry>,
+            Res<RegionMap>,
+            Res<MainCamera>,
+            Res<Input>,
+        ),
+        time: Res<Time>,
+    ) {
+        if !scripts.run_scripts {
+            return;
+        }
+        let ctx = ScriptWorldContext {
+            ecs_world: &mut ecs_world,
+            voxel_registry: &voxel_registry,
+            region_map: &region_map,
+            main_camera: &main_camera,
+            input: &input,
+        };
+        let dt = time.delta_time().as_secs_f64();
+        let result = scripts.with_world(ctx, |lua| {
+            Ok(Self::call_global_callback(lua, "on_update", dt).err())
+        });
+        if let Ok(Some(err)) = result {
+            scripts.script_error = Some(err);
+        }
+    }
+
+    /// Calls the global function `callback_name` if a script defined it.
+    fn call_global_callback(
+        lua: &Lua,
+        callback_name: &str,
+        args: impl mlua::IntoLuaMulti,
+    ) -> Result<(), String> {
+        let Ok(Some(callback)) = lua.globals().get::<Option<mlua::Function>>(callback_name) else {
+            return Ok(());
+        };
+        Self::call_callback(callback_name, &callback, args)
+    }
+
+    /// Errors are logged and returned rather than stopping the other callbacks from running.
+    fn call_callback(
+        callback_name: &str,
+        callback: &mlua::Function,
+        args: impl mlua::IntoLuaMulti,
+    ) -> Result<(), String> {
+        callback.call::<()>(args).map_err(|err| {
+            let message = format!("Error running script callback `{}`, {}", callback_name, err);
+            log::error!("{}", message);
+            message
+        })
+    }
+
+    /// Runs `f` with the world functions bound to `ctx`. They are removed again afterwards
+    /// so scripts can't reach the world outside of a callback.
+    fn with_world<R>(
+        &self,
+        ctx: ScriptWorldContext<'_>,
+        f: impl FnOnce(&Lua) -> mlua::Result<R>,
+    ) -> mlua::Result<R> {
+        let ctx = RefCell::new(ctx);
+        let ctx = &ctx;
+        let globals = self.lua.globals();
+        let result = self.lua.scope(|scope| {
+            globals.set(
+                "spawn_entity",
+                scope.create_function(move |_, (name, position): (String, mlua::Table)| {
+                    let mut transform = Transform::new();
+                    transform.position = vector_from_lua(&position)?;
+                    let entity = ctx
+                        .borrow_mut()
+                        .ecs_world
+                        .spawn((GameEntity::new(name), transform));
+                    Ok(entity_to_lua(entity))
+                })?,
+            )?;
+            globals.set(
+                "entity_exists",
+                scope.create_function(move |_, entity: i64| {
+                    Ok(entity_from_lua(&ctx.borrow().ecs_world, entity).is_ok())
+                })?,
+            )?;
+            globals.set(
+                "set_voxel_model",
+                scope.create_function(move |_, (entity, asset_path): (i64, String)| {
+                    let mut ctx = ctx.borrow_mut();
+                    let entity = entity_from_lua(&ctx.ecs_world, entity)?;
+                    let Some(asset_path) = GameAssetPath::new(&asset_path, false) else {
+                        return Err(mlua::Error::runtime(format!(
+                            "Invalid asset path `{}`",
+                            asset_path
+                        )));
+                    };
+                    // The model is loaded for renderables with a path and a null model id.
+                    if let Ok(mut renderable) =
+                        ctx.ecs_world.get::<&mut RenderableVoxelEntity>(entity)
+                    {
+                        renderable.set_model(Some(asset_path), VoxelModelId::null());
+                        return Ok(());
+                    }
+                    ctx.ecs_world
+                        .insert_one(
+                            entity,
+                            RenderableVoxelEntity::new(
+                                Some(asset_path),
+                                false,
+                                VoxelModelId::null(),
+                            ),
+                        )
+                        .map_err(|err| mlua::Error::runtime(err.to_string()))
+                })?,
+            )?;
+            globals.set(
+                "get_transform",
+                scope.create_function(move |lua, entity: i64| {
+                    let ctx = ctx.borrow();
+                    let entity = entity_from_lua(&ctx.ecs_world, entity)?;
+                    let transform = ctx
+                        .ecs_world
+                        .get::<&Transform>(entity)
+                        .map_err(|_| mlua::Error::runtime("Entity doesn't have a transform"))?;
+                    transform_to_lua(lua, &transform)
+                })?,
+            )?;
+            globals.set(
+                "set_transform",
+                scope.create_function(move |_, (entity, table): (i64, mlua::Table)| {
+                    let ctx = ctx.borrow();
+                    let entity = entity_from_lua(&ctx.ecs_world, entity)?;
+                    let mut transform = ctx
+                        .ecs_world
+                        .get::<&mut Transform>(entity)
+                        .map_err(|_| mlua::Error::runtime("Entity doesn't have a transform"))?;
+                    transform_from_lua(&table, &mut transform)
+                })?,
+            )?;
+            globals.set(
+                "raycast",
+                scope.create_function(move |lua, args: (mlua::Table, mlua::Table, f32)| {
+                    let (origin, direction, max_distance) = args;
+                    let ctx = ctx.borrow();
+                    let origin = vector_from_lua(&origin)?;
+                    let direction = vector_from_lua(&direction)?
+                        .try_normalize(f32::EPSILON)
+                        .ok_or_else(|| mlua::Error::runtime("Raycast direction is zero"))?;
+                    let Some(hit) = World::trace_detailed(
+                        &Ray::new(origin, direction),
+                        max_distance,
+                        &ctx.ecs_world,
+                        ctx.voxel_registry,
+                        ctx.region_map,
+                    ) else {
+                        return Ok(None);
+                    };
+                    let table = lua.create_table()?;
+                    table.set("distance", hit.depth_t)?;
+                    table.set(
+                        "position",
+                        vector_to_lua(lua, &(origin + direction * hit.depth_t))?,
+                    )?;
+                    table.set("normal", vector_to_lua(lua, &hit.normal)?)?;
+                    if let WorldTraceInfo::Entity { entity_id } = hit.info {
+                        table.set("entity", entity_to_lua(entity_id))?;
+                    }
+                    Ok(Some(table))
+                })?,
+            )?;
+            globals.set(
+                "main_camera",
+                scope.create_function(move |_, ()| {
+                    Ok(ctx.borrow().main_camera.camera().map(entity_to_lua))
+                })?,
+            )?;
+            globals.set(
+                "subscribe",
+                scope.create_function(
+                    move |_, (entity, event_name, callback): (i64, String, mlua::Function)| {
+                        let ctx = ctx.borrow();
+                        let owner = entity_from_lua(&ctx.ecs_world, entity)?;
+                        if !ctx.ecs_world.contains::<ScriptableEntity>(owner) {
+                            return Err(mlua::Error::runtime(
+                                "Only scriptable entities can subscribe to events",
+                            ));
+                        }
+                        let Some(event) = ScriptEventKind::from_name(&event_name) else {
+                            return Err(mlua::Error::runtime(format!(
+                                "Unknown event `{}`",
+                                event_name
+                            )));
+                        };
+                        Ok(self.subscriptions.borrow_mut().subscribe(
+                            owner,
+                            event,
+                            callback,
+                            &ctx.ecs_world,
+                        ))
+                    },
+                )?,
+            )?;
+            globals.set(
+                "unsubscribe",
+                scope.create_function(move |_, id: u64| {
+                    Ok(self
+                        .subscriptions
+                        .borrow_mut()
+                        .subscriptions
+                        .remove(&id)
+                        .is_some())
+                })?,
+            )?;
+            globals.set(
+                "is_key_pressed",
+                scope.create_function(move |_, key: String| {
+                    let Some(key) = key_from_name(&key) else {
+                        return Err(mlua::Error::runtime(format!("Unknown key `{}`", key)));
+                    };
+                    Ok(ctx.borrow().input.is_key_pressed(key))
+                })?,
+            )?;
+
+            f(&self.lua)
+        });
+        for name in Self::WORLD_FUNCTIONS {
+            globals.set(name, mlua::Value::Nil)?;
+        }
+        result
+    }
+
+    /// Loads queued scripts and swaps in scripts whose files were modified since they were
+    /// loaded.
     pub fn update_loaded_scripts(
         mut scripts: ResMut<Scripts>,
         ecs_world: ResMut<ECSWorld>,
         mut assets: ResMut<Assets>,
     ) {
         let scripts: &mut Scripts = &mut scripts;
-        for script_path in scripts.to_load_scripts.drain() {
-            let handle = assets.load_asset::<TextAsset>(script_path.clone());
+        for script_path in scripts.to_load_scripts.drain().collect::<Vec<_>>() {
+            let handle = assets.load_watched_asset::<TextAsset>(script_path.clone());
             scripts.loading_scripts.insert(script_path, handle);
         }
 
+        let mut loaded_scripts = Vec::new();
         let mut finished_loading_paths = Vec::new();
         for (path, handle) in scripts.loading_scripts.iter() {
             match assets.get_asset_status(handle) {
                 AssetStatus::Loaded => {
                     let text = assets.take_asset::<TextAsset>(handle).unwrap();
-                    scripts.scripts.insert(path.clone(), text.contents);
+                    loaded_scripts.push((path.clone(), text.contents));
                     finished_loading_paths.push(path.clone());
                 }
                 AssetStatus::NotFound => {
@@ -138,20 +775,108 @@ impl Scripts {
             }
         }
         for path in finished_loading_paths.drain(..) {
-            scripts.loading_scripts.remove(&path);
+            let handle = scripts.loading_scripts.remove(&path).unwrap();
+            scripts.script_handles.insert(path, handle);
+        }
+
+        for (path, handle) in &scripts.script_handles {
+            if !assets.is_asset_reloaded(handle) {
+                continue;
+            }
+            log::info!("Reloading modified script {:?}.", path);
+            let text = assets.take_asset::<TextAsset>(handle).unwrap();
+            loaded_scripts.push((path.clone(), text.contents));
+        }
+        for (path, source) in loaded_scripts {
+            scripts.swap_script(path, source);
         }
     }
 
-    pub fn try_load_world_scripts(&mut self, ecs_world: &mut ECSWorld) {
+    /// Runs the script's chunk, replacing the previous version of the script if it succeeds.
+    fn swap_script(&mut self, path: AssetPath, source: String) {
+        match self.run_script(&path, &source) {
+            Ok(()) => {
+                self.script_error = None;
+                self.scripts.insert(path, source);
+            }
+            Err(err) => {
+                let message = format!("Error running script {:?}, {}", path, err);
+                log::error!("{}", message);
+                self.script_error = Some(message);
+            }
+        }
+    }
+
+    /// Runs the chunk in its own environment and only moves its globals into the lua globals
+    /// once it has run without errors. Global tables which already exist are updated in place
+    /// rather than replaced so any state entities stored in them survives a reload.
+    fn run_script(&self, path: &AssetPath, source: &str) -> mlua::Result<()> {
+        let globals = self.lua.globals();
+        let env = self.lua.create_table()?;
+        // Reads fall through to the globals so the engine functions are still callable.
+        let env_metatable = self.lua.create_table()?;
+        env_metatable.set("__index", globals.clone())?;
+        env.set_metatable(Some(env_metatable.clone()));
+        self.lua
+            .load(source)
+            .set_name(format!("{:?}", path))
+            .set_environment(env.clone())
+            .exec()?;
+
+        let env_globals = env
+            .pairs::<mlua::Value, mlua::Value>()
+            .collect::<mlua::Result<Vec<_>>>()?;
+        for (name, value) in env_globals {
+            // The chunk's functions keep `env` as their environment, so it is emptied to have
+            // them read and write the merged globals from now on.
+            env.raw_set(name.clone(), mlua::Value::Nil)?;
+            if let (mlua::Value::Table(old_table), mlua::Value::Table(new_table)) =
+                (globals.raw_get::<mlua::Value>(name.clone())?, &value)
+            {
+                for pair in new_table.pairs::<mlua::Value, mlua::Value>() {
+                    let (key, new_value) = pair?;
+                    if new_value.is_function()
+                        || old_table.raw_get::<mlua::Value>(key.clone())?.is_nil()
+                    {
+                        old_table.raw_set(key, new_value)?;
+                    }
+                }
+                continue;
+            }
+            globals.raw_set(name, value)?;
+        }
+        env_metatable.set("__newindex", globals)?;
+        Ok(())
+    }
+
+    /// The error of the last script that failed to compile or run.
+    pub fn script_error(&self) -> Option<&str> {
+        self.script_error.as_deref()
+    }
+
+    /// Queues the scripts of every `ScriptableEntity` which aren't loaded yet.
+    pub fn load_world_scripts(
+        mut scripts: ResMut<Scripts>,
+        mut ecs_world: ResMut<ECSWorld>,
+        assets: Res<Assets>,
+    ) {
+        let Some(project_dir) = assets.project_dir() else {
+            return;
+        };
+        scripts.try_load_world_scripts(&mut ecs_world, project_dir);
+    }
+
+    pub fn try_load_world_scripts(&mut self, ecs_world: &mut ECSWorld, project_dir: &Path) {
         for (entity_id, scriptable) in ecs_world.query::<&ScriptableEntity>().into_iter() {
-            for asset_path in &scriptable.scripts {
-                if self.scripts.contains_key(asset_path)
-                    || self.to_load_scripts.contains(asset_path)
-                    || self.loading_scripts.contains_key(asset_path)
+            for game_asset_path in &scriptable.scripts {
+                let asset_path = game_asset_path.as_file_asset_path(project_dir);
+                if self.scripts.contains_key(&asset_path)
+                    || self.to_load_scripts.contains(&asset_path)
+                    || self.loading_scripts.contains_key(&asset_path)
                 {
                     continue;
                 }
-                self.to_load_scripts.insert(asset_path.clone());
+                self.to_load_scripts.insert(asset_path);
             }
         }
     }
@@ -161,15 +886,16 @@ impl Scripts {
     }
 
     /// Alias for `are_scripts_loaded`.
-    pub fn can_start_game(&self, ecs_world: &mut ECSWorld) -> bool {
-        return self.are_scripts_loaded(ecs_world);
+    pub fn can_start_game(&self, ecs_world: &mut ECSWorld, project_dir: &Path) -> bool {
+        return self.are_scripts_loaded(ecs_world, project_dir);
     }
 
-    pub fn are_scripts_loaded(&self, ecs_world: &mut ECSWorld) -> bool {
+    pub fn are_scripts_loaded(&self, ecs_world: &mut ECSWorld, project_dir: &Path) -> bool {
         let mut scripted_entities = ecs_world.query::<&ScriptableEntity>();
         for (entity, scriptable) in scripted_entities.into_iter() {
-            for asset_path in &scriptable.scripts {
-                if !self.scripts.contains_key(asset_path) {
+            for game_asset_path in &scriptable.scripts {
+                let asset_path = game_asset_path.as_file_asset_path(project_dir);
+                if !self.scripts.contains_key(&asset_path) {
                     return false;
                 }
             }
@@ -178,3 +904,240 @@ impl Scripts {
         return true;
     }
 }
+
+/// Entities are passed to scripts as their index and generation packed into one integer so a
+/// stale handle can be told apart from whatever reuses its slot.
+fn entity_to_lua(entity: Entity) -> i64 {
+    ((entity.generation() as i64) << 32) | entity.index() as i64
+}
+
+fn entity_from_lua(ecs_world: &ECSWorld, handle: i64) -> mlua::Result<Entity> {
+    let entity = Entity::new(handle as u32, (handle >> 32) as u32);
+    if !ecs_world.contains_entity(entity) {
+        return Err(mlua::Error::runtime(format!(
+            "Entity {} doesn't exist",
+            handle
+        )));
+    }
+    Ok(entity)
+}
+
+fn vector_to_lua(lua: &Lua, vector: &Vector3<f32>) -> mlua::Result<mlua::Table> {
+    let table = lua.create_table()?;
+    table.set("x", vector.x)?;
+    table.set("y", vector.y)?;
+    table.set("z", vector.z)?;
+    Ok(table)
+}
+
+fn vector_from_lua(table: &mlua::Table) -> mlua::Result<Vector3<f32>> {
+    Ok(Vector3::new(
+        table.get("x")?,
+        table.get("y")?,
+        table.get("z")?,
+    ))
+}
+
+fn transform_to_lua(lua: &Lua, transform: &Transform) -> mlua::Result<mlua::Table> {
+    let table = lua.create_table()?;
+    table.set("position", vector_to_lua(lua, &transform.position)?)?;
+    let rotation = lua.create_table()?;
+    rotation.set("x", transform.rotation.i)?;
+    rotation.set("y", transform.rotation.j)?;
+    rotation.set("z", transform.rotation.k)?;
+    rotation.set("w", transform.rotation.w)?;
+    table.set("rotation", rotation)?;
+    table.set("scale", vector_to_lua(lua, &transform.scale)?)?;
+    table.set("forward", vector_to_lua(lua, &transform.forward())?)?;
+    Ok(table)
+}
+
+fn transform_from_lua(table: &mlua::Table, transform: &mut Transform) -> mlua::Result<()> {
+    if let Some(position) = table.get::<Option<mlua::Table>>("position")? {
+        transform.position = vector_from_lua(&position)?;
+    }
+    if let Some(rotation) = table.get::<Option<mlua::Table>>("rotation")? {
+        transform.rotation = UnitQuaternion::from_quaternion(Quaternion::new(
+            rotation.get("w")?,
+            rotation.get("x")?,
+            rotation.get("y")?,
+            rotation.get("z")?,
+        ));
+    }
+    if let Some(scale) = table.get::<Option<mlua::Table>>("scale")? {
+        transform.scale = vector_from_lua(&scale)?;
+    }
+    Ok(())
+}
+
+fn key_from_name(name: &str) -> Option<Key> {
+    let deserializer: serde::de::value::StrDeserializer<'_, serde::de::value::Error> =
+        name.into_deserializer();
+    Key::deserialize(deserializer).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn reloading_script_keeps_table_state() {
+        let scripts = Scripts::new();
+        let path = AssetPath::new(PathBuf::from("door.lua"));
+        scripts
+            .run_script(
+                &path,
+                "door = { opened = 0 }\nfunction door.open() door.opened = door.opened + 1 end",
+            )
+            .unwrap();
+        scripts.lua.load("door.open()").exec().unwrap();
+
+        scripts
+            .run_script(
+                &path,
+                "door = { opened = 0 }\nfunction door.open() door.opened = door.opened + 10 end",
+            )
+            .unwrap();
+        scripts.lua.load("door.open()").exec().unwrap();
+        let door = scripts.lua.globals().get::<mlua::Table>("door").unwrap();
+        assert_eq!(door.get::<i64>("opened").unwrap(), 11);
+
+        // A chunk that fails to compile leaves the running version alone.
+        assert!(scripts.run_script(&path, "door = {").is_err());
+        scripts.lua.load("door.open()").exec().unwrap();
+        assert_eq!(door.get::<i64>("opened").unwrap(), 21);
+    }
+
+    #[test]
+    fn world_functions_reject_stale_entities() {
+        let scripts = Scripts::new();
+        let mut ecs_world = ECSWorld::new();
+        let voxel_registry = VoxelModelRegistry::new();
+        let region_map = RegionMap::new();
+        let main_camera = MainCamera::new_empty();
+        let input = Input::new(crate::input::mapper::Keybinds::default());
+        let with_world = |ecs_world: &mut ECSWorld, source: &str| {
+            let ctx = ScriptWorldContext {
+                ecs_world,
+                voxel_registry: &voxel_registry,
+                region_map: &region_map,
+                main_camera: &main_camera,
+                input: &input,
+            };
+            scripts.with_world(ctx, |lua| lua.load(source).exec())
+        };
+
+        with_world(
+            &mut ecs_world,
+            r#"
+            cube = spawn_entity("cube", { x = 1, y = 2, z = 3 })
+            local transform = get_transform(cube)
+            transform.position.y = transform.position.y + 5
+            set_transform(cube, { position = transform.position })
+            set_voxel_model(cube, "cube::rvox")
+            "#,
+        )
+        .unwrap();
+        let cube = scripts.lua.globals().get::<i64>("cube").unwrap();
+        let cube = entity_from_lua(&ecs_world, cube).unwrap();
+        assert_eq!(
+            ecs_world.get::<&Transform>(cube).unwrap().position,
+            Vector3::new(1.0, 7.0, 3.0)
+        );
+        assert_eq!(
+            ecs_world
+                .get::<&RenderableVoxelEntity>(cube)
+                .unwrap()
+                .model_asset_path(),
+            GameAssetPath::new("cube::rvox", false).as_ref()
+        );
+
+        // The despawned cube's slot is reused by the new entity.
+        ecs_world.despawn(cube, false);
+        ecs_world.spawn((GameEntity::new("other"), Transform::new()));
+        with_world(&mut ecs_world, "assert(not entity_exists(cube))").unwrap();
+        assert!(with_world(&mut ecs_world, "get_transform(cube)").is_err());
+
+        // The world isn't reachable outside of a callback.
+        assert!(
+            scripts
+                .lua
+                .load("spawn_entity('a', { x = 0, y = 0, z = 0 })")
+                .exec()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn subscriptions_are_dispatched_and_cleaned_up() {
+        let mut scripts = Scripts::new();
+        let mut ecs_world = ECSWorld::new();
+        let voxel_registry = VoxelModelRegistry::new();
+        let region_map = RegionMap::new();
+        let main_camera = MainCamera::new_empty();
+        let input = Input::new(crate::input::mapper::Keybinds::default());
+        let dispatch = |scripts: &mut Scripts, ecs_world: &mut ECSWorld| {
+            let ctx = ScriptWorldContext {
+                ecs_world,
+                voxel_registry: &voxel_registry,
+                region_map: &region_map,
+                main_camera: &main_camera,
+                input: &input,
+            };
+            scripts.dispatch(ctx, &[], &[Key::Space]);
+        };
+
+        let owner = ecs_world.spawn((GameEntity::new("owner"), ScriptableEntity::new()));
+        scripts
+            .lua
+            .globals()
+            .set("owner", entity_to_lua(owner))
+            .unwrap();
+        let ctx = ScriptWorldContext {
+            ecs_world: &mut ecs_world,
+            voxel_registry: &voxel_registry,
+            region_map: &region_map,
+            main_camera: &main_camera,
+            input: &input,
+        };
+        scripts
+            .with_world(ctx, |lua| {
+                lua.load(
+                    r#"
+                    spawned = {}
+                    keys = {}
+                    subscribe(owner, "key_pressed", function(key) error("broken callback") end)
+                    subscribe(owner, "entity_spawned", function(e) table.insert(spawned, e) end)
+                    key_id = subscribe(owner, "key_pressed", function(key)
+                        table.insert(keys, key)
+                        unsubscribe(key_id)
+                    end)
+                    assert(not pcall(subscribe, owner, "explode", function() end))
+                    "#,
+                )
+                .exec()
+            })
+            .unwrap();
+        assert_eq!(scripts.subscriptions.get_mut().subscriptions.len(), 3);
+
+        let cube = ecs_world.spawn((GameEntity::new("cube"), Transform::new()));
+        dispatch(&mut scripts, &mut ecs_world);
+        let globals = scripts.lua.globals();
+        assert_eq!(
+            globals.get::<Vec<i64>>("spawned").unwrap(),
+            [entity_to_lua(cube)]
+        );
+        // The erroring callback didn't stop the others from running.
+        assert_eq!(globals.get::<Vec<String>>("keys").unwrap(), ["Space"]);
+        assert!(scripts.script_error().unwrap().contains("broken callback"));
+        assert_eq!(scripts.subscriptions.get_mut().subscriptions.len(), 2);
+
+        // Despawning the owner drops its subscriptions.
+        ecs_world.despawn(owner, false);
+        dispatch(&mut scripts, &mut ecs_world);
+        assert!(scripts.subscriptions.get_mut().subscriptions.is_empty());
+        assert_eq!(globals.get::<Vec<i64>>("spawned").unwrap().len(), 1);
+    }
+}
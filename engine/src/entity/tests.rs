@@ -1,4 +1,11 @@
-use crate::entity::ecs_world::ECSWorld;
+use std::collections::HashSet;
+
+use nalgebra::{Translation3, UnitQuaternion, Vector3};
+
+use crate::entity::ecs_world::{ECSWorld, Entity};
+use crate::entity::{EntityChildren, EntityParent, GlobalTransform};
+use crate::entity::query::{ChangeTick, Changed};
+use crate::physics::transform::Transform;
 
 #[derive(Debug, PartialEq)]
 struct SimpleComponent(f32);
@@ -25,3 +32,132 @@ fn basic_test() {
     };
     assert_eq!(component.0, 5.0);
 }
+
+#[test]
+fn changed_filter_only_yields_mutated_components() {
+    let mut ecs = ECSWorld::new();
+    let entities = (0..100)
+        .map(|_| ecs.spawn((Transform::new(), SimpleComponent(0.0))))
+        .collect::<Vec<_>>();
+
+    // A system that hasn't run yet sees every component as changed.
+    let mut last_tick = ChangeTick::default();
+    let since = std::mem::replace(&mut last_tick, ChangeTick::now());
+    let changed_count = ecs
+        .query::<&Transform>()
+        .filter(Changed::<Transform>::since(since))
+        .into_iter()
+        .count();
+    assert_eq!(changed_count, 100);
+    let other_system_tick = ChangeTick::now();
+
+    ecs.get::<&mut Transform>(entities[42]).unwrap().position.x = 5.0;
+    // Neither reading nor mutating other component types counts.
+    assert_eq!(ecs.query::<&Transform>().into_iter().count(), 100);
+    for (_, component) in ecs.query::<&mut SimpleComponent>().into_iter() {
+        component.0 += 1.0;
+    }
+
+    let since = std::mem::replace(&mut last_tick, ChangeTick::now());
+    let changed = ecs
+        .query::<&Transform>()
+        .filter(Changed::<Transform>::since(since))
+        .into_iter()
+        .map(|(entity, transform)| (entity, transform.position.x))
+        .collect::<Vec<_>>();
+    assert_eq!(changed, vec![(entities[42], 5.0)]);
+
+    // The flags reset for this system once it has seen them, other systems still see them.
+    let since = std::mem::replace(&mut last_tick, ChangeTick::now());
+    let changed_count = ecs
+        .query::<&Transform>()
+        .filter(Changed::<Transform>::since(since))
+        .into_iter()
+        .count();
+    assert_eq!(changed_count, 0);
+    let changed = ecs
+        .query::<()>()
+        .filter(Changed::<Transform>::since(other_system_tick))
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+    assert_eq!(changed, vec![entities[42]]);
+}
+
+#[test]
+fn global_transforms_match_composed_world_transforms() {
+    fn assert_cached(ecs: &ECSWorld, entity: Entity) {
+        let local = ecs.get::<&Transform>(entity).unwrap().clone();
+        let expected = ecs.get_world_transform(entity, &local);
+        let global = ecs.get::<&GlobalTransform>(entity).unwrap();
+        assert!((global.transform().position - expected.position).norm() < 1e-4);
+        assert!(global.transform().rotation.angle_to(&expected.rotation) < 1e-4);
+        assert!((global.transform().scale - expected.scale).norm() < 1e-4);
+    }
+
+    let mut ecs = ECSWorld::new();
+    // Non-uniform scale with rotation doesn't compose into a single parent transform.
+    let mut root_transform = Transform::with_translation(Translation3::new(1.0, 2.0, 3.0));
+    root_transform.rotation = UnitQuaternion::from_euler_angles(0.3, 0.7, -0.2);
+    root_transform.scale = Vector3::new(2.0, 0.5, 1.0);
+    let mut child_transform = Transform::with_translation(Translation3::new(0.0, 1.0, -2.0));
+    child_transform.rotation = UnitQuaternion::from_euler_angles(-0.5, 0.1, 1.2);
+    child_transform.scale = Vector3::new(1.0, 3.0, 0.5);
+    let root = ecs.spawn((root_transform,));
+    let child = ecs.spawn((child_transform,));
+    let grandchild = ecs.spawn((Transform::with_translation(Translation3::new(
+        4.0, 0.0, 1.0,
+    )),));
+    let other_root = ecs.spawn((Transform::with_translation(Translation3::new(
+        -5.0, 0.0, 0.0,
+    )),));
+    ecs.set_parent(child, Some(root), false);
+    ecs.set_parent(grandchild, Some(child), false);
+
+    ecs.update_global_transforms();
+    for entity in [root, child, grandchild, other_root] {
+        assert_cached(&ecs, entity);
+    }
+
+    // Moving the root dirties its whole subtree.
+    ecs.get::<&mut Transform>(root).unwrap().position.y += 10.0;
+    ecs.update_global_transforms();
+    assert_cached(&ecs, grandchild);
+    assert!(
+        ecs.get::<&GlobalTransform>(grandchild)
+            .unwrap()
+            .transform()
+            .position
+            .y
+            > 10.0
+    );
+
+    // Reparenting refreshes the subtree right away rather than waiting for the next update.
+    ecs.set_parent(child, Some(other_root), false);
+    assert_cached(&ecs, child);
+    assert_cached(&ecs, grandchild);
+    ecs.set_parent(child, None, false);
+    assert_cached(&ecs, grandchild);
+}
+
+#[test]
+fn global_transforms_survive_parent_cycles() {
+    let mut ecs = ECSWorld::new();
+    let a = ecs.spawn((Transform::new(),));
+    let b = ecs.spawn((Transform::new(),));
+    // Only possible with broken data since `set_parent` asserts against it.
+    for (child, parent) in [(a, b), (b, a)] {
+        ecs.insert_one(child, EntityParent::new(parent)).unwrap();
+        ecs.insert_one(
+            parent,
+            EntityChildren {
+                children: HashSet::from([child]),
+            },
+        )
+        .unwrap();
+    }
+
+    ecs.update_global_transforms();
+    assert!(ecs.get::<&GlobalTransform>(a).is_ok());
+    assert!(ecs.get::<&GlobalTransform>(b).is_ok());
+}
@@ -5,8 +5,12 @@ use crate::asset::asset::Assets;
 use crate::audio::{Audio, AudioPlayer};
 use crate::debug::debug_renderer::DebugRenderer;
 use crate::entity::ecs_world::ECSWorld;
+use crate::entity::scripting::Scripts;
 use crate::event::Events;
-use crate::graphics::{device::DeviceResource, renderer::Renderer};
+use crate::graphics::{
+    camera::CameraShake, camera_follow::CameraFollow, device::DeviceResource,
+    passes::taa_pass::TaaPass, renderer::Renderer,
+};
 use crate::input::Input;
 use crate::material::material_bank::MaterialBank;
 use crate::material::material_gpu::MaterialBankGpu;
@@ -16,11 +20,12 @@ use crate::voxel::baker_gpu::VoxelBakerGpu;
 use crate::voxel::voxel_registry::VoxelModelRegistry;
 use crate::voxel::voxel_registry_gpu::VoxelModelRegistryGpu;
 use crate::window::time::Time;
-use crate::world::sky::Sky;
+use crate::world::sky::SkyLight;
 use crate::world::terrain::region_map::RegionMap;
 use crate::world::terrain::region_map_gpu::RegionMapGpu;
 use crate::world::world_entities::WorldEntities;
 use crate::world::world_entities_gpu::WorldEntitiesGpu;
+use crate::world::world_origin::WorldOrigin;
 use crate::world::world_streaming::WorldChunkStreamer;
 
 pub fn game_loop(app: &App) {
@@ -28,8 +33,7 @@ pub fn game_loop(app: &App) {
     // DeviceResource::update
 
     // ------- FRAME SETUP ---------
-    // Wait for any previous processing frames based off our timeline semaphores or capped
-    // framerate.
+    // Wait for any previous processing frames based off our timeline semaphores.
     app.run_system(DeviceResource::begin_frame);
     // Update our time info such as delta time.
     app.run_system(Time::update);
@@ -38,12 +42,18 @@ pub fn game_loop(app: &App) {
     // ------- ASSETS --------
     // Run any queued up asset tasks and update finished tasks.
     app.run_system(Assets::update);
+    // Queue any scripts world entities reference and swap in loaded or modified scripts.
+    app.run_system(Scripts::load_world_scripts);
+    app.run_system(Scripts::update_loaded_scripts);
 
     // -------- PHYSICS ----------
+    // Drop joints whose entities were despawned before they are solved.
+    app.run_system(PhysicsWorld::remove_despawned_joints);
     // Do fixed-timestep physics updates for stability.
+    let delta_time = app.get_resource::<Time>().delta_time();
     let physics_updates = app
         .get_resource_mut::<PhysicsWorld>()
-        .physics_update_count();
+        .physics_update_count(delta_time);
     for _ in 0..physics_updates {
         app.run_system(PhysicsWorld::start_time_step);
 
@@ -64,6 +74,11 @@ pub fn game_loop(app: &App) {
     app.run_system(Animator::update_animators_system);
     app.run_system(AnimationBank::update_loaded_animations);
 
+    // ------- SCRIPTS ---------
+    // Call script callbacks for this frame's physics, entity and key events.
+    app.run_system(Scripts::dispatch_events);
+    app.run_system(Scripts::update_scripts);
+
     // ------- APP-DEFINED UPDATE SYSTEMS ------
     if let Some(systems) = app.systems(AppStage::Update) {
         for system in systems {
@@ -88,7 +103,15 @@ pub fn game_loop(app: &App) {
     // ------- SPATIAL WORLD -------
 
     // Updates the day/night cycle of the world.
-    app.run_system(Sky::update_time);
+    app.run_system(SkyLight::update_day_night_cycle);
+    // Move follow cameras toward their targets.
+    app.run_system(CameraFollow::update_camera_follow);
+    // Decay camera shake trauma, the shake itself is applied when writing the camera uniforms.
+    app.run_system(CameraShake::update_camera_shake);
+    // Re-center the floating world origin on the main camera if it strayed too far.
+    app.run_system(WorldOrigin::update_world_origin);
+    // Cache world transforms for rendering now that entities are done moving for the frame.
+    app.run_system(ECSWorld::update_global_transforms_system);
     // Rendered terrain relative to player/camera anchor updating.
     app.run_system(WorldChunkStreamer::update);
 
@@ -98,6 +121,8 @@ pub fn game_loop(app: &App) {
     app.run_system(RegionMap::update_region_loading);
     // Update from chunk commands and submits chunk events.
     app.run_system(RegionMap::update_chunks);
+    // Free the models of any chunks replaced above.
+    app.run_system(RegionMap::release_replaced_chunk_models);
     // Update any terrain edits.
     app.run_system(RegionMap::update_region_edits);
     // Marks regions which should be written based off of region events.
@@ -129,6 +154,8 @@ pub fn game_loop(app: &App) {
 
     // Only continue with frame graph pass writing if we successfully acquired the swapchain since
     // some images may rely on swapchain info.
+    // Recreate the swapchain if the present mode settings changed before acquiring from it.
+    app.run_system(DeviceResource::update_swapchain_info);
     app.run_system(Renderer::acquire_swapchain_image);
     if app.get_resource::<Renderer>().did_acquire_swapchain() {
         app.run_system(Renderer::begin_frame);
@@ -138,6 +165,8 @@ pub fn game_loop(app: &App) {
                 system.run(app.resource_bank());
             }
         }
+        // Jitter the camera before it is written to the frame uniforms.
+        app.run_system(TaaPass::update_jitter);
         app.run_system(Renderer::write_frame_uniforms);
 
         app.run_system(VoxelBakerGpu::write_graph_passes);
@@ -155,4 +184,7 @@ pub fn game_loop(app: &App) {
     // Discard any inputs and events cached for this frame.
     app.run_system(Input::clear_inputs);
     app.run_system(Events::frame_cleanup);
+
+    // Sleep off the rest of the frame if the frame rate is capped.
+    app.run_system(Time::limit_frame_rate);
 }
@@ -7,7 +7,7 @@ use std::{
     mem::MaybeUninit,
     num::NonZeroU32,
     ops::Deref,
-    sync::Arc,
+    sync::{Arc, atomic::AtomicU32},
     u32,
 };
 
@@ -22,6 +22,7 @@ use super::{
     shader::{Shader, ShaderCompiler, ShaderPath, ShaderSetBinding},
 };
 use crate::common::color::{Color, ColorSpaceSrgb};
+use crate::common::freelist::FreeListHandle;
 use crate::event::Events;
 use crate::graphics::shader::{ShaderBinding, ShaderBindingType};
 use crate::window::window::{Window, WindowHandle};
@@ -32,6 +33,96 @@ pub enum GraphicsBackendEvent {
     PipelineShaderUpdate(ResourceId<Untyped>),
 }
 
+/// An error from the device which interrupts rendering.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GfxDeviceError {
+    /// The swapchain no longer matches the surface, usually after a resize, and must be
+    /// recreated.
+    SwapchainOutOfDate,
+    /// The window surface was destroyed out from under us.
+    SurfaceLost,
+    /// The gpu device was lost, which happens on driver updates or crashes and gpu timeouts.
+    DeviceLost,
+}
+
+impl GfxDeviceError {
+    /// Recoverable errors are handled by recreating the swapchain, otherwise the device is
+    /// unusable and the app must shut down.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, GfxDeviceError::SwapchainOutOfDate)
+    }
+}
+
+impl std::fmt::Display for GfxDeviceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GfxDeviceError::SwapchainOutOfDate => f.write_str("The swapchain is out of date"),
+            GfxDeviceError::SurfaceLost => f.write_str("The window surface was lost"),
+            GfxDeviceError::DeviceLost => f.write_str("The graphics device was lost"),
+        }
+    }
+}
+
+impl std::error::Error for GfxDeviceError {}
+
+/// How much gpu memory the device has allocated, to track down memory growth.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GfxMemoryStats {
+    /// The size of every device memory allocation, shared and dedicated.
+    pub allocated_bytes: u64,
+    /// The bytes handed out to resources, allocations in shared pools are rounded up to a
+    /// power of 2.
+    pub used_bytes: u64,
+    pub dedicated_allocation_count: u32,
+    pub dedicated_bytes: u64,
+    /// The device memory chunks smaller allocations are suballocated from.
+    pub shared_pools: Vec<GfxMemoryPoolStats>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GfxMemoryPoolStats {
+    pub size: u64,
+    pub used_bytes: u64,
+    pub active_allocations: u64,
+    /// The largest allocation which would still fit in this pool.
+    pub largest_free_block: u64,
+    pub free_block_count: u64,
+}
+
+impl GfxMemoryPoolStats {
+    /// 0 when all the free space is in one block, approaching 1 the more the free space is split
+    /// into blocks too small to allocate from.
+    pub fn fragmentation(&self) -> f32 {
+        let free_bytes = self.size - self.used_bytes;
+        if free_bytes == 0 {
+            return 0.0;
+        }
+        1.0 - self.largest_free_block as f32 / free_bytes as f32
+    }
+}
+
+/// Counters of the work recorded in a frame, the first thing to check when a frame gets slow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GfxFrameStats {
+    pub dispatches: u32,
+    /// Indirect draws count as one draw.
+    pub draws: u32,
+    pub descriptor_set_binds: u32,
+    /// Descriptor set updates, these happen when a set isn't in the descriptor cache.
+    pub descriptor_set_writes: u32,
+    pub buffer_writes: u32,
+    pub image_writes: u32,
+    /// Bytes written to staging buffers for buffer and image writes.
+    pub staging_bytes: u64,
+}
+
+/// How long a frame graph pass took to execute on the gpu.
+#[derive(Clone, Debug)]
+pub struct GfxPassTiming {
+    pub name: String,
+    pub duration: std::time::Duration,
+}
+
 pub trait GraphicsBackendDevice {
     /// Updates every window redraw request before recieving `GraphicsBackendEvent::Initialized`.
     fn pre_init_update(&mut self, events: &mut Events);
@@ -53,16 +144,31 @@ pub trait GraphicsBackendDevice {
     ) -> ResourceId<RasterPipeline>;
 
     fn create_image(&mut self, create_info: GfxImageCreateInfo) -> ResourceId<Image>;
+    /// Frees the image once the frames in flight have finished with it, the id is invalid
+    /// immediately.
+    fn destroy_image(&mut self, image: ResourceId<Image>);
     fn get_image_info(&self, image: &ResourceId<Image>) -> GfxImageInfo;
     fn write_image(&mut self, write_info: GfxImageWrite);
 
     fn create_buffer(&mut self, create_info: GfxBufferCreateInfo) -> ResourceId<Buffer>;
+    /// Frees the buffer once the frames in flight have finished with it, the id is invalid
+    /// immediately.
+    fn destroy_buffer(&mut self, buffer: ResourceId<Buffer>);
     fn write_buffer(&mut self, buffer: &ResourceId<Buffer>, offset: u64, size: u64) -> &mut [u8];
     fn write_buffer_slice(&mut self, buffer: &ResourceId<Buffer>, offset: u64, data: &[u8]) {
         self.write_buffer(buffer, offset, data.len() as u64)
             .copy_from_slice(data);
     }
+    /// Reserves `size` bytes of staging memory for buffer writes which can be filled from
+    /// another thread, the group must be ended within the same frame for the writes to upload.
+    fn begin_write_group(&mut self, size: u64) -> GfxWriteGroupHandle;
+    fn end_write_group(&mut self, write_group: GfxWriteGroupHandle);
     fn get_buffer_info(&self, buffer: &ResourceId<Buffer>) -> GfxBufferInfo;
+    /// Creates a buffer in cpu visible memory that gpu copies can be read back from.
+    fn create_readback_buffer(&mut self, create_info: GfxBufferCreateInfo) -> ResourceId<Buffer>;
+    /// Reads a buffer created with `create_readback_buffer`, the contents are only valid once
+    /// the frame which wrote to it has finished on the gpu, see `is_frame_finished`.
+    fn read_buffer(&self, buffer: &ResourceId<Buffer>, offset: u64, size: u64) -> &[u8];
 
     fn create_sampler(&mut self, create_info: GfxSamplerCreateInfo) -> ResourceId<Sampler>;
 
@@ -70,6 +176,8 @@ pub trait GraphicsBackendDevice {
 
     fn update_pipelines(&mut self, shader_compiler: &ShaderCompiler) -> anyhow::Result<()>;
 
+    /// Errors with a `GfxDeviceError` when the swapchain is out of date or the device was
+    /// lost.
     fn acquire_swapchain_image(&mut self) -> anyhow::Result<ResourceId<Image>>;
     /// `skip_frame` is true if we are skipping rendering the current cpu, aka. next gpu frame.
     /// This helps the device with synchronization.
@@ -78,8 +186,31 @@ pub trait GraphicsBackendDevice {
         new_size: winit::dpi::PhysicalSize<NonZeroU32>,
         skip_frame: bool,
     );
+    /// Called instead of `resize_swapchain` when the window has no area, such as when it's
+    /// minimized, since a swapchain can't have a zero extent. `skip_frame` is the same as in
+    /// `resize_swapchain`.
+    fn minimize_swapchain(&mut self, skip_frame: bool);
+    /// True from `minimize_swapchain`, or a resize the surface has no area for, until the next
+    /// resize. There is no swapchain image to acquire so nothing should be rendered.
+    fn is_minimized(&self) -> bool;
+    /// Recreates the swapchain if the present mode or buffering changed.
+    fn set_swapchain_info(&mut self, swapchain_info: GfxSwapchainInfo);
 
     fn device_info(&self) -> GfxDeviceInfo;
+
+    /// The frame number the cpu is currently recording.
+    fn curr_cpu_frame(&self) -> u64;
+    /// Returns true if the gpu has finished executing the work recorded on `cpu_frame`.
+    fn is_frame_finished(&self, cpu_frame: u64) -> bool;
+
+    /// The stats of the last fully recorded frame.
+    fn last_frame_stats(&self) -> GfxFrameStats;
+
+    fn memory_stats(&self) -> GfxMemoryStats;
+
+    /// Set once the device hits an unrecoverable error, after which nothing submitted to the
+    /// device will execute.
+    fn fatal_error(&self) -> Option<GfxDeviceError>;
 }
 
 pub trait GraphicsBackendRecorder {
@@ -97,6 +228,8 @@ pub trait GraphicsBackendRecorder {
     }
     // TODO: Support blitting specific image regions.
     fn blit(&mut self, info: GfxBlitInfo);
+    /// Copies an image region into a buffer tightly packed row by row.
+    fn copy_image_to_buffer(&mut self, info: GfxImageToBufferCopyInfo);
     fn begin_compute_pass(&mut self, compute_pipeline: ResourceId<ComputePipeline>) -> ComputePass;
     fn begin_render_pass(
         &mut self,
@@ -138,6 +271,8 @@ pub struct GfxDeviceInfo {
     pub max_allocation_size: u64,
     pub max_storage_buffer_size: u64,
     pub max_storage_buffer_array_binding_count: u64,
+    /// The largest width or height a 2d image can be created with.
+    pub max_image_dimension_2d: u32,
 }
 
 pub struct GfxBlitInfo {
@@ -150,6 +285,14 @@ pub struct GfxBlitInfo {
     pub filter: GfxFilterMode,
 }
 
+pub struct GfxImageToBufferCopyInfo {
+    pub src: ResourceId<Image>,
+    pub src_offset: Vector2<u32>,
+    pub src_length: Vector2<u32>,
+    pub dst: ResourceId<Buffer>,
+    pub dst_offset: u64,
+}
+
 #[derive(Clone, Hash, PartialEq, Eq, Debug)]
 pub struct GfxRenderPassAttachment {
     pub image: ResourceId<Image>,
@@ -204,6 +347,22 @@ pub trait GraphicsBackendFrameGraphExecutor {
     fn write_uniforms(&mut self, write_fn: &mut dyn FnMut(&mut ShaderWriter, &FrameGraphContext));
 
     fn supply_input(&mut self, name: &str, input_data: Box<dyn std::any::Any>);
+
+    /// The gpu duration of each pass in the most recent frame the gpu has finished, which lags
+    /// behind the cpu by the frames in flight. Empty if the device can't write timestamps.
+    fn last_pass_timings(&self) -> &[GfxPassTiming];
+
+    /// Skips the pass from the next frame on, `supply_pass_ref` does nothing for a disabled pass.
+    /// Images the pass writes that no earlier pass has written that frame are cleared so later
+    /// passes never read uninitialized images, the rest pass through untouched.
+    fn set_pass_enabled(&mut self, name: &str, enabled: bool);
+    fn is_pass_enabled(&self, name: &str) -> bool;
+    /// The names of the passes in the most recently begun frame graph, in execution order.
+    fn pass_names(&self) -> &[String];
+
+    /// Errors from the last time shaders were recompiled after changing on disk, pipelines whose
+    /// shaders failed keep using their last working version. Empty if every shader compiled.
+    fn shader_errors(&self) -> &[String];
 }
 
 pub trait GfxPassOnceImpl<'a> {
@@ -307,14 +466,67 @@ impl<T> Clone for ResourceId<T> {
 #[derive(Clone, PartialEq, Eq)]
 pub struct GfxSwapchainInfo {
     pub present_mode: GfxPresentMode,
-    /// May increase input-to-screen latency
+    /// Requests a third swapchain image with fifo and mailbox so the next frame can be rendered
+    /// while one is queued, ignored with immediate since it has nothing to queue. Smooths out
+    /// frame drops with fifo at the cost of up to a frame of latency.
     pub triple_buffering: bool,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+/// How rendered frames are handed to the display, each mode falls back to fifo when the
+/// surface doesn't support it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, strum_macros::VariantArray)]
 pub enum GfxPresentMode {
-    NoVsync,
-    Vsync,
+    /// True vsync, frames queue up and are shown one per vblank. Never tears and only renders
+    /// as many frames as the display shows so it draws the least power, but a full queue adds
+    /// latency.
+    Fifo,
+    /// Each frame replaces the one waiting for the next vblank. Never tears and has less
+    /// latency than fifo, but keeps rendering frames which are never shown so it draws as much
+    /// power as running uncapped. Needs triple buffering to never block.
+    #[serde(alias = "Vsync")]
+    Mailbox,
+    /// Frames are shown as soon as they are done. The lowest latency but tears, and renders
+    /// uncapped unless the frame rate is capped.
+    #[serde(alias = "NoVsync")]
+    Immediate,
+}
+
+/// Whether the graphics api validation layer and debug messenger are enabled. Validation
+/// skews timings so it should be disabled when profiling, even in debug builds.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, strum_macros::VariantArray)]
+pub enum GfxValidation {
+    /// Enabled in debug builds and disabled in release builds.
+    Auto,
+    Enabled,
+    Disabled,
+}
+
+impl GfxValidation {
+    /// Setting this env var to `1` or `0` overrides the validation setting, so validation can
+    /// be toggled without rebuilding or touching settings.
+    pub const OVERRIDE_ENV_VAR: &'static str = "ROGUE_GFX_DEBUG";
+
+    /// Resolves whether validation should be enabled, returning the reason alongside for
+    /// logging.
+    pub fn resolve(self) -> (bool, &'static str) {
+        match std::env::var(Self::OVERRIDE_ENV_VAR).as_deref() {
+            Ok("1") => return (true, "forced by ROGUE_GFX_DEBUG=1"),
+            Ok("0") => return (false, "forced by ROGUE_GFX_DEBUG=0"),
+            Ok(value) => log::warn!(
+                "Ignoring {}={:?}, expected 1 or 0.",
+                Self::OVERRIDE_ENV_VAR,
+                value
+            ),
+            Err(_) => {}
+        }
+
+        match self {
+            GfxValidation::Auto if cfg!(debug_assertions) => (true, "debug build"),
+            GfxValidation::Auto => (false, "release build"),
+            GfxValidation::Enabled => (true, "enabled in settings"),
+            GfxValidation::Disabled => (false, "disabled in settings"),
+        }
+    }
 }
 
 impl ResourceId<Image> {
@@ -1000,11 +1212,123 @@ pub struct GfxComputePipelineCreateInfo<'a> {
     pub shader: &'a Shader,
 }
 
+/// A reserved slice of staging memory which buffer writes are written into without touching the
+/// device, so it can be sent to a worker thread and returned with `end_write_group`. Dropping
+/// the handle without ending it discards its writes.
+#[must_use]
+pub struct GfxWriteGroupHandle {
+    /// The backend's handle of the staging memory the slice is reserved in.
+    staging: FreeListHandle<()>,
+    /// The offset of the slice within the staging memory.
+    staging_offset: u64,
+    ptr: *mut u8,
+    size: u64,
+    curr_offset: u64,
+    writes: Vec<GfxWriteGroupWrite>,
+    /// The backend's count of open write groups, decremented when the handle is dropped.
+    open_write_groups: Arc<AtomicU32>,
+}
+
+// The reserved slice is only accessed through the handle until the group is ended.
+unsafe impl Send for GfxWriteGroupHandle {}
+
+#[derive(Clone, Debug)]
+pub struct GfxWriteGroupWrite {
+    pub buffer: ResourceId<Buffer>,
+    pub dst_offset: u64,
+    /// The offset within the staging memory.
+    pub src_offset: u64,
+    pub size: u64,
+}
+
+impl GfxWriteGroupHandle {
+    /// Only called by backends, `ptr` must be valid for `size` bytes until the group is ended.
+    /// `open_write_groups` should already count this group.
+    pub(crate) fn new(
+        staging: FreeListHandle<()>,
+        staging_offset: u64,
+        ptr: *mut u8,
+        size: u64,
+        open_write_groups: Arc<AtomicU32>,
+    ) -> Self {
+        Self {
+            staging,
+            staging_offset,
+            ptr,
+            size,
+            curr_offset: 0,
+            writes: Vec::new(),
+            open_write_groups,
+        }
+    }
+
+    pub fn staging(&self) -> FreeListHandle<()> {
+        self.staging
+    }
+
+    pub fn writes(&self) -> &[GfxWriteGroupWrite] {
+        &self.writes
+    }
+
+    /// The number of reserved bytes that have been written to.
+    pub fn used_size(&self) -> u64 {
+        self.curr_offset
+    }
+
+    pub fn remaining_size(&self) -> u64 {
+        self.size - self.curr_offset
+    }
+
+    pub fn write_buffer(
+        &mut self,
+        buffer: &ResourceId<Buffer>,
+        offset: u64,
+        write_len: u64,
+    ) -> &mut [u8] {
+        assert!(
+            write_len <= self.remaining_size(),
+            "Write group only has {} bytes remaining but tried to write {} bytes.",
+            self.remaining_size(),
+            write_len
+        );
+        let local_offset = self.curr_offset;
+        self.curr_offset += write_len;
+        self.writes.push(GfxWriteGroupWrite {
+            buffer: *buffer,
+            dst_offset: offset,
+            src_offset: self.staging_offset + local_offset,
+            size: write_len,
+        });
+
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.ptr.byte_add(local_offset as usize),
+                write_len as usize,
+            )
+        }
+    }
+
+    pub fn write_buffer_slice(&mut self, buffer: &ResourceId<Buffer>, offset: u64, data: &[u8]) {
+        self.write_buffer(buffer, offset, data.len() as u64)
+            .copy_from_slice(data);
+    }
+}
+
+impl Drop for GfxWriteGroupHandle {
+    fn drop(&mut self) {
+        self.open_write_groups
+            .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
 pub struct GfxImageWrite<'a> {
     pub image: ResourceId<Image>,
     pub data: &'a [u8],
     pub offset: Vector2<u32>,
     pub extent: Vector2<u32>,
+    /// The array layer written to, for cubemaps this is the face in the order +x, -x, +y, -y,
+    /// +z, -z.
+    pub layer: u32,
 }
 
 #[derive(Clone)]
@@ -1126,12 +1450,35 @@ pub struct GfxBufferCreateInfo {
     pub size: u64,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct GfxSamplerCreateInfo {
     pub mag_filter: GfxFilterMode,
     pub min_filter: GfxFilterMode,
     pub mipmap_filter: GfxFilterMode,
     pub address_mode: GfxAddressMode,
+    /// Clamped to the device's max sampler anisotropy, ignored if anisotropic filtering isn't
+    /// supported.
+    pub max_anisotropy: Option<f32>,
+    /// The (min, max) mip level of detail that can be sampled.
+    pub lod_range: (f32, f32),
+}
+
+impl GfxSamplerCreateInfo {
+    /// A max lod which doesn't clamp the mip level.
+    pub const LOD_CLAMP_NONE: f32 = 1000.0;
+}
+
+impl Default for GfxSamplerCreateInfo {
+    fn default() -> Self {
+        Self {
+            mag_filter: GfxFilterMode::Linear,
+            min_filter: GfxFilterMode::Linear,
+            mipmap_filter: GfxFilterMode::Linear,
+            address_mode: GfxAddressMode::Repeat,
+            max_anisotropy: None,
+            lod_range: (0.0, Self::LOD_CLAMP_NONE),
+        }
+    }
 }
 
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
@@ -1153,9 +1500,19 @@ pub struct GfxImageCreateInfo {
 pub enum GfxImageType {
     D2,
     DepthD2,
+    /// Six square 2d faces stored as array layers, the extent is the size of one face.
     Cube,
 }
 
+impl GfxImageType {
+    pub fn layer_count(&self) -> u32 {
+        match self {
+            GfxImageType::D2 | GfxImageType::DepthD2 => 1,
+            GfxImageType::Cube => 6,
+        }
+    }
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum GfxFilterMode {
     Nearest,
@@ -1177,7 +1534,7 @@ impl GfxImageInfo {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, strum_macros::VariantArray)]
 pub enum GfxImageFormat {
     R16Float,
     Rgba32Float,
@@ -1188,3 +1545,64 @@ pub enum GfxImageFormat {
     D24UnormS8Uint,
     D32Float,
 }
+
+impl GfxImageFormat {
+    pub fn pixel_byte_size(&self) -> u32 {
+        match self {
+            GfxImageFormat::R16Float | GfxImageFormat::D16Unorm => 2,
+            GfxImageFormat::Rgba8Unorm
+            | GfxImageFormat::Bgra8Unorm
+            | GfxImageFormat::Rgba8Srgb
+            | GfxImageFormat::D24UnormS8Uint
+            | GfxImageFormat::D32Float => 4,
+            GfxImageFormat::Rgba32Float => 16,
+        }
+    }
+
+    pub fn is_depth(&self) -> bool {
+        matches!(
+            self,
+            GfxImageFormat::D16Unorm | GfxImageFormat::D24UnormS8Uint | GfxImageFormat::D32Float
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_group_offsets_writes_into_reserved_slice() {
+        let mut staging = vec![0u8; 64];
+        let staging_offset = 16;
+        let open_write_groups = Arc::new(AtomicU32::new(1));
+        let mut write_group = GfxWriteGroupHandle::new(
+            FreeListHandle::new(0, 0),
+            staging_offset,
+            unsafe { staging.as_mut_ptr().byte_add(staging_offset as usize) },
+            32,
+            open_write_groups.clone(),
+        );
+
+        let buffer_a = ResourceId::new(1);
+        let buffer_b = ResourceId::new(2);
+        write_group.write_buffer_slice(&buffer_a, 128, &[1, 2, 3, 4]);
+        write_group.write_buffer_slice(&buffer_b, 0, &[5; 8]);
+        assert_eq!(write_group.used_size(), 12);
+        assert_eq!(write_group.remaining_size(), 20);
+
+        let writes = write_group.writes();
+        assert_eq!(writes.len(), 2);
+        assert_eq!((writes[0].src_offset, writes[0].dst_offset), (16, 128));
+        assert_eq!((writes[1].src_offset, writes[1].dst_offset), (20, 0));
+        assert_eq!(&staging[16..20], &[1, 2, 3, 4]);
+        assert_eq!(&staging[20..28], &[5; 8]);
+
+        // An abandoned group no longer counts as open.
+        drop(write_group);
+        assert_eq!(
+            open_write_groups.load(std::sync::atomic::Ordering::Relaxed),
+            0
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::common::rng::RngStream;
+
+/// Generates a tileable `size`x`size` blue noise texture with the void and cluster method, each
+/// pixel's value is its rank in [0, 1) so every threshold gives evenly spread out pixels. Used
+/// to decorrelate per pixel random offsets so the noise looks finer than white noise.
+pub fn generate_blue_noise(size: u32, seed: u64) -> Vec<f32> {
+    assert!(
+        size > 0,
+        "Blue noise texture size must be greater than zero."
+    );
+    let mut generator = VoidAndCluster::new(size);
+    let pixel_count = generator.pixel_count();
+
+    // Start with a random tenth of the pixels set then relax them until the tightest cluster
+    // is also the largest void.
+    let initial_count = (pixel_count / 10).max(1);
+    let mut rng = RngStream::new(seed);
+    while generator.set_count < initial_count {
+        let pixel = rng.range_i64(0, pixel_count as i64 - 1) as usize;
+        if !generator.is_set[pixel] {
+            generator.set(pixel);
+        }
+    }
+    loop {
+        let cluster = generator.tightest_cluster();
+        generator.unset(cluster);
+        let void = generator.largest_void();
+        generator.set(void);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; pixel_count];
+    let initial_pattern = generator.clone();
+    // Rank the initial pixels by removing the tightest clusters first.
+    for rank in (0..initial_count).rev() {
+        let cluster = generator.tightest_cluster();
+        generator.unset(cluster);
+        ranks[cluster] = rank;
+    }
+    // Then rank the rest by filling in the largest voids.
+    let mut generator = initial_pattern;
+    for rank in initial_count..pixel_count {
+        let void = generator.largest_void();
+        generator.set(void);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| rank as f32 / pixel_count as f32)
+        .collect()
+}
+
+#[derive(Clone)]
+struct VoidAndCluster {
+    size: usize,
+    is_set: Vec<bool>,
+    set_count: usize,
+    /// The gaussian weighted density of set pixels around each pixel, wrapping around the
+    /// edges so the texture tiles.
+    energy: Vec<f32>,
+    /// The gaussian weight of a pixel by its wrapped x and y offset.
+    kernel: Vec<f32>,
+}
+
+impl VoidAndCluster {
+    const SIGMA: f32 = 1.5;
+
+    fn new(size: u32) -> Self {
+        let size = size as usize;
+        let mut kernel = vec![0.0; size * size];
+        for y in 0..size {
+            for x in 0..size {
+                let dx = x.min(size - x) as f32;
+                let dy = y.min(size - y) as f32;
+                kernel[y * size + x] =
+                    (-(dx * dx + dy * dy) / (2.0 * Self::SIGMA * Self::SIGMA)).exp();
+            }
+        }
+        Self {
+            size,
+            is_set: vec![false; size * size],
+            set_count: 0,
+            energy: vec![0.0; size * size],
+            kernel,
+        }
+    }
+
+    fn pixel_count(&self) -> usize {
+        self.size * self.size
+    }
+
+    fn set(&mut self, pixel: usize) {
+        self.is_set[pixel] = true;
+        self.set_count += 1;
+        self.add_energy(pixel, 1.0);
+    }
+
+    fn unset(&mut self, pixel: usize) {
+        self.is_set[pixel] = false;
+        self.set_count -= 1;
+        self.add_energy(pixel, -1.0);
+    }
+
+    fn add_energy(&mut self, pixel: usize, sign: f32) {
+        let (px, py) = (pixel % self.size, pixel / self.size);
+        for y in 0..self.size {
+            let ky = (y + self.size - py) % self.size;
+            for x in 0..self.size {
+                let kx = (x + self.size - px) % self.size;
+                self.energy[y * self.size + x] += sign * self.kernel[ky * self.size + kx];
+            }
+        }
+    }
+
+    /// The set pixel with the most set pixels around it.
+    fn tightest_cluster(&self) -> usize {
+        (0..self.pixel_count())
+            .filter(|pixel| self.is_set[*pixel])
+            .max_by(|a, b| self.energy[*a].total_cmp(&self.energy[*b]))
+            .unwrap()
+    }
+
+    /// The unset pixel with the fewest set pixels around it.
+    fn largest_void(&self) -> usize {
+        (0..self.pixel_count())
+            .filter(|pixel| !self.is_set[*pixel])
+            .min_by(|a, b| self.energy[*a].total_cmp(&self.energy[*b]))
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blue_noise_ranks_are_spread_out() {
+        let size = 16;
+        let noise = generate_blue_noise(size, 7);
+        assert_eq!(noise, generate_blue_noise(size, 7));
+
+        // Every rank appears exactly once.
+        let mut ranks = noise
+            .iter()
+            .map(|x| (x * (size * size) as f32).round() as u32)
+            .collect::<Vec<_>>();
+        ranks.sort();
+        assert_eq!(ranks, (0..size * size).collect::<Vec<_>>());
+
+        // Neighbouring pixels differ more than the 1/3 expected of white noise, and the darkest
+        // tenth of pixels never touch each other.
+        let size = size as usize;
+        let neighbour_diff = (0..size * size)
+            .map(|i| {
+                let right = (i / size) * size + (i % size + 1) % size;
+                (noise[i] - noise[right]).abs()
+            })
+            .sum::<f32>()
+            / (size * size) as f32;
+        assert!(neighbour_diff > 0.37, "{}", neighbour_diff);
+        for i in (0..size * size).filter(|i| noise[*i] < 0.1) {
+            let right = (i / size) * size + (i % size + 1) % size;
+            let down = (i + size) % (size * size);
+            assert!(noise[right] >= 0.1 && noise[down] >= 0.1);
+        }
+    }
+}
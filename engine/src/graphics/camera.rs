@@ -1,13 +1,20 @@
 use log::debug;
-use nalgebra::{Isometry3, Matrix4, Vector2, Vector3};
+use nalgebra::{Isometry3, Matrix4, UnitQuaternion, Vector2, Vector3};
 use rogue_macros::{Resource, game_component};
 
 use super::renderer;
 use crate::common::geometry::ray::Ray;
 use crate::consts;
 use crate::entity::component::GameComponentSerializeContext;
-use crate::entity::{component::GameComponent, ecs_world::Entity};
+use crate::entity::{component::GameComponent, ecs_world::ECSWorld, ecs_world::Entity};
+use crate::event::{EventReader, Events};
+use crate::graphics::camera_follow::CameraFollow;
+use crate::noise::perlin::PerlinNoise;
+use crate::physics::physics_world::CollisionEvent;
 use crate::physics::transform::Transform;
+use crate::resource::{Res, ResMut};
+use crate::settings::Settings;
+use crate::window::time::Time;
 
 #[derive(Resource)]
 pub struct MainCamera {
@@ -48,6 +55,19 @@ pub struct Camera {
     pub fov: f32,
     pub near_plane: f32,
     pub far_plane: f32,
+    /// Half the height of the view in meters when orthographic, None for perspective.
+    #[serde(default)]
+    pub orthographic_half_height: Option<f32>,
+    /// The distance in meters that is in focus with depth of field.
+    #[serde(default = "Camera::default_focus_distance")]
+    pub focus_distance: f32,
+    /// The diameter of the lens in meters with depth of field, larger apertures blur more
+    /// outside the focus distance.
+    #[serde(default = "Camera::default_aperture")]
+    pub aperture: f32,
+    /// How much the camera is shaking in [0, 1], decays over time, see `CameraShake`.
+    #[serde(skip)]
+    trauma: f32,
 }
 
 impl Default for Camera {
@@ -64,10 +84,44 @@ impl Camera {
             fov,
             near_plane: consts::gfx::CAMERA_NEAR_PLANE,
             far_plane: consts::gfx::CAMERA_FAR_PLANE,
+            orthographic_half_height: None,
+            focus_distance: Self::default_focus_distance(),
+            aperture: Self::default_aperture(),
+            trauma: 0.0,
         }
     }
 
+    fn default_focus_distance() -> f32 {
+        10.0
+    }
+
+    fn default_aperture() -> f32 {
+        0.1
+    }
+
+    /// Shakes the camera more, such as from an impact, clamped so trauma stays within [0, 1].
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    pub fn is_orthographic(&self) -> bool {
+        self.orthographic_half_height.is_some()
+    }
+
     pub fn projection_matrix(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        if let Some(half_height) = self.orthographic_half_height {
+            let mut mat = Matrix4::<f32>::identity();
+            mat.m11 = 1.0 / (aspect_ratio * half_height);
+            mat.m22 = 1.0 / half_height;
+            mat.m33 = 1.0 / (self.far_plane - self.near_plane);
+            mat.m34 = -self.near_plane / (self.far_plane - self.near_plane);
+            return mat;
+        }
+
         let mut mat = Matrix4::<f32>::identity();
         mat.m11 = 1.0 / (aspect_ratio);
         mat.m22 = 1.0;
@@ -88,6 +142,14 @@ impl Camera {
 
     pub fn create_ray(&self, transform: &Transform, uv: Vector2<f32>, aspect_ratio: f32) -> Ray {
         let ndc = Vector2::new(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+        if let Some(half_height) = self.orthographic_half_height {
+            // Parallel rays starting across the camera's view plane.
+            let offset = Vector3::new(ndc.x * aspect_ratio, ndc.y, 0.0) * half_height;
+            return Ray::new(
+                transform.position + transform.rotation.transform_vector(&offset),
+                transform.forward(),
+            );
+        }
         let scaled_ndc = Vector2::new(ndc.x * aspect_ratio, ndc.y) * (self.fov / 2.0).tan();
         let dir = Vector3::new(scaled_ndc.x, scaled_ndc.y, 1.0).normalize();
         Ray::new(
@@ -96,7 +158,194 @@ impl Camera {
         )
     }
 
+    /// The height in meters the view spans at the depth of `world_pos`, for sizing things to
+    /// look the same size on screen.
+    pub fn view_height_at(&self, transform: &Transform, world_pos: Vector3<f32>) -> f32 {
+        if let Some(half_height) = self.orthographic_half_height {
+            return half_height * 2.0;
+        }
+        let depth = (world_pos - transform.position).dot(&transform.forward());
+        depth.max(0.0) * (self.fov / 2.0).tan() * 2.0
+    }
+
     pub fn far_plane(&self) -> f32 {
         self.far_plane
     }
+
+    /// The pixels of depth of field blur per unit of defocus in 1/meters, for a view that is
+    /// `viewport_height` pixels tall. Zero for orthographic cameras which have no focus.
+    pub fn coc_scale(&self, viewport_height: f32) -> f32 {
+        if self.is_orthographic() {
+            return 0.0;
+        }
+        let pixels_per_radian = viewport_height / (2.0 * (self.fov / 2.0).tan());
+        self.aperture.max(0.0) * pixels_per_radian
+    }
+
+    /// The signed radius in pixels of the circle of confusion of a point `depth` meters away,
+    /// negative in front of the focus distance and positive behind it.
+    pub fn coc_radius(&self, depth: f32, viewport_height: f32) -> f32 {
+        self.coc_scale(viewport_height) * (1.0 / self.focus_distance - 1.0 / depth.max(0.0001))
+    }
+}
+
+/// Shakes cameras with trauma by offsetting their view with smooth noise, the offset scales
+/// with trauma squared so small hits barely move the camera while big ones are violent.
+#[derive(Resource)]
+pub struct CameraShake {
+    noise: PerlinNoise,
+    /// Seconds the noise has been sampled over.
+    time: f32,
+    collision_event_reader: EventReader<CollisionEvent>,
+}
+
+impl CameraShake {
+    /// How many times a second the noise changes direction.
+    const FREQUENCY: f32 = 15.0;
+    /// Impacts slower than this in meters per second don't shake the camera.
+    const MIN_IMPACT_SPEED: f32 = 4.0;
+    /// The trauma added per meter per second an impact is faster than `MIN_IMPACT_SPEED`.
+    const IMPACT_TRAUMA_PER_SPEED: f32 = 0.05;
+
+    pub fn new() -> Self {
+        Self {
+            noise: PerlinNoise::new(0),
+            time: 0.0,
+            collision_event_reader: EventReader::new(),
+        }
+    }
+
+    /// Adds trauma to the main camera for hard impacts involving it or the entity it follows,
+    /// then decays the trauma of every camera.
+    pub fn update_camera_shake(
+        mut camera_shake: ResMut<CameraShake>,
+        mut ecs_world: ResMut<ECSWorld>,
+        main_camera: Res<MainCamera>,
+        events: Res<Events>,
+        time: Res<Time>,
+        settings: Res<Settings>,
+    ) {
+        let camera_shake = &mut *camera_shake;
+        if let Some(camera_entity) = main_camera.camera() {
+            let followed_entity = ecs_world
+                .get::<&CameraFollow>(camera_entity)
+                .map(|follow| follow.target)
+                .ok();
+            let shaking_entities = [Some(camera_entity), followed_entity];
+            let impact_trauma = camera_shake
+                .collision_event_reader
+                .read(&events)
+                .filter(|event| {
+                    shaking_entities.contains(&Some(event.entity_a))
+                        || shaking_entities.contains(&Some(event.entity_b))
+                })
+                .map(|event| {
+                    (event.impact_speed - Self::MIN_IMPACT_SPEED).max(0.0)
+                        * Self::IMPACT_TRAUMA_PER_SPEED
+                })
+                .sum::<f32>();
+            if impact_trauma > 0.0
+                && let Ok(mut camera) = ecs_world.get::<&mut Camera>(camera_entity)
+            {
+                camera.add_trauma(impact_trauma);
+            }
+        }
+
+        let delta_time = time.delta_time().as_secs_f32();
+        camera_shake.time += delta_time;
+        for (_entity, camera) in ecs_world.query_mut::<&mut Camera>().into_iter() {
+            camera.trauma = (camera.trauma - settings.camera_shake_decay * delta_time).max(0.0);
+        }
+    }
+
+    /// The transform the view is built from, `transform` with the shake of `camera` applied.
+    /// The camera's stored transform is never touched so the shake can't accumulate.
+    pub fn shaken_transform(
+        &self,
+        camera: &Camera,
+        transform: &Transform,
+        settings: &Settings,
+    ) -> Transform {
+        let shake = camera.trauma * camera.trauma;
+        if shake == 0.0 {
+            return transform.clone();
+        }
+
+        // Each axis samples its own row of the noise so they move independently.
+        let t = self.time * Self::FREQUENCY;
+        let sample = |channel: u32| shake * self.noise.noise_2d(t, channel as f32 + 0.5);
+        let max_angle = settings.camera_shake_max_angle;
+        let max_offset = settings.camera_shake_max_offset;
+        let rotation_offset = UnitQuaternion::from_euler_angles(
+            sample(0) * max_angle,
+            sample(1) * max_angle,
+            sample(2) * max_angle,
+        );
+        let position_offset = Vector3::new(sample(3), sample(4), sample(5)) * max_offset;
+
+        let mut shaken_transform = transform.clone();
+        shaken_transform.rotation = transform.rotation * rotation_offset;
+        shaken_transform.position += transform.rotation.transform_vector(&position_offset);
+        shaken_transform
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orthographic_rays_project_back_to_their_pixel() {
+        let camera = Camera {
+            orthographic_half_height: Some(4.0),
+            ..Camera::new(Camera::FOV_90)
+        };
+        let mut transform = Transform::new();
+        transform.position = Vector3::new(1.0, 2.0, -3.0);
+        transform.rotation = UnitQuaternion::from_euler_angles(0.3, -0.8, 0.0);
+        let aspect_ratio = 16.0 / 9.0;
+        let proj_view = camera.projection_matrix(aspect_ratio) * transform.to_view_matrix();
+
+        for uv in [
+            Vector2::new(0.5, 0.5),
+            Vector2::new(0.1, 0.9),
+            Vector2::new(1.0, 0.0),
+        ] {
+            let ray = camera.create_ray(&transform, uv, aspect_ratio);
+            assert!((ray.dir - transform.forward()).norm() < 1e-5);
+            for t in [1.0, 20.0] {
+                let clip = proj_view * (ray.origin + ray.dir * t).push(1.0);
+                let ndc = clip.xyz() / clip.w;
+                let expected_ndc = Vector2::new(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+                assert!((ndc.xy() - expected_ndc).norm() < 1e-4, "{:?}", ndc);
+                let expected_depth =
+                    (t - camera.near_plane) / (camera.far_plane - camera.near_plane);
+                assert!((ndc.z - expected_depth).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn circle_of_confusion_is_signed_around_focus() {
+        let camera = Camera {
+            focus_distance: 5.0,
+            aperture: 0.1,
+            ..Camera::new(Camera::FOV_90)
+        };
+        // A 90 degree fov spans 2 radians of tangent over the viewport height.
+        assert!((camera.coc_scale(720.0) - 0.1 * 360.0).abs() < 1e-3);
+
+        assert!(camera.coc_radius(5.0, 720.0).abs() < 1e-5);
+        assert!(camera.coc_radius(2.0, 720.0) < 0.0);
+        assert!(camera.coc_radius(20.0, 720.0) > 0.0);
+        // Blur grows towards the camera much faster than towards infinity.
+        assert!(camera.coc_radius(1.0, 720.0).abs() > camera.coc_radius(1000.0, 720.0));
+        assert!(camera.coc_radius(1000.0, 720.0) < 0.2 * 36.0);
+
+        let orthographic = Camera {
+            orthographic_half_height: Some(3.0),
+            ..camera
+        };
+        assert_eq!(orthographic.coc_radius(1.0, 720.0), 0.0);
+    }
 }
@@ -0,0 +1,176 @@
+use nalgebra::Vector3;
+
+use crate::{
+    entity::ecs_world::{ECSWorld, Entity},
+    physics::transform::Transform,
+    resource::Res,
+    window::time::Time,
+};
+
+/// Smoothly moves a camera entity's transform toward a target entity, the camera must be a
+/// root entity since its local transform is set directly. Cameras without this component,
+/// such as the editor camera, are left alone.
+pub struct CameraFollow {
+    pub target: Entity,
+    /// Offset from the target in the target's local space.
+    pub offset: Vector3<f32>,
+    /// Roughly the seconds it takes to catch up to the target's position, zero snaps.
+    pub position_smooth_time: f32,
+    /// Roughly the seconds it takes to catch up to the target's rotation, zero snaps.
+    pub rotation_smooth_time: f32,
+    velocity: Vector3<f32>,
+    angular_velocity: f32,
+    should_snap: bool,
+}
+
+impl CameraFollow {
+    pub fn new(target: Entity, offset: Vector3<f32>) -> Self {
+        Self {
+            target,
+            offset,
+            position_smooth_time: 0.15,
+            rotation_smooth_time: 0.1,
+            velocity: Vector3::zeros(),
+            angular_velocity: 0.0,
+            // Start on the target rather than swooping in from wherever the camera spawned.
+            should_snap: true,
+        }
+    }
+
+    pub fn with_smooth_time(
+        mut self,
+        position_smooth_time: f32,
+        rotation_smooth_time: f32,
+    ) -> Self {
+        self.position_smooth_time = position_smooth_time;
+        self.rotation_smooth_time = rotation_smooth_time;
+        self
+    }
+
+    /// Teleports the camera onto the target next update without smoothing, such as on respawn.
+    pub fn snap(&mut self) {
+        self.should_snap = true;
+    }
+
+    /// Moves `transform` toward the camera's place relative to `target_transform`.
+    pub fn follow(&mut self, transform: &mut Transform, target_transform: &Transform, dt: f32) {
+        let target_position =
+            target_transform.position + target_transform.rotation.transform_vector(&self.offset);
+        let target_rotation = target_transform.rotation;
+        if self.should_snap {
+            self.should_snap = false;
+            self.velocity = Vector3::zeros();
+            self.angular_velocity = 0.0;
+            transform.position = target_position;
+            transform.rotation = target_rotation;
+            return;
+        }
+
+        transform.position = smooth_damp(
+            transform.position,
+            target_position,
+            &mut self.velocity,
+            self.position_smooth_time,
+            dt,
+        );
+
+        // Damp the angle left to the target rotation so rotation settles the same way.
+        let angle = transform.rotation.angle_to(&target_rotation);
+        if angle <= f32::EPSILON {
+            transform.rotation = target_rotation;
+            self.angular_velocity = 0.0;
+            return;
+        }
+        let mut angular_velocity = Vector3::new(self.angular_velocity, 0.0, 0.0);
+        let new_angle = smooth_damp(
+            Vector3::new(angle, 0.0, 0.0),
+            Vector3::zeros(),
+            &mut angular_velocity,
+            self.rotation_smooth_time,
+            dt,
+        )
+        .x
+        .max(0.0);
+        self.angular_velocity = angular_velocity.x;
+        transform.rotation = transform
+            .rotation
+            .slerp(&target_rotation, 1.0 - new_angle / angle);
+    }
+
+    pub fn update_camera_follow(ecs_world: Res<ECSWorld>, time: Res<Time>) {
+        let dt = time.delta_time().as_secs_f32();
+
+        // Resolve the targets first since the targets may themselves be followed.
+        let mut target_transforms = Vec::new();
+        for (entity, follow) in ecs_world.query::<&CameraFollow>().into_iter() {
+            let Ok(target_transform) = ecs_world.get::<&Transform>(follow.target) else {
+                continue;
+            };
+            target_transforms.push((
+                entity,
+                ecs_world.get_world_transform(follow.target, &target_transform),
+            ));
+        }
+
+        for (entity, target_transform) in target_transforms {
+            let Some((mut transform, mut follow)) = ecs_world
+                .query_one::<(&mut Transform, &mut CameraFollow)>(entity)
+                .get()
+            else {
+                continue;
+            };
+            follow.follow(&mut transform, &target_transform, dt);
+        }
+    }
+}
+
+/// Critically damped spring toward `target`, settling in roughly `smooth_time` seconds without
+/// overshooting. Uses the polynomial approximation of the exponential from Game Programming
+/// Gems 4.
+fn smooth_damp(
+    current: Vector3<f32>,
+    target: Vector3<f32>,
+    velocity: &mut Vector3<f32>,
+    smooth_time: f32,
+    dt: f32,
+) -> Vector3<f32> {
+    if smooth_time <= 0.0 {
+        *velocity = Vector3::zeros();
+        return target;
+    }
+
+    let omega = 2.0 / smooth_time;
+    let x = omega * dt;
+    let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - target;
+    let temp = (*velocity + change * omega) * dt;
+    *velocity = (*velocity - temp * omega) * decay;
+    target + (change + temp) * decay
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_damp_settles_without_overshoot() {
+        let target = Vector3::new(10.0, -4.0, 2.0);
+        let mut position = Vector3::zeros();
+        let mut velocity = Vector3::zeros();
+        let dt = 1.0 / 60.0;
+        for _ in 0..120 {
+            position = smooth_damp(position, target, &mut velocity, 0.2, dt);
+            // Each axis approaches from its own side and never passes the target.
+            assert!(position.x <= target.x && position.y >= target.y && position.z <= target.z);
+        }
+        assert!((position - target).norm() < 1e-3);
+
+        // Snaps with no smoothing.
+        let mut velocity = Vector3::new(1.0, 1.0, 1.0);
+        assert_eq!(
+            smooth_damp(Vector3::zeros(), target, &mut velocity, 0.0, dt),
+            target
+        );
+        assert_eq!(velocity, Vector3::zeros());
+    }
+}
@@ -3,22 +3,18 @@ use std::{
     num::NonZeroU32,
     ops::{Deref, DerefMut},
     rc::Rc,
-    time::Duration,
 };
 
 use log::{debug, info};
-use nalgebra::ComplexField;
+use nalgebra::{ComplexField, Vector2};
 use rogue_macros::Resource;
 
 use super::{
     backend::{GfxSwapchainInfo, GraphicsBackendDevice},
-    vulkan::device::{VulkanCreateInfo, VulkanDevice},
+    vulkan::device::{VulkanCreateInfo, VulkanDevice, VulkanPresentTarget},
 };
 use crate::settings::{GraphicsSettings, Settings};
-use crate::window::{
-    time::Instant,
-    window::{Window, WindowHandle},
-};
+use crate::window::window::{Window, WindowHandle};
 use crate::{
     event::Events,
     graphics::backend::{Buffer, ResourceId},
@@ -33,7 +29,6 @@ pub type GfxDevice = Box<dyn GraphicsBackendDevice>;
 #[derive(Resource)]
 pub struct DeviceResource {
     backend_device: Option<GfxDevice>,
-    last_frame_time: Option<Instant>,
 }
 
 impl Deref for DeviceResource {
@@ -53,7 +48,6 @@ impl DerefMut for DeviceResource {
 impl DeviceResource {
     pub fn new() -> Self {
         Self {
-            last_frame_time: None,
             backend_device: None,
         }
     }
@@ -80,15 +74,52 @@ impl DeviceResource {
         }
     }
 
+    /// Same as `create_or_reallocate_buffer` but for cpu readable readback buffers.
+    pub fn create_or_reallocate_readback_buffer(
+        &mut self,
+        buffer: &mut Option<ResourceId<Buffer>>,
+        create_info: GfxBufferCreateInfo,
+    ) -> bool {
+        match buffer {
+            Some(prev_buffer) => {
+                let buffer_info = self.get_buffer_info(prev_buffer);
+                if buffer_info.size < create_info.size {
+                    // Destruction is deferred until the frames in flight are done with it.
+                    self.destroy_buffer(*prev_buffer);
+                    *prev_buffer = self.create_readback_buffer(create_info);
+                    return true;
+                }
+                return false;
+            }
+            None => {
+                *buffer = Some(self.create_readback_buffer(create_info));
+                return true;
+            }
+        }
+    }
+
     pub fn init(&mut self, window: &Window, settings: &GraphicsSettings) {
+        self.init_with_target(VulkanPresentTarget::Window(window), settings);
+    }
+
+    /// Initializes without a surface or swapchain, frames are rendered into offscreen images of
+    /// `size` which are acquired in place of swapchain images.
+    pub fn init_headless(&mut self, size: Vector2<u32>, settings: &GraphicsSettings) {
+        self.init_with_target(VulkanPresentTarget::Offscreen(size), settings);
+    }
+
+    fn init_with_target(&mut self, target: VulkanPresentTarget<'_>, settings: &GraphicsSettings) {
         let device = if cfg!(target_arch = "wasm32") {
             unimplemented!("Wasm target not supported yet (if ever).");
         } else {
-            let enable_debug = std::env::var("ROGUE_GFX_DEBUG")
-                .map(|v| v == "1")
-                .unwrap_or(false);
+            let (enable_debug, reason) = settings.validation.resolve();
+            log::info!(
+                "Vulkan validation layer and debug messenger {} ({}).",
+                if enable_debug { "enabled" } else { "disabled" },
+                reason
+            );
             VulkanDevice::init(VulkanCreateInfo {
-                window,
+                target,
                 swapchain_info: GfxSwapchainInfo {
                     present_mode: settings.present_mode,
                     triple_buffering: settings.triple_buffering,
@@ -102,10 +133,19 @@ impl DeviceResource {
 
     pub fn resize_swapchain(&mut self, new_size: winit::dpi::PhysicalSize<u32>, skip_frame: bool) {
         if let Some(device) = self.backend_device.as_mut() {
+            if new_size.width == 0 || new_size.height == 0 {
+                if !device.is_minimized() {
+                    debug!("Window minimized, pausing rendering");
+                    device.minimize_swapchain(skip_frame);
+                }
+                return;
+            }
+
             let old_size = device.swapchain_size();
-            if new_size.width > 0
-                && new_size.height > 0
-                && (old_size.x != new_size.width || old_size.y != new_size.height)
+            // The swapchain may be restored to the size it had before minimizing.
+            if device.is_minimized()
+                || old_size.x != new_size.width
+                || old_size.y != new_size.height
             {
                 debug!(
                     "Resizing swapchain to {}x{}",
@@ -122,32 +162,44 @@ impl DeviceResource {
         }
     }
 
+    /// Recreates the swapchain even if the size is unchanged, such as when it is out of date.
+    pub fn recreate_swapchain(
+        &mut self,
+        new_size: winit::dpi::PhysicalSize<u32>,
+        skip_frame: bool,
+    ) {
+        let Some(device) = self.backend_device.as_mut() else {
+            return;
+        };
+        let (Some(width), Some(height)) = (
+            NonZeroU32::new(new_size.width),
+            NonZeroU32::new(new_size.height),
+        ) else {
+            device.minimize_swapchain(skip_frame);
+            return;
+        };
+        debug!("Recreating swapchain at {}x{}", width, height);
+        device.resize_swapchain(winit::dpi::PhysicalSize { width, height }, skip_frame);
+    }
+
     // Systems
-    pub fn pre_graphics_update(mut device: ResMut<DeviceResource>, mut events: ResMut<Events>) {
+    /// Applies changes to the present mode and buffering settings.
+    pub fn update_swapchain_info(mut device: ResMut<DeviceResource>, settings: Res<Settings>) {
         if let Some(device) = device.backend_device.as_mut() {
-            device.pre_init_update(&mut events);
+            device.set_swapchain_info(GfxSwapchainInfo {
+                present_mode: settings.graphics.present_mode,
+                triple_buffering: settings.graphics.triple_buffering,
+            });
         }
     }
 
-    pub fn begin_frame(
-        mut device: ResMut<DeviceResource>,
-        mut events: ResMut<Events>,
-        settings: Res<Settings>,
-    ) {
-        // TODO: Move this before present.
-        // Cap framerate.
-        if let Some(last_frame_time) = device.last_frame_time {
-            let elapsed_time_us = last_frame_time.elapsed().as_micros();
-            let minimum_wait_time_us =
-                ((1.0 / settings.frame_rate_cap as f64) * 1_000_000.0).floor() as u128;
-            if elapsed_time_us < minimum_wait_time_us {
-                std::thread::sleep(Duration::from_micros(
-                    (minimum_wait_time_us - elapsed_time_us) as u64,
-                ));
-            }
+    pub fn pre_graphics_update(mut device: ResMut<DeviceResource>, mut events: ResMut<Events>) {
+        if let Some(device) = device.backend_device.as_mut() {
+            device.pre_init_update(&mut events);
         }
-        device.last_frame_time = Some(Instant::now());
+    }
 
+    pub fn begin_frame(mut device: ResMut<DeviceResource>, mut events: ResMut<Events>) {
         device
             .backend_device
             .as_mut()
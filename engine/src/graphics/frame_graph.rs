@@ -1,7 +1,7 @@
 use core::panic;
 use std::{
     any::Any,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     ops::Deref,
 };
 
@@ -55,6 +55,9 @@ pub struct FrameGraphPass {
     pub inputs: HashSet<FrameGraphResource<Untyped>>,
     pub outputs: HashSet<FrameGraphResource<Untyped>>,
     pub pass: Option<Box<dyn Fn(&mut dyn GraphicsBackendRecorder, &FrameGraphContext)>>,
+    /// If true, the pass may only access resources declared as an input or output through
+    /// the `FrameGraphContext`, see `FrameGraphPassBuilder`.
+    pub validate_usage: bool,
 }
 
 #[derive(Clone, Hash, PartialEq, Eq, Debug)]
@@ -72,6 +75,8 @@ pub struct FrameGraphBufferInfo {
 pub struct FrameGraphComputePipelineInfo {
     pub shader_path: ShaderPath,
     pub entry_point_fn: String,
+    /// Shader macro defines used to specialize the pipeline, e.g. the workgroup size.
+    pub macro_defines: BTreeMap<String, String>,
 }
 
 pub struct FrameGraphRasterPipelineInfo {
@@ -179,6 +184,7 @@ impl FrameGraphBuilder {
                     .map(|resource| resource.handle_untyped(self))
                     .collect(),
                 pass: None,
+                validate_usage: false,
             },
         );
         self.pass_order.push(resource_handle.as_untyped());
@@ -211,6 +217,7 @@ impl FrameGraphBuilder {
                     .map(|resource| resource.handle_untyped(self))
                     .collect(),
                 pass: Some(Box::new(pass)),
+                validate_usage: false,
             },
         );
         self.pass_order.push(resource_handle.as_untyped());
@@ -218,6 +225,59 @@ impl FrameGraphBuilder {
         resource_handle
     }
 
+    /// Starts building a pass with explicitly declared resource reads and writes. Passes built
+    /// this way validate that the pass only accesses the resources it declared.
+    pub fn pass_builder(&mut self, name: impl ToString) -> FrameGraphPassBuilder<'_> {
+        FrameGraphPassBuilder {
+            fg: self,
+            name: name.to_string(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    fn insert_pass(
+        &mut self,
+        name: String,
+        inputs: HashSet<FrameGraphResource<Untyped>>,
+        outputs: HashSet<FrameGraphResource<Untyped>>,
+        pass: Option<Box<dyn Fn(&mut dyn GraphicsBackendRecorder, &FrameGraphContext)>>,
+        validate_usage: bool,
+    ) -> FrameGraphResource<Pass> {
+        let resource_handle = self.next_id(name.clone());
+        debug!("Creating pass with name `{}`.", name);
+        self.passes.insert(
+            resource_handle.as_untyped(),
+            FrameGraphPass {
+                id: resource_handle,
+                inputs,
+                outputs,
+                pass,
+                validate_usage,
+            },
+        );
+        self.pass_order.push(resource_handle.as_untyped());
+
+        resource_handle
+    }
+
+    fn expect_resource_type<T: 'static>(
+        &self,
+        resource: FrameGraphResource<Untyped>,
+        usage: &str,
+    ) -> FrameGraphResource<T> {
+        let info = &self.resource_infos[resource.id() as usize];
+        assert_eq!(
+            info.type_id,
+            std::any::TypeId::of::<T>(),
+            "Resource `{}` was declared as {} but it is not a {}.",
+            info.name,
+            usage,
+            std::any::type_name::<T>()
+        );
+        resource.as_typed()
+    }
+
     pub fn present_image(&mut self, image: impl IntoFrameGraphResource<Image>) {
         assert!(
             self.swapchain_image.is_none(),
@@ -266,6 +326,7 @@ impl FrameGraphBuilder {
                 create_info.shader_path
             )),
             entry_point_fn: create_info.entry_point_fn.to_owned(),
+            macro_defines: BTreeMap::new(),
         };
         self.compute_pipelines.insert(resource_handle, create_info);
         resource_handle
@@ -353,6 +414,134 @@ impl FrameGraphBuilder {
     }
 }
 
+/// Builds a frame graph pass from declared image/buffer reads and writes, used for passes
+/// contributed from outside the core render graph definitions.
+///
+/// ```ignore
+/// fg.pass_builder("my_pass")
+///     .read_image(backbuffer)
+///     .write_image(backbuffer)
+///     .use_compute_pipeline(pipeline)
+///     .build(move |recorder, ctx| { ... });
+/// ```
+pub struct FrameGraphPassBuilder<'a> {
+    fg: &'a mut FrameGraphBuilder,
+    name: String,
+    inputs: Vec<FrameGraphResource<Untyped>>,
+    outputs: Vec<FrameGraphResource<Untyped>>,
+}
+
+impl<'a> FrameGraphPassBuilder<'a> {
+    pub fn read_image(mut self, image: impl IntoFrameGraphResource<Image>) -> Self {
+        let handle = image.handle(&*self.fg).as_untyped();
+        self.fg
+            .expect_resource_type::<Image>(handle, "an image read");
+        self.inputs.push(handle);
+        self
+    }
+
+    /// Writing an image implicitly reads it as well, since the pass may only partially
+    /// overwrite its contents.
+    pub fn write_image(mut self, image: impl IntoFrameGraphResource<Image>) -> Self {
+        let handle = image.handle(&*self.fg).as_untyped();
+        self.fg
+            .expect_resource_type::<Image>(handle, "an image write");
+        self.inputs.push(handle);
+        self.outputs.push(handle);
+        self
+    }
+
+    pub fn read_buffer(mut self, buffer: impl IntoFrameGraphResource<Buffer>) -> Self {
+        let handle = buffer.handle(&*self.fg).as_untyped();
+        self.fg
+            .expect_resource_type::<Buffer>(handle, "a buffer read");
+        self.inputs.push(handle);
+        self
+    }
+
+    pub fn write_buffer(mut self, buffer: impl IntoFrameGraphResource<Buffer>) -> Self {
+        let handle = buffer.handle(&*self.fg).as_untyped();
+        self.fg
+            .expect_resource_type::<Buffer>(handle, "a buffer write");
+        self.inputs.push(handle);
+        self.outputs.push(handle);
+        self
+    }
+
+    pub fn use_compute_pipeline(
+        mut self,
+        pipeline: impl IntoFrameGraphResource<ComputePipeline>,
+    ) -> Self {
+        let handle = pipeline.handle(&*self.fg).as_untyped();
+        self.fg
+            .expect_resource_type::<ComputePipeline>(handle, "a compute pipeline");
+        self.inputs.push(handle);
+        self
+    }
+
+    pub fn use_raster_pipeline(
+        mut self,
+        pipeline: impl IntoFrameGraphResource<RasterPipeline>,
+    ) -> Self {
+        let handle = pipeline.handle(&*self.fg).as_untyped();
+        self.fg
+            .expect_resource_type::<RasterPipeline>(handle, "a raster pipeline");
+        self.inputs.push(handle);
+        self
+    }
+
+    /// Reads a user supplied input such as a `Vector2<u32>` supplied with `supply_input`.
+    pub fn read_input<T: 'static>(mut self, input: impl IntoFrameGraphResource<T>) -> Self {
+        let handle = input.handle(&*self.fg).as_untyped();
+        self.inputs.push(handle);
+        self
+    }
+
+    /// Orders this pass after `pass` without declaring any shared resources.
+    pub fn after(mut self, pass: impl IntoFrameGraphResource<Pass>) -> Self {
+        let handle = pass.handle(&*self.fg).as_untyped();
+        self.inputs.push(handle);
+        self
+    }
+
+    /// Creates the pass with a recording closure that runs every frame.
+    pub fn build<F>(self, pass: F) -> FrameGraphResource<Pass>
+    where
+        F: Fn(&mut dyn GraphicsBackendRecorder, &FrameGraphContext) + 'static,
+    {
+        let (fg, name, inputs, outputs) = self.finish();
+        fg.insert_pass(name, inputs, outputs, Some(Box::new(pass)), true)
+    }
+
+    /// Creates the pass without a recording closure, the pass must then be supplied every frame
+    /// through `GraphicsBackendFrameGraphExecutor::supply_pass_ref`.
+    pub fn build_ref(self) -> FrameGraphResource<Pass> {
+        let (fg, name, inputs, outputs) = self.finish();
+        fg.insert_pass(name, inputs, outputs, None, true)
+    }
+
+    fn finish(
+        self,
+    ) -> (
+        &'a mut FrameGraphBuilder,
+        String,
+        HashSet<FrameGraphResource<Untyped>>,
+        HashSet<FrameGraphResource<Untyped>>,
+    ) {
+        assert!(
+            !self.outputs.is_empty(),
+            "Pass `{}` doesn't declare any writes so it would never be executed.",
+            self.name
+        );
+        (
+            self.fg,
+            self.name,
+            self.inputs.into_iter().collect(),
+            self.outputs.into_iter().collect(),
+        )
+    }
+}
+
 pub struct FrameGraph {
     pub resource_infos: Vec<FrameGraphResourceInfo>,
     pub resource_name_map: HashMap<String, FrameGraphResourceInfo>,
@@ -446,6 +635,27 @@ impl FrameGraph {
     }
 }
 
+impl FrameGraph {
+    /// Sets a shader macro define for the compute pipeline `pipeline_name`, the pipeline is
+    /// recompiled the next time it is used if the define changed.
+    pub fn set_compute_pipeline_define(
+        &mut self,
+        pipeline_name: &str,
+        define: impl ToString,
+        value: impl ToString,
+    ) {
+        let handle = self
+            .get_handle(pipeline_name, std::any::TypeId::of::<ComputePipeline>())
+            .as_typed::<ComputePipeline>();
+        let info = self
+            .compute_pipelines
+            .get_mut(&handle)
+            .expect("Compute pipeline should have a pipeline info.");
+        info.macro_defines
+            .insert(define.to_string(), value.to_string());
+    }
+}
+
 impl FrameGraphContextImpl for FrameGraph {
     fn get_handle_untyped(&self, name: &str) -> FrameGraphResource<Untyped> {
         let Some(resource_info) = self.resource_name_map.get(name) else {
@@ -610,7 +820,7 @@ impl FrameGraphContextImpl for FrameGraphBuilder {
     ) -> FrameGraphResource<Untyped> {
         let handle = self.get_handle_untyped(name);
         assert_eq!(
-            self.resource_infos[handle.id() as usize].type_id(),
+            self.resource_infos[handle.id() as usize].type_id,
             expected_type
         );
         handle
@@ -626,6 +836,8 @@ pub struct FrameGraphContext<'a> {
     pub frame_graph: &'a FrameGraph,
     pub resource_map: &'a HashMap<FrameGraphResource<Untyped>, FGResourceBackendId>,
     pub supplied_inputs: &'a HashMap<FrameGraphResource<Untyped>, Box<dyn std::any::Any>>,
+    /// The pass currently being recorded, if any.
+    pub pass: Option<&'a FrameGraphPass>,
 }
 
 impl<'a> FrameGraphContextImpl for FrameGraphContext<'a> {
@@ -648,6 +860,7 @@ impl<'a> FrameGraphContext<'a> {
         resource: impl IntoFrameGraphResource<T>,
     ) -> ResourceId<T> {
         let fg_resource = resource.handle(self.frame_graph);
+        self.validate_pass_usage(fg_resource.as_untyped());
         let Some(resource) = self.resource_map.get(&fg_resource.as_untyped()) else {
             panic!(
                 "Frame graph resource `{}` has not been supplied to the executor, or has not been defined as an input to this frame pass.",
@@ -662,6 +875,23 @@ impl<'a> FrameGraphContext<'a> {
         ResourceId::new(resource.resource_id.id())
     }
 
+    fn validate_pass_usage(&self, resource: FrameGraphResource<Untyped>) {
+        let Some(pass) = self.pass else {
+            return;
+        };
+        if !pass.validate_usage {
+            return;
+        }
+
+        if !pass.inputs.contains(&resource) && !pass.outputs.contains(&resource) {
+            panic!(
+                "Pass `{}` accessed resource `{}` which it didn't declare as a read or write.",
+                self.frame_graph.resource_infos[pass.id.id() as usize].name,
+                self.frame_graph.resource_infos[resource.id() as usize].name
+            );
+        }
+    }
+
     pub fn get_image(&self, resource: impl IntoFrameGraphResource<Image>) -> ResourceId<Image> {
         self.get_resource_id(resource)
     }
@@ -689,6 +919,7 @@ impl<'a> FrameGraphContext<'a> {
         resource: impl IntoFrameGraphResource<Vector2<T>>,
     ) -> Vector2<T> {
         let handle = resource.handle(self.frame_graph);
+        self.validate_pass_usage(handle.as_untyped());
         let val = self
             .supplied_inputs
             .get(&handle.as_untyped())
@@ -744,3 +975,98 @@ impl From<FrameGraphRasterBlendInfo<'_>> for GfxRasterPipelineBlendStateCreateIn
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+
+    use super::*;
+
+    /// Example of a pass contributed from outside the core render graph, tinting the
+    /// backbuffer before it is presented.
+    fn add_example_tint_pass(
+        fg: &mut FrameGraphBuilder,
+        backbuffer: FrameGraphResource<Image>,
+    ) -> FrameGraphResource<Pass> {
+        let pipeline = fg.create_compute_pipeline(
+            "example_tint_pipeline",
+            FrameGraphComputeInfo {
+                shader_path: "example_tint",
+                entry_point_fn: "main",
+            },
+        );
+        fg.pass_builder("example_tint_pass")
+            .read_image(backbuffer)
+            .write_image(backbuffer)
+            .use_compute_pipeline(pipeline)
+            .build(move |recorder, ctx| {
+                let backbuffer = ctx.get_image(backbuffer);
+                let pipeline = ctx.get_compute_pipeline(pipeline);
+                let mut compute_pass = recorder.begin_compute_pass(pipeline);
+                compute_pass.bind_uniforms(&mut |writer| {
+                    writer.write_binding("u_shader.backbuffer", backbuffer);
+                });
+                compute_pass.dispatch(1, 1, 1);
+            })
+    }
+
+    #[test]
+    fn custom_pass_declares_resources() {
+        let mut fg = FrameGraphBuilder::new();
+        let backbuffer = fg.create_frame_image(
+            "backbuffer",
+            FrameGraphImageInfo::new_rgba8(Vector2::new(4, 4)),
+        );
+        let pass = add_example_tint_pass(&mut fg, backbuffer);
+        fg.present_image(backbuffer);
+
+        let graph = fg.bake().unwrap();
+        let pass_info = graph
+            .passes
+            .iter()
+            .find(|p| p.id == pass)
+            .expect("Custom pass should be part of the baked graph.");
+        assert!(pass_info.validate_usage);
+        assert!(pass_info.pass.is_some());
+        assert!(pass_info.inputs.contains(&backbuffer.as_untyped()));
+        assert!(pass_info.outputs.contains(&backbuffer.as_untyped()));
+        assert_eq!(pass_info.outputs.len(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn custom_pass_rejects_mismatched_resource_type() {
+        let mut fg = FrameGraphBuilder::new();
+        let buffer = fg.create_frame_buffer("buffer");
+        fg.pass_builder("bad_pass")
+            .read_image(buffer.as_typed::<Image>())
+            .build_ref();
+    }
+
+    #[test]
+    #[should_panic]
+    fn custom_pass_rejects_undeclared_access() {
+        let mut fg = FrameGraphBuilder::new();
+        let declared = fg.create_frame_image(
+            "declared",
+            FrameGraphImageInfo::new_rgba8(Vector2::new(4, 4)),
+        );
+        let undeclared = fg.create_frame_image(
+            "undeclared",
+            FrameGraphImageInfo::new_rgba8(Vector2::new(4, 4)),
+        );
+        fg.pass_builder("pass").write_image(declared).build_ref();
+        fg.present_image(declared);
+        let graph = fg.bake().unwrap();
+
+        let resource_map = HashMap::new();
+        let supplied_inputs = HashMap::new();
+        let ctx = FrameGraphContext {
+            frame_graph: &graph,
+            resource_map: &resource_map,
+            supplied_inputs: &supplied_inputs,
+            pass: graph.passes.first(),
+        };
+        ctx.get_image(undeclared);
+    }
+}
@@ -3,7 +3,7 @@ use log::debug;
 use crate::graphics::{backend::GfxBufferCreateInfo, device::DeviceResource};
 
 use super::{
-    backend::{Buffer, GraphicsBackendDevice, ResourceId},
+    backend::{Buffer, GfxWriteGroupHandle, GraphicsBackendDevice, ResourceId},
     device::GfxDevice,
 };
 
@@ -80,6 +80,34 @@ impl GpuBufferAllocator {
         device.write_buffer_slice(self.buffer(), allocation.range.start, data);
     }
 
+    /// Writes `data` starting `offset` bytes into the allocation.
+    pub fn write_allocation_data_at(
+        &self,
+        device: &mut GfxDevice,
+        allocation: &Allocation,
+        offset: u64,
+        data: &[u8],
+    ) {
+        // Ensure we do not write out of bounds.
+        assert!(offset + data.len() as u64 <= allocation.range.end - allocation.range.start);
+
+        device.write_buffer_slice(self.buffer(), allocation.range.start + offset, data);
+    }
+
+    /// Same as `write_allocation_data_at` but writes into the staging memory of `write_group`.
+    pub fn write_allocation_data_in_group(
+        &self,
+        write_group: &mut GfxWriteGroupHandle,
+        allocation: &Allocation,
+        offset: u64,
+        data: &[u8],
+    ) {
+        // Ensure we do not write out of bounds.
+        assert!(offset + data.len() as u64 <= allocation.range.end - allocation.range.start);
+
+        write_group.write_buffer_slice(self.buffer(), allocation.range.start + offset, data);
+    }
+
     pub fn buffer(&self) -> &ResourceId<Buffer> {
         &self.buffer
     }
@@ -244,6 +272,25 @@ impl AllocatorTree {
         return None;
     }
 
+    /// The size of the largest free block and the number of free blocks. Blocks are never
+    /// merged back together after being split, so only unsplit free nodes count.
+    pub fn free_blocks(&self) -> (u64, u64) {
+        if self.is_allocated {
+            return (0, 0);
+        }
+        if self.left.is_none() && self.right.is_none() {
+            return (self.size, 1);
+        }
+
+        let child_free_blocks = |child: &Option<Box<AllocatorTree>>| match child {
+            Some(child) => child.free_blocks(),
+            None => (self.size >> 1, 1),
+        };
+        let (left_largest, left_count) = child_free_blocks(&self.left);
+        let (right_largest, right_count) = child_free_blocks(&self.right);
+        (left_largest.max(right_largest), left_count + right_count)
+    }
+
     fn make_allocated(&mut self) -> Allocation {
         assert!(!self.is_allocated);
         self.is_allocated = true;
@@ -254,3 +301,24 @@ impl AllocatorTree {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_blocks_track_fragmentation() {
+        let mut tree = AllocatorTree::new_root(64);
+        assert_eq!(tree.free_blocks(), (64, 1));
+
+        let a = tree.allocate(16, 4).unwrap();
+        let b = tree.allocate(16, 4).unwrap();
+        assert_eq!(tree.free_blocks(), (32, 1));
+
+        // Freed blocks stay split so the free space is fragmented.
+        tree.free(&a);
+        assert_eq!(tree.free_blocks(), (32, 2));
+        tree.free(&b);
+        assert_eq!(tree.free_blocks(), (32, 3));
+    }
+}
@@ -1,11 +1,14 @@
 use device::DeviceResource;
 use renderer::Renderer;
 pub mod backend;
+pub mod blue_noise;
 pub mod camera;
+pub mod camera_follow;
 pub mod device;
 pub mod frame_graph;
 pub mod gpu_allocator;
 pub mod renderer;
+pub mod screenshot;
 pub mod shader;
 pub mod vulkan;
 pub mod passes;
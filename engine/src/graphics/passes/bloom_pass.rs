@@ -0,0 +1,311 @@
+use nalgebra::Vector2;
+use rogue_macros::Resource;
+
+use crate::{
+    graphics::{
+        backend::{ComputePipeline, GfxFilterMode, GraphicsBackendRecorder, Image, ResourceId},
+        frame_graph::{
+            FrameGraphBuilder, FrameGraphComputeInfo, FrameGraphContext, FrameGraphImageInfo,
+            FrameGraphResource, IntoFrameGraphResource, IntoFrameGraphResourceUntyped,
+        },
+        renderer::Renderer,
+    },
+    resource::{Res, ResMut},
+    settings::Settings,
+};
+
+struct BloomPassGraphConstants {
+    pass_name: &'static str,
+    compute_pipeline_name: &'static str,
+    compute_pipeline_info: FrameGraphComputeInfo<'static>,
+    image_mip_prefix: &'static str,
+    image_blur_prefix: &'static str,
+    image_output: &'static str,
+}
+
+/// Keep in sync with the stages in bloom.slang.
+#[derive(Clone, Copy)]
+#[repr(u32)]
+enum BloomStage {
+    Prefilter = 0,
+    Downsample = 1,
+    Blur = 2,
+    Upsample = 3,
+    Composite = 4,
+}
+
+struct BloomDispatch {
+    stage: BloomStage,
+    src: ResourceId<Image>,
+    base: ResourceId<Image>,
+    dst: ResourceId<Image>,
+    blur_direction: Vector2<u32>,
+}
+
+/// Bloom for bright parts of the hdr framebuffer. The color above the threshold is downsampled
+/// into a chain of half resolution images which are each blurred, then upsampled back up and
+/// added onto the framebuffer.
+#[derive(Resource)]
+pub struct BloomPass {
+    graph_framebuffer: Option<FrameGraphResource<Image>>,
+    graph_mips: Vec<FrameGraphResource<Image>>,
+    graph_blurs: Vec<FrameGraphResource<Image>>,
+    graph_output: Option<FrameGraphResource<Image>>,
+}
+
+impl BloomPass {
+    const GRAPH: BloomPassGraphConstants = BloomPassGraphConstants {
+        pass_name: "bloom_pass",
+        compute_pipeline_name: "bloom_compute_pipeline",
+        compute_pipeline_info: FrameGraphComputeInfo {
+            shader_path: "bloom",
+            entry_point_fn: "main",
+        },
+        image_mip_prefix: "bloom_mip",
+        image_blur_prefix: "bloom_blur",
+        image_output: "bloom_output",
+    };
+
+    /// The most half resolution images in the chain, the first is half the framebuffer size.
+    pub const MAX_MIP_COUNT: u32 = 6;
+    /// Mips smaller than this on either axis are skipped since blurring them only smears a few
+    /// pixels over the whole screen.
+    pub const MIN_MIP_LENGTH: u32 = 4;
+
+    pub fn new() -> Self {
+        Self {
+            graph_framebuffer: None,
+            graph_mips: Vec::new(),
+            graph_blurs: Vec::new(),
+            graph_output: None,
+        }
+    }
+
+    /// The size of mip `level` for a framebuffer of `framebuffer_size`.
+    pub fn mip_size(framebuffer_size: Vector2<u32>, level: u32) -> Vector2<u32> {
+        framebuffer_size.map(|x| (x >> (level + 1)).max(1))
+    }
+
+    /// The number of mips used for a framebuffer of `framebuffer_size`, zero when the
+    /// framebuffer is too small to bloom at all.
+    pub fn mip_count(framebuffer_size: Vector2<u32>) -> u32 {
+        (0..Self::MAX_MIP_COUNT)
+            .take_while(|level| {
+                Self::mip_size(framebuffer_size, *level).min() >= Self::MIN_MIP_LENGTH
+            })
+            .count() as u32
+    }
+
+    /// Adds the bloom pass compositing onto `framebuffer` of `framebuffer_size`, returns the
+    /// composited image.
+    pub fn set_graph_bloom_pass(
+        &mut self,
+        fg: &mut FrameGraphBuilder,
+        framebuffer: impl IntoFrameGraphResource<Image>,
+        framebuffer_size: impl IntoFrameGraphResource<Vector2<u32>>,
+    ) -> FrameGraphResource<Image> {
+        let compute_pipeline = fg.create_compute_pipeline(
+            Self::GRAPH.compute_pipeline_name,
+            Self::GRAPH.compute_pipeline_info,
+        );
+
+        let framebuffer_handle = framebuffer.handle(fg);
+        let framebuffer_size_handle = framebuffer_size.handle(fg);
+        let mut create_mip_images = |prefix: &str| {
+            (0..Self::MAX_MIP_COUNT)
+                .map(|level| {
+                    fg.create_frame_image_with_ctx(&format!("{}_{}", prefix, level), move |ctx| {
+                        FrameGraphImageInfo::new_rgba32float(Self::mip_size(
+                            ctx.get_vec2(framebuffer_size_handle),
+                            level,
+                        ))
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let mip_handles = create_mip_images(Self::GRAPH.image_mip_prefix);
+        let blur_handles = create_mip_images(Self::GRAPH.image_blur_prefix);
+        let output_handle = fg.create_frame_image_with_ctx(Self::GRAPH.image_output, move |ctx| {
+            FrameGraphImageInfo::new_rgba32float(ctx.get_vec2(framebuffer_size_handle))
+        });
+
+        let mut outputs = mip_handles
+            .iter()
+            .chain(blur_handles.iter())
+            .map(|handle| handle as &dyn IntoFrameGraphResourceUntyped)
+            .collect::<Vec<_>>();
+        outputs.push(&output_handle);
+        fg.create_input_pass(
+            Self::GRAPH.pass_name,
+            &[&framebuffer_handle, &compute_pipeline],
+            &outputs,
+        );
+
+        self.graph_framebuffer = Some(framebuffer_handle);
+        self.graph_mips = mip_handles;
+        self.graph_blurs = blur_handles;
+        self.graph_output = Some(output_handle);
+
+        output_handle
+    }
+
+    pub fn write_graph_bloom_pass(
+        bloom: Res<BloomPass>,
+        mut renderer: ResMut<Renderer>,
+        settings: Res<Settings>,
+    ) {
+        let framebuffer_handle = *bloom.graph_framebuffer.as_ref().expect(
+            "Should not be writing bloom pass without setting it up in the render graph first.",
+        );
+        let output_handle = *bloom.graph_output.as_ref().expect(
+            "Should not be writing bloom pass without setting it up in the render graph first.",
+        );
+
+        let graphics = &settings.graphics;
+        let is_enabled = graphics.bloom;
+        let threshold = graphics.bloom_threshold.max(0.0);
+        let intensity = graphics.bloom_intensity.max(0.0);
+        renderer.frame_graph_executor.supply_pass_ref(
+            Self::GRAPH.pass_name,
+            &mut |recorder: &mut dyn GraphicsBackendRecorder, ctx: &FrameGraphContext<'_>| {
+                let framebuffer = ctx.get_image(framebuffer_handle);
+                let output = ctx.get_image(output_handle);
+                let framebuffer_size = recorder.get_image_info(&framebuffer).resolution_xy();
+                let mip_count = Self::mip_count(framebuffer_size) as usize;
+                if !is_enabled || mip_count == 0 {
+                    recorder.blit_full(framebuffer, output, GfxFilterMode::Nearest);
+                    return;
+                }
+
+                let mips = bloom.graph_mips[..mip_count]
+                    .iter()
+                    .map(|handle| ctx.get_image(*handle))
+                    .collect::<Vec<_>>();
+                let blurs = bloom.graph_blurs[..mip_count]
+                    .iter()
+                    .map(|handle| ctx.get_image(*handle))
+                    .collect::<Vec<_>>();
+                let pipeline = ctx.get_compute_pipeline(Self::GRAPH.compute_pipeline_name);
+                let mut record = |stage, src, base, dst, blur_direction| {
+                    Self::record_dispatch(
+                        recorder,
+                        pipeline,
+                        BloomDispatch {
+                            stage,
+                            src,
+                            base,
+                            dst,
+                            blur_direction,
+                        },
+                        threshold,
+                        intensity,
+                    );
+                };
+
+                record(
+                    BloomStage::Prefilter,
+                    framebuffer,
+                    framebuffer,
+                    mips[0],
+                    Vector2::zeros(),
+                );
+                for level in 1..mip_count {
+                    record(
+                        BloomStage::Downsample,
+                        mips[level - 1],
+                        mips[level - 1],
+                        mips[level],
+                        Vector2::zeros(),
+                    );
+                }
+                // Separable gaussian, the blur images hold the horizontal pass.
+                for level in 0..mip_count {
+                    record(
+                        BloomStage::Blur,
+                        mips[level],
+                        mips[level],
+                        blurs[level],
+                        Vector2::x(),
+                    );
+                    record(
+                        BloomStage::Blur,
+                        blurs[level],
+                        blurs[level],
+                        mips[level],
+                        Vector2::y(),
+                    );
+                }
+                // Accumulate the blurred mips from the smallest up into the blur images.
+                let mut accumulated = mips[mip_count - 1];
+                for level in (0..mip_count - 1).rev() {
+                    record(
+                        BloomStage::Upsample,
+                        accumulated,
+                        mips[level],
+                        blurs[level],
+                        Vector2::zeros(),
+                    );
+                    accumulated = blurs[level];
+                }
+                record(
+                    BloomStage::Composite,
+                    accumulated,
+                    framebuffer,
+                    output,
+                    Vector2::zeros(),
+                );
+            },
+        );
+    }
+
+    fn record_dispatch(
+        recorder: &mut dyn GraphicsBackendRecorder,
+        pipeline: ResourceId<ComputePipeline>,
+        dispatch: BloomDispatch,
+        threshold: f32,
+        intensity: f32,
+    ) {
+        let dst_size = recorder.get_image_info(&dispatch.dst).resolution_xy();
+        let mut compute_pass = recorder.begin_compute_pass(pipeline);
+        let wg_size = compute_pass.workgroup_size();
+        compute_pass.bind_uniforms(&mut |writer| {
+            writer.write_binding("u_shader.src", dispatch.src);
+            writer.write_binding("u_shader.base", dispatch.base);
+            writer.write_binding("u_shader.dst", dispatch.dst);
+            writer.write_uniform::<u32>("u_shader.stage", dispatch.stage as u32);
+            writer.write_uniform::<f32>("u_shader.threshold", threshold);
+            writer.write_uniform::<f32>("u_shader.intensity", intensity);
+            writer
+                .write_uniform::<Vector2<u32>>("u_shader.blur_direction", dispatch.blur_direction);
+        });
+
+        compute_pass.dispatch(
+            (dst_size.x as f32 / wg_size.x as f32).ceil() as u32,
+            (dst_size.y as f32 / wg_size.y as f32).ceil() as u32,
+            1,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mip_chain_shrinks_with_low_resolution() {
+        let full_hd = Vector2::new(1920, 1080);
+        assert_eq!(BloomPass::mip_count(full_hd), BloomPass::MAX_MIP_COUNT);
+        assert_eq!(BloomPass::mip_size(full_hd, 0), Vector2::new(960, 540));
+        assert_eq!(BloomPass::mip_size(full_hd, 5), Vector2::new(30, 16));
+
+        // 64 / 2^4 is the last mip at least 4 pixels long.
+        assert_eq!(BloomPass::mip_count(Vector2::new(64, 2000)), 4);
+        assert_eq!(BloomPass::mip_count(Vector2::new(8, 8)), 1);
+        assert_eq!(BloomPass::mip_count(Vector2::new(7, 8)), 0);
+        assert_eq!(BloomPass::mip_count(Vector2::new(0, 0)), 0);
+        assert_eq!(
+            BloomPass::mip_size(Vector2::new(1, 1), 3),
+            Vector2::new(1, 1)
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use nalgebra::Vector2;
+use rogue_macros::Resource;
+
+use crate::{
+    entity::ecs_world::ECSWorld,
+    graphics::{
+        backend::{ComputePipeline, GfxFilterMode, GraphicsBackendRecorder, Image, ResourceId},
+        camera::{Camera, MainCamera},
+        frame_graph::{
+            FrameGraphBuilder, FrameGraphComputeInfo, FrameGraphContext, FrameGraphImageInfo,
+            FrameGraphResource, IntoFrameGraphResource,
+        },
+        renderer::Renderer,
+    },
+    resource::{Res, ResMut},
+    settings::Settings,
+};
+
+struct DepthOfFieldPassGraphConstants {
+    pass_name: &'static str,
+    compute_pipeline_name: &'static str,
+    compute_pipeline_info: FrameGraphComputeInfo<'static>,
+    image_horizontal_blur: &'static str,
+    image_output: &'static str,
+}
+
+/// Keep in sync with the stages in depth_of_field.slang.
+#[derive(Clone, Copy)]
+#[repr(u32)]
+enum DepthOfFieldStage {
+    Horizontal = 0,
+    Vertical = 1,
+}
+
+/// Depth of field, blurs the framebuffer by the main camera's circle of confusion at each
+/// pixel's depth with a separable gather. Samples behind a pixel only blur over it as much as
+/// the pixel is blurred itself so sharp foregrounds don't get haloed by the background.
+#[derive(Resource)]
+pub struct DepthOfFieldPass {
+    graph_framebuffer: Option<FrameGraphResource<Image>>,
+    graph_framebuffer_depth: Option<FrameGraphResource<Image>>,
+    graph_horizontal_blur: Option<FrameGraphResource<Image>>,
+    graph_output: Option<FrameGraphResource<Image>>,
+}
+
+impl DepthOfFieldPass {
+    const GRAPH: DepthOfFieldPassGraphConstants = DepthOfFieldPassGraphConstants {
+        pass_name: "depth_of_field_pass",
+        compute_pipeline_name: "depth_of_field_compute_pipeline",
+        compute_pipeline_info: FrameGraphComputeInfo {
+            shader_path: "depth_of_field",
+            entry_point_fn: "main",
+        },
+        image_horizontal_blur: "depth_of_field_horizontal_blur",
+        image_output: "depth_of_field_output",
+    };
+
+    pub fn new() -> Self {
+        Self {
+            graph_framebuffer: None,
+            graph_framebuffer_depth: None,
+            graph_horizontal_blur: None,
+            graph_output: None,
+        }
+    }
+
+    /// Adds the depth of field pass blurring `framebuffer` of `framebuffer_size` by
+    /// `framebuffer_depth`, returns the blurred image.
+    pub fn set_graph_depth_of_field_pass(
+        &mut self,
+        fg: &mut FrameGraphBuilder,
+        framebuffer: impl IntoFrameGraphResource<Image>,
+        framebuffer_depth: impl IntoFrameGraphResource<Image>,
+        framebuffer_size: impl IntoFrameGraphResource<Vector2<u32>>,
+    ) -> FrameGraphResource<Image> {
+        let compute_pipeline = fg.create_compute_pipeline(
+            Self::GRAPH.compute_pipeline_name,
+            Self::GRAPH.compute_pipeline_info,
+        );
+
+        let framebuffer_handle = framebuffer.handle(fg);
+        let framebuffer_depth_handle = framebuffer_depth.handle(fg);
+        let framebuffer_size_handle = framebuffer_size.handle(fg);
+        let horizontal_blur_handle = fg
+            .create_frame_image_with_ctx(Self::GRAPH.image_horizontal_blur, move |ctx| {
+                FrameGraphImageInfo::new_rgba32float(ctx.get_vec2(framebuffer_size_handle))
+            });
+        let output_handle = fg.create_frame_image_with_ctx(Self::GRAPH.image_output, move |ctx| {
+            FrameGraphImageInfo::new_rgba32float(ctx.get_vec2(framebuffer_size_handle))
+        });
+        fg.create_input_pass(
+            Self::GRAPH.pass_name,
+            &[
+                &framebuffer_handle,
+                &framebuffer_depth_handle,
+                &compute_pipeline,
+            ],
+            &[&horizontal_blur_handle, &output_handle],
+        );
+
+        self.graph_framebuffer = Some(framebuffer_handle);
+        self.graph_framebuffer_depth = Some(framebuffer_depth_handle);
+        self.graph_horizontal_blur = Some(horizontal_blur_handle);
+        self.graph_output = Some(output_handle);
+
+        output_handle
+    }
+
+    pub fn write_graph_depth_of_field_pass(
+        dof: Res<DepthOfFieldPass>,
+        mut renderer: ResMut<Renderer>,
+        settings: Res<Settings>,
+        main_camera: Res<MainCamera>,
+        ecs_world: Res<ECSWorld>,
+    ) {
+        let framebuffer_handle = *dof.graph_framebuffer.as_ref().expect(
+            "Should not be writing dof pass without setting it up in the render graph first.",
+        );
+        let framebuffer_depth_handle = *dof.graph_framebuffer_depth.as_ref().expect(
+            "Should not be writing dof pass without setting it up in the render graph first.",
+        );
+        let horizontal_blur_handle = *dof.graph_horizontal_blur.as_ref().expect(
+            "Should not be writing dof pass without setting it up in the render graph first.",
+        );
+        let output_handle = *dof.graph_output.as_ref().expect(
+            "Should not be writing dof pass without setting it up in the render graph first.",
+        );
+
+        // Orthographic cameras have no focus so they are never blurred.
+        let camera = main_camera
+            .camera()
+            .filter(|_| settings.graphics.depth_of_field)
+            .and_then(|entity| ecs_world.get::<&Camera>(entity).ok().map(|c| c.clone()))
+            .filter(|camera| !camera.is_orthographic() && camera.aperture > 0.0);
+        renderer.frame_graph_executor.supply_pass_ref(
+            Self::GRAPH.pass_name,
+            &mut |recorder: &mut dyn GraphicsBackendRecorder, ctx: &FrameGraphContext<'_>| {
+                let framebuffer = ctx.get_image(framebuffer_handle);
+                let output = ctx.get_image(output_handle);
+                let Some(camera) = &camera else {
+                    recorder.blit_full(framebuffer, output, GfxFilterMode::Nearest);
+                    return;
+                };
+
+                let framebuffer_size = recorder.get_image_info(&framebuffer).resolution_xy();
+                let framebuffer_depth = ctx.get_image(framebuffer_depth_handle);
+                let horizontal_blur = ctx.get_image(horizontal_blur_handle);
+                let pipeline = ctx.get_compute_pipeline(Self::GRAPH.compute_pipeline_name);
+                let coc_scale = camera.coc_scale(framebuffer_size.y as f32);
+                let focus_distance = camera.focus_distance.max(camera.near_plane());
+                for (stage, src, dst) in [
+                    (DepthOfFieldStage::Horizontal, framebuffer, horizontal_blur),
+                    (DepthOfFieldStage::Vertical, horizontal_blur, output),
+                ] {
+                    Self::record_stage(
+                        recorder,
+                        pipeline,
+                        stage,
+                        [src, framebuffer_depth, dst],
+                        coc_scale,
+                        focus_distance,
+                    );
+                }
+            },
+        );
+    }
+
+    /// Records the blur along one axis from `src` into `dst`.
+    fn record_stage(
+        recorder: &mut dyn GraphicsBackendRecorder,
+        pipeline: ResourceId<ComputePipeline>,
+        stage: DepthOfFieldStage,
+        [src, framebuffer_depth, dst]: [ResourceId<Image>; 3],
+        coc_scale: f32,
+        focus_distance: f32,
+    ) {
+        let dst_size = recorder.get_image_info(&dst).resolution_xy();
+        let mut compute_pass = recorder.begin_compute_pass(pipeline);
+        let wg_size = compute_pass.workgroup_size();
+        compute_pass.bind_uniforms(&mut |writer| {
+            writer.write_binding("u_shader.src", src);
+            writer.write_binding("u_shader.framebuffer_depth", framebuffer_depth);
+            writer.write_binding("u_shader.dst", dst);
+            writer.write_uniform::<u32>("u_shader.stage", stage as u32);
+            writer.write_uniform::<f32>("u_shader.coc_scale", coc_scale);
+            writer.write_uniform::<f32>("u_shader.focus_distance", focus_distance);
+        });
+
+        compute_pass.dispatch(
+            (dst_size.x as f32 / wg_size.x as f32).ceil() as u32,
+            (dst_size.y as f32 / wg_size.y as f32).ceil() as u32,
+            1,
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use nalgebra::Vector2;
+use rogue_macros::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    graphics::{
+        backend::{GfxFilterMode, GraphicsBackendRecorder, Image},
+        frame_graph::{
+            FrameGraphBuilder, FrameGraphComputeInfo, FrameGraphContext, FrameGraphImageInfo,
+            FrameGraphResource, IntoFrameGraphResource,
+        },
+        renderer::{Antialiasing, Renderer},
+    },
+    resource::{Res, ResMut},
+    settings::Settings,
+};
+
+struct FxaaPassGraphConstants {
+    pass_name: &'static str,
+    compute_pipeline_name: &'static str,
+    compute_pipeline_info: FrameGraphComputeInfo<'static>,
+    image_output: &'static str,
+}
+
+/// How thoroughly fxaa searches for the ends of an edge, higher presets catch fainter edges
+/// and smooth long shallow edges better.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, strum_macros::VariantArray)]
+pub enum FxaaQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl FxaaQuality {
+    /// The steps taken along an edge in each direction looking for its end.
+    pub fn search_steps(&self) -> u32 {
+        match self {
+            FxaaQuality::Low => 4,
+            FxaaQuality::Medium => 8,
+            FxaaQuality::High => 12,
+        }
+    }
+
+    /// The luma contrast relative to the brightest neighbour needed to be an edge.
+    pub fn edge_threshold(&self) -> f32 {
+        match self {
+            FxaaQuality::Low => 0.25,
+            FxaaQuality::Medium => 0.166,
+            FxaaQuality::High => 0.125,
+        }
+    }
+
+    /// The luma contrast needed to be an edge in dark areas where the relative threshold is
+    /// tiny.
+    pub fn edge_threshold_min(&self) -> f32 {
+        match self {
+            FxaaQuality::Low => 0.0833,
+            FxaaQuality::Medium => 0.0833,
+            FxaaQuality::High => 0.0625,
+        }
+    }
+
+    /// How much single pixel details are blended with their neighbours.
+    pub fn subpixel_blend(&self) -> f32 {
+        match self {
+            FxaaQuality::Low => 0.5,
+            FxaaQuality::Medium => 0.75,
+            FxaaQuality::High => 0.75,
+        }
+    }
+}
+
+/// Fast approximate anti-aliasing, smooths edges found from the luma contrast of the display
+/// encoded image in a single pass. Cheaper than taa and doesn't ghost, but it can't recover
+/// detail smaller than a pixel.
+#[derive(Resource)]
+pub struct FxaaPass {
+    graph_image: Option<FrameGraphResource<Image>>,
+    graph_region_offset: Option<FrameGraphResource<Vector2<u32>>>,
+    graph_region_size: Option<FrameGraphResource<Vector2<u32>>>,
+    graph_output: Option<FrameGraphResource<Image>>,
+}
+
+impl FxaaPass {
+    const GRAPH: FxaaPassGraphConstants = FxaaPassGraphConstants {
+        pass_name: "fxaa_pass",
+        compute_pipeline_name: "fxaa_compute_pipeline",
+        compute_pipeline_info: FrameGraphComputeInfo {
+            shader_path: "fxaa",
+            entry_point_fn: "main",
+        },
+        image_output: "fxaa_output",
+    };
+
+    pub fn new() -> Self {
+        Self {
+            graph_image: None,
+            graph_region_offset: None,
+            graph_region_size: None,
+            graph_output: None,
+        }
+    }
+
+    /// Adds the fxaa pass smoothing the rect of `region_offset` and `region_size` within the
+    /// srgb encoded `image` of `image_size`, returns the anti-aliased image.
+    pub fn set_graph_fxaa_pass(
+        &mut self,
+        fg: &mut FrameGraphBuilder,
+        image: impl IntoFrameGraphResource<Image>,
+        image_size: impl IntoFrameGraphResource<Vector2<u32>>,
+        region_offset: impl IntoFrameGraphResource<Vector2<u32>>,
+        region_size: impl IntoFrameGraphResource<Vector2<u32>>,
+    ) -> FrameGraphResource<Image> {
+        let compute_pipeline = fg.create_compute_pipeline(
+            Self::GRAPH.compute_pipeline_name,
+            Self::GRAPH.compute_pipeline_info,
+        );
+
+        let image_handle = image.handle(fg);
+        let image_size_handle = image_size.handle(fg);
+        let region_offset_handle = region_offset.handle(fg);
+        let region_size_handle = region_size.handle(fg);
+        let output_handle = fg.create_frame_image_with_ctx(Self::GRAPH.image_output, move |ctx| {
+            FrameGraphImageInfo::new_rgba8(ctx.get_vec2(image_size_handle))
+        });
+        fg.create_input_pass(
+            Self::GRAPH.pass_name,
+            &[&image_handle, &compute_pipeline],
+            &[&output_handle],
+        );
+
+        self.graph_image = Some(image_handle);
+        self.graph_region_offset = Some(region_offset_handle);
+        self.graph_region_size = Some(region_size_handle);
+        self.graph_output = Some(output_handle);
+
+        output_handle
+    }
+
+    pub fn write_graph_fxaa_pass(
+        fxaa: Res<FxaaPass>,
+        mut renderer: ResMut<Renderer>,
+        settings: Res<Settings>,
+    ) {
+        let image_handle = *fxaa.graph_image.as_ref().expect(
+            "Should not be writing fxaa pass without setting it up in the render graph first.",
+        );
+        let region_offset_handle = *fxaa.graph_region_offset.as_ref().expect(
+            "Should not be writing fxaa pass without setting it up in the render graph first.",
+        );
+        let region_size_handle = *fxaa.graph_region_size.as_ref().expect(
+            "Should not be writing fxaa pass without setting it up in the render graph first.",
+        );
+        let output_handle = *fxaa.graph_output.as_ref().expect(
+            "Should not be writing fxaa pass without setting it up in the render graph first.",
+        );
+
+        let is_enabled = settings.graphics.antialiasing == Antialiasing::FXAA;
+        let quality = settings.graphics.fxaa_quality;
+        renderer.frame_graph_executor.supply_pass_ref(
+            Self::GRAPH.pass_name,
+            &mut |recorder: &mut dyn GraphicsBackendRecorder, ctx: &FrameGraphContext<'_>| {
+                let image = ctx.get_image(image_handle);
+                let output = ctx.get_image(output_handle);
+                if !is_enabled {
+                    recorder.blit_full(image, output, GfxFilterMode::Nearest);
+                    return;
+                }
+
+                let image_size = recorder.get_image_info(&image).resolution_xy();
+                let region_offset = ctx.get_vec2(region_offset_handle);
+                let region_size = ctx.get_vec2(region_size_handle);
+                let pipeline = ctx.get_compute_pipeline(Self::GRAPH.compute_pipeline_name);
+                let mut compute_pass = recorder.begin_compute_pass(pipeline);
+                let wg_size = compute_pass.workgroup_size();
+                compute_pass.bind_uniforms(&mut |writer| {
+                    writer.write_binding("u_shader.src", image);
+                    writer.write_binding("u_shader.dst", output);
+                    writer.write_uniform::<Vector2<u32>>("u_shader.region_offset", region_offset);
+                    writer.write_uniform::<Vector2<u32>>("u_shader.region_size", region_size);
+                    writer.write_uniform::<u32>("u_shader.search_steps", quality.search_steps());
+                    writer
+                        .write_uniform::<f32>("u_shader.edge_threshold", quality.edge_threshold());
+                    writer.write_uniform::<f32>(
+                        "u_shader.edge_threshold_min",
+                        quality.edge_threshold_min(),
+                    );
+                    writer
+                        .write_uniform::<f32>("u_shader.subpixel_blend", quality.subpixel_blend());
+                });
+
+                compute_pass.dispatch(
+                    (image_size.x as f32 / wg_size.x as f32).ceil() as u32,
+                    (image_size.y as f32 / wg_size.y as f32).ceil() as u32,
+                    1,
+                );
+            },
+        );
+    }
+}
@@ -1 +1,5 @@
-pub mod post_process_pass;
\ No newline at end of file
+pub mod bloom_pass;
+pub mod depth_of_field_pass;
+pub mod fxaa_pass;
+pub mod post_process_pass;
+pub mod taa_pass;
@@ -0,0 +1,288 @@
+use nalgebra::{Matrix4, Vector2};
+use rogue_macros::Resource;
+
+use crate::{
+    event::{EventReader, Events},
+    graphics::{
+        backend::{
+            GfxFilterMode, GfxImageCreateInfo, GfxImageFormat, GfxImageType,
+            GraphicsBackendRecorder, Image, ResourceId,
+        },
+        device::DeviceResource,
+        frame_graph::{
+            FrameGraphBuilder, FrameGraphComputeInfo, FrameGraphContext, FrameGraphResource,
+            IntoFrameGraphResource,
+        },
+        renderer::{Antialiasing, Renderer},
+    },
+    resource::{Res, ResMut},
+    settings::Settings,
+    world::world_origin::WorldOriginShiftEvent,
+};
+
+struct TaaPassGraphConstants {
+    pass_name: &'static str,
+    compute_pipeline_name: &'static str,
+    compute_pipeline_info: FrameGraphComputeInfo<'static>,
+    image_history: &'static str,
+    image_history_out: &'static str,
+}
+
+/// Temporal anti-aliasing, the camera is jittered within the pixel every frame and the frames
+/// are accumulated into a history image by reprojecting the last frame with the camera's
+/// motion.
+#[derive(Resource)]
+pub struct TaaPass {
+    is_enabled: bool,
+    jitter_index: u32,
+    /// Swapped each frame, last frame's resolve is read from one while this frame's is written
+    /// to the other.
+    history_images: Option<[ResourceId<Image>; 2]>,
+    history_size: Vector2<u32>,
+    history_read_index: usize,
+    /// False when the history doesn't hold last frame's resolve, such as after a resize or
+    /// toggling taa on.
+    history_valid: bool,
+    prev_proj_view: Matrix4<f32>,
+    origin_shift_reader: EventReader<WorldOriginShiftEvent>,
+
+    graph_framebuffer: Option<FrameGraphResource<Image>>,
+    graph_framebuffer_depth: Option<FrameGraphResource<Image>>,
+    graph_history: Option<FrameGraphResource<Image>>,
+    graph_history_out: Option<FrameGraphResource<Image>>,
+}
+
+impl TaaPass {
+    const GRAPH: TaaPassGraphConstants = TaaPassGraphConstants {
+        pass_name: "taa_pass",
+        compute_pipeline_name: "taa_compute_pipeline",
+        compute_pipeline_info: FrameGraphComputeInfo {
+            shader_path: "taa",
+            entry_point_fn: "main",
+        },
+        image_history: "taa_history",
+        image_history_out: "taa_history_out",
+    };
+
+    /// The number of jitter offsets cycled through, each pixel converges to the average of
+    /// this many samples.
+    pub const JITTER_SEQUENCE_LENGTH: u32 = 8;
+
+    pub fn new() -> Self {
+        Self {
+            is_enabled: false,
+            jitter_index: 0,
+            history_images: None,
+            history_size: Vector2::zeros(),
+            history_read_index: 0,
+            history_valid: false,
+            prev_proj_view: Matrix4::identity(),
+            origin_shift_reader: EventReader::new(),
+            graph_framebuffer: None,
+            graph_framebuffer_depth: None,
+            graph_history: None,
+            graph_history_out: None,
+        }
+    }
+
+    /// The jitter offset in pixels for `index` in the halton (2, 3) sequence, within
+    /// [-0.5, 0.5) on each axis.
+    pub fn jitter(index: u32) -> Vector2<f32> {
+        // The sequence starts at 1 since index 0 is the origin for every base.
+        let index = index % Self::JITTER_SEQUENCE_LENGTH + 1;
+        Vector2::new(halton(index, 2), halton(index, 3)) - Vector2::new(0.5, 0.5)
+    }
+
+    /// Adds the taa pass resolving `framebuffer` with the history, returns the resolved image.
+    pub fn set_graph_taa_pass(
+        &mut self,
+        fg: &mut FrameGraphBuilder,
+        framebuffer: impl IntoFrameGraphResource<Image>,
+        framebuffer_depth: impl IntoFrameGraphResource<Image>,
+    ) -> FrameGraphResource<Image> {
+        let compute_pipeline = fg.create_compute_pipeline(
+            Self::GRAPH.compute_pipeline_name,
+            Self::GRAPH.compute_pipeline_info,
+        );
+
+        let framebuffer_handle = framebuffer.handle(fg);
+        let framebuffer_depth_handle = framebuffer_depth.handle(fg);
+        let history_handle = fg.create_input_image(Self::GRAPH.image_history);
+        let history_out_handle = fg.create_input_image(Self::GRAPH.image_history_out);
+        fg.create_input_pass(
+            Self::GRAPH.pass_name,
+            &[
+                &framebuffer_handle,
+                &framebuffer_depth_handle,
+                &history_handle,
+                &compute_pipeline,
+            ],
+            &[&history_out_handle],
+        );
+
+        self.graph_framebuffer = Some(framebuffer_handle);
+        self.graph_framebuffer_depth = Some(framebuffer_depth_handle);
+        self.graph_history = Some(history_handle);
+        self.graph_history_out = Some(history_out_handle);
+
+        history_out_handle
+    }
+
+    /// Jitters the camera for this frame, must run before the frame uniforms are written.
+    pub fn update_jitter(
+        mut taa: ResMut<TaaPass>,
+        mut renderer: ResMut<Renderer>,
+        settings: Res<Settings>,
+        events: Res<Events>,
+    ) {
+        let taa = &mut *taa;
+        let is_enabled = settings.graphics.antialiasing == Antialiasing::TAA;
+        if is_enabled != taa.is_enabled {
+            taa.is_enabled = is_enabled;
+            taa.history_valid = false;
+        }
+        // Entity positions jump when the origin shifts so the reprojection would be wrong.
+        if taa.origin_shift_reader.read(&events).count() > 0 {
+            taa.history_valid = false;
+        }
+
+        if !taa.is_enabled {
+            renderer.set_camera_jitter(Vector2::zeros());
+            return;
+        }
+        taa.jitter_index = (taa.jitter_index + 1) % Self::JITTER_SEQUENCE_LENGTH;
+        renderer.set_camera_jitter(Self::jitter(taa.jitter_index));
+    }
+
+    pub fn write_graph_taa_pass(
+        mut taa: ResMut<TaaPass>,
+        mut renderer: ResMut<Renderer>,
+        mut device: ResMut<DeviceResource>,
+        settings: Res<Settings>,
+    ) {
+        let taa = &mut *taa;
+        let framebuffer_handle = *taa.graph_framebuffer.as_ref().expect(
+            "Should not be writing taa pass without setting it up in the render graph first.",
+        );
+        let framebuffer_depth_handle = *taa.graph_framebuffer_depth.as_ref().expect(
+            "Should not be writing taa pass without setting it up in the render graph first.",
+        );
+        let history_handle = *taa.graph_history.as_ref().expect(
+            "Should not be writing taa pass without setting it up in the render graph first.",
+        );
+        let history_out_handle = *taa.graph_history_out.as_ref().expect(
+            "Should not be writing taa pass without setting it up in the render graph first.",
+        );
+
+        let backbuffer_size = renderer.backbuffer_size();
+        if taa.history_images.is_none() || taa.history_size != backbuffer_size {
+            for old_history in taa.history_images.into_iter().flatten() {
+                device.destroy_image(old_history);
+            }
+            let mut create_history_image = |i: u32| {
+                device.create_image(GfxImageCreateInfo {
+                    name: format!("taa_history_{}", i),
+                    image_type: GfxImageType::D2,
+                    format: GfxImageFormat::Rgba32Float,
+                    extent: backbuffer_size,
+                })
+            };
+            taa.history_images = Some([create_history_image(0), create_history_image(1)]);
+            taa.history_size = backbuffer_size;
+            taa.history_valid = false;
+        }
+        let history_images = taa.history_images.unwrap();
+        let history = history_images[taa.history_read_index];
+        let history_out = history_images[1 - taa.history_read_index];
+        renderer
+            .frame_graph_executor
+            .supply_image_ref(Self::GRAPH.image_history, &history);
+        renderer
+            .frame_graph_executor
+            .supply_image_ref(Self::GRAPH.image_history_out, &history_out);
+
+        let is_enabled = taa.is_enabled;
+        let history_valid = taa.history_valid;
+        let prev_proj_view = taa.prev_proj_view;
+        let feedback = settings.graphics.taa_feedback.clamp(0.0, 1.0);
+        renderer.frame_graph_executor.supply_pass_ref(
+            Self::GRAPH.pass_name,
+            &mut |recorder: &mut dyn GraphicsBackendRecorder, ctx: &FrameGraphContext<'_>| {
+                let framebuffer = ctx.get_image(framebuffer_handle);
+                let history_out = ctx.get_image(history_out_handle);
+                if !is_enabled {
+                    recorder.blit_full(framebuffer, history_out, GfxFilterMode::Nearest);
+                    return;
+                }
+
+                let framebuffer_size = recorder.get_image_info(&framebuffer).resolution_xy();
+                let framebuffer_depth = ctx.get_image(framebuffer_depth_handle);
+                let history = ctx.get_image(history_handle);
+
+                let pipeline = ctx.get_compute_pipeline(Self::GRAPH.compute_pipeline_name);
+                let mut compute_pass = recorder.begin_compute_pass(pipeline);
+                let wg_size = compute_pass.workgroup_size();
+                compute_pass.bind_uniforms(&mut |writer| {
+                    writer.use_set_cache("u_frame", Renderer::SET_CACHE_SLOT_FRAME);
+                    writer.write_binding("u_shader.framebuffer", framebuffer);
+                    writer.write_binding("u_shader.framebuffer_depth", framebuffer_depth);
+                    writer.write_binding("u_shader.history", history);
+                    writer.write_binding("u_shader.history_out", history_out);
+                    writer.write_uniform_mat4("u_shader.prev_proj_view", &prev_proj_view);
+                    writer.write_uniform::<f32>("u_shader.feedback", feedback);
+                    writer.write_uniform::<u32>("u_shader.history_valid", history_valid as u32);
+                });
+
+                compute_pass.dispatch(
+                    (framebuffer_size.x as f32 / wg_size.x as f32).ceil() as u32,
+                    (framebuffer_size.y as f32 / wg_size.y as f32).ceil() as u32,
+                    1,
+                );
+            },
+        );
+
+        taa.history_read_index = 1 - taa.history_read_index;
+        taa.history_valid = is_enabled;
+        taa.prev_proj_view = *renderer.camera_proj_view();
+    }
+}
+
+/// The radical inverse of `index` in `base`.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_sequence_is_centered_within_pixel() {
+        assert_eq!(halton(1, 2), 0.5);
+        assert_eq!(halton(3, 2), 0.75);
+        assert!((halton(2, 3) - 2.0 / 3.0).abs() < 1e-6);
+
+        let jitters = (0..TaaPass::JITTER_SEQUENCE_LENGTH)
+            .map(TaaPass::jitter)
+            .collect::<Vec<_>>();
+        for (i, jitter) in jitters.iter().enumerate() {
+            assert!(jitter.iter().all(|x| (-0.5..0.5).contains(x)));
+            assert!(jitters[..i].iter().all(|other| other != jitter));
+        }
+        // Wraps around so the pattern repeats.
+        assert_eq!(
+            TaaPass::jitter(TaaPass::JITTER_SEQUENCE_LENGTH),
+            TaaPass::jitter(0)
+        );
+
+        let mean = jitters.iter().sum::<Vector2<f32>>() / jitters.len() as f32;
+        assert!(mean.norm() < 0.1);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, f32};
+use std::{collections::HashMap, f32, path::PathBuf};
 
 use log::{debug, warn};
 use nalgebra::{Matrix3, Matrix4, UnitQuaternion, Vector2, Vector3};
@@ -7,13 +7,13 @@ use serde::{Deserialize, Serialize};
 
 use super::{
     backend::{
-        Binding, GfxBlendFactor, GfxBlendOp, GfxCullMode, GfxFilterMode, GfxFrontFace,
-        GfxImageFormat, GfxRasterPipelineBlendStateAttachmentInfo,
+        Binding, GfxBlendFactor, GfxBlendOp, GfxCullMode, GfxDeviceError, GfxFilterMode,
+        GfxFrontFace, GfxImageFormat, GfxPassTiming, GfxRasterPipelineBlendStateAttachmentInfo,
         GfxRasterPipelineBlendStateCreateInfo, GfxVertexAttribute, GfxVertexAttributeFormat,
         GfxVertexFormat, GraphicsBackendFrameGraphExecutor, GraphicsBackendRecorder, Image,
         ShaderWriter,
     },
-    camera::MainCamera,
+    camera::{CameraShake, MainCamera},
     device::DeviceResource,
     frame_graph::{
         FrameGraph, FrameGraphComputeInfo, FrameGraphContext, FrameGraphContextImpl,
@@ -27,7 +27,7 @@ use crate::{
     debug::debug_renderer::DebugRenderer,
     graphics::backend::ResourceId,
     settings::{GraphicsSettings, Settings},
-    world::sky::Sky,
+    world::{sky::SkyLight, world_origin::WorldOrigin},
 };
 use crate::{
     entity::{self, ecs_world::ECSWorld},
@@ -46,10 +46,24 @@ use crate::{
 use crate::world::terrain::region_map_gpu::RegionMapGpu;
 use crate::world::world_entities_gpu::WorldEntitiesGpu;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Debug,
+    Hash,
+    Ord,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+    strum_macros::VariantArray,
+)]
 pub enum Antialiasing {
     None,
     TAA,
+    /// A single post process pass on the display encoded image, see `FxaaPass`.
+    FXAA,
 }
 
 #[derive(Resource)]
@@ -59,6 +73,16 @@ pub struct Renderer {
     backbuffer_size_input: Option<FrameGraphResource<Vector2<u32>>>,
     swapchain_size: Vector2<u32>,
     swapchain_image: Option<ResourceId<Image>>,
+    /// Compute pipeline defines (pipeline name, define, value) applied to the frame graph at the
+    /// start of the next frame.
+    pending_compute_pipeline_defines: Vec<(String, String, String)>,
+    /// Screenshot paths requested since the last frame, handed off to `Screenshots`.
+    pending_screenshots: Vec<PathBuf>,
+    /// Sub-pixel offset in pixels applied to the camera rays and projection.
+    camera_jitter: Vector2<f32>,
+    backbuffer_size: Vector2<u32>,
+    /// The main camera's projection view matrix this frame without the jitter applied.
+    camera_proj_view: Matrix4<f32>,
 }
 
 pub struct GraphConstants {
@@ -83,9 +107,57 @@ impl Renderer {
             backbuffer_size_input: None,
             swapchain_size: Vector2::zeros(),
             swapchain_image: None,
+            pending_compute_pipeline_defines: Vec::new(),
+            pending_screenshots: Vec::new(),
+            camera_jitter: Vector2::zeros(),
+            backbuffer_size: Vector2::zeros(),
+            camera_proj_view: Matrix4::identity(),
         }
     }
 
+    /// Specializes a compute pipeline in the frame graph with a shader macro define, taking
+    /// effect next frame.
+    pub fn set_compute_pipeline_define(
+        &mut self,
+        pipeline_name: &str,
+        define: impl ToString,
+        value: impl ToString,
+    ) {
+        self.pending_compute_pipeline_defines.push((
+            pipeline_name.to_owned(),
+            define.to_string(),
+            value.to_string(),
+        ));
+    }
+
+    /// Captures the full composited frame to a png at `path`. The capture starts next frame
+    /// and is written once the gpu has finished that frame.
+    pub fn request_screenshot(&mut self, path: PathBuf) {
+        self.pending_screenshots.push(path);
+    }
+
+    pub fn take_screenshot_requests(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.pending_screenshots)
+    }
+
+    /// Offsets the camera by `jitter` pixels when the frame uniforms are next written, used by
+    /// temporal anti-aliasing to sample different points within each pixel every frame.
+    pub fn set_camera_jitter(&mut self, jitter: Vector2<f32>) {
+        self.camera_jitter = jitter;
+    }
+
+    /// The size of the backbuffer the world is rendered to, valid once the frame uniforms have
+    /// been written this frame.
+    pub fn backbuffer_size(&self) -> Vector2<u32> {
+        self.backbuffer_size
+    }
+
+    /// The main camera's unjittered projection view matrix, valid once the frame uniforms have
+    /// been written this frame.
+    pub fn camera_proj_view(&self) -> &Matrix4<f32> {
+        &self.camera_proj_view
+    }
+
     /// Backbuffer is used for setting up the world cameras projection.
     pub fn set_frame_graph(
         &mut self,
@@ -101,6 +173,33 @@ impl Renderer {
         &mut *self.frame_graph_executor
     }
 
+    /// The gpu durations of each frame graph pass, see
+    /// `GraphicsBackendFrameGraphExecutor::last_pass_timings`.
+    pub fn last_pass_timings(&self) -> &[GfxPassTiming] {
+        self.frame_graph_executor.last_pass_timings()
+    }
+
+    /// Skips a frame graph pass from the next frame on, useful to isolate what each pass costs.
+    /// See `GraphicsBackendFrameGraphExecutor::set_pass_enabled`.
+    pub fn set_pass_enabled(&mut self, pass_name: &str, enabled: bool) {
+        self.frame_graph_executor
+            .set_pass_enabled(pass_name, enabled);
+    }
+
+    pub fn is_pass_enabled(&self, pass_name: &str) -> bool {
+        self.frame_graph_executor.is_pass_enabled(pass_name)
+    }
+
+    /// The frame graph's passes in execution order.
+    pub fn pass_names(&self) -> &[String] {
+        self.frame_graph_executor.pass_names()
+    }
+
+    /// See `GraphicsBackendFrameGraphExecutor::shader_errors`.
+    pub fn shader_errors(&self) -> &[String] {
+        self.frame_graph_executor.shader_errors()
+    }
+
     pub fn begin_frame(
         mut renderer: ResMut<Renderer>,
         device: ResMut<DeviceResource>,
@@ -108,10 +207,13 @@ impl Renderer {
     ) {
         let renderer: &mut Renderer = &mut renderer;
 
-        let Some(frame_graph) = renderer.frame_graph.take() else {
+        let Some(mut frame_graph) = renderer.frame_graph.take() else {
             log::error!("No frame graph has been submitted to the renderer.");
             return;
         };
+        for (pipeline_name, define, value) in renderer.pending_compute_pipeline_defines.drain(..) {
+            frame_graph.set_compute_pipeline_define(&pipeline_name, define, value);
+        }
         renderer.frame_graph_executor.begin_frame(frame_graph);
 
         // Write swapchain constants immediately.
@@ -142,13 +244,21 @@ impl Renderer {
         entities_gpu: ResMut<WorldEntitiesGpu>,
         voxel_registry_gpu: Res<VoxelModelRegistryGpu>,
         debug_renderer: Res<DebugRenderer>,
-        sky: Res<Sky>,
+        sky_light: Res<SkyLight>,
+        world_origin: Res<WorldOrigin>,
+        camera_shake: Res<CameraShake>,
+        settings: Res<Settings>,
     ) {
         let renderer = &mut *renderer;
+        let camera_jitter = renderer.camera_jitter;
+        let mut backbuffer_size = Vector2::zeros();
+        let mut camera_proj_view = Matrix4::identity();
         renderer
             .frame_graph_executor
             .write_uniforms(&mut |writer, ctx| {
                 writer.write_set_cache("u_frame", Self::SET_CACHE_SLOT_FRAME);
+                backbuffer_size =
+                    ctx.get_vec2(renderer.backbuffer_size_input.expect("Should exist."));
 
                 // FrameInfo struct
                 writer.write_uniform(
@@ -162,18 +272,33 @@ impl Renderer {
                         .query_one::<(&Transform, &Camera)>(main_camera)
                         .get()
                         .expect("Main camera should have a transform and camera component.");
-                    let camera_world_transform =
-                        ecs_world.get_world_transform(main_camera, camera_transform);
+                    let camera_world_transform = camera_shake.shaken_transform(
+                        camera,
+                        &ecs_world.get_global_transform(main_camera, camera_transform),
+                        &settings,
+                    );
 
-                    let backbuffer_size =
-                        ctx.get_vec2(renderer.backbuffer_size_input.expect("Should exist."));
                     let aspect_ratio = backbuffer_size.x as f32 / backbuffer_size.y as f32;
-                    let proj_view_matrix = camera.projection_matrix(aspect_ratio)
-                        * camera_world_transform.to_view_matrix();
+                    let projection_matrix = camera.projection_matrix(aspect_ratio);
+                    let view_matrix = camera_world_transform.to_view_matrix();
+                    camera_proj_view = projection_matrix * view_matrix;
+
+                    // Offset clip xy by the jitter scaled by w so it is a constant ndc offset
+                    // after the perspective divide, ndc y points up.
+                    let mut jittered_projection_matrix = projection_matrix;
+                    jittered_projection_matrix.m13 +=
+                        camera_jitter.x * 2.0 / backbuffer_size.x as f32;
+                    jittered_projection_matrix.m23 -=
+                        camera_jitter.y * 2.0 / backbuffer_size.y as f32;
+                    let proj_view_matrix = jittered_projection_matrix * view_matrix;
                     writer.write_uniform_mat4(
                         "u_frame.world_info.camera.proj_view",
                         &proj_view_matrix,
                     );
+                    writer.write_uniform_mat4(
+                        "u_frame.world_info.camera.unjittered_proj_view",
+                        &camera_proj_view,
+                    );
 
                     let transformation_matrix = camera_world_transform.to_transformation_matrix();
                     writer.write_uniform_mat4(
@@ -185,6 +310,10 @@ impl Renderer {
                     writer
                         .write_uniform_mat3("u_frame.world_info.camera.rotation", &rot_matrix_3x3);
                     writer.write_uniform::<f32>("u_frame.world_info.camera.fov", camera.fov());
+                    writer.write_uniform::<f32>(
+                        "u_frame.world_info.camera.ortho_half_height",
+                        camera.orthographic_half_height.unwrap_or(0.0),
+                    );
                     writer.write_uniform::<f32>(
                         "u_frame.world_info.camera.near_plane",
                         camera.near_plane(),
@@ -193,11 +322,19 @@ impl Renderer {
                         "u_frame.world_info.camera.far_plane",
                         camera.far_plane(),
                     );
+                    writer.write_uniform::<Vector2<f32>>(
+                        "u_frame.world_info.camera.jitter",
+                        camera_jitter,
+                    );
                 } else {
                     writer.write_uniform_mat4(
                         "u_frame.world_info.camera.proj_view",
                         &Matrix4::zeros(),
                     );
+                    writer.write_uniform_mat4(
+                        "u_frame.world_info.camera.unjittered_proj_view",
+                        &Matrix4::zeros(),
+                    );
                     writer.write_uniform_mat4(
                         "u_frame.world_info.camera.transform",
                         &Matrix4::zeros(),
@@ -207,11 +344,30 @@ impl Renderer {
                         &Matrix3::zeros(),
                     );
                     writer.write_uniform::<f32>("u_frame.world_info.camera.fov", 0.0);
+                    writer.write_uniform::<f32>("u_frame.world_info.camera.ortho_half_height", 0.0);
                     writer.write_uniform::<f32>("u_frame.world_info.camera.near_plane", 0.0);
                     writer.write_uniform::<f32>("u_frame.world_info.camera.far_plane", 0.0);
+                    writer.write_uniform::<Vector2<f32>>(
+                        "u_frame.world_info.camera.jitter",
+                        Vector2::zeros(),
+                    );
                 }
-                writer
-                    .write_uniform::<Vector3<f32>>("u_frame.world_info.sky.sun_dir", sky.sun_dir());
+                writer.write_uniform::<Vector3<f32>>(
+                    "u_frame.world_info.sky.sun_dir",
+                    sky_light.sun_dir(),
+                );
+                writer.write_uniform::<Vector3<f32>>(
+                    "u_frame.world_info.sky.sun_color",
+                    sky_light.sun_radiance(),
+                );
+                writer.write_uniform::<Vector3<f32>>(
+                    "u_frame.world_info.sky.zenith_color",
+                    sky_light.zenith_color.linear_rgb_vec(),
+                );
+                writer.write_uniform::<Vector3<f32>>(
+                    "u_frame.world_info.sky.horizon_color",
+                    sky_light.horizon_color.linear_rgb_vec(),
+                );
 
                 // Material bank bindings
                 writer.write_binding_array(
@@ -271,6 +427,10 @@ impl Renderer {
                     "u_frame.voxel.terrain.region_anchor",
                     Vector3::<i32>::from(region_map_gpu.region_window_anchor()),
                 );
+                writer.write_uniform(
+                    "u_frame.voxel.terrain.origin_region",
+                    Vector3::<i32>::from(world_origin.region()),
+                );
                 writer.write_uniform(
                     "u_frame.voxel.terrain.region_offset",
                     region_map_gpu.region_window_offset(),
@@ -278,6 +438,8 @@ impl Renderer {
 
                 debug_renderer.write_global_uniforms(writer);
             });
+        renderer.backbuffer_size = backbuffer_size;
+        renderer.camera_proj_view = camera_proj_view;
     }
 
     /// Size of the last acquired swapchain image.
@@ -290,15 +452,30 @@ impl Renderer {
         mut device: ResMut<DeviceResource>,
         window: Res<Window>,
     ) {
+        // Skip rendering until the window is restored.
+        if device.is_minimized() {
+            renderer.swapchain_image = None;
+            return;
+        }
         renderer.swapchain_image = match device.acquire_swapchain_image() {
             Ok(image) => Some(image),
             Err(err) => {
                 let inner_size = window.inner_size();
-                warn!(
-                    "Tried to acquire swapchain error but got an error `{}`, trying to resize swapchain to {}x{}.",
-                    err, inner_size.width, inner_size.height
-                );
-                device.resize_swapchain(inner_size, true);
+                match err.downcast_ref::<GfxDeviceError>() {
+                    // The app shuts down once it sees the device's fatal error.
+                    Some(device_error) if !device_error.is_recoverable() => {}
+                    Some(device_error) => {
+                        debug!("{}, recreating the swapchain.", device_error);
+                        device.recreate_swapchain(inner_size, true);
+                    }
+                    None => {
+                        warn!(
+                            "Tried to acquire swapchain error but got an error `{}`, trying to resize swapchain to {}x{}.",
+                            err, inner_size.width, inner_size.height
+                        );
+                        device.resize_swapchain(inner_size, true);
+                    }
+                }
                 None
             }
         };
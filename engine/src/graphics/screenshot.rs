@@ -0,0 +1,382 @@
+use std::collections::VecDeque;
+
+use nalgebra::{Vector2, Vector4};
+use rogue_macros::Resource;
+
+use crate::{
+    asset::{
+        asset::{AssetPath, Assets},
+        repr::image::{ImageAsset, ImageAssetFormat},
+    },
+    common::color::{Color, ColorSpaceSrgb, ColorSpaceSrgbLinear},
+    graphics::{
+        backend::{
+            Buffer, GfxBufferCreateInfo, GfxImageFormat, GfxImageToBufferCopyInfo,
+            GraphicsBackendRecorder, Image, ResourceId,
+        },
+        device::DeviceResource,
+        frame_graph::{
+            FrameGraphBuilder, FrameGraphContext, FrameGraphResource, IntoFrameGraphResource, Pass,
+        },
+        renderer::Renderer,
+    },
+    resource::{Res, ResMut},
+};
+
+struct ScreenshotsGraphConstants {
+    pass_name: &'static str,
+}
+
+/// The region of the composited image to capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScreenshotRegion {
+    Full,
+    /// The rect left after removing the padding (top, bottom, left, right) from the edges of the
+    /// image, the same layout as the editor's content padding.
+    Padded(Vector4<u32>),
+}
+
+impl ScreenshotRegion {
+    /// Resolves the region into an (offset, size) rect within an image of `image_size`.
+    pub fn rect(&self, image_size: Vector2<u32>) -> (Vector2<u32>, Vector2<u32>) {
+        match self {
+            ScreenshotRegion::Full => (Vector2::zeros(), image_size),
+            ScreenshotRegion::Padded(padding) => {
+                let offset = Vector2::new(padding.z.min(image_size.x), padding.x.min(image_size.y));
+                let size = Vector2::new(
+                    image_size.x.saturating_sub(padding.z + padding.w),
+                    image_size.y.saturating_sub(padding.x + padding.y),
+                );
+                (offset, size)
+            }
+        }
+    }
+}
+
+pub struct ScreenshotRequest {
+    pub region: ScreenshotRegion,
+    /// Renders the scene at this multiple of its usual resolution for the captured frame. When
+    /// greater than 1 the capture is taken from the scene image before any ui is composited, so
+    /// `region` is ignored.
+    pub supersample_scale: u32,
+    /// Renders the scene at exactly this resolution for the captured frame instead of the
+    /// viewport's, `region` is ignored like with supersampling.
+    pub resolution: Option<Vector2<u32>>,
+    pub path: AssetPath,
+}
+
+impl ScreenshotRequest {
+    pub fn new(region: ScreenshotRegion) -> Self {
+        Self {
+            region,
+            supersample_scale: 1,
+            resolution: None,
+            path: Screenshots::new_screenshot_path(),
+        }
+    }
+
+    pub fn with_supersample_scale(mut self, supersample_scale: u32) -> Self {
+        assert!(
+            supersample_scale >= 1,
+            "Supersample scale must be at least 1."
+        );
+        self.supersample_scale = supersample_scale;
+        self
+    }
+
+    pub fn with_resolution(mut self, resolution: Vector2<u32>) -> Self {
+        assert!(
+            resolution.x > 0 && resolution.y > 0,
+            "Screenshot resolution must be non-zero."
+        );
+        self.resolution = Some(resolution);
+        self
+    }
+
+    pub fn with_path(mut self, path: AssetPath) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Whether the capture is taken from the scene image rather than the composited image.
+    fn captures_scene_image(&self) -> bool {
+        self.supersample_scale > 1 || self.resolution.is_some()
+    }
+}
+
+/// A capture recorded on the gpu which we are waiting on to finish before reading it back.
+struct ScreenshotReadback {
+    cpu_frame: u64,
+    byte_size: u64,
+    size: Vector2<u32>,
+    format: GfxImageFormat,
+    path: AssetPath,
+}
+
+#[derive(Resource)]
+pub struct Screenshots {
+    queued_requests: VecDeque<ScreenshotRequest>,
+    /// The request being captured in the current frame.
+    capturing: Option<ScreenshotRequest>,
+    readback: Option<ScreenshotReadback>,
+    /// Reused between captures since only one readback is in flight at a time.
+    readback_buffer: Option<ResourceId<Buffer>>,
+
+    graph_composited_image: Option<FrameGraphResource<Image>>,
+    graph_scene_image: Option<FrameGraphResource<Image>>,
+}
+
+impl Screenshots {
+    const GRAPH: ScreenshotsGraphConstants = ScreenshotsGraphConstants {
+        pass_name: "screenshot_pass",
+    };
+
+    pub fn new() -> Self {
+        Self {
+            queued_requests: VecDeque::new(),
+            capturing: None,
+            readback: None,
+            readback_buffer: None,
+            graph_composited_image: None,
+            graph_scene_image: None,
+        }
+    }
+
+    pub fn new_screenshot_path() -> AssetPath {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or(0);
+        AssetPath::new_user_dir(format!("screenshots::screenshot_{}::png", timestamp))
+    }
+
+    pub fn request(&mut self, request: ScreenshotRequest) {
+        self.queued_requests.push_back(request);
+    }
+
+    /// Whether there are no queued, capturing or in flight screenshots.
+    pub fn is_idle(&self) -> bool {
+        self.queued_requests.is_empty() && self.capturing.is_none() && self.readback.is_none()
+    }
+
+    /// Whether a capture was recorded and is waiting on the gpu to be read back.
+    pub fn is_reading_back(&self) -> bool {
+        self.readback.is_some()
+    }
+
+    /// The size the scene should be rendered at this frame, `render_size` being the size it is
+    /// usually rendered at for a viewport of `viewport_size`.
+    pub fn capture_scene_size(
+        &self,
+        viewport_size: Vector2<u32>,
+        render_size: Vector2<u32>,
+    ) -> Vector2<u32> {
+        match &self.capturing {
+            Some(request) if request.captures_scene_image() => {
+                request.resolution.unwrap_or(viewport_size) * request.supersample_scale
+            }
+            _ => render_size,
+        }
+    }
+
+    /// Adds the screenshot pass, `composited_image` is the final image the scene is composited
+    /// into and `scene_image` is the scene image used for supersampled captures.
+    pub fn set_graph_screenshot_pass(
+        &mut self,
+        fg: &mut FrameGraphBuilder,
+        composited_image: impl IntoFrameGraphResource<Image>,
+        scene_image: impl IntoFrameGraphResource<Image>,
+    ) -> FrameGraphResource<Pass> {
+        let composited_image_handle = composited_image.handle(fg);
+        let scene_image_handle = scene_image.handle(fg);
+        let pass = fg.create_input_pass(
+            Self::GRAPH.pass_name,
+            &[&composited_image_handle, &scene_image_handle],
+            &[&composited_image_handle],
+        );
+
+        self.graph_composited_image = Some(composited_image_handle);
+        self.graph_scene_image = Some(scene_image_handle);
+
+        pass
+    }
+
+    /// Starts the next queued capture once the previous capture has been read back.
+    pub fn update_captures(mut screenshots: ResMut<Screenshots>, mut renderer: ResMut<Renderer>) {
+        for path in renderer.take_screenshot_requests() {
+            screenshots.request(
+                ScreenshotRequest::new(ScreenshotRegion::Full).with_path(AssetPath::new(path)),
+            );
+        }
+        if screenshots.readback.is_none() && screenshots.capturing.is_none() {
+            screenshots.capturing = screenshots.queued_requests.pop_front();
+        }
+    }
+
+    pub fn write_graph_screenshot_pass(
+        mut screenshots: ResMut<Screenshots>,
+        mut renderer: ResMut<Renderer>,
+        mut device: ResMut<DeviceResource>,
+    ) {
+        let screenshots: &mut Screenshots = &mut screenshots;
+        let composited_image_handle = *screenshots.graph_composited_image.as_ref().expect(
+            "Should not be writing screenshot pass without setting it up in the render graph first.",
+        );
+        let scene_image_handle = *screenshots.graph_scene_image.as_ref().expect(
+            "Should not be writing screenshot pass without setting it up in the render graph first.",
+        );
+        let capturing = screenshots.capturing.take();
+        let mut readback_buffer = screenshots.readback_buffer;
+        let mut readback = None;
+        renderer.frame_graph_executor.supply_pass_ref(
+            Self::GRAPH.pass_name,
+            &mut |recorder: &mut dyn GraphicsBackendRecorder, ctx: &FrameGraphContext<'_>| {
+                let Some(request) = &capturing else {
+                    return;
+                };
+
+                let (src, src_offset, src_length) = if request.captures_scene_image() {
+                    let scene_image = ctx.get_image(scene_image_handle);
+                    let scene_size = recorder.get_image_info(&scene_image).resolution_xy();
+                    (scene_image, Vector2::zeros(), scene_size)
+                } else {
+                    let composited_image = ctx.get_image(composited_image_handle);
+                    let composited_size =
+                        recorder.get_image_info(&composited_image).resolution_xy();
+                    let (offset, size) = request.region.rect(composited_size);
+                    (composited_image, offset, size)
+                };
+                if src_length.x == 0 || src_length.y == 0 {
+                    log::warn!("Skipping screenshot since the captured region is empty.");
+                    return;
+                }
+
+                let format = recorder.get_image_info(&src).format;
+                let byte_size =
+                    src_length.x as u64 * src_length.y as u64 * format.pixel_byte_size() as u64;
+                device.create_or_reallocate_readback_buffer(
+                    &mut readback_buffer,
+                    GfxBufferCreateInfo {
+                        name: "screenshot_readback_buffer".to_owned(),
+                        size: byte_size,
+                    },
+                );
+                recorder.copy_image_to_buffer(GfxImageToBufferCopyInfo {
+                    src,
+                    src_offset,
+                    src_length,
+                    dst: readback_buffer.unwrap(),
+                    dst_offset: 0,
+                });
+
+                readback = Some(ScreenshotReadback {
+                    cpu_frame: device.curr_cpu_frame(),
+                    byte_size,
+                    size: src_length,
+                    format,
+                    path: request.path.clone(),
+                });
+            },
+        );
+        screenshots.readback_buffer = readback_buffer;
+        // Only replace the in flight readback when a capture was recorded this frame.
+        if readback.is_some() {
+            screenshots.readback = readback;
+        }
+    }
+
+    /// Saves any captures which have finished on the gpu.
+    pub fn update_readbacks(
+        mut screenshots: ResMut<Screenshots>,
+        device: Res<DeviceResource>,
+        mut assets: ResMut<Assets>,
+    ) {
+        let Some(readback) = &screenshots.readback else {
+            return;
+        };
+        if !device.is_frame_finished(readback.cpu_frame) {
+            return;
+        }
+
+        let readback = screenshots.readback.take().unwrap();
+        let data = device.read_buffer(
+            screenshots.readback_buffer.as_ref().unwrap(),
+            0,
+            readback.byte_size,
+        );
+        let Some(rgba_data) = Self::convert_to_rgba8(data, readback.format) else {
+            log::error!(
+                "Can't save screenshot of image format {:?}.",
+                readback.format
+            );
+            return;
+        };
+
+        log::info!(
+            "Saving {}x{} screenshot to {}.",
+            readback.size.x,
+            readback.size.y,
+            readback.path.path_str()
+        );
+        assets.save_asset(
+            readback.path,
+            ImageAsset {
+                data: rgba_data,
+                format: ImageAssetFormat::RGBA,
+                size: readback.size,
+            },
+        );
+    }
+
+    /// Converts tightly packed pixels to srgb encoded RGBA8 with full alpha.
+    fn convert_to_rgba8(data: &[u8], format: GfxImageFormat) -> Option<Vec<u8>> {
+        match format {
+            GfxImageFormat::Rgba8Unorm | GfxImageFormat::Rgba8Srgb => Some(
+                data.chunks_exact(4)
+                    .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+                    .collect(),
+            ),
+            GfxImageFormat::Bgra8Unorm => Some(
+                data.chunks_exact(4)
+                    .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], 255])
+                    .collect(),
+            ),
+            // The scene image is in linear srgb, so do what the post process pass would.
+            GfxImageFormat::Rgba32Float => Some(
+                data.chunks_exact(16)
+                    .flat_map(|pixel| {
+                        let channel = |i: usize| {
+                            f32::from_ne_bytes(pixel[i * 4..(i + 1) * 4].try_into().unwrap())
+                        };
+                        let color =
+                            Color::<ColorSpaceSrgbLinear>::new(channel(0), channel(1), channel(2))
+                                .into_color_space::<ColorSpaceSrgb>();
+                        let to_u8 = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+                        [to_u8(color.r()), to_u8(color.g()), to_u8(color.b()), 255]
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded_region_matches_content_rect() {
+        let window_size = Vector2::new(1920, 1080);
+        // Top, bottom, left, right.
+        let padding = Vector4::new(40, 200, 300, 250);
+        let (offset, size) = ScreenshotRegion::Padded(padding).rect(window_size);
+
+        assert_eq!(offset, Vector2::new(300, 40));
+        assert_eq!(size, Vector2::new(1920 - 300 - 250, 1080 - 40 - 200));
+        assert!(offset.x + size.x <= window_size.x && offset.y + size.y <= window_size.y);
+
+        let (_, size) = ScreenshotRegion::Padded(Vector4::new(600, 600, 0, 0)).rect(window_size);
+        assert_eq!(size.y, 0);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     borrow::Cow,
-    collections::{HashMap, HashSet, hash_map::Entry},
+    collections::{BTreeMap, HashMap, HashSet, hash_map::Entry},
     fs::File,
     future::Future,
     hash::Hash,
@@ -44,6 +44,11 @@ impl ShaderCompilationOptions {
         ShaderDesc {
             module: ShaderPath::new_unchecked(self.module.clone()),
             entry_point_name: self.entry_point.clone(),
+            macro_defines: self
+                .macro_defines
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
         }
     }
 }
@@ -319,6 +324,11 @@ impl ShaderCompiler {
                 for (key, value) in &self.shader_constants {
                     shader_slang_opts = shader_slang_opts.macro_define(key, value);
                 }
+                // Per-pipeline defines, such as workgroup sizes, take precedence over the global
+                // shader constants.
+                for (key, value) in &options.macro_defines {
+                    shader_slang_opts = shader_slang_opts.macro_define(key, value);
+                }
 
                 let mut session = self
                     .global_session
@@ -529,7 +539,9 @@ impl ShaderCompiler {
                             }
                             _ => unreachable!(),
                         },
-                        shader_slang::ResourceShape::SlangTextureCube => todo!(),
+                        shader_slang::ResourceShape::SlangTextureCube => {
+                            ShaderBindingType::SampledImage
+                        }
                         shader_slang::ResourceShape::SlangByteAddressBuffer
                         | shader_slang::ResourceShape::SlangStructuredBuffer => {
                             if let Some(descriptor_array_size) = descriptor_array_size {
@@ -1096,4 +1108,6 @@ impl ShaderModificationTree {
 pub struct ShaderDesc {
     pub module: ShaderPath,
     pub entry_point_name: String,
+    /// Macros defined only for this shader, a different set of defines is a different shader.
+    pub macro_defines: BTreeMap<String, String>,
 }
@@ -8,7 +8,7 @@ use std::{
     str::FromStr,
     sync::{
         Arc,
-        atomic::{AtomicU32, AtomicU64},
+        atomic::{AtomicBool, AtomicU32, AtomicU64},
     },
     u32, u64,
 };
@@ -30,13 +30,14 @@ use crate::graphics::{
     backend::{
         BindGroup, Binding, Buffer, ComputePipeline, GfxAddressMode, GfxBlendFactor, GfxBlendOp,
         GfxBufferCreateInfo, GfxBufferInfo, GfxComputePipelineCreateInfo, GfxComputePipelineInfo,
-        GfxCullMode, GfxDeviceInfo, GfxFilterMode, GfxFrontFace, GfxImageCreateInfo,
-        GfxImageFormat, GfxImageInfo, GfxImageType, GfxImageWrite, GfxLoadOp, GfxPresentMode,
+        GfxCullMode, GfxDeviceError, GfxDeviceInfo, GfxFilterMode, GfxFrameStats, GfxFrontFace,
+        GfxImageCreateInfo, GfxImageFormat, GfxImageInfo, GfxImageType, GfxImageWrite, GfxLoadOp,
+        GfxMemoryPoolStats, GfxMemoryStats, GfxPresentMode,
         GfxRasterPipelineBlendStateAttachmentInfo, GfxRasterPipelineBlendStateCreateInfo,
         GfxRasterPipelineCreateInfo, GfxSamplerCreateInfo, GfxSwapchainInfo, GfxVertexAttribute,
-        GfxVertexAttributeFormat, GfxVertexFormat, GraphicsBackendDevice, GraphicsBackendEvent,
-        GraphicsBackendFrameGraphExecutor, Image, Memory, RasterPipeline, ResourceId, Sampler,
-        ShaderSetData, ShaderWriter, UniformSetData, Untyped,
+        GfxVertexAttributeFormat, GfxVertexFormat, GfxWriteGroupHandle, GraphicsBackendDevice,
+        GraphicsBackendEvent, GraphicsBackendFrameGraphExecutor, Image, Memory, RasterPipeline,
+        ResourceId, Sampler, ShaderSetData, ShaderWriter, UniformSetData, Untyped,
     },
     gpu_allocator::{Allocation, AllocatorTree},
     shader::{
@@ -55,7 +56,8 @@ pub struct VulkanContextInner {
     entry: ash::Entry,
     instance: ash::Instance,
     debug_messenger: Option<ash::vk::DebugUtilsMessengerEXT>,
-    surface: ash::vk::SurfaceKHR,
+    /// None for headless devices which never present.
+    surface: Option<ash::vk::SurfaceKHR>,
     physical_device: VulkanPhysicalDevice,
     device: ash::Device,
     debug_utils_device: Option<ash::ext::debug_utils::Device>,
@@ -91,6 +93,37 @@ impl VulkanContextInner {
         }
         .expect("Failed to get gpu timeline semaphore.")
     }
+
+    /// Names the object in validation messages and capture tools, does nothing unless debugging
+    /// is enabled.
+    pub fn set_object_name(&self, handle: impl ash::vk::Handle, name: &str) {
+        let Some(debug_utils) = &self.debug_utils_device else {
+            return;
+        };
+        let Ok(c_name) = CString::new(name) else {
+            log::warn!("Vulkan object name `{}` contains a nul byte.", name);
+            return;
+        };
+        if let Err(err) = unsafe {
+            debug_utils.set_debug_utils_object_name(
+                &ash::vk::DebugUtilsObjectNameInfoEXT::default()
+                    .object_handle(handle)
+                    .object_name(&c_name),
+            )
+        } {
+            log::warn!("Failed to set vulkan object name `{}`, {}", name, err);
+        }
+    }
+
+    /// The max sampler anisotropy, `None` if anisotropic filtering isn't supported.
+    pub fn max_sampler_anisotropy(&self) -> Option<f32> {
+        (self.physical_device.features.sampler_anisotropy == ash::vk::TRUE).then_some(
+            self.physical_device
+                .properties
+                .limits
+                .max_sampler_anisotropy,
+        )
+    }
 }
 
 impl Drop for VulkanContextInner {
@@ -104,8 +137,10 @@ impl Drop for VulkanContextInner {
             self.device.destroy_device(None);
         };
 
-        let surface_loader = ash::khr::surface::Instance::new(&self.entry, &self.instance);
-        unsafe { surface_loader.destroy_surface(self.surface, None) };
+        if let Some(surface) = self.surface {
+            let surface_loader = ash::khr::surface::Instance::new(&self.entry, &self.instance);
+            unsafe { surface_loader.destroy_surface(surface, None) };
+        }
 
         if let Some(debug_messenger) = self.debug_messenger {
             let debug_utils_loader =
@@ -126,7 +161,10 @@ pub struct VulkanContext {
     memory_allocator: parking_lot::RwLock<VulkanAllocator>,
     resource_manager: VulkanResourceManager,
 
-    swapchain: parking_lot::RwLock<Arc<VulkanSwapchain>>,
+    /// None for headless devices, which render into `offscreen_images` instead.
+    swapchain: Option<parking_lot::RwLock<Arc<VulkanSwapchain>>>,
+    /// One per frame in flight so a frame never renders into an image the gpu is still reading.
+    offscreen_images: Vec<ResourceId<Image>>,
     inner: Arc<VulkanContextInner>,
 
     // Semaphore when the swapchain image is acquired.
@@ -136,6 +174,12 @@ pub struct VulkanContext {
 
     // The current swapchain image index of the most recently acquired image.
     swapchain_image_index: AtomicU32,
+    /// Set when presenting finds the swapchain out of date or suboptimal, it is recreated
+    /// before the next acquire.
+    swapchain_out_of_date: AtomicBool,
+
+    /// Set once the device or surface is lost.
+    fatal_error: parking_lot::Mutex<Option<GfxDeviceError>>,
 }
 
 impl VulkanContext {
@@ -148,17 +192,43 @@ impl VulkanContext {
     }
 
     pub fn swapchain(&self) -> parking_lot::RwLockReadGuard<Arc<VulkanSwapchain>> {
-        self.swapchain.read()
+        self.swapchain
+            .as_ref()
+            .expect("Headless devices have no swapchain.")
+            .read()
     }
 
     pub fn surface(&self) -> ash::vk::SurfaceKHR {
-        self.inner.surface.clone()
+        self.inner
+            .surface
+            .expect("Headless devices have no surface.")
+    }
+
+    /// Whether frames are rendered into offscreen images rather than presented to a window.
+    pub fn is_headless(&self) -> bool {
+        self.swapchain.is_none()
+    }
+
+    /// The offscreen image used in place of a swapchain image this frame.
+    pub fn curr_offscreen_image(&self) -> ResourceId<Image> {
+        self.offscreen_images[self.curr_cpu_frame_index() as usize]
     }
 
     pub fn frames_in_flight(&self) -> u32 {
         self.inner.frames_in_flight
     }
 
+    /// Nanoseconds per timestamp query tick, `None` if the main queue can't write timestamps.
+    pub fn timestamp_period(&self) -> Option<f32> {
+        let physical_device = &self.inner.physical_device;
+        let limits = &physical_device.properties.limits;
+        let valid_bits = physical_device.queue_family_properties
+            [self.inner.main_queue_family_index as usize]
+            .timestamp_valid_bits;
+        (limits.timestamp_compute_and_graphics == ash::vk::TRUE && valid_bits > 0)
+            .then_some(limits.timestamp_period)
+    }
+
     pub fn surface_loader(&self) -> ash::khr::surface::Instance {
         ash::khr::surface::Instance::new(&self.inner.entry, &self.inner.instance)
     }
@@ -199,6 +269,28 @@ impl VulkanContext {
         self.inner.gpu_timeline_semaphore
     }
 
+    pub fn fatal_error(&self) -> Option<GfxDeviceError> {
+        *self.fatal_error.lock()
+    }
+
+    /// Maps `err` to a `GfxDeviceError` if it is one, recording it if it is unrecoverable.
+    pub fn record_vk_error(&self, err: ash::vk::Result) -> Option<GfxDeviceError> {
+        let device_error = match err {
+            ash::vk::Result::ERROR_OUT_OF_DATE_KHR => GfxDeviceError::SwapchainOutOfDate,
+            ash::vk::Result::ERROR_SURFACE_LOST_KHR => GfxDeviceError::SurfaceLost,
+            ash::vk::Result::ERROR_DEVICE_LOST => GfxDeviceError::DeviceLost,
+            _ => return None,
+        };
+        if !device_error.is_recoverable() {
+            let mut fatal_error = self.fatal_error.lock();
+            if fatal_error.is_none() {
+                log::error!("{}, vulkan returned {}.", device_error, err);
+                *fatal_error = Some(device_error);
+            }
+        }
+        Some(device_error)
+    }
+
     pub fn main_queue(&self) -> ash::vk::Queue {
         self.inner.main_queue
     }
@@ -207,11 +299,30 @@ impl VulkanContext {
         self.inner.transfer_queue
     }
 
+    pub fn main_queue_family_index(&self) -> u32 {
+        self.inner.main_queue_family_index
+    }
+
+    pub fn transfer_queue_family_index(&self) -> Option<u32> {
+        self.inner.transfer_queue_family_index
+    }
+
     pub fn curr_swapchain_image_index(&self) -> u32 {
         self.swapchain_image_index
             .load(std::sync::atomic::Ordering::Relaxed)
     }
 
+    pub fn mark_swapchain_out_of_date(&self) {
+        self.swapchain_out_of_date
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Returns true once after `mark_swapchain_out_of_date` was called.
+    pub fn take_swapchain_out_of_date(&self) -> bool {
+        self.swapchain_out_of_date
+            .swap(false, std::sync::atomic::Ordering::Relaxed)
+    }
+
     pub fn create_image(
         &self,
         create_info: GfxImageCreateInfo,
@@ -221,6 +332,10 @@ impl VulkanContext {
             .create_image(&mut memory_allocator, create_info)
     }
 
+    pub fn destroy_image(&self, image: ResourceId<Image>) {
+        self.resource_manager.queue_destroy_image(image);
+    }
+
     pub fn get_image_info(&self, image: &ResourceId<Image>) -> GfxImageInfo {
         let image = self.resource_manager.get_image(*image);
         let info = &image.info;
@@ -252,16 +367,51 @@ impl VulkanContext {
         )
     }
 
+    pub fn destroy_buffer(&self, buffer: ResourceId<Buffer>) {
+        self.resource_manager.queue_destroy_buffer(buffer);
+    }
+
     pub fn get_buffer(&self, buffer: ResourceId<Buffer>) -> VulkanBuffer {
         self.resource_manager.get_buffer_info(&buffer)
     }
 
+    /// Creates a host cached buffer which is persistently mapped so gpu writes can be read back.
+    pub fn create_readback_buffer(
+        &self,
+        create_info: GfxBufferCreateInfo,
+    ) -> anyhow::Result<ResourceId<Buffer>> {
+        let mut memory_allocator = self.memory_allocator.write();
+        self.resource_manager.create_buffer(
+            &mut memory_allocator,
+            create_info,
+            VulkanAllocationType::CpuLocal,
+            true,
+        )
+    }
+
+    /// The mapped pointer to the start of the buffer, only exists for buffers created with
+    /// `create_readback_buffer`.
+    pub fn get_buffer_mapped_ptr(&self, buffer: ResourceId<Buffer>) -> Option<*mut u8> {
+        let buffer = self.resource_manager.get_buffer_info(&buffer);
+        let memory_allocator = self.memory_allocator.read();
+        memory_allocator
+            .get_allocation_info(&buffer.allocation)
+            .mapped_ptr
+    }
+
     pub fn get_pipeline_layout(&self, pipeline: ResourceId<Untyped>) -> VulkanPipelineLayout {
         self.resource_manager.get_pipeline_layout(pipeline)
     }
 
-    pub fn record_buffer_writes(&self, recorder: &mut VulkanRecorder) {
-        self.resource_manager.record_buffer_writes(recorder);
+    /// Records the staging copies, buffer copies are recorded on `transfer_recorder` when given,
+    /// see `VulkanResourceManager::record_buffer_writes`.
+    pub fn record_buffer_writes(
+        &self,
+        recorder: &mut VulkanRecorder,
+        transfer_recorder: Option<&VulkanRecorder>,
+    ) {
+        self.resource_manager
+            .record_buffer_writes(recorder, transfer_recorder);
     }
 
     /// Guarantees that the buffer write will be available when recording the current cpu/gpu frame.
@@ -283,6 +433,16 @@ impl VulkanContext {
             .write_image(&mut memory_allocator, info)
     }
 
+    pub fn begin_write_group(&self, size: u64) -> GfxWriteGroupHandle {
+        let mut memory_allocator = self.memory_allocator.write();
+        self.resource_manager
+            .begin_write_group(&mut memory_allocator, size)
+    }
+
+    pub fn end_write_group(&self, write_group: GfxWriteGroupHandle) {
+        self.resource_manager.end_write_group(write_group);
+    }
+
     pub fn create_compute_pipeline(
         &self,
         create_info: GfxComputePipelineCreateInfo,
@@ -291,7 +451,7 @@ impl VulkanContext {
     }
 
     pub fn destroy_compute_pipeline(&self, id: ResourceId<ComputePipeline>) {
-        warn!("TODO: Work on destroying pipelines.");
+        self.resource_manager.destroy_compute_pipeline(id);
     }
 
     pub fn create_raster_pipeline(
@@ -301,6 +461,10 @@ impl VulkanContext {
         self.resource_manager.create_raster_pipeline(create_info)
     }
 
+    pub fn destroy_raster_pipeline(&self, id: ResourceId<RasterPipeline>) {
+        self.resource_manager.destroy_raster_pipeline(id);
+    }
+
     pub fn get_compute_pipeline(
         &self,
         compute_pipeline: ResourceId<ComputePipeline>,
@@ -348,10 +512,12 @@ pub struct VulkanUniformBinding {
 pub type VulkanContextHandle = Arc<VulkanContext>;
 pub struct VulkanDevice {
     context: VulkanContextHandle,
+    swapchain_info: GfxSwapchainInfo,
+    /// Set while the surface has no area so there is no valid swapchain to render to.
+    is_minimized: bool,
 
-    // Size is # of frames in flight (swapchain images).
-    destroy_frame_queue: Vec<Vec<ResourceId<Untyped>>>,
     skipped_gpu_frames: HashSet<u64>,
+    last_frame_stats: GfxFrameStats,
 }
 
 pub struct VulkanSwapchain {
@@ -372,20 +538,35 @@ pub struct VulkanPhysicalDevice {
     features: ash::vk::PhysicalDeviceFeatures,
 }
 
+/// Where the device's frames end up.
+pub enum VulkanPresentTarget<'a> {
+    Window(&'a Window),
+    /// Renders into offscreen images of this size without creating a surface or swapchain.
+    Offscreen(Vector2<u32>),
+}
+
 pub struct VulkanCreateInfo<'a> {
-    pub window: &'a Window,
+    pub target: VulkanPresentTarget<'a>,
     pub swapchain_info: GfxSwapchainInfo,
     pub enable_debug: bool,
 }
 
 impl VulkanDevice {
+    /// The format of offscreen images, swapchains usually pick bgra but offscreen images are
+    /// only ever read back so use the format screenshots save in.
+    const OFFSCREEN_IMAGE_FORMAT: GfxImageFormat = GfxImageFormat::Rgba8Unorm;
+
     pub fn init(
         VulkanCreateInfo {
-            window,
+            target,
             swapchain_info,
             enable_debug,
         }: VulkanCreateInfo,
     ) -> anyhow::Result<Self> {
+        let window = match &target {
+            VulkanPresentTarget::Window(window) => Some(*window),
+            VulkanPresentTarget::Offscreen(_) => None,
+        };
         let entry = unsafe { ash::Entry::load() }?;
 
         let instance = {
@@ -419,7 +600,9 @@ impl VulkanDevice {
                 if available_layer_names.contains(&validation_layer_cstring) {
                     enabled_layers.push(validation_layer_cstring);
                 } else {
-                    log::warn!("Couldn't enable vulkan validation layers since they exist.");
+                    log::warn!(
+                        "Couldn't enable vulkan validation layers since they aren't installed."
+                    );
                 }
             }
 
@@ -434,9 +617,11 @@ impl VulkanDevice {
                 .iter()
                 .map(|cstr| cstr.as_ptr())
                 .collect::<Vec<_>>();
-            enabled_extensions_ptrs.extend_from_slice(ash_window::enumerate_required_extensions(
-                window.display_handle()?.as_raw(),
-            )?);
+            if let Some(window) = window {
+                enabled_extensions_ptrs.extend_from_slice(
+                    ash_window::enumerate_required_extensions(window.display_handle()?.as_raw())?,
+                );
+            }
             if enable_debug {
                 debug!("Pushing debug extensions");
                 enabled_extensions_ptrs.push(ash::ext::debug_utils::NAME.as_ptr());
@@ -489,15 +674,18 @@ impl VulkanDevice {
         };
 
         let surface_loader = ash::khr::surface::Instance::new(&entry, &instance);
-        let surface = unsafe {
-            ash_window::create_surface(
-                &entry,
-                &instance,
-                window.display_handle()?.as_raw(),
-                window.window_handle()?.as_raw(),
-                None,
-            )
-        }?;
+        let surface = match window {
+            Some(window) => Some(unsafe {
+                ash_window::create_surface(
+                    &entry,
+                    &instance,
+                    window.display_handle()?.as_raw(),
+                    window.window_handle()?.as_raw(),
+                    None,
+                )
+            }?),
+            None => None,
+        };
 
         let physical_device = {
             let mut devices = unsafe { instance.enumerate_physical_devices() }?
@@ -519,8 +707,12 @@ impl VulkanDevice {
                         instance.get_physical_device_queue_family_properties(physical_device)
                     };
 
-                    let mut has_surface_support = false;
+                    // Headless devices don't need to present.
+                    let mut has_surface_support = surface.is_none();
                     for i in 0..queue_family_properties.len() {
+                        let Some(surface) = surface else {
+                            break;
+                        };
                         if unsafe {
                             surface_loader
                                 .get_physical_device_surface_support(
@@ -620,12 +812,14 @@ impl VulkanDevice {
             });
 
         let device = {
-            let enabled_extensions_ptrs = vec![
-                ash::khr::swapchain::NAME.as_ptr(),
+            let mut enabled_extensions_ptrs = vec![
                 ash::khr::dynamic_rendering_local_read::NAME.as_ptr(),
                 ash::khr::draw_indirect_count::NAME.as_ptr(),
                 ash::khr::shader_draw_parameters::NAME.as_ptr(),
             ];
+            if surface.is_some() {
+                enabled_extensions_ptrs.push(ash::khr::swapchain::NAME.as_ptr());
+            }
 
             let mut feature_descriptor_indexing =
                 ash::vk::PhysicalDeviceDescriptorIndexingFeatures::default()
@@ -655,6 +849,8 @@ impl VulkanDevice {
                 instance
                     .get_physical_device_features2(physical_device.physical_device, &mut features2)
             };
+            // Samplers fall back to no anisotropy when it isn't supported.
+            features2.features.sampler_anisotropy = physical_device.features.sampler_anisotropy;
 
             let queue_priorities = [1.0];
             let mut queue_create_infos = vec![
@@ -688,82 +884,75 @@ impl VulkanDevice {
                 device.get_device_queue(transfer_queue_family_index, 0)
             });
 
-        let (
-            swapchain,
-            swapchain_create_info,
-            swapchain_images,
-            swapchain_format,
-            swapchain_extent,
-            swapchain_image_usage,
-        ) = {
-            let mut surface_capabilities = unsafe {
-                surface_loader.get_physical_device_surface_capabilities(
-                    physical_device.physical_device,
-                    surface,
-                )
-            }?;
-            debug!("Surface capabilities: {:?}", surface_capabilities);
-            let present_mode = Self::get_optimal_present_mode(
-                &surface_loader,
-                &surface,
-                &physical_device,
-                &swapchain_info.present_mode,
-            )?;
-            let swapchain_format =
-                Self::get_optimal_swapchain_format(&surface_loader, &surface, &physical_device)?;
-            log::info!("Chosen swapchain format: {:?}", swapchain_format);
-
-            // A max image count of 0 means there is no limit, so choose some arbitrary upper limit.
-            if surface_capabilities.max_image_count == 0 {
-                surface_capabilities.max_image_count = surface_capabilities.min_image_count + 1;
-            }
+        // Headless devices create their offscreen images once the resource manager exists.
+        let swapchain_parts = match (window, surface) {
+            (Some(window), Some(surface)) => Some({
+                let surface_capabilities = unsafe {
+                    surface_loader.get_physical_device_surface_capabilities(
+                        physical_device.physical_device,
+                        surface,
+                    )
+                }?;
+                debug!("Surface capabilities: {:?}", surface_capabilities);
+                let present_mode = Self::get_optimal_present_mode(
+                    &surface_loader,
+                    &surface,
+                    &physical_device,
+                    &swapchain_info.present_mode,
+                )?;
+                log::info!("Chosen present mode: {:?}", present_mode);
+                let swapchain_format = Self::get_optimal_swapchain_format(
+                    &surface_loader,
+                    &surface,
+                    &physical_device,
+                )?;
+                log::info!("Chosen swapchain format: {:?}", swapchain_format);
 
-            let min_image_count = swapchain_info
-                .triple_buffering
-                .then_some(3)
-                .unwrap_or(2)
-                .clamp(
-                    surface_capabilities.min_image_count,
-                    surface_capabilities.max_image_count,
+                let min_image_count = Self::get_swapchain_image_count(
+                    present_mode,
+                    swapchain_info.triple_buffering,
+                    &surface_capabilities,
                 );
-            let swapchain_extent = ash::vk::Extent2D {
-                width: window.width().clamp(
-                    surface_capabilities.min_image_extent.width,
-                    surface_capabilities.max_image_extent.width,
-                ),
-                height: window.height().clamp(
-                    surface_capabilities.min_image_extent.height,
-                    surface_capabilities.max_image_extent.height,
-                ),
-            };
-            let swapchain_image_usage =
-                ash::vk::ImageUsageFlags::TRANSFER_DST | ash::vk::ImageUsageFlags::COLOR_ATTACHMENT;
-
-            let swapchain_create_info = ash::vk::SwapchainCreateInfoKHR::default()
-                .surface(surface)
-                .min_image_count(min_image_count)
-                .image_format(swapchain_format.format)
-                .image_color_space(swapchain_format.color_space)
-                .image_extent(swapchain_extent)
-                .image_array_layers(1)
-                .image_sharing_mode(ash::vk::SharingMode::EXCLUSIVE)
-                .image_usage(swapchain_image_usage)
-                .composite_alpha(ash::vk::CompositeAlphaFlagsKHR::OPAQUE)
-                .pre_transform(ash::vk::SurfaceTransformFlagsKHR::IDENTITY)
-                .present_mode(present_mode);
-
-            let swapchain_loader = ash::khr::swapchain::Device::new(&instance, &device);
-            let swapchain =
-                unsafe { swapchain_loader.create_swapchain(&swapchain_create_info, None) }?;
-            let swapchain_images = unsafe { swapchain_loader.get_swapchain_images(swapchain) }?;
-            (
-                swapchain,
-                swapchain_create_info,
-                swapchain_images,
-                swapchain_format,
-                swapchain_extent,
-                swapchain_image_usage,
-            )
+                let swapchain_extent = ash::vk::Extent2D {
+                    width: window.width().clamp(
+                        surface_capabilities.min_image_extent.width,
+                        surface_capabilities.max_image_extent.width,
+                    ),
+                    height: window.height().clamp(
+                        surface_capabilities.min_image_extent.height,
+                        surface_capabilities.max_image_extent.height,
+                    ),
+                };
+                let swapchain_image_usage = ash::vk::ImageUsageFlags::TRANSFER_DST
+                    | ash::vk::ImageUsageFlags::COLOR_ATTACHMENT;
+
+                let swapchain_create_info = ash::vk::SwapchainCreateInfoKHR::default()
+                    .surface(surface)
+                    .min_image_count(min_image_count)
+                    .image_format(swapchain_format.format)
+                    .image_color_space(swapchain_format.color_space)
+                    .image_extent(swapchain_extent)
+                    .image_array_layers(1)
+                    .image_sharing_mode(ash::vk::SharingMode::EXCLUSIVE)
+                    .image_usage(swapchain_image_usage)
+                    .composite_alpha(ash::vk::CompositeAlphaFlagsKHR::OPAQUE)
+                    .pre_transform(ash::vk::SurfaceTransformFlagsKHR::IDENTITY)
+                    .present_mode(present_mode);
+
+                let swapchain_loader = ash::khr::swapchain::Device::new(&instance, &device);
+                let swapchain =
+                    unsafe { swapchain_loader.create_swapchain(&swapchain_create_info, None) }?;
+                let swapchain_images = unsafe { swapchain_loader.get_swapchain_images(swapchain) }?;
+                (
+                    swapchain,
+                    swapchain_create_info,
+                    swapchain_images,
+                    swapchain_format,
+                    swapchain_extent,
+                    swapchain_image_usage,
+                )
+            }),
+            _ => None,
         };
 
         let timeline_semaphore = {
@@ -809,47 +998,91 @@ impl VulkanDevice {
             gpu_timeline_semaphore: timeline_semaphore,
         });
         let resource_manager = VulkanResourceManager::new(&context_inner);
-        let swapchain_images = swapchain_images
-            .into_iter()
-            .map(|image| {
-                resource_manager
-                    .create_image_borrowed(VulkanBorrowedImageCreateInfo {
-                        image,
-                        usage: swapchain_image_usage,
-                        info: VulkanImageInfo {
-                            image_type: GfxImageType::D2,
-                            format: swapchain_format.format,
-                            extent: swapchain_extent,
-                        },
+        let mut memory_allocator = VulkanAllocator::new(&context_inner);
+        let swapchain = swapchain_parts.map(
+            |(
+                swapchain,
+                swapchain_create_info,
+                swapchain_images,
+                swapchain_format,
+                swapchain_extent,
+                swapchain_image_usage,
+            )| {
+                let swapchain_images = swapchain_images
+                    .into_iter()
+                    .map(|image| {
+                        resource_manager
+                            .create_image_borrowed(VulkanBorrowedImageCreateInfo {
+                                image,
+                                usage: swapchain_image_usage,
+                                info: VulkanImageInfo {
+                                    image_type: GfxImageType::D2,
+                                    format: swapchain_format.format,
+                                    extent: swapchain_extent,
+                                },
+                            })
+                            .expect("Failed to create swapchain image")
                     })
-                    .expect("Failed to create swapchain image")
-            })
-            .collect::<Vec<_>>();
-        let swapchain = Arc::new(VulkanSwapchain {
-            ctx_ref: context_inner.clone(),
-            create_info: swapchain_create_info,
-            swapchain,
-            swapchain_images,
-        });
+                    .collect::<Vec<_>>();
+                parking_lot::RwLock::new(Arc::new(VulkanSwapchain {
+                    ctx_ref: context_inner.clone(),
+                    create_info: swapchain_create_info,
+                    swapchain,
+                    swapchain_images,
+                }))
+            },
+        );
+        let offscreen_images = match target {
+            VulkanPresentTarget::Window(_) => Vec::new(),
+            VulkanPresentTarget::Offscreen(size) => {
+                anyhow::ensure!(
+                    size.x > 0 && size.y > 0,
+                    "Offscreen image size must be non-zero."
+                );
+                log::info!(
+                    "Rendering headless into {}x{} offscreen images.",
+                    size.x,
+                    size.y
+                );
+                (0..frames_in_flight)
+                    .map(|i| {
+                        resource_manager.create_image(
+                            &mut memory_allocator,
+                            GfxImageCreateInfo {
+                                name: format!("offscreen_image_{}", i),
+                                image_type: GfxImageType::D2,
+                                format: Self::OFFSCREEN_IMAGE_FORMAT,
+                                extent: size,
+                            },
+                        )
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?
+            }
+        };
 
         let context = Arc::new(VulkanContext {
             inner: context_inner.clone(),
-            swapchain: parking_lot::RwLock::new(swapchain),
+            swapchain,
+            offscreen_images,
 
             swapchain_image_index: AtomicU32::new(0),
+            swapchain_out_of_date: AtomicBool::new(false),
+            fatal_error: parking_lot::Mutex::new(None),
 
             image_acquire_semaphores,
             image_ready_semaphores,
 
-            memory_allocator: parking_lot::RwLock::new(VulkanAllocator::new(&context_inner)),
+            memory_allocator: parking_lot::RwLock::new(memory_allocator),
             resource_manager,
         });
 
         Ok(VulkanDevice {
             context,
+            swapchain_info,
+            is_minimized: false,
 
-            destroy_frame_queue: (0..frames_in_flight).map(|_| Vec::new()).collect(),
             skipped_gpu_frames: HashSet::new(),
+            last_frame_stats: GfxFrameStats::default(),
         })
     }
 
@@ -872,23 +1105,84 @@ impl VulkanDevice {
                 *surface,
             )
         }?;
-        match requested_present_mode {
-            GfxPresentMode::NoVsync => {
-                if supported_modes.contains(&ash::vk::PresentModeKHR::IMMEDIATE) {
-                    return Ok(ash::vk::PresentModeKHR::IMMEDIATE);
-                }
-            }
-            GfxPresentMode::Vsync => {
-                if supported_modes.contains(&ash::vk::PresentModeKHR::MAILBOX) {
-                    return Ok(ash::vk::PresentModeKHR::MAILBOX);
-                }
-            }
+        let present_mode = match requested_present_mode {
+            GfxPresentMode::Fifo => ash::vk::PresentModeKHR::FIFO,
+            GfxPresentMode::Mailbox => ash::vk::PresentModeKHR::MAILBOX,
+            GfxPresentMode::Immediate => ash::vk::PresentModeKHR::IMMEDIATE,
+        };
+        if supported_modes.contains(&present_mode) {
+            return Ok(present_mode);
         }
 
         // Only present mode to be guaranteed support.
         return Ok(ash::vk::PresentModeKHR::FIFO);
     }
 
+    /// Triple buffering only helps modes which queue frames for the vblank.
+    fn get_swapchain_image_count(
+        present_mode: ash::vk::PresentModeKHR,
+        triple_buffering: bool,
+        surface_capabilities: &ash::vk::SurfaceCapabilitiesKHR,
+    ) -> u32 {
+        let image_count = if triple_buffering && present_mode != ash::vk::PresentModeKHR::IMMEDIATE
+        {
+            3
+        } else {
+            2
+        };
+        // A max image count of 0 means there is no limit.
+        let max_image_count = match surface_capabilities.max_image_count {
+            0 => u32::MAX,
+            max_image_count => max_image_count,
+        };
+        image_count.clamp(surface_capabilities.min_image_count, max_image_count)
+    }
+
+    /// Errors with `ERROR_OUT_OF_DATE_KHR` while minimized since there is no valid swapchain
+    /// to acquire from, recreating the swapchain may have found the surface minimized.
+    fn try_acquire_swapchain_image(&self) -> Result<(u32, bool), ash::vk::Result> {
+        if self.is_minimized {
+            return Err(ash::vk::Result::ERROR_OUT_OF_DATE_KHR);
+        }
+        unsafe {
+            self.context.swapchain_loader().acquire_next_image(
+                self.context.swapchain().swapchain,
+                20 * 1_000_000_000, // 20 second timeout
+                self.context.curr_image_acquire_semaphore(),
+                ash::vk::Fence::null(),
+            )
+        }
+    }
+
+    /// Recreates the swapchain at the surface's current size.
+    fn recreate_out_of_date_swapchain(&mut self) {
+        let surface_capabilities = unsafe {
+            self.context
+                .surface_loader()
+                .get_physical_device_surface_capabilities(
+                    self.context.inner.physical_device.physical_device,
+                    self.context.surface(),
+                )
+        };
+        let Ok(surface_capabilities) = surface_capabilities else {
+            return;
+        };
+        // A current extent of u32::MAX means the surface takes the size of the swapchain.
+        let extent = match surface_capabilities.current_extent.width {
+            u32::MAX => self.swapchain_size(),
+            _ => Vector2::new(
+                surface_capabilities.current_extent.width,
+                surface_capabilities.current_extent.height,
+            ),
+        };
+        match (NonZeroU32::new(extent.x), NonZeroU32::new(extent.y)) {
+            (Some(width), Some(height)) => {
+                self.resize_swapchain(winit::dpi::PhysicalSize { width, height }, false)
+            }
+            _ => self.minimize_swapchain(false),
+        }
+    }
+
     fn get_optimal_swapchain_format(
         surface_loader: &ash::khr::surface::Instance,
         surface: &ash::vk::SurfaceKHR,
@@ -998,7 +1292,13 @@ impl GraphicsBackendDevice for VulkanDevice {
                     .values(std::slice::from_ref(&wait_gpu_frame));
 
                 // TODO: Worry about timeout.
-                unsafe { self.context.device().wait_semaphores(&wait_info, u64::MAX) };
+                if let Err(err) =
+                    unsafe { self.context.device().wait_semaphores(&wait_info, u64::MAX) }
+                {
+                    if self.context.record_vk_error(err).is_none() {
+                        log::error!("Failed to wait on gpu frame {}, {}", wait_gpu_frame, err);
+                    }
+                }
             } else {
                 let signal_semaphore_info = ash::vk::SemaphoreSignalInfo::default()
                     .semaphore(self.context.inner.gpu_timeline_semaphore)
@@ -1011,12 +1311,30 @@ impl GraphicsBackendDevice for VulkanDevice {
             }
         }
 
+        // Nothing is rendered while minimized so don't wait on this frame later.
+        if self.is_minimized {
+            self.skipped_gpu_frames
+                .insert(self.context.curr_cpu_frame());
+        }
+
         // Free previously used events, cache slots, and descriptor owned uniform buffers..
-        self.context.resource_manager.retire_resources();
+        let mut memory_allocator = self.context.memory_allocator.write();
+        self.context
+            .resource_manager
+            .retire_resources(&mut memory_allocator);
+        drop(memory_allocator);
+
+        self.last_frame_stats = self.context.resource_manager.frame_stats().take();
     }
 
     fn swapchain_size(&self) -> Vector2<u32> {
-        let extent = self.context.swapchain.read().create_info.image_extent;
+        if self.context.is_headless() {
+            return self
+                .context
+                .get_image_info(&self.context.curr_offscreen_image())
+                .resolution_xy();
+        }
+        let extent = self.context.swapchain().create_info.image_extent;
         Vector2::new(extent.width, extent.height)
     }
 
@@ -1042,6 +1360,10 @@ impl GraphicsBackendDevice for VulkanDevice {
         self.context.create_image(create_info).unwrap()
     }
 
+    fn destroy_image(&mut self, image: ResourceId<Image>) {
+        self.context.destroy_image(image)
+    }
+
     fn get_image_info(&self, image: &ResourceId<Image>) -> GfxImageInfo {
         self.context.get_image_info(image)
     }
@@ -1054,6 +1376,10 @@ impl GraphicsBackendDevice for VulkanDevice {
         self.context.create_buffer(create_info).unwrap()
     }
 
+    fn destroy_buffer(&mut self, buffer: ResourceId<Buffer>) {
+        self.context.destroy_buffer(buffer)
+    }
+
     fn write_buffer(
         &mut self,
         buffer: &ResourceId<Buffer>,
@@ -1064,6 +1390,14 @@ impl GraphicsBackendDevice for VulkanDevice {
         unsafe { std::slice::from_raw_parts_mut(ptr, write_len as usize) }
     }
 
+    fn begin_write_group(&mut self, size: u64) -> GfxWriteGroupHandle {
+        self.context.begin_write_group(size)
+    }
+
+    fn end_write_group(&mut self, write_group: GfxWriteGroupHandle) {
+        self.context.end_write_group(write_group)
+    }
+
     fn create_sampler(&mut self, create_info: GfxSamplerCreateInfo) -> ResourceId<Sampler> {
         self.context.create_sampler(create_info)
     }
@@ -1077,24 +1411,45 @@ impl GraphicsBackendDevice for VulkanDevice {
     }
 
     fn acquire_swapchain_image(&mut self) -> anyhow::Result<ResourceId<Image>> {
-        let swapchain_loader = ash::khr::swapchain::Device::new(
-            &self.context.inner.instance,
-            &self.context.inner.device,
-        );
+        if let Some(err) = self.context.fatal_error() {
+            return Err(err.into());
+        }
+        if self.context.is_headless() {
+            return Ok(self.context.curr_offscreen_image());
+        }
+        if self.is_minimized {
+            return Err(GfxDeviceError::SwapchainOutOfDate.into());
+        }
+        if self.context.take_swapchain_out_of_date() {
+            debug!("Swapchain was out of date on present, recreating it.");
+            self.recreate_out_of_date_swapchain();
+        }
+
         let acquire_timer = Instant::now();
-        let (image_index, out_of_date) = match unsafe {
-            swapchain_loader.acquire_next_image(
-                self.context.swapchain().swapchain,
-                20 * 1_000_000_000, // 20 second timeout
-                self.context.curr_image_acquire_semaphore(),
-                ash::vk::Fence::null(),
-            )
-        } {
-            Ok((image_index, out_of_date)) => (image_index, out_of_date),
-            Err(err) => anyhow::bail!("Got error {}", err),
+        let mut acquire_result = self.try_acquire_swapchain_image();
+        // Routine while resizing, the swapchain is recreated to match the surface and the
+        // acquire is retried once before giving up on the frame.
+        if acquire_result == Err(ash::vk::Result::ERROR_OUT_OF_DATE_KHR) && !self.is_minimized {
+            debug!("Swapchain is out of date on acquire, recreating it.");
+            self.recreate_out_of_date_swapchain();
+            acquire_result = self.try_acquire_swapchain_image();
+        }
+        let (image_index, suboptimal) = match acquire_result {
+            Ok((image_index, suboptimal)) => (image_index, suboptimal),
+            Err(err) => {
+                // Nothing is rendered this frame so don't wait on it.
+                self.skipped_gpu_frames
+                    .insert(self.context.curr_cpu_frame());
+                return match self.context.record_vk_error(err) {
+                    Some(device_error) => Err(device_error.into()),
+                    None => Err(anyhow!("Got error {}", err)),
+                };
+            }
         };
-        if out_of_date {
-            debug!("Swapchain is out of date.");
+        // The image is still presentable, so use it and recreate the swapchain next frame.
+        if suboptimal {
+            debug!("Swapchain is suboptimal.");
+            self.context.mark_swapchain_out_of_date();
         }
         // debug!(
         //     "Took {}ms to acquire vk swapchain image.",
@@ -1103,9 +1458,6 @@ impl GraphicsBackendDevice for VulkanDevice {
         self.context
             .swapchain_image_index
             .store(image_index, std::sync::atomic::Ordering::Relaxed);
-        if out_of_date {
-            warn!("Swapchain image is out of date");
-        }
 
         let image_resource_id = *self
             .context
@@ -1121,7 +1473,11 @@ impl GraphicsBackendDevice for VulkanDevice {
         new_size: winit::dpi::PhysicalSize<NonZeroU32>,
         skip_frame: bool,
     ) {
-        let mut swapchain = self.context.swapchain.write();
+        // Offscreen images keep the size they were created with.
+        let Some(swapchain) = &self.context.swapchain else {
+            return;
+        };
+        let mut swapchain = swapchain.write();
 
         let surface_loader = self.context.surface_loader();
         let surface_capabilities = unsafe {
@@ -1141,10 +1497,35 @@ impl GraphicsBackendDevice for VulkanDevice {
                 surface_capabilities.min_image_extent.height,
                 surface_capabilities.max_image_extent.height,
             ));
+        // Some platforms report a max extent of zero while the window is minimized, a
+        // swapchain can't be created until it is restored.
+        if new_extent.width == 0 || new_extent.height == 0 {
+            self.is_minimized = true;
+            if skip_frame {
+                self.skipped_gpu_frames
+                    .insert(self.context.curr_cpu_frame());
+            }
+            return;
+        }
+
+        let present_mode = Self::get_optimal_present_mode(
+            &surface_loader,
+            &self.context.surface(),
+            &self.context.inner.physical_device,
+            &self.swapchain_info.present_mode,
+        )
+        .expect("Failed to get vulkan surface present modes.");
+        let min_image_count = Self::get_swapchain_image_count(
+            present_mode,
+            self.swapchain_info.triple_buffering,
+            &surface_capabilities,
+        );
 
         let new_swapchain_create_info = swapchain
             .create_info
             .image_extent(new_extent)
+            .present_mode(present_mode)
+            .min_image_count(min_image_count)
             .old_swapchain(swapchain.swapchain);
         log::debug!(
             "Resizing swapchain with image format {:?}",
@@ -1191,18 +1572,78 @@ impl GraphicsBackendDevice for VulkanDevice {
                 .destroy_image_borrowed(image_id);
         }
         drop(new_swapchain);
+        self.is_minimized = false;
+
+        if skip_frame {
+            self.skipped_gpu_frames
+                .insert(self.context.curr_cpu_frame());
+        }
+    }
 
+    fn minimize_swapchain(&mut self, skip_frame: bool) {
+        if self.context.is_headless() {
+            return;
+        }
+        self.is_minimized = true;
         if skip_frame {
             self.skipped_gpu_frames
                 .insert(self.context.curr_cpu_frame());
         }
     }
 
+    fn is_minimized(&self) -> bool {
+        self.is_minimized
+    }
+
+    fn set_swapchain_info(&mut self, swapchain_info: GfxSwapchainInfo) {
+        if self.swapchain_info == swapchain_info {
+            return;
+        }
+        self.swapchain_info = swapchain_info;
+        // Applied when the window is restored.
+        if self.is_minimized {
+            return;
+        }
+
+        let size = self.swapchain_size();
+        debug!(
+            "Recreating swapchain for present mode {:?}",
+            self.swapchain_info.present_mode
+        );
+        self.resize_swapchain(
+            winit::dpi::PhysicalSize {
+                width: NonZeroU32::new(size.x).unwrap(),
+                height: NonZeroU32::new(size.y).unwrap(),
+            },
+            false,
+        );
+    }
+
     fn get_buffer_info(&self, buffer: &ResourceId<Buffer>) -> GfxBufferInfo {
         let buf = self.context.get_buffer(*buffer);
         GfxBufferInfo { size: buf.size }
     }
 
+    fn create_readback_buffer(&mut self, create_info: GfxBufferCreateInfo) -> ResourceId<Buffer> {
+        self.context.create_readback_buffer(create_info).unwrap()
+    }
+
+    fn read_buffer(&self, buffer: &ResourceId<Buffer>, offset: u64, size: u64) -> &[u8] {
+        let buffer_size = self.context.get_buffer(*buffer).size;
+        assert!(
+            offset + size <= buffer_size,
+            "Tried to read {} bytes at offset {} from a buffer of size {}.",
+            size,
+            offset,
+            buffer_size
+        );
+        let ptr = self
+            .context
+            .get_buffer_mapped_ptr(*buffer)
+            .expect("Tried to read a buffer which wasn't created as a readback buffer.");
+        unsafe { std::slice::from_raw_parts(ptr.byte_add(offset as usize), size as usize) }
+    }
+
     fn device_info(&self) -> GfxDeviceInfo {
         let dev = self.context.physical_device();
         GfxDeviceInfo {
@@ -1213,13 +1654,36 @@ impl GraphicsBackendDevice for VulkanDevice {
                 .limits
                 .max_descriptor_set_storage_buffers
                 as u64,
+            max_image_dimension_2d: dev.properties.limits.max_image_dimension2_d,
         }
     }
+
+    fn curr_cpu_frame(&self) -> u64 {
+        self.context.curr_cpu_frame()
+    }
+
+    fn is_frame_finished(&self, cpu_frame: u64) -> bool {
+        // The gpu timeline semaphore is signaled with the cpu frame that was submitted.
+        self.context.curr_gpu_frame() >= cpu_frame
+    }
+
+    fn last_frame_stats(&self) -> GfxFrameStats {
+        self.last_frame_stats
+    }
+
+    fn memory_stats(&self) -> GfxMemoryStats {
+        self.context.memory_allocator.read().memory_stats()
+    }
+
+    fn fatal_error(&self) -> Option<GfxDeviceError> {
+        self.context.fatal_error()
+    }
 }
 
 struct VulkanAllocator {
     ctx: Arc<VulkanContextInner>,
-    shared_memory: Vec<VulkanSharedMemory>,
+    /// Freed chunks leave a `None` so the memory indices of other allocations remain valid.
+    shared_memory: Vec<Option<VulkanSharedMemory>>,
     dedicated_memory: Vec<VulkanMemory>,
 }
 
@@ -1319,14 +1783,19 @@ impl VulkanAllocator {
         let device_memory_size = Self::SHARED_MEMORY_CHUNK_SIZE;
         let (device_memory, memory_type) =
             self.allocate_device_memory(device_memory_size, memory_property_flags)?;
-        self.shared_memory.push(VulkanSharedMemory {
+        let shared_memory = VulkanSharedMemory {
             memory: device_memory,
             memory_property_flags: memory_type.property_flags,
             allocator: AllocatorTree::new_root(device_memory_size),
             free_size_remaining: device_memory_size,
             active_allocations: 0,
-        });
+        };
 
+        if let Some(memory_index) = self.shared_memory.iter().position(Option::is_none) {
+            self.shared_memory[memory_index] = Some(shared_memory);
+            return Ok(memory_index as VulkanMemoryIndex);
+        }
+        self.shared_memory.push(Some(shared_memory));
         Ok(self.shared_memory.len() as VulkanMemoryIndex - 1)
     }
 
@@ -1338,6 +1807,9 @@ impl VulkanAllocator {
     ) -> anyhow::Result<VulkanMemoryIndex> {
         // Check for available shared memory with the sample memory properties.
         for (memory_index, shared_memory) in self.shared_memory.iter().enumerate() {
+            let Some(shared_memory) = shared_memory else {
+                continue;
+            };
             if shared_memory
                 .memory_property_flags
                 .contains(memory_property_flags)
@@ -1399,9 +1871,8 @@ impl VulkanAllocator {
                     alignment as u64,
                     memory_property_flags,
                 )?;
-                let mut shared_memory = self
-                    .shared_memory
-                    .get_mut(shared_memory_index as usize)
+                let mut shared_memory = self.shared_memory[shared_memory_index as usize]
+                    .as_mut()
                     .unwrap();
                 let shared_memory_traversal = match shared_memory
                     .allocator
@@ -1414,9 +1885,8 @@ impl VulkanAllocator {
                             alignment as u64,
                             memory_property_flags,
                         )?;
-                        shared_memory = self
-                            .shared_memory
-                            .get_mut(shared_memory_index as usize)
+                        shared_memory = self.shared_memory[shared_memory_index as usize]
+                            .as_mut()
                             .unwrap();
 
                         shared_memory
@@ -1497,12 +1967,102 @@ impl VulkanAllocator {
         Ok(allocation)
     }
 
+    fn free_memory(&mut self, allocation: &VulkanMemoryAllocation) {
+        match &allocation.traversal {
+            Some(traversal) => {
+                let shared_memory = self
+                    .shared_memory
+                    .get_mut(allocation.memory_index as usize)
+                    .and_then(Option::as_mut)
+                    .expect("Tried to free an allocation but its shared memory doesn't exist.");
+                shared_memory.allocator.free(traversal);
+                shared_memory.free_size_remaining += traversal.length_bytes();
+                shared_memory.active_allocations -= 1;
+                if shared_memory.active_allocations == 0 {
+                    self.free_empty_shared_memory(allocation.memory_index);
+                }
+            }
+            None => {
+                // Dedicated memory stays in place so the indices of other dedicated allocations
+                // remain valid, it is freed once the allocator is dropped.
+                log::warn!(
+                    "Freeing dedicated memory isn't supported yet, memory index {} will leak until shutdown.",
+                    allocation.memory_index
+                );
+            }
+        }
+    }
+
+    /// Frees the empty chunk at `memory_index` if another chunk of the same memory type has
+    /// space left, so the next allocation of that type doesn't immediately allocate it again.
+    fn free_empty_shared_memory(&mut self, memory_index: VulkanMemoryIndex) {
+        let empty_memory = self.shared_memory[memory_index as usize].as_ref().unwrap();
+        let has_spare_capacity = self
+            .shared_memory
+            .iter()
+            .enumerate()
+            .filter_map(|(i, shared_memory)| Some((i, shared_memory.as_ref()?)))
+            .any(|(i, shared_memory)| {
+                i != memory_index as usize
+                    && shared_memory.memory_property_flags == empty_memory.memory_property_flags
+                    && shared_memory.free_size_remaining > 0
+            });
+        if !has_spare_capacity {
+            return;
+        }
+
+        let empty_memory = self.shared_memory[memory_index as usize].take().unwrap();
+        debug!(
+            "Freeing empty shared memory index {} of {} bytes",
+            memory_index, empty_memory.memory.size
+        );
+        unsafe {
+            self.ctx
+                .device
+                .free_memory(empty_memory.memory.device_memory, None)
+        };
+    }
+
+    fn memory_stats(&self) -> GfxMemoryStats {
+        let shared_pools = self
+            .shared_memory
+            .iter()
+            .flatten()
+            .map(|shared_memory| {
+                let (largest_free_block, free_block_count) = shared_memory.allocator.free_blocks();
+                GfxMemoryPoolStats {
+                    size: shared_memory.memory.size,
+                    used_bytes: shared_memory.memory.size - shared_memory.free_size_remaining,
+                    active_allocations: shared_memory.active_allocations,
+                    largest_free_block,
+                    free_block_count,
+                }
+            })
+            .collect::<Vec<_>>();
+        let dedicated_bytes = self
+            .dedicated_memory
+            .iter()
+            .map(|memory| memory.size)
+            .sum::<u64>();
+
+        GfxMemoryStats {
+            allocated_bytes: shared_pools.iter().map(|pool| pool.size).sum::<u64>()
+                + dedicated_bytes,
+            used_bytes: shared_pools.iter().map(|pool| pool.used_bytes).sum::<u64>()
+                + dedicated_bytes,
+            dedicated_allocation_count: self.dedicated_memory.len() as u32,
+            dedicated_bytes,
+            shared_pools,
+        }
+    }
+
     fn get_allocation_info(&self, allocation: &VulkanMemoryAllocation) -> VulkanAllocationInfo {
         match &allocation.traversal {
             Some(traversal) => {
                 let shared_memory = self
                     .shared_memory
                     .get(allocation.memory_index as usize)
+                    .and_then(Option::as_ref)
                     .expect("Tried to get allocation info but allocation was freed/invalid.");
 
                 VulkanAllocationInfo {
@@ -1538,6 +2098,7 @@ impl Drop for VulkanAllocator {
         for memory in self
             .shared_memory
             .iter()
+            .flatten()
             .map(|shared_memory| &shared_memory.memory)
             .chain(self.dedicated_memory.iter())
         {
@@ -1591,6 +2152,49 @@ pub struct VulkanResourceManager {
     copy_tasks: parking_lot::RwLock<
         HashMap<FreeListHandle<VulkanStagingBuffer>, Vec<VulkanStagingCopyTask>>,
     >,
+    /// Write groups which have reserved staging memory but haven't enqueued their copies yet,
+    /// shared with the handles which decrement it when dropped.
+    open_write_groups: Arc<AtomicU32>,
+
+    /// Resources destroyed on a frame index, destroyed for real once the gpu has finished that
+    /// frame since frames in flight may still be using them.
+    destroy_frame_queue: Vec<parking_lot::RwLock<Vec<VulkanRetiredResource>>>,
+
+    frame_stats: VulkanFrameStats,
+}
+
+/// Counters for `GfxFrameStats` accumulated while recording the current cpu frame.
+#[derive(Default)]
+pub struct VulkanFrameStats {
+    pub dispatches: AtomicU32,
+    pub draws: AtomicU32,
+    pub descriptor_set_binds: AtomicU32,
+    pub descriptor_set_writes: AtomicU32,
+    pub buffer_writes: AtomicU32,
+    pub image_writes: AtomicU32,
+    pub staging_bytes: AtomicU64,
+}
+
+impl VulkanFrameStats {
+    pub fn add(counter: &AtomicU32, count: u32) {
+        counter.fetch_add(count, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Returns the accumulated stats, resetting the counters for the next frame.
+    fn take(&self) -> GfxFrameStats {
+        let take = |counter: &AtomicU32| counter.swap(0, std::sync::atomic::Ordering::Relaxed);
+        GfxFrameStats {
+            dispatches: take(&self.dispatches),
+            draws: take(&self.draws),
+            descriptor_set_binds: take(&self.descriptor_set_binds),
+            descriptor_set_writes: take(&self.descriptor_set_writes),
+            buffer_writes: take(&self.buffer_writes),
+            image_writes: take(&self.image_writes),
+            staging_bytes: self
+                .staging_bytes
+                .swap(0, std::sync::atomic::Ordering::Relaxed),
+        }
+    }
 }
 
 struct VulkanStagingBuffer {
@@ -1679,6 +2283,11 @@ impl VulkanDescriptorSetGroup {
 struct VulkanDescriptorSet {
     descriptor_set: ash::vk::DescriptorSet,
     usage_score: VulkanUsageScore,
+    /// The owning group and frame, along with the bindings keying this set in that frame's map,
+    /// so the GC can remove the set's reference.
+    set_binding: ShaderSetBinding,
+    frame_index: u32,
+    bindings: VulkanShaderSetData,
 }
 
 #[derive(Clone)]
@@ -1702,6 +2311,12 @@ pub struct VulkanRasterPipeline {
     pub pipeline: ash::vk::Pipeline,
 }
 
+enum VulkanRetiredResource {
+    Pipeline(ash::vk::Pipeline),
+    Buffer(VulkanBuffer),
+    Image(VulkanImage),
+}
+
 #[derive(Clone)]
 pub struct VulkanBuffer {
     pub buffer: ash::vk::Buffer,
@@ -1720,7 +2335,7 @@ impl VulkanImageRef {
     pub fn full_subresource_range(&self) -> ash::vk::ImageSubresourceRange {
         ash::vk::ImageSubresourceRange::default()
             .base_array_layer(0)
-            .layer_count(1)
+            .layer_count(self.info.image_type.layer_count())
             .base_mip_level(0)
             .level_count(1)
             .aspect_mask(self.info.image_type.into())
@@ -1729,7 +2344,7 @@ impl VulkanImageRef {
     pub fn full_subresource_layer(&self) -> ash::vk::ImageSubresourceLayers {
         ash::vk::ImageSubresourceLayers::default()
             .base_array_layer(0)
-            .layer_count(1)
+            .layer_count(self.info.image_type.layer_count())
             .mip_level(0)
             .aspect_mask(self.info.image_type.into())
     }
@@ -1756,9 +2371,10 @@ impl From<GfxSamplerCreateInfo> for ash::vk::SamplerCreateInfo<'_> {
             .address_mode_u(value.address_mode.into())
             .address_mode_v(value.address_mode.into())
             .address_mode_w(value.address_mode.into())
-            .min_lod(1.0)
-            .max_lod(1.0)
-            .anisotropy_enable(false)
+            .min_lod(value.lod_range.0)
+            .max_lod(value.lod_range.1)
+            .anisotropy_enable(value.max_anisotropy.is_some())
+            .max_anisotropy(value.max_anisotropy.unwrap_or(1.0))
     }
 }
 
@@ -1814,11 +2430,8 @@ pub struct VulkanImageInfo {
 impl VulkanImageInfo {
     pub fn pixel_byte_size(&self) -> usize {
         match self.format {
-            ash::vk::Format::R8G8B8A8_UINT
-            | ash::vk::Format::R8G8B8A8_SINT
-            | ash::vk::Format::R8G8B8A8_SRGB
-            | ash::vk::Format::R8G8B8A8_UNORM => 4,
-            _ => todo!("Support image format"),
+            ash::vk::Format::R8G8B8A8_UINT | ash::vk::Format::R8G8B8A8_SINT => 4,
+            format => GfxImageFormat::from(format).pixel_byte_size() as usize,
         }
     }
 }
@@ -1948,6 +2561,7 @@ impl From<ash::vk::Format> for GfxImageFormat {
         match value {
             ash::vk::Format::R16_SFLOAT => GfxImageFormat::R16Float,
             ash::vk::Format::R8G8B8A8_UNORM => GfxImageFormat::Rgba8Unorm,
+            ash::vk::Format::R8G8B8A8_SRGB => GfxImageFormat::Rgba8Srgb,
             ash::vk::Format::D16_UNORM => GfxImageFormat::D16Unorm,
             ash::vk::Format::D24_UNORM_S8_UINT => GfxImageFormat::D24UnormS8Uint,
             ash::vk::Format::D32_SFLOAT => GfxImageFormat::D32Float,
@@ -1980,6 +2594,7 @@ enum VulkanStagingCopyTask {
         src_offset: u64,
         image_offset: ash::vk::Offset3D,
         image_extent: ash::vk::Extent3D,
+        layer: u32,
     },
 }
 
@@ -2057,9 +2672,20 @@ impl VulkanResourceManager {
                 .collect(),
 
             copy_tasks: parking_lot::RwLock::new(HashMap::new()),
+            open_write_groups: Arc::new(AtomicU32::new(0)),
+
+            destroy_frame_queue: (0..ctx.frames_in_flight)
+                .map(|_| parking_lot::RwLock::new(Vec::new()))
+                .collect(),
+
+            frame_stats: VulkanFrameStats::default(),
         }
     }
 
+    pub fn frame_stats(&self) -> &VulkanFrameStats {
+        &self.frame_stats
+    }
+
     fn next_resource_id<T: 'static>(&self) -> ResourceId<T> {
         let resource_id = ResourceId::new(
             self.current_resource_id
@@ -2068,7 +2694,68 @@ impl VulkanResourceManager {
         resource_id
     }
 
-    fn retire_resources(&self) {
+    fn destroy_buffer(&self, allocator: &mut VulkanAllocator, buffer: ResourceId<Buffer>) {
+        let buffer = self
+            .owned_buffers
+            .write()
+            .remove(&buffer)
+            .expect("Tried to destroy a vulkan buffer that doesn't exist.");
+        unsafe { self.ctx.device.destroy_buffer(buffer.buffer, None) };
+        allocator.free_memory(&buffer.allocation);
+    }
+
+    /// Destroys the buffer once the frames in flight which may be using it have finished.
+    fn queue_destroy_buffer(&self, buffer: ResourceId<Buffer>) {
+        let Some(buffer) = self.owned_buffers.write().remove(&buffer) else {
+            warn!(
+                "Tried to destroy a vulkan buffer that doesn't exist, {:?}.",
+                buffer
+            );
+            return;
+        };
+        self.destroy_frame_queue[self.ctx.curr_cpu_frame_index() as usize]
+            .write()
+            .push(VulkanRetiredResource::Buffer(buffer));
+    }
+
+    /// Destroys the image once the frames in flight which may be using it have finished.
+    fn queue_destroy_image(&self, image: ResourceId<Image>) {
+        let Some(image) = self.owned_images.write().remove(&image) else {
+            warn!(
+                "Tried to destroy a vulkan image that doesn't exist, {:?}.",
+                image
+            );
+            return;
+        };
+        self.destroy_frame_queue[self.ctx.curr_cpu_frame_index() as usize]
+            .write()
+            .push(VulkanRetiredResource::Image(image));
+    }
+
+    fn destroy_retired_resource(
+        &self,
+        allocator: &mut VulkanAllocator,
+        resource: VulkanRetiredResource,
+    ) {
+        match resource {
+            VulkanRetiredResource::Pipeline(pipeline) => unsafe {
+                self.ctx.device.destroy_pipeline(pipeline, None)
+            },
+            VulkanRetiredResource::Buffer(buffer) => {
+                unsafe { self.ctx.device.destroy_buffer(buffer.buffer, None) };
+                allocator.free_memory(&buffer.allocation);
+            }
+            VulkanRetiredResource::Image(image) => {
+                if let Some(image_view) = image.view {
+                    unsafe { self.ctx.device.destroy_image_view(image_view, None) };
+                }
+                unsafe { self.ctx.device.destroy_image(image.image, None) };
+                allocator.free_memory(&image.allocation);
+            }
+        }
+    }
+
+    fn retire_resources(&self, allocator: &mut VulkanAllocator) {
         let curr_gpu_frame = self.ctx.curr_gpu_frame();
         let curr_cpu_frame = self.ctx.curr_cpu_frame();
         // This is called after we wait for our gpu timeline semaphore n - 2 so
@@ -2095,54 +2782,81 @@ impl VulkanResourceManager {
                 let staging_buffer = staging_buffers.get_mut(staging_buffer_handle).unwrap();
                 staging_buffer.curr_write_pointer = 0;
             }
+
+            for resource in self.destroy_frame_queue[frame_index as usize]
+                .write()
+                .drain(..)
+            {
+                self.destroy_retired_resource(allocator, resource);
+            }
         }
 
         // Garbage collect descriptor sets and reset
         // descriptor set global uniform buffer counters.
+        let mut descriptor_set_groups = self.descriptor_set_groups.write();
         let mut descriptor_sets = self.descriptor_sets.write();
+        let mut cache_slots = self.cache_slots.write();
+        // A set in a cache slot may still be bound later in the frame regardless of its score.
+        let cached_handles = cache_slots
+            .values()
+            .map(|(_, handle)| *handle)
+            .collect::<HashSet<_>>();
         let mut to_remove_handles = Vec::new();
         for (handle, descriptor_set) in descriptor_sets.iter_with_handle_mut() {
-            if descriptor_set.usage_score.should_delete() {
+            if descriptor_set.usage_score.should_delete() && !cached_handles.contains(&handle) {
                 to_remove_handles.push(handle);
             }
             descriptor_set.usage_score.increment_unused();
         }
 
-        let mut descriptor_set_groups = self.descriptor_set_groups.write();
         for set_group in descriptor_set_groups.values_mut() {
             set_group.binding_set_maps[self.ctx.curr_cpu_frame_index() as usize]
                 .uniform_buffer_counter = 0;
         }
 
+        let mut to_trim_frame_groups = HashSet::new();
         for to_remove_handle in to_remove_handles {
-            // TODO: Make descriptor reference freeing more efficient, though it should be rare to
-            // gc, we don't want it to take up much of our frame at all.
-            for set_group in descriptor_set_groups.values_mut() {
-                for binding_set_map in &mut set_group.binding_set_maps {
-                    let mut to_remove_binding_data = None;
-                    for (binding_data, handle) in binding_set_map.descriptor_sets.iter() {
-                        if handle == &to_remove_handle {
-                            to_remove_binding_data = Some(binding_data.clone());
-                            break;
-                        }
-                    }
-                    if let Some(binding_data) = to_remove_binding_data {
-                        binding_set_map.descriptor_sets.remove(&binding_data);
-                    }
-                }
-            }
-
             log::debug!("Removing descriptor handle {:?}", to_remove_handle);
             let descriptor_set = descriptor_sets.remove(to_remove_handle);
+            let set_group = descriptor_set_groups
+                .get_mut(&descriptor_set.set_binding)
+                .expect("Descriptor set should belong to an existing descriptor set group.");
+            set_group.binding_set_maps[descriptor_set.frame_index as usize]
+                .descriptor_sets
+                .remove(&descriptor_set.bindings);
             unsafe {
                 self.ctx
                     .device
                     .free_descriptor_sets(self.descriptor_pool, &[descriptor_set.descriptor_set])
             };
+            to_trim_frame_groups.insert((descriptor_set.set_binding, descriptor_set.frame_index));
+        }
+
+        // Free trailing uniform buffers no longer referenced by any remaining set, buffers are
+        // only used through their sets so the gpu is done with them as well. Only trailing
+        // buffers are freed since the per frame counter indexes into the buffers.
+        for (set_binding, frame_index) in to_trim_frame_groups {
+            let frame_group = &mut descriptor_set_groups
+                .get_mut(&set_binding)
+                .unwrap()
+                .binding_set_maps[frame_index as usize];
+            while let Some(uniform_buffer) = frame_group.uniform_buffers.last().copied() {
+                let is_referenced = frame_group.descriptor_sets.keys().any(|set_data| {
+                    set_data.bindings.iter().any(|(_, binding)| {
+                        matches!(binding, Binding::UniformBuffer { buffer } if *buffer == uniform_buffer)
+                    })
+                });
+                if is_referenced {
+                    break;
+                }
+
+                frame_group.uniform_buffers.pop();
+                self.destroy_buffer(allocator, uniform_buffer);
+            }
         }
 
         // Wipe cache slots for the new frame.
-        self.cache_slots.write().clear();
+        cache_slots.clear();
     }
 
     /// Returns a vkEvent that is valid for the current cpu recording next gpu frame.
@@ -2481,6 +3195,16 @@ impl VulkanResourceManager {
         }
         .map_err(|(_, err)| anyhow!("Failed to create vulkan raster pipeline. Error: {}", err))?
         .remove(0);
+        self.ctx.set_object_name(
+            raster_pipeline,
+            &format!(
+                "{}::{}, {}::{}",
+                vertex_shader.module_name(),
+                vertex_shader.entry_point_name(),
+                fragment_shader.module_name(),
+                fragment_shader.entry_point_name()
+            ),
+        );
 
         unsafe {
             self.ctx
@@ -2544,6 +3268,10 @@ impl VulkanResourceManager {
         }
         .map_err(|_| anyhow!("Failed to create vulkan compute pipeline."))?
         .remove(0);
+        self.ctx.set_object_name(
+            compute_pipeline,
+            &format!("{}::{}", shader.module_name(), shader.entry_point_name()),
+        );
 
         unsafe { self.ctx.device.destroy_shader_module(shader_module, None) };
 
@@ -2663,6 +3391,8 @@ impl VulkanResourceManager {
                         .expect("Failed to create global descriptor set")
                         .remove(0);
                     debug!("Creating descriptor set for set `{}`.", set_binding.name);
+                    self.ctx
+                        .set_object_name(new_set, &format!("set_{}", set_binding.name));
 
                     for (binding_idx, binding) in bindings.bindings.iter() {
                         match binding {
@@ -2834,6 +3564,9 @@ impl VulkanResourceManager {
                     let set_handle = descriptor_sets.push(VulkanDescriptorSet {
                         descriptor_set: new_set,
                         usage_score: VulkanUsageScore::zero(),
+                        set_binding: set_binding.clone(),
+                        frame_index: self.ctx.curr_cpu_frame_index(),
+                        bindings: bindings.clone(),
                     });
                     set_handle
                 });
@@ -2872,6 +3605,10 @@ impl VulkanResourceManager {
                 })
                 .collect::<Vec<_>>();
 
+            VulkanFrameStats::add(
+                &self.frame_stats.descriptor_set_writes,
+                vk_descriptor_set_writes.len() as u32,
+            );
             unsafe {
                 self.ctx
                     .device
@@ -3142,6 +3879,17 @@ impl VulkanResourceManager {
             create_info.name, create_info.size, mapped_ptr
         );
         anyhow::ensure!(create_info.size > 0);
+        // Buffers are shared with the transfer queue so staging copies don't need a queue
+        // family ownership transfer, the transfer and main submits are ordered by semaphores.
+        let concurrent_queue_family_indices =
+            self.ctx
+                .transfer_queue_family_index
+                .map(|transfer_queue_family_index| {
+                    [
+                        self.ctx.main_queue_family_index,
+                        transfer_queue_family_index,
+                    ]
+                });
         let vk_create_info = ash::vk::BufferCreateInfo::default()
             .size(create_info.size)
             .usage(
@@ -3152,19 +3900,15 @@ impl VulkanResourceManager {
                     | ash::vk::BufferUsageFlags::VERTEX_BUFFER
                     | ash::vk::BufferUsageFlags::INDEX_BUFFER
                     | ash::vk::BufferUsageFlags::INDIRECT_BUFFER,
-            )
-            .sharing_mode(ash::vk::SharingMode::EXCLUSIVE);
+            );
+        let vk_create_info = match &concurrent_queue_family_indices {
+            Some(queue_family_indices) => vk_create_info
+                .sharing_mode(ash::vk::SharingMode::CONCURRENT)
+                .queue_family_indices(queue_family_indices),
+            None => vk_create_info.sharing_mode(ash::vk::SharingMode::EXCLUSIVE),
+        };
         let buffer = unsafe { self.ctx.device.create_buffer(&vk_create_info, None) }?;
-        if let Some(debug_utils) = &self.ctx.debug_utils_device {
-            let c_name = CString::new(create_info.name.clone()).unwrap();
-            unsafe {
-                debug_utils.set_debug_utils_object_name(
-                    &ash::vk::DebugUtilsObjectNameInfoEXT::default()
-                        .object_handle(buffer)
-                        .object_name(&c_name),
-                )
-            };
-        }
+        self.ctx.set_object_name(buffer, &create_info.name);
 
         let buffer_memory_requirements =
             unsafe { self.ctx.device.get_buffer_memory_requirements(buffer) };
@@ -3250,16 +3994,16 @@ impl VulkanResourceManager {
         new_handle
     }
 
-    fn write_buffer(
+    /// Reserves `size` bytes in a staging buffer, returning the staging buffer along with the
+    /// offset and mapped pointer of the reserved bytes.
+    fn reserve_staging(
         &self,
         allocator: &mut VulkanAllocator,
-        dst_buffer: &ResourceId<Buffer>,
-        dst_offset: u64,
-        write_len: u64,
-    ) -> *mut u8 {
+        size: u64,
+    ) -> (FreeListHandle<VulkanStagingBuffer>, u64, *mut u8) {
         // TODO: only write staging buffers rwlock list here and pass it into
         // `get_or_create_staging_buffer`.
-        let staging_buffer_index = self.get_or_create_staging_buffer(allocator, write_len);
+        let staging_buffer_index = self.get_or_create_staging_buffer(allocator, size);
 
         let mut staging_buffers = self.staging_buffers.write();
         let staging_buffer = staging_buffers.get_mut(staging_buffer_index).unwrap();
@@ -3271,21 +4015,87 @@ impl VulkanResourceManager {
         };
 
         let src_offset = staging_buffer.curr_write_pointer;
-        staging_buffer.curr_write_pointer += write_len;
+        staging_buffer.curr_write_pointer += size;
         assert!(staging_buffer.curr_write_pointer <= staging_buffer.size);
 
-        let mut copy_tasks = self.copy_tasks.write();
-        let mut staging_buffer_copy_tasks = copy_tasks.entry(staging_buffer_index).or_default();
-        staging_buffer_copy_tasks.push(VulkanStagingCopyTask::Buffer {
-            dst_buffer: *dst_buffer,
-            src_offset,
-            dst_offset,
-            copy_size: write_len,
-        });
+        (staging_buffer_index, src_offset, write_ptr)
+    }
+
+    fn push_copy_tasks(
+        &self,
+        staging_buffer_index: FreeListHandle<VulkanStagingBuffer>,
+        tasks: impl IntoIterator<Item = VulkanStagingCopyTask>,
+    ) {
+        self.copy_tasks
+            .write()
+            .entry(staging_buffer_index)
+            .or_default()
+            .extend(tasks);
+    }
+
+    fn write_buffer(
+        &self,
+        allocator: &mut VulkanAllocator,
+        dst_buffer: &ResourceId<Buffer>,
+        dst_offset: u64,
+        write_len: u64,
+    ) -> *mut u8 {
+        let (staging_buffer_index, src_offset, write_ptr) =
+            self.reserve_staging(allocator, write_len);
+        self.push_copy_tasks(
+            staging_buffer_index,
+            [VulkanStagingCopyTask::Buffer {
+                dst_buffer: *dst_buffer,
+                src_offset,
+                dst_offset,
+                copy_size: write_len,
+            }],
+        );
+        VulkanFrameStats::add(&self.frame_stats.buffer_writes, 1);
+        self.frame_stats
+            .staging_bytes
+            .fetch_add(write_len, std::sync::atomic::Ordering::Relaxed);
 
         write_ptr
     }
 
+    fn begin_write_group(&self, allocator: &mut VulkanAllocator, size: u64) -> GfxWriteGroupHandle {
+        let (staging_buffer_index, staging_offset, ptr) = self.reserve_staging(allocator, size);
+        self.open_write_groups
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        GfxWriteGroupHandle::new(
+            staging_buffer_index.as_untyped(),
+            staging_offset,
+            ptr,
+            size,
+            self.open_write_groups.clone(),
+        )
+    }
+
+    /// The handle is dropped once its copies are enqueued which closes the group.
+    fn end_write_group(&self, write_group: GfxWriteGroupHandle) {
+        VulkanFrameStats::add(
+            &self.frame_stats.buffer_writes,
+            write_group.writes().len() as u32,
+        );
+        self.frame_stats.staging_bytes.fetch_add(
+            write_group.used_size(),
+            std::sync::atomic::Ordering::Relaxed,
+        );
+        self.push_copy_tasks(
+            write_group.staging().as_typed(),
+            write_group
+                .writes()
+                .iter()
+                .map(|write| VulkanStagingCopyTask::Buffer {
+                    dst_buffer: write.buffer,
+                    src_offset: write.src_offset,
+                    dst_offset: write.dst_offset,
+                    copy_size: write.size,
+                }),
+        );
+    }
+
     fn write_image(&self, allocator: &mut VulkanAllocator, info: GfxImageWrite) {
         let image_info = self.get_image(info.image);
         assert!(
@@ -3299,6 +4109,12 @@ impl VulkanResourceManager {
             image_info.info.extent,
             info.extent,
         );
+        assert!(
+            info.layer < image_info.info.image_type.layer_count(),
+            "Tried to write to layer {} of an image with {} layers.",
+            info.layer,
+            image_info.info.image_type.layer_count()
+        );
         let write_len =
             image_info.info.pixel_byte_size() as u64 * info.extent.x as u64 * info.extent.y as u64;
         assert_eq!(
@@ -3312,39 +4128,30 @@ impl VulkanResourceManager {
             image_info.info.format
         );
 
-        // TODO: only write staging buffers rwlock list here and pass it into
-        // `get_or_create_staging_buffer`.
-        let staging_buffer_index = self.get_or_create_staging_buffer(allocator, write_len);
-
-        let mut staging_buffers = self.staging_buffers.write();
-        let staging_buffer = staging_buffers.get_mut(staging_buffer_index).unwrap();
-
-        let write_ptr = unsafe {
-            staging_buffer
-                .mapped_pointer
-                .byte_add(staging_buffer.curr_write_pointer as usize)
-        };
-
-        let src_offset = staging_buffer.curr_write_pointer;
-        staging_buffer.curr_write_pointer += write_len;
-        assert!(staging_buffer.curr_write_pointer < staging_buffer.size);
-
-        let mut copy_tasks = self.copy_tasks.write();
-        let mut staging_buffer_copy_tasks = copy_tasks.entry(staging_buffer_index).or_default();
-        staging_buffer_copy_tasks.push(VulkanStagingCopyTask::Image {
-            dst_image: info.image,
-            src_offset,
-            image_offset: ash::vk::Offset3D {
-                x: info.offset.x as i32,
-                y: info.offset.y as i32,
-                z: 0,
-            },
-            image_extent: ash::vk::Extent3D {
-                width: info.extent.x,
-                height: info.extent.y,
-                depth: 1,
-            },
-        });
+        let (staging_buffer_index, src_offset, write_ptr) =
+            self.reserve_staging(allocator, write_len);
+        VulkanFrameStats::add(&self.frame_stats.image_writes, 1);
+        self.frame_stats
+            .staging_bytes
+            .fetch_add(write_len, std::sync::atomic::Ordering::Relaxed);
+        self.push_copy_tasks(
+            staging_buffer_index,
+            [VulkanStagingCopyTask::Image {
+                dst_image: info.image,
+                src_offset,
+                image_offset: ash::vk::Offset3D {
+                    x: info.offset.x as i32,
+                    y: info.offset.y as i32,
+                    z: 0,
+                },
+                image_extent: ash::vk::Extent3D {
+                    width: info.extent.x,
+                    height: info.extent.y,
+                    depth: 1,
+                },
+                layer: info.layer,
+            }],
+        );
 
         unsafe { write_ptr.copy_from_nonoverlapping(info.data.as_ptr(), info.data.len()) };
     }
@@ -3367,15 +4174,33 @@ impl VulkanResourceManager {
         buffer_info.clone()
     }
 
-    // TODO: buffer write group api with write group handle so we can write buffers on multiple
-    // threads.
-    fn record_buffer_writes(&self, recorder: &mut VulkanRecorder) {
+    /// Records the staging copies onto `recorder`. When a `transfer_recorder` for the transfer
+    /// queue is given, buffer copies are recorded on it instead. Buffers use concurrent sharing
+    /// so there is no ownership transfer, the transfer submission must wait on the previous
+    /// frame and the main queue submission must then wait on the transfer submission. Image
+    /// copies always stay on the main queue since image layouts are tracked by the main queue's
+    /// recorders.
+    fn record_buffer_writes(
+        &self,
+        recorder: &mut VulkanRecorder,
+        transfer_recorder: Option<&VulkanRecorder>,
+    ) {
+        assert_eq!(
+            self.open_write_groups
+                .load(std::sync::atomic::Ordering::Relaxed),
+            0,
+            "Write groups must be ended before the frame's buffer writes are recorded."
+        );
         let staging_buffers = self.staging_buffers.read();
         let mut staging_buffer_gpu_timeline =
             self.staging_buffer_gpu_timeline[self.ctx.curr_cpu_frame_index() as usize].write();
         let mut in_use_staging_buffers = self.in_use_staging_buffers.write();
         let mut copy_tasks = self.copy_tasks.write();
 
+        let buffer_copy_command_buffer = transfer_recorder
+            .map_or(recorder.command_buffer(), |transfer_recorder| {
+                transfer_recorder.command_buffer()
+            });
         let mut buffer_barriers = Vec::new();
 
         // Transition image
@@ -3384,13 +4209,15 @@ impl VulkanResourceManager {
             for task in copy_tasks {
                 match task {
                     VulkanStagingCopyTask::Buffer { .. } => {}
-                    VulkanStagingCopyTask::Image {
-                        dst_image,
-                        src_offset,
-                        image_offset,
-                        image_extent,
-                    } => {
+                    VulkanStagingCopyTask::Image { dst_image, .. } => {
                         let image_info = self.get_image(*dst_image);
+                        // Writes to multiple layers of the same image share the barrier.
+                        if image_barriers
+                            .iter()
+                            .any(|barrier| barrier.image == image_info.image)
+                        {
+                            continue;
+                        }
                         image_barriers.push(
                             ash::vk::ImageMemoryBarrier::default()
                                 .image(image_info.image)
@@ -3448,6 +4275,7 @@ impl VulkanResourceManager {
                         src_offset,
                         image_offset,
                         image_extent,
+                        layer,
                     } => {
                         let dst_image_info = self.get_image(*dst_image);
                         let mut vec = dst_image_copy_map.entry(dst_image_info.image).or_default();
@@ -3455,7 +4283,12 @@ impl VulkanResourceManager {
                             ash::vk::BufferImageCopy::default()
                                 .image_offset(*image_offset)
                                 .image_extent(*image_extent)
-                                .image_subresource(dst_image_info.full_subresource_layer())
+                                .image_subresource(
+                                    dst_image_info
+                                        .full_subresource_layer()
+                                        .base_array_layer(*layer)
+                                        .layer_count(1),
+                                )
                                 .buffer_offset(*src_offset)
                                 // TODO: Set to zero for now so that the buffer data is interpreted
                                 // as tightly packed.
@@ -3483,7 +4316,7 @@ impl VulkanResourceManager {
 
                 unsafe {
                     self.ctx.device.cmd_copy_buffer(
-                        recorder.command_buffer(),
+                        buffer_copy_command_buffer,
                         src_buffer.buffer,
                         dst_buffer.buffer,
                         &regions,
@@ -3504,6 +4337,11 @@ impl VulkanResourceManager {
             }
         }
 
+        // Copies on the transfer queue are made visible to the main queue by the semaphore it
+        // waits on, buffers are created with concurrent sharing so no ownership transfer is needed.
+        if transfer_recorder.is_some() && self.ctx.transfer_queue_family_index.is_some() {
+            return;
+        }
         unsafe {
             self.ctx.device.cmd_pipeline_barrier(
                 recorder.command_buffer(),
@@ -3525,6 +4363,13 @@ impl VulkanResourceManager {
     ) -> anyhow::Result<ResourceId<Image>> {
         debug!("creating vulkan owned image {}.", create_info.name);
         anyhow::ensure!(create_info.extent.x > 0 && create_info.extent.y > 0);
+        anyhow::ensure!(
+            create_info.image_type != GfxImageType::Cube
+                || create_info.extent.x == create_info.extent.y,
+            "Cubemap faces must be square, got extent {:?}.",
+            create_info.extent
+        );
+        let image_name = create_info.name;
         let image_info = VulkanImageInfo {
             image_type: create_info.image_type,
             format: create_info.format.into(),
@@ -3563,9 +4408,14 @@ impl VulkanResourceManager {
                     .depth(1),
             )
             .samples(ash::vk::SampleCountFlags::TYPE_1)
-            .array_layers(1)
+            .array_layers(image_info.image_type.layer_count())
             .mip_levels(1);
+        let create_info = match image_info.image_type {
+            GfxImageType::Cube => create_info.flags(ash::vk::ImageCreateFlags::CUBE_COMPATIBLE),
+            _ => create_info,
+        };
         let image = unsafe { self.ctx.device.create_image(&create_info, None) }?;
+        self.ctx.set_object_name(image, &image_name);
 
         let image_memory_requirements =
             unsafe { self.ctx.device.get_image_memory_requirements(image) };
@@ -3585,6 +4435,8 @@ impl VulkanResourceManager {
         }?;
 
         let image_view = self.create_image_view(image, &image_info)?;
+        self.ctx
+            .set_object_name(image_view, &format!("{}_view", image_name));
 
         let resource_id = self.next_resource_id();
         self.owned_images.write().insert(
@@ -3616,7 +4468,7 @@ impl VulkanResourceManager {
                     .base_mip_level(0)
                     .level_count(1)
                     .base_array_layer(0)
-                    .layer_count(1),
+                    .layer_count(image_info.image_type.layer_count()),
             );
         let image_view = unsafe { self.ctx.device.create_image_view(&create_info, None) }?;
         Ok(image_view)
@@ -3663,6 +4515,32 @@ impl VulkanResourceManager {
         return image_ref;
     }
 
+    fn destroy_compute_pipeline(&self, id: ResourceId<ComputePipeline>) {
+        let Some(pipeline) = self.compute_pipelines.write().remove(&id) else {
+            warn!(
+                "Tried to destroy a vulkan compute pipeline that doesn't exist, {:?}.",
+                id
+            );
+            return;
+        };
+        self.destroy_frame_queue[self.ctx.curr_cpu_frame_index() as usize]
+            .write()
+            .push(VulkanRetiredResource::Pipeline(pipeline.pipeline));
+    }
+
+    fn destroy_raster_pipeline(&self, id: ResourceId<RasterPipeline>) {
+        let Some(pipeline) = self.raster_pipelines.write().remove(&id) else {
+            warn!(
+                "Tried to destroy a vulkan raster pipeline that doesn't exist, {:?}.",
+                id
+            );
+            return;
+        };
+        self.destroy_frame_queue[self.ctx.curr_cpu_frame_index() as usize]
+            .write()
+            .push(VulkanRetiredResource::Pipeline(pipeline.pipeline));
+    }
+
     fn get_compute_pipeline(&self, id: ResourceId<ComputePipeline>) -> VulkanComputePipeline {
         self.compute_pipelines
             .read()
@@ -3681,7 +4559,16 @@ impl VulkanResourceManager {
 
     fn create_sampler(&self, create_info: GfxSamplerCreateInfo) -> ResourceId<Sampler> {
         let resource_id = self.next_resource_id();
-        let create_info: ash::vk::SamplerCreateInfo<'_> = create_info.into();
+        let mut create_info: ash::vk::SamplerCreateInfo<'_> = create_info.into();
+        match self.ctx.max_sampler_anisotropy() {
+            Some(max_anisotropy) if create_info.anisotropy_enable == ash::vk::TRUE => {
+                create_info.max_anisotropy = create_info.max_anisotropy.clamp(1.0, max_anisotropy);
+            }
+            _ => {
+                create_info.anisotropy_enable = ash::vk::FALSE;
+                create_info.max_anisotropy = 1.0;
+            }
+        }
         let sampler = unsafe { self.ctx.device.create_sampler(&create_info, None) }
             .expect("TODO: Turn this into a result.");
         let samplers = self.samplers.write().insert(resource_id, sampler);
@@ -3711,6 +4598,24 @@ impl Drop for VulkanResourceManager {
         for (_, pipeline) in self.compute_pipelines.write().iter() {
             unsafe { self.ctx.device.destroy_pipeline(pipeline.pipeline, None) };
         }
+        for destroy_queue in &self.destroy_frame_queue {
+            for resource in destroy_queue.write().drain(..) {
+                match resource {
+                    VulkanRetiredResource::Pipeline(pipeline) => unsafe {
+                        self.ctx.device.destroy_pipeline(pipeline, None)
+                    },
+                    VulkanRetiredResource::Buffer(buffer) => unsafe {
+                        self.ctx.device.destroy_buffer(buffer.buffer, None)
+                    },
+                    VulkanRetiredResource::Image(image) => {
+                        if let Some(image_view) = image.view {
+                            unsafe { self.ctx.device.destroy_image_view(image_view, None) };
+                        }
+                        unsafe { self.ctx.device.destroy_image(image.image, None) };
+                    }
+                }
+            }
+        }
         for (_, pipeline) in self.raster_pipelines.write().iter() {
             unsafe { self.ctx.device.destroy_pipeline(pipeline.pipeline, None) };
         }
@@ -3763,3 +4668,41 @@ pub fn vk_is_depth_format(format: ash::vk::Format) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use strum::VariantArray;
+
+    use super::*;
+
+    #[test]
+    fn every_image_format_has_a_pixel_byte_size() {
+        for format in GfxImageFormat::VARIANTS {
+            let info = VulkanImageInfo {
+                image_type: GfxImageType::D2,
+                format: (*format).into(),
+                extent: ash::vk::Extent2D {
+                    width: 4,
+                    height: 4,
+                },
+            };
+            assert_eq!(GfxImageFormat::from(info.format), *format);
+            assert_eq!(info.pixel_byte_size(), format.pixel_byte_size() as usize);
+        }
+
+        // A 2x2 hdr write is four pixels of four f32 channels.
+        let info = VulkanImageInfo {
+            image_type: GfxImageType::D2,
+            format: ash::vk::Format::R32G32B32A32_SFLOAT,
+            extent: ash::vk::Extent2D {
+                width: 2,
+                height: 2,
+            },
+        };
+        let pixels = [[1.0f32, 0.5, 0.25, 1.0]; 4];
+        assert_eq!(
+            info.pixel_byte_size() * 4,
+            bytemuck::cast_slice::<_, u8>(&pixels).len()
+        );
+    }
+}
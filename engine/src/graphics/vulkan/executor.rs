@@ -1,7 +1,7 @@
 use core::panic;
 use std::{
     any::Any,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     ops::Deref,
     sync::Arc,
     time::Duration,
@@ -17,10 +17,10 @@ use super::{
 use crate::graphics::{
     backend::{
         Buffer, ComputePipeline, GfxBufferCreateInfo, GfxComputePipelineCreateInfo,
-        GfxComputePipelineInfo, GfxCullMode, GfxFrontFace, GfxImageCreateInfo, GfxImageFormat,
-        GfxPassOnceImpl, GfxRasterPipelineBlendStateCreateInfo, GfxRasterPipelineCreateInfo,
-        GfxVertexFormat, GraphicsBackendFrameGraphExecutor, Image, RasterPipeline, ResourceId,
-        ShaderWriter, Untyped,
+        GfxComputePipelineInfo, GfxCullMode, GfxDeviceError, GfxFrontFace, GfxImageCreateInfo,
+        GfxImageFormat, GfxPassOnceImpl, GfxPassTiming, GfxRasterPipelineBlendStateCreateInfo,
+        GfxRasterPipelineCreateInfo, GfxVertexFormat, GraphicsBackendFrameGraphExecutor,
+        GraphicsBackendRecorder, Image, RasterPipeline, ResourceId, ShaderWriter, Untyped,
     },
     frame_graph::{
         self, FGResourceBackendId, FrameGraph, FrameGraphBufferInfo, FrameGraphComputePipelineInfo,
@@ -32,16 +32,82 @@ use crate::graphics::{
         ShaderModificationTree, ShaderSetBinding, ShaderStage,
     },
 };
-use crate::window::time::{Instant, Timer};
+use crate::{
+    common::color::Color,
+    window::time::{Instant, Timer},
+};
 
 pub struct VulkanFrameGraphExecutor {
     ctx: Arc<VulkanContext>,
     session: Option<FrameSession>,
     command_pools: Vec<VulkanCommandPool>,
+    /// Command pools for staging buffer copies on the transfer queue, empty if the device
+    /// doesn't have a separate transfer queue family.
+    transfer_command_pools: Vec<VulkanCommandPool>,
+    /// Signaled with the cpu frame once that frame's staging buffer copies have finished on the
+    /// transfer queue.
+    transfer_semaphore: Option<ash::vk::Semaphore>,
+    /// One per frame in flight, empty if the device can't write timestamps.
+    pass_timestamps: Vec<VulkanPassTimestamps>,
+    last_pass_timings: Vec<GfxPassTiming>,
+    /// Names of the passes skipped each frame, kept across frame graph changes.
+    disabled_passes: HashSet<String>,
+    pass_names: Vec<String>,
 
     resource_manager: VulkanExecutorResourceManager,
 }
 
+/// Timestamps written at the beginning and end of each pass. The results are read back when
+/// the same frame in flight index comes around again since the gpu has finished it by then.
+struct VulkanPassTimestamps {
+    query_pool: ash::vk::QueryPool,
+    /// The pass of each begin and end query pair, in query order.
+    pass_names: Vec<String>,
+}
+
+impl VulkanPassTimestamps {
+    const MAX_PASSES: u32 = 128;
+
+    /// Writes the begin timestamp of a pass, returning the query of the end timestamp. `None`
+    /// if timestamps aren't supported or there are too many passes this frame.
+    fn begin_pass(
+        pass_timestamps: &mut [VulkanPassTimestamps],
+        ctx: &VulkanContext,
+        recorder: &VulkanRecorder,
+        pass_name: &str,
+    ) -> Option<u32> {
+        let pass_timestamps = pass_timestamps.get_mut(ctx.curr_cpu_frame_index() as usize)?;
+        if pass_timestamps.pass_names.len() as u32 >= Self::MAX_PASSES {
+            return None;
+        }
+
+        let begin_query = pass_timestamps.pass_names.len() as u32 * 2;
+        pass_timestamps.pass_names.push(pass_name.to_owned());
+        recorder.write_timestamp(
+            pass_timestamps.query_pool,
+            begin_query,
+            ash::vk::PipelineStageFlags::TOP_OF_PIPE,
+        );
+        Some(begin_query + 1)
+    }
+
+    fn end_pass(
+        pass_timestamps: &[VulkanPassTimestamps],
+        ctx: &VulkanContext,
+        recorder: &VulkanRecorder,
+        end_query: Option<u32>,
+    ) {
+        let Some(end_query) = end_query else {
+            return;
+        };
+        recorder.write_timestamp(
+            pass_timestamps[ctx.curr_cpu_frame_index() as usize].query_pool,
+            end_query,
+            ash::vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        );
+    }
+}
+
 #[derive(Clone, Hash, PartialEq, Eq)]
 struct VulkanExecutorComputePipelineDesc {
     shader_desc: ShaderDesc,
@@ -70,7 +136,16 @@ struct VulkanExecutorResourceManager {
     // in flight since these buffers are only references during a gpu frame.
     frame_buffers: Vec<(FrameGraphBufferInfo, ResourceId<Buffer>)>,
     compute_pipelines: HashMap<VulkanExecutorComputePipelineDesc, ResourceId<ComputePipeline>>,
+    /// Pipelines standing in for a desc whose shader failed to compile, owned by the desc in
+    /// `compute_pipelines` with the same shader but other macro defines. Cleared to retry
+    /// compiling them when shaders change.
+    fallback_compute_pipelines:
+        HashMap<VulkanExecutorComputePipelineDesc, ResourceId<ComputePipeline>>,
     raster_pipelines: HashMap<VulkanExecutorRasterPipelineDesc, ResourceId<RasterPipeline>>,
+    /// Pipelines which failed to be created without a fallback, passes using them are disabled.
+    /// Cleared to retry creating them when shaders change.
+    failed_compute_pipelines: HashSet<VulkanExecutorComputePipelineDesc>,
+    failed_raster_pipelines: HashSet<VulkanExecutorRasterPipelineDesc>,
     // Holds the in use buffers and images during the current execution context to prevent using
     // the same image or buffer accidentally since we don't have proper aliasing yet.
     in_use_resources: HashSet<ResourceId<Untyped>>,
@@ -79,6 +154,7 @@ struct VulkanExecutorResourceManager {
     shader_modification_tree: ShaderModificationTree,
     shader_compiler: ShaderCompiler,
     cached_library_bindings: Option<(Vec<ShaderSetBinding>, bool)>,
+    shader_errors: Vec<String>,
     // new_compute_pipeline: HashMap<VulkanExecutorComputePipelineDesc, ResourceId<ComputePipeline>>,
     // // The timeline in which the new compute pipeline is old news, meaning all the frames in flight
     // // have updated to the new pipeline.
@@ -125,13 +201,17 @@ impl VulkanExecutorResourceManager {
             frame_images: HashMap::new(),
             frame_buffers: Vec::new(),
             compute_pipelines: HashMap::new(),
+            fallback_compute_pipelines: HashMap::new(),
             raster_pipelines: HashMap::new(),
+            failed_compute_pipelines: HashSet::new(),
+            failed_raster_pipelines: HashSet::new(),
             in_use_resources: HashSet::new(),
 
             shader_compiler: ShaderCompiler::new(),
             invalidate_shader_timer: Timer::new(Duration::from_millis(250)),
             shader_modification_tree: ShaderModificationTree::from_current_state(),
             cached_library_bindings: None,
+            shader_errors: Vec::new(),
             //             new_compute_pipeline: HashMap::new(),
             //             new_compute_pipeline_deletion_timeline: (0..ctx.frames_in_flight())
             //                 .map(|_| HashSet::new())
@@ -163,6 +243,10 @@ impl VulkanExecutorResourceManager {
         if let Some((_, lib_binding_invalid)) = &mut self.cached_library_bindings {
             *lib_binding_invalid = true;
         }
+        self.shader_errors.clear();
+        self.fallback_compute_pipelines.clear();
+        self.failed_compute_pipelines.clear();
+        self.failed_raster_pipelines.clear();
 
         // let mut recreation_compute_pipelines = Vec::new();
         // for (info, id) in self.cached_compute_pipelines.iter() {
@@ -186,26 +270,31 @@ impl VulkanExecutorResourceManager {
                 desc.shader_desc.clone(),
                 ShaderStage::Compute,
             );
-            match self.shader_compiler.compile_shader(opts) {
-                Ok(shader) => {
-                    let new_pipeline = self
-                        .ctx
-                        .create_compute_pipeline(GfxComputePipelineCreateInfo { shader })
-                        .expect("Failed to create new compute pipeline.");
-
-                    new_pipelines.push((desc.clone(), new_pipeline));
-                    // TODO: Destroy pipeline.
-                }
+            let shader = match self.shader_compiler.compile_shader(opts) {
+                Ok(shader) => shader,
                 Err(err) => {
                     log::error!("Got error compiling new slang compute shader:");
-                    log::error!("{}", err)
+                    log::error!("{}", err);
+                    self.shader_errors.push(err.to_string());
+                    continue;
+                }
+            };
+            match self
+                .ctx
+                .create_compute_pipeline(GfxComputePipelineCreateInfo { shader })
+            {
+                Ok(new_pipeline) => new_pipelines.push((desc.clone(), new_pipeline)),
+                Err(err) => {
+                    log::error!("Failed to create new compute pipeline, {}", err);
+                    self.shader_errors.push(err.to_string());
                 }
             }
         }
 
         for (desc, new_pipeline) in new_pipelines {
             let old_pipeline = self.compute_pipelines.get_mut(&desc).unwrap();
-            *old_pipeline = new_pipeline;
+            self.ctx
+                .destroy_compute_pipeline(std::mem::replace(old_pipeline, new_pipeline));
         }
 
         // Raster pipeline updates.
@@ -227,11 +316,12 @@ impl VulkanExecutorResourceManager {
                 Err(err) => {
                     log::error!("Got error compiling new slang shaders:");
                     log::error!("{}", err);
+                    self.shader_errors.push(err.to_string());
                     continue;
                 }
             };
 
-            let new_pipeline = self
+            match self
                 .ctx
                 .create_raster_pipeline(GfxRasterPipelineCreateInfo {
                     vertex_shader,
@@ -242,16 +332,19 @@ impl VulkanExecutorResourceManager {
                     blend_state: desc.blend_state.clone(),
                     color_formats: desc.color_formats.clone(),
                     depth_format: desc.depth_format,
-                })
-                .expect("Failed to create graphics raster pipeline.");
-
-            new_pipelines.push((desc.clone(), new_pipeline));
-            // TODO: Destroy old raster pipeline.
+                }) {
+                Ok(new_pipeline) => new_pipelines.push((desc.clone(), new_pipeline)),
+                Err(err) => {
+                    log::error!("Failed to create new raster pipeline, {}", err);
+                    self.shader_errors.push(err.to_string());
+                }
+            }
         }
 
         for (desc, new_pipeline) in new_pipelines {
             let old_pipeline = self.raster_pipelines.get_mut(&desc).unwrap();
-            *old_pipeline = new_pipeline;
+            self.ctx
+                .destroy_raster_pipeline(std::mem::replace(old_pipeline, new_pipeline));
         }
     }
 
@@ -365,47 +458,88 @@ impl VulkanExecutorResourceManager {
         buffer_id
     }
 
+    /// The pipeline for `create_info`, `None` if it failed to compile or be created and has no
+    /// fallback. Errors are reported through `shader_errors`.
     fn get_or_create_compute_pipeline(
         &mut self,
         create_info: &FrameGraphComputePipelineInfo,
-    ) -> ResourceId<ComputePipeline> {
+    ) -> Option<ResourceId<ComputePipeline>> {
         let compute_desc = VulkanExecutorComputePipelineDesc {
             shader_desc: ShaderDesc {
                 module: create_info.shader_path.clone(),
                 entry_point_name: create_info.entry_point_fn.clone(),
+                macro_defines: create_info.macro_defines.clone(),
             },
         };
 
+        if let Some(fallback_pipeline) = self.fallback_compute_pipelines.get(&compute_desc) {
+            return Some(*fallback_pipeline);
+        }
+        if self.failed_compute_pipelines.contains(&compute_desc) {
+            return None;
+        }
         if !self.compute_pipelines.contains_key(&compute_desc) {
             let opts = Self::create_shader_compilation_options(
                 compute_desc.shader_desc.clone(),
                 ShaderStage::Compute,
             );
-            let shader = self
-                .shader_compiler
-                .compile_shader(opts)
-                .expect("Failed to compile pipeline shader.");
+            let shader = match self.shader_compiler.compile_shader(opts) {
+                Ok(shader) => shader,
+                Err(err) => {
+                    log::error!("Got error compiling slang compute shader:");
+                    log::error!("{}", err);
+                    self.shader_errors.push(err.to_string());
+                    // Only the macro defines changed if the same shader has a pipeline, keep
+                    // using it so a broken shader doesn't take down the frame.
+                    let Some(fallback_pipeline) = self
+                        .compute_pipelines
+                        .iter()
+                        .find(|(desc, _)| {
+                            desc.shader_desc.module == compute_desc.shader_desc.module
+                                && desc.shader_desc.entry_point_name
+                                    == compute_desc.shader_desc.entry_point_name
+                        })
+                        .map(|(_, pipeline)| *pipeline)
+                    else {
+                        self.failed_compute_pipelines.insert(compute_desc);
+                        return None;
+                    };
+                    self.fallback_compute_pipelines
+                        .insert(compute_desc, fallback_pipeline);
+                    return Some(fallback_pipeline);
+                }
+            };
 
-            let compute_pipeline = self
+            let compute_pipeline = match self
                 .ctx
                 .create_compute_pipeline(GfxComputePipelineCreateInfo { shader })
-                .expect("Failed to create graphics compute pipeline.");
+            {
+                Ok(compute_pipeline) => compute_pipeline,
+                Err(err) => {
+                    log::error!("Failed to create compute pipeline, {}", err);
+                    self.shader_errors.push(err.to_string());
+                    self.failed_compute_pipelines.insert(compute_desc);
+                    return None;
+                }
+            };
 
             self.compute_pipelines
                 .insert(compute_desc.clone(), compute_pipeline);
 
-            return compute_pipeline;
+            return Some(compute_pipeline);
         }
 
-        *self.compute_pipelines.get(&compute_desc).unwrap()
+        Some(*self.compute_pipelines.get(&compute_desc).unwrap())
     }
 
+    /// The pipeline for `create_info`, `None` if it failed to compile or be created. Errors are
+    /// reported through `shader_errors`.
     fn get_or_create_raster_pipeline(
         &mut self,
         create_info: &FrameGraphRasterPipelineInfo,
         color_attachment_ids: Vec<ResourceId<Image>>,
         depth_attachment_id: Option<ResourceId<Image>>,
-    ) -> ResourceId<RasterPipeline> {
+    ) -> Option<ResourceId<RasterPipeline>> {
         let color_formats = color_attachment_ids
             .into_iter()
             .map(|attachment| self.ctx.get_image_info(&attachment).format)
@@ -417,10 +551,12 @@ impl VulkanExecutorResourceManager {
             vertex_shader_desc: ShaderDesc {
                 module: create_info.vertex_shader_path.clone(),
                 entry_point_name: create_info.vertex_entry_point_fn.clone(),
+                macro_defines: BTreeMap::new(),
             },
             fragment_shader_desc: ShaderDesc {
                 module: create_info.fragment_shader_path.clone(),
                 entry_point_name: create_info.fragment_entry_point_fn.clone(),
+                macro_defines: BTreeMap::new(),
             },
             vertex_format: create_info.vertex_format.clone(),
             cull_mode: create_info.cull_mode,
@@ -430,6 +566,9 @@ impl VulkanExecutorResourceManager {
             depth_format,
         };
 
+        if self.failed_raster_pipelines.contains(&raster_desc) {
+            return None;
+        }
         if !self.raster_pipelines.contains_key(&raster_desc) {
             let vertex_opts = Self::create_shader_compilation_options(
                 raster_desc.vertex_shader_desc.clone(),
@@ -439,32 +578,49 @@ impl VulkanExecutorResourceManager {
                 raster_desc.fragment_shader_desc.clone(),
                 ShaderStage::Fragment,
             );
-            let (vertex_shader, fragment_shader) = self
+            let (vertex_shader, fragment_shader) = match self
                 .shader_compiler
                 .compile_shader_pair(vertex_opts, fragment_opts)
-                .expect("Failed to compile raster pipeline shaders.");
+            {
+                Ok(shaders) => shaders,
+                Err(err) => {
+                    log::error!("Got error compiling slang shaders:");
+                    log::error!("{}", err);
+                    self.shader_errors.push(err.to_string());
+                    self.failed_raster_pipelines.insert(raster_desc);
+                    return None;
+                }
+            };
 
-            let raster_pipeline = self
-                .ctx
-                .create_raster_pipeline(GfxRasterPipelineCreateInfo {
-                    vertex_shader,
-                    fragment_shader,
-                    cull_mode: raster_desc.cull_mode,
-                    front_face: raster_desc.front_face,
-                    vertex_format: raster_desc.vertex_format.clone(),
-                    blend_state: raster_desc.blend_state.clone(),
-                    color_formats: raster_desc.color_formats.clone(),
-                    depth_format: raster_desc.depth_format,
-                })
-                .expect("Failed to create graphics raster pipeline.");
+            let raster_pipeline =
+                match self
+                    .ctx
+                    .create_raster_pipeline(GfxRasterPipelineCreateInfo {
+                        vertex_shader,
+                        fragment_shader,
+                        cull_mode: raster_desc.cull_mode,
+                        front_face: raster_desc.front_face,
+                        vertex_format: raster_desc.vertex_format.clone(),
+                        blend_state: raster_desc.blend_state.clone(),
+                        color_formats: raster_desc.color_formats.clone(),
+                        depth_format: raster_desc.depth_format,
+                    }) {
+                    Ok(raster_pipeline) => raster_pipeline,
+                    Err(err) => {
+                        log::error!("Failed to create raster pipeline, {}", err);
+                        self.shader_errors.push(err.to_string());
+                        self.failed_raster_pipelines.insert(raster_desc);
+                        return None;
+                    }
+                };
 
             self.raster_pipelines
                 .insert(raster_desc.clone(), raster_pipeline);
 
-            return raster_pipeline;
+            return Some(raster_pipeline);
         }
 
-        *self.raster_pipelines.get(&raster_desc).unwrap()
+        Some(*self.raster_pipelines.get(&raster_desc).unwrap())
     }
 
     fn create_shader_compilation_options(
@@ -476,7 +632,7 @@ impl VulkanExecutorResourceManager {
             entry_point: desc.entry_point_name.clone(),
             stage,
             target: ShaderCompilationTarget::SpirV,
-            macro_defines: HashMap::new(),
+            macro_defines: desc.macro_defines.into_iter().collect(),
         }
     }
 }
@@ -490,12 +646,14 @@ struct FrameSession {
     pass_set_events: Vec<Option<ash::vk::Event>>,
 
     supplied_inputs: HashMap<FrameGraphResource<Untyped>, Box<dyn Any>>,
+    /// Passes skipped this frame, fixed when the frame begins.
+    disabled_passes: HashSet<FrameGraphResource<Pass>>,
 
     buffer_writes_event: Option<ash::vk::Event>,
 }
 
 impl FrameSession {
-    fn new(frame_graph: FrameGraph) -> Self {
+    fn new(frame_graph: FrameGraph, disabled_passes: HashSet<FrameGraphResource<Pass>>) -> Self {
         let pass_len = frame_graph.passes.len();
         Self {
             frame_graph,
@@ -504,6 +662,7 @@ impl FrameSession {
             recorded_pass_refs: HashMap::new(),
             pass_set_events: vec![None; pass_len.saturating_sub(1)],
             supplied_inputs: HashMap::new(),
+            disabled_passes,
             buffer_writes_event: None,
         }
     }
@@ -513,6 +672,7 @@ impl FrameSession {
             frame_graph: &self.frame_graph,
             resource_map: &self.resource_map,
             supplied_inputs: &self.supplied_inputs,
+            pass: None,
         }
     }
 }
@@ -528,26 +688,114 @@ impl VulkanFrameGraphExecutor {
         Self {
             ctx: ctx.clone(),
             session: None,
-            command_pools: (0..ctx.frames_in_flight())
-                .map(|_| {
-                    let vk_command_pool = unsafe {
-                        ctx.device()
-                            .create_command_pool(&ash::vk::CommandPoolCreateInfo::default(), None)
-                    }
-                    .expect("Failed to create vk command pool.");
-
-                    VulkanCommandPool {
-                        command_pool: vk_command_pool,
-                        in_use_command_buffers: Vec::new(),
-                        free_command_buffers: Vec::new(),
-                    }
-                })
-                .collect::<Vec<_>>(),
+            command_pools: Self::create_command_pools(ctx, ctx.main_queue_family_index()),
+            transfer_command_pools: ctx
+                .transfer_queue_family_index()
+                .map(|queue_family_index| Self::create_command_pools(ctx, queue_family_index))
+                .unwrap_or_default(),
+            transfer_semaphore: ctx.transfer_queue().map(|_| {
+                let mut timeline_create_info = ash::vk::SemaphoreTypeCreateInfo::default()
+                    .semaphore_type(ash::vk::SemaphoreType::TIMELINE)
+                    .initial_value(0);
+                let create_info =
+                    ash::vk::SemaphoreCreateInfo::default().push_next(&mut timeline_create_info);
+                unsafe { ctx.device().create_semaphore(&create_info, None) }
+                    .expect("Failed to create transfer timeline semaphore.")
+            }),
+            pass_timestamps: Self::create_pass_timestamps(ctx),
+            last_pass_timings: Vec::new(),
+            disabled_passes: HashSet::new(),
+            pass_names: Vec::new(),
 
             resource_manager: VulkanExecutorResourceManager::new(ctx),
         }
     }
 
+    fn create_command_pools(
+        ctx: &VulkanContextHandle,
+        queue_family_index: u32,
+    ) -> Vec<VulkanCommandPool> {
+        (0..ctx.frames_in_flight())
+            .map(|_| {
+                let create_info = ash::vk::CommandPoolCreateInfo::default()
+                    .queue_family_index(queue_family_index);
+                let vk_command_pool =
+                    unsafe { ctx.device().create_command_pool(&create_info, None) }
+                        .expect("Failed to create vk command pool.");
+
+                VulkanCommandPool {
+                    command_pool: vk_command_pool,
+                    in_use_command_buffers: Vec::new(),
+                    free_command_buffers: Vec::new(),
+                }
+            })
+            .collect::<Vec<_>>()
+    }
+
+    fn create_pass_timestamps(ctx: &VulkanContextHandle) -> Vec<VulkanPassTimestamps> {
+        if ctx.timestamp_period().is_none() {
+            log::warn!("Device doesn't support timestamp queries, gpu pass timings are disabled.");
+            return Vec::new();
+        }
+
+        (0..ctx.frames_in_flight())
+            .map(|_| {
+                let create_info = ash::vk::QueryPoolCreateInfo::default()
+                    .query_type(ash::vk::QueryType::TIMESTAMP)
+                    .query_count(VulkanPassTimestamps::MAX_PASSES * 2);
+                let query_pool = unsafe { ctx.device().create_query_pool(&create_info, None) }
+                    .expect("Failed to create vk query pool.");
+                VulkanPassTimestamps {
+                    query_pool,
+                    pass_names: Vec::new(),
+                }
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// Reads back the pass timestamps written the last time this frame in flight index was
+    /// recorded, the gpu must have finished that frame.
+    fn resolve_pass_timestamps(&mut self) {
+        let Some(timestamp_period) = self.ctx.timestamp_period() else {
+            return;
+        };
+        let pass_timestamps = &mut self.pass_timestamps[self.ctx.curr_cpu_frame_index() as usize];
+        if pass_timestamps.pass_names.is_empty() {
+            return;
+        }
+
+        let mut timestamps = vec![0u64; pass_timestamps.pass_names.len() * 2];
+        let result = unsafe {
+            self.ctx.device().get_query_pool_results(
+                pass_timestamps.query_pool,
+                0,
+                &mut timestamps,
+                ash::vk::QueryResultFlags::TYPE_64,
+            )
+        };
+        if let Err(err) = result {
+            // Not ready if the frame was never submitted.
+            if err != ash::vk::Result::NOT_READY {
+                log::warn!("Failed to read pass timestamps, {}", err);
+            }
+            pass_timestamps.pass_names.clear();
+            return;
+        }
+
+        self.last_pass_timings = pass_timestamps
+            .pass_names
+            .drain(..)
+            .zip(timestamps.chunks_exact(2))
+            .map(|(name, timestamps)| {
+                let ticks = timestamps[1].saturating_sub(timestamps[0]);
+                GfxPassTiming {
+                    name,
+                    duration: Duration::from_nanos((ticks as f64 * timestamp_period as f64) as u64),
+                }
+            })
+            .collect();
+    }
+
     fn session_mut(&mut self) -> &mut FrameSession {
         self.session
             .as_mut()
@@ -564,7 +812,8 @@ impl VulkanFrameGraphExecutor {
                     Ok(new_bindings) => *old_bindings = new_bindings,
                     Err(err) => {
                         log::error!("Got error compiling slang library module:");
-                        log::error!("{}", err)
+                        log::error!("{}", err);
+                        self.resource_manager.shader_errors.push(err.to_string());
                     }
                 }
                 *invalid = false;
@@ -581,9 +830,13 @@ impl VulkanFrameGraphExecutor {
 
         // Initialize compute pipelines since they don't rely on any graph dependencies.
         for (frame_resource, compute_pipeline_info) in &session.frame_graph.compute_pipelines {
-            let compute_pipeline = self
+            // Passes using a failed pipeline are disabled when their inputs are prepared.
+            let Some(compute_pipeline) = self
                 .resource_manager
-                .get_or_create_compute_pipeline(compute_pipeline_info);
+                .get_or_create_compute_pipeline(compute_pipeline_info)
+            else {
+                continue;
+            };
             session.resource_map.insert(
                 frame_resource.as_untyped(),
                 FGResourceBackendId {
@@ -618,13 +871,15 @@ impl VulkanFrameGraphExecutor {
         Ok(command_buffer)
     }
 
+    /// Returns whether the pipelines the pass uses were created successfully.
     fn prep_pass_inputs(
         frame_graph: &FrameGraph,
         resource_map: &mut HashMap<FrameGraphResource<Untyped>, FGResourceBackendId>,
         supplied_inputs: &HashMap<FrameGraphResource<Untyped>, Box<dyn std::any::Any>>,
         resource_manager: &mut VulkanExecutorResourceManager,
         pass: &FrameGraphPass,
-    ) {
+    ) -> bool {
+        let mut pipelines_ready = true;
         // Iterate over outputs and inputs since we outputs implicity define inputs.
         for input in pass.inputs.iter().chain(pass.outputs.iter()) {
             if resource_map.contains_key(input) {
@@ -645,6 +900,7 @@ impl VulkanFrameGraphExecutor {
                         frame_graph,
                         resource_map,
                         supplied_inputs,
+                        pass: None,
                     };
                     let image_info = info_create_fn(&ctx);
                     Some(image_info)
@@ -707,7 +963,12 @@ impl VulkanFrameGraphExecutor {
                     .compute_pipelines
                     .get(&input.as_typed::<ComputePipeline>())
                 {
-                    let compute_pipeline = resource_manager.get_or_create_compute_pipeline(info);
+                    let Some(compute_pipeline) =
+                        resource_manager.get_or_create_compute_pipeline(info)
+                    else {
+                        pipelines_ready = false;
+                        continue;
+                    };
                     resource_map.insert(
                         input.as_untyped(),
                         FGResourceBackendId {
@@ -776,11 +1037,14 @@ impl VulkanFrameGraphExecutor {
                 // TODO: make color attachments an input to this pass and then process the
                 // raster pipeline last so we know we have all the required images loaded in
                 // the resource map.
-                let raster_pipeline = resource_manager.get_or_create_raster_pipeline(
+                let Some(raster_pipeline) = resource_manager.get_or_create_raster_pipeline(
                     info,
                     color_attachment_ids,
                     depth_attachment_id,
-                );
+                ) else {
+                    pipelines_ready = false;
+                    continue;
+                };
                 resource_map.insert(
                     input.as_untyped(),
                     FGResourceBackendId {
@@ -790,20 +1054,29 @@ impl VulkanFrameGraphExecutor {
                 );
             }
         }
+
+        pipelines_ready
     }
 
     fn flush(&mut self) {
         let mut session = self.session.as_mut().unwrap();
+        // Images written by any pass so far this frame, disabled passes included.
+        let mut written_images = HashSet::new();
         for (pass_idx, pass) in session.frame_graph.passes.iter().enumerate() {
-            Self::prep_pass_inputs(
+            let pipelines_ready = Self::prep_pass_inputs(
                 &session.frame_graph,
                 &mut session.resource_map,
                 &session.supplied_inputs,
                 &mut self.resource_manager,
                 pass,
             );
+            if !pipelines_ready {
+                // Skip the pass like a disabled one, the pipeline errors were already reported.
+                session.disabled_passes.insert(pass.id);
+            }
 
-            let recorder = if let Some(pass_fn) = &pass.pass {
+            let recorder = if session.disabled_passes.contains(&pass.id) {
+                // Still record the pass so the event chain between passes stays intact.
                 let command_buffer =
                     Self::acquire_command_buffer(&self.ctx, &mut self.command_pools)
                         .expect("Failed to acquire a command buffer.");
@@ -818,13 +1091,59 @@ impl VulkanFrameGraphExecutor {
                 };
                 recorder.wait_event(*wait_event);
 
+                Self::clear_disabled_pass_outputs(
+                    &session.frame_graph,
+                    &session.resource_map,
+                    &written_images,
+                    &mut recorder,
+                    pass,
+                );
+
+                // True for all but the last pass.
+                if pass_idx < session.pass_set_events.len() {
+                    let curr_pass_event = session.pass_set_events[pass_idx]
+                        .get_or_insert_with(|| self.ctx.create_frame_event());
+
+                    recorder.set_event(*curr_pass_event);
+                }
+
+                recorder
+            } else if let Some(pass_fn) = &pass.pass {
+                let command_buffer =
+                    Self::acquire_command_buffer(&self.ctx, &mut self.command_pools)
+                        .expect("Failed to acquire a command buffer.");
+                let mut recorder = VulkanRecorder::new(&self.ctx, command_buffer);
+                recorder.begin();
+
+                let wait_event = if pass_idx == 0 {
+                    session.buffer_writes_event.as_mut().unwrap()
+                } else {
+                    session.pass_set_events[pass_idx - 1]
+                        .get_or_insert_with(|| self.ctx.create_frame_event())
+                };
+                recorder.wait_event(*wait_event);
+
+                let end_query = VulkanPassTimestamps::begin_pass(
+                    &mut self.pass_timestamps,
+                    &self.ctx,
+                    &recorder,
+                    &session.frame_graph.resource_infos[pass.id.id() as usize].name,
+                );
+
                 let ctx = FrameGraphContext {
                     frame_graph: &session.frame_graph,
                     resource_map: &session.resource_map,
                     supplied_inputs: &session.supplied_inputs,
+                    pass: Some(pass),
                 };
 
                 pass_fn(&mut recorder, &ctx);
+                VulkanPassTimestamps::end_pass(
+                    &self.pass_timestamps,
+                    &self.ctx,
+                    &recorder,
+                    end_query,
+                );
 
                 // True for all but the last pass.
                 if pass_idx < session.pass_set_events.len() {
@@ -844,6 +1163,35 @@ impl VulkanFrameGraphExecutor {
             };
 
             session.recorded_command_buffers.push(recorder);
+            written_images.extend(pass.outputs.iter().copied());
+        }
+    }
+
+    /// Clears the images a disabled pass writes which no earlier pass has written this frame,
+    /// images that have been written pass through untouched.
+    fn clear_disabled_pass_outputs(
+        frame_graph: &FrameGraph,
+        resource_map: &HashMap<FrameGraphResource<Untyped>, FGResourceBackendId>,
+        written_images: &HashSet<FrameGraphResource<Untyped>>,
+        recorder: &mut VulkanRecorder,
+        pass: &FrameGraphPass,
+    ) {
+        for output in &pass.outputs {
+            if written_images.contains(output)
+                || frame_graph.resource_infos[output.id() as usize].type_id
+                    != std::any::TypeId::of::<Image>()
+            {
+                continue;
+            }
+            let Some(image_id) = resource_map.get(output) else {
+                continue;
+            };
+            let image = image_id.resource_id.as_typed::<Image>();
+            // Depth images are cleared by the render passes attaching them.
+            if recorder.get_image_info(&image).format.is_depth() {
+                continue;
+            }
+            recorder.clear_color(image, Color::BLACK);
         }
     }
 }
@@ -853,31 +1201,72 @@ impl Drop for VulkanFrameGraphExecutor {
         println!("Dropping executor");
         // TODO: Resource tracking so we dont block like this.
         unsafe { self.ctx.device().device_wait_idle() };
-        for command_pool in &self.command_pools {
+        for command_pool in self
+            .command_pools
+            .iter()
+            .chain(&self.transfer_command_pools)
+        {
             unsafe {
                 self.ctx
                     .device()
                     .destroy_command_pool(command_pool.command_pool, None)
             }
         }
+        if let Some(transfer_semaphore) = self.transfer_semaphore {
+            unsafe {
+                self.ctx
+                    .device()
+                    .destroy_semaphore(transfer_semaphore, None)
+            };
+        }
+        for pass_timestamps in &self.pass_timestamps {
+            unsafe {
+                self.ctx
+                    .device()
+                    .destroy_query_pool(pass_timestamps.query_pool, None)
+            }
+        }
     }
 }
 
 impl GraphicsBackendFrameGraphExecutor for VulkanFrameGraphExecutor {
     fn begin_frame(&mut self, frame_graph: FrameGraph) {
-        let curr_cmd_pool = &mut self.command_pools[self.ctx.curr_cpu_frame_index() as usize];
-        //debug!("Resetting command pool {:?}", curr_cmd_pool.command_pool);
-        unsafe {
-            self.ctx.device().reset_command_pool(
-                curr_cmd_pool.command_pool,
-                ash::vk::CommandPoolResetFlags::empty(),
-            )
-        };
-        curr_cmd_pool
-            .free_command_buffers
-            .append(&mut curr_cmd_pool.in_use_command_buffers);
+        let frame_index = self.ctx.curr_cpu_frame_index() as usize;
+        for curr_cmd_pool in std::iter::once(&mut self.command_pools[frame_index])
+            .chain(self.transfer_command_pools.get_mut(frame_index))
+        {
+            //debug!("Resetting command pool {:?}", curr_cmd_pool.command_pool);
+            unsafe {
+                self.ctx.device().reset_command_pool(
+                    curr_cmd_pool.command_pool,
+                    ash::vk::CommandPoolResetFlags::empty(),
+                )
+            };
+            curr_cmd_pool
+                .free_command_buffers
+                .append(&mut curr_cmd_pool.in_use_command_buffers);
+        }
+        self.resolve_pass_timestamps();
 
-        self.session = Some(FrameSession::new(frame_graph));
+        self.pass_names = frame_graph
+            .passes
+            .iter()
+            .map(|pass| {
+                frame_graph.resource_infos[pass.id.id() as usize]
+                    .name
+                    .clone()
+            })
+            .collect();
+        let disabled_passes = frame_graph
+            .passes
+            .iter()
+            .filter(|pass| {
+                self.disabled_passes
+                    .contains(&frame_graph.resource_infos[pass.id.id() as usize].name)
+            })
+            .map(|pass| pass.id)
+            .collect();
+        self.session = Some(FrameSession::new(frame_graph, disabled_passes));
         self.resource_manager.retire_resources();
 
         // Invalidate pipelines before initializing resources so we can ensure we have the most up
@@ -894,13 +1283,40 @@ impl GraphicsBackendFrameGraphExecutor for VulkanFrameGraphExecutor {
             return session.frame_graph;
         }
 
+        // Buffer copies go on the transfer queue when there is one so they can overlap with
+        // recording, the main queue waits on them before running any pass. The copies themselves
+        // wait on the previous frame since it may still be reading the destination buffers.
+        let transfer_recorder = (!self.transfer_command_pools.is_empty()).then(|| {
+            let command_buffer =
+                Self::acquire_command_buffer(&self.ctx, &mut self.transfer_command_pools)
+                    .expect("Failed to acquire transfer command buffer.");
+            let transfer_recorder = VulkanRecorder::new(&self.ctx, command_buffer);
+            transfer_recorder.begin();
+            transfer_recorder
+        });
+
         // Record staging buffer transfer operations.
         let staging_buffer_copies_vk_command_buffer = {
             let command_buffer = Self::acquire_command_buffer(&self.ctx, &mut self.command_pools)
                 .expect("Failed to acquire command buffer.");
             let mut transition_recorder = VulkanRecorder::new(&self.ctx, command_buffer);
             transition_recorder.begin();
-            self.ctx.record_buffer_writes(&mut transition_recorder);
+            // This is submitted before any pass so the queries are reset before they are written.
+            if let Some(pass_timestamps) = self
+                .pass_timestamps
+                .get(self.ctx.curr_cpu_frame_index() as usize)
+            {
+                unsafe {
+                    self.ctx.device().cmd_reset_query_pool(
+                        command_buffer,
+                        pass_timestamps.query_pool,
+                        0,
+                        VulkanPassTimestamps::MAX_PASSES * 2,
+                    )
+                };
+            }
+            self.ctx
+                .record_buffer_writes(&mut transition_recorder, transfer_recorder.as_ref());
             transition_recorder.set_event(session.buffer_writes_event.take().unwrap());
             transition_recorder.finish();
 
@@ -911,10 +1327,12 @@ impl GraphicsBackendFrameGraphExecutor for VulkanFrameGraphExecutor {
             .resource_map
             .get(&session.frame_graph.swapchain_image.as_untyped())
             .expect("Swapchain image hasn't been populated in the resource map.");
+        // Headless devices render into offscreen images which are never presented.
+        let is_headless = self.ctx.is_headless();
         let recorder_count = session.recorded_command_buffers.len();
         for (i, recorder) in session.recorded_command_buffers.iter_mut().enumerate() {
             let is_last = i == recorder_count - 1;
-            if is_last {
+            if is_last && !is_headless {
                 recorder.transition_images(
                     &[VulkanImageTransition {
                         image_id: ResourceId::new(swapchain_image_id.resource_id.id()),
@@ -939,29 +1357,82 @@ impl GraphicsBackendFrameGraphExecutor for VulkanFrameGraphExecutor {
             );
         }
 
-        let wait_semaphore_infos = [ash::vk::SemaphoreSubmitInfo::default()
-            .semaphore(self.ctx.curr_image_acquire_semaphore())
-            .stage_mask(ash::vk::PipelineStageFlags2::TOP_OF_PIPE)];
-        let signal_semaphore_infos = [
+        let mut wait_semaphore_infos = Vec::new();
+        if !is_headless {
+            wait_semaphore_infos.push(
+                ash::vk::SemaphoreSubmitInfo::default()
+                    .semaphore(self.ctx.curr_image_acquire_semaphore())
+                    .stage_mask(ash::vk::PipelineStageFlags2::TOP_OF_PIPE),
+            );
+        }
+        if let Some(transfer_recorder) = transfer_recorder {
+            transfer_recorder.finish();
+            let transfer_semaphore = self.transfer_semaphore.unwrap();
+            let command_buffer_infos = [ash::vk::CommandBufferSubmitInfo::default()
+                .command_buffer(transfer_recorder.command_buffer())];
+            let signal_semaphore_infos = [ash::vk::SemaphoreSubmitInfo::default()
+                .semaphore(transfer_semaphore)
+                .value(self.ctx.curr_cpu_frame())
+                .stage_mask(ash::vk::PipelineStageFlags2::ALL_COMMANDS)];
+            let transfer_wait_semaphore_infos = [ash::vk::SemaphoreSubmitInfo::default()
+                .semaphore(self.ctx.gpu_timeline_semaphore())
+                .value(self.ctx.curr_cpu_frame() - 1)
+                .stage_mask(ash::vk::PipelineStageFlags2::TRANSFER)];
+            let submit_info = ash::vk::SubmitInfo2::default()
+                .command_buffer_infos(&command_buffer_infos)
+                .wait_semaphore_infos(&transfer_wait_semaphore_infos)
+                .signal_semaphore_infos(&signal_semaphore_infos);
+            if let Err(err) = unsafe {
+                self.ctx.device().queue_submit2(
+                    self.ctx.transfer_queue().unwrap(),
+                    &[submit_info],
+                    ash::vk::Fence::null(),
+                )
+            } {
+                if self.ctx.record_vk_error(err).is_none() {
+                    log::error!("Failed to submit staging buffer copies, {}", err);
+                }
+            }
+
+            wait_semaphore_infos.push(
+                ash::vk::SemaphoreSubmitInfo::default()
+                    .semaphore(transfer_semaphore)
+                    .value(self.ctx.curr_cpu_frame())
+                    .stage_mask(ash::vk::PipelineStageFlags2::ALL_COMMANDS),
+            );
+        }
+        let mut signal_semaphore_infos = vec![
             ash::vk::SemaphoreSubmitInfo::default()
                 .semaphore(self.ctx.gpu_timeline_semaphore())
                 .value(self.ctx.curr_cpu_frame())
                 .stage_mask(ash::vk::PipelineStageFlags2::BOTTOM_OF_PIPE),
-            ash::vk::SemaphoreSubmitInfo::default()
-                .semaphore(self.ctx.curr_image_ready_semaphore())
-                .stage_mask(ash::vk::PipelineStageFlags2::BOTTOM_OF_PIPE),
         ];
+        if !is_headless {
+            signal_semaphore_infos.push(
+                ash::vk::SemaphoreSubmitInfo::default()
+                    .semaphore(self.ctx.curr_image_ready_semaphore())
+                    .stage_mask(ash::vk::PipelineStageFlags2::BOTTOM_OF_PIPE),
+            );
+        }
         let mut submit_info_2 = ash::vk::SubmitInfo2::default()
             .command_buffer_infos(&command_buffer_infos)
             .wait_semaphore_infos(&wait_semaphore_infos)
             .signal_semaphore_infos(&signal_semaphore_infos);
-        unsafe {
+        if let Err(err) = unsafe {
             self.ctx.device().queue_submit2(
                 self.ctx.main_queue(),
                 &[submit_info_2],
                 ash::vk::Fence::null(),
             )
-        };
+        } {
+            if self.ctx.record_vk_error(err).is_none() {
+                log::error!("Failed to submit frame, {}", err);
+            }
+        }
+
+        if is_headless {
+            return session.frame_graph;
+        }
 
         let swapchains = [self.ctx.swapchain().swapchain];
         let image_indices = [self.ctx.curr_swapchain_image_index()];
@@ -971,11 +1442,24 @@ impl GraphicsBackendFrameGraphExecutor for VulkanFrameGraphExecutor {
             .image_indices(&image_indices)
             .wait_semaphores(&wait_semaphores);
         let present_timer = Instant::now();
-        unsafe {
+        let present_result = unsafe {
             self.ctx
                 .swapchain_loader()
                 .queue_present(self.ctx.main_queue(), &present_info)
         };
+        match present_result {
+            // Recreated before the next swapchain image is acquired.
+            Ok(true) => self.ctx.mark_swapchain_out_of_date(),
+            Ok(false) => {}
+            Err(err) => match self.ctx.record_vk_error(err) {
+                Some(GfxDeviceError::SwapchainOutOfDate) => {
+                    debug!("Swapchain is out of date on present.");
+                    self.ctx.mark_swapchain_out_of_date();
+                }
+                Some(_) => {}
+                None => log::error!("Failed to present swapchain image, {}", err),
+            },
+        }
         // debug!(
         //     "Took {}ms to present swapchain image with present_mode {:?}.",
         //     present_timer.elapsed().as_micros() as f32 / 1000.0,
@@ -1075,6 +1559,21 @@ impl GraphicsBackendFrameGraphExecutor for VulkanFrameGraphExecutor {
     }
 
     fn supply_pass_ref<'a>(&mut self, name: &str, mut pass: &mut (dyn GfxPassOnceImpl + 'a)) {
+        let session = self.session.as_ref().unwrap();
+        if session
+            .frame_graph
+            .resource_name_map
+            .get(name)
+            .is_some_and(|resource| {
+                session
+                    .disabled_passes
+                    .contains(&FrameGraphResource::new(resource.id))
+            })
+        {
+            // Recorded when flushing instead.
+            return;
+        }
+
         let command_buffer = Self::acquire_command_buffer(&self.ctx, &mut self.command_pools)
             .expect("Failed to acquire command buffer.");
 
@@ -1108,6 +1607,7 @@ impl GraphicsBackendFrameGraphExecutor for VulkanFrameGraphExecutor {
             frame_graph: &session.frame_graph,
             resource_map: &session.resource_map,
             supplied_inputs: &session.supplied_inputs,
+            pass: Some(pass_info),
         };
 
         recorder.begin();
@@ -1120,7 +1620,14 @@ impl GraphicsBackendFrameGraphExecutor for VulkanFrameGraphExecutor {
         };
         recorder.wait_event(*wait_event);
 
+        let end_query = VulkanPassTimestamps::begin_pass(
+            &mut self.pass_timestamps,
+            &self.ctx,
+            &recorder,
+            &session.frame_graph.resource_infos[pass_info.id.id() as usize].name,
+        );
         pass.run(&mut recorder, &ctx);
+        VulkanPassTimestamps::end_pass(&self.pass_timestamps, &self.ctx, &recorder, end_query);
 
         // True for all but the last pass.
         if pass_idx < session.pass_set_events.len() {
@@ -1139,6 +1646,30 @@ impl GraphicsBackendFrameGraphExecutor for VulkanFrameGraphExecutor {
         );
     }
 
+    fn last_pass_timings(&self) -> &[GfxPassTiming] {
+        &self.last_pass_timings
+    }
+
+    fn set_pass_enabled(&mut self, name: &str, enabled: bool) {
+        if enabled {
+            self.disabled_passes.remove(name);
+        } else {
+            self.disabled_passes.insert(name.to_owned());
+        }
+    }
+
+    fn is_pass_enabled(&self, name: &str) -> bool {
+        !self.disabled_passes.contains(name)
+    }
+
+    fn pass_names(&self) -> &[String] {
+        &self.pass_names
+    }
+
+    fn shader_errors(&self) -> &[String] {
+        &self.resource_manager.shader_errors
+    }
+
     fn supply_input(&mut self, name: &str, input_data: Box<dyn std::any::Any>) {
         let session = self.session_mut();
         let resource = session.frame_graph.get_handle_untyped(name);
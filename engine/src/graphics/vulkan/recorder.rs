@@ -2,12 +2,12 @@ use std::{collections::HashMap, sync::Arc};
 
 use nalgebra::Vector2;
 
-use super::device::{VulkanComputePipeline, VulkanContext, VulkanRasterPipeline};
+use super::device::{VulkanComputePipeline, VulkanContext, VulkanFrameStats, VulkanRasterPipeline};
 use crate::common::color::{Color, ColorSpaceSrgb};
 use crate::graphics::backend::{
-    Buffer, ComputePass, ComputePipeline, GfxBlitInfo, GfxImageInfo, GfxRenderPassAttachment,
-    GraphicsBackendComputePass, GraphicsBackendRecorder, GraphicsBackendRenderPass, Image,
-    RasterPipeline, RenderPass, ResourceId, ShaderWriter,
+    Buffer, ComputePass, ComputePipeline, GfxBlitInfo, GfxImageInfo, GfxImageToBufferCopyInfo,
+    GfxRenderPassAttachment, GraphicsBackendComputePass, GraphicsBackendRecorder,
+    GraphicsBackendRenderPass, Image, RasterPipeline, RenderPass, ResourceId, ShaderWriter,
 };
 
 pub struct VulkanRecorder {
@@ -59,6 +59,19 @@ impl VulkanRecorder {
         }
     }
 
+    pub fn write_timestamp(
+        &self,
+        query_pool: ash::vk::QueryPool,
+        query: u32,
+        stage: ash::vk::PipelineStageFlags,
+    ) {
+        unsafe {
+            self.ctx
+                .device()
+                .cmd_write_timestamp(self.command_buffer, stage, query_pool, query)
+        }
+    }
+
     pub fn set_event(&self, event: ash::vk::Event) {
         unsafe {
             self.ctx.device().cmd_set_event(
@@ -109,6 +122,12 @@ impl VulkanRecorder {
                     ash::vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL => {
                         ash::vk::PipelineStageFlags::ALL_GRAPHICS
                     }
+                    // Sampled by a previous compute or raster pass, eg. an image which is blurred
+                    // into and then read back from.
+                    ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL => {
+                        ash::vk::PipelineStageFlags::COMPUTE_SHADER
+                            | ash::vk::PipelineStageFlags::FRAGMENT_SHADER
+                    }
                     _ => todo!(),
                 });
 
@@ -256,6 +275,74 @@ impl GraphicsBackendRecorder for VulkanRecorder {
         }
     }
 
+    fn copy_image_to_buffer(&mut self, info: GfxImageToBufferCopyInfo) {
+        let src_image = self.ctx.resource_manager().get_image(info.src);
+        let dst_buffer = self.ctx.get_buffer(info.dst);
+        assert!(
+            info.src_offset.x + info.src_length.x <= src_image.info.extent.width
+                && info.src_offset.y + info.src_length.y <= src_image.info.extent.height,
+            "Tried to copy outside of the image's bounds, image has extent {:?} but tried to copy {:?} at offset {:?}.",
+            src_image.info.extent,
+            info.src_length,
+            info.src_offset,
+        );
+
+        self.transition_images(
+            &[VulkanImageTransition {
+                image_id: info.src,
+                new_layout: ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                new_access_flags: ash::vk::AccessFlags::TRANSFER_READ,
+            }],
+            None,
+            ash::vk::PipelineStageFlags::TRANSFER,
+        );
+
+        let region = ash::vk::BufferImageCopy::default()
+            .buffer_offset(info.dst_offset)
+            // Zero means tightly packed.
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(src_image.full_subresource_layer())
+            .image_offset(ash::vk::Offset3D {
+                x: info.src_offset.x as i32,
+                y: info.src_offset.y as i32,
+                z: 0,
+            })
+            .image_extent(ash::vk::Extent3D {
+                width: info.src_length.x,
+                height: info.src_length.y,
+                depth: 1,
+            });
+        unsafe {
+            self.ctx.device().cmd_copy_image_to_buffer(
+                self.command_buffer,
+                src_image.image,
+                ash::vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                dst_buffer.buffer,
+                &[region],
+            )
+        };
+
+        // Make the copy visible to the host once the frame finishes.
+        let buffer_barrier = ash::vk::BufferMemoryBarrier::default()
+            .buffer(dst_buffer.buffer)
+            .offset(0)
+            .size(ash::vk::WHOLE_SIZE)
+            .src_access_mask(ash::vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(ash::vk::AccessFlags::HOST_READ);
+        unsafe {
+            self.ctx.device().cmd_pipeline_barrier(
+                self.command_buffer,
+                ash::vk::PipelineStageFlags::TRANSFER,
+                ash::vk::PipelineStageFlags::HOST,
+                ash::vk::DependencyFlags::empty(),
+                &[],
+                &[buffer_barrier],
+                &[],
+            )
+        };
+    }
+
     fn begin_compute_pass<'a>(
         &mut self,
         compute_pipeline_id: ResourceId<ComputePipeline>,
@@ -437,6 +524,15 @@ impl GraphicsBackendComputePass for VulkanComputePass<'_> {
             ash::vk::PipelineStageFlags::COMPUTE_SHADER,
         );
 
+        VulkanFrameStats::add(
+            &self
+                .recorder
+                .ctx
+                .resource_manager()
+                .frame_stats()
+                .descriptor_set_binds,
+            uniform_bind_info.descriptor_sets.len() as u32,
+        );
         unsafe {
             self.recorder.ctx.device().cmd_bind_descriptor_sets(
                 self.recorder.command_buffer,
@@ -454,6 +550,15 @@ impl GraphicsBackendComputePass for VulkanComputePass<'_> {
             self.uniforms_bound,
             "Tried to dispatch without binding uniforms."
         );
+        VulkanFrameStats::add(
+            &self
+                .recorder
+                .ctx
+                .resource_manager()
+                .frame_stats()
+                .dispatches,
+            1,
+        );
         unsafe {
             self.recorder
                 .ctx
@@ -513,6 +618,15 @@ impl GraphicsBackendRenderPass for VulkanRenderPass<'_> {
             ash::vk::PipelineStageFlags::FRAGMENT_SHADER,
         );
 
+        VulkanFrameStats::add(
+            &self
+                .recorder
+                .ctx
+                .resource_manager()
+                .frame_stats()
+                .descriptor_set_binds,
+            uniform_bind_info.descriptor_sets.len() as u32,
+        );
         unsafe {
             self.recorder.ctx.device().cmd_bind_descriptor_sets(
                 self.recorder.command_buffer,
@@ -565,6 +679,7 @@ impl GraphicsBackendRenderPass for VulkanRenderPass<'_> {
     }
 
     fn draw_indexed(&mut self, index_count: u32) {
+        VulkanFrameStats::add(&self.recorder.ctx.resource_manager().frame_stats().draws, 1);
         unsafe {
             self.recorder.ctx.device().cmd_draw_indexed(
                 self.recorder.command_buffer,
@@ -587,6 +702,7 @@ impl GraphicsBackendRenderPass for VulkanRenderPass<'_> {
     ) {
         let draw_buffer = self.recorder.ctx.get_buffer(draw_buffer);
         let draw_count_buffer = self.recorder.ctx.get_buffer(draw_count_buffer);
+        VulkanFrameStats::add(&self.recorder.ctx.resource_manager().frame_stats().draws, 1);
         unsafe {
             self.recorder.ctx.device().cmd_draw_indirect_count(
                 self.recorder.command_buffer,
@@ -2,76 +2,260 @@ use std::collections::HashSet;
 
 use nalgebra::Vector2;
 
+/// Gamepads connected through gilrs, each controller is a player indexed in the order it
+/// connected. A disconnected controller's index is given to the next controller to connect so
+/// the other players keep theirs.
 pub struct Gamepad {
-    gilrs: gilrs::Gilrs,
-
-    left_axis: Vector2<f32>,
-    right_axis: Vector2<f32>,
-
-    pressed_buttons: HashSet<Button>,
-    released_buttons: HashSet<Button>,
-    down_buttons: HashSet<Button>,
+    /// Only `None` for the gamepads built in tests so they don't depend on the host's input
+    /// subsystem.
+    gilrs: Option<gilrs::Gilrs>,
+    players: Vec<Option<GamepadPlayer>>,
 
+    /// Stick and trigger values within this of resting are ignored, the rest of the range is
+    /// rescaled to start from zero.
     pub deadzone: f32,
 }
 
 impl Gamepad {
     pub fn new() -> Self {
+        let gilrs = gilrs::GilrsBuilder::new()
+            .build()
+            .expect("Failed to initialize gamepad context.");
+        // Controllers connected before startup don't send a connected event.
+        let connected = gilrs
+            .gamepads()
+            .map(|(id, pad)| (id.into(), pad.name().to_owned()))
+            .collect::<Vec<_>>();
+        let mut gamepad = Self {
+            gilrs: Some(gilrs),
+            players: Vec::new(),
+            deadzone: 0.1,
+        };
+        for (gamepad_id, name) in connected {
+            gamepad.connect(gamepad_id, name);
+        }
+        gamepad
+    }
+
+    /// A gamepad with no gilrs context, so no controller will ever connect.
+    #[cfg(test)]
+    pub(crate) fn new_disconnected() -> Self {
         Self {
-            gilrs: gilrs::GilrsBuilder::new()
-                .build()
-                .expect("Failed to initialize gamepad context."),
-            left_axis: Vector2::new(0.0, 0.0),
-            right_axis: Vector2::new(0.0, 0.0),
-            pressed_buttons: HashSet::new(),
-            released_buttons: HashSet::new(),
-            down_buttons: HashSet::new(),
+            gilrs: None,
+            players: Vec::new(),
             deadzone: 0.1,
         }
     }
 
     pub fn collect_events(&mut self) {
-        while let Some(event) = self.gilrs.next_event() {
-            match event.event {
-                gilrs::EventType::ButtonPressed(button, _) => {
-                    self.pressed_buttons.insert(button.into());
-                    self.down_buttons.insert(button.into());
-                }
-                gilrs::EventType::ButtonReleased(button, _) => {
-                    self.released_buttons.insert(button.into());
-                    self.down_buttons.remove(&button.into());
+        // Taken so players can be connected while reading events.
+        let Some(mut gilrs) = self.gilrs.take() else {
+            return;
+        };
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            let gamepad_id = id.into();
+            if matches!(event, gilrs::EventType::Disconnected) {
+                if let Some(player_index) = self.disconnect(gamepad_id) {
+                    log::info!("Gamepad player {} disconnected.", player_index);
                 }
-                gilrs::EventType::AxisChanged(axis, value, _) => match axis {
-                    gilrs::Axis::LeftStickX => {
-                        self.left_axis.x = value;
-                    }
-                    gilrs::Axis::LeftStickY => {
-                        self.left_axis.y = value;
-                    }
-                    gilrs::Axis::RightStickX => {
-                        self.right_axis.x = value;
-                    }
-                    gilrs::Axis::RightStickY => {
-                        self.right_axis.y = value;
-                    }
-                    _ => {}
-                },
-                _ => {}
+                continue;
             }
+            if self.player_index(gamepad_id).is_none() {
+                let name = gilrs.gamepad(id).name().to_owned();
+                let player_index = self.connect(gamepad_id, name.clone());
+                log::info!("Gamepad `{}` connected as player {}.", name, player_index);
+            }
+
+            let input = match event {
+                gilrs::EventType::ButtonPressed(button, _) => SubmitInput::Pressed(button.into()),
+                gilrs::EventType::ButtonReleased(button, _) => SubmitInput::Released(button.into()),
+                // Analog triggers report their value as a button on most controllers.
+                gilrs::EventType::ButtonChanged(gilrs::Button::LeftTrigger2, value, _) => {
+                    SubmitInput::Axis(Axis::LeftTrigger, value)
+                }
+                gilrs::EventType::ButtonChanged(gilrs::Button::RightTrigger2, value, _) => {
+                    SubmitInput::Axis(Axis::RightTrigger, value)
+                }
+                gilrs::EventType::AxisChanged(axis, value, _) => {
+                    let Some(axis) = Axis::from_gilrs(axis) else {
+                        continue;
+                    };
+                    SubmitInput::Axis(axis, value)
+                }
+                _ => continue,
+            };
+            let player_index = self.player_index(gamepad_id).unwrap();
+            self.players[player_index]
+                .as_mut()
+                .unwrap()
+                .submit_input(input);
+        }
+        self.gilrs = Some(gilrs);
+    }
+
+    /// Gives the controller `gamepad_id` a player index if it doesn't have one yet.
+    fn connect(&mut self, gamepad_id: usize, name: String) -> usize {
+        if let Some(player_index) = self.player_index(gamepad_id) {
+            return player_index;
+        }
+
+        let player = GamepadPlayer::new(gamepad_id, name);
+        if let Some(player_index) = self.players.iter().position(Option::is_none) {
+            self.players[player_index] = Some(player);
+            return player_index;
         }
+        self.players.push(Some(player));
+        self.players.len() - 1
+    }
+
+    fn disconnect(&mut self, gamepad_id: usize) -> Option<usize> {
+        let player_index = self.player_index(gamepad_id)?;
+        self.players[player_index] = None;
+        Some(player_index)
+    }
+
+    fn player_index(&self, gamepad_id: usize) -> Option<usize> {
+        self.players.iter().position(|player| {
+            player
+                .as_ref()
+                .is_some_and(|player| player.gamepad_id == gamepad_id)
+        })
     }
 
     pub fn clear_inputs(&mut self) {
+        for player in self.players.iter_mut().flatten() {
+            player.clear_inputs();
+        }
+    }
+
+    /// The controller of player `player_index` if it's connected.
+    pub fn player(&self, player_index: usize) -> Option<&GamepadPlayer> {
+        self.players.get(player_index)?.as_ref()
+    }
+
+    /// The connected players along with their player index.
+    pub fn players(&self) -> impl Iterator<Item = (usize, &GamepadPlayer)> {
+        self.players
+            .iter()
+            .enumerate()
+            .filter_map(|(player_index, player)| Some((player_index, player.as_ref()?)))
+    }
+
+    /// The connected player with the lowest index, the functions below without a player index
+    /// read from this player.
+    pub fn primary_player(&self) -> Option<&GamepadPlayer> {
+        self.players().next().map(|(_, player)| player)
+    }
+
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.primary_player()
+            .map_or(0.0, |player| player.axis(axis, self.deadzone))
+    }
+
+    pub fn left_axis(&self) -> Vector2<f32> {
+        Vector2::new(self.axis(Axis::LeftStickX), self.axis(Axis::LeftStickY))
+    }
+
+    pub fn right_axis(&self) -> Vector2<f32> {
+        Vector2::new(self.axis(Axis::RightStickX), self.axis(Axis::RightStickY))
+    }
+
+    pub fn is_button_pressed(&self, button: Button) -> bool {
+        self.primary_player()
+            .is_some_and(|player| player.is_button_pressed(button))
+    }
+
+    pub fn is_button_down(&self, button: Button) -> bool {
+        self.primary_player()
+            .is_some_and(|player| player.is_button_down(button))
+    }
+
+    pub fn is_button_released(&self, button: Button) -> bool {
+        self.primary_player()
+            .is_some_and(|player| player.is_button_released(button))
+    }
+}
+
+#[derive(Debug)]
+pub enum SubmitInput {
+    Pressed(Button),
+    Released(Button),
+    Axis(Axis, f32),
+}
+
+/// The state of one connected controller.
+pub struct GamepadPlayer {
+    gamepad_id: usize,
+    name: String,
+
+    axes: [f32; Axis::ALL.len()],
+    pressed_buttons: HashSet<Button>,
+    released_buttons: HashSet<Button>,
+    down_buttons: HashSet<Button>,
+}
+
+impl GamepadPlayer {
+    fn new(gamepad_id: usize, name: String) -> Self {
+        Self {
+            gamepad_id,
+            name,
+            axes: [0.0; Axis::ALL.len()],
+            pressed_buttons: HashSet::new(),
+            released_buttons: HashSet::new(),
+            down_buttons: HashSet::new(),
+        }
+    }
+
+    pub fn submit_input(&mut self, input: SubmitInput) {
+        match input {
+            SubmitInput::Pressed(button) => {
+                self.pressed_buttons.insert(button);
+                self.down_buttons.insert(button);
+            }
+            SubmitInput::Released(button) => {
+                self.released_buttons.insert(button);
+                self.down_buttons.remove(&button);
+            }
+            SubmitInput::Axis(axis, value) => {
+                self.axes[axis as usize] = value;
+            }
+        }
+    }
+
+    fn clear_inputs(&mut self) {
         self.pressed_buttons.clear();
         self.released_buttons.clear();
     }
 
-    pub fn left_axis(&self) -> &Vector2<f32> {
-        &self.left_axis
+    /// The name of the controller reported by the os.
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
-    pub fn right_axis(&self) -> &Vector2<f32> {
-        &self.right_axis
+    /// The value of `axis` without the deadzone, sticks are in [-1, 1] with positive y being up
+    /// and triggers are in [0, 1].
+    pub fn raw_axis(&self, axis: Axis) -> f32 {
+        self.axes[axis as usize]
+    }
+
+    /// The value of `axis` with `deadzone` applied, the deadzone of a stick is radial so
+    /// diagonals aren't snapped to an axis.
+    pub fn axis(&self, axis: Axis, deadzone: f32) -> f32 {
+        let stick = |x: Axis, y: Axis| {
+            apply_stick_deadzone(Vector2::new(self.raw_axis(x), self.raw_axis(y)), deadzone)
+        };
+        match axis {
+            Axis::LeftStickX => stick(Axis::LeftStickX, Axis::LeftStickY).x,
+            Axis::LeftStickY => stick(Axis::LeftStickX, Axis::LeftStickY).y,
+            Axis::RightStickX => stick(Axis::RightStickX, Axis::RightStickY).x,
+            Axis::RightStickY => stick(Axis::RightStickX, Axis::RightStickY).y,
+            Axis::LeftTrigger | Axis::RightTrigger => apply_deadzone(self.raw_axis(axis), deadzone),
+        }
+    }
+
+    /// The buttons pressed this frame.
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = &Button> {
+        self.pressed_buttons.iter()
     }
 
     pub fn is_button_pressed(&self, button: Button) -> bool {
@@ -87,7 +271,61 @@ impl Gamepad {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+/// Zeroes `value` within `deadzone` of zero and rescales the rest back to [0, 1].
+pub fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    let deadzone = deadzone.clamp(0.0, 0.99);
+    let magnitude = value.abs();
+    if magnitude <= deadzone {
+        return 0.0;
+    }
+    value.signum() * ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0)
+}
+
+/// `apply_deadzone` on the length of `stick`, keeping its direction.
+pub fn apply_stick_deadzone(stick: Vector2<f32>, deadzone: f32) -> Vector2<f32> {
+    let length = stick.norm();
+    if length <= f32::EPSILON {
+        return Vector2::zeros();
+    }
+    stick * (apply_deadzone(length, deadzone) / length)
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Axis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 6] = [
+        Axis::LeftStickX,
+        Axis::LeftStickY,
+        Axis::RightStickX,
+        Axis::RightStickY,
+        Axis::LeftTrigger,
+        Axis::RightTrigger,
+    ];
+
+    fn from_gilrs(axis: gilrs::Axis) -> Option<Self> {
+        match axis {
+            gilrs::Axis::LeftStickX => Some(Axis::LeftStickX),
+            gilrs::Axis::LeftStickY => Some(Axis::LeftStickY),
+            gilrs::Axis::RightStickX => Some(Axis::RightStickX),
+            gilrs::Axis::RightStickY => Some(Axis::RightStickY),
+            gilrs::Axis::LeftZ => Some(Axis::LeftTrigger),
+            gilrs::Axis::RightZ => Some(Axis::RightTrigger),
+            _ => None,
+        }
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize,
+)]
 /// Copied from gilrs::Button.
 ///
 /// Gamepad's elements which state can be represented by value from 0.0 to 1.0.
@@ -175,3 +413,27 @@ impl From<Button> for gilrs::Button {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_rescales_sticks_and_triggers() {
+        assert_eq!(apply_deadzone(0.05, 0.1), 0.0);
+        assert!((apply_deadzone(0.55, 0.1) - 0.5).abs() < 1e-6);
+        assert!((apply_deadzone(-1.0, 0.1) + 1.0).abs() < 1e-6);
+
+        let mut player = GamepadPlayer::new(0, "test".to_owned());
+        player.submit_input(SubmitInput::Axis(Axis::LeftStickX, 0.08));
+        player.submit_input(SubmitInput::Axis(Axis::LeftStickY, 0.08));
+        // Each component is within the deadzone but the stick as a whole isn't.
+        let x = player.axis(Axis::LeftStickX, 0.1);
+        let y = player.axis(Axis::LeftStickY, 0.1);
+        assert!(x > 0.0 && (x - y).abs() < 1e-6, "{} {}", x, y);
+        assert_eq!(player.axis(Axis::RightStickX, 0.1), 0.0);
+
+        player.submit_input(SubmitInput::Axis(Axis::RightTrigger, 1.0));
+        assert_eq!(player.axis(Axis::RightTrigger, 0.1), 1.0);
+    }
+}
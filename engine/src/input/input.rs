@@ -3,11 +3,11 @@ use nalgebra::Vector3;
 use rogue_macros::Resource;
 
 use crate::common::geometry::ray::Ray;
-use crate::consts;
 use crate::input::gamepad;
 use crate::physics::transform::Transform;
 use crate::resource::Res;
 use crate::resource::ResMut;
+use crate::settings::Settings;
 use crate::window::window::Window;
 
 use winit::event::DeviceEvent as WinitDeviceEvent;
@@ -27,6 +27,8 @@ pub struct Input {
     gamepad: Gamepad,
     keybinds: Keybinds,
     pub input_block: bool,
+    /// Set while a text field has keyboard focus so typing doesn't trigger actions.
+    pub text_input_focused: bool,
 }
 
 macro_rules! return_if_input_block {
@@ -46,27 +48,14 @@ macro_rules! return_if_input_block_vec2 {
 }
 
 impl Input {
-    pub fn new() -> Self {
-        let mut keybinds = Keybinds::new();
-        keybinds.register_key(
-            consts::actions::EDITOR_TOGGLE,
-            consts::actions::keybind::EDITOR_TOGGLE_DEBUG,
-        );
-        keybinds.register_key(
-            consts::actions::EDITOR_GIZMO_TRANSLATION,
-            consts::actions::keybind::EDITOR_GIZMO_TRANSLATION,
-        );
-        keybinds.register_key(
-            consts::actions::EDITOR_GIZMO_ROTATION,
-            consts::actions::keybind::EDITOR_GIZMO_ROTATION,
-        );
-
+    pub fn new(keybinds: Keybinds) -> Self {
         Self {
             keyboard: Keyboard::new(),
             mouse: Mouse::new(),
             gamepad: Gamepad::new(),
             keybinds,
             input_block: false,
+            text_input_focused: false,
         }
     }
 
@@ -76,7 +65,8 @@ impl Input {
         input.gamepad.clear_inputs();
     }
 
-    pub fn collect_gamepad_events(mut input: ResMut<Input>) {
+    pub fn collect_gamepad_events(mut input: ResMut<Input>, settings: Res<Settings>) {
+        input.gamepad.deadzone = settings.gamepad_deadzone;
         input.gamepad.collect_events();
     }
 
@@ -101,10 +91,9 @@ impl Input {
     // General Input
     pub fn movement_axes(&self) -> Vector2<f32> {
         return_if_input_block_vec2!(self);
-        if self.gamepad.left_axis().x.abs() >= self.gamepad.deadzone
-            || self.gamepad.left_axis().y.abs() >= self.gamepad.deadzone
-        {
-            return *self.gamepad.left_axis();
+        let left_axis = self.gamepad.left_axis();
+        if left_axis != Vector2::zeros() {
+            return left_axis;
         }
 
         let mut axes = Vector2::new(0.0, 0.0);
@@ -124,24 +113,30 @@ impl Input {
         return axes;
     }
 
+    /// True on the frame `action`'s binding is pressed, see `ActionBinding::is_pressed`.
     pub fn is_action_pressed(&self, action: &str) -> bool {
         return_if_input_block!(self);
-        let key = *self
+        if self.text_input_focused {
+            return false;
+        }
+        let binding = self
             .keybinds
-            .pressed_key_mappings
-            .get(action)
+            .binding(action)
             .expect("Action does not exist.");
-        return self.is_key_pressed(key);
+        return binding.is_pressed(&self.keyboard, &self.mouse, &self.gamepad);
     }
 
     pub fn did_action(&self, action: &str) -> bool {
-        return_if_input_block!(self);
-        let key = *self
-            .keybinds
-            .pressed_key_mappings
-            .get(action)
-            .expect("Action does not exist.");
-        return self.is_key_pressed(key);
+        self.is_action_pressed(action)
+    }
+
+    /// The value of `axis` on the first connected gamepad with the deadzone applied, the other
+    /// players are read through `Input::gamepad`.
+    pub fn gamepad_axis(&self, axis: gamepad::Axis) -> f32 {
+        if self.input_block {
+            return 0.0;
+        }
+        self.gamepad.axis(axis)
     }
 
     pub fn is_controller_button_pressed(&self, button: gamepad::Button) -> bool {
@@ -161,13 +156,12 @@ impl Input {
 
     pub fn is_controller_camera(&self) -> bool {
         return_if_input_block!(self);
-        self.gamepad.right_axis().x.abs() >= self.gamepad.deadzone
-            || self.gamepad.right_axis().y.abs() >= self.gamepad.deadzone
+        self.gamepad.right_axis() != Vector2::zeros()
     }
     pub fn camera_axes(&self) -> Vector2<f32> {
         return_if_input_block_vec2!(self);
         if self.is_controller_camera() {
-            return *self.gamepad.right_axis();
+            return self.gamepad.right_axis();
         }
 
         return self.mouse.mouse_delta();
@@ -244,11 +238,21 @@ impl Input {
         self.mouse.mouse_position()
     }
 
+    /// The raw relative mouse motion this frame, unlike the cursor position it keeps updating
+    /// while the cursor is locked or against the edge of the window.
     pub fn mouse_delta(&self) -> Vector2<f32> {
         return_if_input_block_vec2!(self);
         self.mouse.mouse_delta()
     }
 
+    pub fn keybinds(&self) -> &Keybinds {
+        &self.keybinds
+    }
+
+    pub fn keybinds_mut(&mut self) -> &mut Keybinds {
+        &mut self.keybinds
+    }
+
     pub fn keyboard(&self) -> &Keyboard {
         &self.keyboard
     }
@@ -265,6 +269,10 @@ impl Input {
         &mut self.mouse
     }
 
+    pub fn gamepad(&self) -> &Gamepad {
+        &self.gamepad
+    }
+
     pub fn handle_winit_device_event(&mut self, device_id: WinitDeviceId, event: WinitDeviceEvent) {
         match event {
             WinitDeviceEvent::Key(key_event) => {
@@ -48,6 +48,11 @@ impl Keyboard {
         self.pressed_keys.contains(&key)
     }
 
+    /// The keys pressed this frame.
+    pub fn pressed_keys(&self) -> impl Iterator<Item = &Key> {
+        self.pressed_keys.iter()
+    }
+
     pub fn is_key_pressed_with_modifiers(&self, key: Key, modifiers: &[Modifier]) -> bool {
         self.is_key_pressed(key) && self.is_modifiers_down(modifiers)
     }
@@ -93,7 +98,7 @@ pub enum SubmitInput {
     Released(Key),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
 pub enum Key {
     A,
     B,
@@ -218,6 +223,16 @@ impl Key {
             WinitKeyCode::KeyX => Some(Key::X),
             WinitKeyCode::KeyY => Some(Key::Y),
             WinitKeyCode::KeyZ => Some(Key::Z),
+            WinitKeyCode::Digit0 | WinitKeyCode::Numpad0 => Some(Key::Num0),
+            WinitKeyCode::Digit1 | WinitKeyCode::Numpad1 => Some(Key::Num1),
+            WinitKeyCode::Digit2 | WinitKeyCode::Numpad2 => Some(Key::Num2),
+            WinitKeyCode::Digit3 | WinitKeyCode::Numpad3 => Some(Key::Num3),
+            WinitKeyCode::Digit4 | WinitKeyCode::Numpad4 => Some(Key::Num4),
+            WinitKeyCode::Digit5 | WinitKeyCode::Numpad5 => Some(Key::Num5),
+            WinitKeyCode::Digit6 | WinitKeyCode::Numpad6 => Some(Key::Num6),
+            WinitKeyCode::Digit7 | WinitKeyCode::Numpad7 => Some(Key::Num7),
+            WinitKeyCode::Digit8 | WinitKeyCode::Numpad8 => Some(Key::Num8),
+            WinitKeyCode::Digit9 | WinitKeyCode::Numpad9 => Some(Key::Num9),
             WinitKeyCode::Escape => Some(Key::Escape),
             WinitKeyCode::F1 => Some(Key::F1),
             WinitKeyCode::F2 => Some(Key::F2),
@@ -266,7 +281,7 @@ impl Key {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
 pub enum Modifier {
     Shift,
     Control,
@@ -274,7 +289,9 @@ pub enum Modifier {
 }
 
 impl Modifier {
-    pub(crate) fn get_keys(&self) -> Vec<Key> {
+    pub const ALL: [Modifier; 3] = [Modifier::Shift, Modifier::Control, Modifier::Alt];
+
+    pub fn get_keys(&self) -> Vec<Key> {
         match self {
             Modifier::Shift => vec![Key::LShift, Key::RShift],
             Modifier::Control => vec![Key::LControl, Key::RControl],
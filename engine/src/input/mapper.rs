@@ -1,20 +1,410 @@
 use std::collections::HashMap;
 
-use super::keyboard::Key;
+use crate::consts;
 
+use super::{
+    gamepad::{self, Gamepad},
+    keyboard::{Key, Keyboard, Modifier},
+    mouse::{self, Mouse},
+};
+
+/// The key, mouse button or gamepad button which triggers an action.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
+pub enum ActionInput {
+    Key(Key),
+    MouseButton(mouse::Button),
+    GamepadButton(gamepad::Button),
+}
+
+/// The modifiers which must be held for an action binding.
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Debug, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(default)]
+pub struct ActionModifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+}
+
+impl ActionModifiers {
+    pub const NONE: ActionModifiers = ActionModifiers {
+        shift: false,
+        control: false,
+        alt: false,
+    };
+
+    pub const fn with(mut self, modifier: Modifier) -> Self {
+        match modifier {
+            Modifier::Shift => self.shift = true,
+            Modifier::Control => self.control = true,
+            Modifier::Alt => self.alt = true,
+        }
+        self
+    }
+
+    pub fn contains(&self, modifier: Modifier) -> bool {
+        match modifier {
+            Modifier::Shift => self.shift,
+            Modifier::Control => self.control,
+            Modifier::Alt => self.alt,
+        }
+    }
+
+    /// The modifiers currently held on `keyboard`.
+    pub fn held(keyboard: &Keyboard) -> Self {
+        Modifier::ALL
+            .into_iter()
+            .filter(|modifier| keyboard.is_modifiers_down(&[*modifier]))
+            .fold(Self::NONE, Self::with)
+    }
+}
+
+/// A key or mouse button along with the modifiers it must be pressed with, such as `Ctrl+S`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(from = "ActionBindingRepr")]
+pub struct ActionBinding {
+    pub input: ActionInput,
+    pub modifiers: ActionModifiers,
+}
+
+/// Keybinds saved before actions supported chords are just the key.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum ActionBindingRepr {
+    Key(Key),
+    Binding {
+        input: ActionInput,
+        #[serde(default)]
+        modifiers: ActionModifiers,
+    },
+}
+
+impl From<ActionBindingRepr> for ActionBinding {
+    fn from(repr: ActionBindingRepr) -> Self {
+        match repr {
+            ActionBindingRepr::Key(key) => ActionBinding::key(key),
+            ActionBindingRepr::Binding { input, modifiers } => ActionBinding { input, modifiers },
+        }
+    }
+}
+
+impl ActionBinding {
+    pub const fn key(key: Key) -> Self {
+        Self {
+            input: ActionInput::Key(key),
+            modifiers: ActionModifiers::NONE,
+        }
+    }
+
+    pub const fn mouse_button(button: mouse::Button) -> Self {
+        Self {
+            input: ActionInput::MouseButton(button),
+            modifiers: ActionModifiers::NONE,
+        }
+    }
+
+    pub const fn gamepad_button(button: gamepad::Button) -> Self {
+        Self {
+            input: ActionInput::GamepadButton(button),
+            modifiers: ActionModifiers::NONE,
+        }
+    }
+
+    pub const fn with_modifier(mut self, modifier: Modifier) -> Self {
+        self.modifiers = self.modifiers.with(modifier);
+        self
+    }
+
+    /// True on the frame the input is pressed with exactly the binding's modifiers held, so an
+    /// action bound to `C` doesn't also trigger on `Ctrl+C`. Gamepad buttons are read from the
+    /// first connected gamepad and ignore the keyboard modifiers.
+    pub fn is_pressed(&self, keyboard: &Keyboard, mouse: &Mouse, gamepad: &Gamepad) -> bool {
+        let is_input_pressed = match self.input {
+            ActionInput::Key(key) => keyboard.is_key_pressed(key),
+            ActionInput::MouseButton(button) => mouse.is_mouse_button_pressed(button),
+            ActionInput::GamepadButton(button) => return gamepad.is_button_pressed(button),
+        };
+        is_input_pressed
+            && Modifier::ALL.into_iter().all(|modifier| {
+                // A modifier key bound on its own is held by pressing it.
+                let is_bound_key = matches!(
+                    self.input,
+                    ActionInput::Key(key) if modifier.get_keys().contains(&key)
+                );
+                is_bound_key
+                    || self.modifiers.contains(modifier) == keyboard.is_modifiers_down(&[modifier])
+            })
+    }
+}
+
+impl std::fmt::Display for ActionBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, name) in [
+            (Modifier::Control, "Ctrl"),
+            (Modifier::Shift, "Shift"),
+            (Modifier::Alt, "Alt"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.input {
+            ActionInput::Key(key) => write!(f, "{:?}", key),
+            ActionInput::MouseButton(button) => write!(f, "Mouse {:?}", button),
+            ActionInput::GamepadButton(button) => write!(f, "Gamepad {:?}", button),
+        }
+    }
+}
+
+/// Maps action names to the binding which triggers them, loaded from and saved with the user
+/// settings so actions can be rebound at runtime.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct Keybinds {
-    pub pressed_key_mappings: HashMap</*action=*/ String, Key>,
+    pub action_bindings: HashMap</*action=*/ String, ActionBinding>,
 }
 
 impl Keybinds {
+    /// Every rebindable action along with its default binding, in the order they are listed.
+    pub const DEFAULT_KEYBINDS: [(&'static str, ActionBinding); 15] = [
+        (
+            consts::actions::EDITOR_TOGGLE,
+            consts::actions::keybind::EDITOR_TOGGLE,
+        ),
+        (
+            consts::actions::EDITOR_TOGGLE_DEBUG,
+            consts::actions::keybind::EDITOR_TOGGLE_DEBUG,
+        ),
+        (
+            consts::actions::EDITOR_GIZMO_TRANSLATION,
+            consts::actions::keybind::EDITOR_GIZMO_TRANSLATION,
+        ),
+        (
+            consts::actions::EDITOR_GIZMO_ROTATION,
+            consts::actions::keybind::EDITOR_GIZMO_ROTATION,
+        ),
+        (
+            consts::actions::EDITOR_SAVE,
+            consts::actions::keybind::EDITOR_SAVE,
+        ),
+        (
+            consts::actions::EDITOR_VIEW_FRONT,
+            consts::actions::keybind::EDITOR_VIEW_FRONT,
+        ),
+        (
+            consts::actions::EDITOR_VIEW_BACK,
+            consts::actions::keybind::EDITOR_VIEW_BACK,
+        ),
+        (
+            consts::actions::EDITOR_VIEW_RIGHT,
+            consts::actions::keybind::EDITOR_VIEW_RIGHT,
+        ),
+        (
+            consts::actions::EDITOR_VIEW_LEFT,
+            consts::actions::keybind::EDITOR_VIEW_LEFT,
+        ),
+        (
+            consts::actions::EDITOR_VIEW_TOP,
+            consts::actions::keybind::EDITOR_VIEW_TOP,
+        ),
+        (
+            consts::actions::EDITOR_VIEW_BOTTOM,
+            consts::actions::keybind::EDITOR_VIEW_BOTTOM,
+        ),
+        (
+            consts::actions::EDITOR_EYEDROPPER,
+            consts::actions::keybind::EDITOR_EYEDROPPER,
+        ),
+        (
+            consts::actions::EDITOR_SCREENSHOT,
+            consts::actions::keybind::EDITOR_SCREENSHOT,
+        ),
+        (
+            consts::actions::EDITOR_FRAME_SELECTION,
+            consts::actions::keybind::EDITOR_FRAME_SELECTION,
+        ),
+        (
+            consts::actions::EDITOR_CLEAR_MEASUREMENT,
+            consts::actions::keybind::EDITOR_CLEAR_MEASUREMENT,
+        ),
+    ];
+
     pub fn new() -> Self {
         Self {
-            pressed_key_mappings: HashMap::new(),
+            action_bindings: HashMap::new(),
+        }
+    }
+
+    pub fn default_keybinds() -> Self {
+        let mut keybinds = Self::new();
+        keybinds.insert_missing_defaults();
+        keybinds
+    }
+
+    pub fn register_binding(&mut self, action_name: impl ToString, binding: ActionBinding) {
+        self.action_bindings
+            .insert(action_name.to_string(), binding);
+    }
+
+    pub fn binding(&self, action_name: &str) -> Option<ActionBinding> {
+        self.action_bindings.get(action_name).copied()
+    }
+
+    /// Rebinds `action_name` to `binding`, warning if another action is already bound to it.
+    pub fn set_binding(&mut self, action_name: &str, binding: ActionBinding) {
+        for other in self.conflicting_actions(action_name, binding) {
+            log::warn!(
+                "Binding {} to action `{}` conflicts with action `{}`.",
+                binding,
+                action_name,
+                other
+            );
+        }
+        self.register_binding(action_name, binding);
+    }
+
+    /// The actions other than `action_name` which are bound to `binding`.
+    pub fn conflicting_actions<'a>(
+        &'a self,
+        action_name: &'a str,
+        binding: ActionBinding,
+    ) -> impl Iterator<Item = &'a str> {
+        self.action_bindings
+            .iter()
+            .filter(move |(other, other_binding)| {
+                **other_binding == binding && other.as_str() != action_name
+            })
+            .map(|(other, _)| other.as_str())
+    }
+
+    /// Binds any action missing a binding to its default, such as actions added after the user
+    /// settings were saved.
+    pub fn insert_missing_defaults(&mut self) {
+        for (action_name, binding) in Self::DEFAULT_KEYBINDS {
+            self.action_bindings
+                .entry(action_name.to_owned())
+                .or_insert(binding);
         }
     }
 
-    pub fn register_key(&mut self, action_name: impl ToString, key: Key) {
-        self.pressed_key_mappings
-            .insert(action_name.to_string(), key);
+    pub fn reset_to_defaults(&mut self) {
+        *self = Self::default_keybinds();
+    }
+}
+
+impl Default for Keybinds {
+    fn default() -> Self {
+        Self::default_keybinds()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keyboard::SubmitInput;
+
+    #[test]
+    fn keybinds_conflicts_and_defaults() {
+        let mut keybinds = Keybinds::default_keybinds();
+        assert_eq!(
+            keybinds.binding(consts::actions::EDITOR_TOGGLE_DEBUG),
+            Some(consts::actions::keybind::EDITOR_TOGGLE_DEBUG)
+        );
+        assert_eq!(
+            keybinds
+                .conflicting_actions(consts::actions::EDITOR_TOGGLE, ActionBinding::key(Key::F2))
+                .count(),
+            0
+        );
+
+        keybinds.set_binding(consts::actions::EDITOR_TOGGLE, ActionBinding::key(Key::T));
+        assert_eq!(
+            keybinds
+                .conflicting_actions(consts::actions::EDITOR_TOGGLE, ActionBinding::key(Key::T))
+                .collect::<Vec<_>>(),
+            vec![consts::actions::EDITOR_GIZMO_TRANSLATION]
+        );
+
+        // Saved keybinds missing an action get its default back.
+        keybinds
+            .action_bindings
+            .remove(consts::actions::EDITOR_GIZMO_ROTATION);
+        keybinds.insert_missing_defaults();
+        assert_eq!(
+            keybinds.binding(consts::actions::EDITOR_GIZMO_ROTATION),
+            Some(consts::actions::keybind::EDITOR_GIZMO_ROTATION)
+        );
+        assert_eq!(
+            keybinds.binding(consts::actions::EDITOR_TOGGLE),
+            Some(ActionBinding::key(Key::T))
+        );
+
+        keybinds.reset_to_defaults();
+        assert_eq!(
+            keybinds.binding(consts::actions::EDITOR_TOGGLE),
+            Some(consts::actions::keybind::EDITOR_TOGGLE)
+        );
+    }
+
+    #[test]
+    fn chords_need_exact_modifiers() {
+        let save = ActionBinding::key(Key::S).with_modifier(Modifier::Control);
+        let plain = ActionBinding::key(Key::S);
+        let mouse = Mouse::new();
+        let gamepad = Gamepad::new_disconnected();
+
+        let mut keyboard = Keyboard::new();
+        keyboard.submit_input(SubmitInput::Pressed(Key::S));
+        assert!(!save.is_pressed(&keyboard, &mouse, &gamepad));
+        assert!(plain.is_pressed(&keyboard, &mouse, &gamepad));
+
+        let mut keyboard = Keyboard::new();
+        keyboard.submit_input(SubmitInput::Pressed(Key::RControl));
+        keyboard.submit_input(SubmitInput::Pressed(Key::S));
+        assert!(save.is_pressed(&keyboard, &mouse, &gamepad));
+        assert!(!plain.is_pressed(&keyboard, &mouse, &gamepad));
+
+        // Held but not pressed this frame.
+        keyboard.clear_inputs();
+        assert!(!save.is_pressed(&keyboard, &mouse, &gamepad));
+
+        let mut mouse = Mouse::new();
+        mouse.submit_input(mouse::SubmitInput::Pressed(mouse::Button::Middle));
+        let keyboard = Keyboard::new();
+        assert!(
+            ActionBinding::mouse_button(mouse::Button::Middle)
+                .is_pressed(&keyboard, &mouse, &gamepad)
+        );
+        assert!(
+            !ActionBinding::mouse_button(mouse::Button::Right)
+                .is_pressed(&keyboard, &mouse, &gamepad)
+        );
+        assert_eq!(save.to_string(), "Ctrl+S");
+
+        let south = ActionBinding::gamepad_button(gamepad::Button::South);
+        assert!(!south.is_pressed(&keyboard, &mouse, &gamepad));
+        assert_eq!(south.to_string(), "Gamepad South");
+    }
+
+    #[test]
+    fn keybinds_saved_as_keys_still_load() {
+        let json = r#"{
+            "editor_toggle": "F3",
+            "editor_save": { "input": { "Key": "S" }, "modifiers": { "control": true } }
+        }"#;
+        let keybinds: Keybinds = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            keybinds.binding(consts::actions::EDITOR_TOGGLE),
+            Some(ActionBinding::key(Key::F3))
+        );
+        assert_eq!(
+            keybinds.binding(consts::actions::EDITOR_SAVE),
+            Some(consts::actions::keybind::EDITOR_SAVE)
+        );
+
+        let saved = serde_json::to_string(&keybinds).unwrap();
+        let loaded: Keybinds = serde_json::from_str(&saved).unwrap();
+        assert_eq!(keybinds.action_bindings, loaded.action_bindings);
     }
 }
@@ -53,6 +53,11 @@ impl Mouse {
         }
     }
 
+    /// The buttons pressed this frame.
+    pub fn pressed_buttons(&self) -> impl Iterator<Item = &Button> {
+        self.pressed_buttons.iter()
+    }
+
     pub fn is_mouse_button_pressed(&self, button: Button) -> bool {
         self.pressed_buttons.contains(&button)
     }
@@ -86,7 +91,7 @@ pub enum SubmitInput {
     ScrollDelta(f32),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
 pub enum Button {
     Left,
     Right,
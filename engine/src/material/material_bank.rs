@@ -39,6 +39,8 @@ pub struct MaterialBank {
 
     loading_materials: HashSet<MaterialId>,
     to_load_material_assets: HashMap<GameAssetPath, Vec<MaterialId>>,
+    /// Material assets being loaded by the asset loader, with the material ids that use them.
+    loading_material_assets: HashMap<GameAssetPath, (AssetHandle, Vec<MaterialId>)>,
 
     to_send_events: Vec<MaterialBankEvent>,
 }
@@ -55,6 +57,7 @@ impl MaterialBank {
 
             loading_materials: HashSet::new(),
             to_load_material_assets: HashMap::new(),
+            loading_material_assets: HashMap::new(),
 
             to_send_events: Vec::new(),
         }
@@ -188,37 +191,62 @@ impl MaterialBank {
         };
 
         for (asset_path, material_ids) in material_bank.to_load_material_assets.drain() {
-            let material_asset = Assets::load_asset_sync::<MaterialAsset>(
-                AssetPath::new_game_assets_dir(assets_dir.clone(), &asset_path.asset_path),
-            );
-            match material_asset {
-                Ok(mut loaded_asset) => {
-                    loaded_asset.asset_path = Some(asset_path.clone());
-                    let asset_id = material_bank.materials.push(loaded_asset.clone());
-                    material_bank
-                        .asset_path_map
-                        .insert(asset_path.clone(), asset_id);
-                    for material_id in material_ids {
-                        material_bank.id_to_asset_map.insert(material_id, asset_id);
-                        material_bank.loading_materials.remove(&material_id);
-                    }
-                    events.push(MaterialBankEvent::AssetCreated(asset_id));
-                }
-                Err(err) => {
-                    log::error!(
-                        "Error loading material asset at path {:?} for material {:?}: {}",
-                        asset_path,
-                        material_ids
-                            .iter()
-                            .map(|id| material_bank.id_to_name.get(id))
-                            .collect::<Vec<_>>(),
-                        err
-                    );
-                    for material_id in material_ids {
-                        material_bank.loading_materials.remove(&material_id);
-                    }
-                }
+            if let Some((_, loading_ids)) =
+                material_bank.loading_material_assets.get_mut(&asset_path)
+            {
+                loading_ids.extend(material_ids);
+                continue;
+            }
+            let handle = assets.load_asset::<MaterialAsset>(AssetPath::new_game_assets_dir(
+                assets_dir.clone(),
+                &asset_path.asset_path,
+            ));
+            material_bank
+                .loading_material_assets
+                .insert(asset_path, (handle, material_ids));
+        }
+
+        let mut finished_asset_paths = Vec::new();
+        for (asset_path, (handle, material_ids)) in &material_bank.loading_material_assets {
+            let error = match assets.get_asset_status(handle) {
+                AssetStatus::InProgress => continue,
+                AssetStatus::Loaded => None,
+                AssetStatus::NotFound => Some("Asset doesn't exist.".to_owned()),
+                AssetStatus::Error(err) => Some(err.to_string()),
+                AssetStatus::Saved => unreachable!("Material assets are only loaded here."),
+            };
+            if let Some(error) = error {
+                log::error!(
+                    "Error loading material asset at path {:?} for material {:?}: {}",
+                    asset_path,
+                    material_ids
+                        .iter()
+                        .map(|id| material_bank.id_to_name.get(id))
+                        .collect::<Vec<_>>(),
+                    error
+                );
+            }
+            finished_asset_paths.push(asset_path.clone());
+        }
+
+        for asset_path in finished_asset_paths {
+            let (handle, material_ids) = material_bank
+                .loading_material_assets
+                .remove(&asset_path)
+                .unwrap();
+            for material_id in &material_ids {
+                material_bank.loading_materials.remove(material_id);
+            }
+            let Some(mut loaded_asset) = assets.take_asset::<MaterialAsset>(&handle) else {
+                continue;
+            };
+            loaded_asset.asset_path = Some(asset_path.clone());
+            let asset_id = material_bank.materials.push(*loaded_asset);
+            material_bank.asset_path_map.insert(asset_path, asset_id);
+            for material_id in material_ids {
+                material_bank.id_to_asset_map.insert(material_id, asset_id);
             }
+            events.push(MaterialBankEvent::AssetCreated(asset_id));
         }
     }
 
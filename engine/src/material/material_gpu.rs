@@ -44,6 +44,8 @@ pub struct MaterialBankGpu {
     material_textures: FreeList<Option<ResourceId<Image>>>,
     asset_to_texture_map: HashMap<GameAssetPath, MaterialGpuTextureId>,
     loading_textures: HashMap<GameAssetPath, LoadingTexture>,
+    /// Kept to upload the texture again when its image asset is reloaded.
+    loaded_textures: HashMap<GameAssetPath, LoadingTexture>,
 
     material_samplers: FreeList<ResourceId<Sampler>>,
     options_to_sampler_map: HashMap<MaterialSamplerOptions, MaterialGpuSamplerId>,
@@ -66,6 +68,7 @@ impl MaterialBankGpu {
             material_textures: FreeList::new(),
             asset_to_texture_map: HashMap::new(),
             loading_textures: HashMap::new(),
+            loaded_textures: HashMap::new(),
 
             material_samplers: FreeList::new(),
             options_to_sampler_map: HashMap::new(),
@@ -96,6 +99,7 @@ impl MaterialBankGpu {
             min_filter: GfxFilterMode::Linear,
             mipmap_filter: GfxFilterMode::Linear,
             address_mode: GfxAddressMode::Repeat,
+            ..Default::default()
         });
         let sampler_index = material_samplers.push(sampler);
         options_to_sampler_map.insert(options.clone(), sampler_index);
@@ -129,7 +133,7 @@ impl MaterialBankGpu {
         // Not loaded yet, start loading.
         let image_asset_path =
             asset_path.as_file_asset_path(assets.project_dir().as_ref().unwrap());
-        let image_asset_handle = assets.load_asset::<ImageAsset>(image_asset_path);
+        let image_asset_handle = assets.load_watched_asset::<ImageAsset>(image_asset_path);
         let loading_texture = LoadingTexture {
             asset_handle: image_asset_handle,
             texture_index,
@@ -148,8 +152,26 @@ impl MaterialBankGpu {
     ) {
         let material_bank_gpu = &mut material_bank_gpu as &mut MaterialBankGpu;
 
+        // Textures whose image changed on disk are uploaded again.
+        let reloaded_textures = material_bank_gpu
+            .loaded_textures
+            .iter()
+            .filter(|(_, loaded_texture)| assets.is_asset_reloaded(&loaded_texture.asset_handle))
+            .map(|(loaded_path, _)| loaded_path.clone())
+            .collect::<Vec<_>>();
+        for reloaded_path in reloaded_textures {
+            let loaded_texture = material_bank_gpu
+                .loaded_textures
+                .remove(&reloaded_path)
+                .unwrap();
+            material_bank_gpu
+                .loading_textures
+                .insert(reloaded_path, loaded_texture);
+        }
+
         // Update the status of any currently loading textures.
         let mut finished_textures = Vec::new();
+        let mut loaded_textures = Vec::new();
         for (loading_path, loading_texture) in &material_bank_gpu.loading_textures {
             match assets.get_asset_status(&loading_texture.asset_handle) {
                 AssetStatus::InProgress => {}
@@ -159,17 +181,28 @@ impl MaterialBankGpu {
                         .get_asset::<ImageAsset>(&loading_texture.asset_handle)
                         .expect("Texture asset should be loaded by now.");
                     let image_data = image_asset.convert_to_rgba();
-                    let gpu_image = device.create_image(GfxImageCreateInfo {
-                        name: format!("texture_{}", &loading_path.asset_path),
-                        image_type: GfxImageType::D2,
-                        format: GfxImageFormat::Rgba8Unorm,
-                        extent: image_asset.size,
+                    // Reloaded images of the same size are written over the existing image.
+                    let existing_image = material_bank_gpu
+                        .material_textures
+                        .get(loading_texture.texture_index)
+                        .and_then(|texture| *texture)
+                        .filter(|image| {
+                            device.get_image_info(image).resolution_xy() == image_asset.size
+                        });
+                    let gpu_image = existing_image.unwrap_or_else(|| {
+                        device.create_image(GfxImageCreateInfo {
+                            name: format!("texture_{}", &loading_path.asset_path),
+                            image_type: GfxImageType::D2,
+                            format: GfxImageFormat::Rgba8Unorm,
+                            extent: image_asset.size,
+                        })
                     });
                     device.write_image(GfxImageWrite {
                         image: gpu_image,
                         data: &image_data,
                         offset: Vector2::new(0, 0),
                         extent: image_asset.size,
+                        layer: 0,
                     });
                     log::info!("Loaded material texture {:?}", loading_path);
 
@@ -192,7 +225,7 @@ impl MaterialBankGpu {
                             .collect::<Vec<_>>()
                     );
 
-                    finished_textures.push(loading_path.clone());
+                    loaded_textures.push(loading_path.clone());
                 }
                 AssetStatus::NotFound => {
                     log::error!(
@@ -212,7 +245,20 @@ impl MaterialBankGpu {
             }
         }
         for finished_path in finished_textures {
-            material_bank_gpu.loading_textures.remove(&finished_path);
+            let failed_texture = material_bank_gpu
+                .loading_textures
+                .remove(&finished_path)
+                .unwrap();
+            assets.unwatch_asset(&failed_texture.asset_handle);
+        }
+        for loaded_path in loaded_textures {
+            let loaded_texture = material_bank_gpu
+                .loading_textures
+                .remove(&loaded_path)
+                .unwrap();
+            material_bank_gpu
+                .loaded_textures
+                .insert(loaded_path, loaded_texture);
         }
 
         // Load and register any new project materials for the gpu representation
@@ -5,6 +5,7 @@ pub struct Fbm<T: Noise> {
     options: FbmOptions,
 }
 
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct FbmOptions {
     /// How much the frequency changes each octave.
     pub lacunarity: f32,
@@ -1,11 +1,14 @@
+use nalgebra::Vector3;
+
 use super::transform::Transform;
 use crate::common::color::ColorSrgba;
 use crate::common::geometry::aabb::AABB;
 use crate::common::geometry::obb::OBB;
+use crate::common::geometry::ray::Ray;
 use crate::common::{color::Color, geometry::shape::Shape};
 use crate::debug::debug_renderer::DebugRenderer;
 use crate::egui::util::{position_ui, rotation_ui, scale_ui};
-use crate::physics::collider::{Collider, ColliderDebugColoring, ContactManifold};
+use crate::physics::collider::{Collider, ColliderDebugColoring, ColliderRayHit, ContactManifold};
 use crate::physics::collider_voxel_registry::VoxelColliderRegistry;
 use erased_serde::Serialize;
 
@@ -13,12 +16,14 @@ use erased_serde::Serialize;
 #[serde(default)]
 pub struct BoxCollider {
     pub obb: OBB,
+    pub is_trigger: bool,
 }
 
 impl Default for BoxCollider {
     fn default() -> Self {
         Self {
             obb: OBB::new_identity(),
+            is_trigger: false,
         }
     }
 }
@@ -42,6 +47,34 @@ impl Collider for BoxCollider {
         Some(world_transform.transform_obb(&self.obb).bounding_aabb())
     }
 
+    fn raycast(
+        &self,
+        world_transform: &Transform,
+        ray: &Ray,
+        max_distance: f32,
+        _: &VoxelColliderRegistry,
+    ) -> Option<ColliderRayHit> {
+        let obb = world_transform.transform_obb(&self.obb);
+        let hit = ray.intersect_obb(&obb)?;
+        if hit.t_enter > max_distance {
+            return None;
+        }
+
+        // The entered face is on the axis which was entered last.
+        let local_dir = obb.rotation.inverse() * ray.dir;
+        let axis = hit.t_min.imax();
+        let mut local_normal = Vector3::zeros();
+        local_normal[axis] = -local_dir[axis].signum();
+        Some(ColliderRayHit {
+            distance: hit.t_enter,
+            normal: obb.rotation * local_normal,
+        })
+    }
+
+    fn is_trigger(&self) -> bool {
+        self.is_trigger
+    }
+
     fn render_debug(
         &self,
         world_transform: &Transform,
@@ -98,5 +131,6 @@ impl Collider for BoxCollider {
         if center != original_center || half_side_length != original_half_side_length {
             self.obb.aabb = AABB::new_center_extents(center, half_side_length);
         }
+        ui.checkbox(&mut self.is_trigger, "Trigger");
     }
 }
@@ -3,8 +3,12 @@ use nalgebra::{UnitQuaternion, Vector3};
 use super::transform::Transform;
 use crate::common::color::{Color, ColorSrgba};
 use crate::common::geometry::aabb::AABB;
+use crate::common::geometry::ray::Ray;
 use crate::debug::debug_renderer::{DebugRenderer, DebugShapeFlags};
-use crate::physics::collider::{ColliderDebugColoring, ContactManifold};
+use crate::common::geometry::obb::OBB;
+use crate::physics::collider::{
+    ColliderDebugColoring, ColliderRayHit, ContactManifold, ContactPoint, TimeOfImpact,
+};
 use crate::physics::collider_voxel_registry::VoxelColliderRegistry;
 use crate::physics::{box_collider::BoxCollider, collider::Collider};
 
@@ -15,6 +19,8 @@ pub struct CapsuleCollider {
     pub orientation: UnitQuaternion<f32>,
     pub radius: f32,
     pub half_height: f32,
+    #[serde(default)]
+    pub is_trigger: bool,
 }
 
 impl CapsuleCollider {
@@ -24,6 +30,7 @@ impl CapsuleCollider {
             orientation: UnitQuaternion::identity(),
             radius: 1.0,
             half_height: 0.5,
+            is_trigger: false,
         }
     }
 
@@ -33,20 +40,35 @@ impl CapsuleCollider {
         let top = self.center + self.orientation * up;
         return (bottom, top);
     }
+
+    /// The world space segment end points and radius, the radius is scaled by the largest
+    /// axis of the transform's scale.
+    pub fn world_segment(&self, world_transform: &Transform) -> (Vector3<f32>, Vector3<f32>, f32) {
+        let (bottom, top) = self.bottom_top_points();
+        let to_world = |point: Vector3<f32>| {
+            world_transform.position
+                + world_transform.rotation * point.component_mul(&world_transform.scale)
+        };
+        let radius = self.radius * world_transform.scale.max();
+        (to_world(bottom), to_world(top), radius)
+    }
 }
 
 impl Collider for CapsuleCollider {
     const NAME: &str = "CapsuleCollider";
 
     fn aabb(&self, world_transform: &Transform, _: &VoxelColliderRegistry) -> Option<AABB> {
-        let up = Vector3::y() * self.half_height;
-        let forward = Vector3::z() * self.radius;
-        let right = Vector3::x() * self.radius;
-        let min = self.center + self.orientation * (-up - forward - right);
-        let max = self.center + self.orientation * (up + forward + right);
+        let (bottom, top, radius) = self.world_segment(world_transform);
+        let radius = Vector3::repeat(radius);
+        let min = bottom.inf(&top) - radius;
+        let max = bottom.sup(&top) + radius;
         return Some(AABB::new_two_point(min, max));
     }
 
+    fn is_trigger(&self) -> bool {
+        self.is_trigger
+    }
+
     fn collider_component_ui(&mut self, ui: &mut egui::Ui) {
         use crate::egui::util::{position_ui, rotation_ui};
         position_ui(ui, &mut self.center);
@@ -71,6 +93,30 @@ impl Collider for CapsuleCollider {
             );
             self.half_height = (height - (2.0 * self.radius)) * 0.5;
         });
+        ui.checkbox(&mut self.is_trigger, "Trigger");
+    }
+
+    fn raycast(
+        &self,
+        world_transform: &Transform,
+        ray: &Ray,
+        max_distance: f32,
+        _: &VoxelColliderRegistry,
+    ) -> Option<ColliderRayHit> {
+        let (bottom, top, radius) = self.world_segment(world_transform);
+
+        // Starting inside the capsule hits immediately.
+        let distance = ray
+            .intersect_line_segment(bottom, top, radius, max_distance)?
+            .max(0.0);
+        let hit_point = ray.origin + ray.dir * distance;
+        let segment = top - bottom;
+        let segment_t =
+            ((hit_point - bottom).dot(&segment) / segment.norm_squared()).clamp(0.0, 1.0);
+        let normal = (hit_point - (bottom + segment * segment_t))
+            .try_normalize(f32::EPSILON)
+            .unwrap_or(-ray.dir);
+        Some(ColliderRayHit { distance, normal })
     }
 
     fn render_debug(
@@ -116,30 +162,102 @@ pub fn box_capsule_collision_test(
     transform_box: &Transform,
     transform_capsule: &Transform,
 ) -> Option<ContactManifold> {
-    let transform_box = transform_box.to_transformation_matrix();
-    let transform_capsule = transform_capsule.to_transformation_matrix();
-
-    let (mut min, mut max) = box_collider.obb.rotated_min_max();
-    min = transform_box.transform_vector(&min);
-    max = transform_box.transform_vector(&max);
-
-    let (mut bottom, mut top) = capsule.bottom_top_points();
-    bottom = transform_capsule.transform_vector(&bottom);
-    top = transform_capsule.transform_vector(&top);
+    let obb = transform_box.transform_obb(&box_collider.obb);
+    let (bottom, top, radius) = capsule.world_segment(transform_capsule);
 
-    let lv = top - bottom;
-    let mv = min - bottom;
-    // Projective of mv onto the line lv.
-    let p_t = (mv.dot(&lv) / lv.norm_squared()).clamp(0.0, 1.0);
-    let pv = p_t * lv;
+    // Work in the box's local space where it is centered and axis aligned.
+    let box_center = obb.aabb.center();
+    let half_length = obb.aabb.half_side_length();
+    let to_local = |point: Vector3<f32>| obb.rotation.inverse() * (point - box_center);
+    let (bottom, top) = (to_local(bottom), to_local(top));
+    let segment = top - bottom;
 
-    let closest_vec = pv - min;
+    // Find the closest points between the segment and box by alternately projecting onto each,
+    // which converges since both are convex.
+    let clamp_to_box = |point: Vector3<f32>| point.zip_map(&half_length, |x, h| x.clamp(-h, h));
+    let mut segment_point = bottom + segment * 0.5;
+    for _ in 0..16 {
+        let box_point = clamp_to_box(segment_point);
+        let t = if segment.norm_squared() > 0.0 {
+            ((box_point - bottom).dot(&segment) / segment.norm_squared()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        segment_point = bottom + segment * t;
+    }
+    let box_point = clamp_to_box(segment_point);
 
-    if closest_vec.norm() > capsule.radius {
+    let offset = segment_point - box_point;
+    let distance = offset.norm();
+    if distance > radius {
         return None;
     }
+    let (local_normal, penetration) = if distance > f32::EPSILON {
+        (offset / distance, distance - radius)
+    } else {
+        // The segment is inside the box so push out along the shallowest axis.
+        let depth = half_length - segment_point.abs();
+        let axis = depth.imin();
+        let mut normal = Vector3::zeros();
+        normal[axis] = if segment_point[axis] < 0.0 { -1.0 } else { 1.0 };
+        (normal, -(depth[axis] + radius))
+    };
+
+    Some(ContactManifold {
+        points: vec![ContactPoint {
+            position: obb.rotation * box_point + box_center,
+            distance: penetration,
+            normal_impulse: 0.0,
+            tangent_impulse: 0.0,
+        }],
+        normal: obb.rotation * local_normal,
+    })
+}
+
+/// Sweeps the capsule along `motion` against the box, the capsule is approximated by spheres at
+/// its ends and center so this is meant for small fast bodies. Spheres starting within the box
+/// are left to the discrete contacts.
+pub fn capsule_box_time_of_impact(
+    capsule: &CapsuleCollider,
+    box_collider: &BoxCollider,
+    transform_capsule: &Transform,
+    transform_box: &Transform,
+    motion: Vector3<f32>,
+) -> Option<TimeOfImpact> {
+    let (bottom, top, radius) = capsule.world_segment(transform_capsule);
+    let obb = transform_box.transform_obb(&box_collider.obb);
+    // Sweeping a sphere against a box is roughly a ray against the box grown by the radius.
+    let radius = Vector3::repeat(radius);
+    let grown_obb = OBB::new(
+        AABB::new_two_point(obb.aabb.min - radius, obb.aabb.max + radius),
+        obb.rotation,
+        Vector3::zeros(),
+    );
+    let center = grown_obb.aabb.center();
+    let half_length = grown_obb.aabb.half_side_length();
+    let local_motion = grown_obb.rotation.inverse() * motion;
 
-    let box_to_capsule_penetration = closest_vec.normalize() * capsule.radius;
+    let mut closest: Option<TimeOfImpact> = None;
+    for point in [bottom, (bottom + top) * 0.5, top] {
+        let local_point = grown_obb.rotation.inverse() * (point - center);
+        if (0..3).all(|i| local_point[i].abs() < half_length[i]) {
+            continue;
+        }
+        let Some(hit) = Ray::new(point, motion).intersect_obb(&grown_obb) else {
+            continue;
+        };
+        if hit.t_enter > 1.0 || closest.as_ref().is_some_and(|toi| toi.t <= hit.t_enter) {
+            continue;
+        }
 
-    todo!();
+        // The entered face is on the axis which was entered last.
+        let axis = hit.t_min.imax();
+        let mut local_normal = Vector3::zeros();
+        local_normal[axis] = -local_motion[axis].signum();
+        closest = Some(TimeOfImpact {
+            t: hit.t_enter,
+            normal: grown_obb.rotation * local_normal,
+        });
+    }
+    closest
 }
@@ -1,13 +1,28 @@
 use nalgebra::Vector3;
 
 use crate::common::color::{Color, ColorSrgba};
-use crate::common::geometry::aabb::AABB;
+use crate::common::geometry::{aabb::AABB, ray::Ray};
 use crate::debug::debug_renderer::DebugRenderer;
 use crate::entity::ecs_world::Entity;
 use crate::physics::collider_registry::ColliderRegistry;
 use crate::physics::collider_voxel_registry::VoxelColliderRegistry;
 use crate::physics::{collider_registry::ColliderId, transform::Transform};
 
+/// Where a ray first hits a collider.
+pub struct ColliderRayHit {
+    /// Distance along the normalized ray direction.
+    pub distance: f32,
+    pub normal: Vector3<f32>,
+}
+
+/// Where a collider swept along a motion first touches another collider.
+pub struct TimeOfImpact {
+    /// Fraction of the motion from 0 to 1 travelled before touching.
+    pub t: f32,
+    /// The surface normal of the hit collider facing the swept collider.
+    pub normal: Vector3<f32>,
+}
+
 pub struct ContactPoint {
     pub position: Vector3<f32>,
     // Distance along `ContactManifold.normal`, negative if penetrating.
@@ -75,7 +90,7 @@ where
         collider_registry: &ColliderRegistry,
     ) -> Option<ContactManifold> {
         let collider_a = collider_registry.get_collider::<A>(collider_id_a);
-        let collider_b = collider_registry.get_collider::<B>(collider_id_a);
+        let collider_b = collider_registry.get_collider::<B>(collider_id_b);
         self(
             collider_a,
             collider_b,
@@ -98,7 +113,7 @@ where
         collider_registry: &ColliderRegistry,
     ) -> Option<ContactManifold> {
         let collider_a = collider_registry.get_collider::<A>(collider_id_a);
-        let collider_b = collider_registry.get_collider::<B>(collider_id_a);
+        let collider_b = collider_registry.get_collider::<B>(collider_id_b);
         let voxel_collider_registry = &collider_registry.voxel_collider_registry;
         self(
             collider_a,
@@ -197,6 +212,24 @@ pub trait Collider: Clone + 'static {
         dst_ptr: *mut u8,
     ) -> erased_serde::Result<()>;
 
+    /// The first hit of `ray` within `max_distance`, `ray.dir` must be normalized. Colliders
+    /// which don't support raycasts are never hit.
+    fn raycast(
+        &self,
+        world_transform: &Transform,
+        ray: &Ray,
+        max_distance: f32,
+        voxel_registry: &VoxelColliderRegistry,
+    ) -> Option<ColliderRayHit> {
+        None
+    }
+
+    /// Triggers only report overlaps with `TriggerOverlapEvent`s and never physically push
+    /// bodies apart.
+    fn is_trigger(&self) -> bool {
+        false
+    }
+
     fn render_debug(
         &self,
         world_transform: &Transform,
@@ -225,6 +258,16 @@ pub trait ColliderMethods: downcast::Any {
         ser: &mut dyn erased_serde::Serializer,
     ) -> erased_serde::Result<()>;
 
+    fn raycast(
+        &self,
+        world_transform: &Transform,
+        ray: &Ray,
+        max_distance: f32,
+        voxel_registry: &VoxelColliderRegistry,
+    ) -> Option<ColliderRayHit>;
+
+    fn is_trigger(&self) -> bool;
+
     fn render_debug(
         &self,
         world_transform: &Transform,
@@ -250,6 +293,20 @@ impl<T: Collider> ColliderMethods for T {
         Collider::serialize_collider(self, ser)
     }
 
+    fn raycast(
+        &self,
+        world_transform: &Transform,
+        ray: &Ray,
+        max_distance: f32,
+        voxel_registry: &VoxelColliderRegistry,
+    ) -> Option<ColliderRayHit> {
+        Collider::raycast(self, world_transform, ray, max_distance, voxel_registry)
+    }
+
+    fn is_trigger(&self) -> bool {
+        Collider::is_trigger(self)
+    }
+
     fn render_debug(
         &self,
         world_transform: &Transform,
@@ -43,11 +43,14 @@ impl GameComponent for EntityColliders {
         ctx: &mut crate::entity::component::GameComponentCloneContext<'_>,
         dst_ptr: *mut u8,
     ) {
-        let new_colliders = self
-            .colliders
-            .iter()
-            .map(|id| ctx.collider_registry.clone_collider(id))
-            .collect::<Vec<_>>();
+        let new_colliders = if ctx.share_colliders {
+            self.colliders.clone()
+        } else {
+            self.colliders
+                .iter()
+                .map(|id| ctx.collider_registry.clone_collider(id))
+                .collect::<Vec<_>>()
+        };
 
         // Safety: dst_ptr should be allocated with the memory layout for this type.
         unsafe {
@@ -1,8 +1,13 @@
-use std::{any::TypeId, collections::HashMap, ptr::NonNull};
+use std::{
+    any::TypeId,
+    collections::{HashMap, HashSet},
+    ptr::NonNull,
+};
 
 use crate::entity::ecs_world::{ECSWorld, Entity};
-use crate::physics::capsule_collider::CapsuleCollider;
+use crate::physics::capsule_collider::{self, CapsuleCollider};
 use crate::physics::collider_voxel_registry::{self, VoxelColliderRegistry};
+use crate::physics::plane_collider::PlaneCollider;
 use crate::physics::voxel_collider::VoxelModelCollider;
 use crate::physics::{
     box_collider::{self, BoxCollider},
@@ -13,7 +18,7 @@ use crate::physics::{
     collider_component::EntityColliders,
     transform::Transform,
 };
-use crate::world::terrain::region_pos::RegionPos;
+use crate::common::geometry::aabb::AABB;
 use crate::common::{
     dyn_vec::{DynVecCloneable, TypeInfoCloneable},
     vtable,
@@ -42,9 +47,13 @@ impl ColliderIntersectionPair {
 
 type ColliderMethodsVtablePtr = *const ();
 
-// Spatial hashmap binning colliders per region.
+// Spatial hashmap binning colliders into a uniform grid of cells.
 pub struct ColliderRegistry {
-    pub bins: HashMap</*region_pos*/ Vector3<i32>, Vec<(Entity, ColliderId)>>,
+    pub bins: HashMap</*cell_pos*/ Vector3<i32>, Vec<(Entity, ColliderId)>>,
+    /// Colliders spanning too many cells to bin, these are paired with every other collider.
+    pub unbinned: Vec<(Entity, ColliderId)>,
+    /// The side length of the cells `bins` was last built with.
+    pub bin_cell_size: f32,
 
     pub colliders: HashMap<TypeId, DynVecCloneable>,
 
@@ -53,7 +62,9 @@ pub struct ColliderRegistry {
     pub collider_type_info: HashMap</*Collider::NAME*/ String, TypeInfoCloneable>,
     pub collider_names: HashMap<TypeId, /*Collider::NAME*/ String>,
 
-    intersection_functions: HashMap<ColliderIntersectionPair, ColliderIntersectionTestCaller>,
+    /// The intersection function along with the collider type it expects first.
+    intersection_functions:
+        HashMap<ColliderIntersectionPair, (TypeId, ColliderIntersectionTestCaller)>,
 
     pub voxel_collider_registry: VoxelColliderRegistry,
 }
@@ -62,6 +73,8 @@ impl ColliderRegistry {
     pub fn new() -> Self {
         let mut reg = Self {
             bins: HashMap::new(),
+            unbinned: Vec::new(),
+            bin_cell_size: 1.0,
             colliders: HashMap::new(),
             collider_vtables: HashMap::new(),
             collider_deserialize_fns: HashMap::new(),
@@ -74,10 +87,14 @@ impl ColliderRegistry {
 
         reg.register_collider_type::<BoxCollider>();
         reg.register_collider_type::<CapsuleCollider>();
+        reg.register_collider_type::<PlaneCollider>();
         reg.register_collider_type::<VoxelModelCollider>();
         reg.register_collider_intersection_fn::<BoxCollider, BoxCollider, _, _>(
             box_collider::test_intersection_box_box,
         );
+        reg.register_collider_intersection_fn::<BoxCollider, CapsuleCollider, _, _>(
+            capsule_collider::box_capsule_collision_test,
+        );
         reg.register_collider_intersection_fn::<VoxelModelCollider, VoxelModelCollider, _, _>(
             collider_voxel_registry::test_intersection_voxel_voxel,
         );
@@ -96,9 +113,13 @@ impl ColliderRegistry {
     ) {
         let pair =
             ColliderIntersectionPair::new(std::any::TypeId::of::<A>(), std::any::TypeId::of::<B>());
-        let old = self
-            .intersection_functions
-            .insert(pair, ColliderIntersectionTestCaller::new(func));
+        let old = self.intersection_functions.insert(
+            pair,
+            (
+                std::any::TypeId::of::<A>(),
+                ColliderIntersectionTestCaller::new(func),
+            ),
+        );
         assert!(
             old.is_none(),
             "Already register intersection function for colliders {} and {}",
@@ -198,41 +219,124 @@ impl ColliderRegistry {
         return collider_id.index < colliders.len();
     }
 
-    pub fn update_entity_collider_positions(&mut self, ecs_world: &mut ECSWorld) {
+    /// Colliders overlapping more cells than this are left unbinned, so a huge collider such
+    /// as a voxel model doesn't fill thousands of cells.
+    const MAX_BINNED_CELLS: f64 = 64.0;
+    /// Keeps the automatic cell size sane when most colliders are tiny or flat.
+    const MIN_BIN_CELL_SIZE: f32 = 0.1;
+
+    /// Bins every collider into the grid cells its world aabb overlaps. A `cell_size` of None
+    /// uses the median collider size so most colliders only overlap a few cells.
+    pub fn update_entity_collider_positions(
+        &mut self,
+        ecs_world: &mut ECSWorld,
+        cell_size: Option<f32>,
+    ) {
         // Clear all the bins and then populate each one with the colliders.
         // This is trading off so we do O(2n) here so we don't do O(n^2) during collision
-        // detection. TODO: Benchmark and also don't clear entire bin each time and figure out how
-        // to selectively modify colliders.
+        // detection. TODO: Don't clear entire bin each time and figure out how to selectively
+        // modify colliders.
         self.bins.clear();
+        self.unbinned.clear();
+        let mut collider_aabbs = Vec::new();
         for (entity, (transform, colliders)) in ecs_world
             .query::<(&Transform, &EntityColliders)>()
             .into_iter()
         {
-            let world_transform = ecs_world.get_world_transform(entity, transform);
+            let world_transform = ecs_world.get_global_transform(entity, transform);
             for collider_id in &colliders.colliders {
                 let Some(aabb) = self
                     .get_collider_dyn(collider_id)
                     .aabb(&world_transform, &self.voxel_collider_registry)
                 else {
                     // Collider isn't ready to be used yet.
-                    return;
+                    continue;
                 };
-                let region_min = RegionPos::from_world_pos(&aabb.min);
-                let region_max = RegionPos::from_world_pos(&aabb.max);
-                for region_x in region_min.x..=region_max.x {
-                    for region_y in region_min.y..=region_max.y {
-                        for region_z in region_min.z..=region_max.z {
-                            self.bins
-                                .entry(Vector3::new(region_x, region_y, region_z))
-                                .or_default()
-                                .push((entity, collider_id.clone()));
-                        }
+                collider_aabbs.push((entity, *collider_id, aabb));
+            }
+        }
+
+        self.bin_cell_size = cell_size
+            .unwrap_or_else(|| Self::median_collider_size(&collider_aabbs))
+            .max(Self::MIN_BIN_CELL_SIZE);
+        let inv_cell_size = 1.0 / self.bin_cell_size;
+        for (entity, collider_id, aabb) in collider_aabbs {
+            let cell_min = aabb.min.map(|x| (x * inv_cell_size).floor() as i64);
+            let cell_max = aabb.max.map(|x| (x * inv_cell_size).floor() as i64);
+            let cell_count = (cell_max - cell_min).map(|x| (x + 1) as f64).product();
+            if cell_count > Self::MAX_BINNED_CELLS {
+                self.unbinned.push((entity, collider_id));
+                continue;
+            }
+            for cell_x in cell_min.x..=cell_max.x {
+                for cell_y in cell_min.y..=cell_max.y {
+                    for cell_z in cell_min.z..=cell_max.z {
+                        self.bins
+                            .entry(Vector3::new(cell_x as i32, cell_y as i32, cell_z as i32))
+                            .or_default()
+                            .push((entity, collider_id));
                     }
                 }
             }
         }
     }
 
+    fn median_collider_size(collider_aabbs: &[(Entity, ColliderId, AABB)]) -> f32 {
+        let mut sizes = collider_aabbs
+            .iter()
+            .map(|(_, _, aabb)| aabb.side_length().max())
+            .collect::<Vec<_>>();
+        if sizes.is_empty() {
+            return 1.0;
+        }
+        let middle = sizes.len() / 2;
+        *sizes
+            .select_nth_unstable_by(middle, |a, b| a.total_cmp(b))
+            .1
+    }
+
+    /// The collider pairs which share a bin, plus every unbinned collider paired with all
+    /// other colliders. Each pair is returned once and colliders of the same entity are never
+    /// paired. The pairs are sorted so the solver sees contacts in the same order regardless
+    /// of how the colliders were binned.
+    pub fn broad_phase_candidate_pairs(&self) -> Vec<[(Entity, ColliderId); 2]> {
+        let sort_key = |(_, id): &(Entity, ColliderId)| (id.collider_type, id.index);
+        let mut tested_pairs = HashSet::new();
+        let mut pairs = Vec::new();
+        let mut try_push_pair = |a: &(Entity, ColliderId), b: &(Entity, ColliderId)| {
+            if a.0 == b.0 {
+                return;
+            }
+            let (a, b) = if sort_key(a) <= sort_key(b) {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            if tested_pairs.insert((a.1, b.1)) {
+                pairs.push([*a, *b]);
+            }
+        };
+
+        for bin in self.bins.values() {
+            for (i, a) in bin.iter().enumerate() {
+                for b in &bin[(i + 1)..] {
+                    try_push_pair(a, b);
+                }
+            }
+        }
+        for (i, a) in self.unbinned.iter().enumerate() {
+            for b in self.unbinned[(i + 1)..]
+                .iter()
+                .chain(self.bins.values().flatten())
+            {
+                try_push_pair(a, b);
+            }
+        }
+
+        pairs.sort_unstable_by_key(|[a, b]| (sort_key(a), sort_key(b)));
+        pairs
+    }
+
     pub fn clone_collider(&mut self, collider_id: &ColliderId) -> ColliderId {
         let vec = self
             .colliders
@@ -255,17 +359,31 @@ impl ColliderRegistry {
     ) -> Option<ContactManifold> {
         let pair =
             ColliderIntersectionPair::new(collider_id_a.collider_type, collider_id_b.collider_type);
-        let Some(func) = self.intersection_functions.get(&pair) else {
+        let Some((first_type, func)) = self.intersection_functions.get(&pair) else {
             return None;
         };
 
-        func.run_erased(
-            collider_id_a,
+        if *first_type == collider_id_a.collider_type {
+            return func.run_erased(
+                collider_id_a,
+                collider_id_b,
+                entity_transform_a,
+                entity_transform_b,
+                self,
+            );
+        }
+
+        // The function expects the colliders the other way around, so the normal will be
+        // pointing from B to A.
+        let mut manifold = func.run_erased(
             collider_id_b,
-            entity_transform_a,
+            collider_id_a,
             entity_transform_b,
+            entity_transform_a,
             self,
-        )
+        )?;
+        manifold.normal = -manifold.normal;
+        Some(manifold)
     }
 
     pub fn get_collider_dyn(&self, collider_id: &ColliderId) -> &dyn ColliderMethods {
@@ -356,3 +474,26 @@ impl ColliderId {
         self.collider_type == TypeId::of::<()>()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colliders_bin_while_another_collider_is_not_ready() {
+        let mut registry = ColliderRegistry::new();
+        let mut ecs_world = ECSWorld::new();
+        // A voxel collider without a model has no aabb until its model loads.
+        let voxel_collider = registry.register_collider(VoxelModelCollider::default());
+        let box_collider = registry.register_collider(BoxCollider::default());
+        let mut colliders = EntityColliders::new();
+        colliders.colliders.push(voxel_collider);
+        colliders.colliders.push(box_collider);
+        let entity = ecs_world.spawn((Transform::new(), colliders));
+
+        registry.update_entity_collider_positions(&mut ecs_world, Some(1.0));
+        let binned = registry.bins.values().flatten().collect::<Vec<_>>();
+        assert!(binned.contains(&&(entity, box_collider)));
+        assert!(!binned.contains(&&(entity, voxel_collider)));
+    }
+}
@@ -0,0 +1,291 @@
+use std::time::Duration;
+
+use nalgebra::Vector3;
+
+use super::{rigid_body::RigidBody, transform::Transform};
+use crate::common::freelist::FreeListHandle;
+use crate::entity::ecs_world::Entity;
+
+pub type JointId = FreeListHandle<Joint>;
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum JointKind {
+    /// Keeps the anchors `distance` meters apart.
+    Distance { distance: f32 },
+    /// Keeps the anchors together and only lets the bodies rotate relative to each other around
+    /// the hinge axis.
+    Hinge {
+        local_axis_a: Vector3<f32>,
+        local_axis_b: Vector3<f32>,
+        /// Perpendicular to the axes, the hinge angle is zero when these line up.
+        local_reference_a: Vector3<f32>,
+        local_reference_b: Vector3<f32>,
+        /// The min and max hinge angle in radians.
+        limits: Option<(f32, f32)>,
+    },
+}
+
+/// Constrains two entities relative to each other, solved alongside contacts. Entities without
+/// a rigid body act as static anchors. Anchors and axes are local to each entity's position and
+/// rotation, scale is ignored.
+#[derive(Clone, Debug)]
+pub struct Joint {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    pub local_anchor_a: Vector3<f32>,
+    pub local_anchor_b: Vector3<f32>,
+    pub kind: JointKind,
+}
+
+impl Joint {
+    /// How much of the positional error is corrected each second, relative to the timestep.
+    const BAUMGARTE_FACTOR: f32 = 0.2;
+
+    /// Keeps the world anchors at their current distance apart.
+    pub fn new_distance(
+        entity_a: Entity,
+        world_transform_a: &Transform,
+        world_anchor_a: Vector3<f32>,
+        entity_b: Entity,
+        world_transform_b: &Transform,
+        world_anchor_b: Vector3<f32>,
+    ) -> Self {
+        Self {
+            entity_a,
+            entity_b,
+            local_anchor_a: to_local_point(world_transform_a, world_anchor_a),
+            local_anchor_b: to_local_point(world_transform_b, world_anchor_b),
+            kind: JointKind::Distance {
+                distance: world_anchor_a.metric_distance(&world_anchor_b),
+            },
+        }
+    }
+
+    /// Hinges both entities around `world_axis` through `world_pivot`, the current relative
+    /// rotation is the zero hinge angle.
+    pub fn new_hinge(
+        entity_a: Entity,
+        world_transform_a: &Transform,
+        entity_b: Entity,
+        world_transform_b: &Transform,
+        world_pivot: Vector3<f32>,
+        world_axis: Vector3<f32>,
+        limits: Option<(f32, f32)>,
+    ) -> Self {
+        let world_axis = world_axis.normalize();
+        let world_reference = any_perpendicular(&world_axis);
+        let inv_rotation_a = world_transform_a.rotation.inverse();
+        let inv_rotation_b = world_transform_b.rotation.inverse();
+        Self {
+            entity_a,
+            entity_b,
+            local_anchor_a: to_local_point(world_transform_a, world_pivot),
+            local_anchor_b: to_local_point(world_transform_b, world_pivot),
+            kind: JointKind::Hinge {
+                local_axis_a: inv_rotation_a * world_axis,
+                local_axis_b: inv_rotation_b * world_axis,
+                local_reference_a: inv_rotation_a * world_reference,
+                local_reference_b: inv_rotation_b * world_reference,
+                limits,
+            },
+        }
+    }
+
+    pub fn world_anchors(
+        &self,
+        world_transform_a: &Transform,
+        world_transform_b: &Transform,
+    ) -> (Vector3<f32>, Vector3<f32>) {
+        (
+            world_transform_a.position + world_transform_a.rotation * self.local_anchor_a,
+            world_transform_b.position + world_transform_b.rotation * self.local_anchor_b,
+        )
+    }
+
+    /// The rotation of b relative to a around the hinge axis in radians, None if this isn't a
+    /// hinge.
+    pub fn hinge_angle(
+        &self,
+        world_transform_a: &Transform,
+        world_transform_b: &Transform,
+    ) -> Option<f32> {
+        let JointKind::Hinge {
+            local_axis_a,
+            local_reference_a,
+            local_reference_b,
+            ..
+        } = &self.kind
+        else {
+            return None;
+        };
+        let axis = world_transform_a.rotation * local_axis_a;
+        let reference_a = world_transform_a.rotation * local_reference_a;
+        let reference_b = world_transform_b.rotation * local_reference_b;
+        Some(
+            reference_a
+                .cross(&reference_b)
+                .dot(&axis)
+                .atan2(reference_a.dot(&reference_b)),
+        )
+    }
+
+    /// Applies one iteration of impulses pushing the bodies' velocities toward satisfying the
+    /// joint, with a bias correcting the positional error.
+    pub fn solve_velocities(
+        &self,
+        world_transform_a: &Transform,
+        rb_a: &mut RigidBody,
+        world_transform_b: &Transform,
+        rb_b: &mut RigidBody,
+        timestep: Duration,
+    ) {
+        let bias_factor = Self::BAUMGARTE_FACTOR / timestep.as_secs_f32();
+        let (anchor_a, anchor_b) = self.world_anchors(world_transform_a, world_transform_b);
+        let center_to_anchor_a = anchor_a - world_transform_a.position;
+        let center_to_anchor_b = anchor_b - world_transform_b.position;
+        let offset = anchor_b - anchor_a;
+
+        match &self.kind {
+            JointKind::Distance { distance } => {
+                let length = offset.norm();
+                if length <= f32::EPSILON {
+                    return;
+                }
+                let direction = offset / length;
+                solve_linear_row(
+                    rb_a,
+                    rb_b,
+                    center_to_anchor_a,
+                    center_to_anchor_b,
+                    direction,
+                    bias_factor * (length - distance),
+                );
+            }
+            JointKind::Hinge {
+                local_axis_a,
+                local_axis_b,
+                limits,
+                ..
+            } => {
+                // Pin the anchors together.
+                for direction in [Vector3::x(), Vector3::y(), Vector3::z()] {
+                    solve_linear_row(
+                        rb_a,
+                        rb_b,
+                        center_to_anchor_a,
+                        center_to_anchor_b,
+                        direction,
+                        bias_factor * offset.dot(&direction),
+                    );
+                }
+
+                // Only allow relative rotation around the axis, the cross product of the axes is
+                // the small rotation b has drifted off of a's axis by.
+                let axis_a = world_transform_a.rotation * local_axis_a;
+                let axis_b = world_transform_b.rotation * local_axis_b;
+                let axis_error = axis_a.cross(&axis_b);
+                let tangent = any_perpendicular(&axis_a);
+                let bitangent = axis_a.cross(&tangent);
+                for direction in [tangent, bitangent] {
+                    solve_angular_row(
+                        rb_a,
+                        rb_b,
+                        direction,
+                        bias_factor * axis_error.dot(&direction),
+                        f32::NEG_INFINITY..=f32::INFINITY,
+                    );
+                }
+
+                let Some((min_angle, max_angle)) = limits else {
+                    return;
+                };
+                let angle = self
+                    .hinge_angle(world_transform_a, world_transform_b)
+                    .unwrap();
+                if angle < *min_angle {
+                    solve_angular_row(
+                        rb_a,
+                        rb_b,
+                        axis_a,
+                        bias_factor * (angle - min_angle),
+                        0.0..=f32::INFINITY,
+                    );
+                } else if angle > *max_angle {
+                    solve_angular_row(
+                        rb_a,
+                        rb_b,
+                        axis_a,
+                        bias_factor * (angle - max_angle),
+                        f32::NEG_INFINITY..=0.0,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn to_local_point(world_transform: &Transform, world_point: Vector3<f32>) -> Vector3<f32> {
+    world_transform.rotation.inverse() * (world_point - world_transform.position)
+}
+
+fn any_perpendicular(axis: &Vector3<f32>) -> Vector3<f32> {
+    let other = if axis.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    axis.cross(&other).normalize()
+}
+
+fn angular_velocity(rb: &RigidBody) -> Vector3<f32> {
+    if rb.is_static() {
+        return Vector3::zeros();
+    }
+    rb.angular_velocity
+}
+
+/// Drives the relative velocity of the anchors along `direction` to `-bias`.
+fn solve_linear_row(
+    rb_a: &mut RigidBody,
+    rb_b: &mut RigidBody,
+    center_to_anchor_a: Vector3<f32>,
+    center_to_anchor_b: Vector3<f32>,
+    direction: Vector3<f32>,
+    bias: f32,
+) {
+    let v_rel = rb_b.velocity() + rb_b.angular_linear_velocity(center_to_anchor_b)
+        - rb_a.velocity()
+        - rb_a.angular_linear_velocity(center_to_anchor_a);
+    let eff_mass_rot_a =
+        (rb_a.inv_inertia() * center_to_anchor_a.cross(&direction)).cross(&center_to_anchor_a);
+    let eff_mass_rot_b =
+        (rb_b.inv_inertia() * center_to_anchor_b.cross(&direction)).cross(&center_to_anchor_b);
+    let k = rb_a.inv_mass() + rb_b.inv_mass() + (eff_mass_rot_a + eff_mass_rot_b).dot(&direction);
+    if k <= f32::EPSILON {
+        return;
+    }
+    let impulse = direction * (-(v_rel.dot(&direction) + bias) / k);
+    rb_a.apply_impulse_at_point(-impulse, center_to_anchor_a);
+    rb_b.apply_impulse_at_point(impulse, center_to_anchor_b);
+}
+
+/// Drives the relative angular velocity around `direction` to `-bias`, with the impulse
+/// clamped to `impulse_range` for one sided limits.
+fn solve_angular_row(
+    rb_a: &mut RigidBody,
+    rb_b: &mut RigidBody,
+    direction: Vector3<f32>,
+    bias: f32,
+    impulse_range: std::ops::RangeInclusive<f32>,
+) {
+    let w_rel = angular_velocity(rb_b) - angular_velocity(rb_a);
+    let k = direction.dot(&(rb_a.inv_inertia() * direction))
+        + direction.dot(&(rb_b.inv_inertia() * direction));
+    if k <= f32::EPSILON {
+        return;
+    }
+    let impulse =
+        (-(w_rel.dot(&direction) + bias) / k).clamp(*impulse_range.start(), *impulse_range.end());
+    rb_a.set_angular_velocity(rb_a.angular_velocity - rb_a.inv_inertia() * direction * impulse);
+    rb_b.set_angular_velocity(rb_b.angular_velocity + rb_b.inv_inertia() * direction * impulse);
+}
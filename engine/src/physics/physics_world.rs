@@ -1,4 +1,5 @@
 use std::{
+    any::TypeId,
     collections::{HashMap, HashSet},
     time::Duration,
 };
@@ -7,8 +8,10 @@ use nalgebra::{UnitQuaternion, Vector3};
 use rogue_macros::Resource;
 
 use super::{
-    capsule_collider::CapsuleCollider,
-    plane_collider::PlaneCollider,
+    box_collider::BoxCollider,
+    capsule_collider::{self, CapsuleCollider},
+    joint::{Joint, JointId},
+    plane_collider::{self, PlaneCollider},
     rigid_body::{ForceType, RigidBody},
     transform::Transform,
 };
@@ -18,13 +21,12 @@ use crate::entity::{
 };
 use crate::physics::collider_registry::ColliderRegistry;
 use crate::physics::{
-    collider::{ColliderDebugColoring, ContactManifold, ContactPair},
+    collider::{ColliderDebugColoring, ContactManifold, ContactPair, TimeOfImpact},
     collider_component::EntityColliders,
     collider_registry::ColliderId,
     rigid_body::{self, RigidBodyType},
 };
 use crate::resource::{Res, ResMut};
-use crate::window::time::Instant;
 use crate::{
     common::{
         color::Color,
@@ -46,11 +48,17 @@ pub enum PhysicsTimestep {
 
 pub struct PhysicsSettings {
     timestep: PhysicsTimestep,
+    /// The most physics steps done in one frame, once a frame takes longer than this many
+    /// steps physics slows down instead of taking even longer to catch up.
+    max_substeps: u32,
     time_scale: f32,
     impulse_iterations: u32,
     baumgarte_iterations: u32,
     /// acceleration, meters / seconds^2
     gravity: Vector3<f32>,
+    /// The side length of the broad phase grid cells in meters, None uses the median collider
+    /// size each step.
+    broad_phase_cell_size: Option<f32>,
 }
 
 impl Default for PhysicsSettings {
@@ -58,10 +66,12 @@ impl Default for PhysicsSettings {
         Self {
             // 60 fps physics updates
             timestep: PhysicsTimestep::Fixed(Duration::from_secs_f32(1.0 / 60.0)),
+            max_substeps: 8,
             time_scale: 1.0,
             impulse_iterations: 15,
             baumgarte_iterations: 1,
             gravity: Vector3::new(0.0, -9.8, 0.0),
+            broad_phase_cell_size: None,
         }
     }
 }
@@ -88,31 +98,141 @@ impl BroadPhase {
 pub struct NarrowPhase {
     contact_pairs: Vec<ContactPair>,
     collider_contacts: HashMap<ColliderId, Vec<u32>>,
+    /// Overlapping pairs involving a trigger, these get no contact response.
+    trigger_overlaps: ColliderPairs,
+    /// The trigger overlaps from the previous step, to find which overlaps entered or exited.
+    last_trigger_overlaps: ColliderPairs,
+    /// The pairs in `contact_pairs`, and the ones from the previous step to find which pairs
+    /// started touching.
+    contacts: ColliderPairs,
+    last_contacts: ColliderPairs,
 }
 
+type ColliderPairs = HashMap<(ColliderId, ColliderId), (Entity, Entity)>;
+
 impl NarrowPhase {
     pub fn new() -> Self {
         Self {
             contact_pairs: Vec::new(),
             collider_contacts: HashMap::new(),
+            trigger_overlaps: HashMap::new(),
+            last_trigger_overlaps: HashMap::new(),
+            contacts: HashMap::new(),
+            last_contacts: HashMap::new(),
         }
     }
 
     pub fn reset(&mut self) {
         self.contact_pairs.clear();
         self.collider_contacts.clear();
+        std::mem::swap(&mut self.trigger_overlaps, &mut self.last_trigger_overlaps);
+        self.trigger_overlaps.clear();
+        std::mem::swap(&mut self.contacts, &mut self.last_contacts);
+        self.contacts.clear();
+    }
+
+    fn push_collision_events(&self, ecs_world: &ECSWorld, events: &mut Events) {
+        let velocity = |entity: Entity| {
+            ecs_world
+                .get::<&RigidBody>(entity)
+                .map_or(Vector3::zeros(), |rigid_body| rigid_body.velocity)
+        };
+        for contact_pair in &self.contact_pairs {
+            let (collider_a, collider_b) = (contact_pair.collider_a, contact_pair.collider_b);
+            if self.last_contacts.contains_key(&(collider_a, collider_b)) {
+                continue;
+            }
+            let relative_velocity =
+                velocity(contact_pair.entity_a) - velocity(contact_pair.entity_b);
+            events.push(CollisionEvent {
+                entity_a: contact_pair.entity_a,
+                collider_a,
+                entity_b: contact_pair.entity_b,
+                collider_b,
+                impact_speed: relative_velocity.dot(&contact_pair.manifold.normal).abs(),
+            });
+        }
+    }
+
+    fn push_trigger_events(&self, events: &mut Events) {
+        let mut push_events = |from: &ColliderPairs, to: &ColliderPairs, state| {
+            for ((collider_a, collider_b), (entity_a, entity_b)) in from {
+                if to.contains_key(&(*collider_a, *collider_b)) {
+                    continue;
+                }
+                events.push(TriggerOverlapEvent {
+                    state,
+                    entity_a: *entity_a,
+                    collider_a: *collider_a,
+                    entity_b: *entity_b,
+                    collider_b: *collider_b,
+                });
+            }
+        };
+        push_events(
+            &self.trigger_overlaps,
+            &self.last_trigger_overlaps,
+            TriggerOverlapState::Enter,
+        );
+        push_events(
+            &self.last_trigger_overlaps,
+            &self.trigger_overlaps,
+            TriggerOverlapState::Exit,
+        );
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TriggerOverlapState {
+    /// The colliders started overlapping this physics step.
+    Enter,
+    /// The colliders stopped overlapping this physics step.
+    Exit,
+}
+
+/// Pushed when a collider starts or stops overlapping a trigger collider, at least one of the
+/// two colliders is a trigger.
+#[derive(Clone)]
+pub struct TriggerOverlapEvent {
+    pub state: TriggerOverlapState,
+    pub entity_a: Entity,
+    pub collider_a: ColliderId,
+    pub entity_b: Entity,
+    pub collider_b: ColliderId,
+}
+
+/// Pushed when two colliders which aren't triggers start touching.
+#[derive(Clone)]
+pub struct CollisionEvent {
+    pub entity_a: Entity,
+    pub collider_a: ColliderId,
+    pub entity_b: Entity,
+    pub collider_b: ColliderId,
+    /// How fast the bodies were moving toward each other along the contact normal, in meters
+    /// per second.
+    pub impact_speed: f32,
+}
+
+/// The closest collider hit by a `PhysicsWorld::raycast`.
+pub struct RaycastHit {
+    pub collider_id: ColliderId,
+    pub entity: Entity,
+    pub point: Vector3<f32>,
+    pub normal: Vector3<f32>,
+    /// Distance along the ray in meters.
+    pub distance: f32,
+}
+
 #[derive(Resource)]
 pub struct PhysicsWorld {
     // The timestep set by Self::next_time_step().
     curr_timestep: Duration,
     last_timestep: Duration,
-    last_update_instant: Instant,
-    update_time: Instant,
+    /// Frame time which hasn't been simulated yet.
+    accumulator: Duration,
     settings: PhysicsSettings,
     pub colliders: ColliderRegistry,
+    joints: FreeList<Joint>,
     // Whether to update rigid bodies or not.
     pub do_dynamics: bool,
 
@@ -123,12 +243,12 @@ pub struct PhysicsWorld {
 impl PhysicsWorld {
     pub fn new() -> Self {
         Self {
-            last_update_instant: Instant::now(),
-            update_time: Instant::now(),
+            accumulator: Duration::ZERO,
             curr_timestep: Duration::ZERO,
             last_timestep: Duration::ZERO,
             settings: PhysicsSettings::default(),
             colliders: ColliderRegistry::new(),
+            joints: FreeList::new(),
             do_dynamics: false,
 
             broad_phase: BroadPhase::new(),
@@ -137,21 +257,99 @@ impl PhysicsWorld {
     }
 
     pub fn reset_last_timestep(&mut self) {
-        self.last_update_instant = Instant::now();
+        self.accumulator = Duration::ZERO;
     }
 
-    // Determine the number of physics updates between the last physics update and now.
-    pub fn physics_update_count(&mut self) -> u32 {
-        self.update_time = Instant::now();
-        let dur = self.update_time - self.last_update_instant;
+    pub fn fixed_timestep(&self) -> Duration {
         match self.settings.timestep {
-            PhysicsTimestep::Fixed(fixed_duration) => {
-                let updates = (dur.as_secs_f32() / fixed_duration.as_secs_f32()).floor() as u32;
-                updates
-            }
+            PhysicsTimestep::Fixed(duration) => duration,
         }
     }
 
+    pub fn set_fixed_timestep(&mut self, timestep: Duration) {
+        assert!(!timestep.is_zero(), "Physics timestep must be non-zero.");
+        self.settings.timestep = PhysicsTimestep::Fixed(timestep);
+    }
+
+    pub fn set_max_substeps(&mut self, max_substeps: u32) {
+        self.settings.max_substeps = max_substeps;
+    }
+
+    pub fn set_broad_phase_cell_size(&mut self, cell_size: Option<f32>) {
+        if let Some(cell_size) = cell_size {
+            assert!(cell_size > 0.0, "Broad phase cell size must be positive.");
+        }
+        self.settings.broad_phase_cell_size = cell_size;
+    }
+
+    /// Accumulates the frame's delta time returning how many fixed physics steps to run this
+    /// frame.
+    pub fn physics_update_count(&mut self, delta_time: Duration) -> u32 {
+        self.accumulator += delta_time;
+        let fixed_timestep = self.fixed_timestep();
+        let updates =
+            (self.accumulator.as_secs_f64() / fixed_timestep.as_secs_f64()).floor() as u32;
+        if updates > self.settings.max_substeps {
+            // Drop the time we can't catch up on, otherwise a long frame causes more steps next
+            // frame which makes that frame even longer.
+            log::debug!(
+                "Physics is {} steps behind, clamping to {} steps.",
+                updates,
+                self.settings.max_substeps
+            );
+            self.accumulator = fixed_timestep * self.settings.max_substeps;
+            return self.settings.max_substeps;
+        }
+        updates
+    }
+
+    pub fn add_joint(&mut self, joint: Joint) -> anyhow::Result<JointId> {
+        if joint.entity_a == joint.entity_b {
+            anyhow::bail!("Joint must be between two different entities.");
+        }
+        Ok(self.joints.push(joint))
+    }
+
+    pub fn remove_joint(&mut self, joint_id: JointId) -> Joint {
+        self.joints.remove(joint_id)
+    }
+
+    pub fn clear_joints(&mut self) {
+        self.joints = FreeList::new();
+    }
+
+    pub fn get_joint_mut(&mut self, joint_id: JointId) -> Option<&mut Joint> {
+        self.joints.get_mut(joint_id)
+    }
+
+    pub fn joints(&self) -> impl Iterator<Item = (JointId, &Joint)> {
+        self.joints.iter_with_handle()
+    }
+
+    /// Removes the joints connected to entities which have been despawned.
+    pub fn remove_despawned_joints(
+        mut physics_world: ResMut<PhysicsWorld>,
+        ecs_world: Res<ECSWorld>,
+    ) {
+        let despawned_joints = physics_world
+            .joints()
+            .filter(|(_, joint)| {
+                !ecs_world.contains_entity(joint.entity_a)
+                    || !ecs_world.contains_entity(joint.entity_b)
+            })
+            .map(|(joint_id, _)| joint_id)
+            .collect::<Vec<_>>();
+        for joint_id in despawned_joints {
+            physics_world.remove_joint(joint_id);
+        }
+    }
+
+    /// How far between the last and next physics step the frame is from 0 to 1, used to blend
+    /// between the previous and current rigid body transforms.
+    pub fn interpolation_alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f32() / self.fixed_timestep().as_secs_f32()).clamp(0.0, 1.0)
+    }
+
     pub fn render_debug_colliders(
         mut physics_world: ResMut<PhysicsWorld>,
         mut debug_renderer: ResMut<DebugRenderer>,
@@ -162,7 +360,7 @@ impl PhysicsWorld {
             .query::<(&Transform, &EntityColliders)>()
             .into_iter()
         {
-            let world_transform = ecs_world.get_world_transform(entity, &transform);
+            let world_transform = ecs_world.get_global_transform(entity, &transform);
             for collider_id in &colliders.colliders {
                 let mut coloring = ColliderDebugColoring::Untouched;
                 if physics_world
@@ -235,20 +433,131 @@ impl PhysicsWorld {
         self.last_timestep
     }
 
-    /// Different from time_step() since time_step() is only valid during a physics_update.
-    pub fn time_since_last_physics_update(&self) -> Duration {
-        Instant::now() - self.last_update_instant
-    }
-
     pub fn end_time_step(mut physics_world: ResMut<PhysicsWorld>) {
-        physics_world.last_update_instant =
-            physics_world.last_update_instant + physics_world.curr_timestep;
+        physics_world.accumulator = physics_world
+            .accumulator
+            .saturating_sub(physics_world.curr_timestep);
         physics_world.last_timestep = physics_world.curr_timestep;
         physics_world.curr_timestep = Duration::ZERO;
     }
 
-    pub fn ray_cast(&self, ray: Ray) -> Option<ColliderId> {
-        None
+    /// The closest collider hit by `ray` within `max_distance` meters.
+    pub fn raycast(
+        &self,
+        ecs_world: &ECSWorld,
+        ray: &Ray,
+        max_distance: f32,
+    ) -> Option<RaycastHit> {
+        let ray = Ray::new(ray.origin, ray.dir.normalize());
+        let mut closest_hit: Option<RaycastHit> = None;
+        for (entity, (transform, colliders)) in ecs_world
+            .query::<(&Transform, &EntityColliders)>()
+            .into_iter()
+        {
+            let world_transform = ecs_world.get_world_transform(entity, transform);
+            for collider_id in &colliders.colliders {
+                // Only look for hits closer than the current closest.
+                let max_distance = closest_hit
+                    .as_ref()
+                    .map_or(max_distance, |hit| hit.distance);
+                let Some(hit) = self.colliders.get_collider_dyn(collider_id).raycast(
+                    &world_transform,
+                    &ray,
+                    max_distance,
+                    &self.colliders.voxel_collider_registry,
+                ) else {
+                    continue;
+                };
+                if hit.distance > max_distance {
+                    continue;
+                }
+                closest_hit = Some(RaycastHit {
+                    collider_id: *collider_id,
+                    entity,
+                    point: ray.origin + ray.dir * hit.distance,
+                    normal: hit.normal,
+                    distance: hit.distance,
+                });
+            }
+        }
+        closest_hit
+    }
+
+    /// How far continuous bodies are stopped before their impact so they don't start the next
+    /// step touching the collider they hit.
+    const CONTINUOUS_SKIN: f32 = 0.001;
+
+    /// The earliest impact of the entity's capsule colliders moving by `motion` against the
+    /// box and plane colliders of every other entity it can collide with.
+    fn continuous_time_of_impact(
+        &self,
+        ecs_world: &ECSWorld,
+        entity: Entity,
+        rigid_body: &RigidBody,
+        colliders: &EntityColliders,
+        world_transform: &Transform,
+        motion: Vector3<f32>,
+    ) -> Option<TimeOfImpact> {
+        // Bodies without a rigid body are on the default layer and mask.
+        let default_body = RigidBody::new_static();
+        let mut closest: Option<TimeOfImpact> = None;
+        for (other_entity, (other_transform, other_colliders, other_rigid_body)) in ecs_world
+            .query::<(&Transform, &EntityColliders, Option<&RigidBody>)>()
+            .into_iter()
+        {
+            if other_entity == entity
+                || !rigid_body.can_collide_with(other_rigid_body.unwrap_or(&default_body))
+            {
+                continue;
+            }
+            let other_world_transform =
+                ecs_world.get_world_transform(other_entity, other_transform);
+            for collider_id in &colliders.colliders {
+                if collider_id.collider_type != TypeId::of::<CapsuleCollider>() {
+                    continue;
+                }
+                let capsule = self.colliders.get_collider::<CapsuleCollider>(collider_id);
+                if capsule.is_trigger {
+                    continue;
+                }
+                for other_collider_id in &other_colliders.colliders {
+                    let other_type = other_collider_id.collider_type;
+                    let impact = if other_type == TypeId::of::<BoxCollider>() {
+                        let box_collider = self
+                            .colliders
+                            .get_collider::<BoxCollider>(other_collider_id);
+                        if box_collider.is_trigger {
+                            continue;
+                        }
+                        capsule_collider::capsule_box_time_of_impact(
+                            capsule,
+                            box_collider,
+                            world_transform,
+                            &other_world_transform,
+                            motion,
+                        )
+                    } else if other_type == TypeId::of::<PlaneCollider>() {
+                        plane_collider::capsule_plane_time_of_impact(
+                            capsule,
+                            self.colliders
+                                .get_collider::<PlaneCollider>(other_collider_id),
+                            world_transform,
+                            &other_world_transform,
+                            motion,
+                        )
+                    } else {
+                        None
+                    };
+                    let Some(impact) = impact else {
+                        continue;
+                    };
+                    if closest.as_ref().is_none_or(|closest| impact.t < closest.t) {
+                        closest = Some(impact);
+                    }
+                }
+            }
+        }
+        closest
     }
 
     /// Runs every frame.
@@ -259,9 +568,7 @@ impl PhysicsWorld {
         if !physics_world.do_dynamics {
             return;
         }
-        let timestep = physics_world.last_time_step();
-        let last_timestep = physics_world.time_since_last_physics_update();
-        let t = last_timestep.as_secs_f32() / timestep.as_secs_f32();
+        let t = physics_world.interpolation_alpha();
         for (entity, (transform, rigid_body)) in ecs_world
             .query_mut::<(&mut Transform, &mut RigidBody)>()
             .into_iter()
@@ -288,6 +595,7 @@ impl PhysicsWorld {
     pub fn do_physics_update(
         mut physics_world: ResMut<PhysicsWorld>,
         mut ecs_world: ResMut<ECSWorld>,
+        mut events: ResMut<Events>,
     ) {
         let physics_world = &mut physics_world as &mut PhysicsWorld;
         let timestep = physics_world
@@ -300,7 +608,7 @@ impl PhysicsWorld {
                 .into_iter()
             {
                 if rigid_body.rigid_body_type == RigidBodyType::Static {
-                    return;
+                    continue;
                 }
 
                 // Initialize position/rotation of the rigid body if we haven't already.
@@ -329,74 +637,72 @@ impl PhysicsWorld {
             }
         }
 
-        physics_world
-            .colliders
-            .update_entity_collider_positions(&mut ecs_world);
+        // Transforms are constant until the contact resolution so the collider positions and
+        // contact detection can use the cached world transforms.
+        ecs_world.update_global_transforms();
+        physics_world.colliders.update_entity_collider_positions(
+            &mut ecs_world,
+            physics_world.settings.broad_phase_cell_size,
+        );
 
         // Broad phase detection
         physics_world.broad_phase.reset();
-        let mut tested_collision = HashSet::new();
-        for (_, bin) in physics_world.colliders.bins.iter() {
-            for (entity_a, collider_id_a) in bin {
-                for (entity_b, collider_id_b) in bin {
-                    if *entity_a == *entity_b {
-                        continue;
-                    }
+        for [(entity_a, collider_id_a), (entity_b, collider_id_b)] in
+            &physics_world.colliders.broad_phase_candidate_pairs()
+        {
+            let mut query = ecs_world
+                .query_many_mut::<(&Transform, Option<&RigidBody>), 2>([*entity_a, *entity_b]);
+            let [Some((transform_a, rb_a)), Some((transform_b, rb_b))] = query.get() else {
+                log::debug!(
+                    "Broad phase skipping collision between {:?} and {:?} due to missing transform or rigid body",
+                    entity_a,
+                    entity_b
+                );
+                continue;
+            };
 
-                    if tested_collision.contains(&(*entity_a, *entity_b)) {
-                        continue;
-                    }
-                    tested_collision.insert((*entity_a, *entity_b));
-                    tested_collision.insert((*entity_b, *entity_a));
-
-                    let mut query = ecs_world
-                        .query_many_mut::<(&Transform, Option<&RigidBody>), 2>([
-                            *entity_a, *entity_b,
-                        ]);
-                    let [Some((transform_a, rb_a)), Some((transform_b, rb_b))] = query.get() else {
-                        log::debug!(
-                            "Broad phase skipping collision between {:?} and {:?} due to missing transform or rigid body",
-                            entity_a,
-                            entity_b
-                        );
-                        continue;
-                    };
+            // Skip collision checking between static bodies.
+            let is_static_a = rb_a.map(|rb| rb.is_static()).unwrap_or(true);
+            let is_static_b = rb_b.map(|rb| rb.is_static()).unwrap_or(true);
+            if is_static_a && is_static_b {
+                continue;
+            }
 
-                    // Skip collision checking between static bodies.
-                    let is_static_a = rb_a.map(|rb| rb.is_static()).unwrap_or(true);
-                    let is_static_b = rb_b.map(|rb| rb.is_static()).unwrap_or(true);
-                    if is_static_a && is_static_b {
-                        continue;
-                    }
+            // Bodies without a rigid body are on the default layer and mask.
+            let default_body = RigidBody::new_static();
+            let can_collide = rb_a
+                .unwrap_or(&default_body)
+                .can_collide_with(rb_b.unwrap_or(&default_body));
+            if !can_collide {
+                continue;
+            }
 
-                    let world_transform_a = ecs_world.get_world_transform(*entity_a, transform_a);
-                    let world_transform_b = ecs_world.get_world_transform(*entity_b, transform_b);
-
-                    let collider_a = physics_world.colliders.get_collider_dyn(collider_id_a);
-                    let collider_b = physics_world.colliders.get_collider_dyn(collider_id_b);
-                    let voxel_collider_registry = &physics_world.colliders.voxel_collider_registry;
-                    let aabb_a = collider_a
-                        .aabb(&world_transform_a, voxel_collider_registry)
-                        .expect("AABB should exist if collider was binned.");
-                    let aabb_b = collider_b
-                        .aabb(&world_transform_b, voxel_collider_registry)
-                        .expect("AABB should exist if collider was binned.");
-                    let could_collide = aabb_a.intersects_aabb(&aabb_b);
-                    if could_collide {
-                        physics_world
-                            .broad_phase
-                            .collisions
-                            .push([(*entity_a, *collider_id_a), (*entity_b, *collider_id_b)]);
-                        physics_world
-                            .broad_phase
-                            .involved_colliders
-                            .insert(*collider_id_a);
-                        physics_world
-                            .broad_phase
-                            .involved_colliders
-                            .insert(*collider_id_b);
-                    }
-                }
+            let world_transform_a = ecs_world.get_global_transform(*entity_a, transform_a);
+            let world_transform_b = ecs_world.get_global_transform(*entity_b, transform_b);
+
+            let collider_a = physics_world.colliders.get_collider_dyn(collider_id_a);
+            let collider_b = physics_world.colliders.get_collider_dyn(collider_id_b);
+            let voxel_collider_registry = &physics_world.colliders.voxel_collider_registry;
+            let aabb_a = collider_a
+                .aabb(&world_transform_a, voxel_collider_registry)
+                .expect("AABB should exist if collider was binned.");
+            let aabb_b = collider_b
+                .aabb(&world_transform_b, voxel_collider_registry)
+                .expect("AABB should exist if collider was binned.");
+            let could_collide = aabb_a.intersects_aabb(&aabb_b);
+            if could_collide {
+                physics_world
+                    .broad_phase
+                    .collisions
+                    .push([(*entity_a, *collider_id_a), (*entity_b, *collider_id_b)]);
+                physics_world
+                    .broad_phase
+                    .involved_colliders
+                    .insert(*collider_id_a);
+                physics_world
+                    .broad_phase
+                    .involved_colliders
+                    .insert(*collider_id_b);
             }
         }
 
@@ -410,8 +716,8 @@ impl PhysicsWorld {
                 continue;
             };
 
-            let world_transform_a = ecs_world.get_world_transform(*entity_a, &transform_a);
-            let world_transform_b = ecs_world.get_world_transform(*entity_b, &transform_b);
+            let world_transform_a = ecs_world.get_global_transform(*entity_a, &transform_a);
+            let world_transform_b = ecs_world.get_global_transform(*entity_b, &transform_b);
             let Some(manifold) = physics_world.colliders.test_narrow_phase(
                 collider_a,
                 collider_b,
@@ -421,6 +727,26 @@ impl PhysicsWorld {
                 continue;
             };
 
+            let is_trigger = physics_world
+                .colliders
+                .get_collider_dyn(collider_a)
+                .is_trigger()
+                || physics_world
+                    .colliders
+                    .get_collider_dyn(collider_b)
+                    .is_trigger();
+            if is_trigger {
+                physics_world
+                    .narrow_phase
+                    .trigger_overlaps
+                    .insert((*collider_a, *collider_b), (*entity_a, *entity_b));
+                continue;
+            }
+
+            physics_world
+                .narrow_phase
+                .contacts
+                .insert((*collider_a, *collider_b), (*entity_a, *entity_b));
             let manifold_idx = physics_world.narrow_phase.contact_pairs.len();
             physics_world.narrow_phase.contact_pairs.push(ContactPair {
                 manifold,
@@ -442,6 +768,10 @@ impl PhysicsWorld {
                 .or_default()
                 .push(manifold_idx as u32);
         }
+        physics_world.narrow_phase.push_trigger_events(&mut events);
+        physics_world
+            .narrow_phase
+            .push_collision_events(&ecs_world, &mut events);
 
         if !physics_world.do_dynamics {
             return;
@@ -565,6 +895,30 @@ impl PhysicsWorld {
                     );
                 }
             }
+
+            // Solve joint constraints.
+            for joint in physics_world.joints.iter() {
+                let mut query = ecs_world
+                    .query_many_mut::<(&Transform, Option<&mut RigidBody>), 2>([
+                        joint.entity_a,
+                        joint.entity_b,
+                    ]);
+                let [Some((transform_a, rb_a)), Some((transform_b, rb_b))] = query.get() else {
+                    continue;
+                };
+
+                let rb_a = rb_a.unwrap_or(&mut static_body_a);
+                let rb_b = rb_b.unwrap_or(&mut static_body_b);
+                let world_transform_a = ecs_world.get_world_transform(joint.entity_a, transform_a);
+                let world_transform_b = ecs_world.get_world_transform(joint.entity_b, transform_b);
+                joint.solve_velocities(
+                    &world_transform_a,
+                    rb_a,
+                    &world_transform_b,
+                    rb_b,
+                    timestep,
+                );
+            }
         }
 
         // Positional overlap correction
@@ -636,6 +990,34 @@ impl PhysicsWorld {
         }
 
         if physics_world.do_dynamics {
+            // Sweep continuous bodies along this step's motion before integrating so they can
+            // be stopped at their first impact.
+            let mut impacts = HashMap::new();
+            for (entity, (transform, rigid_body, colliders)) in ecs_world
+                .query::<(&Transform, &RigidBody, &EntityColliders)>()
+                .into_iter()
+            {
+                if !rigid_body.continuous || rigid_body.is_static() {
+                    continue;
+                }
+                let motion = rigid_body.velocity * timestep.as_secs_f32();
+                if motion.norm_squared() == 0.0 {
+                    continue;
+                }
+                let world_transform = ecs_world.get_world_transform(entity, transform);
+                let Some(impact) = physics_world.continuous_time_of_impact(
+                    &ecs_world,
+                    entity,
+                    rigid_body,
+                    colliders,
+                    &world_transform,
+                    motion,
+                ) else {
+                    continue;
+                };
+                impacts.insert(entity, (impact, rigid_body.position(), motion));
+            }
+
             for (entity, (transform, rigid_body)) in ecs_world
                 .query_mut::<(&mut Transform, &mut RigidBody)>()
                 .into_iter()
@@ -649,7 +1031,567 @@ impl PhysicsWorld {
                         rigid_body.integrate_velocities(timestep);
                     }
                 }
+
+                let Some((impact, start_position, motion)) = impacts.get(&entity) else {
+                    continue;
+                };
+                let t = (impact.t - Self::CONTINUOUS_SKIN / motion.norm()).max(0.0);
+                rigid_body.set_position(start_position + motion * t);
+                // Bounce off of the hit surface since the discrete contacts never saw it.
+                let normal_velocity = rigid_body.velocity.dot(&impact.normal);
+                if normal_velocity < 0.0 {
+                    rigid_body.velocity -=
+                        (1.0 + rigid_body.restitution) * normal_velocity * impact.normal;
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use nalgebra::Vector2;
+
+    use super::*;
+    use crate::event::EventReader;
+
+    #[test]
+    fn raycast_hits_closest_collider() {
+        let mut physics_world = PhysicsWorld::new();
+        let mut ecs_world = ECSWorld::new();
+        let mut spawn_box = |position: Vector3<f32>| {
+            let mut transform = Transform::new();
+            transform.position = position;
+            let collider_id = physics_world
+                .colliders
+                .register_collider(BoxCollider::default());
+            let mut colliders = EntityColliders::new();
+            colliders.colliders.push(collider_id);
+            (ecs_world.spawn((transform, colliders)), collider_id)
+        };
+        let (_far_entity, _) = spawn_box(Vector3::new(0.0, 0.0, 10.0));
+        let (near_entity, near_collider) = spawn_box(Vector3::new(0.0, 0.0, 5.0));
+
+        // The direction doesn't need to be normalized.
+        let ray = Ray::new(Vector3::zeros(), Vector3::new(0.0, 0.0, 2.0));
+        let hit = physics_world
+            .raycast(&ecs_world, &ray, 100.0)
+            .expect("Ray should hit the boxes.");
+        assert!(hit.entity == near_entity);
+        assert!(hit.collider_id == near_collider);
+        assert!((hit.distance - 4.5).abs() < 1e-4);
+        assert!((hit.point - Vector3::new(0.0, 0.0, 4.5)).norm() < 1e-4);
+        assert!((hit.normal - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-4);
+
+        assert!(physics_world.raycast(&ecs_world, &ray, 4.0).is_none());
+        let miss_ray = Ray::new(Vector3::new(0.0, 2.0, 0.0), Vector3::z());
+        assert!(
+            physics_world
+                .raycast(&ecs_world, &miss_ray, 100.0)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn raycast_hits_plane_collider() {
+        let mut physics_world = PhysicsWorld::new();
+        let mut ecs_world = ECSWorld::new();
+        let mut transform = Transform::new();
+        transform.position = Vector3::new(0.0, -2.0, 0.0);
+        let collider_id = physics_world.colliders.register_collider(PlaneCollider {
+            size: Vector2::new(4.0, 4.0),
+            ..Default::default()
+        });
+        let mut colliders = EntityColliders::new();
+        colliders.colliders.push(collider_id);
+        let entity = ecs_world.spawn((transform, colliders));
+
+        let ray = Ray::new(Vector3::new(1.0, 0.0, 1.0), -Vector3::y());
+        let hit = physics_world
+            .raycast(&ecs_world, &ray, 100.0)
+            .expect("Ray should hit the plane.");
+        assert!(hit.entity == entity);
+        assert!(hit.collider_id == collider_id);
+        assert!((hit.distance - 2.0).abs() < 1e-4);
+        assert!((hit.normal - Vector3::y()).norm() < 1e-4);
+
+        // The plane is two sided.
+        let below_ray = Ray::new(Vector3::new(1.0, -4.0, 1.0), Vector3::y());
+        let hit = physics_world
+            .raycast(&ecs_world, &below_ray, 100.0)
+            .expect("Ray should hit the plane from below.");
+        assert!((hit.normal + Vector3::y()).norm() < 1e-4);
+
+        let outside_ray = Ray::new(Vector3::new(5.0, 0.0, 0.0), -Vector3::y());
+        assert!(
+            physics_world
+                .raycast(&ecs_world, &outside_ray, 100.0)
+                .is_none()
+        );
+        let parallel_ray = Ray::new(Vector3::new(-5.0, -2.0, 0.0), Vector3::x());
+        assert!(
+            physics_world
+                .raycast(&ecs_world, &parallel_ray, 100.0)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn collision_masks_filter_contacts() {
+        let step = |layer_b: u32, mask_b: u32| {
+            let physics_world = RefCell::new(PhysicsWorld::new());
+            let ecs_world = RefCell::new(ECSWorld::new());
+            physics_world.borrow_mut().do_dynamics = true;
+            physics_world.borrow_mut().curr_timestep = Duration::from_secs_f32(1.0 / 60.0);
+
+            // Two overlapping boxes moving into each other.
+            let spawn_box = |x: f32, velocity: f32, layer: u32, mask: u32| {
+                let mut transform = Transform::new();
+                transform.position = Vector3::new(x, 0.0, 0.0);
+                let mut rigid_body = RigidBody::default();
+                rigid_body.velocity = Vector3::new(velocity, 0.0, 0.0);
+                rigid_body.collision_layer = layer;
+                rigid_body.collision_mask = mask;
+                let mut colliders = EntityColliders::new();
+                colliders.colliders.push(
+                    physics_world
+                        .borrow_mut()
+                        .colliders
+                        .register_collider(BoxCollider::default()),
+                );
+                ecs_world
+                    .borrow_mut()
+                    .spawn((transform, rigid_body, colliders))
+            };
+            let a = spawn_box(0.0, 5.0, 0b01, 0b01);
+            let b = spawn_box(0.5, -5.0, layer_b, mask_b);
+
+            let events = RefCell::new(Events::new());
+            PhysicsWorld::do_physics_update(
+                physics_world.borrow_mut(),
+                ecs_world.borrow_mut(),
+                events.borrow_mut(),
+            );
+            let contacts = physics_world.borrow().narrow_phase.contact_pairs.len();
+            let ecs_world = ecs_world.borrow();
+            let velocity_a = ecs_world.get::<&RigidBody>(a).unwrap().velocity.x;
+            let velocity_b = ecs_world.get::<&RigidBody>(b).unwrap().velocity.x;
+            (contacts, velocity_a, velocity_b)
+        };
+
+        // Overlapping layers and masks collide.
+        let (contacts, _, _) = step(0b01, 0b11);
+        assert!(contacts > 0);
+
+        // B is on a layer A doesn't collide with so they pass through each other.
+        let (contacts, velocity_a, velocity_b) = step(0b10, 0b11);
+        assert_eq!(contacts, 0);
+        assert_eq!((velocity_a, velocity_b), (5.0, -5.0));
+    }
+
+    #[test]
+    fn broad_phase_grid_matches_brute_force_with_fewer_pairs() {
+        let mut physics_world = PhysicsWorld::new();
+        let mut ecs_world = ECSWorld::new();
+        // Unit boxes closer than their size so each one overlaps its neighbors.
+        let mut aabbs = HashMap::new();
+        for x in 0..20 {
+            for y in 0..10 {
+                for z in 0..10 {
+                    let mut transform = Transform::new();
+                    transform.position = Vector3::new(x as f32, y as f32, z as f32) * 0.9;
+                    let collider_id = physics_world
+                        .colliders
+                        .register_collider(BoxCollider::default());
+                    let mut colliders = EntityColliders::new();
+                    colliders.colliders.push(collider_id);
+                    let aabb = physics_world
+                        .colliders
+                        .get_collider_dyn(&collider_id)
+                        .aabb(&transform, &physics_world.colliders.voxel_collider_registry)
+                        .unwrap();
+                    ecs_world.spawn((transform, colliders));
+                    aabbs.insert(collider_id, aabb);
+                }
+            }
+        }
+
+        physics_world
+            .colliders
+            .update_entity_collider_positions(&mut ecs_world, None);
+        // The median box size.
+        assert!((physics_world.colliders.bin_cell_size - 1.0).abs() < 1e-4);
+        let candidates = physics_world.colliders.broad_phase_candidate_pairs();
+        let brute_force_pair_count = aabbs.len() * (aabbs.len() - 1) / 2;
+        assert!(candidates.len() * 10 < brute_force_pair_count);
+
+        let grid_overlaps = candidates
+            .iter()
+            .filter(|[(_, a), (_, b)]| aabbs[a].intersects_aabb(&aabbs[b]))
+            .map(|[(_, a), (_, b)]| [a.index.min(b.index), a.index.max(b.index)])
+            .collect::<HashSet<_>>();
+        let aabbs = aabbs.into_iter().collect::<Vec<_>>();
+        let mut brute_force_overlaps = HashSet::new();
+        for (i, (a, aabb_a)) in aabbs.iter().enumerate() {
+            for (b, aabb_b) in &aabbs[(i + 1)..] {
+                if aabb_a.intersects_aabb(aabb_b) {
+                    brute_force_overlaps.insert([a.index.min(b.index), a.index.max(b.index)]);
+                }
+            }
+        }
+        assert!(!brute_force_overlaps.is_empty());
+        assert_eq!(grid_overlaps, brute_force_overlaps);
+    }
+
+    #[test]
+    fn broad_phase_cell_size_doesnt_change_contacts() {
+        let step = |cell_size: Option<f32>| {
+            let physics_world = RefCell::new(PhysicsWorld::new());
+            let ecs_world = RefCell::new(ECSWorld::new());
+            physics_world.borrow_mut().do_dynamics = true;
+            physics_world.borrow_mut().curr_timestep = Duration::from_secs_f32(1.0 / 60.0);
+            physics_world
+                .borrow_mut()
+                .set_broad_phase_cell_size(cell_size);
+
+            // A row of boxes each moving into the next, plus one far away from the rest.
+            let mut entities = Vec::new();
+            for (x, velocity) in [
+                (0.0, 5.0),
+                (0.8, -5.0),
+                (1.6, 2.0),
+                (2.4, -1.0),
+                (40.0, 0.0),
+            ] {
+                let mut transform = Transform::new();
+                transform.position = Vector3::new(x, 0.0, 0.0);
+                let mut rigid_body = RigidBody::default();
+                rigid_body.velocity = Vector3::new(velocity, 0.0, 0.0);
+                let mut colliders = EntityColliders::new();
+                colliders.colliders.push(
+                    physics_world
+                        .borrow_mut()
+                        .colliders
+                        .register_collider(BoxCollider::default()),
+                );
+                entities.push(
+                    ecs_world
+                        .borrow_mut()
+                        .spawn((transform, rigid_body, colliders)),
+                );
+            }
+
+            let events = RefCell::new(Events::new());
+            PhysicsWorld::do_physics_update(
+                physics_world.borrow_mut(),
+                ecs_world.borrow_mut(),
+                events.borrow_mut(),
+            );
+            let mut contacts = physics_world
+                .borrow()
+                .narrow_phase
+                .contact_pairs
+                .iter()
+                .map(|pair| {
+                    let (a, b) = (pair.collider_a.index, pair.collider_b.index);
+                    (a.min(b), a.max(b), pair.manifold.points.len())
+                })
+                .collect::<Vec<_>>();
+            contacts.sort();
+            let ecs_world = ecs_world.borrow();
+            let velocities = entities
+                .iter()
+                .map(|entity| ecs_world.get::<&RigidBody>(*entity).unwrap().velocity)
+                .collect::<Vec<_>>();
+            (contacts, velocities)
+        };
+
+        let (contacts, velocities) = step(None);
+        assert_eq!(contacts.len(), 3);
+        // Small cells spread each box over several cells, a huge cell holds every box.
+        for cell_size in [0.5, 1000.0] {
+            let (grid_contacts, grid_velocities) = step(Some(cell_size));
+            assert_eq!(grid_contacts, contacts);
+            for (a, b) in grid_velocities.iter().zip(&velocities) {
+                assert!((a - b).norm() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn continuous_sphere_stops_at_thin_plane() {
+        let fire_sphere = |continuous: bool| {
+            let physics_world = RefCell::new(PhysicsWorld::new());
+            let ecs_world = RefCell::new(ECSWorld::new());
+            physics_world.borrow_mut().do_dynamics = true;
+
+            // A wall facing the sphere with no thickness.
+            let plane_collider =
+                physics_world
+                    .borrow_mut()
+                    .colliders
+                    .register_collider(PlaneCollider {
+                        center: Vector3::zeros(),
+                        normal: Vector3::z(),
+                        size: Vector2::new(2.0, 2.0),
+                    });
+            let mut plane_colliders = EntityColliders::new();
+            plane_colliders.colliders.push(plane_collider);
+            ecs_world
+                .borrow_mut()
+                .spawn((Transform::new(), plane_colliders));
+
+            // A small sphere covering many times its size each step.
+            let mut sphere = CapsuleCollider::new();
+            sphere.radius = 0.05;
+            sphere.half_height = 0.0;
+            let sphere_collider = physics_world
+                .borrow_mut()
+                .colliders
+                .register_collider(sphere);
+            let mut sphere_colliders = EntityColliders::new();
+            sphere_colliders.colliders.push(sphere_collider);
+            let mut transform = Transform::new();
+            transform.position = Vector3::new(0.0, 0.0, 3.0);
+            let mut rigid_body = RigidBody::default();
+            rigid_body.velocity = Vector3::new(0.0, 0.0, -100.0);
+            rigid_body.restitution = 0.0;
+            rigid_body.continuous = continuous;
+            let body = ecs_world
+                .borrow_mut()
+                .spawn((transform, rigid_body, sphere_colliders));
+
+            let events = RefCell::new(Events::new());
+            for _ in 0..10 {
+                physics_world.borrow_mut().curr_timestep = Duration::from_secs_f32(1.0 / 60.0);
+                PhysicsWorld::do_physics_update(
+                    physics_world.borrow_mut(),
+                    ecs_world.borrow_mut(),
+                    events.borrow_mut(),
+                );
+                PhysicsWorld::do_transform_interpolation(
+                    physics_world.borrow_mut(),
+                    ecs_world.borrow_mut(),
+                );
+            }
+            let ecs_world = ecs_world.borrow();
+            let rigid_body = ecs_world.get::<&RigidBody>(body).unwrap();
+            (rigid_body.position(), rigid_body.velocity)
+        };
+
+        let (position, velocity) = fire_sphere(true);
+        assert!(position.z >= 0.05 && position.z < 0.06);
+        assert!(velocity.z.abs() < 1e-4);
+
+        // Without sweeping it passes straight through.
+        let (position, _) = fire_sphere(false);
+        assert!(position.z < -10.0);
+    }
+
+    #[test]
+    fn double_pendulum_keeps_joint_distances() {
+        let physics_world = RefCell::new(PhysicsWorld::new());
+        let ecs_world = RefCell::new(ECSWorld::new());
+        physics_world.borrow_mut().do_dynamics = true;
+
+        // A static pivot with two bodies hanging off of it horizontally.
+        let pivot = ecs_world.borrow_mut().spawn((Transform::new(),));
+        let spawn_body = |x: f32| {
+            let mut transform = Transform::new();
+            transform.position = Vector3::new(x, 0.0, 0.0);
+            ecs_world
+                .borrow_mut()
+                .spawn((transform, RigidBody::default()))
+        };
+        let body_a = spawn_body(1.0);
+        let body_b = spawn_body(2.0);
+        let world_transform = |entity: Entity| {
+            let ecs_world = ecs_world.borrow();
+            let transform = ecs_world.get::<&Transform>(entity).unwrap();
+            ecs_world.get_world_transform(entity, &transform)
+        };
+        for (entity_a, entity_b) in [(pivot, body_a), (body_a, body_b)] {
+            let (transform_a, transform_b) = (world_transform(entity_a), world_transform(entity_b));
+            let joint = Joint::new_distance(
+                entity_a,
+                &transform_a,
+                transform_a.position,
+                entity_b,
+                &transform_b,
+                transform_b.position,
+            );
+            physics_world.borrow_mut().add_joint(joint).unwrap();
+        }
+
+        let events = RefCell::new(Events::new());
+        let mut lowest_y = 0.0f32;
+        for _ in 0..120 {
+            physics_world.borrow_mut().curr_timestep = Duration::from_secs_f32(1.0 / 60.0);
+            PhysicsWorld::do_physics_update(
+                physics_world.borrow_mut(),
+                ecs_world.borrow_mut(),
+                events.borrow_mut(),
+            );
+            PhysicsWorld::do_transform_interpolation(
+                physics_world.borrow_mut(),
+                ecs_world.borrow_mut(),
+            );
+
+            let ecs_world = ecs_world.borrow();
+            let position_a = ecs_world.get::<&RigidBody>(body_a).unwrap().position();
+            let position_b = ecs_world.get::<&RigidBody>(body_b).unwrap().position();
+            assert!((position_a.norm() - 1.0).abs() < 0.05);
+            assert!((position_b.metric_distance(&position_a) - 1.0).abs() < 0.05);
+            lowest_y = lowest_y.min(position_a.y);
+        }
+        // It swings down rather than staying put.
+        assert!(lowest_y < -0.5);
+    }
+
+    #[test]
+    fn despawned_entities_lose_their_joints() {
+        let physics_world = RefCell::new(PhysicsWorld::new());
+        let ecs_world = RefCell::new(ECSWorld::new());
+        let spawn = |x: f32| {
+            let mut transform = Transform::new();
+            transform.position = Vector3::new(x, 0.0, 0.0);
+            ecs_world.borrow_mut().spawn((transform,))
+        };
+        let (entity_a, entity_b, entity_c) = (spawn(0.0), spawn(1.0), spawn(2.0));
+        let joint = |entity_a: Entity, entity_b: Entity| {
+            let transform = Transform::new();
+            Joint::new_hinge(
+                entity_a,
+                &transform,
+                entity_b,
+                &transform,
+                Vector3::zeros(),
+                Vector3::x(),
+                None,
+            )
+        };
+
+        assert!(
+            physics_world
+                .borrow_mut()
+                .add_joint(joint(entity_a, entity_a))
+                .is_err()
+        );
+        physics_world
+            .borrow_mut()
+            .add_joint(joint(entity_a, entity_b))
+            .unwrap();
+        let kept_joint = physics_world
+            .borrow_mut()
+            .add_joint(joint(entity_b, entity_c))
+            .unwrap();
+
+        ecs_world.borrow_mut().despawn(entity_a, false);
+        PhysicsWorld::remove_despawned_joints(physics_world.borrow_mut(), ecs_world.borrow());
+        let physics_world = physics_world.borrow();
+        let joints = physics_world
+            .joints()
+            .map(|(joint_id, _)| joint_id)
+            .collect::<Vec<_>>();
+        assert_eq!(joints, vec![kept_joint]);
+    }
+
+    #[test]
+    fn trigger_overlap_reports_enter_without_response() {
+        let mut physics_world = PhysicsWorld::new();
+        physics_world.do_dynamics = true;
+        physics_world.curr_timestep = Duration::from_secs_f32(1.0 / 60.0);
+        let physics_world = RefCell::new(physics_world);
+        let ecs_world = RefCell::new(ECSWorld::new());
+        let events = RefCell::new(Events::new());
+
+        let mut trigger_collider = BoxCollider::default();
+        trigger_collider.is_trigger = true;
+        let trigger_collider = physics_world
+            .borrow_mut()
+            .colliders
+            .register_collider(trigger_collider);
+        let mut trigger_colliders = EntityColliders::new();
+        trigger_colliders.colliders.push(trigger_collider);
+        let trigger = ecs_world
+            .borrow_mut()
+            .spawn((Transform::new(), trigger_colliders));
+
+        // A capsule falling through the middle of the trigger.
+        let mut capsule = CapsuleCollider::new();
+        capsule.radius = 0.25;
+        capsule.half_height = 0.25;
+        let capsule_collider = physics_world
+            .borrow_mut()
+            .colliders
+            .register_collider(capsule);
+        let mut capsule_colliders = EntityColliders::new();
+        capsule_colliders.colliders.push(capsule_collider);
+        let mut rigid_body = RigidBody::default();
+        rigid_body.velocity = Vector3::new(0.0, -1.0, 0.0);
+        let body = ecs_world
+            .borrow_mut()
+            .spawn((Transform::new(), rigid_body, capsule_colliders));
+
+        let mut reader = EventReader::<TriggerOverlapEvent>::new();
+        let mut step = || {
+            PhysicsWorld::do_physics_update(
+                physics_world.borrow_mut(),
+                ecs_world.borrow_mut(),
+                events.borrow_mut(),
+            );
+            reader
+                .read(&events.borrow())
+                .map(|event| {
+                    let entities = [event.entity_a, event.entity_b];
+                    assert!(entities.contains(&trigger) && entities.contains(&body));
+                    event.state
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(step(), vec![TriggerOverlapState::Enter]);
+        // Still overlapping so no new events.
+        assert!(step().is_empty());
+
+        assert!(physics_world.borrow().narrow_phase.contact_pairs.is_empty());
+        let ecs_world = ecs_world.borrow();
+        let rigid_body = ecs_world.get::<&RigidBody>(body).unwrap();
+        assert_eq!(rigid_body.velocity.x, 0.0);
+        assert_eq!(rigid_body.velocity.z, 0.0);
+        assert!(rigid_body.velocity.y < -1.0);
+    }
+
+    #[test]
+    fn fixed_timestep_accumulates_frame_time() {
+        let mut physics_world = PhysicsWorld::new();
+        physics_world.set_fixed_timestep(Duration::from_millis(10));
+        physics_world.set_max_substeps(4);
+        let physics_world = RefCell::new(physics_world);
+        let mut run_frame = |delta_time: Duration| {
+            let updates = physics_world.borrow_mut().physics_update_count(delta_time);
+            for _ in 0..updates {
+                PhysicsWorld::start_time_step(physics_world.borrow_mut());
+                PhysicsWorld::end_time_step(physics_world.borrow_mut());
+            }
+            (updates, physics_world.borrow().interpolation_alpha())
+        };
+
+        // Frames shorter than a step carry over to the next frame.
+        let (updates, alpha) = run_frame(Duration::from_millis(4));
+        assert_eq!(updates, 0);
+        assert!((alpha - 0.4).abs() < 1e-4);
+        let (updates, alpha) = run_frame(Duration::from_millis(21));
+        assert_eq!(updates, 2);
+        assert!((alpha - 0.5).abs() < 1e-4);
+
+        // A hitch only runs up to the max substeps and drops the rest.
+        let (updates, alpha) = run_frame(Duration::from_secs(1));
+        assert_eq!(updates, 4);
+        assert_eq!(alpha, 0.0);
+        let (updates, _) = run_frame(Duration::from_millis(10));
+        assert_eq!(updates, 1);
+    }
+}
@@ -2,7 +2,10 @@ use nalgebra::{Rotation3, UnitQuaternion, Vector2, Vector3};
 
 use super::{capsule_collider::CapsuleCollider, transform::Transform};
 use crate::common::geometry::aabb::AABB;
-use crate::physics::collider::{Collider, ColliderMethods, ContactManifold, ContactPair};
+use crate::common::geometry::ray::Ray;
+use crate::physics::collider::{
+    Collider, ColliderMethods, ColliderRayHit, ContactManifold, ContactPair, TimeOfImpact,
+};
 use crate::physics::collider_voxel_registry::VoxelColliderRegistry;
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
@@ -23,33 +26,128 @@ impl Default for PlaneCollider {
     }
 }
 
-impl PlaneCollider {}
+impl PlaneCollider {
+    /// The world space center and rotation of the plane, the rotation takes +y to the plane's
+    /// normal and +x/+z along its `size`.
+    pub fn world_plane(&self, world_transform: &Transform) -> (Vector3<f32>, UnitQuaternion<f32>) {
+        let rot = UnitQuaternion::from_rotation_matrix(
+            &Rotation3::rotation_between(&Vector3::y(), &self.normal)
+                .unwrap_or(Rotation3::identity()),
+        );
+        let center = world_transform.position
+            + world_transform.rotation * self.center.component_mul(&world_transform.scale);
+        (center, world_transform.rotation * rot)
+    }
+
+    /// The half extents along the plane's local x and z axes.
+    pub fn world_half_size(&self, world_transform: &Transform) -> Vector2<f32> {
+        self.size.component_mul(&Vector2::new(
+            world_transform.scale.x,
+            world_transform.scale.z,
+        ))
+    }
+}
 
 impl Collider for PlaneCollider {
     const NAME: &str = "PlaneCollider";
 
     fn aabb(&self, world_transform: &Transform, _: &VoxelColliderRegistry) -> Option<AABB> {
-        let rot = UnitQuaternion::from_rotation_matrix(
-            &Rotation3::rotation_between(&Vector3::y(), &self.normal)
-                .unwrap_or(Rotation3::identity()),
-        );
-        let size_3 = Vector3::new(self.size.x, 0.0, self.size.y);
-        let min = self.center + rot * -size_3;
-        let max = self.center + rot * size_3;
-        return Some(AABB::new_two_point(min, max));
+        let (center, rot) = self.world_plane(world_transform);
+        let half_size = self.world_half_size(world_transform);
+        let corner_a = rot * Vector3::new(half_size.x, 0.0, half_size.y);
+        let corner_b = rot * Vector3::new(half_size.x, 0.0, -half_size.y);
+        let extents = corner_a.abs().sup(&corner_b.abs());
+        return Some(AABB::new_two_point(center - extents, center + extents));
+    }
+
+    fn raycast(
+        &self,
+        world_transform: &Transform,
+        ray: &Ray,
+        max_distance: f32,
+        _: &VoxelColliderRegistry,
+    ) -> Option<ColliderRayHit> {
+        let (center, rot) = self.world_plane(world_transform);
+        let normal = rot * Vector3::y();
+        let approach = ray.dir.dot(&normal);
+        // Parallel rays never hit the infinitely thin plane.
+        if approach.abs() <= f32::EPSILON {
+            return None;
+        }
+        let distance = (center - ray.origin).dot(&normal) / approach;
+        if distance < 0.0 || distance > max_distance {
+            return None;
+        }
+
+        let local_point = rot.inverse() * (ray.origin + ray.dir * distance - center);
+        let half_size = self.world_half_size(world_transform);
+        if local_point.x.abs() > half_size.x || local_point.z.abs() > half_size.y {
+            return None;
+        }
+        // The plane is two sided so face the normal back toward the ray.
+        Some(ColliderRayHit {
+            distance,
+            normal: normal * -approach.signum(),
+        })
     }
 
     fn serialize_collider(
         &self,
         ser: &mut dyn erased_serde::Serializer,
     ) -> erased_serde::Result<()> {
-        todo!()
+        use erased_serde::Serialize;
+        self.erased_serialize(ser)
     }
 
     unsafe fn deserialize_collider(
         de: &mut dyn erased_serde::Deserializer,
         dst_ptr: *mut u8,
     ) -> erased_serde::Result<()> {
-        todo!()
+        let dst_ptr = dst_ptr as *mut Self;
+        // Safety: dst_ptr should be allocated with the memory layout for this type.
+        unsafe { dst_ptr.write(erased_serde::deserialize::<Self>(de)?) };
+        Ok(())
+    }
+}
+
+/// Sweeps the capsule along `motion` against the two sided plane. Capsules already touching
+/// the plane are only stopped if they move further into it.
+pub fn capsule_plane_time_of_impact(
+    capsule: &CapsuleCollider,
+    plane: &PlaneCollider,
+    transform_capsule: &Transform,
+    transform_plane: &Transform,
+    motion: Vector3<f32>,
+) -> Option<TimeOfImpact> {
+    let (bottom, top, radius) = capsule.world_segment(transform_capsule);
+    let (center, rot) = plane.world_plane(transform_plane);
+    let normal = rot * Vector3::y();
+
+    // Face the normal toward the side the capsule is on.
+    let side = ((bottom + top) * 0.5 - center).dot(&normal).signum();
+    let normal = normal * side;
+    let distance_bottom = (bottom - center).dot(&normal);
+    let distance_top = (top - center).dot(&normal);
+    let (closest_point, distance) = if distance_bottom < distance_top {
+        (bottom, distance_bottom)
+    } else {
+        (top, distance_top)
+    };
+    let approach = -motion.dot(&normal);
+    // Moving away, or the capsule end is already through the plane.
+    if approach <= 0.0 || distance < 0.0 {
+        return None;
+    }
+    let t = ((distance - radius) / approach).max(0.0);
+    if t > 1.0 {
+        return None;
+    }
+
+    // Only hits within the plane's bounds, grown by the radius so the edges aren't skipped.
+    let local_point = rot.inverse() * (closest_point + motion * t - center);
+    let half_size = plane.world_half_size(transform_plane);
+    if local_point.x.abs() > half_size.x + radius || local_point.z.abs() > half_size.y + radius {
+        return None;
     }
+    Some(TimeOfImpact { t, normal })
 }
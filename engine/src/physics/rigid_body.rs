@@ -64,6 +64,9 @@ pub struct RigidBodyCreateInfo {
     pub friction: f32,
     pub locked_rotational_axes: Vector3<bool>,
     pub interpolation: RigidBodyPositionInterpolation,
+    pub collision_layer: u32,
+    pub collision_mask: u32,
+    pub continuous: bool,
 }
 
 impl Default for RigidBodyCreateInfo {
@@ -75,6 +78,9 @@ impl Default for RigidBodyCreateInfo {
             friction: 0.7,
             locked_rotational_axes: Vector3::new(false, false, false),
             interpolation: RigidBodyPositionInterpolation::None,
+            collision_layer: RigidBody::DEFAULT_COLLISION_LAYER,
+            collision_mask: RigidBody::DEFAULT_COLLISION_MASK,
+            continuous: false,
         }
     }
 }
@@ -125,6 +131,15 @@ pub struct RigidBody {
     // Where 1 is fully elastic and 0 is non-elastic.
     pub restitution: f32,
     pub friction: f32,
+
+    /// The layers this body is on, bodies only collide if each one's layer overlaps the
+    /// other's mask.
+    pub collision_layer: u32,
+    /// The layers this body collides with.
+    pub collision_mask: u32,
+    /// Sweeps the body's capsule colliders along its motion each step so fast bodies stop at
+    /// box and plane colliders instead of tunneling through them.
+    pub continuous: bool,
 }
 
 impl Default for RigidBody {
@@ -134,6 +149,9 @@ impl Default for RigidBody {
 }
 
 impl RigidBody {
+    pub const DEFAULT_COLLISION_LAYER: u32 = 1;
+    pub const DEFAULT_COLLISION_MASK: u32 = u32::MAX;
+
     pub fn new(create_info: RigidBodyCreateInfo) -> Self {
         Self {
             last_position: Vector3::zeros(),
@@ -157,6 +175,9 @@ impl RigidBody {
             inv_inertia_world: Matrix3::identity(),
             restitution: create_info.restitution,
             friction: create_info.friction,
+            collision_layer: create_info.collision_layer,
+            collision_mask: create_info.collision_mask,
+            continuous: create_info.continuous,
         }
     }
 
@@ -168,6 +189,9 @@ impl RigidBody {
             friction: 0.7,
             locked_rotational_axes: Vector3::new(false, false, false),
             interpolation: RigidBodyPositionInterpolation::None,
+            collision_layer: Self::DEFAULT_COLLISION_LAYER,
+            collision_mask: Self::DEFAULT_COLLISION_MASK,
+            continuous: false,
         })
     }
 
@@ -216,9 +240,18 @@ impl RigidBody {
             friction: self.friction,
             locked_rotational_axes: self.locked_rotational_axes,
             interpolation: self.interpolation,
+            collision_layer: self.collision_layer,
+            collision_mask: self.collision_mask,
+            continuous: self.continuous,
         }
     }
 
+    /// Whether the collision layers and masks of both bodies allow them to interact.
+    pub fn can_collide_with(&self, other: &RigidBody) -> bool {
+        self.collision_layer & other.collision_mask != 0
+            && other.collision_layer & self.collision_mask != 0
+    }
+
     pub fn velocity(&self) -> Vector3<f32> {
         if self.rigid_body_type == RigidBodyType::Static {
             return Vector3::zeros();
@@ -295,6 +328,12 @@ impl RigidBody {
         );
     }
 
+    /// Moves the rigid body without affecting its velocity, used when the world origin shifts.
+    pub fn translate(&mut self, offset: Vector3<f32>) {
+        self.position += offset;
+        self.last_position += offset;
+    }
+
     pub fn set_position(&mut self, position: Vector3<f32>) {
         if self.is_static() {
             return;
@@ -7,7 +7,11 @@ use nalgebra::Vector2;
 use rogue_macros::Resource;
 use serde::{Deserialize, Serialize};
 use crate::asset::repr::settings::UserSettingsAsset;
-use crate::graphics::{backend::GfxPresentMode, renderer::Antialiasing};
+use crate::graphics::{
+    backend::{GfxPresentMode, GfxValidation},
+    passes::fxaa_pass::FxaaQuality,
+    renderer::Antialiasing,
+};
 
 /// Called/recieved whenever a graphics setting is changed.
 pub enum GraphicsSettingsEvent {
@@ -18,9 +22,42 @@ pub enum GraphicsSettingsEvent {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GraphicsSettings {
     pub rt_size: Vector2<u32>,
+    /// The resolution the scene is rendered at relative to the viewport, above 1 supersamples
+    /// and below 1 renders fewer pixels which are upscaled when blitted to the viewport.
+    pub render_scale: f32,
     pub antialiasing: Antialiasing,
+    pub fxaa_quality: FxaaQuality,
+    /// How much of the accumulated history is kept each frame with taa, higher converges to a
+    /// smoother image but ghosts more in motion.
+    pub taa_feedback: f32,
     pub present_mode: GfxPresentMode,
     pub triple_buffering: bool,
+    /// Applied on startup since the graphics device must be recreated.
+    pub validation: GfxValidation,
+    /// Advanced: Forces the workgroup size of the world rt pass instead of auto-tuning it for
+    /// the current device at startup.
+    pub rt_workgroup_size_override: Option<Vector2<u32>>,
+    /// The workgroup size auto-tuning chose for the world rt pass, saved so tuning only runs
+    /// once per install.
+    pub rt_workgroup_size: Option<Vector2<u32>>,
+    /// Softens sun shadows by tracing a cone of jittered shadow rays per pixel, the noise is
+    /// accumulated over frames with taa.
+    pub soft_shadows: bool,
+    /// The shadow rays traced per pixel each frame with soft shadows.
+    pub soft_shadow_samples: u32,
+    /// The angular diameter of the sun in radians, larger suns cast softer shadows.
+    pub sun_angular_size: f32,
+    /// How much the ambient occlusion baked into voxel models darkens them, zero disables it.
+    pub voxel_ao_strength: f32,
+    /// Blurs the frame outside the main camera's focus distance, see `Camera::aperture`.
+    pub depth_of_field: bool,
+    /// Blurs the parts of the frame brighter than the bloom threshold over their surroundings,
+    /// making emissive voxels glow.
+    pub bloom: bool,
+    /// The hdr brightness a color channel must pass to bloom.
+    pub bloom_threshold: f32,
+    /// How strongly the blurred bloom is added back onto the frame.
+    pub bloom_intensity: f32,
 }
 
 impl Default for GraphicsSettings {
@@ -28,10 +65,47 @@ impl Default for GraphicsSettings {
         Self {
             // Target 720p upscaled to native resolution running at >90fps on my gtx 1070.
             rt_size: Vector2::new(1280, 720),
+            render_scale: 1.0,
             antialiasing: Antialiasing::None,
-            present_mode: GfxPresentMode::Vsync,
+            fxaa_quality: FxaaQuality::Medium,
+            taa_feedback: 0.9,
+            present_mode: GfxPresentMode::Mailbox,
             triple_buffering: true,
+            validation: GfxValidation::Auto,
+            rt_workgroup_size_override: None,
+            rt_workgroup_size: None,
+            soft_shadows: false,
+            soft_shadow_samples: 2,
+            sun_angular_size: 0.05,
+            voxel_ao_strength: 1.0,
+            depth_of_field: false,
+            bloom: false,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.1,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    pub const MIN_RENDER_SCALE: f32 = 0.25;
+    pub const MAX_RENDER_SCALE: f32 = 4.0;
+
+    /// The size the scene is rendered at for a viewport of `viewport_size`, shrunk to keep the
+    /// aspect ratio when either side would be larger than `max_image_dimension`.
+    pub fn render_size(
+        &self,
+        viewport_size: Vector2<u32>,
+        max_image_dimension: u32,
+    ) -> Vector2<u32> {
+        let render_scale = self
+            .render_scale
+            .clamp(Self::MIN_RENDER_SCALE, Self::MAX_RENDER_SCALE);
+        let mut render_size = viewport_size.cast::<f32>() * render_scale;
+        let longest_side = render_size.max();
+        if longest_side > max_image_dimension as f32 {
+            render_size *= max_image_dimension as f32 / longest_side;
         }
+        render_size.map(|x| (x.round() as u32).min(max_image_dimension))
     }
 }
 
@@ -46,11 +120,22 @@ pub struct Settings {
     /// The field of view in degrees of the camera.
     pub editor_camera_fov: f32,
 
-    /// The mouse sensitivity of pixels per degree of rotation.
+    /// The editor camera's radians of rotation per unit of relative mouse motion.
     pub editor_mouse_sensitivity: f32,
+    /// The game camera's radians of rotation per unit of relative mouse motion.
+    pub game_mouse_sensitivity: f32,
 
     /// The controller sensitivity of degrees per second.
     pub controller_sensitity: f32,
+    /// How far a gamepad stick or trigger must move from rest before it registers, in [0, 1].
+    pub gamepad_deadzone: f32,
+
+    /// How much camera shake trauma is lost per second.
+    pub camera_shake_decay: f32,
+    /// The max rotation in radians on each axis of a camera shaking with full trauma.
+    pub camera_shake_max_angle: f32,
+    /// The max offset in meters on each axis of a camera shaking with full trauma.
+    pub camera_shake_max_offset: f32,
 
     /// The chunk render distance, also acts as the load
     /// and simulation distance for simplicity.
@@ -64,6 +149,7 @@ pub struct Settings {
     pub ticks_per_seconds: u32,
 
     pub graphics: GraphicsSettings,
+    /// The max frames per second independent of the present mode, 0 is uncapped.
     pub frame_rate_cap: u32,
 }
 
@@ -73,7 +159,13 @@ impl From<&UserSettingsAsset> for Settings {
         Self {
             editor_camera_fov: consts::FRAC_PI_2,
             editor_mouse_sensitivity: s.mouse_sensitivity,
+            game_mouse_sensitivity: s.game_mouse_sensitivity,
             controller_sensitity: s.controller_sensitivity,
+            gamepad_deadzone: s.gamepad_deadzone,
+
+            camera_shake_decay: s.camera_shake_decay,
+            camera_shake_max_angle: s.camera_shake_max_angle,
+            camera_shake_max_offset: s.camera_shake_max_offset,
 
             chunk_render_distance: s.chunk_render_distance,
             chunk_queue_capacity: std::thread::available_parallelism()
@@ -82,18 +174,45 @@ impl From<&UserSettingsAsset> for Settings {
 
             ticks_per_seconds: 10,
 
-            graphics: GraphicsSettings::default(),
-            frame_rate_cap: 144,
+            graphics: GraphicsSettings {
+                validation: s.graphics_validation,
+                present_mode: s.present_mode,
+                rt_workgroup_size: s.rt_workgroup_size,
+                rt_workgroup_size_override: s.rt_workgroup_size_override,
+                ..GraphicsSettings::default()
+            },
+            frame_rate_cap: s.frame_rate_cap,
         }
     }
 }
 
-impl From<&Settings> for UserSettingsAsset {
-    fn from(s: &Settings) -> Self {
-        Self {
-            mouse_sensitivity: s.editor_mouse_sensitivity,
-            controller_sensitivity: s.controller_sensitity,
-            chunk_render_distance: s.chunk_render_distance,
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_size_scales_within_device_limits() {
+        let mut graphics = GraphicsSettings::default();
+        let viewport_size = Vector2::new(1600, 900);
+        assert_eq!(graphics.render_size(viewport_size, 16384), viewport_size);
+
+        graphics.render_scale = 0.5;
+        assert_eq!(
+            graphics.render_size(viewport_size, 16384),
+            Vector2::new(800, 450)
+        );
+
+        // Supersampling past the device limit keeps the aspect ratio.
+        graphics.render_scale = 4.0;
+        assert_eq!(
+            graphics.render_size(viewport_size, 4096),
+            Vector2::new(4096, 2304)
+        );
+
+        graphics.render_scale = 0.0;
+        assert_eq!(
+            graphics.render_size(viewport_size, 16384),
+            Vector2::new(400, 225)
+        );
     }
 }
@@ -4,7 +4,10 @@ use log::warn;
 use nalgebra::Vector3;
 
 use crate::{
-    common::color::{Color, ColorSpaceSrgb, ColorSpaceSrgbLinear, ColorSpaceXYZ},
+    common::{
+        color::{Color, ColorSpaceSrgb, ColorSpaceSrgbLinear, ColorSpaceXYZ},
+        geometry::axis::Axis,
+    },
     consts::voxel::attachment,
 };
 
@@ -22,7 +25,8 @@ impl Attachment {
     pub const NORMAL_ID: AttachmentId = 1;
     pub const EMMISIVE_ID: AttachmentId = 2;
     pub const BMAT_ID: AttachmentId = 3;
-    pub const MAX_ATTACHMENT_ID: AttachmentId = 3;
+    pub const AO_ID: AttachmentId = 4;
+    pub const MAX_ATTACHMENT_ID: AttachmentId = 4;
     pub const MAX_ATTACHMENT_COUNT: AttachmentId = Self::MAX_ATTACHMENT_ID + 1;
 
     pub const PTMATERIAL: Attachment =
@@ -30,11 +34,15 @@ impl Attachment {
     pub const NORMAL: Attachment = Attachment::new(Attachment::NORMAL_ID, "normal", 1);
     pub const EMMISIVE: Attachment = Attachment::new(Attachment::EMMISIVE_ID, "emmisive", 1);
     pub const BMAT: Attachment = Attachment::new(Attachment::BMAT_ID, "builtin_material", 2);
+    pub const AO: Attachment = Attachment::new(Attachment::AO_ID, "ambient_occlusion", 1);
 
     pub fn from_id(id: AttachmentId) -> Self {
         match id {
             Self::PTMATERIAL_ID => Self::PTMATERIAL,
+            Self::NORMAL_ID => Self::NORMAL,
+            Self::EMMISIVE_ID => Self::EMMISIVE,
             Self::BMAT_ID => Self::BMAT,
+            Self::AO_ID => Self::AO,
             _ => panic!("Can't find attachment for id {}.", id),
         }
     }
@@ -77,7 +85,7 @@ impl Attachment {
     }
 
     pub fn encode_normal(normal: &Vector3<f32>) -> u32 {
-        assert!(normal.norm() == 1.0);
+        assert!((normal.norm() - 1.0).abs() < 1e-4);
 
         let mut x = 0u32;
         x |= (((normal.x * 0.5 + 0.5) * 255.0).ceil() as u32) << 16;
@@ -87,6 +95,14 @@ impl Attachment {
         x
     }
 
+    /// Negates the `axis` component of an encoded normal, flipping the component's byte keeps
+    /// this exact so mirroring twice gives back the same normal.
+    pub fn mirror_normal(normal: u32, axis: Axis) -> u32 {
+        let shift = 16 - 8 * axis.index() as u32;
+        let component = (normal >> shift) & 0xFF;
+        (normal & !(0xFF << shift)) | ((0xFF - component) << shift)
+    }
+
     pub fn decode_normal(normal: u32) -> Vector3<f32> {
         let x = (((normal >> 16) & 0xFF) as f32 / 255.0) * 2.0 - 1.0;
         let y = (((normal >> 8) & 0xFF) as f32 / 255.0) * 2.0 - 1.0;
@@ -94,6 +110,16 @@ impl Attachment {
 
         Vector3::new(x, y, z)
     }
+
+    /// Quantizes the ambient light reaching a voxel in [0, 1] to the lowest byte, 1 being
+    /// unoccluded.
+    pub fn encode_ao(ao: f32) -> u32 {
+        (ao.clamp(0.0, 1.0) * 255.0).round() as u32
+    }
+
+    pub fn decode_ao(ao: u32) -> f32 {
+        (ao & 0xFF) as f32 / 255.0
+    }
 }
 
 /// First 16 most signifigant bits are reserved for the octahedral encoded normal.
@@ -143,9 +169,9 @@ impl PTMaterial {
         let mat_ty = val >> 30;
         match mat_ty {
             0 => {
-                let r = ((val >> 24) & 0xFF) as f32 / 255.0;
-                let g = ((val >> 16) & 0xFF) as f32 / 255.0;
-                let b = ((val >> 8) & 0xFF) as f32 / 255.0;
+                let r = ((val >> 16) & 0xFF) as f32 / 255.0;
+                let g = ((val >> 8) & 0xFF) as f32 / 255.0;
+                let b = (val & 0xFF) as f32 / 255.0;
 
                 PTMaterial::Diffuse {
                     albedo: Color::<ColorSpaceSrgb>::new(r, g, b),
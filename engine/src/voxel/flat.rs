@@ -15,14 +15,14 @@ use nalgebra::Vector3;
 use super::{
     attachment::{Attachment, AttachmentId, AttachmentInfoMap, AttachmentMap},
     voxel::{
-        VoxelModelEdit, VoxelModelGpuImpl, VoxelModelGpuImplMethods, VoxelModelImpl,
-        VoxelModelImplMethods, VoxelModelTrace,
+        VoxelMaterialData, VoxelModelEdit, VoxelModelGpuImpl, VoxelModelGpuImplMethods,
+        VoxelModelImpl, VoxelModelImplMethods, VoxelModelTrace,
     },
     voxel_allocator::{VoxelDataAllocation, VoxelDataAllocator},
 };
 use crate::{common::geometry::ray::Ray, material::model_material_map::ModelMaterialMap};
 use crate::{
-    common::geometry::{aabb::AABB, ray::RayAABBHitInfo},
+    common::geometry::{aabb::AABB, axis::Axis, ray::RayAABBHitInfo},
     material::material_gpu::MaterialBankGpu,
 };
 use crate::{
@@ -37,6 +37,17 @@ use crate::{
     material::material_bank::MaterialBank,
 };
 
+/// How `VoxelModelFlat::boolean` combines the presence of two models.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoxelModelBooleanOp {
+    /// Voxels present in either model.
+    Union,
+    /// Voxels present in both models.
+    Intersect,
+    /// Voxels present in `self` but not in `other`.
+    Subtract,
+}
+
 /// A float 1D array representing a 3D voxel region.
 #[derive(Clone)]
 pub struct VoxelModelFlat {
@@ -184,6 +195,359 @@ impl VoxelModelFlat {
         }
     }
 
+    /// Returns this model flipped along `axis`. Normals are negated along `axis` so shading
+    /// stays correct, other attachments are copied as is.
+    pub fn mirrored(&self, axis: Axis) -> VoxelModelFlat {
+        let axis_index = axis.index();
+        let mut mirrored = self.clone();
+        for index in 0..self.volume {
+            let mut position = self.get_voxel_position(index);
+            position[axis_index] = self.side_length[axis_index] - 1 - position[axis_index];
+            let mirrored_index = self.get_voxel_index(position);
+            mirrored
+                .presence_data
+                .set_bit(mirrored_index, self.presence_data.get_bit(index));
+
+            for (attachment_id, attachment) in self.attachment_map.iter() {
+                let is_present = self
+                    .attachment_presence_data
+                    .get(attachment_id)
+                    .unwrap()
+                    .get_bit(index);
+                mirrored
+                    .attachment_presence_data
+                    .get_mut(attachment_id)
+                    .unwrap()
+                    .set_bit(mirrored_index, is_present);
+
+                let size = attachment.size() as usize;
+                let src = &self.attachment_data.get(attachment_id).unwrap()
+                    [(index * size)..((index + 1) * size)];
+                let dst = &mut mirrored.attachment_data.get_mut(attachment_id).unwrap()
+                    [(mirrored_index * size)..((mirrored_index + 1) * size)];
+                dst.copy_from_slice(src);
+                if attachment_id == Attachment::NORMAL_ID {
+                    for normal in dst {
+                        *normal = Attachment::mirror_normal(*normal, axis);
+                    }
+                }
+            }
+        }
+
+        mirrored
+    }
+
+    /// Writes `Attachment::NORMAL` for every surface voxel, pointing away from the occupied
+    /// voxels in its 26 neighborhood. Voxels enclosed on all 6 faces are interior and are left
+    /// without a normal, out of bounds neighbors count as empty.
+    pub fn compute_normals(&mut self) {
+        let is_occupied = |position: Vector3<i32>| {
+            position.iter().all(|x| *x >= 0)
+                && self.in_bounds(position.map(|x| x as u32))
+                && self
+                    .presence_data
+                    .get_bit(self.get_voxel_index(position.map(|x| x as u32)))
+        };
+
+        let mut normals = Vec::new();
+        for index in 0..self.volume {
+            if !self.presence_data.get_bit(index) {
+                continue;
+            }
+            let position = self.get_voxel_position(index).cast::<i32>();
+            let exposed_faces = Axis::ALL
+                .iter()
+                .flat_map(|axis| {
+                    let mut face = Vector3::zeros();
+                    face[axis.index()] = 1;
+                    [face, -face]
+                })
+                .filter(|face| !is_occupied(position + face))
+                .collect::<Vec<_>>();
+            if exposed_faces.is_empty() {
+                continue;
+            }
+
+            // The occupancy gradient, closer neighbors weigh more.
+            let mut gradient = Vector3::<f32>::zeros();
+            for x in -1..=1 {
+                for y in -1..=1 {
+                    for z in -1..=1 {
+                        let offset = Vector3::new(x, y, z);
+                        if offset != Vector3::zeros() && !is_occupied(position + offset) {
+                            let offset = offset.cast::<f32>();
+                            gradient += offset / offset.norm_squared();
+                        }
+                    }
+                }
+            }
+            // Empty neighbors on opposite sides can cancel out, such as on a one voxel
+            // thick wall, so fall back to a face that is exposed.
+            if gradient.norm_squared() <= f32::EPSILON {
+                gradient = exposed_faces[0].cast::<f32>();
+            }
+            normals.push((index, gradient.normalize()));
+        }
+
+        for (index, normal) in normals {
+            let position = self.get_voxel_position(index);
+            self.get_voxel_mut(position)
+                .set_attachment(Attachment::NORMAL, Some(Attachment::encode_normal(&normal)));
+        }
+    }
+
+    /// Writes `Attachment::AO` for every surface voxel, the ambient light reaching its exposed
+    /// faces. Each exposed face is occluded by the 8 voxels around the empty neighbor in front
+    /// of it, where the diagonal corners weigh half as much as the edges. Interior voxels are
+    /// left without ao, out of bounds neighbors count as empty.
+    ///
+    /// Only baked when a `.vox` is imported, voxel edits don't recompute it so ao around edited
+    /// voxels goes stale until the model is imported again.
+    pub fn compute_ao(&mut self) {
+        let is_occupied = |position: Vector3<i32>| {
+            position.iter().all(|x| *x >= 0)
+                && self.in_bounds(position.map(|x| x as u32))
+                && self
+                    .presence_data
+                    .get_bit(self.get_voxel_index(position.map(|x| x as u32)))
+        };
+
+        let mut aos = Vec::new();
+        for index in 0..self.volume {
+            if !self.presence_data.get_bit(index) {
+                continue;
+            }
+            let position = self.get_voxel_position(index).cast::<i32>();
+            let mut ao_sum = 0.0;
+            let mut exposed_face_count = 0;
+            for axis in Axis::ALL {
+                for sign in [1, -1] {
+                    let mut face = Vector3::zeros();
+                    face[axis.index()] = sign;
+                    let front = position + face;
+                    if is_occupied(front) {
+                        continue;
+                    }
+
+                    let (u, v) = ((axis.index() + 1) % 3, (axis.index() + 2) % 3);
+                    let mut occlusion = 0.0;
+                    for du in -1..=1 {
+                        for dv in -1..=1 {
+                            if du == 0 && dv == 0 {
+                                continue;
+                            }
+                            let mut offset = Vector3::zeros();
+                            offset[u] = du;
+                            offset[v] = dv;
+                            if is_occupied(front + offset) {
+                                occlusion += if du != 0 && dv != 0 { 0.5 } else { 1.0 };
+                            }
+                        }
+                    }
+                    // 4 edges and 4 half weighted corners.
+                    ao_sum += 1.0 - occlusion / 6.0;
+                    exposed_face_count += 1;
+                }
+            }
+            if exposed_face_count == 0 {
+                continue;
+            }
+            aos.push((index, ao_sum / exposed_face_count as f32));
+        }
+
+        for (index, ao) in aos {
+            let position = self.get_voxel_position(index);
+            self.get_voxel_mut(position)
+                .set_attachment(Attachment::AO, Some(Attachment::encode_ao(ao)));
+        }
+    }
+
+    /// Combines this model with `other` placed at `offset` in this model's voxel space, see
+    /// `VoxelModelBooleanOp`. A union is expanded to fit both models, so its origin is the
+    /// minimum corner of the two and `self` ends up at `-offset.inf(&Vector3::zeros())`. An
+    /// intersection or subtraction keeps the bounds of `self`.
+    ///
+    /// Surviving voxels keep their attachments, preferring `self` where both models overlap.
+    /// The result uses the material map of `self`, with a union adding the materials of `other`
+    /// and remapping its builtin materials to them.
+    pub fn boolean(
+        &self,
+        other: &VoxelModelFlat,
+        op: VoxelModelBooleanOp,
+        offset: Vector3<i32>,
+    ) -> VoxelModelFlat {
+        let (min, max) = match op {
+            VoxelModelBooleanOp::Union => (
+                offset.inf(&Vector3::zeros()),
+                (offset + other.side_length.cast::<i32>()).sup(&self.side_length.cast::<i32>()),
+            ),
+            VoxelModelBooleanOp::Intersect | VoxelModelBooleanOp::Subtract => {
+                (Vector3::zeros(), self.side_length.cast::<i32>())
+            }
+        };
+
+        let mut result = VoxelModelFlat::new_empty((max - min).map(|x| x as u32));
+        result.material_map = self.material_map.clone();
+        for (_, attachment) in self.attachment_map.iter() {
+            result.initialize_attachment_buffers(attachment);
+        }
+        if op == VoxelModelBooleanOp::Union {
+            for (_, attachment) in other.attachment_map.iter() {
+                result.initialize_attachment_buffers(attachment);
+            }
+        }
+
+        // `position` is in the voxel space of `self`.
+        let other_index = |position: Vector3<i32>| {
+            let other_position = position - offset;
+            (other_position.iter().all(|x| *x >= 0)
+                && other.in_bounds(other_position.map(|x| x as u32)))
+            .then(|| other.get_voxel_index(other_position.map(|x| x as u32)))
+        };
+        let other_exists = |position: Vector3<i32>| {
+            other_index(position).is_some_and(|index| other.presence_data.get_bit(index))
+        };
+
+        if op == VoxelModelBooleanOp::Union {
+            // Indexed by `other`'s model material id.
+            let material_remap = other
+                .material_map
+                .model_materials
+                .iter()
+                .map(|model_material| {
+                    result
+                        .material_map
+                        .ensure_global_material_exists(&model_material.material_id);
+                    result
+                        .material_map
+                        .get_model_material(&model_material.material_id)
+                        .unwrap()
+                        .model_material_id
+                })
+                .collect::<Vec<_>>();
+            for index in 0..other.volume {
+                if other.presence_data.get_bit(index) {
+                    let position = other.get_voxel_position(index).cast::<i32>() + offset - min;
+                    let dst_index = result.get_voxel_index(position.map(|x| x as u32));
+                    result.copy_voxel(dst_index, other, index, Some(&material_remap));
+                }
+            }
+        }
+        for index in 0..self.volume {
+            if !self.presence_data.get_bit(index) {
+                continue;
+            }
+            let position = self.get_voxel_position(index).cast::<i32>();
+            let survives = match op {
+                VoxelModelBooleanOp::Union => true,
+                VoxelModelBooleanOp::Intersect => other_exists(position),
+                VoxelModelBooleanOp::Subtract => !other_exists(position),
+            };
+            if survives {
+                let dst_index = result.get_voxel_index((position - min).map(|x| x as u32));
+                result.copy_voxel(dst_index, self, index, None);
+            }
+        }
+
+        result
+    }
+
+    /// Returns a copy of this model with a side length of `new_side_length`, each voxel is moved
+    /// by `offset` and voxels moved out of the new bounds are discarded.
+    pub fn resized(&self, new_side_length: Vector3<u32>, offset: Vector3<i32>) -> VoxelModelFlat {
+        let mut resized = VoxelModelFlat::new_empty(new_side_length);
+        resized.material_map = self.material_map.clone();
+        for (_, attachment) in self.attachment_map.iter() {
+            resized.initialize_attachment_buffers(attachment);
+        }
+
+        for index in 0..self.volume {
+            if !self.presence_data.get_bit(index) {
+                continue;
+            }
+            let position = self.get_voxel_position(index).cast::<i32>() + offset;
+            if position.iter().any(|x| *x < 0) || !resized.in_bounds(position.map(|x| x as u32)) {
+                continue;
+            }
+            let dst_index = resized.get_voxel_index(position.map(|x| x as u32));
+            resized.copy_voxel(dst_index, self, index, None);
+        }
+
+        resized
+    }
+
+    /// Shrinks this model to the bounding box of its present voxels, returning the cropped
+    /// model and the offset its voxels were moved by, see `VoxelModelFlat::resized`. The
+    /// offset is never positive, so shift the model's transform by `-offset` voxels to keep the
+    /// voxels in place. Returns `None` if the model is empty.
+    pub fn cropped_to_content(&self) -> Option<(VoxelModelFlat, Vector3<i32>)> {
+        let mut min = self.side_length;
+        let mut max = Vector3::zeros();
+        for index in 0..self.volume {
+            if self.presence_data.get_bit(index) {
+                let position = self.get_voxel_position(index);
+                min = min.inf(&position);
+                max = max.sup(&position);
+            }
+        }
+        if min.x > max.x {
+            return None;
+        }
+
+        let offset = -min.cast::<i32>();
+        Some((self.resized(max - min + Vector3::repeat(1), offset), offset))
+    }
+
+    /// Overwrites the voxel at `dst_index` with the voxel at `src_index` of `src`, the
+    /// attachments of `src` must already be initialized.
+    /// Copies the voxel at `src_index` of `src` to `dst_index`, `material_remap` maps the model
+    /// material ids of unbaked builtin materials in `src` to the ones in this model.
+    fn copy_voxel(
+        &mut self,
+        dst_index: usize,
+        src: &VoxelModelFlat,
+        src_index: usize,
+        material_remap: Option<&[u32]>,
+    ) {
+        self.presence_data
+            .set_bit(dst_index, src.presence_data.get_bit(src_index));
+        for (_, presence) in self.attachment_presence_data.iter_mut() {
+            presence.set_bit(dst_index, false);
+        }
+
+        for (attachment_id, attachment) in src.attachment_map.iter() {
+            let is_present = src
+                .attachment_presence_data
+                .get(attachment_id)
+                .unwrap()
+                .get_bit(src_index);
+            if !is_present {
+                continue;
+            }
+            self.attachment_presence_data
+                .get_mut(attachment_id)
+                .unwrap()
+                .set_bit(dst_index, true);
+
+            let size = attachment.size() as usize;
+            let src_data = &src.attachment_data.get(attachment_id).unwrap()
+                [(src_index * size)..((src_index + 1) * size)];
+            let dst_data = &mut self.attachment_data.get_mut(attachment_id).unwrap()
+                [(dst_index * size)..((dst_index + 1) * size)];
+            dst_data.copy_from_slice(src_data);
+
+            // Stored little endian in terms of u32s, the model material id is in the low bits.
+            let encoded = ((dst_data[1] as u64) << 32) | dst_data[0] as u64;
+            if attachment_id == Attachment::BMAT_ID
+                && let Some(material_remap) = material_remap
+                && (encoded & VoxelMaterialData::NEEDS_MATERIAL_BAKE_FLAG) > 0
+            {
+                let model_material_id = (encoded & 0xFFFF) as usize;
+                dst_data[0] = (dst_data[0] & !0xFFFF) | material_remap[model_material_id];
+            }
+        }
+    }
+
     // Creates a rect with with given attributes for each voxel.
     // TODO: pub fn rect_filled(length: Vector3<u32>, voxel_data: VoxelData) -> Self {}
 }
@@ -1052,3 +1416,297 @@ impl VoxelModelGpuImplMethods for VoxelModelFlatGpu {
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::color::Color;
+    use crate::material::material_bank::MaterialId;
+    use crate::voxel::attachment::PTMaterial;
+
+    fn test_model() -> VoxelModelFlat {
+        let mut model = VoxelModelFlat::new_empty(Vector3::new(3, 4, 5));
+        let material = PTMaterial::diffuse(Color::new_srgb(0.8, 0.1, 0.3)).encode();
+        let normal = Attachment::encode_normal(&Vector3::new(0.0, 1.0, 0.0));
+        for position in [
+            Vector3::new(0, 0, 0),
+            Vector3::new(2, 1, 3),
+            Vector3::new(1, 3, 4),
+        ] {
+            let mut voxel = model.get_voxel_mut(position);
+            voxel.set_attachment(Attachment::PTMATERIAL, Some(material));
+            voxel.set_attachment(Attachment::NORMAL, Some(normal));
+        }
+        model
+    }
+
+    fn voxel_data(model: &VoxelModelFlat, position: Vector3<u32>) -> Vec<(u8, Vec<u32>)> {
+        model
+            .get_voxel(position)
+            .get_attachment_data()
+            .map(|(id, data)| (id, data.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn mirroring_twice_is_identity() {
+        let model = test_model();
+        for axis in Axis::ALL {
+            let twice = model.mirrored(axis).mirrored(axis);
+            for index in 0..model.volume() {
+                let position = model.get_voxel_position(index);
+                assert_eq!(
+                    model.get_voxel(position).exists(),
+                    twice.get_voxel(position).exists()
+                );
+                assert_eq!(voxel_data(&model, position), voxel_data(&twice, position));
+            }
+        }
+    }
+
+    #[test]
+    fn mirroring_negates_one_normal_component() {
+        let model = test_model();
+        let position = Vector3::new(2, 1, 3);
+        let normal_of = |model: &VoxelModelFlat, position: Vector3<u32>| {
+            let (_, data) = voxel_data(model, position)
+                .into_iter()
+                .find(|(id, _)| *id == Attachment::NORMAL_ID)
+                .unwrap();
+            Attachment::decode_normal(data[0])
+        };
+        let material_of = |model: &VoxelModelFlat, position: Vector3<u32>| {
+            voxel_data(model, position)
+                .into_iter()
+                .find(|(id, _)| *id == Attachment::PTMATERIAL_ID)
+                .unwrap()
+        };
+
+        for axis in Axis::ALL {
+            let mirrored = model.mirrored(axis);
+            let mut mirrored_position = position;
+            mirrored_position[axis.index()] =
+                model.side_length()[axis.index()] - 1 - position[axis.index()];
+            assert_eq!(
+                material_of(&model, position),
+                material_of(&mirrored, mirrored_position)
+            );
+
+            let normal = normal_of(&model, position);
+            let mirrored_normal = normal_of(&mirrored, mirrored_position);
+            for i in 0..3 {
+                if i == axis.index() {
+                    assert!((mirrored_normal[i] + normal[i]).abs() < 1e-5);
+                } else {
+                    assert_eq!(mirrored_normal[i], normal[i]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn slab_normals_face_out_of_the_surface() {
+        let side_length = Vector3::new(6, 3, 6);
+        let mut model = VoxelModelFlat::new_empty(side_length);
+        let material = PTMaterial::diffuse(Color::new_srgb(0.5, 0.5, 0.5)).encode();
+        for index in 0..model.volume() {
+            let position = model.get_voxel_position(index);
+            model
+                .get_voxel_mut(position)
+                .set_attachment(Attachment::PTMATERIAL, Some(material));
+        }
+        model.compute_normals();
+
+        let normal_of = |position: Vector3<u32>| {
+            voxel_data(&model, position)
+                .into_iter()
+                .find(|(id, _)| *id == Attachment::NORMAL_ID)
+                .map(|(_, data)| Attachment::decode_normal(data[0]))
+        };
+        for x in 1..5 {
+            for z in 1..5 {
+                let top = normal_of(Vector3::new(x, 2, z)).unwrap();
+                assert!((top - Vector3::y()).norm() < 1e-2, "{:?}", top);
+                let bottom = normal_of(Vector3::new(x, 0, z)).unwrap();
+                assert!((bottom + Vector3::y()).norm() < 1e-2, "{:?}", bottom);
+                assert!(normal_of(Vector3::new(x, 1, z)).is_none());
+            }
+        }
+        // Edges lean out of both exposed faces.
+        let edge = normal_of(Vector3::new(0, 2, 3)).unwrap();
+        assert!(edge.x < -0.5 && edge.y > 0.5, "{:?}", edge);
+    }
+
+    #[test]
+    fn inner_corner_ao_is_darker_than_an_open_face() {
+        // A floor with a wall along x = 0, both one voxel thick.
+        let side_length = Vector3::new(6, 4, 6);
+        let mut model = VoxelModelFlat::new_empty(side_length);
+        let material = PTMaterial::diffuse(Color::new_srgb(0.5, 0.5, 0.5)).encode();
+        for index in 0..model.volume() {
+            let position = model.get_voxel_position(index);
+            if position.y == 0 || position.x == 0 {
+                model
+                    .get_voxel_mut(position)
+                    .set_attachment(Attachment::PTMATERIAL, Some(material));
+            }
+        }
+        model.compute_ao();
+
+        let ao_of = |position: Vector3<u32>| {
+            voxel_data(&model, position)
+                .into_iter()
+                .find(|(id, _)| *id == Attachment::AO_ID)
+                .map(|(_, data)| Attachment::decode_ao(data[0]))
+        };
+        // Away from the wall and edges, only the floor's top and bottom faces are exposed.
+        let open = ao_of(Vector3::new(3, 0, 3)).unwrap();
+        let corner = ao_of(Vector3::new(1, 0, 3)).unwrap();
+        assert!(open > 0.99, "{}", open);
+        assert!(corner < open, "{} >= {}", corner, open);
+        // The wall voxel at the corner is darkened by the floor in front of it.
+        let wall = ao_of(Vector3::new(0, 1, 3)).unwrap();
+        assert!(wall < ao_of(Vector3::new(0, 3, 3)).unwrap());
+    }
+
+    #[test]
+    fn resize_keeps_voxels_in_bounds() {
+        let model = test_model();
+        let resized = model.resized(Vector3::new(4, 4, 4), Vector3::new(1, 0, 0));
+        assert_eq!(*resized.side_length(), Vector3::new(4, 4, 4));
+        assert_eq!(
+            voxel_data(&resized, Vector3::new(1, 0, 0)),
+            voxel_data(&model, Vector3::new(0, 0, 0))
+        );
+        assert_eq!(
+            voxel_data(&resized, Vector3::new(3, 1, 3)),
+            voxel_data(&model, Vector3::new(2, 1, 3))
+        );
+        // (1, 3, 4) moves out of the shorter z axis.
+        let present = (0..resized.volume())
+            .filter(|i| resized.get_voxel(resized.get_voxel_position(*i)).exists())
+            .count();
+        assert_eq!(present, 2);
+    }
+
+    #[test]
+    fn crop_shrinks_to_content() {
+        let mut model = VoxelModelFlat::new_empty(Vector3::new(8, 8, 8));
+        let material = PTMaterial::diffuse(Color::new_srgb(0.5, 0.5, 0.5)).encode();
+        for index in 0..model.volume() {
+            let position = model.get_voxel_position(index);
+            if position.iter().all(|x| (2..6).contains(x)) {
+                model
+                    .get_voxel_mut(position)
+                    .set_attachment(Attachment::PTMATERIAL, Some(material));
+            }
+        }
+
+        let (cropped, offset) = model.cropped_to_content().unwrap();
+        assert_eq!(*cropped.side_length(), Vector3::new(4, 4, 4));
+        assert_eq!(offset, Vector3::new(-2, -2, -2));
+        assert!((0..cropped.volume()).all(|i| cropped.presence_data.get_bit(i)));
+        assert_eq!(
+            voxel_data(&cropped, Vector3::zeros()),
+            voxel_data(&model, Vector3::new(2, 2, 2))
+        );
+        assert!(
+            VoxelModelFlat::new_empty(Vector3::new(2, 2, 2))
+                .cropped_to_content()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn boolean_ops_combine_presence_and_attachments() {
+        let row = |r: f32| {
+            let mut model = VoxelModelFlat::new_empty(Vector3::new(2, 1, 1));
+            let material = PTMaterial::diffuse(Color::new_srgb(r, 0.0, 0.0)).encode();
+            for x in 0..2 {
+                model
+                    .get_voxel_mut(Vector3::new(x, 0, 0))
+                    .set_attachment(Attachment::PTMATERIAL, Some(material));
+            }
+            (model, material)
+        };
+        let (a, material_a) = row(1.0);
+        let (b, material_b) = row(0.5);
+        let materials = |model: &VoxelModelFlat| {
+            (0..model.volume())
+                .map(|index| {
+                    voxel_data(model, model.get_voxel_position(index))
+                        .into_iter()
+                        .find(|(id, _)| *id == Attachment::PTMATERIAL_ID)
+                        .map(|(_, data)| data[0])
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let union = a.boolean(&b, VoxelModelBooleanOp::Union, Vector3::new(1, 0, 0));
+        assert_eq!(*union.side_length(), Vector3::new(3, 1, 1));
+        assert_eq!(
+            materials(&union),
+            vec![Some(material_a), Some(material_a), Some(material_b)]
+        );
+        // The union's origin moves to fit `other` before `self`.
+        let union = a.boolean(&b, VoxelModelBooleanOp::Union, Vector3::new(-1, 0, 0));
+        assert_eq!(
+            materials(&union),
+            vec![Some(material_b), Some(material_a), Some(material_a)]
+        );
+
+        let intersect = a.boolean(&b, VoxelModelBooleanOp::Intersect, Vector3::new(1, 0, 0));
+        assert_eq!(*intersect.side_length(), Vector3::new(2, 1, 1));
+        assert_eq!(materials(&intersect), vec![None, Some(material_a)]);
+        assert!(!intersect.get_voxel(Vector3::new(0, 0, 0)).exists());
+
+        let subtract = a.boolean(&b, VoxelModelBooleanOp::Subtract, Vector3::new(1, 0, 0));
+        assert_eq!(materials(&subtract), vec![Some(material_a), None]);
+        assert!(!subtract.get_voxel(Vector3::new(1, 0, 0)).exists());
+        // Disjoint models subtract nothing and intersect to nothing.
+        let far = Vector3::new(5, 0, 0);
+        let subtract = a.boolean(&b, VoxelModelBooleanOp::Subtract, far);
+        assert_eq!(materials(&subtract), vec![Some(material_a); 2]);
+        assert!(
+            a.boolean(&b, VoxelModelBooleanOp::Intersect, far)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn boolean_union_remaps_other_materials() {
+        let model = |material_ids: &[MaterialId]| {
+            let mut model = VoxelModelFlat::new_empty(Vector3::new(1, 1, 1));
+            for material_id in material_ids {
+                model.material_map.push(*material_id);
+            }
+            let last_material = VoxelMaterialData::Unbaked(*material_ids.last().unwrap());
+            let encoded = last_material.encode(&model.material_map);
+            model
+                .get_voxel_mut(Vector3::zeros())
+                .set_attachment(Attachment::BMAT, Some(encoded));
+            model
+        };
+        // Both models use model material id 1 for different materials and share material 7.
+        let a = model(&[7, 3]);
+        let b = model(&[7, 5]);
+
+        let union = a.boolean(&b, VoxelModelBooleanOp::Union, Vector3::new(1, 0, 0));
+        let material = |x: u32| {
+            let data = voxel_data(&union, Vector3::new(x, 0, 0))
+                .into_iter()
+                .find(|(id, _)| *id == Attachment::BMAT_ID)
+                .unwrap()
+                .1;
+            let encoded = ((data[1] as u64) << 32) | data[0] as u64;
+            match VoxelMaterialData::decode(encoded, &union.material_map) {
+                VoxelMaterialData::Unbaked(material_id) => material_id,
+                VoxelMaterialData::Baked { .. } => panic!("Material should be unbaked."),
+            }
+        };
+        assert_eq!(material(0), 3);
+        assert_eq!(material(1), 5);
+        // The shared material isn't added twice.
+        assert_eq!(union.material_map.model_materials.len(), 3);
+    }
+}
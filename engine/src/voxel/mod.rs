@@ -6,6 +6,7 @@ pub mod attachment;
 pub mod baker_gpu;
 pub mod factory;
 pub mod flat;
+pub mod rflt_asset;
 pub mod rvox_asset;
 //pub mod sft;
 pub mod sft_compressed;
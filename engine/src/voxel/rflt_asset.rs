@@ -0,0 +1,269 @@
+use crate::{
+    asset::{
+        asset::{AssetLoader, AssetSaver},
+        util::{AssetByteReader, AssetByteWriter},
+    },
+    common::bitset::Bitset,
+    material::{
+        material_bank::MaterialId,
+        model_material_map::{ModelMaterial, ModelMaterialMap},
+    },
+    voxel::{
+        attachment::{Attachment, AttachmentId, AttachmentMap},
+        flat::VoxelModelFlat,
+    },
+};
+use nalgebra::Vector3;
+
+/// A `VoxelModelFlat` stored with its presence run-length encoded and attachment data only for
+/// the voxels which have the attachment, so mostly empty models stay small.
+pub struct RFLTAsset {
+    pub flat: VoxelModelFlat,
+}
+
+impl RFLTAsset {
+    const HEADER: &'static str = "RFLT";
+    const VERSION: u32 = 1;
+
+    pub fn write_to_file(&self, file: std::fs::File) -> anyhow::Result<()> {
+        let mut writer = AssetByteWriter::new(file, Self::HEADER, Self::VERSION);
+        self.write(&mut writer);
+        writer.finish_writes()
+    }
+
+    fn write(&self, writer: &mut AssetByteWriter) {
+        let flat = &self.flat;
+        writer.write_u32(flat.side_length.x);
+        writer.write_u32(flat.side_length.y);
+        writer.write_u32(flat.side_length.z);
+
+        writer.write_u32(flat.attachment_map.count() as u32);
+        for (attachment_id, attachment) in flat.attachment_map.iter() {
+            writer.write_u32(attachment_id as u32);
+            writer.write_u32(attachment.size());
+        }
+
+        write_runs(writer, &flat.presence_data);
+        for (attachment_id, attachment) in flat.attachment_map.iter() {
+            let presence = flat.attachment_presence_data.get(attachment_id).unwrap();
+            let data = flat.attachment_data.get(attachment_id).unwrap();
+            write_runs(writer, presence);
+            let size = attachment.size() as usize;
+            for index in (0..flat.volume).filter(|index| presence.get_bit(*index)) {
+                writer.write_slice(&data[(index * size)..((index + 1) * size)]);
+            }
+        }
+
+        let model_material_data = flat
+            .material_map
+            .model_materials
+            .iter()
+            .map(|m| m.material_id)
+            .collect::<Vec<_>>();
+        writer.write_u32(model_material_data.len() as u32);
+        writer.write_slice(&model_material_data);
+    }
+
+    pub fn read_from_file(file: std::fs::File) -> anyhow::Result<Self> {
+        let mut reader = AssetByteReader::new(file, Self::HEADER)?;
+        match reader.version() {
+            1 => load_v1(&mut reader),
+            version => anyhow::bail!("Unknown version {}", version),
+        }
+    }
+}
+
+impl AssetLoader for RFLTAsset {
+    fn load(
+        file: &crate::asset::asset::AssetFile,
+    ) -> std::result::Result<Self, crate::asset::asset::AssetLoadError>
+    where
+        Self: Sized + std::any::Any,
+    {
+        Self::read_from_file(file.read_file()?)
+            .map_err(|e| anyhow::anyhow!("Failed to load RFLT asset: {:?}", e).into())
+    }
+}
+
+impl AssetSaver for RFLTAsset {
+    fn save(data: &Self, out_file: &crate::asset::asset::AssetFile) -> anyhow::Result<()>
+    where
+        Self: Sized,
+    {
+        data.write_to_file(out_file.write_file())
+            .map_err(|e| anyhow::anyhow!("Failed to write bytes into RFLT file: {:?}", e))
+    }
+}
+
+/// Writes the lengths of the alternating runs of unset and set bits, starting with unset bits.
+fn write_runs(writer: &mut AssetByteWriter, bitset: &Bitset) {
+    let mut runs = Vec::new();
+    let mut run_value = false;
+    let mut run_length = 0u32;
+    for bit in 0..bitset.bits() {
+        if bitset.get_bit(bit) != run_value {
+            runs.push(run_length);
+            run_value = !run_value;
+            run_length = 0;
+        }
+        run_length += 1;
+    }
+    runs.push(run_length);
+
+    writer.write_u32(runs.len() as u32);
+    writer.write_slice(&runs);
+}
+
+fn read_runs(reader: &mut AssetByteReader, bits: usize) -> anyhow::Result<Bitset> {
+    let mut runs = vec![0u32; reader.read_u32()? as usize];
+    reader.read_to_slice(&mut runs)?;
+
+    let mut bitset = Bitset::new(bits);
+    let mut bit = 0;
+    for (i, run_length) in runs.into_iter().enumerate() {
+        let run_end = bit + run_length as usize;
+        if run_end > bits {
+            anyhow::bail!("Bit runs exceed the model volume of {}.", bits);
+        }
+        // Odd runs are the set bits.
+        if i % 2 == 1 {
+            for set_bit in bit..run_end {
+                bitset.set_bit(set_bit, true);
+            }
+        }
+        bit = run_end;
+    }
+    if bit != bits {
+        anyhow::bail!(
+            "Bit runs cover {} bits but the model volume is {}.",
+            bit,
+            bits
+        );
+    }
+
+    Ok(bitset)
+}
+
+fn load_v1(reader: &mut AssetByteReader) -> anyhow::Result<RFLTAsset> {
+    let side_length = Vector3::new(reader.read_u32()?, reader.read_u32()?, reader.read_u32()?);
+    let volume = side_length.cast::<usize>().product();
+
+    let attachment_count = reader.read_u32()?;
+    let mut attachment_map = AttachmentMap::new();
+    for _ in 0..attachment_count {
+        let attachment_id = reader.read_u32()?;
+        if attachment_id > Attachment::MAX_ATTACHMENT_ID as u32 {
+            anyhow::bail!("Unknown attachment id {}", attachment_id);
+        }
+        let attachment = Attachment::from_id(attachment_id as AttachmentId);
+        let size = reader.read_u32()?;
+        if size != attachment.size() {
+            anyhow::bail!(
+                "Attachment {} has size {} but expected {}.",
+                attachment.name(),
+                size,
+                attachment.size()
+            );
+        }
+        attachment_map.register_attachment(attachment);
+    }
+
+    let presence_data = read_runs(reader, volume)?;
+    let mut attachment_presence_data = AttachmentMap::new();
+    let mut attachment_data = AttachmentMap::new();
+    for (attachment_id, attachment) in attachment_map.iter() {
+        let presence = read_runs(reader, volume)?;
+        let size = attachment.size() as usize;
+        let mut data = vec![0u32; size * volume];
+        for index in (0..volume).filter(|index| presence.get_bit(*index)) {
+            reader.read_to_slice(&mut data[(index * size)..((index + 1) * size)])?;
+        }
+        attachment_presence_data.insert(attachment_id, presence);
+        attachment_data.insert(attachment_id, data);
+    }
+
+    let model_material_data_len = reader.read_u32()? as usize;
+    let mut model_material_data = vec![0 as MaterialId; model_material_data_len];
+    reader.read_to_slice(&mut model_material_data)?;
+
+    let mut flat = VoxelModelFlat::new(
+        presence_data,
+        attachment_data,
+        attachment_presence_data,
+        attachment_map.clone(),
+        side_length,
+    );
+    flat.attachment_map = attachment_map;
+    flat.material_map = ModelMaterialMap {
+        model_materials: model_material_data
+            .into_iter()
+            .enumerate()
+            .map(|(i, material_id)| ModelMaterial {
+                material_id,
+                model_material_id: i as u32,
+            })
+            .collect(),
+    };
+
+    Ok(RFLTAsset { flat })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sparse_model_round_trip() {
+        let mut flat = VoxelModelFlat::new_empty(Vector3::new(64, 64, 64));
+        let mut present_count = 0;
+        for index in 0..flat.volume() {
+            // Roughly 1% fill scattered through the volume.
+            let hash = (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+            if hash % 100 != 0 {
+                continue;
+            }
+            let mut voxel = flat.get_voxel_mut(flat.get_voxel_position(index));
+            voxel.set_attachment(Attachment::PTMATERIAL, Some(index as u32));
+            if present_count % 2 == 0 {
+                voxel.set_attachment(Attachment::NORMAL, Some(hash as u32));
+            }
+            present_count += 1;
+        }
+        assert!(present_count > 0);
+
+        let path =
+            std::env::temp_dir().join(format!("rogue_rflt_round_trip_{}.rflt", std::process::id()));
+        RFLTAsset { flat: flat.clone() }
+            .write_to_file(std::fs::File::create(&path).unwrap())
+            .unwrap();
+        let file_size = std::fs::metadata(&path).unwrap().len();
+        let loaded = RFLTAsset::read_from_file(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .flat;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.side_length(), flat.side_length());
+        for index in 0..flat.volume() {
+            let position = flat.get_voxel_position(index);
+            assert_eq!(
+                loaded.get_voxel(position).exists(),
+                flat.get_voxel(position).exists()
+            );
+            let voxel_data = |model: &VoxelModelFlat| {
+                model
+                    .get_voxel(position)
+                    .get_attachment_data()
+                    .map(|(id, data)| (id, data.to_vec()))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(voxel_data(&loaded), voxel_data(&flat));
+        }
+
+        // Dense storage is the presence bits plus every attachment's data for every voxel.
+        let dense_size = (flat.volume() / 8
+            + flat.volume()
+                * (Attachment::PTMATERIAL.byte_size() + Attachment::NORMAL.byte_size()) as usize)
+            as u64;
+        assert!(file_size * 10 < dense_size);
+    }
+}
@@ -1,17 +1,19 @@
-use std::{collections::HashMap, io::Read};
-
-use zune_jpeg::zune_core::bytestream::ZByteWriterTrait;
+use std::collections::HashMap;
 
 use crate::{
-    asset::asset::{AssetLoader, AssetSaver},
+    asset::{
+        asset::{AssetLoader, AssetSaver},
+        util::{AssetByteReader, AssetByteWriter},
+    },
     material::{
         material_bank::MaterialId,
         model_material_map::{ModelMaterial, ModelMaterialMap},
     },
     voxel::{
-        attachment::{Attachment, AttachmentMap},
+        attachment::{Attachment, AttachmentId, AttachmentMap},
         sft_compressed::{
-            SFTAttachmentLookupNodeCompressed, SFTNodeCompressed, VoxelModelSFTCompressed,
+            SFTAttachmentLookupNodeCompressed, SFTDirtyLog, SFTNodeCompressed,
+            VoxelModelSFTCompressed,
         },
     },
 };
@@ -20,7 +22,66 @@ pub struct RVOXAsset {
     pub sft_compressed: VoxelModelSFTCompressed,
 }
 impl RVOXAsset {
-    const VERSION: u32 = 1;
+    const HEADER: &'static str = "RVOX";
+    /// Version 2 stores every attachment with its id, version 1 only stored the builtin
+    /// material attachment.
+    const VERSION: u32 = 2;
+
+    pub fn write_to_file(&self, file: std::fs::File) -> anyhow::Result<()> {
+        let sft = &self.sft_compressed;
+        let mut writer = AssetByteWriter::new(file, Self::HEADER, Self::VERSION);
+        writer.write_u32(sft.side_length);
+
+        writer.write_u32(sft.node_data.len() as u32);
+        for node in &sft.node_data {
+            writer.write_u32(node.child_ptr);
+            writer.write(&node.child_mask);
+            writer.write(&node.leaf_mask);
+        }
+
+        writer.write_u32(sft.attachment_map.count() as u32);
+        for (attachment_id, _) in sft.attachment_map.iter() {
+            writer.write_u32(attachment_id as u32);
+
+            // Lookup nodes may be shorter than the node data.
+            let lookup_nodes = sft
+                .attachment_lookup_data
+                .get(attachment_id)
+                .map_or(&[][..], |nodes| nodes.as_slice());
+            writer.write_u32(lookup_nodes.len() as u32);
+            for node in lookup_nodes {
+                writer.write_u32(node.data_ptr);
+                writer.write(&node.attachment_mask);
+            }
+
+            let raw_data = sft
+                .attachment_raw_data
+                .get(attachment_id)
+                .map_or(&[][..], |data| data.as_slice());
+            writer.write_u32(raw_data.len() as u32);
+            writer.write_slice(raw_data);
+        }
+
+        let model_material_data = sft
+            .material_map
+            .model_materials
+            .iter()
+            .map(|m| m.material_id)
+            .collect::<Vec<_>>();
+        writer.write_u32(model_material_data.len() as u32);
+        writer.write_slice(&model_material_data);
+
+        writer.finish_writes()
+    }
+
+    pub fn read_from_file(file: std::fs::File) -> anyhow::Result<Self> {
+        let mut reader = AssetByteReader::new(file, Self::HEADER)?;
+        match reader.version() {
+            1 => load_v1(&reader.read_remaining()?),
+            2 => load_v2(&mut reader),
+            version => anyhow::bail!("Unknown version {}", version),
+        }
+    }
 }
 
 impl AssetLoader for RVOXAsset {
@@ -30,21 +91,7 @@ impl AssetLoader for RVOXAsset {
     where
         Self: Sized + std::any::Any,
     {
-        let mut file = file.read_file()?;
-        let mut buf = Vec::new();
-        file.read_to_end(&mut buf);
-
-        if buf[0..4] != [b'R', b'V', b'O', b'X'] {
-            return Err(anyhow::anyhow!("Invalid RVOX header").into());
-        }
-
-        let mut cursor = 4;
-        let version = u32::from_le_bytes(buf[cursor..(cursor + 4)].try_into().unwrap());
-        if version != Self::VERSION {
-            return Err(anyhow::anyhow!("Unknown version {}", version).into());
-        }
-        cursor += 4;
-        load_v1(&buf[cursor..])
+        Self::read_from_file(file.read_file()?)
             .map_err(|e| anyhow::anyhow!("Failed to load RVOX asset: {:?}", e).into())
     }
 }
@@ -54,56 +101,78 @@ impl AssetSaver for RVOXAsset {
     where
         Self: Sized,
     {
-        let sft = &data.sft_compressed;
-        let mut bytes = vec![b'R', b'V', b'O', b'X'];
-        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
-        // Write side length.
-        bytes.extend_from_slice(&sft.side_length.to_le_bytes());
-
-        // Node data chunk.
-        // Write node data length.
-        bytes.extend_from_slice(&(sft.node_data.len() as u32).to_le_bytes());
-        for node in &sft.node_data {
-            bytes.extend_from_slice(&node.child_ptr.to_le_bytes());
-            bytes.extend_from_slice(&node.child_mask.to_le_bytes());
-            bytes.extend_from_slice(&node.leaf_mask.to_le_bytes());
-        }
+        data.write_to_file(out_file.write_file())
+            .map_err(|e| anyhow::anyhow!("Failed to write bytes into RVOX file: {:?}", e))
+    }
+}
 
-        let attachment_nodes = sft
-            .attachment_lookup_data
-            .get(Attachment::BMAT_ID)
-            .expect("all other attachments are legacy now but idk maybe its different later.");
-        // This might be shorder than node length.
-        bytes.extend_from_slice(&(attachment_nodes.len() as u32).to_le_bytes());
-        for node in attachment_nodes {
-            bytes.extend_from_slice(&node.data_ptr.to_le_bytes());
-            bytes.extend_from_slice(&node.attachment_mask.to_le_bytes());
-        }
+fn load_v2(reader: &mut AssetByteReader) -> anyhow::Result<RVOXAsset> {
+    let side_length = reader.read_u32()?;
 
-        let attachment_data = sft
-            .attachment_raw_data
-            .get(Attachment::BMAT_ID)
-            .expect("all other attachments are legacy now but idk maybe its different later.");
-        bytes.extend_from_slice(
-            &(attachment_data.len() as u32 / Attachment::BMAT.size()).to_le_bytes(),
-        );
-        bytes.extend_from_slice(bytemuck::cast_slice(attachment_data.as_slice()));
+    let node_data_len = reader.read_u32()? as usize;
+    let mut node_data = Vec::with_capacity(node_data_len);
+    for _ in 0..node_data_len {
+        node_data.push(SFTNodeCompressed {
+            child_ptr: reader.read_u32()?,
+            child_mask: reader.read::<u64>()?,
+            leaf_mask: reader.read::<u64>()?,
+        });
+    }
 
-        let model_material_data = sft
-            .material_map
-            .model_materials
-            .iter()
-            .map(|m| m.material_id)
-            .collect::<Vec<_>>();
-        bytes.extend_from_slice(&(model_material_data.len() as u32).to_le_bytes());
-        bytes.extend_from_slice(bytemuck::cast_slice(model_material_data.as_slice()));
+    let mut attachment_map = AttachmentMap::new();
+    let mut attachment_lookup_data = AttachmentMap::new();
+    let mut attachment_raw_data = AttachmentMap::new();
+    let attachment_count = reader.read_u32()?;
+    for _ in 0..attachment_count {
+        let attachment_id = reader.read_u32()?;
+        if attachment_id > Attachment::MAX_ATTACHMENT_ID as u32 {
+            anyhow::bail!("Unknown attachment id {}", attachment_id);
+        }
+        let attachment_id = attachment_id as AttachmentId;
+        attachment_map.register_attachment(Attachment::from_id(attachment_id));
 
-        let mut file = out_file.write_file();
-        file.write_all_bytes(&bytes)
-            .map_err(|e| anyhow::anyhow!("Failed to write bytes into RVOX file: {:?}", e))?;
+        let lookup_nodes_len = reader.read_u32()? as usize;
+        let mut lookup_nodes = Vec::with_capacity(lookup_nodes_len);
+        for _ in 0..lookup_nodes_len {
+            lookup_nodes.push(SFTAttachmentLookupNodeCompressed {
+                data_ptr: reader.read_u32()?,
+                attachment_mask: reader.read::<u64>()?,
+            });
+        }
+        attachment_lookup_data.insert(attachment_id, lookup_nodes);
 
-        Ok(())
+        let raw_data_len = reader.read_u32()? as usize;
+        let mut raw_data = vec![0u32; raw_data_len];
+        reader.read_to_slice(&mut raw_data)?;
+        attachment_raw_data.insert(attachment_id, raw_data);
     }
+
+    let model_material_data_len = reader.read_u32()? as usize;
+    let mut model_material_data = vec![0 as MaterialId; model_material_data_len];
+    reader.read_to_slice(&mut model_material_data)?;
+    let material_map = ModelMaterialMap {
+        model_materials: model_material_data
+            .into_iter()
+            .enumerate()
+            .map(|(i, material_id)| ModelMaterial {
+                material_id,
+                model_material_id: i as u32,
+            })
+            .collect(),
+    };
+
+    Ok(RVOXAsset {
+        sft_compressed: VoxelModelSFTCompressed {
+            side_length,
+            attachment_map,
+            material_map,
+            node_data,
+            attachment_lookup_data,
+            attachment_raw_data,
+            update_tracker: 0,
+            dirty_log: SFTDirtyLog::default(),
+        },
+    })
 }
 
 fn load_v1(buf: &[u8]) -> anyhow::Result<RVOXAsset> {
@@ -186,9 +255,81 @@ fn load_v1(buf: &[u8]) -> anyhow::Result<RVOXAsset> {
         attachment_lookup_data,
         attachment_raw_data,
         update_tracker: 0,
+        dirty_log: SFTDirtyLog::default(),
     };
 
     Ok(RVOXAsset {
         sft_compressed: sft,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_load_round_trip() {
+        let mut sft = VoxelModelSFTCompressed::new_empty(16);
+        sft.node_data = vec![
+            SFTNodeCompressed {
+                child_ptr: 1,
+                child_mask: 0b1011,
+                leaf_mask: 0,
+            },
+            SFTNodeCompressed {
+                child_ptr: 0x8000_0002,
+                child_mask: u64::MAX,
+                leaf_mask: 0xF0F0_F0F0_F0F0_F0F0,
+            },
+        ];
+        sft.attachment_map.register_attachment(Attachment::BMAT);
+        sft.attachment_map.register_attachment(Attachment::NORMAL);
+        sft.attachment_lookup_data.insert(
+            Attachment::BMAT_ID,
+            vec![SFTAttachmentLookupNodeCompressed {
+                data_ptr: 0,
+                attachment_mask: 0b11,
+            }],
+        );
+        sft.attachment_raw_data
+            .insert(Attachment::BMAT_ID, vec![1, 2, 3, 4]);
+        sft.attachment_lookup_data
+            .insert(Attachment::NORMAL_ID, Vec::new());
+        sft.attachment_raw_data
+            .insert(Attachment::NORMAL_ID, vec![7]);
+
+        let path =
+            std::env::temp_dir().join(format!("rogue_rvox_round_trip_{}.rvox", std::process::id()));
+        RVOXAsset {
+            sft_compressed: sft.clone(),
+        }
+        .write_to_file(std::fs::File::create(&path).unwrap())
+        .unwrap();
+        let loaded = RVOXAsset::read_from_file(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .sft_compressed;
+        std::fs::remove_file(&path).unwrap();
+
+        let nodes = |sft: &VoxelModelSFTCompressed| {
+            sft.node_data
+                .iter()
+                .map(|node| (node.child_ptr, node.child_mask, node.leaf_mask))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(loaded.side_length, sft.side_length);
+        assert_eq!(nodes(&loaded), nodes(&sft));
+        assert!(loaded.attachment_raw_data == sft.attachment_raw_data);
+        for attachment_id in [Attachment::BMAT_ID, Attachment::NORMAL_ID] {
+            assert!(loaded.attachment_map.contains(attachment_id));
+            let lookup = |sft: &VoxelModelSFTCompressed| {
+                sft.attachment_lookup_data
+                    .get(attachment_id)
+                    .unwrap()
+                    .iter()
+                    .map(|node| (node.data_ptr, node.attachment_mask))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(lookup(&loaded), lookup(&sft));
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use std::{collections::HashMap, ops::Deref};
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::{Deref, Range},
+};
 
 use nalgebra::Vector3;
 
@@ -78,6 +81,109 @@ impl SFTAttachmentLookupNodeCompressed {
     }
 }
 
+/// A range of model data written by an edit. Node ranges are in nodes and also cover the
+/// attachment lookups of those nodes, raw attachment ranges are in u32s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SFTDirtyRange {
+    Nodes(Range<usize>),
+    AttachmentRaw(AttachmentId, Range<usize>),
+}
+
+/// The merged ranges of model data to upload for a partial gpu update.
+#[derive(Default, Debug)]
+pub struct SFTDirtyRanges {
+    pub nodes: Vec<Range<usize>>,
+    pub attachment_raw: HashMap<AttachmentId, Vec<Range<usize>>>,
+}
+
+/// The ranges written by recent edits, tagged with the model's update tracker at the time so the
+/// gpu model can upload only what changed since it last uploaded.
+#[derive(Clone, Default)]
+pub struct SFTDirtyLog {
+    /// The oldest update tracker whose changes are all still in the log.
+    base_tracker: u32,
+    entries: VecDeque<(u32, SFTDirtyRange)>,
+}
+
+impl SFTDirtyLog {
+    /// Past this the oldest entries are dropped so the log doesn't grow unbounded while the gpu
+    /// model isn't uploading, any gpu model older than them is then rewritten in full.
+    const MAX_ENTRIES: usize = 4096;
+
+    pub fn new(base_tracker: u32) -> Self {
+        Self {
+            base_tracker,
+            entries: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, update_tracker: u32, range: SFTDirtyRange) {
+        let (SFTDirtyRange::Nodes(r) | SFTDirtyRange::AttachmentRaw(_, r)) = &range;
+        if r.is_empty() {
+            return;
+        }
+        self.entries.push_back((update_tracker, range));
+        while self.entries.len() > Self::MAX_ENTRIES {
+            let (oldest_tracker, _) = self.entries.pop_front().unwrap();
+            while self
+                .entries
+                .front()
+                .is_some_and(|(tracker, _)| *tracker == oldest_tracker)
+            {
+                self.entries.pop_front();
+            }
+            self.base_tracker = oldest_tracker + 1;
+        }
+    }
+
+    /// The ranges written at or after `update_tracker`, merging ranges at most `merge_distance`
+    /// elements apart. Returns `None` if the log doesn't reach back that far.
+    pub fn ranges_since(
+        &self,
+        update_tracker: u32,
+        merge_distance: usize,
+    ) -> Option<SFTDirtyRanges> {
+        if update_tracker < self.base_tracker {
+            return None;
+        }
+
+        let mut ranges = SFTDirtyRanges::default();
+        for (tracker, range) in &self.entries {
+            if *tracker < update_tracker {
+                continue;
+            }
+            match range {
+                SFTDirtyRange::Nodes(r) => ranges.nodes.push(r.clone()),
+                SFTDirtyRange::AttachmentRaw(attachment_id, r) => ranges
+                    .attachment_raw
+                    .entry(*attachment_id)
+                    .or_default()
+                    .push(r.clone()),
+            }
+        }
+        merge_ranges(&mut ranges.nodes, merge_distance);
+        for attachment_ranges in ranges.attachment_raw.values_mut() {
+            merge_ranges(attachment_ranges, merge_distance);
+        }
+        Some(ranges)
+    }
+}
+
+/// Sorts `ranges` and merges any overlapping or at most `merge_distance` elements apart.
+fn merge_ranges(ranges: &mut Vec<Range<usize>>, merge_distance: usize) {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges.drain(..) {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end + merge_distance => {
+                last.end = last.end.max(range.end);
+            }
+            _ => merged.push(range),
+        }
+    }
+    *ranges = merged;
+}
+
 #[derive(Clone)]
 pub struct VoxelModelSFTCompressed {
     pub side_length: u32,
@@ -88,6 +194,8 @@ pub struct VoxelModelSFTCompressed {
     pub attachment_lookup_data: AttachmentMap<Vec<SFTAttachmentLookupNodeCompressed>>,
     pub attachment_raw_data: AttachmentMap<Vec<u32>>,
     pub update_tracker: u32,
+    /// What `set_voxel` wrote, so edits upload only the ranges they changed.
+    pub dirty_log: SFTDirtyLog,
 }
 
 impl VoxelModelSFTCompressed {
@@ -106,9 +214,14 @@ impl VoxelModelSFTCompressed {
             attachment_map: AttachmentMap::new(),
             material_map: ModelMaterialMap::new(),
             update_tracker: 0,
+            dirty_log: SFTDirtyLog::default(),
         }
     }
 
+    fn mark_dirty(&mut self, range: SFTDirtyRange) {
+        self.dirty_log.push(self.update_tracker, range);
+    }
+
     pub fn is_empty(&self) -> bool {
         self.node_data[0].child_mask == 0
     }
@@ -156,6 +269,7 @@ impl VoxelModelSFTCompressed {
                         & ((1 << curr_child_index) - 1))
                         .count_ones();
                     let new_child_ptr = self.node_data.len();
+                    self.mark_dirty(SFTDirtyRange::Nodes(curr_node_index..curr_node_index + 1));
                     for i in 0..child_offset {
                         let n = self.node_data[(child_ptr + i) as usize];
                         self.node_data.push(n);
@@ -183,6 +297,7 @@ impl VoxelModelSFTCompressed {
                         self.node_data.len() - new_child_ptr
                     );
                     self.node_data[curr_node_index].child_ptr = new_child_ptr as u32;
+                    self.mark_dirty(SFTDirtyRange::Nodes(new_child_ptr..self.node_data.len()));
                     curr_node_index = new_child_ptr + child_offset as usize;
                     curr_child_pos = position.map(|x| (x >> ((height - i - 1) * 2)) & 3);
                     curr_child_index = morton::morton_encode(curr_child_pos);
@@ -193,6 +308,9 @@ impl VoxelModelSFTCompressed {
             }
         }
 
+        self.mark_dirty(SFTDirtyRange::Nodes(curr_node_index..curr_node_index + 1));
+        let update_tracker = self.update_tracker;
+        let dirty_log = &mut self.dirty_log;
         let curr_node = &mut self.node_data[curr_node_index];
         let child_bit = 1 << curr_child_index;
 
@@ -218,6 +336,10 @@ impl VoxelModelSFTCompressed {
                 let end = start + Attachment::BMAT.size() as usize;
                 bmat_attachment_data[start..end]
                     .copy_from_slice(bytemuck::cast_slice(&comp_mat.to_le_bytes()));
+                dirty_log.push(
+                    update_tracker,
+                    SFTDirtyRange::AttachmentRaw(Attachment::BMAT_ID, start..end),
+                );
             } else {
                 bmat_lookup_node.attachment_mask |= child_bit;
                 let attachment_offset =
@@ -237,6 +359,13 @@ impl VoxelModelSFTCompressed {
                         .push(bmat_attachment_data[(bmat_lookup_node.data_ptr + i) as usize]);
                 }
                 bmat_lookup_node.data_ptr = data_ptr as u32;
+                dirty_log.push(
+                    update_tracker,
+                    SFTDirtyRange::AttachmentRaw(
+                        Attachment::BMAT_ID,
+                        data_ptr..bmat_attachment_data.len(),
+                    ),
+                );
             }
         } else {
             curr_node.child_mask &= !child_bit;
@@ -258,6 +387,15 @@ impl VoxelModelSFTCompressed {
                                 [(data_ptr + offset + Attachment::BMAT.size() + j) as usize];
                     }
                 }
+                let size = Attachment::BMAT.size();
+                dirty_log.push(
+                    update_tracker,
+                    SFTDirtyRange::AttachmentRaw(
+                        Attachment::BMAT_ID,
+                        (data_ptr + attachment_offset * size) as usize
+                            ..(data_ptr + attachment_count * size) as usize,
+                    ),
+                );
             }
         }
     }
@@ -316,6 +454,40 @@ impl VoxelModelSFTCompressed {
         }
     }
 
+    /// The nodes in `range` as laid out on the gpu.
+    pub fn packed_node_data(&self, range: Range<usize>) -> Vec<u32> {
+        let mut packed = Vec::with_capacity(range.len() * SFTNodeCompressed::U32_SIZE as usize);
+        for node in &self.node_data[range] {
+            packed.push(node.child_ptr);
+            // Little endian.
+            packed.push((node.child_mask & 0xFFFF_FFFF) as u32);
+            packed.push((node.child_mask >> 32) as u32);
+            packed.push((node.leaf_mask & 0xFFFF_FFFF) as u32);
+            packed.push((node.leaf_mask >> 32) as u32);
+        }
+        packed
+    }
+
+    /// The attachment lookups of the nodes in `range` as laid out on the gpu.
+    pub fn packed_attachment_lookup_data(
+        &self,
+        attachment_id: AttachmentId,
+        range: Range<usize>,
+    ) -> Vec<u32> {
+        let Some(lookup_data) = self.attachment_lookup_data.get(attachment_id) else {
+            return Vec::new();
+        };
+        let mut packed =
+            Vec::with_capacity(range.len() * SFTAttachmentLookupNodeCompressed::U32_SIZE as usize);
+        for lookup in &lookup_data[range] {
+            packed.push(lookup.data_ptr);
+            // Little endian.
+            packed.push((lookup.attachment_mask & 0xFFFF_FFFF) as u32);
+            packed.push((lookup.attachment_mask >> 32) as u32);
+        }
+        packed
+    }
+
     pub fn tree_height(&self) -> u32 {
         self.side_length.trailing_zeros() / 2
     }
@@ -368,6 +540,96 @@ impl VoxelModelSFTCompressed {
             as u64;
         return Some(VoxelMaterialData::decode((b << 32) | a, &self.material_map));
     }
+
+    /// Returns a lower resolution copy where each 4x4x4 group of voxels is collapsed into one
+    /// voxel per level, so a side length of 256 becomes 64 after one level. A collapsed voxel
+    /// exists if any voxel in its group does so distant geometry never gains holes, and takes
+    /// the most common material of its group.
+    pub fn downsample(&self, levels: u32) -> VoxelModelSFTCompressed {
+        assert!(
+            levels < self.tree_height(),
+            "Can't downsample a model with side length {} by {} levels.",
+            self.side_length,
+            levels
+        );
+        let group_shift = levels * 2;
+
+        let mut group_materials: HashMap<Vector3<u32>, HashMap<u64, u32>> = HashMap::new();
+        self.for_each_voxel(|position, material| {
+            *group_materials
+                .entry(position.map(|x| x >> group_shift))
+                .or_default()
+                .entry(material)
+                .or_default() += 1;
+        });
+
+        let mut downsampled = VoxelModelSFTCompressed::new_empty(self.side_length >> group_shift);
+        downsampled.material_map = self.material_map.clone();
+        downsampled.initialize_attachment_buffers(&Attachment::BMAT);
+        for (group_position, materials) in group_materials {
+            // Ties go to the lowest encoded material so downsampling is deterministic.
+            let (material, _) = materials
+                .into_iter()
+                .max_by_key(|(material, count)| (*count, std::cmp::Reverse(*material)))
+                .unwrap();
+            downsampled.set_voxel(
+                group_position,
+                Some(&VoxelMaterialData::decode(material, &self.material_map)),
+            );
+        }
+
+        downsampled
+    }
+
+    /// Calls `f` with the position and encoded builtin material of every voxel.
+    fn for_each_voxel(&self, mut f: impl FnMut(Vector3<u32>, u64)) {
+        let (Some(bmat_lookup_data), Some(bmat_attachment_data)) = (
+            self.attachment_lookup_data.get(Attachment::BMAT_ID),
+            self.attachment_raw_data.get(Attachment::BMAT_ID),
+        ) else {
+            return;
+        };
+
+        // (node index, node min position, side length of each child)
+        let mut stack = vec![(0usize, Vector3::<u32>::zeros(), self.side_length / 4)];
+        while let Some((node_index, node_position, child_length)) = stack.pop() {
+            let node = &self.node_data[node_index];
+            for child_index in 0..64u32 {
+                let child_bit = 1u64 << child_index;
+                if node.child_mask & child_bit == 0 {
+                    continue;
+                }
+                let child_position =
+                    node_position + morton::morton_decode(child_index as u64) * child_length;
+                if child_length > 1 {
+                    let child_offset = (node.child_mask & (child_bit - 1)).count_ones();
+                    stack.push((
+                        (node.child_ptr + child_offset) as usize,
+                        child_position,
+                        child_length / 4,
+                    ));
+                    continue;
+                }
+
+                // Lookup data may be shorter than the node data.
+                let Some(bmat_lookup_node) = bmat_lookup_data.get(node_index) else {
+                    continue;
+                };
+                if node.leaf_mask & child_bit == 0
+                    || bmat_lookup_node.attachment_mask & child_bit == 0
+                {
+                    continue;
+                }
+                let attachment_offset =
+                    (bmat_lookup_node.attachment_mask & (child_bit - 1)).count_ones() as usize;
+                let data_index = bmat_lookup_node.data_ptr() as usize
+                    + attachment_offset * Attachment::BMAT.size() as usize;
+                let material = ((bmat_attachment_data[data_index + 1] as u64) << 32)
+                    | bmat_attachment_data[data_index] as u64;
+                f(child_position, material);
+            }
+        }
+    }
 }
 
 impl VoxelModelImpl for VoxelModelSFTCompressed {
@@ -385,6 +647,8 @@ impl VoxelModelImpl for VoxelModelSFTCompressed {
         for attachment_raw_data in self.attachment_raw_data.values_mut() {
             attachment_raw_data.clear();
         }
+        self.update_tracker += 1;
+        self.dirty_log = SFTDirtyLog::new(self.update_tracker);
     }
 
     fn resize_model(&mut self, new_side_length: Vector3<u32>) {
@@ -417,6 +681,7 @@ impl VoxelModelImpl for VoxelModelSFTCompressed {
             // TODO: do this
         }
         new_sft.update_tracker = self.update_tracker + 1;
+        new_sft.dirty_log = SFTDirtyLog::new(new_sft.update_tracker);
 
         *self = new_sft;
     }
@@ -548,7 +813,7 @@ impl VoxelModelImpl for VoxelModelSFTCompressed {
         let sl = self.side_length;
         let volume = (sl as u64).pow(3);
 
-        let calculate_mask_weight = |prev_voxel: Option<VoxelMaterialData>,
+        let calculate_mask_weight = |prev_voxel: Option<&VoxelMaterialData>,
                                      voxel_pos: Vector3<u32>| {
             let mut weight = 1.0;
             for mask in &edit.mask.layers {
@@ -560,19 +825,55 @@ impl VoxelModelImpl for VoxelModelSFTCompressed {
                         }
                     }
                     crate::voxel::voxel::VoxelModelEditMaskLayer::Sphere { center, diameter } => {
-                        let mut center = center.cast::<f32>();
-                        let mut radius = *diameter as f32 / 2.0;
-                        if diameter % 2 == 0 {
-                            center += Vector3::new(0.5, 0.5, 0.5);
+                        let (offset, radius) =
+                            crate::voxel::voxel::VoxelModelEditMaskLayer::brush_offset(
+                                center, *diameter, &voxel_pos,
+                            );
+                        let distance = offset.norm();
+                        if distance > radius {
+                            weight = 0.0;
+                            break;
                         }
-                        if (Vector3::new(
-                            voxel_pos.x as f32,
-                            voxel_pos.y as f32,
-                            voxel_pos.z as f32,
-                        ) - center)
-                            .norm()
-                            > radius
-                        {
+                        weight *= crate::voxel::voxel::VoxelModelEditMaskLayer::brush_falloff(
+                            distance, radius,
+                        );
+                    }
+                    crate::voxel::voxel::VoxelModelEditMaskLayer::Cube { center, diameter } => {
+                        let (offset, radius) =
+                            crate::voxel::voxel::VoxelModelEditMaskLayer::brush_offset(
+                                center, *diameter, &voxel_pos,
+                            );
+                        let distance = offset.abs().max();
+                        if distance > radius {
+                            weight = 0.0;
+                            break;
+                        }
+                        weight *= crate::voxel::voxel::VoxelModelEditMaskLayer::brush_falloff(
+                            distance, radius,
+                        );
+                    }
+                    crate::voxel::voxel::VoxelModelEditMaskLayer::Cylinder {
+                        center,
+                        diameter,
+                        axis,
+                    } => {
+                        let (mut offset, radius) =
+                            crate::voxel::voxel::VoxelModelEditMaskLayer::brush_offset(
+                                center, *diameter, &voxel_pos,
+                            );
+                        let height_offset = offset[axis.index()];
+                        offset[axis.index()] = 0.0;
+                        let distance = offset.norm().max(height_offset.abs());
+                        if distance > radius {
+                            weight = 0.0;
+                            break;
+                        }
+                        weight *= crate::voxel::voxel::VoxelModelEditMaskLayer::brush_falloff(
+                            distance, radius,
+                        );
+                    }
+                    crate::voxel::voxel::VoxelModelEditMaskLayer::Voxels { voxels, offset } => {
+                        if !voxels.contains(&(voxel_pos.cast::<i32>() + offset)) {
                             weight = 0.0;
                             break;
                         }
@@ -594,23 +895,23 @@ impl VoxelModelImpl for VoxelModelSFTCompressed {
                         } else {
                             self.get_voxel(voxel_pos)
                         };
-                        let weight = calculate_mask_weight(prev_mat, voxel_pos);
+                        let weight = calculate_mask_weight(prev_mat.as_ref(), voxel_pos);
                         if weight == 0.0 {
                             continue;
                         }
-                        match &edit.operator {
-                            crate::voxel::voxel::VoxelModelEditOperator::Replace(
-                                voxel_material_data,
-                            ) => {
-                                if let Some(VoxelMaterialData::Unbaked(material_id)) =
-                                    voxel_material_data
-                                {
-                                    self.material_map.ensure_global_material_exists(material_id);
-                                }
-                                self.update_tracker += 1;
-                                self.set_voxel(Vector3::new(x, y, z), voxel_material_data.as_ref());
-                            }
+                        // Blending reads this model's voxel, not the mask source's.
+                        let prev_voxel = if edit.mask.mask_source.is_some() {
+                            self.get_voxel(voxel_pos)
+                        } else {
+                            prev_mat
+                        };
+                        let voxel_material_data = edit.operator.apply(prev_voxel, weight);
+                        if let Some(VoxelMaterialData::Unbaked(material_id)) = &voxel_material_data
+                        {
+                            self.material_map.ensure_global_material_exists(material_id);
                         }
+                        self.update_tracker += 1;
+                        self.set_voxel(Vector3::new(x, y, z), voxel_material_data.as_ref());
                     }
                 }
             }
@@ -954,6 +1255,7 @@ impl From<&VoxelModelFlat> for VoxelModelSFTCompressed {
                     attachment_lookup_data,
                     attachment_raw_data,
                     update_tracker: 0,
+                    dirty_log: SFTDirtyLog::default(),
                 };
             }
             SFTFlatNode::Child(nodes) => nodes,
@@ -1010,6 +1312,122 @@ impl From<&VoxelModelFlat> for VoxelModelSFTCompressed {
             attachment_lookup_data,
             attachment_raw_data,
             update_tracker: 0,
+            dirty_log: SFTDirtyLog::default(),
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::{color::ColorSrgba, geometry::aabb::AABB},
+        voxel::attachment::PTMaterial,
+    };
+
+    #[test]
+    fn trace_matches_flat_model() {
+        let length = 16;
+        let mut flat = VoxelModelFlat::new_empty(Vector3::new(length, length, length));
+        let material = PTMaterial::diffuse(Color::new_srgb(0.2, 0.6, 0.4)).encode();
+        // A floor with scattered voxels above it so rays cross empty nodes before hitting.
+        for (position, mut voxel) in flat.xyz_iter_mut() {
+            if position.y == 0 || (position.x * 7 + position.y * 13 + position.z * 5) % 23 == 0 {
+                voxel.set_attachment(Attachment::PTMATERIAL, Some(material));
+            }
+        }
+        let sft = VoxelModelSFTCompressed::from(&flat);
+
+        let aabb = AABB::new_two_point(Vector3::zeros(), Vector3::repeat(length as f32));
+        let directions = [
+            Vector3::new(0.3, -0.8, 0.5),
+            Vector3::new(-0.6, -0.4, -0.7),
+            Vector3::new(0.9, -0.1, 0.2),
+        ];
+        let mut hits = 0;
+        for dir in directions {
+            let dir = dir.normalize();
+            // Offset from the voxel grid so no ray grazes a voxel edge.
+            for u in 0..24 {
+                for v in 0..24 {
+                    let target = Vector3::new(u as f32 * 0.71 - 0.13, 8.37, v as f32 * 0.71 - 0.29);
+                    let ray = Ray::new(target - dir * 32.0, dir);
+                    let flat_trace = flat.trace(&ray, &aabb);
+                    let sft_trace = sft.trace(&ray, &aabb);
+                    assert_eq!(
+                        flat_trace.as_ref().map(|trace| trace.local_position),
+                        sft_trace.as_ref().map(|trace| trace.local_position),
+                        "Hit voxel differs for ray {:?} {:?}.",
+                        ray.origin,
+                        ray.dir,
+                    );
+                    if let (Some(flat_trace), Some(sft_trace)) = (flat_trace, sft_trace) {
+                        assert!((flat_trace.depth_t - sft_trace.depth_t).abs() < 1e-3);
+                        hits += 1;
+                    }
+                }
+            }
+        }
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn downsample_keeps_every_occupied_group() {
+        let red = VoxelMaterialData::Baked {
+            color: ColorSrgba::new(1.0, 0.0, 0.0, 1.0),
+        };
+        let blue = VoxelMaterialData::Baked {
+            color: ColorSrgba::new(0.0, 0.0, 1.0, 1.0),
+        };
+        let mut model = VoxelModelSFTCompressed::new_empty(64);
+        model.initialize_attachment_buffers(&Attachment::BMAT);
+        let mut voxels = Vec::new();
+        for x in 0..64u32 {
+            for y in 0..64u32 {
+                for z in 0..64u32 {
+                    if (x * 7 + y * 13 + z * 5) % 97 == 0 {
+                        voxels.push(Vector3::new(x, y, z));
+                    }
+                }
+            }
+        }
+        for position in &voxels {
+            model.set_voxel(*position, Some(&blue));
+        }
+        // A group with mostly red voxels.
+        for position in [
+            Vector3::new(40, 40, 40),
+            Vector3::new(41, 40, 40),
+            Vector3::new(40, 41, 40),
+            Vector3::new(43, 43, 42),
+        ] {
+            let material = if position.z == 42 { &blue } else { &red };
+            model.set_voxel(position, Some(material));
+            voxels.push(position);
+        }
+
+        let downsampled = model.downsample(1);
+        assert_eq!(downsampled.side_length(), 16);
+        for x in 0..16u32 {
+            for y in 0..16u32 {
+                for z in 0..16u32 {
+                    let group_position = Vector3::new(x, y, z);
+                    let is_occupied = voxels
+                        .iter()
+                        .any(|voxel| voxel.map(|x| x / 4) == group_position);
+                    assert_eq!(downsampled.get_voxel(group_position).is_some(), is_occupied);
+                }
+            }
+        }
+        assert!(downsampled.get_voxel(Vector3::new(10, 10, 10)) == Some(red));
+
+        // The downsampled layout is traceable.
+        let aabb = AABB::new_two_point(Vector3::zeros(), Vector3::repeat(16.0));
+        let ray = Ray::new(Vector3::new(10.5, 30.0, 10.5), Vector3::new(0.0, -1.0, 0.0));
+        let trace = downsampled.trace(&ray, &aabb).unwrap();
+        assert_eq!(trace.local_position.xz(), Vector3::new(10, 10, 10).xz());
+        assert!(trace.local_position.y >= 10);
+
+        assert_eq!(model.downsample(2).side_length(), 4);
+    }
+}
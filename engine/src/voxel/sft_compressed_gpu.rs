@@ -8,7 +8,8 @@ use crate::{
 use super::{
     attachment::{Attachment, AttachmentId},
     sft_compressed::{
-        SFTAttachmentLookupNodeCompressed, SFTNodeCompressed, VoxelModelSFTCompressed,
+        SFTAttachmentLookupNodeCompressed, SFTDirtyRanges, SFTNodeCompressed,
+        VoxelModelSFTCompressed,
     },
     voxel::{VoxelModelGpuImpl, VoxelModelGpuImplMethods, VoxelModelImplMethods},
     voxel_allocator::{VoxelDataAllocation, VoxelDataAllocator},
@@ -22,6 +23,9 @@ pub struct VoxelModelSFTCompressedGpu {
     attachment_lookup_allocations: HashMap<AttachmentId, VoxelDataAllocation>,
     attachment_raw_allocations: HashMap<AttachmentId, VoxelDataAllocation>,
 
+    // Set whenever an allocation is created or moves since its contents are then undefined, so
+    // the next write can't upload only the model's dirty ranges.
+    needs_full_write: bool,
     initialized_data: bool,
     invalidated_material: bool,
     update_tracker: u32,
@@ -44,6 +48,7 @@ impl VoxelModelSFTCompressedGpu {
         for (_, alloc) in self.attachment_raw_allocations.drain() {
             allocator.free(&alloc);
         }
+        self.needs_full_write = true;
     }
 
     // Returns true if a data pointer was updated, triggering an update in the model info.
@@ -64,6 +69,7 @@ impl VoxelModelSFTCompressedGpu {
                         != new_allocation.start_index_stride_bytes()
                     {
                         *allocation = Some(new_allocation);
+                        self.needs_full_write = true;
                         return true;
                     }
                 }
@@ -74,6 +80,7 @@ impl VoxelModelSFTCompressedGpu {
                     .allocate(device, required_size)
                     .expect("Failed to allocate voxel model data.");
                 *allocation = Some(new_allocation);
+                self.needs_full_write = true;
                 return true;
             }
         }
@@ -81,6 +88,7 @@ impl VoxelModelSFTCompressedGpu {
 
     fn try_create_or_update_attachment_allocation(
         allocations: &mut HashMap<AttachmentId, VoxelDataAllocation>,
+        needs_full_write: &mut bool,
         attachment_id: &AttachmentId,
         device: &mut GfxDevice,
         allocator: &mut VoxelDataAllocator,
@@ -92,6 +100,7 @@ impl VoxelModelSFTCompressedGpu {
                     *old_allocation = allocator
                         .reallocate(device, old_allocation, required_size)
                         .expect("Failed to reallocate attachment data.");
+                    *needs_full_write = true;
 
                     return true;
                 }
@@ -102,12 +111,41 @@ impl VoxelModelSFTCompressedGpu {
                     .allocate(device, required_size)
                     .expect("Failed to allocate attachment data.");
                 allocations.insert(attachment_id.clone(), new_allocation);
+                *needs_full_write = true;
                 return true;
             }
         }
     }
 }
 
+impl VoxelModelSFTCompressedGpu {
+    /// Ranges closer than this many u32s are merged into one write, trading a few redundant
+    /// bytes for fewer staging copies.
+    const WRITE_MERGE_DISTANCE: usize = 8;
+
+    /// The ranges to write to bring this gpu model up to date with `model`, everything if the
+    /// allocations' contents are undefined or the model's dirty log doesn't reach back far
+    /// enough.
+    fn ranges_to_write(&self, model: &VoxelModelSFTCompressed) -> SFTDirtyRanges {
+        let partial_ranges = (self.initialized_data && !self.needs_full_write)
+            .then(|| {
+                model
+                    .dirty_log
+                    .ranges_since(self.update_tracker, Self::WRITE_MERGE_DISTANCE)
+            })
+            .flatten();
+        partial_ranges.unwrap_or_else(|| SFTDirtyRanges {
+            nodes: vec![0..model.node_data.len()],
+            attachment_raw: model
+                .attachment_raw_data
+                .iter()
+                .filter(|(_, raw_data)| !raw_data.is_empty())
+                .map(|(attachment_id, raw_data)| (attachment_id, vec![0..raw_data.len()]))
+                .collect(),
+        })
+    }
+}
+
 impl VoxelModelGpuImpl for VoxelModelSFTCompressedGpu {
     const SCHEMA: u32 = 0;
 
@@ -119,6 +157,7 @@ impl VoxelModelGpuImpl for VoxelModelSFTCompressedGpu {
             attachment_lookup_allocations: HashMap::new(),
             attachment_raw_allocations: HashMap::new(),
 
+            needs_full_write: true,
             initialized_data: false,
             invalidated_material: false,
             update_tracker: 0,
@@ -197,6 +236,7 @@ impl VoxelModelGpuImplMethods for VoxelModelSFTCompressedGpu {
         for (attachment_id, data) in model.attachment_lookup_data.iter() {
             did_allocate |= Self::try_create_or_update_attachment_allocation(
                 &mut self.attachment_lookup_allocations,
+                &mut self.needs_full_write,
                 &attachment_id,
                 device,
                 allocator,
@@ -208,6 +248,7 @@ impl VoxelModelGpuImplMethods for VoxelModelSFTCompressedGpu {
             let attachment = model.attachment_map.get_unchecked(attachment_id);
             did_allocate |= Self::try_create_or_update_attachment_allocation(
                 &mut self.attachment_raw_allocations,
+                &mut self.needs_full_write,
                 &attachment_id,
                 device,
                 allocator,
@@ -236,6 +277,7 @@ impl VoxelModelGpuImplMethods for VoxelModelSFTCompressedGpu {
                     .len() as u64;
             did_allocate |= Self::try_create_or_update_attachment_allocation(
                 &mut self.attachment_raw_allocations,
+                &mut self.needs_full_write,
                 &Attachment::NORMAL_ID,
                 device,
                 allocator,
@@ -277,75 +319,93 @@ impl VoxelModelGpuImplMethods for VoxelModelSFTCompressedGpu {
     ) {
         let model = model.downcast_ref::<VoxelModelSFTCompressed>().unwrap();
 
-        let mut should_write_raw_data = self.invalidated_material;
-        self.invalidated_material = false;
-
+        if self.invalidated_material {
+            // Baked materials were written to the raw data on the gpu so it must be rewritten
+            // in full.
+            self.needs_full_write = true;
+        }
         let did_model_update = model.update_tracker != self.update_tracker;
         // If data allocation is some and we haven't initialized yet, expected the attachment data
         // to also be ready.
-        if (!self.initialized_data || did_model_update) && self.nodes_allocation.is_some() {
-            should_write_raw_data = true;
-            self.initialized_data = true;
-            self.update_tracker = model.update_tracker;
-
-            {
-                let mut node_data_packed = Vec::with_capacity(
-                    model.node_data.len() * SFTNodeCompressed::U32_SIZE as usize,
-                );
-                for node in &model.node_data {
-                    node_data_packed.push(node.child_ptr);
-                    // Little endian.
-                    node_data_packed.push((node.child_mask & 0xFFFF_FFFF) as u32);
-                    node_data_packed.push((node.child_mask >> 32) as u32);
-                    node_data_packed.push((node.leaf_mask & 0xFFFF_FFFF) as u32);
-                    node_data_packed.push((node.leaf_mask >> 32) as u32);
-                }
-
-                let node_data_bytes = bytemuck::cast_slice::<u32, u8>(&node_data_packed);
-                allocator.write_allocation_data(
-                    device,
-                    self.nodes_allocation.as_ref().unwrap(),
-                    node_data_bytes,
-                );
-            }
-
-            for (attachment_id, lookup_data) in model.attachment_lookup_data.iter() {
-                assert_eq!(lookup_data.len(), model.node_data.len());
-                let allocation = self
-                    .attachment_lookup_allocations
-                    .get(&attachment_id)
-                    .expect("Lookup allocation should exist by now.");
+        let needs_write = !self.initialized_data || did_model_update || self.invalidated_material;
+        if !needs_write || self.nodes_allocation.is_none() {
+            return;
+        }
+        // Only the ranges edits wrote since the last upload are written, so a single voxel
+        // edit uploads a handful of bytes rather than the whole model.
+        let ranges = self.ranges_to_write(model);
+        self.invalidated_material = false;
+        self.needs_full_write = false;
+        self.initialized_data = true;
+        self.update_tracker = model.update_tracker;
+
+        // Every range is staged in one write group so the device's staging lock is only taken
+        // once per model instead of once per range.
+        let dirty_node_count = ranges
+            .nodes
+            .iter()
+            .map(|range| range.len() as u64)
+            .sum::<u64>();
+        let dirty_raw_count = ranges
+            .attachment_raw
+            .values()
+            .flatten()
+            .map(|range| range.len() as u64)
+            .sum::<u64>();
+        let write_size = dirty_node_count
+            * (SFTNodeCompressed::BYTE_SIZE
+                + model.attachment_lookup_data.iter().count() as u64
+                    * SFTAttachmentLookupNodeCompressed::BYTE_SIZE)
+            + dirty_raw_count * 4;
+        if write_size == 0 {
+            return;
+        }
+        let mut write_group = device.begin_write_group(write_size);
+
+        let nodes_allocation = self.nodes_allocation.as_ref().unwrap();
+        for range in &ranges.nodes {
+            allocator.write_allocation_data_in_group(
+                &mut write_group,
+                nodes_allocation,
+                range.start as u64 * SFTNodeCompressed::BYTE_SIZE,
+                bytemuck::cast_slice(&model.packed_node_data(range.clone())),
+            );
+        }
 
-                let mut lookup_data_packed = Vec::with_capacity(
-                    lookup_data.len() * SFTAttachmentLookupNodeCompressed::U32_SIZE as usize,
+        for (attachment_id, lookup_data) in model.attachment_lookup_data.iter() {
+            assert_eq!(lookup_data.len(), model.node_data.len());
+            let allocation = self
+                .attachment_lookup_allocations
+                .get(&attachment_id)
+                .expect("Lookup allocation should exist by now.");
+            for range in &ranges.nodes {
+                allocator.write_allocation_data_in_group(
+                    &mut write_group,
+                    allocation,
+                    range.start as u64 * SFTAttachmentLookupNodeCompressed::BYTE_SIZE,
+                    bytemuck::cast_slice(
+                        &model.packed_attachment_lookup_data(attachment_id, range.clone()),
+                    ),
                 );
-                for lookup in lookup_data {
-                    lookup_data_packed.push(lookup.data_ptr);
-                    // Little endian.
-                    lookup_data_packed.push((lookup.attachment_mask & 0xFFFF_FFFF) as u32);
-                    lookup_data_packed.push((lookup.attachment_mask >> 32) as u32);
-                }
-                let lookup_data_bytes = bytemuck::cast_slice::<u32, u8>(&lookup_data_packed);
-                allocator.write_allocation_data(device, allocation, lookup_data_bytes);
             }
         }
 
-        if should_write_raw_data {
-            for (attachment, raw_data) in model.attachment_raw_data.iter() {
-                let allocation = self
-                    .attachment_raw_allocations
-                    .get(&attachment)
-                    .expect("Raw allocation should exist by now.");
-
-                allocator.write_allocation_data(
-                    device,
+        for (attachment_id, attachment_ranges) in &ranges.attachment_raw {
+            let raw_data = model.attachment_raw_data.get(*attachment_id).unwrap();
+            let allocation = self
+                .attachment_raw_allocations
+                .get(attachment_id)
+                .expect("Raw allocation should exist by now.");
+            for range in attachment_ranges {
+                allocator.write_allocation_data_in_group(
+                    &mut write_group,
                     allocation,
-                    bytemuck::cast_slice::<u32, u8>(raw_data.as_slice()),
+                    range.start as u64 * 4,
+                    bytemuck::cast_slice(&raw_data[range.clone()]),
                 );
             }
-
-            return;
         }
+        device.end_write_group(write_group);
     }
 
     fn mark_for_invalidation(&mut self) {
@@ -362,5 +422,80 @@ impl VoxelModelGpuImplMethods for VoxelModelSFTCompressedGpu {
         for (_, alloc) in self.attachment_raw_allocations.drain() {
             allocator.free(&alloc);
         }
+        self.needs_full_write = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+
+    use super::*;
+    use crate::{common::color::ColorSrgba, voxel::voxel::VoxelMaterialData};
+
+    fn ranges_bytes(model: &VoxelModelSFTCompressed, ranges: &SFTDirtyRanges) -> u64 {
+        let node_bytes = SFTNodeCompressed::BYTE_SIZE
+            + model.attachment_lookup_data.iter().count() as u64
+                * SFTAttachmentLookupNodeCompressed::BYTE_SIZE;
+        let nodes = ranges.nodes.iter().map(|r| r.len() as u64).sum::<u64>();
+        let raw = ranges
+            .attachment_raw
+            .values()
+            .flatten()
+            .map(|r| r.len() as u64)
+            .sum::<u64>();
+        nodes * node_bytes + raw * 4
+    }
+
+    #[test]
+    fn single_voxel_edit_uploads_bounded_bytes() {
+        let material = VoxelMaterialData::Baked {
+            color: ColorSrgba::new(1.0, 0.0, 0.0, 1.0),
+        };
+        let mut model = VoxelModelSFTCompressed::new_empty(64);
+        model.initialize_attachment_buffers(&Attachment::BMAT);
+        for x in 0..16 {
+            for z in 0..16 {
+                model.set_voxel(Vector3::new(x * 4, 0, z * 4), Some(&material));
+            }
+        }
+
+        // A fresh gpu model uploads everything.
+        let mut gpu_model = VoxelModelSFTCompressedGpu::construct();
+        let full_bytes = ranges_bytes(&model, &gpu_model.ranges_to_write(&model));
+        gpu_model.initialized_data = true;
+        gpu_model.needs_full_write = false;
+        gpu_model.update_tracker = model.update_tracker;
+
+        // Same leaf node as an existing voxel so no nodes are added.
+        model.update_tracker += 1;
+        model.set_voxel(Vector3::new(1, 0, 0), Some(&material));
+        let ranges = gpu_model.ranges_to_write(&model);
+        let edit_bytes = ranges_bytes(&model, &ranges);
+        assert!(edit_bytes > 0);
+        assert!(
+            edit_bytes <= 128,
+            "Single voxel edit queued {} bytes of {}.",
+            edit_bytes,
+            full_bytes
+        );
+        assert!(edit_bytes * 10 < full_bytes);
+
+        // A moved allocation or a log that doesn't reach back far enough rewrites everything.
+        gpu_model.needs_full_write = true;
+        assert_eq!(
+            ranges_bytes(&model, &gpu_model.ranges_to_write(&model)),
+            ranges_bytes(
+                &model,
+                &VoxelModelSFTCompressedGpu::construct().ranges_to_write(&model)
+            )
+        );
+        gpu_model.needs_full_write = false;
+        model.clear();
+        model.set_voxel(Vector3::new(1, 0, 0), Some(&material));
+        assert_eq!(
+            gpu_model.ranges_to_write(&model).nodes,
+            vec![0..model.node_data.len()]
+        );
     }
 }
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use super::{voxel_allocator::VoxelDataAllocator, voxel_registry::VoxelModelId};
 use crate::material::material_bank::{MaterialAssetId, MaterialBank, MaterialId, NULL_MATERIAL_ID};
@@ -8,7 +9,10 @@ use crate::physics::voxel_collider::VoxelModelColliderData;
 use crate::voxel::rvox_asset::RVOXAsset;
 use crate::world::terrain::region::WorldChunkData;
 use crate::world::terrain::{chunk_lod::ChunkLOD, region_map::ChunkId};
-use crate::{common::geometry::ray::Ray, consts};
+use crate::{
+    common::geometry::{axis::Axis, ray::Ray},
+    consts,
+};
 use crate::{
     common::{color::ColorSrgba, geometry::aabb::AABB},
     voxel::voxel_registry::VoxelModelRegistry,
@@ -191,9 +195,172 @@ pub enum VoxelModelEditMaskLayer {
     Presence,
     /// Apply the edit in a sphere.
     Sphere { center: Vector3<i32>, diameter: u32 },
+    /// Apply the edit in a cube, the whole brush rect.
+    Cube { center: Vector3<i32>, diameter: u32 },
+    /// Apply the edit in a cylinder standing along `axis`, as tall as it is wide.
+    Cylinder {
+        center: Vector3<i32>,
+        diameter: u32,
+        axis: Axis,
+    },
+    /// Apply the edit to only the voxels in the set, such as the result of a flood fill. The
+    /// set is sampled at the edited voxel position plus `offset`.
+    Voxels {
+        voxels: Arc<HashSet<Vector3<i32>>>,
+        offset: Vector3<i32>,
+    },
+}
+
+impl VoxelModelEditMaskLayer {
+    /// The layer masking the mirrored voxels of this layer.
+    pub fn mirrored(&self, mirror: &VoxelEditMirror) -> Self {
+        match self {
+            VoxelModelEditMaskLayer::Presence => VoxelModelEditMaskLayer::Presence,
+            VoxelModelEditMaskLayer::Sphere { center, diameter } => {
+                VoxelModelEditMaskLayer::Sphere {
+                    center: Self::mirror_brush_center(mirror, center, *diameter),
+                    diameter: *diameter,
+                }
+            }
+            VoxelModelEditMaskLayer::Cube { center, diameter } => VoxelModelEditMaskLayer::Cube {
+                center: Self::mirror_brush_center(mirror, center, *diameter),
+                diameter: *diameter,
+            },
+            VoxelModelEditMaskLayer::Cylinder {
+                center,
+                diameter,
+                axis,
+            } => VoxelModelEditMaskLayer::Cylinder {
+                center: Self::mirror_brush_center(mirror, center, *diameter),
+                diameter: *diameter,
+                axis: *axis,
+            },
+            VoxelModelEditMaskLayer::Voxels { voxels, offset } => VoxelModelEditMaskLayer::Voxels {
+                voxels: Arc::new(
+                    voxels
+                        .iter()
+                        .map(|voxel| mirror.mirror_voxel(&(voxel - offset)))
+                        .collect(),
+                ),
+                offset: Vector3::zeros(),
+            },
+        }
+    }
+
+    fn mirror_brush_center(
+        mirror: &VoxelEditMirror,
+        center: &Vector3<i32>,
+        diameter: u32,
+    ) -> Vector3<i32> {
+        let mut mirrored_center = mirror.mirror_voxel(center);
+        if diameter % 2 == 0 {
+            // Even diameters are centered on the far corner of `center`, so the mirrored
+            // center moves back a voxel to keep the same brush.
+            mirrored_center -= mirror.axes_vector();
+        }
+        mirrored_center
+    }
+
+    /// How strongly a brush applies `distance` from its center, fading toward the edge without
+    /// reaching zero inside the brush so edits that don't use the weight still fill it.
+    pub fn brush_falloff(distance: f32, radius: f32) -> f32 {
+        (1.0 - distance / (radius + 0.5)).max(0.0)
+    }
+
+    /// The offset of `voxel_pos` from the center of a brush layer along with the brush's
+    /// radius, even diameters are centered on the far corner of `center`.
+    pub fn brush_offset(
+        center: &Vector3<i32>,
+        diameter: u32,
+        voxel_pos: &Vector3<u32>,
+    ) -> (Vector3<f32>, f32) {
+        let mut center = center.cast::<f32>();
+        if diameter % 2 == 0 {
+            center += Vector3::new(0.5, 0.5, 0.5);
+        }
+        (voxel_pos.cast::<f32>() - center, diameter as f32 / 2.0)
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    pub struct VoxelEditMirrorAxes: u8 {
+        const NONE = 0;
+        const X = 1;
+        const Y = 1 << 1;
+        const Z = 1 << 2;
+    }
 }
 
-impl VoxelModelEditMaskLayer {}
+/// Mirrors voxel positions across the planes perpendicular to `axes`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoxelEditMirror {
+    pub axes: VoxelEditMirrorAxes,
+    /// The position of each axis' plane in half voxels, so even positions lie on voxel faces and
+    /// odd positions go through voxel centers.
+    pub plane_half_voxels: Vector3<i32>,
+}
+
+impl VoxelEditMirror {
+    /// Mirrors across the center of a model with the side lengths `model_length`.
+    pub fn around_model_center(axes: VoxelEditMirrorAxes, model_length: Vector3<u32>) -> Self {
+        Self {
+            axes,
+            plane_half_voxels: model_length.cast::<i32>(),
+        }
+    }
+
+    /// Mirrors across the min faces of the voxel `plane_voxel`.
+    pub fn around_voxel_faces(axes: VoxelEditMirrorAxes, plane_voxel: Vector3<i32>) -> Self {
+        Self {
+            axes,
+            plane_half_voxels: plane_voxel * 2,
+        }
+    }
+
+    /// Every mirror from a non-empty combination of the axes, applying an edit with each of
+    /// these gives every mirrored copy of that edit.
+    pub fn combinations(&self) -> impl Iterator<Item = VoxelEditMirror> + '_ {
+        (1..8)
+            .map(VoxelEditMirrorAxes::from_bits_truncate)
+            .filter(|axes| self.axes.contains(*axes))
+            .map(|axes| VoxelEditMirror {
+                axes,
+                plane_half_voxels: self.plane_half_voxels,
+            })
+    }
+
+    /// 1 for each mirrored axis, otherwise 0.
+    pub fn axes_vector(&self) -> Vector3<i32> {
+        Vector3::new(
+            self.axes.contains(VoxelEditMirrorAxes::X) as i32,
+            self.axes.contains(VoxelEditMirrorAxes::Y) as i32,
+            self.axes.contains(VoxelEditMirrorAxes::Z) as i32,
+        )
+    }
+
+    pub fn mirror_voxel(&self, voxel_pos: &Vector3<i32>) -> Vector3<i32> {
+        let axes = self.axes_vector();
+        Vector3::from_fn(|i, _| {
+            if axes[i] == 1 {
+                self.plane_half_voxels[i] - 1 - voxel_pos[i]
+            } else {
+                voxel_pos[i]
+            }
+        })
+    }
+
+    /// The mirrored inclusive min and max of the rect `min`..=`max`.
+    pub fn mirror_rect(
+        &self,
+        min: &Vector3<i32>,
+        max: &Vector3<i32>,
+    ) -> (Vector3<i32>, Vector3<i32>) {
+        let a = self.mirror_voxel(min);
+        let b = self.mirror_voxel(max);
+        (a.inf(&b), a.sup(&b))
+    }
+}
 
 #[derive(Clone)]
 pub enum VoxelModelEditRegion {
@@ -239,9 +406,75 @@ pub struct VoxelModelEdit<'a> {
     pub operator: VoxelModelEditOperator,
 }
 
+impl VoxelModelEdit<'_> {
+    /// The edit mirrored within a model of `model_length`, None if the edit samples a mask
+    /// source since the source can't be mirrored.
+    pub fn mirrored(
+        &self,
+        mirror: &VoxelEditMirror,
+        model_length: Vector3<u32>,
+    ) -> Option<VoxelModelEdit<'static>> {
+        if self.mask.mask_source.is_some() {
+            return None;
+        }
+        let VoxelModelEditRegion::Rect { min, max } = &self.region else {
+            return None;
+        };
+        let (min, max) = mirror.mirror_rect(&min.cast::<i32>(), &max.cast::<i32>());
+        Some(VoxelModelEdit {
+            region: VoxelModelEditRegion::saturate_rect(min, max, model_length),
+            mask: VoxelModelEditMask {
+                layers: self
+                    .mask
+                    .layers
+                    .iter()
+                    .map(|layer| layer.mirrored(mirror))
+                    .collect(),
+                mask_source: None,
+            },
+            operator: self.operator.clone(),
+        })
+    }
+}
+
 #[derive(Clone)]
 pub enum VoxelModelEditOperator {
     Replace(Option<VoxelMaterialData>),
+    /// Mixes the color of existing voxels toward `material` by `opacity` scaled by the mask
+    /// weight, empty voxels get `material` outright. Unbaked materials can't be mixed so they
+    /// replace the voxel instead.
+    Blend {
+        material: VoxelMaterialData,
+        opacity: f32,
+    },
+}
+
+impl VoxelModelEditOperator {
+    /// The material to write over `prev_voxel` with the mask `weight`.
+    pub fn apply(
+        &self,
+        prev_voxel: Option<VoxelMaterialData>,
+        weight: f32,
+    ) -> Option<VoxelMaterialData> {
+        match self {
+            VoxelModelEditOperator::Replace(material) => material.clone(),
+            VoxelModelEditOperator::Blend { material, opacity } => match (prev_voxel, material) {
+                (
+                    Some(VoxelMaterialData::Baked { color: prev_color }),
+                    VoxelMaterialData::Baked { color },
+                ) => {
+                    let t = (opacity * weight).clamp(0.0, 1.0);
+                    Some(VoxelMaterialData::Baked {
+                        color: ColorSrgba {
+                            rgb: prev_color.rgb.mix(&color.rgb, t),
+                            alpha: prev_color.alpha * (1.0 - t) + color.alpha * t,
+                        },
+                    })
+                }
+                _ => Some(material.clone()),
+            },
+        }
+    }
 }
 
 pub struct VoxelModelTrace {
@@ -257,7 +490,7 @@ pub struct MaterialPalette {
 /// 64 bit material data, two halves:
 /// Starting from MSB:
 ///
-#[derive(Clone, strum_macros::EnumIs)]
+#[derive(Clone, PartialEq, strum_macros::EnumIs, serde::Serialize, serde::Deserialize)]
 pub enum VoxelMaterialData {
     Unbaked(MaterialId),
     Baked { color: ColorSrgba },
@@ -452,3 +685,216 @@ pub trait VoxelModelGpuImplMethods: Send + Sync + Any {
 }
 
 downcast!(dyn VoxelModelGpuImplMethods);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::{attachment::Attachment, sft_compressed::VoxelModelSFTCompressed};
+
+    #[test]
+    fn mirrored_edit_places_mirrored_voxels() {
+        let model_length = Vector3::new(16, 16, 16);
+        let mirror = VoxelEditMirror::around_model_center(
+            VoxelEditMirrorAxes::X | VoxelEditMirrorAxes::Z,
+            model_length,
+        );
+        assert_eq!(
+            mirror.mirror_voxel(&Vector3::new(0, 3, 15)),
+            Vector3::new(15, 3, 0)
+        );
+
+        // Odd and even diameters have differently centered spheres.
+        for diameter in [3, 4] {
+            let center = Vector3::new(3, 5, 9);
+            let edit = VoxelModelEdit {
+                region: VoxelModelEditRegion::saturate_rect(
+                    center.add_scalar(-(diameter as i32)),
+                    center.add_scalar(diameter as i32),
+                    model_length,
+                ),
+                mask: VoxelModelEditMask {
+                    layers: vec![VoxelModelEditMaskLayer::Sphere { center, diameter }],
+                    mask_source: None,
+                },
+                operator: VoxelModelEditOperator::Replace(Some(VoxelMaterialData::Baked {
+                    color: ColorSrgba::new(1.0, 0.0, 0.0, 1.0),
+                })),
+            };
+            let mirrored_edit = edit
+                .mirrored(&mirror, model_length)
+                .expect("Edit has no mask source.");
+
+            let mut model = VoxelModelSFTCompressed::new_empty(16);
+            model.initialize_attachment_buffers(&Attachment::BMAT);
+            let mut mirrored_model = model.clone();
+            VoxelModelImpl::set_voxel_range_impl(&mut model, &edit);
+            VoxelModelImpl::set_voxel_range_impl(&mut mirrored_model, &mirrored_edit);
+
+            let mut voxel_count = 0;
+            for x in 0..16 {
+                for y in 0..16 {
+                    for z in 0..16 {
+                        let voxel_pos = Vector3::new(x, y, z);
+                        let mirrored_pos = mirror.mirror_voxel(&voxel_pos).map(|x| x as u32);
+                        let exists = model.get_voxel(voxel_pos.map(|x| x as u32)).is_some();
+                        assert_eq!(exists, mirrored_model.get_voxel(mirrored_pos).is_some());
+                        voxel_count += exists as u32;
+                    }
+                }
+            }
+            assert!(voxel_count > 0);
+        }
+    }
+    #[test]
+    fn voxels_mask_edits_only_its_voxels() {
+        let model_length = Vector3::new(16, 16, 16);
+        let voxels = HashSet::from([
+            Vector3::new(3, 4, 5),
+            Vector3::new(4, 4, 5),
+            Vector3::new(9, 2, 7),
+        ]);
+        let offset = Vector3::new(1, 1, 1);
+        let edit = VoxelModelEdit {
+            region: VoxelModelEditRegion::saturate_rect(
+                Vector3::zeros(),
+                model_length.cast::<i32>(),
+                model_length,
+            ),
+            mask: VoxelModelEditMask {
+                layers: vec![VoxelModelEditMaskLayer::Voxels {
+                    voxels: Arc::new(voxels.clone()),
+                    offset,
+                }],
+                mask_source: None,
+            },
+            operator: VoxelModelEditOperator::Replace(Some(VoxelMaterialData::Baked {
+                color: ColorSrgba::new(1.0, 0.0, 0.0, 1.0),
+            })),
+        };
+        let mut model = VoxelModelSFTCompressed::new_empty(16);
+        model.initialize_attachment_buffers(&Attachment::BMAT);
+        VoxelModelImpl::set_voxel_range_impl(&mut model, &edit);
+
+        // The set is sampled at the edited voxel plus the offset.
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    let voxel_pos = Vector3::new(x, y, z);
+                    assert_eq!(
+                        model.get_voxel(voxel_pos).is_some(),
+                        voxels.contains(&(voxel_pos.cast::<i32>() + offset))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn brush_shapes_fill_brush_rect() {
+        let model_length = Vector3::new(16, 16, 16);
+        let center = Vector3::new(8, 8, 8);
+        let fill = |layer: VoxelModelEditMaskLayer, diameter: u32| {
+            let edit = VoxelModelEdit {
+                region: VoxelModelEditRegion::saturate_rect(
+                    center.add_scalar(-(diameter as i32)),
+                    center.add_scalar(diameter as i32),
+                    model_length,
+                ),
+                mask: VoxelModelEditMask {
+                    layers: vec![layer],
+                    mask_source: None,
+                },
+                operator: VoxelModelEditOperator::Replace(Some(VoxelMaterialData::Baked {
+                    color: ColorSrgba::new(1.0, 0.0, 0.0, 1.0),
+                })),
+            };
+            let mut model = VoxelModelSFTCompressed::new_empty(16);
+            model.initialize_attachment_buffers(&Attachment::BMAT);
+            VoxelModelImpl::set_voxel_range_impl(&mut model, &edit);
+            model
+        };
+
+        // Small cylinders fill the corners of the brush rect so these are large enough not to.
+        for diameter in [4, 5] {
+            let cube = fill(VoxelModelEditMaskLayer::Cube { center, diameter }, diameter);
+            let cylinder = fill(
+                VoxelModelEditMaskLayer::Cylinder {
+                    center,
+                    diameter,
+                    axis: Axis::Y,
+                },
+                diameter,
+            );
+            let mut cube_count = 0;
+            for x in 0..16 {
+                for y in 0..16 {
+                    for z in 0..16 {
+                        let voxel_pos = Vector3::new(x, y, z);
+                        cube_count += cube.get_voxel(voxel_pos).is_some() as u32;
+                        // The cylinder is within the cube.
+                        assert!(
+                            cylinder.get_voxel(voxel_pos).is_none()
+                                || cube.get_voxel(voxel_pos).is_some()
+                        );
+                    }
+                }
+            }
+            assert_eq!(cube_count, diameter.pow(3));
+
+            // The cylinder's caps are full height but its side edges are cut off.
+            let (min, max) = (
+                center.map(|x| (x - (diameter as i32 - 1) / 2) as u32),
+                center.map(|x| (x + diameter as i32 / 2) as u32),
+            );
+            assert!(
+                cylinder
+                    .get_voxel(Vector3::new(center.x as u32, min.y, center.z as u32))
+                    .is_some()
+            );
+            assert!(
+                cylinder
+                    .get_voxel(Vector3::new(center.x as u32, max.y, center.z as u32))
+                    .is_some()
+            );
+            assert!(
+                cylinder
+                    .get_voxel(Vector3::new(min.x, center.y as u32, min.z))
+                    .is_none()
+            );
+            assert!(
+                cube.get_voxel(Vector3::new(min.x, center.y as u32, min.z))
+                    .is_some()
+            );
+        }
+    }
+
+    #[test]
+    fn blend_mixes_toward_brush_color() {
+        let black = ColorSrgba::new(0.0, 0.0, 0.0, 1.0);
+        let white = ColorSrgba::new(1.0, 1.0, 1.0, 1.0);
+        let operator = VoxelModelEditOperator::Blend {
+            material: VoxelMaterialData::Baked { color: white },
+            opacity: 0.5,
+        };
+
+        let Some(VoxelMaterialData::Baked { color }) =
+            operator.apply(Some(VoxelMaterialData::Baked { color: black }), 1.0)
+        else {
+            panic!("Blending baked colors should stay baked.");
+        };
+        assert!((color.r() - 0.5).abs() < 1e-6);
+        // The falloff weight scales the opacity.
+        let Some(VoxelMaterialData::Baked { color }) =
+            operator.apply(Some(VoxelMaterialData::Baked { color: black }), 0.5)
+        else {
+            panic!("Blending baked colors should stay baked.");
+        };
+        assert!((color.r() - 0.25).abs() < 1e-6);
+        // Empty voxels get the full brush color.
+        assert!(operator.apply(None, 0.5) == Some(VoxelMaterialData::Baked { color: white }));
+
+        // The edge of the brush is still applied.
+        assert!(VoxelModelEditMaskLayer::brush_falloff(2.0, 2.0) > 0.0);
+        assert_eq!(VoxelModelEditMaskLayer::brush_falloff(0.0, 2.0), 1.0);
+    }
+}
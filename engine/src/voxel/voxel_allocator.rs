@@ -1,5 +1,5 @@
 use crate::graphics::{
-    backend::{Buffer, ResourceId},
+    backend::{Buffer, GfxWriteGroupHandle, ResourceId},
     device::GfxDevice,
     gpu_allocator::{Allocation, GpuBufferAllocator},
 };
@@ -178,6 +178,44 @@ impl VoxelDataAllocator {
         allocator.write_allocation_data(device, &allocation.as_buffer_allocation(), data);
     }
 
+    pub fn write_allocation_data_at(
+        &mut self,
+        device: &mut GfxDevice,
+        allocation: &VoxelDataAllocation,
+        offset: u64,
+        data: &[u8],
+    ) {
+        let allocator = self
+            .allocators
+            .get_mut(allocation.buffer_index() as usize)
+            .unwrap();
+        allocator.write_allocation_data_at(
+            device,
+            &allocation.as_buffer_allocation(),
+            offset,
+            data,
+        );
+    }
+
+    pub fn write_allocation_data_in_group(
+        &self,
+        write_group: &mut GfxWriteGroupHandle,
+        allocation: &VoxelDataAllocation,
+        offset: u64,
+        data: &[u8],
+    ) {
+        let allocator = self
+            .allocators
+            .get(allocation.buffer_index() as usize)
+            .unwrap();
+        allocator.write_allocation_data_in_group(
+            write_group,
+            &allocation.as_buffer_allocation(),
+            offset,
+            data,
+        );
+    }
+
     pub fn free(&mut self, allocation: &VoxelDataAllocation) {
         self.allocators
             .get_mut(allocation.buffer_index() as usize)
@@ -97,6 +97,8 @@ pub struct VoxelModelRegistry {
     /// Essentially allows for caching and reuse of model data between entities.
     pub static_asset_models: HashMap<GameAssetPath, VoxelModelId>,
     loading_static_model_handles: HashMap<GameAssetPath, AssetHandle>,
+    /// Kept to replace the model when its asset is reloaded.
+    loaded_static_model_handles: HashMap<GameAssetPath, AssetHandle>,
     failed_loading_paths: HashSet<GameAssetPath>,
     to_load_static_asset_models: HashSet<GameAssetPath>,
 }
@@ -111,6 +113,7 @@ impl VoxelModelRegistry {
 
             static_asset_models: HashMap::new(),
             loading_static_model_handles: HashMap::new(),
+            loaded_static_model_handles: HashMap::new(),
             failed_loading_paths: HashSet::new(),
             to_load_static_asset_models: HashSet::new(),
         };
@@ -248,7 +251,7 @@ impl VoxelModelRegistry {
 
     pub fn handle_model_load_events(
         mut voxel_registry: ResMut<VoxelModelRegistry>,
-        events: Res<Events>,
+        mut events: ResMut<Events>,
         mut assets: ResMut<Assets>,
         mut ecs_world: ResMut<ECSWorld>,
     ) {
@@ -257,6 +260,28 @@ impl VoxelModelRegistry {
         };
         let voxel_registry = &mut voxel_registry as &mut VoxelModelRegistry;
 
+        // Static models whose asset changed on disk are replaced in place so every entity
+        // sharing the model picks up the change.
+        let reloaded_models = voxel_registry
+            .loaded_static_model_handles
+            .iter()
+            .filter(|(_, asset_handle)| assets.is_asset_reloaded(asset_handle))
+            .map(|(asset_path, asset_handle)| (asset_path.clone(), asset_handle.clone()))
+            .collect::<Vec<_>>();
+        for (asset_path, asset_handle) in reloaded_models {
+            let Some(voxel_model_id) = voxel_registry.static_asset_models.get(&asset_path).copied()
+            else {
+                continue;
+            };
+            let Some(asset) = assets.take_asset::<RVOXAsset>(&asset_handle) else {
+                continue;
+            };
+            *voxel_registry.get_model_mut::<VoxelModelSFTCompressed>(voxel_model_id) =
+                asset.sft_compressed;
+            events.push(VoxelModelEvent::UpdatedModel(voxel_model_id));
+            log::debug!("Reloaded static model {}.", asset_path.asset_path);
+        }
+
         for to_load_asset in voxel_registry.to_load_static_asset_models.drain() {
             assert!(
                 !voxel_registry
@@ -265,7 +290,7 @@ impl VoxelModelRegistry {
                 "Should only request load of an asset once."
             );
             let asset_path = to_load_asset.as_file_asset_path(&project_dir);
-            let asset_handle = assets.load_asset::<RVOXAsset>(asset_path);
+            let asset_handle = assets.load_watched_asset::<RVOXAsset>(asset_path);
             voxel_registry
                 .loading_static_model_handles
                 .insert(to_load_asset.clone(), asset_handle);
@@ -292,9 +317,13 @@ impl VoxelModelRegistry {
                     voxel_registry
                         .static_asset_models
                         .insert(asset_path.clone(), voxel_model_id);
+                    voxel_registry
+                        .loaded_static_model_handles
+                        .insert(asset_path.clone(), asset_handle.clone());
                     log::debug!("Loaded static model {}.", asset_path.asset_path);
                 }
                 AssetStatus::NotFound => {
+                    assets.unwatch_asset(asset_handle);
                     voxel_registry
                         .loading_static_model_handles
                         .remove(asset_path);
@@ -318,6 +347,7 @@ impl VoxelModelRegistry {
                         asset_path.asset_path,
                         error
                     );
+                    assets.unwatch_asset(asset_handle);
                 }
             }
         }
@@ -347,6 +377,36 @@ impl VoxelModelRegistry {
         VoxelModelId::new(voxel_id)
     }
 
+    /// Drops the model's data, the id is invalid afterwards. Any gpu representation must be
+    /// unloaded separately through `VoxelModelRegistryGpu::unload_gpu_model`.
+    pub fn unregister_voxel_model(&mut self, voxel_model_id: VoxelModelId) {
+        let info = self.voxel_model_info.remove(voxel_model_id.handle);
+        let data = self
+            .voxel_model_data
+            .get_mut(&info.model_type_id)
+            .expect("Given id doesn't exist since its type id doesnt exist in the data vec.");
+        let last_index = data.len() as u64 - 1;
+        data.swap_remove(info.index as usize);
+
+        // The last model of this type was moved into the removed slot.
+        if info.index != last_index {
+            let (_, moved_info) = self
+                .voxel_model_info
+                .iter_with_handle_mut()
+                .find(|(_, other)| {
+                    other.model_type_id == info.model_type_id && other.index == last_index
+                })
+                .expect("Moved voxel model should have an info entry.");
+            moved_info.index = info.index;
+        }
+
+        if let Some(asset_path) = info.asset_path {
+            if self.static_asset_models.get(&asset_path) == Some(&voxel_model_id) {
+                self.static_asset_models.remove(&asset_path);
+            }
+        }
+    }
+
     pub fn get_model<'a, T: VoxelModelImpl>(&'a self, id: VoxelModelId) -> &'a T {
         let info = self
             .voxel_model_info
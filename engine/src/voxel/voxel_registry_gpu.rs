@@ -6,7 +6,7 @@ use rogue_macros::Resource;
 
 use crate::graphics::backend::{Buffer, ResourceId};
 use crate::graphics::device::DeviceResource;
-use crate::graphics::gpu_allocator::GpuBufferAllocator;
+use crate::graphics::gpu_allocator::{Allocation, GpuBufferAllocator};
 use crate::material::material_bank::MaterialBank;
 use crate::material::material_gpu::MaterialBankGpu;
 use crate::resource::{Res, ResMut};
@@ -24,6 +24,7 @@ use crate::world::terrain::region_map::ChunkId;
 struct VoxelModelGpuInfo {
     gpu_model: Box<dyn VoxelModelGpuImplMethods>,
     gpu_model_ptr: Option<u32>,
+    info_allocation: Option<Allocation>,
 }
 
 pub struct VoxelModelGpuInvalidationInfo {
@@ -98,6 +99,24 @@ impl VoxelModelRegistryGpu {
         self.to_allocate_models.push(voxel_model_id);
     }
 
+    /// Frees the model's gpu info and voxel data, does nothing if the model was never loaded.
+    pub fn unload_gpu_model(&mut self, voxel_model_id: VoxelModelId) {
+        self.to_allocate_models.retain(|id| *id != voxel_model_id);
+        self.to_update_models.retain(|id| *id != voxel_model_id);
+        self.to_invalidate_models
+            .retain(|info| info.model_id != voxel_model_id);
+
+        let Some(mut gpu_model_info) = self.gpu_models.remove(&voxel_model_id) else {
+            return;
+        };
+        if let Some(info_allocation) = gpu_model_info.info_allocation.take() {
+            self.voxel_model_info_allocator.free(&info_allocation);
+        }
+        gpu_model_info
+            .gpu_model
+            .deallocate(&mut self.voxel_data_allocator);
+    }
+
     pub fn mark_gpu_model_update(&mut self, voxel_model_id: &VoxelModelId) {
         assert!(self.gpu_models.contains_key(voxel_model_id));
         self.to_update_models.push(*voxel_model_id);
@@ -159,6 +178,7 @@ impl VoxelModelRegistryGpu {
                 VoxelModelGpuInfo {
                     gpu_model: construct_fn(),
                     gpu_model_ptr: None,
+                    info_allocation: None,
                 },
             );
         }
@@ -200,12 +220,8 @@ impl VoxelModelRegistryGpu {
         needs_info_allocation |= gpu_model_info.gpu_model_ptr.is_none();
 
         if needs_info_allocation {
-            if let Some(old_ptr) = gpu_model_info.gpu_model_ptr {
-                // TODO: Deallocate old info.
-                log::debug!(
-                    "New allocation requested with old ptr pointing at {}",
-                    old_ptr
-                );
+            if let Some(old_allocation) = gpu_model_info.info_allocation.take() {
+                self.voxel_model_info_allocator.free(&old_allocation);
             }
             let allocation_size = (model_info_gpu_repr.len() + 1) as u64 * 4;
             let info_allocation = self
@@ -220,6 +236,7 @@ impl VoxelModelRegistryGpu {
                 bytemuck::cast_slice(&data),
             );
             gpu_model_info.gpu_model_ptr = Some(info_allocation.start_index_stride_dword() as u32);
+            gpu_model_info.info_allocation = Some(info_allocation);
         }
 
         // Write model render data, models have their own update tracking state within
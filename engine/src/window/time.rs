@@ -7,13 +7,19 @@ use std::{
 use log::debug;
 use rogue_macros::Resource;
 
-use crate::resource::ResMut;
+use crate::{
+    resource::{Res, ResMut},
+    settings::Settings,
+};
 
 #[derive(Resource)]
 pub struct Time {
     delta_time: Duration,
     last_time: Instant,
     frame_count: u32,
+    /// Advances time by this much every frame instead of the wall clock, so frames are
+    /// reproducible.
+    fixed_delta_time: Option<Duration>,
 
     start_time: Instant,
 }
@@ -24,18 +30,59 @@ impl Time {
             delta_time: Duration::ZERO,
             last_time: Instant::now(),
             frame_count: 0,
+            fixed_delta_time: None,
 
             start_time: Instant::now(),
         }
     }
 
+    /// How long sleeping stops short of the frame deadline, the rest is busy waited since the
+    /// OS scheduler can oversleep by a millisecond or more.
+    const FRAME_LIMIT_SPIN_DURATION: Duration = Duration::from_millis(2);
+
     pub fn update(mut time: ResMut<Time>) {
-        let curr_time = Instant::now();
+        let curr_time = match time.fixed_delta_time {
+            Some(fixed_delta_time) => time.last_time + fixed_delta_time,
+            None => Instant::now(),
+        };
         time.delta_time = curr_time - time.last_time;
         time.last_time = curr_time;
         time.frame_count += 1;
     }
 
+    pub fn set_fixed_delta_time(&mut self, fixed_delta_time: Option<Duration>) {
+        self.fixed_delta_time = fixed_delta_time;
+    }
+
+    /// Waits out the rest of the frame so frames start at most `Settings::frame_rate_cap`
+    /// times a second, regardless of the swapchain present mode.
+    pub fn limit_frame_rate(time: Res<Time>, settings: Res<Settings>) {
+        // The browser already paces frames and can't be blocked, and a fixed delta time isn't
+        // tied to the wall clock.
+        if cfg!(target_arch = "wasm32") || time.fixed_delta_time.is_some() {
+            return;
+        }
+        let Some(frame_interval) = Self::frame_interval(settings.frame_rate_cap) else {
+            return;
+        };
+        Self::wait_until(time.last_time + frame_interval);
+    }
+
+    /// The minimum time between frame starts, None when uncapped.
+    fn frame_interval(frame_rate_cap: u32) -> Option<Duration> {
+        (frame_rate_cap > 0).then(|| Duration::from_secs_f64(1.0 / frame_rate_cap as f64))
+    }
+
+    fn wait_until(deadline: Instant) {
+        let remaining = deadline - Instant::now();
+        if remaining > Self::FRAME_LIMIT_SPIN_DURATION {
+            std::thread::sleep(remaining - Self::FRAME_LIMIT_SPIN_DURATION);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+
     pub fn delta_time(&self) -> Duration {
         self.delta_time
     }
@@ -181,3 +228,38 @@ impl Drop for Stopwatch {
         log::info!("Stopwatch {}: Dropped in {:?}.", self.name, self.elapsed());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resource::ResourceBank;
+
+    #[test]
+    fn frame_limiter_waits_until_deadline() {
+        assert_eq!(Time::frame_interval(0), None);
+        assert_eq!(Time::frame_interval(50), Some(Duration::from_millis(20)));
+
+        let deadline = Instant::now() + Duration::from_millis(5);
+        Time::wait_until(deadline);
+        assert!(Instant::now() >= deadline);
+    }
+
+    #[test]
+    fn fixed_delta_time_ignores_wall_clock() {
+        let mut rb = ResourceBank::new();
+        let mut time = Time::new();
+        time.set_fixed_delta_time(Some(Duration::from_millis(16)));
+        let start_time = time.curr_time();
+        rb.insert(time);
+
+        for _ in 0..3 {
+            rb.run_system(Time::update);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let time = rb.get_resource::<Time>();
+        assert_eq!(time.delta_time(), Duration::from_millis(16));
+        assert_eq!(time.curr_time() - start_time, Duration::from_millis(48));
+        assert_eq!(time.frame_count(), 3);
+    }
+}
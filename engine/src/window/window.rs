@@ -3,7 +3,7 @@ use nalgebra::Vector2;
 use rogue_macros::Resource;
 use winit::{
     self,
-    dpi::{LogicalSize, PhysicalSize},
+    dpi::{LogicalSize, PhysicalPosition, PhysicalSize},
     event_loop,
     window::{Window as WinitWindow, WindowAttributes},
 };
@@ -37,7 +37,11 @@ pub struct Window {
     winit_window: WindowHandle,
     is_first_frame: bool,
     cursor_locked: bool,
-    locked_cursor_position: Option<(i32, i32)>,
+    cursor_visible: bool,
+    /// The last cursor position reported by the window, None until the cursor enters it.
+    cursor_position: Option<PhysicalPosition<f64>>,
+    /// Where the cursor was when it got locked, restored when it is unlocked.
+    locked_cursor_position: Option<PhysicalPosition<f64>>,
 }
 
 impl raw_window_handle::HasDisplayHandle for Window {
@@ -57,10 +61,13 @@ impl raw_window_handle::HasWindowHandle for Window {
 }
 
 impl Window {
-    pub fn new(event_loop: &winit::event_loop::ActiveEventLoop) -> Self {
+    /// Hidden windows are for headless rendering, where the window only exists for the event
+    /// loop and the systems reading it.
+    pub fn new(event_loop: &winit::event_loop::ActiveEventLoop, visible: bool) -> Self {
         let mut window_attrs = WindowAttributes::default()
             .with_title("Rogue")
-            .with_resizable(true);
+            .with_resizable(true)
+            .with_visible(visible);
         cfg_if::cfg_if! {
             if #[cfg(target_arch = "wasm32")] {
                 use wasm_bindgen::JsCast;
@@ -85,6 +92,8 @@ impl Window {
             winit_window: WindowHandle::new(winit_window),
             is_first_frame: true,
             cursor_locked: false,
+            cursor_visible: true,
+            cursor_position: None,
             locked_cursor_position: None,
         }
     }
@@ -105,6 +114,24 @@ impl Window {
         if self.cursor_locked {}
     }
 
+    /// Tracks the cursor so it can be restored after being locked, called before the app's
+    /// window event callback so positions aren't offset by the editor's viewport.
+    pub fn handle_winit_window_event(&mut self, event: &winit::event::WindowEvent) {
+        match event {
+            winit::event::WindowEvent::CursorMoved { position, .. } => {
+                // Locked cursors may still report movement while confined on x11.
+                if !self.cursor_locked {
+                    self.cursor_position = Some(*position);
+                }
+            }
+            // The compositor releases the grab when the window loses focus.
+            winit::event::WindowEvent::Focused(true) if self.cursor_locked => {
+                self.set_cursor_grabbed(true);
+            }
+            _ => {}
+        }
+    }
+
     pub fn inner_size_vec2(&self) -> Vector2<u32> {
         Vector2::new(self.width(), self.height())
     }
@@ -128,9 +155,29 @@ impl Window {
         }
     }
 
+    /// Locks the cursor to the window and hides it, read `Input::mouse_delta` for the relative
+    /// motion while locked. Unlocking restores the cursor's prior position and visibility.
     pub fn set_cursor_lock(&mut self, locked: bool) {
-        self.set_cursor_visible(!locked);
-        self.set_cursor_grabbed(locked);
+        if locked == self.cursor_locked {
+            return;
+        }
+
+        if locked {
+            self.locked_cursor_position = self.cursor_position;
+            self.winit_window.set_cursor_visible(false);
+            self.set_cursor_grabbed(true);
+        } else {
+            self.set_cursor_grabbed(false);
+            // Wayland can't warp the cursor but it also never moved while locked, x11 only
+            // supports confining so it has to be moved back.
+            if let Some(position) = self.locked_cursor_position.take() {
+                if let Err(err) = self.winit_window.set_cursor_position(position) {
+                    debug!("Couldn't restore the cursor position: {}", err);
+                }
+                self.cursor_position = Some(position);
+            }
+            self.winit_window.set_cursor_visible(self.cursor_visible);
+        }
         self.cursor_locked = locked;
     }
 
@@ -144,8 +191,12 @@ impl Window {
         self.winit_window.set_cursor(icon);
     }
 
-    pub fn set_cursor_visible(&self, visible: bool) {
-        self.winit_window.set_cursor_visible(visible);
+    /// Applied once the cursor is unlocked if it is currently locked.
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+        if !self.cursor_locked {
+            self.winit_window.set_cursor_visible(visible);
+        }
     }
 
     pub fn set_visible(&self, visible: bool) {
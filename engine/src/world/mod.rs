@@ -9,4 +9,5 @@ pub mod sky;
 pub mod terrain;
 pub mod world_entities;
 pub mod world_entities_gpu;
+pub mod world_origin;
 pub mod world_streaming;
@@ -1,15 +1,26 @@
+use std::time::Duration;
+
+use nalgebra::Vector2;
 use rogue_macros::Resource;
 
 use crate::{
+    asset::repr::settings::UserSettingsAsset,
     graphics::{
-        backend::{GraphicsBackendRecorder, Image},
+        backend::{
+            GfxImageCreateInfo, GfxImageFormat, GfxImageType, GfxImageWrite,
+            GraphicsBackendRecorder, Image, ResourceId,
+        },
+        blue_noise,
+        device::DeviceResource,
         frame_graph::{
             FrameGraphBuilder, FrameGraphComputeInfo, FrameGraphContext, FrameGraphResource,
             IntoFrameGraphResource, Pass,
         },
-        renderer::Renderer,
+        renderer::{Antialiasing, Renderer},
     },
     resource::{Res, ResMut},
+    settings::Settings,
+    window::time::Time,
 };
 
 struct WorldRTPassGraphConstants {
@@ -26,11 +37,80 @@ pub enum ShadingMode {
     Lambert = 2,
 }
 
+/// Tries each candidate workgroup size for a few frames and picks the one with the lowest
+/// average gpu time of the rt pass on the current device.
+pub struct WorkgroupSizeTuner {
+    candidate_index: usize,
+    frames_measured: u32,
+    accumulated_time: Duration,
+    best: Option<(Vector2<u32>, Duration)>,
+}
+
+impl WorkgroupSizeTuner {
+    pub const CANDIDATES: [[u32; 2]; 5] = [[8, 8], [16, 8], [8, 16], [16, 16], [32, 4]];
+    /// Frames skipped after switching sizes so pipeline compilation doesn't skew the results.
+    const WARMUP_FRAMES: u32 = 10;
+    const SAMPLE_FRAMES: u32 = 30;
+
+    pub fn new() -> Self {
+        Self {
+            candidate_index: 0,
+            frames_measured: 0,
+            accumulated_time: Duration::ZERO,
+            best: None,
+        }
+    }
+
+    pub fn current_candidate(&self) -> Vector2<u32> {
+        Vector2::from(Self::CANDIDATES[self.candidate_index])
+    }
+
+    /// Records a frame for the current candidate, returns the chosen size once every candidate
+    /// has been measured.
+    pub fn record_frame(&mut self, pass_time: Duration) -> Option<Vector2<u32>> {
+        self.frames_measured += 1;
+        if self.frames_measured <= Self::WARMUP_FRAMES {
+            return None;
+        }
+        self.accumulated_time += pass_time;
+        if self.frames_measured < Self::WARMUP_FRAMES + Self::SAMPLE_FRAMES {
+            return None;
+        }
+
+        let average = self.accumulated_time / Self::SAMPLE_FRAMES;
+        let candidate = self.current_candidate();
+        log::debug!(
+            "World rt pass workgroup size {}x{} averaged {:.3}ms.",
+            candidate.x,
+            candidate.y,
+            average.as_secs_f32() * 1000.0
+        );
+        if self.best.map_or(true, |(_, best_time)| average < best_time) {
+            self.best = Some((candidate, average));
+        }
+
+        self.candidate_index += 1;
+        self.frames_measured = 0;
+        self.accumulated_time = Duration::ZERO;
+        if self.candidate_index == Self::CANDIDATES.len() {
+            return self.best.map(|(size, _)| size);
+        }
+        None
+    }
+}
+
 #[derive(Resource)]
 pub struct WorldRTPass {
     pub shading_mode: ShadingMode,
     graph_framebuffer: Option<FrameGraphResource<Image>>,
     graph_framebuffer_depth: Option<FrameGraphResource<Image>>,
+    /// The workgroup size the rt compute pipeline is currently specialized with.
+    workgroup_size: Option<Vector2<u32>>,
+    workgroup_size_tuner: Option<WorkgroupSizeTuner>,
+    /// Rotates the soft shadow ray pattern per pixel, created on the first frame.
+    blue_noise: Option<ResourceId<Image>>,
+    /// Advances every frame with taa so the shadow rays differ between accumulated frames.
+    shadow_noise_frame: u32,
 }
 
 impl WorldRTPass {
@@ -43,12 +123,122 @@ impl WorldRTPass {
         },
     };
 
+    const BLUE_NOISE_SIZE: u32 = 64;
+
     pub fn new() -> Self {
         Self {
             shading_mode: ShadingMode::Lambert,
             graph_framebuffer: None,
             graph_framebuffer_depth: None,
+            workgroup_size: None,
+            workgroup_size_tuner: Some(WorkgroupSizeTuner::new()),
+            blue_noise: None,
+            shadow_noise_frame: 0,
+        }
+    }
+
+    fn create_blue_noise_image(device: &mut DeviceResource) -> ResourceId<Image> {
+        let size = Self::BLUE_NOISE_SIZE;
+        let image = device.create_image(GfxImageCreateInfo {
+            name: "rt_blue_noise".to_owned(),
+            image_type: GfxImageType::D2,
+            format: GfxImageFormat::Rgba8Unorm,
+            extent: Vector2::new(size, size),
+        });
+        let data = blue_noise::generate_blue_noise(size, 0)
+            .into_iter()
+            .flat_map(|x| [(x * 256.0) as u8, 0, 0, 255])
+            .collect::<Vec<u8>>();
+        device.write_image(GfxImageWrite {
+            image,
+            data: &data,
+            offset: Vector2::zeros(),
+            extent: Vector2::new(size, size),
+            layer: 0,
+        });
+        image
+    }
+
+    /// The workgroup size the rt compute pipeline is specialized with, this changes while tuning.
+    pub fn workgroup_size(&self) -> Option<Vector2<u32>> {
+        self.workgroup_size
+    }
+
+    /// Specializes the rt compute pipeline's workgroup size, either with the override in the
+    /// graphics settings, the size saved by a previous tuning, or by auto-tuning over the first
+    /// few hundred frames. The tuned size is saved to the user settings.
+    pub fn update_workgroup_size(
+        mut rt_pass: ResMut<WorldRTPass>,
+        mut renderer: ResMut<Renderer>,
+        mut settings: ResMut<Settings>,
+        time: Res<Time>,
+    ) {
+        let rt_pass = &mut *rt_pass;
+        let target_size = if let Some(override_size) = settings.graphics.rt_workgroup_size_override
+        {
+            if rt_pass.workgroup_size_tuner.take().is_some()
+                || rt_pass.workgroup_size != Some(override_size)
+            {
+                log::info!(
+                    "Using world rt pass workgroup size {}x{} from settings override.",
+                    override_size.x,
+                    override_size.y
+                );
+            }
+            override_size
+        } else if let Some(tuned_size) = settings.graphics.rt_workgroup_size {
+            if rt_pass.workgroup_size_tuner.take().is_some() {
+                log::info!(
+                    "Using world rt pass workgroup size {}x{} saved from a previous tuning.",
+                    tuned_size.x,
+                    tuned_size.y
+                );
+            }
+            tuned_size
+        } else if let Some(tuner) = &mut rt_pass.workgroup_size_tuner {
+            // Fall back to the frame time when the device doesn't support timestamp queries.
+            let pass_time = renderer
+                .last_pass_timings()
+                .iter()
+                .find(|timing| timing.name == Self::GRAPH.rt_pass_name)
+                .map_or(time.delta_time(), |timing| timing.duration);
+            match tuner.record_frame(pass_time) {
+                Some(chosen_size) => {
+                    log::info!(
+                        "Selected world rt pass workgroup size {}x{} for this device.",
+                        chosen_size.x,
+                        chosen_size.y
+                    );
+                    rt_pass.workgroup_size_tuner = None;
+                    settings.graphics.rt_workgroup_size = Some(chosen_size);
+                    let mut user_settings = UserSettingsAsset::load_user_settings();
+                    user_settings.rt_workgroup_size = Some(chosen_size);
+                    user_settings.save_user_settings();
+                    chosen_size
+                }
+                None => tuner.current_candidate(),
+            }
+        } else {
+            let Some(size) = rt_pass.workgroup_size else {
+                return;
+            };
+            size
+        };
+
+        if rt_pass.workgroup_size == Some(target_size) {
+            return;
         }
+        rt_pass.workgroup_size = Some(target_size);
+        renderer.set_compute_pipeline_define(
+            Self::GRAPH.rt_compute_pipeline_name,
+            "RT_WORKGROUP_SIZE_X",
+            target_size.x,
+        );
+        renderer.set_compute_pipeline_define(
+            Self::GRAPH.rt_compute_pipeline_name,
+            "RT_WORKGROUP_SIZE_Y",
+            target_size.y,
+        );
     }
 
     /// Adds the rt pass for rendering the world (terrain and entities).
@@ -81,7 +271,13 @@ impl WorldRTPass {
         pass
     }
 
-    pub fn write_graph_rt_pass(rt_pass: Res<WorldRTPass>, mut renderer: ResMut<Renderer>) {
+    pub fn write_graph_rt_pass(
+        mut rt_pass: ResMut<WorldRTPass>,
+        mut renderer: ResMut<Renderer>,
+        mut device: ResMut<DeviceResource>,
+        settings: Res<Settings>,
+    ) {
+        let rt_pass = &mut *rt_pass;
         let framebuffer_image_handle = rt_pass.graph_framebuffer.as_ref().expect(
             "Should not be writing egui pass without setting it up in the render graph first.",
         );
@@ -89,6 +285,26 @@ impl WorldRTPass {
             "Should not be writing egui pass without setting it up in the render graph first.",
         );
         let shading_mode = rt_pass.shading_mode;
+
+        let blue_noise = *rt_pass
+            .blue_noise
+            .get_or_insert_with(|| Self::create_blue_noise_image(&mut device));
+        let graphics = &settings.graphics;
+        let soft_shadow_samples = if graphics.soft_shadows {
+            graphics.soft_shadow_samples.max(1)
+        } else {
+            0
+        };
+        let sun_angular_size = graphics.sun_angular_size.max(0.0);
+        let ao_strength = graphics.voxel_ao_strength.clamp(0.0, 1.0);
+        // Without taa to accumulate, changing the pattern every frame would only flicker.
+        if graphics.antialiasing == Antialiasing::TAA {
+            rt_pass.shadow_noise_frame = rt_pass.shadow_noise_frame.wrapping_add(1);
+        } else {
+            rt_pass.shadow_noise_frame = 0;
+        }
+        let shadow_noise_frame = rt_pass.shadow_noise_frame;
+
         renderer.frame_graph_executor.supply_pass_ref(
             Self::GRAPH.rt_pass_name,
             &mut |recorder: &mut dyn GraphicsBackendRecorder, ctx: &FrameGraphContext<'_>| {
@@ -107,6 +323,12 @@ impl WorldRTPass {
                     writer.write_binding("u_shader.backbuffer", framebuffer_image);
                     writer.write_binding("u_shader.backbuffer_depth", framebuffer_depth);
                     writer.write_uniform::<u32>("u_shader.shading_mode", shading_mode as u32);
+                    writer.write_binding("u_shader.blue_noise", blue_noise);
+                    writer
+                        .write_uniform::<u32>("u_shader.soft_shadow_samples", soft_shadow_samples);
+                    writer.write_uniform::<f32>("u_shader.sun_angular_size", sun_angular_size);
+                    writer.write_uniform::<u32>("u_shader.shadow_noise_frame", shadow_noise_frame);
+                    writer.write_uniform::<f32>("u_shader.ao_strength", ao_strength);
                 });
 
                 compute_pass.dispatch(
@@ -1,55 +1,104 @@
-use std::time::Duration;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_3, PI, TAU};
 
 use nalgebra::Vector3;
 use rogue_macros::Resource;
 
 use crate::{
+    common::color::Color,
     resource::{Res, ResMut},
     window::time::Time,
 };
 
-#[derive(Resource)]
-pub struct Sky {
-    pub time_of_day_secs: f32,
+/// The sun and the gradient sky lighting the world, saved with the project so scenes keep
+/// their lighting. Colors are srgb like everywhere else and uploaded in linear srgb.
+#[derive(Resource, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SkyLight {
+    /// Radians around the up axis, zero points the sun toward +z.
+    pub sun_azimuth: f32,
+    /// Radians above the horizon, the moon lights the world opposite the sun while this is
+    /// negative.
+    pub sun_elevation: f32,
+    pub sun_color: Color,
+    pub sun_intensity: f32,
+    /// The sky color looking straight up, blending toward `horizon_color` looking down.
+    pub zenith_color: Color,
+    pub horizon_color: Color,
+    /// Rotates the sun elevation through a full day every `REAL_SECONDS_PER_DAY`.
     pub do_day_night_cycle: bool,
 }
 
-impl Sky {
-    pub const SECS_PER_DAY: f32 = 24.0 * 60.0 * 60.0;
-    pub const REAL_MINUTES_PER_DAY: f32 = 1.0;
-    pub const REAL_SECONDS_PER_DAY: f32 = Self::REAL_MINUTES_PER_DAY * 60.0;
-    pub const TIME_SCALE: f32 = Self::SECS_PER_DAY / Self::REAL_SECONDS_PER_DAY;
-
-    pub fn new() -> Self {
+impl Default for SkyLight {
+    fn default() -> Self {
         Self {
-            time_of_day_secs: 60.0 * 60.0 * 12.0,
+            sun_azimuth: -FRAC_PI_2,
+            sun_elevation: FRAC_PI_3,
+            sun_color: Color::new_srgb(1.0, 0.98, 0.95),
+            sun_intensity: 1.0,
+            zenith_color: Color::new_srgb(0.6, 0.73, 0.88),
+            horizon_color: Color::new_srgb(0.4, 0.5, 0.75),
             do_day_night_cycle: false,
         }
     }
+}
 
-    pub fn update_time(mut sky: ResMut<Sky>, time: Res<Time>) {
-        if sky.do_day_night_cycle {
-            sky.time_of_day_secs += time.delta_time().as_secs_f32() * Self::TIME_SCALE;
-        }
-        sky.time_of_day_secs %= Self::SECS_PER_DAY;
-    }
+impl SkyLight {
+    pub const REAL_MINUTES_PER_DAY: f32 = 1.0;
+    pub const REAL_SECONDS_PER_DAY: f32 = Self::REAL_MINUTES_PER_DAY * 60.0;
 
-    fn sun_angle(&self) -> f32 {
-        // Night-time is half of daytime cause daytime is nice :)
-        // t-value of when night-time starts.
-        let night_time_factor = 0.75;
-        let t = self.time_of_day_secs / Self::SECS_PER_DAY;
-        if t < night_time_factor {
-            (t / night_time_factor) * std::f32::consts::PI
-        } else {
-            // Map [night_time_factor, 1] to [pi, 2 * pi]
-            let night_t = (t - night_time_factor) / (1.0 - night_time_factor);
-            night_t * std::f32::consts::PI + std::f32::consts::PI
+    pub fn update_day_night_cycle(mut sky_light: ResMut<SkyLight>, time: Res<Time>) {
+        if !sky_light.do_day_night_cycle {
+            return;
         }
+        let elevation = sky_light.sun_elevation
+            + time.delta_time().as_secs_f32() * TAU / Self::REAL_SECONDS_PER_DAY;
+        // Keep the elevation within [-pi, pi) so it stays editable.
+        sky_light.sun_elevation = (elevation + PI).rem_euclid(TAU) - PI;
     }
 
+    /// The normalized direction toward the sun.
     pub fn sun_dir(&self) -> Vector3<f32> {
-        let angle = self.sun_angle();
-        Vector3::new(angle.cos(), angle.sin(), 0.0)
+        let (elevation_sin, elevation_cos) = self.sun_elevation.sin_cos();
+        let (azimuth_sin, azimuth_cos) = self.sun_azimuth.sin_cos();
+        Vector3::new(
+            elevation_cos * azimuth_sin,
+            elevation_sin,
+            elevation_cos * azimuth_cos,
+        )
+    }
+
+    /// The linear srgb sun color scaled by its intensity.
+    pub fn sun_radiance(&self) -> Vector3<f32> {
+        self.sun_color.linear_rgb_vec() * self.sun_intensity.max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun_dir_follows_azimuth_and_elevation() {
+        let mut sky_light = SkyLight {
+            sun_azimuth: 0.0,
+            sun_elevation: 0.0,
+            ..SkyLight::default()
+        };
+        assert!((sky_light.sun_dir() - Vector3::z()).norm() < 1e-6);
+
+        sky_light.sun_azimuth = FRAC_PI_2;
+        assert!((sky_light.sun_dir() - Vector3::x()).norm() < 1e-6);
+
+        sky_light.sun_elevation = FRAC_PI_2;
+        assert!((sky_light.sun_dir() - Vector3::y()).norm() < 1e-6);
+
+        sky_light.sun_elevation = -0.3;
+        assert!(sky_light.sun_dir().y < 0.0);
+        assert!((sky_light.sun_dir().norm() - 1.0).abs() < 1e-6);
+
+        // Round trips through the project format.
+        let loaded =
+            serde_json::from_str::<SkyLight>(&serde_json::to_string(&sky_light).unwrap()).unwrap();
+        assert_eq!(loaded, sky_light);
     }
 }
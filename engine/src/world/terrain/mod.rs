@@ -8,3 +8,4 @@ pub mod region_map_gpu;
 pub mod region_pos;
 pub mod region_window_gpu;
 pub mod rmap_asset;
+pub mod terrain_generator;
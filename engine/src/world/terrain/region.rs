@@ -71,13 +71,16 @@ impl WorldRegion {
         voxel_registry: &VoxelModelRegistry,
         in_ray: &Ray,
         max_t: f32,
+        world_origin: RegionPos,
     ) -> Option<TerrainRaycastHit> {
         //
         // Most of this code is similar to SFTCompressed::trace but follows same logic as
         // the region in terrain.slang
         //
         let mut ray = in_ray.clone();
-        let min = self.region_pos.cast::<f32>() * consts::voxel::TERRAIN_REGION_METER_LENGTH;
+        // Subtract the origin before converting to floats so far regions keep their precision.
+        let min = (*self.region_pos - *world_origin).cast::<f32>()
+            * consts::voxel::TERRAIN_REGION_METER_LENGTH;
         let max = min.map(|x| x + consts::voxel::TERRAIN_REGION_METER_LENGTH);
         let aabb = &AABB::new_two_point(min, max);
         // Early exit if the ray doesn't intersect the bounding box of this model.
@@ -218,6 +221,38 @@ impl WorldRegion {
         return None;
     }
 
+    /// Every chunk in the region with chunk data, at any lod.
+    pub fn chunk_ids(&self) -> Vec<ChunkId> {
+        let mut chunk_ids = Vec::new();
+        // The node index, its height in the tree and the child indices traversed to reach it,
+        // which put together are the morton code of the node within the region.
+        let mut stack = vec![(0, 0, 0u64)];
+        while let Some((node_idx, height, morton)) = stack.pop() {
+            let node = &self.tree.nodes[node_idx];
+            if node.has_model_ptr() {
+                let chunk_lod = ChunkLOD::from_tree_height(height);
+                let local_chunk_pos = morton::morton_decode(morton) * chunk_lod.leaf_chunk_length();
+                chunk_ids.push(ChunkId {
+                    chunk_pos: self.region_pos.into_chunk_pos() + local_chunk_pos.cast::<i32>(),
+                    chunk_lod,
+                });
+            }
+            let Some(child_ptr) = node.child_ptr() else {
+                continue;
+            };
+            for child_index in 0..64u64 {
+                if node.child_mask & (1 << child_index) != 0 {
+                    stack.push((
+                        child_ptr as usize + child_index as usize,
+                        height + 1,
+                        (morton << 6) | child_index,
+                    ));
+                }
+            }
+        }
+        chunk_ids
+    }
+
     pub fn get_chunk_model(&self, chunk_id: ChunkId) -> Option<VoxelModelId> {
         self.get_chunk_data(chunk_id)
             .and_then(|chunk_data| chunk_data.model_id.clone())
@@ -488,3 +523,40 @@ impl WorldRegionNode {
         self.parent_ptr != u32::MAX
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::terrain::chunk_pos::ChunkPos;
+
+    #[test]
+    fn chunk_ids_finds_every_chunk_with_data() {
+        let region_pos = RegionPos::new(1, 0, -1);
+        let region_chunk_min = *region_pos.into_chunk_pos();
+        let chunk_ids = [
+            ChunkId {
+                chunk_pos: ChunkPos::new(region_chunk_min + Vector3::new(5, 2, 63)),
+                chunk_lod: ChunkLOD::FULL_RES_LOD,
+            },
+            ChunkId {
+                chunk_pos: ChunkPos::new(region_chunk_min + Vector3::new(5, 3, 63)),
+                chunk_lod: ChunkLOD::FULL_RES_LOD,
+            },
+            ChunkId {
+                chunk_pos: ChunkPos::new(region_chunk_min + Vector3::new(32, 16, 0)),
+                chunk_lod: ChunkLOD::new(2),
+            },
+        ];
+
+        let mut region = WorldRegion::new_empty(region_pos);
+        assert!(region.chunk_ids().is_empty());
+        for (i, chunk_id) in chunk_ids.iter().enumerate() {
+            region.set_chunk_data(chunk_id, Some(WorldChunkData::from_io_ptr(i as u64)));
+        }
+        let found = region.chunk_ids();
+        assert_eq!(found.len(), chunk_ids.len());
+        for chunk_id in &chunk_ids {
+            assert!(found.contains(chunk_id), "{:?}", chunk_id);
+        }
+    }
+}
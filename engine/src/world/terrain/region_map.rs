@@ -17,13 +17,19 @@ use crate::{
     asset::asset::GameAssetPath,
     common::geometry::ray::RayDDA,
     resource::ResMut,
-    voxel::{voxel::VoxelModelEditMask, voxel_registry::VoxelModelRegistry},
+    voxel::{
+        voxel::VoxelModelEditMask, voxel_registry::VoxelModelRegistry,
+        voxel_registry_gpu::VoxelModelRegistryGpu,
+    },
 };
 use crate::{
     asset::asset::{AssetHandle, AssetPath, AssetStatus, Assets},
     voxel::voxel::VoxelModelEditOperator,
 };
-use crate::{common::geometry::ray::Ray, consts};
+use crate::{
+    common::geometry::{aabb::AABB, ray::Ray},
+    consts,
+};
 use crate::{
     common::morton,
     event::Events,
@@ -31,7 +37,7 @@ use crate::{
 };
 use crate::{
     event::EventReader,
-    voxel::voxel::{VoxelModelEditMaskLayer, VoxelModelTrace},
+    voxel::voxel::{VoxelEditMirror, VoxelMaterialData, VoxelModelEditMaskLayer, VoxelModelTrace},
 };
 use crate::{voxel::attachment::Attachment, world::terrain::chunk_pos::ChunkPos};
 use crate::{voxel::voxel::VoxelModelEdit, world::terrain::chunk_lod::ChunkLOD};
@@ -124,7 +130,10 @@ impl VoxelTerrainEditMaskLayer {
         let mut s = self.0.clone();
         match &mut s {
             VoxelModelEditMaskLayer::Presence => {}
-            VoxelModelEditMaskLayer::Sphere { center, diameter } => *center -= chunk_voxel_pos,
+            VoxelModelEditMaskLayer::Sphere { center, .. }
+            | VoxelModelEditMaskLayer::Cube { center, .. }
+            | VoxelModelEditMaskLayer::Cylinder { center, .. } => *center -= chunk_voxel_pos,
+            VoxelModelEditMaskLayer::Voxels { offset, .. } => *offset += chunk_voxel_pos,
         }
         return s;
     }
@@ -136,6 +145,25 @@ pub struct VoxelTerrainEdit {
     pub operator: VoxelModelEditOperator,
 }
 
+impl VoxelTerrainEdit {
+    /// The edit mirrored across the world voxel planes of `mirror`.
+    pub fn mirrored(&self, mirror: &VoxelEditMirror) -> Self {
+        let (min, max) = mirror.mirror_rect(&self.region.min, &self.region.max);
+        Self {
+            region: VoxelTerrainRegion::new_rect(min, max),
+            mask: VoxelTerrainEditMask {
+                layers: self
+                    .mask
+                    .layers
+                    .iter()
+                    .map(|layer| VoxelTerrainEditMaskLayer(layer.0.mirrored(mirror)))
+                    .collect(),
+            },
+            operator: self.operator.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct ChunkId {
     pub chunk_pos: ChunkPos,
@@ -196,10 +224,15 @@ pub struct RegionMap {
 
     pub to_set_chunk_sfts: HashMap<RegionPos, Vec<(ChunkId, Option<VoxelModelId>)>>,
     pub to_apply_edits: Vec<VoxelTerrainEdit>,
+    /// Chunk models replaced by `set_chunk`, freed once nothing can point to them anymore.
+    to_release_chunk_models: Vec<VoxelModelId>,
 
     pub used_materials: HashSet<GameAssetPath>,
 
     pub disk: Option<RegionMapDisk>,
+
+    /// The region of the `WorldOrigin` which world space positions are relative to.
+    world_origin: RegionPos,
 }
 
 impl RegionMap {
@@ -215,12 +248,33 @@ impl RegionMap {
 
             to_set_chunk_sfts: HashMap::new(),
             to_apply_edits: Vec::new(),
+            to_release_chunk_models: Vec::new(),
             disk: None,
             used_materials: HashSet::new(),
+            world_origin: RegionPos::zeros(),
         }
     }
 
-    /// Returns the world voxel that was hit.
+    pub fn set_world_origin(&mut self, world_origin: RegionPos) {
+        self.world_origin = world_origin;
+    }
+
+    pub fn world_origin(&self) -> RegionPos {
+        self.world_origin
+    }
+
+    /// The world space bounds of the chunk, relative to the world origin.
+    pub fn chunk_aabb(&self, chunk_id: &ChunkId) -> AABB {
+        // Subtract the origin before converting to floats so far chunks keep their precision.
+        let min = (*chunk_id.chunk_pos - *self.world_origin.into_chunk_pos()).cast::<f32>()
+            * consts::voxel::TERRAIN_CHUNK_METER_LENGTH;
+        let length = chunk_id.chunk_lod.leaf_chunk_length() as f32
+            * consts::voxel::TERRAIN_CHUNK_METER_LENGTH;
+        AABB::new_two_point(min, min.add_scalar(length))
+    }
+
+    /// Returns the world voxel that was hit, `ray` is relative to the world origin while the
+    /// hit voxel position is absolute.
     pub fn raycast_terrain(
         &self,
         voxel_registry: &VoxelModelRegistry,
@@ -244,8 +298,10 @@ impl RegionMap {
             .map(|x| if x.is_infinite() { 0.0 } else { x.abs() });
         let mut traversed_distance = 0.0;
         while (traversed_distance * consts::voxel::TERRAIN_REGION_METER_LENGTH < max_t) {
-            if let Some(region) = self.get_region(&RegionPos::new_vec(curr_grid)) {
-                if let Some(res) = region.raycast_region(voxel_registry, ray, max_t) {
+            if let Some(region) = self.get_region(&(self.world_origin + curr_grid)) {
+                if let Some(res) =
+                    region.raycast_region(voxel_registry, ray, max_t, self.world_origin)
+                {
                     return Some(res);
                 }
             }
@@ -315,6 +371,25 @@ impl RegionMap {
         return chunk_data.and_then(|data| data.model_id);
     }
 
+    /// The voxel at the absolute world voxel position, None if it is empty or its chunk isn't
+    /// loaded.
+    pub fn get_voxel(
+        &self,
+        voxel_registry: &VoxelModelRegistry,
+        world_voxel_pos: &Vector3<i32>,
+    ) -> Option<VoxelMaterialData> {
+        let chunk_pos = ChunkPos::from_world_voxel_pos(world_voxel_pos);
+        let chunk_model_id = self.get_chunk_model(&ChunkId {
+            chunk_pos,
+            chunk_lod: ChunkLOD::FULL_RES_LOD,
+        })?;
+        let local_voxel_pos =
+            (world_voxel_pos - chunk_pos.get_min_world_voxel_pos()).map(|x| x as u32);
+        voxel_registry
+            .get_dyn_model(chunk_model_id)
+            .get_voxel(local_voxel_pos)
+    }
+
     /// Enqueues the chunk to be set, will be applied before rendering.
     pub fn set_chunk(&mut self, chunk_id: ChunkId, sft_id: Option<VoxelModelId>) {
         let region_pos = chunk_id.chunk_pos.get_region_pos();
@@ -362,7 +437,13 @@ impl RegionMap {
                 continue;
             }
             for (chunk_id, sft_id) in vec.drain(..) {
-                Self::set_chunk_unchecked(&mut region_map.regions, &chunk_id, sft_id);
+                let old_sft_id =
+                    Self::set_chunk_unchecked(&mut region_map.regions, &chunk_id, sft_id);
+                if let Some(old_sft_id) = old_sft_id
+                    && Some(old_sft_id) != sft_id
+                {
+                    region_map.to_release_chunk_models.push(old_sft_id);
+                }
                 region_map.chunk_events.push(ChunkEvent {
                     chunk_id,
                     event_type: if sft_id.is_some() {
@@ -379,6 +460,19 @@ impl RegionMap {
         }
     }
 
+    /// Frees the models of chunks which were regenerated so they don't pile up in the registry
+    /// and on the gpu.
+    pub fn release_replaced_chunk_models(
+        mut region_map: ResMut<RegionMap>,
+        mut voxel_registry: ResMut<VoxelModelRegistry>,
+        mut voxel_registry_gpu: ResMut<VoxelModelRegistryGpu>,
+    ) {
+        for model_id in region_map.to_release_chunk_models.drain(..) {
+            voxel_registry_gpu.unload_gpu_model(model_id);
+            voxel_registry.unregister_voxel_model(model_id);
+        }
+    }
+
     pub fn update_process_commands(
         mut region_map: ResMut<RegionMap>,
         mut assets: ResMut<Assets>,
@@ -0,0 +1,331 @@
+use nalgebra::Vector3;
+use rogue_macros::Resource;
+
+use crate::common::rng::RngStream;
+use crate::noise::{
+    fbm::{Fbm, FbmOptions},
+    perlin::PerlinNoise,
+};
+
+/// The noise layers terrain chunks are generated from, saved with the project. Generation is
+/// deterministic so the same settings always produce the same chunks.
+#[derive(Resource, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TerrainGeneratorSettings {
+    pub seed: u64,
+    /// Run in order on each voxel, later layers modify the result of earlier ones.
+    pub layers: Vec<TerrainNoiseLayer>,
+    /// The name of the material solid voxels use when no material band covers them.
+    pub base_material: String,
+}
+
+impl Default for TerrainGeneratorSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            layers: vec![
+                TerrainNoiseLayer::new_height_field(),
+                TerrainNoiseLayer::new_caves(),
+                TerrainNoiseLayer::MaterialBands {
+                    bands: vec![MaterialBand {
+                        min_height: 4.0,
+                        material: "Grass".to_owned(),
+                    }],
+                },
+            ],
+            base_material: "Dirt".to_owned(),
+        }
+    }
+}
+
+impl TerrainGeneratorSettings {
+    /// Every material name the layers reference, the base material first.
+    pub fn material_names(&self) -> Vec<&str> {
+        let mut names = vec![self.base_material.as_str()];
+        for layer in &self.layers {
+            let TerrainNoiseLayer::MaterialBands { bands } = layer else {
+                continue;
+            };
+            for band in bands {
+                if !names.contains(&band.material.as_str()) {
+                    names.push(&band.material);
+                }
+            }
+        }
+        names
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TerrainNoiseLayer {
+    /// Fills everything below `base_height` offset by up to `amplitude` meters of 2d fbm noise.
+    HeightField {
+        frequency: f32,
+        base_height: f32,
+        amplitude: f32,
+        fbm: FbmOptions,
+    },
+    /// Carves out solid voxels where 3d fbm noise is above `threshold`.
+    Caves {
+        frequency: f32,
+        threshold: f32,
+        fbm: FbmOptions,
+    },
+    /// Paints solid voxels at or above a band's `min_height` with its material, the highest
+    /// band a voxel is in wins.
+    MaterialBands { bands: Vec<MaterialBand> },
+}
+
+impl TerrainNoiseLayer {
+    pub fn new_height_field() -> Self {
+        Self::HeightField {
+            frequency: 0.003,
+            base_height: 0.0,
+            amplitude: 16.0,
+            fbm: FbmOptions {
+                lacunarity: 1.7,
+                octaves: 8,
+                gain: 0.6,
+            },
+        }
+    }
+
+    pub fn new_caves() -> Self {
+        Self::Caves {
+            frequency: 0.01,
+            threshold: 0.4,
+            fbm: FbmOptions::default(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::HeightField { .. } => "Height field",
+            Self::Caves { .. } => "Caves",
+            Self::MaterialBands { .. } => "Material bands",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MaterialBand {
+    pub min_height: f32,
+    pub material: String,
+}
+
+/// `TerrainGeneratorSettings` with the noise of each layer created, ready to sample voxels.
+pub struct TerrainNoiseStack {
+    layers: Vec<TerrainNoiseStackLayer>,
+}
+
+enum TerrainNoiseStackLayer {
+    HeightField {
+        noise: Fbm<PerlinNoise>,
+        sample_offset: Vector3<f32>,
+        frequency: f32,
+        base_height: f32,
+        amplitude: f32,
+    },
+    Caves {
+        noise: Fbm<PerlinNoise>,
+        sample_offset: Vector3<f32>,
+        frequency: f32,
+        threshold: f32,
+    },
+    MaterialBands {
+        /// The min height and material index, sorted from the highest band down.
+        bands: Vec<(f32, u32)>,
+    },
+}
+
+impl TerrainNoiseStack {
+    pub fn new(settings: &TerrainGeneratorSettings) -> Self {
+        let material_names = settings.material_names();
+        // Each layer gets its own noise so stacking two of the same layer isn't redundant.
+        let mut rng = RngStream::new(settings.seed);
+        let mut next_noise = |fbm: &FbmOptions| {
+            let noise = Fbm::new(PerlinNoise::new(rng.next_u64()), fbm.clone());
+            let sample_offset = Vector3::new(
+                (rng.next_u32() % 25600) as f32 * 0.01,
+                (rng.next_u32() % 25600) as f32 * 0.01,
+                (rng.next_u32() % 25600) as f32 * 0.01,
+            );
+            (noise, sample_offset)
+        };
+
+        let layers = settings
+            .layers
+            .iter()
+            .map(|layer| match layer {
+                TerrainNoiseLayer::HeightField {
+                    frequency,
+                    base_height,
+                    amplitude,
+                    fbm,
+                } => {
+                    let (noise, sample_offset) = next_noise(fbm);
+                    TerrainNoiseStackLayer::HeightField {
+                        noise,
+                        sample_offset,
+                        frequency: *frequency,
+                        base_height: *base_height,
+                        amplitude: *amplitude,
+                    }
+                }
+                TerrainNoiseLayer::Caves {
+                    frequency,
+                    threshold,
+                    fbm,
+                } => {
+                    let (noise, sample_offset) = next_noise(fbm);
+                    TerrainNoiseStackLayer::Caves {
+                        noise,
+                        sample_offset,
+                        frequency: *frequency,
+                        threshold: *threshold,
+                    }
+                }
+                TerrainNoiseLayer::MaterialBands { bands } => {
+                    let mut bands = bands
+                        .iter()
+                        .map(|band| {
+                            let material_index = material_names
+                                .iter()
+                                .position(|name| *name == band.material)
+                                .unwrap() as u32;
+                            (band.min_height, material_index)
+                        })
+                        .collect::<Vec<_>>();
+                    bands.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+                    TerrainNoiseStackLayer::MaterialBands { bands }
+                }
+            })
+            .collect();
+
+        Self { layers }
+    }
+
+    /// The index into `TerrainGeneratorSettings::material_names()` of the voxel's material, None
+    /// if the voxel is empty.
+    pub fn sample(&self, world_voxel_pos: Vector3<f32>) -> Option<u32> {
+        let mut is_solid = false;
+        let mut material_index = 0;
+        for layer in &self.layers {
+            match layer {
+                TerrainNoiseStackLayer::HeightField {
+                    noise,
+                    sample_offset,
+                    frequency,
+                    base_height,
+                    amplitude,
+                } => {
+                    let sample_pos = world_voxel_pos * *frequency + sample_offset;
+                    let height =
+                        base_height + noise.noise_2d(sample_pos.x, sample_pos.z) * amplitude;
+                    is_solid |= world_voxel_pos.y <= height;
+                }
+                TerrainNoiseStackLayer::Caves {
+                    noise,
+                    sample_offset,
+                    frequency,
+                    threshold,
+                } => {
+                    if !is_solid {
+                        continue;
+                    }
+                    let sample_pos = world_voxel_pos * *frequency + sample_offset;
+                    if noise.noise_3d(sample_pos.x, sample_pos.y, sample_pos.z) > *threshold {
+                        is_solid = false;
+                    }
+                }
+                TerrainNoiseStackLayer::MaterialBands { bands } => {
+                    if let Some((_, band_material_index)) = bands
+                        .iter()
+                        .find(|(min_height, _)| world_voxel_pos.y >= *min_height)
+                    {
+                        material_index = *band_material_index;
+                    }
+                }
+            }
+        }
+
+        is_solid.then_some(material_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_grid(stack: &TerrainNoiseStack) -> Vec<Option<u32>> {
+        let mut samples = Vec::new();
+        for x in (-64..64).step_by(8) {
+            for y in (-32..32).step_by(2) {
+                for z in (-64..64).step_by(8) {
+                    samples.push(stack.sample(Vector3::new(x as f32, y as f32, z as f32)));
+                }
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn same_settings_generate_the_same_terrain() {
+        let settings = TerrainGeneratorSettings::default();
+        let samples = sample_grid(&TerrainNoiseStack::new(&settings));
+        assert_eq!(samples, sample_grid(&TerrainNoiseStack::new(&settings)));
+
+        // Round tripping through the project format doesn't change anything.
+        let loaded = serde_json::from_str::<TerrainGeneratorSettings>(
+            &serde_json::to_string(&settings).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(loaded, settings);
+        assert_eq!(samples, sample_grid(&TerrainNoiseStack::new(&loaded)));
+
+        let reseeded = TerrainGeneratorSettings {
+            seed: 1,
+            ..settings.clone()
+        };
+        assert_ne!(samples, sample_grid(&TerrainNoiseStack::new(&reseeded)));
+
+        assert!(samples.contains(&None) && samples.iter().any(Option::is_some));
+    }
+
+    #[test]
+    fn material_bands_paint_by_height() {
+        let settings = TerrainGeneratorSettings {
+            seed: 0,
+            layers: vec![
+                TerrainNoiseLayer::HeightField {
+                    frequency: 0.01,
+                    base_height: 10.0,
+                    amplitude: 0.0,
+                    fbm: FbmOptions::default(),
+                },
+                TerrainNoiseLayer::MaterialBands {
+                    bands: vec![
+                        MaterialBand {
+                            min_height: 4.0,
+                            material: "Grass".to_owned(),
+                        },
+                        MaterialBand {
+                            min_height: 8.0,
+                            material: "Snow".to_owned(),
+                        },
+                    ],
+                },
+            ],
+            base_material: "Dirt".to_owned(),
+        };
+        assert_eq!(settings.material_names(), ["Dirt", "Grass", "Snow"]);
+
+        let stack = TerrainNoiseStack::new(&settings);
+        let sample_height = |y: f32| stack.sample(Vector3::new(3.0, y, -7.0));
+        assert_eq!(sample_height(-20.0), Some(0));
+        assert_eq!(sample_height(5.0), Some(1));
+        assert_eq!(sample_height(9.0), Some(2));
+        assert_eq!(sample_height(10.5), None);
+    }
+}
@@ -1,21 +1,96 @@
 use nalgebra::Vector3;
-use crate::world::terrain::region_map::RegionMap;
+use crate::common::geometry::ray::Ray;
 use crate::entity::ecs_world::{ECSWorld, Entity};
+use crate::physics::transform::Transform;
+use crate::voxel::voxel::VoxelMaterialData;
 use crate::voxel::voxel_registry::VoxelModelRegistry;
+use crate::world::terrain::region_map::RegionMap;
+use crate::world::world_entities::WorldEntities;
 
 pub enum WorldTraceInfo {
     Terrain { global_voxel_pos: Vector3<i32> },
     Entity { entity_id: Entity },
 }
 
+/// A voxel hit by `World::trace_detailed`.
+pub struct WorldVoxelHit {
+    pub info: WorldTraceInfo,
+    /// The voxel position local to the hit model, for terrain this is within its chunk.
+    pub local_voxel_pos: Vector3<u32>,
+    /// Distance along the ray to the hit.
+    pub depth_t: f32,
+    /// World space normal of the voxel face that was hit.
+    pub normal: Vector3<f32>,
+    pub material: Option<VoxelMaterialData>,
+}
+
 pub struct World;
 
 impl World {
     pub fn trace(
+        ray: &Ray,
+        max_distance: f32,
         ecs_world: &ECSWorld,
         voxel_registry: &VoxelModelRegistry,
         region_map: &RegionMap,
     ) -> Option<WorldTraceInfo> {
-        todo!()
+        Self::trace_detailed(ray, max_distance, ecs_world, voxel_registry, region_map)
+            .map(|hit| hit.info)
+    }
+
+    /// Traces both the terrain and voxel entities returning the closest hit with the voxel's
+    /// material, `ray` is relative to the world origin.
+    pub fn trace_detailed(
+        ray: &Ray,
+        max_distance: f32,
+        ecs_world: &ECSWorld,
+        voxel_registry: &VoxelModelRegistry,
+        region_map: &RegionMap,
+    ) -> Option<WorldVoxelHit> {
+        let entity_hit = WorldEntities::raycast_voxel_entities(ray, ecs_world, voxel_registry)
+            .filter(|hit| hit.model_trace.depth_t <= max_distance)
+            .map(|hit| {
+                let transform = ecs_world
+                    .get::<&Transform>(hit.entity)
+                    .expect("Raycast entities should have a transform.");
+                let rotation = ecs_world
+                    .get_world_transform(hit.entity, &transform)
+                    .rotation;
+                let trace = hit.model_trace;
+                WorldVoxelHit {
+                    info: WorldTraceInfo::Entity {
+                        entity_id: hit.entity,
+                    },
+                    local_voxel_pos: trace.local_position,
+                    depth_t: trace.depth_t,
+                    normal: rotation.transform_vector(&trace.local_normal.cast::<f32>()),
+                    material: voxel_registry
+                        .get_dyn_model(hit.model_id)
+                        .get_voxel(trace.local_position),
+                }
+            });
+        let terrain_hit = region_map
+            .raycast_terrain(voxel_registry, ray, max_distance)
+            .filter(|hit| hit.model_trace.depth_t <= max_distance)
+            .map(|hit| WorldVoxelHit {
+                material: region_map.get_voxel(voxel_registry, &hit.world_voxel_pos),
+                info: WorldTraceInfo::Terrain {
+                    global_voxel_pos: hit.world_voxel_pos,
+                },
+                local_voxel_pos: hit.model_trace.local_position,
+                depth_t: hit.model_trace.depth_t,
+                normal: hit.model_trace.local_normal.cast::<f32>(),
+            });
+
+        match (entity_hit, terrain_hit) {
+            (Some(entity_hit), Some(terrain_hit)) => {
+                if entity_hit.depth_t <= terrain_hit.depth_t {
+                    Some(entity_hit)
+                } else {
+                    Some(terrain_hit)
+                }
+            }
+            (entity_hit, terrain_hit) => entity_hit.or(terrain_hit),
+        }
     }
 }
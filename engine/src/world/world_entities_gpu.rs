@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use nalgebra::Vector3;
 use rogue_macros::Resource;
@@ -24,10 +24,34 @@ use crate::{
 
 pub enum WorldEntityGpuEvent {}
 
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct EntityModel {
+    model_info_ptr: u32,
+    instance_offset: u32,
+    instance_count: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct EntityInstance {
+    // 4 floats to account for alignment
+    aabb_min: [f32; 4],
+    aabb_max: [f32; 4],
+    rotation_1: [f32; 4],
+    rotation_2: [f32; 4],
+    rotation_3: [f32; 4],
+}
+
+/// Entities sharing a voxel model are instanced, the model is uploaded once and each entity
+/// only writes its transform to the instance buffer. Dynamic entities own a unique model so
+/// they are a model with a single instance.
 #[derive(Resource)]
 pub struct WorldEntitiesGpu {
-    entity_accel_buf: Option<ResourceId<Buffer>>,
-    written_entity_count: u32,
+    entity_model_buf: Option<ResourceId<Buffer>>,
+    entity_instance_buf: Option<ResourceId<Buffer>>,
+    written_model_count: u32,
+    written_instance_count: u32,
 
     /// Entity models which have their gpu model loaded
     pending_loading_models: HashSet<VoxelModelId>,
@@ -38,8 +62,10 @@ pub struct WorldEntitiesGpu {
 impl WorldEntitiesGpu {
     pub fn new() -> Self {
         Self {
-            entity_accel_buf: None,
-            written_entity_count: 0,
+            entity_model_buf: None,
+            entity_instance_buf: None,
+            written_model_count: 0,
+            written_instance_count: 0,
 
             pending_loading_models: HashSet::new(),
             pending_update_models: HashSet::new(),
@@ -67,9 +93,9 @@ impl WorldEntitiesGpu {
             }
         }
 
-        let mut entity_accel_data = Vec::new();
-
-        entities_gpu.written_entity_count = 0;
+        // Instances grouped by their model in query order so the buffers are deterministic.
+        let mut model_instances: Vec<(VoxelModelId, Vec<EntityInstance>)> = Vec::new();
+        let mut model_indices: HashMap<VoxelModelId, usize> = HashMap::new();
         for (entity, (transform, renderable)) in ecs_world
             .query::<(&Transform, &RenderableVoxelEntity)>()
             .into_iter()
@@ -78,12 +104,40 @@ impl WorldEntitiesGpu {
                 continue;
             };
 
+            let voxel_model = voxel_registry.get_dyn_model(voxel_model_id);
+            let world_transform = ecs_world.get_global_transform(entity, &transform);
+            let obb = world_transform.as_voxel_model_obb(voxel_model.length());
+            let aabb = obb.aabb;
+            // TODO: It feels like the matrix is getting inverted somewhere being sent to the
+            // shader but i havent debuged it yet so im not sure if it is or what, its
+            // just weird the math works currently cause theoretically it shouldnt be working i
+            // need to send the inverted rotation which im not explicitly doing idk.
+            let rotation = world_transform.rotation.to_homogeneous();
+            let instance = EntityInstance {
+                aabb_min: [aabb.min.x, aabb.min.y, aabb.min.z, 0.0],
+                aabb_max: [aabb.max.x, aabb.max.y, aabb.max.z, 0.0],
+                rotation_1: [rotation.m11, rotation.m21, rotation.m31, 0.0],
+                rotation_2: [rotation.m12, rotation.m22, rotation.m32, 0.0],
+                rotation_3: [rotation.m13, rotation.m23, rotation.m33, 0.0],
+            };
+            let model_index = *model_indices.entry(voxel_model_id).or_insert_with(|| {
+                model_instances.push((voxel_model_id, Vec::new()));
+                model_instances.len() - 1
+            });
+            model_instances[model_index].1.push(instance);
+        }
+
+        let mut entity_model_data = Vec::new();
+        let mut entity_instance_data = Vec::new();
+        entities_gpu.written_model_count = 0;
+        entities_gpu.written_instance_count = 0;
+        for (voxel_model_id, instances) in model_instances {
             if entities_gpu.pending_update_models.contains(&voxel_model_id)
                 || voxel_registry_gpu
                     .get_model_gpu_ptr(&voxel_model_id)
                     .is_none()
             {
-                // Try and load the gpu model for the entity.
+                // Try and load the gpu model once for all of its instances.
                 let side_length = voxel_registry.get_dyn_model(voxel_model_id).length();
                 let success = voxel_registry_gpu.allocate_or_update_model(
                     &mut GpuModelAllocationContext {
@@ -111,69 +165,80 @@ impl WorldEntitiesGpu {
                 .get_model_gpu_ptr(&voxel_model_id)
                 .expect("Model ptr should exist since we checked above");
 
-            #[repr(C)]
-            #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
-            struct EntityInfo {
-                // 4 floats to account for alignment
-                aabb_min: [f32; 4],
-                aabb_max: [f32; 4],
-                rotation_1: [f32; 4],
-                rotation_2: [f32; 4],
-                rotation_3: [f32; 3],
-                model_ptr: u32,
-            }
-            let voxel_model = voxel_registry.get_dyn_model(voxel_model_id);
-            let world_transform = ecs_world.get_world_transform(entity, &transform);
-            let obb = world_transform.as_voxel_model_obb(voxel_model.length());
-            let aabb = obb.aabb;
-            // TODO: It feels like the matrix is getting inverted somewhere being sent to the
-            // shader but i havent debuged it yet so im not sure if it is or what, its
-            // just weird the math works currently cause theoretically it shouldnt be working i
-            // need to send the inverted rotation which im not explicitly doing idk.
-            let rotation = world_transform.rotation.to_homogeneous();
-            let entity_info = EntityInfo {
-                aabb_min: [aabb.min.x, aabb.min.y, aabb.min.z, 0.0],
-                aabb_max: [aabb.max.x, aabb.max.y, aabb.max.z, 0.0],
-                rotation_1: [rotation.m11, rotation.m21, rotation.m31, 0.0],
-                rotation_2: [rotation.m12, rotation.m22, rotation.m32, 0.0],
-                rotation_3: [rotation.m13, rotation.m23, rotation.m33],
-                model_ptr: gpu_model_ptr,
+            let entity_model = EntityModel {
+                model_info_ptr: gpu_model_ptr,
+                instance_offset: entities_gpu.written_instance_count,
+                instance_count: instances.len() as u32,
             };
-            entity_accel_data.extend_from_slice(bytemuck::bytes_of(&entity_info));
-            entities_gpu.written_entity_count += 1;
+            entity_model_data.extend_from_slice(bytemuck::bytes_of(&entity_model));
+            entity_instance_data.extend_from_slice(bytemuck::cast_slice(instances.as_slice()));
+            entities_gpu.written_model_count += 1;
+            entities_gpu.written_instance_count += instances.len() as u32;
         }
 
-        let req_bytes = entity_accel_data.len() as u64;
+        Self::write_buffer(
+            &mut device_resource,
+            &mut entities_gpu.entity_model_buf,
+            "entities_model_buffer",
+            &entity_model_data,
+        );
+        Self::write_buffer(
+            &mut device_resource,
+            &mut entities_gpu.entity_instance_buf,
+            "entities_instance_buffer",
+            &entity_instance_data,
+        );
+    }
+
+    fn write_buffer(
+        device_resource: &mut DeviceResource,
+        buffer: &mut Option<ResourceId<Buffer>>,
+        name: &str,
+        data: &[u8],
+    ) {
+        let req_bytes = data.len() as u64;
         device_resource.create_or_reallocate_buffer(
-            &mut entities_gpu.entity_accel_buf,
+            buffer,
             crate::graphics::backend::GfxBufferCreateInfo {
-                name: "entities_accel_buffer".to_owned(),
+                name: name.to_owned(),
                 size: req_bytes.max(16), // Can't do zero sized buffer and needed in descriptor i
                                          // need a better way for this.
             },
         );
 
-        if !entity_accel_data.is_empty() {
+        if !data.is_empty() {
             device_resource.write_buffer_slice(
-                entities_gpu
-                    .entity_accel_buf
-                    .as_ref()
-                    .expect("Bad if this failed to allocate."),
+                buffer.as_ref().expect("Bad if this failed to allocate."),
                 0,
-                &entity_accel_data,
+                data,
             );
         }
     }
 
+    /// The number of distinct voxel models written this frame, each is uploaded once.
+    pub fn unique_model_count(&self) -> u32 {
+        self.written_model_count
+    }
+
+    /// The number of entities written this frame across all models.
+    pub fn instance_count(&self) -> u32 {
+        self.written_instance_count
+    }
+
     pub fn write_global_uniforms(&self, writer: &mut ShaderWriter) {
         writer.write_uniform::<u32>(
-            "u_frame.voxel.entity_data.entity_count",
-            self.written_entity_count,
+            "u_frame.voxel.entity_data.model_count",
+            self.written_model_count,
+        );
+        writer.write_binding(
+            "u_frame.voxel.entity_data.model_buf",
+            self.entity_model_buf
+                .expect("Model buffer should exist by now"),
         );
         writer.write_binding(
-            "u_frame.voxel.entity_data.accel_buf",
-            self.entity_accel_buf
-                .expect("Accelleration buffer should exist by now"),
+            "u_frame.voxel.entity_data.instance_buf",
+            self.entity_instance_buf
+                .expect("Instance buffer should exist by now"),
         );
     }
 }
@@ -0,0 +1,194 @@
+use nalgebra::Vector3;
+use rogue_macros::Resource;
+
+use crate::{
+    consts,
+    entity::{EntityParent, ecs_world::ECSWorld},
+    event::Events,
+    graphics::camera::MainCamera,
+    physics::{rigid_body::RigidBody, transform::Transform},
+    resource::{Res, ResMut},
+    world::terrain::{region_map::RegionMap, region_pos::RegionPos},
+};
+
+/// Pushed after the world origin shifts and every root entity was moved by `offset`.
+#[derive(Clone, Debug)]
+pub struct WorldOriginShiftEvent {
+    pub origin: RegionPos,
+    /// The offset in meters that was added to every root entity's position.
+    pub offset: Vector3<f32>,
+}
+
+/// The floating origin of the world. Entity transforms, physics and ray traces all work in f32
+/// meters relative to this origin so precision doesn't degrade as the camera travels far from
+/// the world's (0, 0, 0). The origin is always region aligned so voxel and chunk positions stay
+/// exact integers, terrain regions, chunks and voxel positions are always absolute.
+#[derive(Resource)]
+pub struct WorldOrigin {
+    region: RegionPos,
+    /// How many regions the main camera can be away from the origin before it is shifted,
+    /// `None` disables shifting.
+    shift_distance: Option<u32>,
+}
+
+impl WorldOrigin {
+    pub const DEFAULT_SHIFT_DISTANCE: u32 = 4;
+
+    pub fn new() -> Self {
+        Self {
+            region: RegionPos::zeros(),
+            shift_distance: Some(Self::DEFAULT_SHIFT_DISTANCE),
+        }
+    }
+
+    pub fn region(&self) -> RegionPos {
+        self.region
+    }
+
+    pub fn set_shift_distance(&mut self, shift_distance: Option<u32>) {
+        self.shift_distance = shift_distance;
+    }
+
+    /// The absolute voxel position of the origin.
+    pub fn voxel_offset(&self) -> Vector3<i32> {
+        self.region
+            .map(|x| x * consts::voxel::TERRAIN_REGION_VOXEL_LENGTH as i32)
+    }
+
+    /// The absolute position in meters of an origin-relative position, in f64 since the whole
+    /// point is that it may not fit in an f32.
+    pub fn to_absolute_world_pos(&self, relative_pos: &Vector3<f32>) -> Vector3<f64> {
+        self.region
+            .map(|x| x as f64 * consts::voxel::TERRAIN_REGION_METER_LENGTH as f64)
+            + relative_pos.cast::<f64>()
+    }
+
+    /// The absolute region containing the origin-relative position.
+    pub fn to_absolute_region_pos(&self, relative_pos: &Vector3<f32>) -> RegionPos {
+        RegionPos::from_world_pos(relative_pos) + self.region
+    }
+
+    /// The origin-relative position of the minimum corner of an absolute voxel position.
+    pub fn to_relative_voxel_world_pos(&self, world_voxel_pos: &Vector3<i32>) -> Vector3<f32> {
+        (world_voxel_pos - self.voxel_offset()).cast::<f32>() * consts::voxel::VOXEL_METER_LENGTH
+    }
+
+    /// The region shift needed to re-center the origin on `relative_pos` if it has strayed
+    /// past the shift distance.
+    pub fn required_shift(&self, relative_pos: &Vector3<f32>) -> Option<Vector3<i32>> {
+        let shift_distance = self.shift_distance? as i32;
+        let region_pos = RegionPos::from_world_pos(relative_pos);
+        if region_pos.iter().all(|x| x.abs() <= shift_distance) {
+            return None;
+        }
+        Some(*region_pos)
+    }
+
+    /// Moves the origin by `shift` regions, moving every root entity and rigid body by the
+    /// opposite so nothing moves in absolute space. Child entities are relative to their
+    /// parent so they follow along. Returns the offset in meters applied to the entities.
+    pub fn shift(&mut self, shift: Vector3<i32>, ecs_world: &mut ECSWorld) -> Vector3<f32> {
+        self.region = self.region + shift;
+        let offset = -shift.cast::<f32>() * consts::voxel::TERRAIN_REGION_METER_LENGTH;
+        for (_entity, (transform, parent, rigid_body)) in ecs_world
+            .query_mut::<(
+                &mut Transform,
+                Option<&EntityParent>,
+                Option<&mut RigidBody>,
+            )>()
+            .into_iter()
+        {
+            if parent.is_some() {
+                continue;
+            }
+            transform.position += offset;
+            if let Some(rigid_body) = rigid_body {
+                rigid_body.translate(offset);
+            }
+        }
+
+        offset
+    }
+
+    /// Shifts the origin once the main camera strays too far from it.
+    pub fn update_world_origin(
+        mut origin: ResMut<WorldOrigin>,
+        mut ecs_world: ResMut<ECSWorld>,
+        mut region_map: ResMut<RegionMap>,
+        main_camera: Res<MainCamera>,
+        mut events: ResMut<Events>,
+    ) {
+        let Some(camera) = main_camera.camera() else {
+            return;
+        };
+        let Ok(camera_transform) = ecs_world.get::<&Transform>(camera) else {
+            return;
+        };
+        let camera_pos = ecs_world
+            .get_world_transform(camera, &camera_transform)
+            .position;
+        drop(camera_transform);
+        let Some(shift) = origin.required_shift(&camera_pos) else {
+            return;
+        };
+
+        let offset = origin.shift(shift, &mut ecs_world);
+        region_map.set_world_origin(origin.region());
+        log::info!("Shifted world origin to region {:?}.", *origin.region());
+        events.push(WorldOriginShiftEvent {
+            origin: origin.region(),
+            offset,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shift_preserves_relative_positions() {
+        let mut ecs_world = ECSWorld::new();
+        let mut origin = WorldOrigin::new();
+
+        let region_meters = consts::voxel::TERRAIN_REGION_METER_LENGTH;
+        let camera_pos = Vector3::new(region_meters * 5.5, 3.0, -region_meters * 0.5);
+        let other_pos = camera_pos + Vector3::new(2.0, -1.0, 7.5);
+        let mut camera_transform = Transform::new();
+        camera_transform.position = camera_pos;
+        let mut other_transform = Transform::new();
+        other_transform.position = other_pos;
+        let camera = ecs_world.spawn((camera_transform,));
+        let other = ecs_world.spawn((other_transform,));
+
+        let voxel_pos = Vector3::new(1234, -56, 789);
+        let voxel_world_pos = origin.to_relative_voxel_world_pos(&voxel_pos);
+        let camera_absolute_pos = origin.to_absolute_world_pos(&camera_pos);
+
+        let shift = origin
+            .required_shift(&camera_pos)
+            .expect("Camera is past the shift distance.");
+        assert_eq!(shift, Vector3::new(5, 0, -1));
+        origin.shift(shift, &mut ecs_world);
+
+        let new_camera_pos = ecs_world.get::<&Transform>(camera).unwrap().position;
+        let new_other_pos = ecs_world.get::<&Transform>(other).unwrap().position;
+        assert!(origin.required_shift(&new_camera_pos).is_none());
+        assert_eq!(new_other_pos - new_camera_pos, other_pos - camera_pos);
+        assert_eq!(
+            origin.to_absolute_world_pos(&new_camera_pos),
+            camera_absolute_pos
+        );
+        assert_eq!(
+            origin.to_absolute_region_pos(&new_camera_pos),
+            RegionPos::from_world_pos(&camera_pos)
+        );
+
+        // The voxel stays in the same place relative to the entities.
+        let new_voxel_world_pos = origin.to_relative_voxel_world_pos(&voxel_pos);
+        assert_eq!(
+            new_voxel_world_pos - new_camera_pos,
+            voxel_world_pos - camera_pos
+        );
+    }
+}
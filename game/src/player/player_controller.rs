@@ -19,6 +19,7 @@ use rogue_engine::physics::{
     transform::Transform,
 };
 use rogue_engine::resource::{Res, ResMut, ResourceBank};
+use rogue_engine::settings::Settings;
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 #[game_component(name = "PlayerController")]
@@ -90,6 +91,7 @@ impl PlayerController {
         input: Res<Input>,
         mut window: ResMut<Window>,
         time: Res<Time>,
+        settings: Res<Settings>,
     ) {
         let Some((entity, (mut transform, rigid_body, controller, animator, audio_player))) =
             ecs_world
@@ -118,7 +120,7 @@ impl PlayerController {
             if input.is_controller_camera() {
                 rot_delta *= 145.0f32.to_radians() * time.delta_time().as_secs_f32();
             } else {
-                rot_delta *= 0.0005;
+                rot_delta *= settings.game_mouse_sensitivity;
             }
             controller.looking.aim_rot.x = (controller.looking.aim_rot.x + rot_delta.y)
                 .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
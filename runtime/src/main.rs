@@ -1,10 +1,10 @@
 #![allow(warnings)]
 
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use nalgebra::Vector2;
 use rogue_engine::{
-    app::{App, AppCreateInfo, AppStage},
+    app::{App, AppCreateInfo, AppStage, HeadlessRenderInfo},
     asset::{
         asset::{AssetPath, Assets},
         repr::project::ProjectAsset,
@@ -13,7 +13,14 @@ use rogue_engine::{
     debug::debug_renderer::DebugRenderer,
     egui::{Egui, egui_gpu::EguiGpu},
     entity::ecs_world::ECSWorld,
-    graphics::camera::MainCamera,
+    graphics::{
+        camera::MainCamera,
+        passes::{
+            bloom_pass::BloomPass, depth_of_field_pass::DepthOfFieldPass, fxaa_pass::FxaaPass,
+            taa_pass::TaaPass,
+        },
+        screenshot::Screenshots,
+    },
     impl_asset_load_save_serde,
     input::Input,
     resource::ResourceBank,
@@ -51,6 +58,14 @@ fn main() {
         .filter(Some("sctk"), log::LevelFilter::Info)
         .init();
 
+    let headless = match parse_headless_args(std::env::args().skip(1)) {
+        Ok(headless) => headless,
+        Err(err) => {
+            log::error!("{}\n{}", err, HEADLESS_USAGE);
+            std::process::exit(2);
+        }
+    };
+
     let project = RuntimeProjectLoader::load_project();
 
     // Setup runtime session early since it relys on ProjectSettings.
@@ -60,12 +75,72 @@ fn main() {
         on_post_graphics_init_fn: Some(Box::new(on_post_graphics_init)),
         on_window_event_fn: None,
         on_device_event_fn: None,
+        on_fatal_error_fn: None,
     });
     app.insert_resource(runtime_session);
 
     setup_systems(&mut app);
 
-    app.run_with_window();
+    let Some(headless) = headless else {
+        app.run_with_window();
+        return;
+    };
+    let output_path = headless.output_path.clone();
+    if let Err(err) = app.run_headless(headless) {
+        log::error!("Headless render failed: {}", err);
+        std::process::exit(1);
+    }
+    log::info!("Saved headless render to {}.", output_path.display());
+}
+
+const HEADLESS_USAGE: &str = "Usage: rogue_runtime [--headless <output.png> [--frames <count>] \
+[--size <width>x<height>] [--delta-time <seconds>]]";
+
+/// Parses `--headless` and its options, which render a fixed number of frames offscreen and
+/// save the last one instead of opening a window.
+fn parse_headless_args(
+    mut args: impl Iterator<Item = String>,
+) -> anyhow::Result<Option<HeadlessRenderInfo>> {
+    let mut output_path = None;
+    let mut info = HeadlessRenderInfo {
+        size: Vector2::new(1280, 720),
+        frame_count: 60,
+        delta_time: Duration::from_secs_f64(1.0 / 60.0),
+        output_path: PathBuf::new(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow::anyhow!("Missing value for `{}`.", arg))
+        };
+        match arg.as_str() {
+            "--headless" => output_path = Some(PathBuf::from(value()?)),
+            "--frames" => {
+                info.frame_count = value()?.parse()?;
+                anyhow::ensure!(info.frame_count > 0, "`--frames` must be at least 1.");
+            }
+            "--size" => {
+                let size = value()?;
+                let (width, height) = size
+                    .split_once('x')
+                    .ok_or_else(|| anyhow::anyhow!("`--size` must be <width>x<height>."))?;
+                info.size = Vector2::new(width.parse()?, height.parse()?);
+                anyhow::ensure!(
+                    info.size.x > 0 && info.size.y > 0,
+                    "`--size` must be non-zero."
+                );
+            }
+            "--delta-time" => {
+                info.delta_time = Duration::try_from_secs_f64(value()?.parse()?)?;
+            }
+            _ => anyhow::bail!("Unknown argument `{}`.", arg),
+        }
+    }
+
+    Ok(output_path.map(|output_path| HeadlessRenderInfo {
+        output_path,
+        ..info
+    }))
 }
 
 /// Called only once after graphics initialization.
@@ -84,12 +159,33 @@ fn setup_systems(app: &mut App) {
     );
 
     // ======== RENDER GRAPH =========
+    // Pick the world raytrace pass workgroup size for this device.
+    app.insert_system(AppStage::Update, WorldRTPass::update_workgroup_size);
     app.insert_system(
         AppStage::PreUniformsRenderWrite,
         RuntimeRenderGraph::write_general_inputs,
     );
     // Write the world raytrace pass.
     app.insert_system(AppStage::RenderWrite, WorldRTPass::write_graph_rt_pass);
+    // Write the taa pass resolving the world render with the previous frames.
+    app.insert_system(AppStage::RenderWrite, TaaPass::write_graph_taa_pass);
+    // Write the depth of field pass blurring by the main camera's focus.
+    app.insert_system(
+        AppStage::RenderWrite,
+        DepthOfFieldPass::write_graph_depth_of_field_pass,
+    );
+    // Write the bloom pass blurring the bright parts of the resolved world render.
+    app.insert_system(AppStage::RenderWrite, BloomPass::write_graph_bloom_pass);
+    // Start any queued screenshot captures and save the ones read back from the gpu.
+    app.insert_system(AppStage::Update, Screenshots::update_captures);
+    app.insert_system(AppStage::Update, Screenshots::update_readbacks);
+    // Write the fxaa pass smoothing the edges of the display encoded image.
+    app.insert_system(AppStage::RenderWrite, FxaaPass::write_graph_fxaa_pass);
+    // Write the screenshot pass, copying out the final image if a capture was requested.
+    app.insert_system(
+        AppStage::RenderWrite,
+        Screenshots::write_graph_screenshot_pass,
+    );
 }
 
 fn init_ecs_world() -> ECSWorld {
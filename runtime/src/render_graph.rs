@@ -5,9 +5,15 @@ use rogue_engine::egui::egui_gpu::EguiGpu;
 use rogue_engine::graphics::backend::{GfxBlitInfo, GfxFilterMode};
 use rogue_engine::graphics::device::DeviceResource;
 use rogue_engine::graphics::frame_graph::FrameGraphImageInfo;
+use rogue_engine::graphics::passes::bloom_pass::BloomPass;
+use rogue_engine::graphics::passes::depth_of_field_pass::DepthOfFieldPass;
+use rogue_engine::graphics::passes::fxaa_pass::FxaaPass;
 use rogue_engine::graphics::passes::post_process_pass::PostProcessPass;
+use rogue_engine::graphics::passes::taa_pass::TaaPass;
+use rogue_engine::graphics::screenshot::Screenshots;
 use rogue_engine::graphics::{frame_graph::FrameGraphBuilder, renderer::Renderer};
 use rogue_engine::resource::{Res, ResMut};
+use rogue_engine::settings::Settings;
 use rogue_engine::voxel::baker_gpu::VoxelBakerGpu;
 use rogue_engine::world::renderable::rt_pass::WorldRTPass;
 
@@ -17,6 +23,7 @@ pub struct RuntimeRenderGraphConstants {
     pub backbuffer_depth_r16_name: &'static str,
     pub backbuffer_depth_name: &'static str,
     pub backbuffer_blit_offset_input: &'static str,
+    pub backbuffer_blit_size_input: &'static str,
 
     pub intermediate_image_name: &'static str,
 }
@@ -30,27 +37,46 @@ impl RuntimeRenderGraph {
         backbuffer_depth_r16_name: "backbuffer_r16_depth",
         backbuffer_depth_name: "backbuffer_depth",
         backbuffer_blit_offset_input: "backbuffer_blit_offset",
+        backbuffer_blit_size_input: "backbuffer_blit_size",
         intermediate_image_name: "intermediate_image",
     };
 
     /// Supplies inputs such as backbuffer size or backbuffer blit offset, etc. to the
     /// render graph executor.
-    pub fn write_general_inputs(mut renderer: ResMut<Renderer>) {
+    pub fn write_general_inputs(
+        mut renderer: ResMut<Renderer>,
+        screenshots: Res<Screenshots>,
+        settings: Res<Settings>,
+        device: Res<DeviceResource>,
+    ) {
         let swapchain_size = renderer.swapchain_size();
+        let render_size = settings
+            .graphics
+            .render_size(swapchain_size, device.device_info().max_image_dimension_2d);
+        let backbuffer_size = screenshots.capture_scene_size(swapchain_size, render_size);
         renderer
             .executor()
-            .supply_input(Self::GRAPH.backbuffer_size_input, Box::new(swapchain_size));
+            .supply_input(Self::GRAPH.backbuffer_size_input, Box::new(backbuffer_size));
 
         renderer.executor().supply_input(
             Self::GRAPH.backbuffer_blit_offset_input,
             Box::new(Vector2::new(0u32, 0u32)),
         );
+        renderer.executor().supply_input(
+            Self::GRAPH.backbuffer_blit_size_input,
+            Box::new(swapchain_size),
+        );
     }
 
     pub fn init_render_graph(
         mut renderer: ResMut<Renderer>,
         mut world_rt_pass_gpu: ResMut<WorldRTPass>,
         mut voxel_baker_gpu: ResMut<VoxelBakerGpu>,
+        mut screenshots: ResMut<Screenshots>,
+        mut taa_pass: ResMut<TaaPass>,
+        mut dof_pass: ResMut<DepthOfFieldPass>,
+        mut bloom_pass: ResMut<BloomPass>,
+        mut fxaa_pass: ResMut<FxaaPass>,
     ) {
         let mut fg = FrameGraphBuilder::new();
 
@@ -99,6 +125,20 @@ impl RuntimeRenderGraph {
             },
         );
 
+        // Resolve the jittered backbuffer with the previous frames.
+        let resolved_backbuffer =
+            taa_pass.set_graph_taa_pass(&mut fg, backbuffer, backbuffer_depth_r16);
+        // Blur the parts of the image out of the camera's focus.
+        let resolved_backbuffer = dof_pass.set_graph_depth_of_field_pass(
+            &mut fg,
+            resolved_backbuffer,
+            backbuffer_depth_r16,
+            backbuffer_size_input,
+        );
+        // Bloom the bright parts of the hdr image before it's converted for display.
+        let resolved_backbuffer =
+            bloom_pass.set_graph_bloom_pass(&mut fg, resolved_backbuffer, backbuffer_size_input);
+
         let swapchain_image = fg.create_input_image(Renderer::GRAPH.image_swapchain);
         let swapchain_image_size =
             fg.create_input::<Vector2<u32>>(Renderer::GRAPH.image_swapchain_size);
@@ -111,12 +151,25 @@ impl RuntimeRenderGraph {
         // post processing effects.
         let blit_offset_input =
             fg.create_input::<Vector2<u32>>(Self::GRAPH.backbuffer_blit_offset_input);
+        let blit_size_input =
+            fg.create_input::<Vector2<u32>>(Self::GRAPH.backbuffer_blit_size_input);
         PostProcessPass::set_graph_post_process_blit_pass(
             &mut fg,
             blit_offset_input,
-            backbuffer,
+            blit_size_input,
+            resolved_backbuffer,
             intermediate_image,
         );
+        // Smooth the edges of the display encoded scene when fxaa is the anti-aliasing.
+        let intermediate_image = fxaa_pass.set_graph_fxaa_pass(
+            &mut fg,
+            intermediate_image,
+            swapchain_image_size,
+            blit_offset_input,
+            blit_size_input,
+        );
+        // Captures screenshots and headless frames of the final image.
+        screenshots.set_graph_screenshot_pass(&mut fg, intermediate_image, resolved_backbuffer);
 
         fg.create_pass(
             "blit_intermediate_to_swapchain_pass",
@@ -1,8 +1,10 @@
 use rogue_engine::{
     asset::repr::project::ProjectSettings,
+    common::rng::Rng,
     entity::{
         component::GameComponentCloneContext,
         ecs_world::{ECSWorld, Entity},
+        scripting::Scripts,
     },
     event::{EventReader, Events},
     graphics::camera::MainCamera,
@@ -31,9 +33,12 @@ impl RuntimeSession {
         runtime_session: Res<RuntimeSession>,
         mut main_camera: ResMut<MainCamera>,
         mut physics_world: ResMut<PhysicsWorld>,
+        mut scripts: ResMut<Scripts>,
+        rng: Res<Rng>,
     ) {
         main_camera.set_camera(runtime_session.game_camera, "runtime_default_camera");
         physics_world.do_dynamics = true;
+        scripts.start(&rng);
     }
 
     pub fn run_game_on_update(rb: &ResourceBank) {
@@ -0,0 +1,29 @@
+-- Spawns a cube a few meters in front of the camera each time E is pressed, dropping it onto
+-- whatever the camera is looking at if that is closer.
+spawn_distance = 4
+
+function on_update(dt)
+    if not is_key_pressed("E") then
+        return
+    end
+    local camera = main_camera()
+    if camera == nil then
+        return
+    end
+
+    local camera_transform = get_transform(camera)
+    local origin = camera_transform.position
+    local forward = camera_transform.forward
+    local distance = spawn_distance
+    local hit = raycast(origin, forward, spawn_distance)
+    if hit ~= nil then
+        distance = hit.distance - 0.5
+    end
+
+    local cube = spawn_entity("Cube", {
+        x = origin.x + forward.x * distance,
+        y = origin.y + forward.y * distance,
+        z = origin.z + forward.z * distance,
+    })
+    set_voxel_model(cube, "cube::rvox")
+end